@@ -0,0 +1,756 @@
+//! End-to-end smoke test against a deployed environment.
+//!
+//! Logs in a client and a provider through the OTP debug hook, registers (or
+//! reuses) the provider, sends a message, confirms its delivery (signed with
+//! the smoke device key) and checks earnings and the delivery webhook. Prints a pass/fail report and exits
+//! non-zero on any failure, so deploys can be gated on it.
+//!
+//! ```text
+//! SMOKE_BASE_URL=https://staging.peerpower.network \
+//! SMOKE_DEBUG_OTP_TOKEN=... \
+//! SMOKE_CLIENT_PHONE=+85512000001 SMOKE_PROVIDER_PHONE=+85512000002 \
+//! cargo run --bin peerpower-smoke -- --json
+//! ```
+
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use reqwest::{Method, StatusCode};
+use rsa::pkcs1v15::SigningKey;
+use rsa::pkcs8::{DecodePrivateKey, EncodePrivateKey, EncodePublicKey, LineEnding};
+use rsa::signature::{SignatureEncoding, Signer};
+use rsa::{RsaPrivateKey, RsaPublicKey};
+use serde::Serialize;
+use serde_json::{json, Value};
+use sha2::Digest;
+use std::time::{Duration, Instant};
+
+/// Exit code when the environment under test fails a step
+const EXIT_FAILED: i32 = 1;
+/// Exit code when the smoke test itself is misconfigured
+const EXIT_USAGE: i32 = 2;
+
+/// How often message status is polled while waiting for dispatch
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+struct SmokeConfig {
+    base_url: String,
+    debug_otp_token: String,
+    client_phone: String,
+    provider_phone: String,
+    recipient: String,
+    webhook_integration: Option<String>,
+    webhook_secret: Option<String>,
+    delivery_timeout: Duration,
+    device_key: RsaPrivateKey,
+    json: bool,
+}
+
+impl SmokeConfig {
+    fn from_env() -> Result<Self, String> {
+        let required = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .filter(|value| !value.is_empty())
+                .ok_or_else(|| format!("{} is required", name))
+        };
+        let optional = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+
+        let provider_phone = required("SMOKE_PROVIDER_PHONE")?;
+        Ok(Self {
+            base_url: optional("SMOKE_BASE_URL")
+                .unwrap_or_else(|| "http://localhost:8080".to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            debug_otp_token: required("SMOKE_DEBUG_OTP_TOKEN")?,
+            client_phone: required("SMOKE_CLIENT_PHONE")?,
+            recipient: optional("SMOKE_RECIPIENT").unwrap_or_else(|| provider_phone.clone()),
+            provider_phone,
+            webhook_integration: optional("SMOKE_WEBHOOK_INTEGRATION"),
+            webhook_secret: optional("SMOKE_WEBHOOK_SECRET"),
+            delivery_timeout: Duration::from_secs(
+                optional("SMOKE_DELIVERY_TIMEOUT_SECONDS")
+                    .and_then(|value| value.parse().ok())
+                    .unwrap_or(60),
+            ),
+            device_key: load_device_key(optional("SMOKE_DEVICE_KEY_FILE"))?,
+            json: std::env::args().any(|arg| arg == "--json"),
+        })
+    }
+}
+
+/// The smoke provider's device key: loaded from `path` when it exists,
+/// otherwise generated (and saved there, so reruns reuse the enrolled key)
+fn load_device_key(path: Option<String>) -> Result<RsaPrivateKey, String> {
+    if let Some(pem) = path
+        .as_ref()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+    {
+        return RsaPrivateKey::from_pkcs8_pem(&pem)
+            .map_err(|e| format!("SMOKE_DEVICE_KEY_FILE is not a PKCS#8 RSA key: {}", e));
+    }
+
+    let key = RsaPrivateKey::new(&mut rand::thread_rng(), 2048)
+        .map_err(|e| format!("failed to generate device key: {}", e))?;
+    if let Some(path) = path {
+        let pem = key
+            .to_pkcs8_pem(LineEnding::LF)
+            .map_err(|e| format!("failed to encode device key: {}", e))?;
+        std::fs::write(&path, pem.as_bytes())
+            .map_err(|e| format!("failed to write {}: {}", path, e))?;
+    }
+    Ok(key)
+}
+
+fn device_public_key(config: &SmokeConfig) -> Result<String, String> {
+    RsaPublicKey::from(&config.device_key)
+        .to_public_key_pem(LineEnding::LF)
+        .map_err(|e| format!("failed to encode device public key: {}", e))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Outcome {
+    Pass,
+    Fail,
+    Skip,
+}
+
+#[derive(Debug, Serialize)]
+struct StepResult {
+    name: &'static str,
+    outcome: Outcome,
+    duration_ms: u128,
+    detail: String,
+}
+
+#[derive(Debug, Serialize)]
+struct SmokeReport {
+    target: String,
+    passed: bool,
+    started_at: String,
+    duration_ms: u128,
+    steps: Vec<StepResult>,
+}
+
+struct Api {
+    http: reqwest::Client,
+    base_url: String,
+}
+
+impl Api {
+    async fn call(
+        &self,
+        method: Method,
+        path: &str,
+        token: Option<&str>,
+        body: Option<&Value>,
+    ) -> Result<(StatusCode, Value), String> {
+        let mut request = self
+            .http
+            .request(method, format!("{}{}", self.base_url, path));
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+        Self::read(request).await
+    }
+
+    async fn read(request: reqwest::RequestBuilder) -> Result<(StatusCode, Value), String> {
+        let response = request
+            .send()
+            .await
+            .map_err(|e| format!("request failed: {}", e))?;
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| format!("failed to read response: {}", e))?;
+        let body = serde_json::from_str(&text).unwrap_or(Value::String(text));
+        Ok((status, body))
+    }
+
+    /// Like `call`, but anything other than a 2xx is an error carrying the body
+    async fn expect_ok(
+        &self,
+        method: Method,
+        path: &str,
+        token: Option<&str>,
+        body: Option<&Value>,
+    ) -> Result<Value, String> {
+        let (status, body) = self.call(method.clone(), path, token, body).await?;
+        if !status.is_success() {
+            return Err(format!("{} {} returned {}: {}", method, path, status, body));
+        }
+        Ok(body)
+    }
+}
+
+struct Runner {
+    steps: Vec<StepResult>,
+}
+
+impl Runner {
+    fn record<T>(
+        &mut self,
+        name: &'static str,
+        started: Instant,
+        result: Result<(T, String), String>,
+    ) -> Option<T> {
+        let (outcome, detail, value) = match result {
+            Ok((value, detail)) => (Outcome::Pass, detail, Some(value)),
+            Err(detail) => (Outcome::Fail, detail, None),
+        };
+        self.steps.push(StepResult {
+            name,
+            outcome,
+            duration_ms: started.elapsed().as_millis(),
+            detail,
+        });
+        value
+    }
+
+    fn skip(&mut self, name: &'static str, detail: impl Into<String>) {
+        self.steps.push(StepResult {
+            name,
+            outcome: Outcome::Skip,
+            duration_ms: 0,
+            detail: detail.into(),
+        });
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let config = match SmokeConfig::from_env() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("peerpower-smoke: {}", e);
+            std::process::exit(EXIT_USAGE);
+        }
+    };
+
+    let report = run(&config).await;
+    if config.json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&report).expect("report serializes")
+        );
+    } else {
+        print_report(&report);
+    }
+
+    if !report.passed {
+        std::process::exit(EXIT_FAILED);
+    }
+}
+
+async fn run(config: &SmokeConfig) -> SmokeReport {
+    let started_at = chrono::Utc::now();
+    let started = Instant::now();
+    let api = Api {
+        http: reqwest::Client::builder()
+            .timeout(Duration::from_secs(15))
+            .build()
+            .expect("HTTP client builds"),
+        base_url: config.base_url.clone(),
+    };
+    let mut runner = Runner { steps: Vec::new() };
+
+    flow(config, &api, &mut runner).await;
+
+    SmokeReport {
+        target: config.base_url.clone(),
+        passed: runner
+            .steps
+            .iter()
+            .all(|step| step.outcome != Outcome::Fail),
+        started_at: started_at.to_rfc3339(),
+        duration_ms: started.elapsed().as_millis(),
+        steps: runner.steps,
+    }
+}
+
+/// The scripted flow; stops at the first step later steps depend on
+async fn flow(config: &SmokeConfig, api: &Api, runner: &mut Runner) {
+    let at = Instant::now();
+    let ready = check_health(api).await;
+    if runner.record("health", at, ready).is_none() {
+        return;
+    }
+
+    let at = Instant::now();
+    let client_login = login(config, api, &config.client_phone, "client").await;
+    let Some(client_token) = runner.record("client_login", at, client_login) else {
+        return;
+    };
+
+    let at = Instant::now();
+    let provider_login = login(config, api, &config.provider_phone, "provider").await;
+    let Some(provider_token) = runner.record("provider_login", at, provider_login) else {
+        return;
+    };
+
+    let at = Instant::now();
+    let registration = register_provider(config, api, &provider_token).await;
+    let Some(provider_id) = runner.record("provider_register", at, registration) else {
+        return;
+    };
+
+    let at = Instant::now();
+    let heartbeat = send_heartbeat(api, &provider_token, &provider_id).await;
+    runner.record("provider_heartbeat", at, heartbeat);
+
+    let at = Instant::now();
+    let sent = send_message(config, api, &client_token).await;
+    let Some(message_id) = runner.record("message_send", at, sent) else {
+        return;
+    };
+
+    let at = Instant::now();
+    let dispatched = wait_for_dispatch(config, api, &client_token, &message_id).await;
+    let assigned_provider = runner.record("message_dispatch", at, dispatched).flatten();
+
+    // A provider can only confirm messages it was assigned; otherwise an
+    // integration reports the delivery through the signed webhook instead
+    let delivered_by_us = assigned_provider.as_deref() == Some(provider_id.as_str());
+    if delivered_by_us {
+        let at = Instant::now();
+        let confirmed = confirm_delivery(config, api, &provider_token, &message_id).await;
+        runner.record("delivery_confirm", at, confirmed);
+    } else {
+        runner.skip(
+            "delivery_confirm",
+            format!(
+                "message went to {} rather than the smoke provider (not onboarded, or another provider was picked)",
+                assigned_provider.as_deref().unwrap_or("no provider")
+            ),
+        );
+    }
+
+    let at = Instant::now();
+    let unsigned = check_unsigned_webhook_rejected(api, &message_id).await;
+    runner.record("webhook_rejects_unsigned", at, unsigned);
+
+    if delivered_by_us {
+        runner.skip(
+            "webhook_delivery",
+            "delivery already confirmed by the provider",
+        );
+    } else if let (Some(integration), Some(secret)) =
+        (&config.webhook_integration, &config.webhook_secret)
+    {
+        let at = Instant::now();
+        let reported = report_via_webhook(api, integration, secret, &message_id).await;
+        runner.record("webhook_delivery", at, reported);
+    } else {
+        runner.skip(
+            "webhook_delivery",
+            "SMOKE_WEBHOOK_INTEGRATION / SMOKE_WEBHOOK_SECRET not set",
+        );
+    }
+
+    let at = Instant::now();
+    let final_status = check_delivered(api, &client_token, &message_id).await;
+    runner.record("message_delivered", at, final_status);
+
+    let at = Instant::now();
+    let earnings = check_earnings(api, &provider_token, delivered_by_us).await;
+    runner.record("provider_earnings", at, earnings);
+}
+
+async fn check_health(api: &Api) -> Result<((), String), String> {
+    api.expect_ok(Method::GET, "/health", None, None).await?;
+    let ready = api.expect_ok(Method::GET, "/ready", None, None).await?;
+    Ok(((), format!("ready: {}", ready["checks"])))
+}
+
+/// Sign in through the OTP debug hook, for the given app (`client` or `provider`)
+async fn login(
+    config: &SmokeConfig,
+    api: &Api,
+    phone: &str,
+    app: &str,
+) -> Result<(String, String), String> {
+    let challenge = solve_otp_challenge(api).await?;
+    api.expect_ok(
+        Method::POST,
+        "/api/v1/auth/send-otp",
+        None,
+        Some(&json!({ "phone": phone, "challenge": challenge })),
+    )
+    .await?;
+
+    let (status, pending) = Api::read(
+        api.http
+            .get(format!("{}/api/v1/debug/otp/{}", api.base_url, phone))
+            .header("x-peerpower-debug-token", &config.debug_otp_token),
+    )
+    .await?;
+    if !status.is_success() {
+        return Err(format!(
+            "OTP debug hook returned {} (is DEBUG_OTP_TOKEN set on the target?): {}",
+            status, pending
+        ));
+    }
+    let otp = pending["otp"]
+        .as_str()
+        .ok_or("OTP debug hook returned no code")?;
+
+    let auth = api
+        .expect_ok(
+            Method::POST,
+            "/api/v1/auth/verify-otp",
+            None,
+            Some(&json!({ "phone": phone, "otp": otp, "app": app })),
+        )
+        .await?;
+    let token = auth["access_token"]
+        .as_str()
+        .ok_or("verify-otp returned no access token")?;
+    Ok((
+        token.to_string(),
+        format!("signed in as user {}", auth["user"]["id"]),
+    ))
+}
+
+/// Solve the send-otp proof-of-work; the target ignores it for trusted IPs
+async fn solve_otp_challenge(api: &Api) -> Result<Value, String> {
+    let challenge = api
+        .expect_ok(Method::POST, "/api/v1/auth/challenge", None, None)
+        .await?;
+    let prefix = challenge["prefix"]
+        .as_str()
+        .ok_or("challenge returned no prefix")?;
+    let difficulty_bits = challenge["difficulty_bits"].as_u64().unwrap_or(0) as u32;
+
+    let solution = (0u64..)
+        .map(|n| n.to_string())
+        .find(|candidate| {
+            let digest = sha2::Sha256::digest(format!("{}:{}", prefix, candidate).as_bytes());
+            leading_zero_bits(&digest) >= difficulty_bits
+        })
+        .expect("a solution exists");
+    Ok(json!({
+        "challenge_id": challenge["challenge_id"],
+        "solution": solution,
+    }))
+}
+
+fn leading_zero_bits(bytes: &[u8]) -> u32 {
+    let mut bits = 0;
+    for byte in bytes {
+        if *byte != 0 {
+            return bits + byte.leading_zeros();
+        }
+        bits += 8;
+    }
+    bits
+}
+
+/// Register the smoke provider, or reuse it from an earlier run
+async fn register_provider(
+    config: &SmokeConfig,
+    api: &Api,
+    token: &str,
+) -> Result<(String, String), String> {
+    let (status, registered) = api
+        .call(
+            Method::POST,
+            "/api/v1/providers/register",
+            Some(token),
+            Some(&json!({
+                "phone": config.provider_phone,
+                "fcm_token": "smoke-test",
+                "device_public_key": device_public_key(config)?,
+            })),
+        )
+        .await?;
+    if status.is_success() {
+        let provider_id = registered["provider_id"]
+            .as_str()
+            .ok_or("registration returned no provider id")?;
+        return Ok((provider_id.to_string(), "registered".to_string()));
+    }
+
+    let providers = api
+        .expect_ok(Method::GET, "/api/v1/providers", Some(token), None)
+        .await?;
+    let existing = providers["items"]
+        .as_array()
+        .and_then(|providers| providers.first())
+        .and_then(|provider| provider["provider_id"].as_str())
+        .ok_or_else(|| format!("registration returned {}: {}", status, registered))?;
+
+    // Registrations from before device keys still need one; an already
+    // enrolled key is left alone (set SMOKE_DEVICE_KEY_FILE to keep reusing it)
+    let (enrolled, _) = api
+        .call(
+            Method::PUT,
+            &format!("/api/v1/providers/{}/device-key", existing),
+            Some(token),
+            Some(&json!({ "device_public_key": device_public_key(config)? })),
+        )
+        .await?;
+    Ok((
+        existing.to_string(),
+        if enrolled.is_success() {
+            "reused existing registration, enrolled device key".to_string()
+        } else {
+            "reused existing registration".to_string()
+        },
+    ))
+}
+
+async fn send_heartbeat(api: &Api, token: &str, provider_id: &str) -> Result<((), String), String> {
+    api.expect_ok(
+        Method::POST,
+        &format!("/api/v1/providers/{}/heartbeat", provider_id),
+        Some(token),
+        Some(&json!({
+            "status": "Online",
+            "battery_level": 100,
+            "signal_strength": 100,
+        })),
+    )
+    .await?;
+
+    // Providers that haven't finished onboarding are held offline
+    let provider = api
+        .expect_ok(
+            Method::GET,
+            &format!("/api/v1/providers/{}", provider_id),
+            Some(token),
+            None,
+        )
+        .await?;
+    Ok(((), format!("provider status {}", provider["status"])))
+}
+
+async fn send_message(
+    config: &SmokeConfig,
+    api: &Api,
+    token: &str,
+) -> Result<(String, String), String> {
+    let sent = api
+        .expect_ok(
+            Method::POST,
+            "/api/v1/messages/send",
+            Some(token),
+            Some(&json!({
+                "recipient": config.recipient,
+                "content": format!("PeerPower smoke test {}", chrono::Utc::now().to_rfc3339()),
+                "tags": ["smoke-test"],
+            })),
+        )
+        .await?;
+    let message_id = sent["message_id"]
+        .as_str()
+        .ok_or("send returned no message id")?;
+    Ok((
+        message_id.to_string(),
+        format!("message {} queued", message_id),
+    ))
+}
+
+/// Wait until the job processor has picked the message up; returns the assigned provider
+async fn wait_for_dispatch(
+    config: &SmokeConfig,
+    api: &Api,
+    token: &str,
+    message_id: &str,
+) -> Result<(Option<String>, String), String> {
+    let deadline = Instant::now() + config.delivery_timeout;
+    loop {
+        let message = api
+            .expect_ok(
+                Method::GET,
+                &format!("/api/v1/messages/{}", message_id),
+                Some(token),
+                None,
+            )
+            .await?;
+        let status = message["status"]
+            .as_str()
+            .unwrap_or_default()
+            .to_lowercase();
+        let provider_id = message["provider_id"].as_str().map(str::to_string);
+
+        if status == "failed" {
+            return Err(format!(
+                "message failed before dispatch: {}",
+                message["last_error"]
+            ));
+        }
+        if provider_id.is_some() {
+            return Ok((provider_id, format!("status {}", status)));
+        }
+        if Instant::now() >= deadline {
+            return Err(format!(
+                "not dispatched within {}s (status {})",
+                config.delivery_timeout.as_secs(),
+                status
+            ));
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+async fn confirm_delivery(
+    config: &SmokeConfig,
+    api: &Api,
+    token: &str,
+    message_id: &str,
+) -> Result<((), String), String> {
+    let delivery_time = chrono::Utc::now().to_rfc3339();
+    let signed_at = chrono::Utc::now().timestamp();
+    let payload = format!("{}.delivered.{}..{}", message_id, delivery_time, signed_at);
+    let signature = base64::engine::general_purpose::STANDARD.encode(
+        SigningKey::<sha2::Sha256>::new(config.device_key.clone())
+            .sign(payload.as_bytes())
+            .to_bytes(),
+    );
+
+    let confirmed = api
+        .expect_ok(
+            Method::POST,
+            &format!("/api/v1/messages/{}/delivery", message_id),
+            Some(token),
+            Some(&json!({
+                "status": "delivered",
+                "delivery_time": delivery_time,
+                "signed_at": signed_at,
+                "signature": signature,
+            })),
+        )
+        .await?;
+    Ok(((), format!("earned {}", confirmed["provider_earnings"])))
+}
+
+async fn check_unsigned_webhook_rejected(
+    api: &Api,
+    message_id: &str,
+) -> Result<((), String), String> {
+    let (status, body) = api
+        .call(
+            Method::POST,
+            &format!("/api/v1/webhooks/delivery/{}", message_id),
+            None,
+            Some(&json!({ "status": "delivered" })),
+        )
+        .await?;
+    if status != StatusCode::UNAUTHORIZED {
+        return Err(format!(
+            "unsigned delivery report got {}, expected 401: {}",
+            status, body
+        ));
+    }
+    Ok(((), "unsigned report rejected".to_string()))
+}
+
+async fn report_via_webhook(
+    api: &Api,
+    integration: &str,
+    secret: &str,
+    message_id: &str,
+) -> Result<((), String), String> {
+    let body = json!({
+        "status": "delivered",
+        "delivery_time": chrono::Utc::now().to_rfc3339(),
+    })
+    .to_string();
+    let timestamp = chrono::Utc::now().timestamp().to_string();
+    let nonce = uuid::Uuid::new_v4().simple().to_string();
+
+    let mut mac = Hmac::<sha2::Sha256>::new_from_slice(secret.as_bytes()).expect("any key length");
+    mac.update(format!("{}.{}.{}.{}", timestamp, nonce, message_id, body).as_bytes());
+    let signature = hex::encode(mac.finalize().into_bytes());
+
+    let (status, response) = Api::read(
+        api.http
+            .post(format!(
+                "{}/api/v1/webhooks/delivery/{}",
+                api.base_url, message_id
+            ))
+            .header("content-type", "application/json")
+            .header("x-peerpower-integration", integration)
+            .header("x-peerpower-timestamp", timestamp)
+            .header("x-peerpower-nonce", nonce)
+            .header("x-peerpower-signature", format!("sha256={}", signature))
+            .body(body),
+    )
+    .await?;
+    if !status.is_success() {
+        return Err(format!(
+            "signed delivery report got {}: {}",
+            status, response
+        ));
+    }
+    Ok(((), format!("reported by {}", integration)))
+}
+
+async fn check_delivered(api: &Api, token: &str, message_id: &str) -> Result<((), String), String> {
+    let message = api
+        .expect_ok(
+            Method::GET,
+            &format!("/api/v1/messages/{}", message_id),
+            Some(token),
+            None,
+        )
+        .await?;
+    let status = message["status"]
+        .as_str()
+        .unwrap_or_default()
+        .to_lowercase();
+    if status != "delivered" {
+        return Err(format!("message status is {}, expected delivered", status));
+    }
+    Ok(((), "delivered".to_string()))
+}
+
+async fn check_earnings(
+    api: &Api,
+    token: &str,
+    delivered_by_us: bool,
+) -> Result<((), String), String> {
+    let earnings = api
+        .expect_ok(
+            Method::GET,
+            "/api/v1/earnings/summary?period=today",
+            Some(token),
+            None,
+        )
+        .await?;
+    let delivered = earnings["messages_delivered"].as_u64().unwrap_or_default();
+    if delivered_by_us && delivered == 0 {
+        return Err("delivered message not counted in today's earnings".to_string());
+    }
+    Ok((
+        (),
+        format!(
+            "{} delivered today, {} PPT",
+            delivered, earnings["total_earnings"]
+        ),
+    ))
+}
+
+fn print_report(report: &SmokeReport) {
+    println!("PeerPower smoke test against {}", report.target);
+    for step in &report.steps {
+        let outcome = match step.outcome {
+            Outcome::Pass => "PASS",
+            Outcome::Fail => "FAIL",
+            Outcome::Skip => "SKIP",
+        };
+        println!(
+            "  {:<26} {} {:>6}ms  {}",
+            step.name, outcome, step.duration_ms, step.detail
+        );
+    }
+    println!(
+        "{} in {}ms",
+        if report.passed { "PASSED" } else { "FAILED" },
+        report.duration_ms
+    );
+}
@@ -1,5 +1,8 @@
-use crate::shared::PeerPowerError;
+use crate::shared::retry::{RetryClass, RetryOverride};
+use crate::shared::{PeerPowerError, RetryPolicy};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppConfig {
@@ -9,6 +12,34 @@ pub struct AppConfig {
     pub auth: AuthConfig,
     pub external: ExternalServicesConfig,
     pub instance: InstanceConfig,
+    pub providers: ProviderConfig,
+    pub jobs: JobProcessorConfig,
+    pub retries: RetryConfig,
+    pub quiet_hours: QuietHoursConfig,
+    pub downloads: DownloadConfig,
+    pub quality: QualityConfig,
+    pub earnings: EarningsConfig,
+    pub reconciliation: ReconciliationConfig,
+    pub backups: BackupConfig,
+    pub exchange_rates: ExchangeRateConfig,
+    pub payouts: PayoutConfig,
+    pub privacy: PrivacyConfig,
+    pub rate_limits: RateLimitConfig,
+    pub number_pool: NumberPoolConfig,
+    pub pricing: PricingConfig,
+    pub surge_pricing: SurgePricingConfig,
+    pub referrals: ReferralConfig,
+    pub staking: StakingConfig,
+    pub load_shedding: LoadSheddingConfig,
+    pub backpressure: BackpressureConfig,
+    pub sla: SlaConfig,
+    pub retention: RetentionConfig,
+    pub change_streams: ChangeStreamConfig,
+    pub cors: CorsConfig,
+    pub lockout: LockoutConfig,
+    pub otp_challenge: OtpChallengeConfig,
+    pub admin: AdminConfig,
+    pub identity: IdentityConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,6 +47,8 @@ pub struct ServerConfig {
     pub host: String,
     pub port: u16,
     pub environment: Environment,
+    pub max_body_bytes: usize, // larger request bodies are rejected with 413
+    pub trusted_proxies: Vec<IpAddr>, // peers whose X-Forwarded-For is believed
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,6 +58,11 @@ pub struct DatabaseConfig {
     pub max_connections: u32,
     pub min_connections: u32,
     pub connection_timeout_seconds: u64,
+    pub slow_operation_threshold_ms: u64,
+    pub pool_saturation_threshold: f64, // fraction of max_connections checked out
+    pub pool_saturation_alert_seconds: u64,
+    pub reporting_read_preference: String, // primary, primaryPreferred, secondary, secondaryPreferred or nearest
+    pub reporting_max_staleness_seconds: Option<u64>, // at least 90 when set
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -36,9 +74,19 @@ pub struct RedisConfig {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuthConfig {
-    pub jwt_secret: String,
+    pub jwt_secret: Option<String>, // legacy HS256 tokens are accepted while set
+    pub jwt_keys_dir: Option<String>,
+    pub jwt_active_kid: Option<String>,
     pub jwt_expiration_hours: i64,
+    pub jwt_issuer: String, // distinct per deployment, so tokens don't cross environments
+    pub jwt_leeway_seconds: u64, // clock skew tolerated on exp/nbf
     pub otp_expiration_minutes: i64,
+    pub otp_coalesce_seconds: i64,
+    pub otp_resend_cooldown_seconds: i64,
+    pub client_token_lifetime_seconds: i64,
+    pub client_token_max_lifetime_seconds: i64,
+    pub impersonation_token_lifetime_seconds: i64,
+    pub debug_otp_token: Option<String>, // enables the OTP debug hook outside production
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -46,6 +94,12 @@ pub struct ExternalServicesConfig {
     pub fcm: FcmConfig,
     pub baray: BarayConfig,
     pub selendra: SelendraConfig,
+    pub sms_gateway: SmsGatewayConfig,
+    pub telegram: TelegramConfig,
+    pub voice_gateway: VoiceGatewayConfig,
+    pub delivery_model: DeliveryModelConfig,
+    pub delivery_webhook: DeliveryWebhookConfig,
+    pub play_integrity: PlayIntegrityConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -60,6 +114,57 @@ pub struct BarayConfig {
     pub api_key: String,
     pub webhook_secret: String,
     pub base_url: String,
+    pub checkout_return_url: String, // where Baray sends the client after paying
+    pub webhook_max_skew_seconds: i64, // oldest webhook timestamp accepted
+}
+
+/// External SMS gateway used when no PeerPower provider can take a message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmsGatewayConfig {
+    pub url: String,
+    pub api_key: String,
+    pub sender_id: String,
+}
+
+/// Telegram bot used as an OTP channel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TelegramConfig {
+    pub bot_token: String,
+    pub webhook_secret: String, // checked against X-Telegram-Bot-Api-Secret-Token
+    pub api_url: String,
+}
+
+/// Text-to-speech call gateway used as the last-resort OTP channel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VoiceGatewayConfig {
+    pub url: String,
+    pub api_key: String,
+    pub language: String,
+}
+
+/// Optional external delivery-time model; the built-in heuristic is used when unset
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeliveryModelConfig {
+    pub url: String,
+    pub api_key: String,
+    pub timeout_ms: u64, // kept short, the heuristic answers if it's exceeded
+}
+
+/// Integrations allowed to report delivery status through the public webhook
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeliveryWebhookConfig {
+    pub secrets: HashMap<String, String>, // integration id -> HMAC secret
+    pub max_skew_seconds: i64,            // oldest signed timestamp accepted
+}
+
+/// Google Play Integrity, used to attest provider devices
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayIntegrityConfig {
+    pub package_name: String,
+    pub service_account_key_file: Option<String>, // Google service account JSON; unset disables attestation
+    pub required_at_registration: bool,
+    pub max_token_age_seconds: i64,
+    pub unattested_max_daily_messages: u32, // admins can't raise an unattested device above this
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -67,6 +172,17 @@ pub struct SelendraConfig {
     pub rpc_url: String,
     pub private_key: String,
     pub token_contract_address: String,
+    pub chain_id: u64,
+    pub token_decimals: u32,
+    pub gas_limit: u64, // cap on gas estimates
+    pub confirmations: u64,
+    pub rpc_timeout_ms: u64,
+    pub rpc_max_retries: u32, // for transport errors, 429s and 5xxs
+    pub rpc_pool_size: usize,
+    pub settlement_interval_seconds: u64,
+    pub settlement_batch_size: i64,
+    pub min_settlement_ppt: f64, // smaller balances wait for the next batch
+    pub staking_contract_address: String, // provider stakes; empty disables staking
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -74,6 +190,446 @@ pub struct InstanceConfig {
     pub id: String,
     pub region: String,
     pub zone: Option<String>,
+    pub canary: bool,
+    pub canary_traffic_percent: u8, // initial share; overridable at runtime via Redis
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderConfig {
+    pub carrier_reverify_interval_days: i64,
+    pub self_test_number: Option<String>,
+    pub require_signed_confirmations: bool, // reject confirmations from providers without a device key
+    pub confirmation_max_age_seconds: i64,
+}
+
+/// Concurrency of the message dispatch workers on each instance
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobProcessorConfig {
+    pub workers: usize,
+    pub max_in_flight_fcm: usize, // FCM dispatch requests open at once, across all workers
+    pub fcm_batch_window_ms: u64, // how long a dispatch waits for others to the same provider
+    pub shutdown_grace_seconds: u64, // how long workers get to finish their jobs on shutdown
+    pub dispatch_ack_timeout_seconds: u64, // how long a device gets to acknowledge a dispatch
+    pub dispatch_strategy: DispatchStrategyKind,
+}
+
+/// How the dispatcher picks a provider among those eligible for a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DispatchStrategyKind {
+    SameCarrierFirst,   // the recipient's carrier if any is free, best-scored within it
+    GeoAware,           // the recipient's province first, then its carrier
+    ReputationWeighted, // best-scored of any carrier, with reputation weighted up
+    RoundRobin,         // the longest idle of any carrier
+}
+
+/// System-wide hours when non-urgent messages are held, unless a client
+/// sets its own
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuietHoursConfig {
+    pub enabled: bool,
+    pub start_hour: u32,       // local hour, inclusive
+    pub end_hour: u32,         // local hour, exclusive
+    pub utc_offset_hours: i32, // Cambodia is UTC+7
+}
+
+/// Retry policies for the dispatch pipeline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetryConfig {
+    pub jobs: RetryPolicy, // failed dispatch attempts, re-queued through Redis
+    pub fcm: RetryPolicy,  // FCM HTTP requests, retried in place
+}
+
+impl RetryConfig {
+    /// `{PREFIX}_MAX_ATTEMPTS`, `_BASE_DELAY_MS`, `_MAX_DELAY_MS`, `_JITTER`,
+    /// and `_OVERRIDES` as `class:max_attempts:base_delay_ms,...`
+    fn policy_from_env(
+        prefix: &str,
+        max_attempts: u32,
+        base_delay_ms: u64,
+        max_delay_ms: u64,
+        overrides: &str,
+    ) -> RetryPolicy {
+        let var = |name: &str| std::env::var(format!("{}_{}", prefix, name)).ok();
+        RetryPolicy {
+            max_attempts: var("MAX_ATTEMPTS")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(max_attempts)
+                .max(1),
+            base_delay_ms: var("BASE_DELAY_MS")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(base_delay_ms),
+            max_delay_ms: var("MAX_DELAY_MS")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(max_delay_ms),
+            jitter: var("JITTER")
+                .and_then(|v| v.parse::<f64>().ok())
+                .unwrap_or(0.2)
+                .clamp(0.0, 1.0),
+            overrides: var("OVERRIDES")
+                .unwrap_or_else(|| overrides.to_string())
+                .split(',')
+                .filter_map(|entry| {
+                    let mut parts = entry.split(':');
+                    let class = RetryClass::parse(parts.next()?)?;
+                    let max_attempts = parts.next()?.trim().parse().ok()?;
+                    let base_delay_ms = parts.next()?.trim().parse().ok()?;
+                    Some((
+                        class,
+                        RetryOverride {
+                            max_attempts,
+                            base_delay_ms,
+                        },
+                    ))
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DownloadConfig {
+    pub link_secret: String,
+    pub link_ttl_seconds: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QualityConfig {
+    pub apdex_target_seconds: i64,
+    pub alert_webhook_url: Option<String>, // the account team's channel for SLA breaches
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EarningsConfig {
+    pub off_peak_multiplier: f64,
+    pub off_peak_start_hour: u32, // local hour, inclusive
+    pub off_peak_end_hour: u32,   // local hour, exclusive
+    pub utc_offset_hours: i32,    // Cambodia is UTC+7
+    pub volume_bonus_tiers: Vec<VolumeBonusTier>, // ascending by deliveries
+    pub quality_bonus: QualityBonusConfig,
+}
+
+/// Monthly bonus for each quality target a provider meets
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QualityBonusConfig {
+    pub percent_per_target: f64,  // of the month's earnings, per target met
+    pub min_deliveries: u64,      // fewer in a month and no bonus is paid
+    pub success_rate_target: f64, // percent delivered of delivered + failed, at least
+    pub confirmation_seconds_target: f64, // average dispatch to confirmation, at most
+    pub dispute_rate_target: f64, // percent of deliveries disputed, at most
+}
+
+/// Bonus on a month's earnings once a provider delivers enough messages in it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VolumeBonusTier {
+    pub min_deliveries: u64,
+    pub bonus_percent: f64,
+}
+
+/// Nightly check of provider delivery counts and earnings against the ledger.
+/// Drift on the provider records within the tolerances can be corrected to
+/// match the ledger; anything larger is only reported.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReconciliationConfig {
+    pub auto_correct: bool,
+    pub count_tolerance: u64, // deliveries
+    pub amount_tolerance_ppt: f64,
+}
+
+impl EarningsConfig {
+    /// Whether `at` falls in the off-peak window (which may wrap past midnight)
+    pub fn is_off_peak(&self, at: chrono::DateTime<chrono::Utc>) -> bool {
+        use chrono::Timelike;
+
+        let hour = (at + chrono::Duration::hours(self.utc_offset_hours as i64)).hour();
+        if self.off_peak_start_hour <= self.off_peak_end_hour {
+            hour >= self.off_peak_start_hour && hour < self.off_peak_end_hour
+        } else {
+            hour >= self.off_peak_start_hour || hour < self.off_peak_end_hour
+        }
+    }
+
+    /// The local calendar day `at` falls on, used to bucket earnings
+    pub fn local_date(&self, at: chrono::DateTime<chrono::Utc>) -> chrono::NaiveDate {
+        (at + chrono::Duration::hours(self.utc_offset_hours as i64)).date_naive()
+    }
+
+    /// The UTC instant the local calendar day `date` starts
+    pub fn local_day_start(&self, date: chrono::NaiveDate) -> chrono::DateTime<chrono::Utc> {
+        date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc()
+            - chrono::Duration::hours(self.utc_offset_hours as i64)
+    }
+
+    /// Bonus percent for a month with `deliveries` deliveries: the highest tier reached
+    pub fn volume_bonus_percent(&self, deliveries: u64) -> f64 {
+        self.volume_bonus_tiers
+            .iter()
+            .filter(|tier| deliveries >= tier.min_deliveries)
+            .map(|tier| tier.bonus_percent)
+            .fold(0.0, f64::max)
+    }
+
+    pub fn time_of_day_multiplier(&self, at: chrono::DateTime<chrono::Utc>) -> f64 {
+        if self.is_off_peak(at) {
+            self.off_peak_multiplier
+        } else {
+            1.0
+        }
+    }
+}
+
+/// S3-compatible object storage for state backups
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupConfig {
+    pub endpoint: String,
+    pub bucket: String,
+    pub region: String,
+    pub access_key: String,
+    pub secret_key: String,
+    pub kms_key_id: Option<String>, // SSE-KMS when set, otherwise SSE-S3 (AES256)
+    pub prefix: String,
+}
+
+/// PPT conversion rates used when locking payout FX
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExchangeRateConfig {
+    pub ppt_khr: f64, // riel per PPT
+    pub ppt_usd: f64, // US dollars per PPT
+    pub source_url: String, // empty: use the rates above
+    pub source_timeout_ms: u64,
+    pub cache_ttl_seconds: u64,
+}
+
+/// Batching of approved payouts into Baray disbursements
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PayoutConfig {
+    pub batch_interval_seconds: u64,
+    pub batch_size: i64,
+    pub max_attempts: u32, // failed submissions before a payout is failed and refunded
+    // Withdrawal limits; requests breaking a hard limit are refused, and
+    // suspicious ones wait for admin review instead of being approved
+    pub auto_approve: bool,
+    pub min_payout_ppt: f64,
+    pub daily_cap_ppt: f64,         // per provider, over the trailing 24 hours
+    pub velocity_max_requests: u32, // requests beyond this many in the window go to review
+    pub velocity_window_minutes: i64,
+    pub review_threshold_ppt: f64,   // larger withdrawals go to review
+    pub new_provider_hold_days: i64, // no withdrawals this soon after registering
+}
+
+/// Keys for clients in recipient privacy mode
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrivacyConfig {
+    pub recipient_encryption_key: Option<String>, // AES-256 key, hex
+    pub recipient_hash_salt: String,
+    pub phone_encryption_key: Option<String>, // AES-256 key, hex; encrypts stored phone numbers
+}
+
+/// Per-IP request budgets, each refilled continuously over a minute
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    pub default_per_minute: u32,
+    pub send_otp_per_minute: u32, // send-otp and resend-otp share a bucket
+    pub verify_otp_per_minute: u32,
+    pub send_message_per_minute: u32,
+    pub admin_per_minute: u32,
+}
+
+/// Dedicated number rental pricing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NumberPoolConfig {
+    pub default_monthly_rent: f64, // PPT tokens, when not set per number
+    pub provider_rent_share: f64,  // fraction of rent credited to the SIM's provider
+}
+
+/// Message prices and the platform's cut of each charge
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PricingConfig {
+    pub base_message_cost: f64, // PPT per 160-character segment at normal priority
+    pub platform_fee_percent: f64, // share of a charge kept by the platform
+    // Message type ("otp", "standard") or priority ("urgent") -> percent
+    pub fee_percent_overrides: HashMap<String, f64>,
+}
+
+impl PricingConfig {
+    /// Fee for a message: a message type override wins over a priority
+    /// override, which wins over the default
+    pub fn fee_percent(&self, message_type: &str, priority: &str) -> f64 {
+        self.fee_percent_overrides
+            .get(message_type)
+            .or_else(|| self.fee_percent_overrides.get(priority))
+            .copied()
+            .unwrap_or(self.platform_fee_percent)
+            .clamp(0.0, 100.0)
+    }
+}
+
+/// Bounds on the per-carrier surge multiplier, applied to a message's
+/// charge (and so the provider's share) when queued messages outrun the
+/// providers online to deliver them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SurgePricingConfig {
+    pub enabled: bool,
+    pub min_multiplier: f64, // below 1.0 discounts carriers with idle providers
+    pub max_multiplier: f64,
+    pub target_queue_per_provider: f64, // queued messages per online provider priced at 1.0
+    pub recalc_interval_seconds: u64,
+}
+
+/// Bonuses paid when a referred user's deliveries show they stuck around
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReferralConfig {
+    pub required_deliveries: u32, // referee's successful deliveries before paying out
+    pub referrer_bonus: f64,      // PPT
+    pub referee_bonus: f64,       // PPT
+}
+
+/// Provider stakes on the staking contract and what they unlock
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StakingConfig {
+    pub tiers: Vec<StakeTier>,   // ascending by stake
+    pub priority_min_stake: f64, // PPT staked for priority in job assignment
+    pub slash_percent: f64,      // of the stake, per confirmed fraudulent confirmation
+}
+
+/// Daily message limit unlocked once a provider stakes enough PPT
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StakeTier {
+    pub min_stake: f64,
+    pub max_daily_messages: u32,
+}
+
+impl StakingConfig {
+    /// Daily limit unlocked by `stake`: the highest tier reached, 0 below the lowest
+    pub fn daily_limit(&self, stake: f64) -> u32 {
+        self.tiers
+            .iter()
+            .filter(|tier| stake >= tier.min_stake)
+            .map(|tier| tier.max_daily_messages)
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn has_priority(&self, stake: f64) -> bool {
+        stake > 0.0 && stake >= self.priority_min_stake
+    }
+}
+
+/// Cross-origin access for browser clients
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CorsConfig {
+    pub allowed_origins: Vec<String>, // "*" allows any origin (not in production)
+    pub allowed_methods: Vec<String>,
+    pub allowed_headers: Vec<String>,
+    pub allow_credentials: bool,
+    pub max_age_seconds: u64,
+}
+
+/// Temporary lockout after repeated failed OTP verifications; each lock
+/// within `level_memory_seconds` of the last doubles the cooldown
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockoutConfig {
+    pub enabled: bool,
+    pub phone_max_failures: i64,
+    pub ip_max_failures: i64,
+    pub failure_window_seconds: u64,
+    pub base_cooldown_seconds: u64,
+    pub max_cooldown_seconds: u64,
+    pub level_memory_seconds: u64,
+}
+
+/// Proof-of-work required before sending OTPs to IPs without a recent
+/// successful sign-in, since every OTP costs real SMS quota
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OtpChallengeConfig {
+    pub enabled: bool,
+    pub difficulty_bits: u32, // leading zero bits of SHA-256 the solution must produce
+    pub ttl_seconds: u64,
+    pub trusted_ips: Vec<String>, // never challenged, e.g. office or partner egress IPs
+    pub trust_days: u64,          // how long a successful sign-in exempts its IP
+}
+
+/// DIDs and verifiable credentials issued by the platform
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdentityConfig {
+    pub issuer_key: Option<String>, // secp256k1 key, hex; unset disables DID issuance
+    pub credential_ttl_days: i64,
+    pub did_proof_max_age_seconds: i64, // oldest proof accepted when a user registers their own DID
+}
+
+/// Who may use the admin API. With no admins listed, development lets any
+/// admin-console token through; staging and production let none through.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdminConfig {
+    pub user_ids: Vec<String>,
+    pub audit_reads: bool, // also audit GET requests, not just changes
+}
+
+/// Thresholds for shedding non-core endpoints when the backends slow down
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoadSheddingConfig {
+    pub enabled: bool,
+    pub sample_interval_seconds: u64,
+    pub degraded_latency_ms: u64, // Mongo or Redis ping round trip
+    pub critical_latency_ms: u64,
+    pub degraded_queue_depth: u64,
+    pub critical_queue_depth: u64,
+    pub degraded_pool_saturation: f64, // fraction of Mongo connections checked out
+    pub critical_pool_saturation: f64,
+}
+
+/// Backlog limits past which `send_message` holds back low-priority traffic
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackpressureConfig {
+    pub enabled: bool,
+    pub sample_interval_seconds: u64,
+    pub max_queue_depth: u64,
+    pub max_pending_age_seconds: u64, // age of the oldest message still Pending
+    pub shed_priorities: Vec<String>, // lowercase priority names held back
+    pub mode: BackpressureMode,
+    pub retry_after_seconds: u64, // sent with rejections; how long deferred jobs wait
+}
+
+/// Delivery time promised per priority, counted from submission (or the
+/// scheduled time), and how often open messages are checked against it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlaConfig {
+    pub enabled: bool,
+    pub check_interval_seconds: u64,
+    pub urgent_seconds: u64,
+    pub high_seconds: u64,
+    pub normal_seconds: u64,
+    pub low_seconds: u64,
+    pub webhook_secret: String, // signs breach notifications to clients; none are sent without it
+}
+
+/// How long finished records are kept before the retention task deletes
+/// them; 0 days keeps a collection forever
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionConfig {
+    pub enabled: bool,
+    pub dry_run: bool, // only report what would be deleted
+    pub interval_hours: u64,
+    pub message_days: i64, // delivered, failed and cancelled messages
+    pub job_days: i64,     // finished jobs
+    pub auth_event_days: i64,
+    pub deleted_days: i64, // soft-deleted users, providers and messages
+}
+
+/// Following message status changes from MongoDB, whoever wrote them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangeStreamConfig {
+    pub enabled: bool,
+    pub status_webhooks: bool, // post final statuses to the message's webhook_url
+}
+
+/// What happens to a message held back by backpressure
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackpressureMode {
+    Reject, // 503 with Retry-After
+    Defer,  // accepted, but queued only after `retry_after_seconds`
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -83,13 +639,23 @@ pub enum Environment {
     Production,
 }
 
+impl Environment {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Environment::Development => "development",
+            Environment::Staging => "staging",
+            Environment::Production => "production",
+        }
+    }
+}
+
 impl AppConfig {
     /// Load configuration from environment variables and config files
     pub fn from_env() -> Result<Self, PeerPowerError> {
         // Load from environment first
         dotenvy::dotenv().ok(); // Don't fail if .env doesn't exist
 
-        let config = AppConfig {
+        let mut config = AppConfig {
             server: ServerConfig {
                 host: std::env::var("HOST").unwrap_or_else(|_| "0.0.0.0".to_string()),
                 port: std::env::var("PORT")
@@ -107,6 +673,21 @@ impl AppConfig {
                     "staging" => Environment::Staging,
                     _ => Environment::Development,
                 },
+                max_body_bytes: std::env::var("MAX_REQUEST_BODY_BYTES")
+                    .unwrap_or_else(|_| "1048576".to_string())
+                    .parse()
+                    .unwrap_or(1_048_576),
+                trusted_proxies: std::env::var("TRUSTED_PROXIES")
+                    .unwrap_or_default()
+                    .split(',')
+                    .map(|ip| ip.trim())
+                    .filter(|ip| !ip.is_empty())
+                    .map(|ip| {
+                        ip.parse().map_err(|_| PeerPowerError::Configuration {
+                            message: format!("Invalid TRUSTED_PROXIES address: {}", ip),
+                        })
+                    })
+                    .collect::<Result<_, _>>()?,
             },
             database: DatabaseConfig {
                 url: std::env::var("DATABASE_URL").map_err(|_| PeerPowerError::Configuration {
@@ -125,6 +706,25 @@ impl AppConfig {
                     .unwrap_or_else(|_| "30".to_string())
                     .parse()
                     .unwrap_or(30),
+                slow_operation_threshold_ms: std::env::var("DATABASE_SLOW_OP_THRESHOLD_MS")
+                    .unwrap_or_else(|_| "200".to_string())
+                    .parse()
+                    .unwrap_or(200),
+                pool_saturation_threshold: std::env::var("DATABASE_POOL_SATURATION_THRESHOLD")
+                    .unwrap_or_else(|_| "0.9".to_string())
+                    .parse()
+                    .unwrap_or(0.9),
+                pool_saturation_alert_seconds: std::env::var("DATABASE_POOL_SATURATION_ALERT_SECONDS")
+                    .unwrap_or_else(|_| "60".to_string())
+                    .parse()
+                    .unwrap_or(60),
+                reporting_read_preference: std::env::var("DATABASE_REPORTING_READ_PREFERENCE")
+                    .unwrap_or_else(|_| "secondaryPreferred".to_string()),
+                reporting_max_staleness_seconds: std::env::var(
+                    "DATABASE_REPORTING_MAX_STALENESS_SECONDS",
+                )
+                .ok()
+                .and_then(|v| v.parse().ok()),
             },
             redis: RedisConfig {
                 url: std::env::var("REDIS_URL").map_err(|_| PeerPowerError::Configuration {
@@ -140,19 +740,49 @@ impl AppConfig {
                     .unwrap_or(10),
             },
             auth: AuthConfig {
-                jwt_secret: std::env::var("JWT_SECRET").map_err(|_| {
-                    PeerPowerError::Configuration {
-                        message: "JWT_SECRET is required".to_string(),
-                    }
-                })?,
+                jwt_secret: std::env::var("JWT_SECRET").ok(),
+                jwt_keys_dir: std::env::var("JWT_KEYS_DIR").ok(),
+                jwt_active_kid: std::env::var("JWT_ACTIVE_KID").ok(),
                 jwt_expiration_hours: std::env::var("JWT_EXPIRATION_HOURS")
                     .unwrap_or_else(|_| "24".to_string())
                     .parse()
                     .unwrap_or(24),
+                jwt_issuer: std::env::var("JWT_ISSUER").unwrap_or_default(),
+                jwt_leeway_seconds: std::env::var("JWT_LEEWAY_SECONDS")
+                    .unwrap_or_else(|_| "30".to_string())
+                    .parse()
+                    .unwrap_or(30),
                 otp_expiration_minutes: std::env::var("OTP_EXPIRATION_MINUTES")
                     .unwrap_or_else(|_| "5".to_string())
                     .parse()
                     .unwrap_or(5),
+                otp_coalesce_seconds: std::env::var("OTP_COALESCE_SECONDS")
+                    .unwrap_or_else(|_| "30".to_string())
+                    .parse()
+                    .unwrap_or(30),
+                otp_resend_cooldown_seconds: std::env::var("OTP_RESEND_COOLDOWN_SECONDS")
+                    .unwrap_or_else(|_| "60".to_string())
+                    .parse()
+                    .unwrap_or(60),
+                client_token_lifetime_seconds: std::env::var("CLIENT_TOKEN_LIFETIME_SECONDS")
+                    .unwrap_or_else(|_| "3600".to_string())
+                    .parse()
+                    .unwrap_or(3600),
+                client_token_max_lifetime_seconds: std::env::var(
+                    "CLIENT_TOKEN_MAX_LIFETIME_SECONDS",
+                )
+                .unwrap_or_else(|_| "86400".to_string())
+                .parse()
+                .unwrap_or(86400),
+                impersonation_token_lifetime_seconds: std::env::var(
+                    "IMPERSONATION_TOKEN_LIFETIME_SECONDS",
+                )
+                .unwrap_or_else(|_| "900".to_string())
+                .parse()
+                .unwrap_or(900),
+                debug_otp_token: std::env::var("DEBUG_OTP_TOKEN")
+                    .ok()
+                    .filter(|token| !token.is_empty()),
             },
             external: ExternalServicesConfig {
                 fcm: FcmConfig {
@@ -166,6 +796,12 @@ impl AppConfig {
                     webhook_secret: std::env::var("BARAY_WEBHOOK_SECRET").unwrap_or_default(),
                     base_url: std::env::var("BARAY_BASE_URL")
                         .unwrap_or_else(|_| "https://api.baray.io".to_string()),
+                    checkout_return_url: std::env::var("BARAY_CHECKOUT_RETURN_URL")
+                        .unwrap_or_else(|_| "https://peerpower.app/topup/complete".to_string()),
+                    webhook_max_skew_seconds: std::env::var("BARAY_WEBHOOK_MAX_SKEW_SECONDS")
+                        .unwrap_or_else(|_| "300".to_string())
+                        .parse()
+                        .unwrap_or(300),
                 },
                 selendra: SelendraConfig {
                     rpc_url: std::env::var("SELENDRA_RPC_URL")
@@ -173,6 +809,109 @@ impl AppConfig {
                     private_key: std::env::var("SELENDRA_PRIVATE_KEY").unwrap_or_default(),
                     token_contract_address: std::env::var("PPT_CONTRACT_ADDRESS")
                         .unwrap_or_default(),
+                    chain_id: std::env::var("SELENDRA_CHAIN_ID")
+                        .unwrap_or_else(|_| "1961".to_string())
+                        .parse()
+                        .unwrap_or(1961),
+                    token_decimals: std::env::var("PPT_TOKEN_DECIMALS")
+                        .unwrap_or_else(|_| "18".to_string())
+                        .parse()
+                        .unwrap_or(18),
+                    gas_limit: std::env::var("SELENDRA_GAS_LIMIT")
+                        .unwrap_or_else(|_| "100000".to_string())
+                        .parse()
+                        .unwrap_or(100_000),
+                    confirmations: std::env::var("SELENDRA_CONFIRMATIONS")
+                        .unwrap_or_else(|_| "3".to_string())
+                        .parse()
+                        .unwrap_or(3),
+                    rpc_timeout_ms: std::env::var("SELENDRA_RPC_TIMEOUT_MS")
+                        .unwrap_or_else(|_| "10000".to_string())
+                        .parse()
+                        .unwrap_or(10_000),
+                    rpc_max_retries: std::env::var("SELENDRA_RPC_MAX_RETRIES")
+                        .unwrap_or_else(|_| "3".to_string())
+                        .parse()
+                        .unwrap_or(3),
+                    rpc_pool_size: std::env::var("SELENDRA_RPC_POOL_SIZE")
+                        .unwrap_or_else(|_| "8".to_string())
+                        .parse()
+                        .unwrap_or(8),
+                    settlement_interval_seconds: std::env::var(
+                        "SELENDRA_SETTLEMENT_INTERVAL_SECONDS",
+                    )
+                    .unwrap_or_else(|_| "3600".to_string())
+                    .parse()
+                    .unwrap_or(3600),
+                    settlement_batch_size: std::env::var("SELENDRA_SETTLEMENT_BATCH_SIZE")
+                        .unwrap_or_else(|_| "50".to_string())
+                        .parse()
+                        .unwrap_or(50),
+                    min_settlement_ppt: std::env::var("SELENDRA_MIN_SETTLEMENT_PPT")
+                        .unwrap_or_else(|_| "10".to_string())
+                        .parse()
+                        .unwrap_or(10.0),
+                    staking_contract_address: std::env::var("PPT_STAKING_CONTRACT_ADDRESS")
+                        .unwrap_or_default(),
+                },
+                sms_gateway: SmsGatewayConfig {
+                    url: std::env::var("SMS_GATEWAY_URL").unwrap_or_default(),
+                    api_key: std::env::var("SMS_GATEWAY_API_KEY").unwrap_or_default(),
+                    sender_id: std::env::var("SMS_GATEWAY_SENDER_ID")
+                        .unwrap_or_else(|_| "PeerPower".to_string()),
+                },
+                telegram: TelegramConfig {
+                    bot_token: std::env::var("TELEGRAM_BOT_TOKEN").unwrap_or_default(),
+                    webhook_secret: std::env::var("TELEGRAM_WEBHOOK_SECRET").unwrap_or_default(),
+                    api_url: std::env::var("TELEGRAM_API_URL")
+                        .unwrap_or_else(|_| "https://api.telegram.org".to_string()),
+                },
+                voice_gateway: VoiceGatewayConfig {
+                    url: std::env::var("VOICE_GATEWAY_URL").unwrap_or_default(),
+                    api_key: std::env::var("VOICE_GATEWAY_API_KEY").unwrap_or_default(),
+                    language: std::env::var("VOICE_GATEWAY_LANGUAGE")
+                        .unwrap_or_else(|_| "km".to_string()),
+                },
+                delivery_model: DeliveryModelConfig {
+                    url: std::env::var("DELIVERY_MODEL_URL").unwrap_or_default(),
+                    api_key: std::env::var("DELIVERY_MODEL_API_KEY").unwrap_or_default(),
+                    timeout_ms: std::env::var("DELIVERY_MODEL_TIMEOUT_MS")
+                        .unwrap_or_else(|_| "300".to_string())
+                        .parse()
+                        .unwrap_or(300),
+                },
+                delivery_webhook: DeliveryWebhookConfig {
+                    // "integration:secret,integration:secret"
+                    secrets: std::env::var("DELIVERY_WEBHOOK_SECRETS")
+                        .unwrap_or_default()
+                        .split(',')
+                        .filter_map(|entry| entry.split_once(':'))
+                        .map(|(id, secret)| (id.trim().to_string(), secret.trim().to_string()))
+                        .filter(|(id, secret)| !id.is_empty() && !secret.is_empty())
+                        .collect(),
+                    max_skew_seconds: std::env::var("DELIVERY_WEBHOOK_MAX_SKEW_SECONDS")
+                        .unwrap_or_else(|_| "300".to_string())
+                        .parse()
+                        .unwrap_or(300),
+                },
+                play_integrity: PlayIntegrityConfig {
+                    package_name: std::env::var("PLAY_INTEGRITY_PACKAGE_NAME").unwrap_or_default(),
+                    service_account_key_file: std::env::var("PLAY_INTEGRITY_SERVICE_ACCOUNT_FILE")
+                        .ok()
+                        .filter(|path| !path.is_empty()),
+                    required_at_registration: std::env::var("PLAY_INTEGRITY_REQUIRED")
+                        .map(|v| v == "true" || v == "1")
+                        .unwrap_or(false),
+                    max_token_age_seconds: std::env::var("PLAY_INTEGRITY_MAX_TOKEN_AGE_SECONDS")
+                        .unwrap_or_else(|_| "600".to_string())
+                        .parse()
+                        .unwrap_or(600),
+                    unattested_max_daily_messages: std::env::var(
+                        "PLAY_INTEGRITY_UNATTESTED_MAX_DAILY",
+                    )
+                    .unwrap_or_else(|_| "50".to_string())
+                    .parse()
+                    .unwrap_or(50),
                 },
             },
             instance: InstanceConfig {
@@ -180,9 +919,637 @@ impl AppConfig {
                     .unwrap_or_else(|_| crate::shared::utils::generate_id()),
                 region: std::env::var("REGION").unwrap_or_else(|_| "unknown".to_string()),
                 zone: std::env::var("ZONE").ok(),
+                canary: std::env::var("INSTANCE_CANARY")
+                    .map(|v| v == "true" || v == "1")
+                    .unwrap_or(false),
+                canary_traffic_percent: std::env::var("CANARY_TRAFFIC_PERCENT")
+                    .unwrap_or_else(|_| "0".to_string())
+                    .parse::<u8>()
+                    .unwrap_or(0)
+                    .min(100),
+            },
+            providers: ProviderConfig {
+                carrier_reverify_interval_days: std::env::var("PROVIDER_CARRIER_REVERIFY_DAYS")
+                    .unwrap_or_else(|_| "30".to_string())
+                    .parse()
+                    .unwrap_or(30),
+                self_test_number: std::env::var("PROVIDER_SELF_TEST_NUMBER").ok(),
+                require_signed_confirmations: std::env::var(
+                    "PROVIDER_REQUIRE_SIGNED_CONFIRMATIONS",
+                )
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(true),
+                confirmation_max_age_seconds: std::env::var(
+                    "PROVIDER_CONFIRMATION_MAX_AGE_SECONDS",
+                )
+                .unwrap_or_else(|_| "300".to_string())
+                .parse()
+                .unwrap_or(300),
+            },
+            jobs: JobProcessorConfig {
+                workers: std::env::var("JOB_WORKERS")
+                    .unwrap_or_else(|_| "4".to_string())
+                    .parse::<usize>()
+                    .unwrap_or(4)
+                    .max(1),
+                max_in_flight_fcm: std::env::var("FCM_MAX_IN_FLIGHT")
+                    .unwrap_or_else(|_| "16".to_string())
+                    .parse::<usize>()
+                    .unwrap_or(16)
+                    .max(1),
+                fcm_batch_window_ms: std::env::var("FCM_BATCH_WINDOW_MS")
+                    .unwrap_or_else(|_| "20".to_string())
+                    .parse()
+                    .unwrap_or(20),
+                shutdown_grace_seconds: std::env::var("JOB_SHUTDOWN_GRACE_SECONDS")
+                    .unwrap_or_else(|_| "20".to_string())
+                    .parse()
+                    .unwrap_or(20),
+                dispatch_ack_timeout_seconds: std::env::var("DISPATCH_ACK_TIMEOUT_SECONDS")
+                    .unwrap_or_else(|_| "60".to_string())
+                    .parse::<u64>()
+                    .unwrap_or(60)
+                    .max(5),
+                dispatch_strategy: match std::env::var("DISPATCH_STRATEGY").as_deref() {
+                    Ok("geo_aware") => DispatchStrategyKind::GeoAware,
+                    Ok("reputation_weighted") => DispatchStrategyKind::ReputationWeighted,
+                    Ok("round_robin") => DispatchStrategyKind::RoundRobin,
+                    _ => DispatchStrategyKind::SameCarrierFirst,
+                },
+            },
+            retries: RetryConfig {
+                jobs: RetryConfig::policy_from_env("JOB_RETRY", 4, 1000, 64_000, ""),
+                fcm: RetryConfig::policy_from_env(
+                    "FCM_RETRY",
+                    3,
+                    200,
+                    2000,
+                    "rejected:1:0,throttled:3:1000",
+                ),
+            },
+            quiet_hours: QuietHoursConfig {
+                enabled: std::env::var("QUIET_HOURS_ENABLED")
+                    .map(|v| v == "true" || v == "1")
+                    .unwrap_or(false),
+                start_hour: std::env::var("QUIET_HOURS_START_HOUR")
+                    .unwrap_or_else(|_| "22".to_string())
+                    .parse::<u32>()
+                    .unwrap_or(22)
+                    .min(23),
+                end_hour: std::env::var("QUIET_HOURS_END_HOUR")
+                    .unwrap_or_else(|_| "7".to_string())
+                    .parse::<u32>()
+                    .unwrap_or(7)
+                    .min(23),
+                utc_offset_hours: std::env::var("QUIET_HOURS_UTC_OFFSET_HOURS")
+                    .unwrap_or_else(|_| "7".to_string())
+                    .parse()
+                    .unwrap_or(7),
+            },
+            downloads: DownloadConfig {
+                link_secret: std::env::var("DOWNLOAD_LINK_SECRET")
+                    .ok()
+                    .filter(|secret| !secret.is_empty())
+                    .or_else(|| std::env::var("JWT_SECRET").ok())
+                    .unwrap_or_default(),
+                link_ttl_seconds: std::env::var("DOWNLOAD_LINK_TTL_SECONDS")
+                    .unwrap_or_else(|_| "900".to_string())
+                    .parse()
+                    .unwrap_or(900),
+            },
+            quality: QualityConfig {
+                apdex_target_seconds: std::env::var("QUALITY_APDEX_TARGET_SECONDS")
+                    .unwrap_or_else(|_| "30".to_string())
+                    .parse()
+                    .unwrap_or(30),
+                alert_webhook_url: std::env::var("QUALITY_ALERT_WEBHOOK_URL")
+                    .ok()
+                    .filter(|url| !url.is_empty()),
+            },
+            earnings: EarningsConfig {
+                off_peak_multiplier: std::env::var("OFF_PEAK_EARNINGS_MULTIPLIER")
+                    .unwrap_or_else(|_| "1.3".to_string())
+                    .parse()
+                    .unwrap_or(1.3),
+                off_peak_start_hour: std::env::var("OFF_PEAK_START_HOUR")
+                    .unwrap_or_else(|_| "1".to_string())
+                    .parse()
+                    .unwrap_or(1),
+                off_peak_end_hour: std::env::var("OFF_PEAK_END_HOUR")
+                    .unwrap_or_else(|_| "6".to_string())
+                    .parse()
+                    .unwrap_or(6),
+                utc_offset_hours: std::env::var("EARNINGS_UTC_OFFSET_HOURS")
+                    .unwrap_or_else(|_| "7".to_string())
+                    .parse()
+                    .unwrap_or(7),
+                // "500:10,2000:15": +10% from 500 deliveries a month, +15% from 2000
+                volume_bonus_tiers: {
+                    let mut tiers: Vec<VolumeBonusTier> = std::env::var("VOLUME_BONUS_TIERS")
+                        .unwrap_or_else(|_| "500:10,2000:15".to_string())
+                        .split(',')
+                        .filter_map(|entry| entry.split_once(':'))
+                        .filter_map(|(deliveries, percent)| {
+                            Some(VolumeBonusTier {
+                                min_deliveries: deliveries.trim().parse().ok()?,
+                                bonus_percent: percent.trim().parse().ok()?,
+                            })
+                        })
+                        .collect();
+                    tiers.sort_by_key(|tier| tier.min_deliveries);
+                    tiers
+                },
+                quality_bonus: QualityBonusConfig {
+                    percent_per_target: std::env::var("QUALITY_BONUS_PERCENT_PER_TARGET")
+                        .unwrap_or_else(|_| "2".to_string())
+                        .parse()
+                        .unwrap_or(2.0),
+                    min_deliveries: std::env::var("QUALITY_BONUS_MIN_DELIVERIES")
+                        .unwrap_or_else(|_| "50".to_string())
+                        .parse()
+                        .unwrap_or(50),
+                    success_rate_target: std::env::var("QUALITY_BONUS_SUCCESS_RATE")
+                        .unwrap_or_else(|_| "95".to_string())
+                        .parse()
+                        .unwrap_or(95.0),
+                    confirmation_seconds_target: std::env::var(
+                        "QUALITY_BONUS_CONFIRMATION_SECONDS",
+                    )
+                    .unwrap_or_else(|_| "60".to_string())
+                    .parse()
+                    .unwrap_or(60.0),
+                    dispute_rate_target: std::env::var("QUALITY_BONUS_MAX_DISPUTE_RATE")
+                        .unwrap_or_else(|_| "1".to_string())
+                        .parse()
+                        .unwrap_or(1.0),
+                },
+            },
+            reconciliation: ReconciliationConfig {
+                auto_correct: std::env::var("RECONCILIATION_AUTO_CORRECT")
+                    .map(|v| v == "true" || v == "1")
+                    .unwrap_or(false),
+                count_tolerance: std::env::var("RECONCILIATION_COUNT_TOLERANCE")
+                    .unwrap_or_else(|_| "5".to_string())
+                    .parse()
+                    .unwrap_or(5),
+                amount_tolerance_ppt: std::env::var("RECONCILIATION_AMOUNT_TOLERANCE_PPT")
+                    .unwrap_or_else(|_| "0.05".to_string())
+                    .parse()
+                    .unwrap_or(0.05),
+            },
+            backups: BackupConfig {
+                endpoint: std::env::var("BACKUP_S3_ENDPOINT")
+                    .unwrap_or_else(|_| "https://s3.amazonaws.com".to_string()),
+                bucket: std::env::var("BACKUP_S3_BUCKET").unwrap_or_default(),
+                region: std::env::var("BACKUP_S3_REGION")
+                    .unwrap_or_else(|_| "ap-southeast-1".to_string()),
+                access_key: std::env::var("BACKUP_S3_ACCESS_KEY").unwrap_or_default(),
+                secret_key: std::env::var("BACKUP_S3_SECRET_KEY").unwrap_or_default(),
+                kms_key_id: std::env::var("BACKUP_S3_KMS_KEY_ID").ok(),
+                prefix: std::env::var("BACKUP_S3_PREFIX").unwrap_or_else(|_| "backups".to_string()),
+            },
+            exchange_rates: ExchangeRateConfig {
+                ppt_khr: std::env::var("PPT_KHR_RATE")
+                    .unwrap_or_else(|_| "4100".to_string())
+                    .parse()
+                    .unwrap_or(4100.0),
+                ppt_usd: std::env::var("PPT_USD_RATE")
+                    .unwrap_or_else(|_| "1.0".to_string())
+                    .parse()
+                    .unwrap_or(1.0),
+                source_url: std::env::var("EXCHANGE_RATE_SOURCE_URL").unwrap_or_default(),
+                source_timeout_ms: std::env::var("EXCHANGE_RATE_SOURCE_TIMEOUT_MS")
+                    .unwrap_or_else(|_| "5000".to_string())
+                    .parse()
+                    .unwrap_or(5000),
+                cache_ttl_seconds: std::env::var("EXCHANGE_RATE_CACHE_TTL_SECONDS")
+                    .unwrap_or_else(|_| "300".to_string())
+                    .parse()
+                    .unwrap_or(300),
+            },
+            payouts: PayoutConfig {
+                batch_interval_seconds: std::env::var("PAYOUT_BATCH_INTERVAL_SECONDS")
+                    .unwrap_or_else(|_| "900".to_string())
+                    .parse()
+                    .unwrap_or(900),
+                batch_size: std::env::var("PAYOUT_BATCH_SIZE")
+                    .unwrap_or_else(|_| "50".to_string())
+                    .parse()
+                    .unwrap_or(50),
+                max_attempts: std::env::var("PAYOUT_MAX_ATTEMPTS")
+                    .unwrap_or_else(|_| "3".to_string())
+                    .parse()
+                    .unwrap_or(3),
+                auto_approve: std::env::var("PAYOUT_AUTO_APPROVE")
+                    .map(|v| v == "true" || v == "1")
+                    .unwrap_or(true),
+                min_payout_ppt: std::env::var("PAYOUT_MIN_PPT")
+                    .unwrap_or_else(|_| "1".to_string())
+                    .parse()
+                    .unwrap_or(1.0),
+                daily_cap_ppt: std::env::var("PAYOUT_DAILY_CAP_PPT")
+                    .unwrap_or_else(|_| "500".to_string())
+                    .parse()
+                    .unwrap_or(500.0),
+                velocity_max_requests: std::env::var("PAYOUT_VELOCITY_MAX_REQUESTS")
+                    .unwrap_or_else(|_| "3".to_string())
+                    .parse()
+                    .unwrap_or(3),
+                velocity_window_minutes: std::env::var("PAYOUT_VELOCITY_WINDOW_MINUTES")
+                    .unwrap_or_else(|_| "60".to_string())
+                    .parse()
+                    .unwrap_or(60),
+                review_threshold_ppt: std::env::var("PAYOUT_REVIEW_THRESHOLD_PPT")
+                    .unwrap_or_else(|_| "100".to_string())
+                    .parse()
+                    .unwrap_or(100.0),
+                new_provider_hold_days: std::env::var("PAYOUT_NEW_PROVIDER_HOLD_DAYS")
+                    .unwrap_or_else(|_| "7".to_string())
+                    .parse()
+                    .unwrap_or(7),
+            },
+            privacy: PrivacyConfig {
+                recipient_encryption_key: std::env::var("RECIPIENT_ENCRYPTION_KEY").ok(),
+                recipient_hash_salt: std::env::var("RECIPIENT_HASH_SALT").unwrap_or_default(),
+                phone_encryption_key: std::env::var("PHONE_ENCRYPTION_KEY")
+                    .ok()
+                    .filter(|key| !key.is_empty()),
+            },
+            rate_limits: RateLimitConfig {
+                enabled: std::env::var("RATE_LIMIT_ENABLED")
+                    .map(|v| v == "true" || v == "1")
+                    .unwrap_or(true),
+                default_per_minute: std::env::var("RATE_LIMIT_DEFAULT_PER_MINUTE")
+                    .unwrap_or_else(|_| "120".to_string())
+                    .parse()
+                    .unwrap_or(120),
+                send_otp_per_minute: std::env::var("RATE_LIMIT_SEND_OTP_PER_MINUTE")
+                    .unwrap_or_else(|_| "5".to_string())
+                    .parse()
+                    .unwrap_or(5),
+                verify_otp_per_minute: std::env::var("RATE_LIMIT_VERIFY_OTP_PER_MINUTE")
+                    .unwrap_or_else(|_| "10".to_string())
+                    .parse()
+                    .unwrap_or(10),
+                send_message_per_minute: std::env::var("RATE_LIMIT_SEND_MESSAGE_PER_MINUTE")
+                    .unwrap_or_else(|_| "60".to_string())
+                    .parse()
+                    .unwrap_or(60),
+                admin_per_minute: std::env::var("RATE_LIMIT_ADMIN_PER_MINUTE")
+                    .unwrap_or_else(|_| "30".to_string())
+                    .parse()
+                    .unwrap_or(30),
+            },
+            number_pool: NumberPoolConfig {
+                default_monthly_rent: std::env::var("NUMBER_RENTAL_MONTHLY_FEE")
+                    .unwrap_or_else(|_| "20.0".to_string())
+                    .parse()
+                    .unwrap_or(20.0),
+                provider_rent_share: std::env::var("NUMBER_RENTAL_PROVIDER_SHARE")
+                    .unwrap_or_else(|_| "0.7".to_string())
+                    .parse()
+                    .unwrap_or(0.7),
+            },
+            pricing: PricingConfig {
+                base_message_cost: std::env::var("MESSAGE_BASE_COST_PPT")
+                    .unwrap_or_else(|_| "0.01".to_string())
+                    .parse()
+                    .unwrap_or(0.01),
+                platform_fee_percent: std::env::var("PLATFORM_FEE_PERCENT")
+                    .unwrap_or_else(|_| "20".to_string())
+                    .parse()
+                    .unwrap_or(20.0),
+                // "otp:10,urgent:25"
+                fee_percent_overrides: std::env::var("PLATFORM_FEE_OVERRIDES")
+                    .unwrap_or_default()
+                    .split(',')
+                    .filter_map(|entry| entry.split_once(':'))
+                    .filter_map(|(key, percent)| {
+                        Some((key.trim().to_lowercase(), percent.trim().parse().ok()?))
+                    })
+                    .collect(),
+            },
+            surge_pricing: SurgePricingConfig {
+                enabled: std::env::var("SURGE_PRICING_ENABLED")
+                    .map(|v| v == "true" || v == "1")
+                    .unwrap_or(false),
+                min_multiplier: std::env::var("SURGE_MIN_MULTIPLIER")
+                    .unwrap_or_else(|_| "1.0".to_string())
+                    .parse()
+                    .unwrap_or(1.0),
+                max_multiplier: std::env::var("SURGE_MAX_MULTIPLIER")
+                    .unwrap_or_else(|_| "2.0".to_string())
+                    .parse()
+                    .unwrap_or(2.0),
+                target_queue_per_provider: std::env::var("SURGE_TARGET_QUEUE_PER_PROVIDER")
+                    .unwrap_or_else(|_| "10".to_string())
+                    .parse()
+                    .unwrap_or(10.0),
+                recalc_interval_seconds: std::env::var("SURGE_RECALC_INTERVAL_SECONDS")
+                    .unwrap_or_else(|_| "60".to_string())
+                    .parse()
+                    .unwrap_or(60),
+            },
+            referrals: ReferralConfig {
+                required_deliveries: std::env::var("REFERRAL_REQUIRED_DELIVERIES")
+                    .unwrap_or_else(|_| "10".to_string())
+                    .parse()
+                    .unwrap_or(10),
+                referrer_bonus: std::env::var("REFERRAL_REFERRER_BONUS_PPT")
+                    .unwrap_or_else(|_| "5".to_string())
+                    .parse()
+                    .unwrap_or(5.0),
+                referee_bonus: std::env::var("REFERRAL_REFEREE_BONUS_PPT")
+                    .unwrap_or_else(|_| "2".to_string())
+                    .parse()
+                    .unwrap_or(2.0),
+            },
+            staking: StakingConfig {
+                // "100:200,500:500": 200 messages a day from 100 PPT staked, 500 from 500
+                tiers: {
+                    let mut tiers: Vec<StakeTier> = std::env::var("STAKING_TIERS")
+                        .unwrap_or_else(|_| "100:200,500:500".to_string())
+                        .split(',')
+                        .filter_map(|entry| entry.split_once(':'))
+                        .filter_map(|(stake, limit)| {
+                            Some(StakeTier {
+                                min_stake: stake.trim().parse().ok()?,
+                                max_daily_messages: limit.trim().parse().ok()?,
+                            })
+                        })
+                        .collect();
+                    tiers.sort_by(|a, b| a.min_stake.total_cmp(&b.min_stake));
+                    tiers
+                },
+                priority_min_stake: std::env::var("STAKING_PRIORITY_MIN_PPT")
+                    .unwrap_or_else(|_| "500".to_string())
+                    .parse()
+                    .unwrap_or(500.0),
+                slash_percent: std::env::var("STAKING_SLASH_PERCENT")
+                    .unwrap_or_else(|_| "10".to_string())
+                    .parse::<f64>()
+                    .unwrap_or(10.0)
+                    .clamp(0.0, 100.0),
+            },
+            load_shedding: LoadSheddingConfig {
+                enabled: std::env::var("LOAD_SHED_ENABLED")
+                    .map(|v| v == "true" || v == "1")
+                    .unwrap_or(true),
+                sample_interval_seconds: std::env::var("LOAD_SHED_SAMPLE_INTERVAL_SECONDS")
+                    .unwrap_or_else(|_| "5".to_string())
+                    .parse()
+                    .unwrap_or(5),
+                degraded_latency_ms: std::env::var("LOAD_SHED_DEGRADED_LATENCY_MS")
+                    .unwrap_or_else(|_| "250".to_string())
+                    .parse()
+                    .unwrap_or(250),
+                critical_latency_ms: std::env::var("LOAD_SHED_CRITICAL_LATENCY_MS")
+                    .unwrap_or_else(|_| "1000".to_string())
+                    .parse()
+                    .unwrap_or(1000),
+                degraded_queue_depth: std::env::var("LOAD_SHED_DEGRADED_QUEUE_DEPTH")
+                    .unwrap_or_else(|_| "5000".to_string())
+                    .parse()
+                    .unwrap_or(5000),
+                critical_queue_depth: std::env::var("LOAD_SHED_CRITICAL_QUEUE_DEPTH")
+                    .unwrap_or_else(|_| "20000".to_string())
+                    .parse()
+                    .unwrap_or(20000),
+                degraded_pool_saturation: std::env::var("LOAD_SHED_DEGRADED_POOL_SATURATION")
+                    .unwrap_or_else(|_| "0.8".to_string())
+                    .parse()
+                    .unwrap_or(0.8),
+                critical_pool_saturation: std::env::var("LOAD_SHED_CRITICAL_POOL_SATURATION")
+                    .unwrap_or_else(|_| "0.95".to_string())
+                    .parse()
+                    .unwrap_or(0.95),
+            },
+            backpressure: BackpressureConfig {
+                enabled: std::env::var("BACKPRESSURE_ENABLED")
+                    .map(|v| v == "true" || v == "1")
+                    .unwrap_or(true),
+                sample_interval_seconds: std::env::var("BACKPRESSURE_SAMPLE_INTERVAL_SECONDS")
+                    .unwrap_or_else(|_| "5".to_string())
+                    .parse()
+                    .unwrap_or(5),
+                max_queue_depth: std::env::var("BACKPRESSURE_MAX_QUEUE_DEPTH")
+                    .unwrap_or_else(|_| "10000".to_string())
+                    .parse()
+                    .unwrap_or(10000),
+                max_pending_age_seconds: std::env::var("BACKPRESSURE_MAX_PENDING_AGE_SECONDS")
+                    .unwrap_or_else(|_| "600".to_string())
+                    .parse()
+                    .unwrap_or(600),
+                shed_priorities: std::env::var("BACKPRESSURE_SHED_PRIORITIES")
+                    .unwrap_or_else(|_| "low".to_string())
+                    .split(',')
+                    .map(|p| p.trim().to_lowercase())
+                    .filter(|p| !p.is_empty())
+                    .collect(),
+                mode: match std::env::var("BACKPRESSURE_MODE").as_deref() {
+                    Ok("defer") => BackpressureMode::Defer,
+                    _ => BackpressureMode::Reject,
+                },
+                retry_after_seconds: std::env::var("BACKPRESSURE_RETRY_AFTER_SECONDS")
+                    .unwrap_or_else(|_| "60".to_string())
+                    .parse()
+                    .unwrap_or(60),
+            },
+            sla: SlaConfig {
+                enabled: std::env::var("SLA_MONITOR_ENABLED")
+                    .map(|v| v == "true" || v == "1")
+                    .unwrap_or(true),
+                check_interval_seconds: std::env::var("SLA_CHECK_INTERVAL_SECONDS")
+                    .unwrap_or_else(|_| "30".to_string())
+                    .parse::<u64>()
+                    .unwrap_or(30)
+                    .max(5),
+                urgent_seconds: std::env::var("SLA_URGENT_SECONDS")
+                    .unwrap_or_else(|_| "120".to_string())
+                    .parse()
+                    .unwrap_or(120),
+                high_seconds: std::env::var("SLA_HIGH_SECONDS")
+                    .unwrap_or_else(|_| "300".to_string())
+                    .parse()
+                    .unwrap_or(300),
+                normal_seconds: std::env::var("SLA_NORMAL_SECONDS")
+                    .unwrap_or_else(|_| "900".to_string())
+                    .parse()
+                    .unwrap_or(900),
+                low_seconds: std::env::var("SLA_LOW_SECONDS")
+                    .unwrap_or_else(|_| "3600".to_string())
+                    .parse()
+                    .unwrap_or(3600),
+                webhook_secret: std::env::var("SLA_WEBHOOK_SECRET").unwrap_or_default(),
+            },
+            retention: RetentionConfig {
+                enabled: std::env::var("RETENTION_ENABLED")
+                    .map(|v| v == "true" || v == "1")
+                    .unwrap_or(true),
+                dry_run: std::env::var("RETENTION_DRY_RUN")
+                    .map(|v| v == "true" || v == "1")
+                    .unwrap_or(false),
+                interval_hours: std::env::var("RETENTION_INTERVAL_HOURS")
+                    .unwrap_or_else(|_| "24".to_string())
+                    .parse::<u64>()
+                    .unwrap_or(24)
+                    .max(1),
+                message_days: std::env::var("RETENTION_MESSAGE_DAYS")
+                    .unwrap_or_else(|_| "90".to_string())
+                    .parse()
+                    .unwrap_or(90),
+                job_days: std::env::var("RETENTION_JOB_DAYS")
+                    .unwrap_or_else(|_| "30".to_string())
+                    .parse()
+                    .unwrap_or(30),
+                auth_event_days: std::env::var("RETENTION_AUTH_EVENT_DAYS")
+                    .unwrap_or_else(|_| "365".to_string())
+                    .parse()
+                    .unwrap_or(365),
+                deleted_days: std::env::var("RETENTION_DELETED_DAYS")
+                    .unwrap_or_else(|_| "30".to_string())
+                    .parse()
+                    .unwrap_or(30),
+            },
+            change_streams: ChangeStreamConfig {
+                enabled: std::env::var("CHANGE_STREAMS_ENABLED")
+                    .map(|v| v == "true" || v == "1")
+                    .unwrap_or(true),
+                status_webhooks: std::env::var("STATUS_WEBHOOKS_ENABLED")
+                    .map(|v| v == "true" || v == "1")
+                    .unwrap_or(true),
+            },
+            cors: CorsConfig {
+                allowed_origins: std::env::var("CORS_ALLOWED_ORIGINS")
+                    .unwrap_or_default()
+                    .split(',')
+                    .map(|origin| origin.trim().to_string())
+                    .filter(|origin| !origin.is_empty())
+                    .collect(),
+                allowed_methods: std::env::var("CORS_ALLOWED_METHODS")
+                    .unwrap_or_else(|_| "GET,POST,PUT,PATCH,DELETE,OPTIONS".to_string())
+                    .split(',')
+                    .map(|method| method.trim().to_uppercase())
+                    .filter(|method| !method.is_empty())
+                    .collect(),
+                allowed_headers: std::env::var("CORS_ALLOWED_HEADERS")
+                    .unwrap_or_else(|_| {
+                        "authorization,content-type,accept,x-request-id".to_string()
+                    })
+                    .split(',')
+                    .map(|header| header.trim().to_lowercase())
+                    .filter(|header| !header.is_empty())
+                    .collect(),
+                allow_credentials: std::env::var("CORS_ALLOW_CREDENTIALS")
+                    .map(|v| v == "true" || v == "1")
+                    .unwrap_or(false),
+                max_age_seconds: std::env::var("CORS_MAX_AGE_SECONDS")
+                    .unwrap_or_else(|_| "600".to_string())
+                    .parse()
+                    .unwrap_or(600),
+            },
+            lockout: LockoutConfig {
+                enabled: std::env::var("OTP_LOCKOUT_ENABLED")
+                    .map(|v| v == "true" || v == "1")
+                    .unwrap_or(true),
+                phone_max_failures: std::env::var("OTP_LOCKOUT_PHONE_MAX_FAILURES")
+                    .unwrap_or_else(|_| "5".to_string())
+                    .parse()
+                    .unwrap_or(5),
+                ip_max_failures: std::env::var("OTP_LOCKOUT_IP_MAX_FAILURES")
+                    .unwrap_or_else(|_| "20".to_string())
+                    .parse()
+                    .unwrap_or(20),
+                failure_window_seconds: std::env::var("OTP_LOCKOUT_WINDOW_SECONDS")
+                    .unwrap_or_else(|_| "900".to_string())
+                    .parse()
+                    .unwrap_or(900),
+                base_cooldown_seconds: std::env::var("OTP_LOCKOUT_BASE_COOLDOWN_SECONDS")
+                    .unwrap_or_else(|_| "300".to_string())
+                    .parse()
+                    .unwrap_or(300),
+                max_cooldown_seconds: std::env::var("OTP_LOCKOUT_MAX_COOLDOWN_SECONDS")
+                    .unwrap_or_else(|_| "86400".to_string())
+                    .parse()
+                    .unwrap_or(86400),
+                level_memory_seconds: std::env::var("OTP_LOCKOUT_LEVEL_MEMORY_SECONDS")
+                    .unwrap_or_else(|_| "86400".to_string())
+                    .parse()
+                    .unwrap_or(86400),
+            },
+            otp_challenge: OtpChallengeConfig {
+                enabled: std::env::var("OTP_CHALLENGE_ENABLED")
+                    .map(|v| v == "true" || v == "1")
+                    .unwrap_or(true),
+                difficulty_bits: std::env::var("OTP_CHALLENGE_DIFFICULTY_BITS")
+                    .unwrap_or_else(|_| "18".to_string())
+                    .parse()
+                    .unwrap_or(18),
+                ttl_seconds: std::env::var("OTP_CHALLENGE_TTL_SECONDS")
+                    .unwrap_or_else(|_| "120".to_string())
+                    .parse()
+                    .unwrap_or(120),
+                trusted_ips: std::env::var("OTP_CHALLENGE_TRUSTED_IPS")
+                    .unwrap_or_default()
+                    .split(',')
+                    .map(|ip| ip.trim().to_string())
+                    .filter(|ip| !ip.is_empty())
+                    .collect(),
+                trust_days: std::env::var("OTP_CHALLENGE_TRUST_DAYS")
+                    .unwrap_or_else(|_| "30".to_string())
+                    .parse()
+                    .unwrap_or(30),
+            },
+            admin: AdminConfig {
+                user_ids: std::env::var("ADMIN_USER_IDS")
+                    .unwrap_or_default()
+                    .split(',')
+                    .map(|id| id.trim().to_string())
+                    .filter(|id| !id.is_empty())
+                    .collect(),
+                audit_reads: std::env::var("ADMIN_AUDIT_READS")
+                    .map(|v| v == "true" || v == "1")
+                    .unwrap_or(true),
+            },
+            identity: IdentityConfig {
+                issuer_key: std::env::var("DID_ISSUER_KEY")
+                    .ok()
+                    .filter(|key| !key.is_empty()),
+                credential_ttl_days: std::env::var("CREDENTIAL_TTL_DAYS")
+                    .unwrap_or_else(|_| "365".to_string())
+                    .parse()
+                    .unwrap_or(365),
+                did_proof_max_age_seconds: std::env::var("DID_PROOF_MAX_AGE_SECONDS")
+                    .unwrap_or_else(|_| "300".to_string())
+                    .parse()
+                    .unwrap_or(300),
             },
         };
 
+        // Development stays open to any origin unless origins are listed explicitly;
+        // staging and production allow none until configured
+        if config.cors.allowed_origins.is_empty() && config.is_development() {
+            config.cors.allowed_origins = vec!["*".to_string()];
+        }
+
+        // Download links signed with an empty key could be forged by anyone
+        if config.downloads.link_secret.is_empty() {
+            if config.is_production() {
+                return Err(PeerPowerError::Configuration {
+                    message: "DOWNLOAD_LINK_SECRET (or JWT_SECRET) is required in production"
+                        .to_string(),
+                });
+            }
+            tracing::warn!(
+                "DOWNLOAD_LINK_SECRET not set; signing download links with an ephemeral key"
+            );
+            config.downloads.link_secret = hex::encode(rand::random::<[u8; 32]>());
+        }
+
+        if config.auth.jwt_issuer.is_empty() {
+            config.auth.jwt_issuer = format!("peerpower-{}", config.server.environment.as_str());
+        }
+
         Ok(config)
     }
 
@@ -0,0 +1,73 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Scopes a machine client may be granted
+pub const API_CLIENT_SCOPES: &[&str] = &["messages:send", "messages:read", "analytics:read"];
+
+/// OAuth2 machine client using the `client_credentials` grant.
+///
+/// Tokens issued to a client act on behalf of its owner, limited to the
+/// scopes granted here. Only an Argon2 hash of the secret is stored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiClient {
+    pub client_id: String,
+    pub name: String,
+    pub owner_user_id: String,
+    pub secret_hash: String,
+    pub scopes: Vec<String>,
+    pub token_lifetime_seconds: Option<i64>, // falls back to the configured default
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl ApiClient {
+    pub fn new(
+        owner_user_id: String,
+        name: String,
+        secret_hash: String,
+        scopes: Vec<String>,
+        token_lifetime_seconds: Option<i64>,
+    ) -> Self {
+        Self {
+            client_id: format!(
+                "ppc_{}",
+                crate::shared::utils::generate_id().replace('-', "")
+            ),
+            name,
+            owner_user_id,
+            secret_hash,
+            scopes,
+            token_lifetime_seconds,
+            created_at: crate::shared::utils::now(),
+            last_used_at: None,
+            revoked_at: None,
+        }
+    }
+
+    pub fn is_revoked(&self) -> bool {
+        self.revoked_at.is_some()
+    }
+
+    /// Resolve a requested space-delimited scope against the granted ones.
+    ///
+    /// No requested scope means everything granted; asking for anything not
+    /// granted returns `None`.
+    pub fn grant_scopes(&self, requested: Option<&str>) -> Option<Vec<String>> {
+        match requested.map(str::trim).filter(|scope| !scope.is_empty()) {
+            None => Some(self.scopes.clone()),
+            Some(requested) => {
+                let mut granted: Vec<String> = Vec::new();
+                for scope in requested.split_whitespace() {
+                    if !self.scopes.iter().any(|allowed| allowed == scope) {
+                        return None;
+                    }
+                    if !granted.iter().any(|existing| existing == scope) {
+                        granted.push(scope.to_string());
+                    }
+                }
+                Some(granted)
+            }
+        }
+    }
+}
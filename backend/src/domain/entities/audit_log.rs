@@ -0,0 +1,48 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditLogEntry {
+    pub id: String,
+    pub actor_id: Option<String>,
+    pub action: String,
+    pub resource_type: String,
+    pub resource_id: String,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub metadata: HashMap<String, String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl AuditLogEntry {
+    pub fn new(
+        actor_id: Option<String>,
+        action: &str,
+        resource_type: &str,
+        resource_id: &str,
+    ) -> Self {
+        Self {
+            id: crate::shared::utils::generate_id(),
+            actor_id,
+            action: action.to_string(),
+            resource_type: resource_type.to_string(),
+            resource_id: resource_id.to_string(),
+            ip_address: None,
+            user_agent: None,
+            metadata: HashMap::new(),
+            created_at: crate::shared::utils::now(),
+        }
+    }
+
+    pub fn with_client(mut self, ip_address: Option<String>, user_agent: Option<String>) -> Self {
+        self.ip_address = ip_address;
+        self.user_agent = user_agent;
+        self
+    }
+
+    pub fn with_metadata(mut self, key: &str, value: impl Into<String>) -> Self {
+        self.metadata.insert(key.to_string(), value.into());
+        self
+    }
+}
@@ -0,0 +1,68 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A background backup or restore of critical collections
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupRun {
+    pub id: String,
+    pub kind: BackupKind,
+    pub status: BackupStatus,
+    pub requested_by: String,
+    pub source_backup_id: Option<String>, // restores only
+    pub collections: Vec<BackupCollection>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BackupKind {
+    Backup,
+    Restore,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BackupStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+}
+
+/// One collection's snapshot in object storage
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupCollection {
+    pub name: String,
+    pub object_key: String,
+    pub documents: u64,
+    pub sha256: String,
+}
+
+/// What restoring a collection from a backup would change
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RestoreDiff {
+    pub collection: String,
+    pub in_backup: u64,
+    pub to_insert: u64,
+    pub to_update: u64,
+    pub unchanged: u64,
+    pub only_in_database: u64, // left untouched by restore
+}
+
+impl BackupRun {
+    pub fn new(kind: BackupKind, requested_by: String, source_backup_id: Option<String>) -> Self {
+        Self {
+            id: crate::shared::utils::generate_id(),
+            kind,
+            status: BackupStatus::Pending,
+            requested_by,
+            source_backup_id,
+            collections: Vec::new(),
+            error: None,
+            created_at: crate::shared::utils::now(),
+            started_at: None,
+            completed_at: None,
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A provider's earnings sent to their wallet as PPT tokens on Selendra
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainSettlement {
+    pub id: String,
+    pub batch_id: String,
+    pub provider_id: String,
+    pub wallet_address: String,
+    pub amount: f64, // PPT
+    pub status: ChainSettlementStatus,
+    pub nonce: Option<u64>,
+    pub tx_hash: Option<String>,
+    pub failure_reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub confirmed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChainSettlementStatus {
+    Pending,   // debited from the ledger, not yet broadcast
+    Submitted, // broadcast, waiting for a receipt
+    Confirmed,
+    Failed, // reverted or refused by the node; the PPT is returned to the ledger
+}
+
+impl ChainSettlement {
+    pub fn new(batch_id: String, provider_id: String, wallet_address: String, amount: f64) -> Self {
+        let now = crate::shared::utils::now();
+        Self {
+            id: crate::shared::utils::generate_id(),
+            batch_id,
+            provider_id,
+            wallet_address,
+            amount,
+            status: ChainSettlementStatus::Pending,
+            nonce: None,
+            tx_hash: None,
+            failure_reason: None,
+            created_at: now,
+            updated_at: now,
+            confirmed_at: None,
+        }
+    }
+}
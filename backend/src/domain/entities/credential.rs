@@ -0,0 +1,47 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A verifiable credential the platform issued to a user's DID, kept so it
+/// can be listed and revoked
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssuedCredential {
+    pub id: String, // the credential's `jti`, as `urn:uuid:{id}`
+    pub user_id: String,
+    pub subject_did: String,
+    pub kind: CredentialKind,
+    pub jwt: String, // ES256K-signed VC-JWT
+    pub issued_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CredentialKind {
+    VerifiedPhone,
+    VerifiedProvider,
+}
+
+impl CredentialKind {
+    /// The VC `type` entry alongside `VerifiableCredential`
+    pub fn credential_type(&self) -> &'static str {
+        match self {
+            CredentialKind::VerifiedPhone => "VerifiedPhoneCredential",
+            CredentialKind::VerifiedProvider => "VerifiedProviderCredential",
+        }
+    }
+
+    pub fn from_credential_type(credential_type: &str) -> Option<Self> {
+        match credential_type {
+            "VerifiedPhoneCredential" => Some(CredentialKind::VerifiedPhone),
+            "VerifiedProviderCredential" => Some(CredentialKind::VerifiedProvider),
+            _ => None,
+        }
+    }
+}
+
+impl IssuedCredential {
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.revoked_at.is_none() && self.expires_at > now
+    }
+}
@@ -0,0 +1,46 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{Job, JobAttempt};
+
+/// A job that used up its retries, kept with its payload and attempt
+/// history until an admin requeues or discards it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetterJob {
+    pub id: String,
+    pub job_id: String,
+    pub message_id: String,
+    pub client_id: String,
+    pub final_error: String,
+    pub attempts: Vec<JobAttempt>,
+    pub job: Job, // as it stood when dead-lettered
+    pub status: DeadLetterStatus,
+    pub resolved_by: Option<String>,
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeadLetterStatus {
+    Pending,   // waiting for an admin
+    Requeued,  // put back on the job queue with a fresh retry budget
+    Discarded, // dropped; the message stays failed
+}
+
+impl DeadLetterJob {
+    pub fn new(job: Job, final_error: String) -> Self {
+        Self {
+            id: crate::shared::utils::generate_id(),
+            job_id: job.id.clone(),
+            message_id: job.message_id.clone(),
+            client_id: job.client_id.clone(),
+            final_error,
+            attempts: job.attempts.clone(),
+            job,
+            status: DeadLetterStatus::Pending,
+            resolved_by: None,
+            resolved_at: None,
+            created_at: crate::shared::utils::now(),
+        }
+    }
+}
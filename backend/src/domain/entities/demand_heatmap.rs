@@ -0,0 +1,46 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Daily snapshot of message demand against provider supply per province
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DemandHeatmap {
+    pub id: String,
+    pub date: String, // YYYY-MM-DD
+    pub window_days: i64,
+    pub rows: Vec<HeatmapRow>,
+    pub generated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeatmapRow {
+    pub province: String,
+    pub messages: u64,
+    pub messages_delivered: u64,
+    pub providers_total: u64,
+    pub providers_online: u64,
+    pub demand_per_provider: f64,
+}
+
+pub const UNKNOWN_PROVINCE: &str = "Unknown";
+
+impl HeatmapRow {
+    pub fn new(province: String) -> Self {
+        Self {
+            province,
+            messages: 0,
+            messages_delivered: 0,
+            providers_total: 0,
+            providers_online: 0,
+            demand_per_provider: 0.0,
+        }
+    }
+
+    /// Messages per registered provider; provinces with demand and no supply rank highest
+    pub fn finalize(&mut self) {
+        self.demand_per_provider = if self.providers_total > 0 {
+            self.messages as f64 / self.providers_total as f64
+        } else {
+            self.messages as f64
+        };
+    }
+}
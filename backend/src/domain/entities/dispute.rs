@@ -0,0 +1,56 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::shared::Money;
+
+/// A client's claim that a message confirmed as delivered never arrived,
+/// and how an admin resolved it. One per message.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Dispute {
+    pub id: String,
+    pub message_id: String,
+    pub client_id: String,
+    pub provider_id: Option<String>,
+    pub reason: String,
+    pub status: DisputeStatus,
+    pub refund_amount: Money,   // returned to the client when upheld
+    pub clawback_amount: Money, // provider earnings reversed when upheld
+    pub resolution_note: Option<String>,
+    pub resolved_by: Option<String>,
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DisputeStatus {
+    Open,     // waiting for an admin
+    Upheld,   // the client was refunded and the provider's earnings clawed back
+    Rejected, // the delivery stands
+}
+
+impl Dispute {
+    pub fn new(
+        message_id: String,
+        client_id: String,
+        provider_id: Option<String>,
+        reason: String,
+    ) -> Self {
+        let now = crate::shared::utils::now();
+        Self {
+            id: crate::shared::utils::generate_id(),
+            message_id,
+            client_id,
+            provider_id,
+            reason,
+            status: DisputeStatus::Open,
+            refund_amount: Money::ZERO,
+            clawback_amount: Money::ZERO,
+            resolution_note: None,
+            resolved_by: None,
+            resolved_at: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A short-lived, revocable link to a generated export
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DownloadLink {
+    pub id: String,
+    pub owner_id: String,
+    pub resource: DownloadResource,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub download_count: u32,
+}
+
+/// What a download link resolves to; the export is generated at download time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum DownloadResource {
+    MessageExport {
+        status: Option<String>,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    },
+}
+
+impl DownloadLink {
+    pub fn new(owner_id: String, resource: DownloadResource, ttl_seconds: i64) -> Self {
+        let now = crate::shared::utils::now();
+        Self {
+            id: crate::shared::utils::generate_id(),
+            owner_id,
+            resource,
+            created_at: now,
+            expires_at: now + chrono::Duration::seconds(ttl_seconds),
+            revoked_at: None,
+            download_count: 0,
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        crate::shared::utils::now() > self.expires_at
+    }
+
+    pub fn is_revoked(&self) -> bool {
+        self.revoked_at.is_some()
+    }
+
+    /// Payload covered by the link signature
+    pub fn signing_payload(&self) -> String {
+        format!("{}:{}", self.id, self.expires_at.timestamp())
+    }
+
+    pub fn file_name(&self) -> String {
+        match &self.resource {
+            DownloadResource::MessageExport { .. } => {
+                format!("messages-{}.csv", self.created_at.format("%Y%m%d%H%M%S"))
+            }
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::MessagePriority;
+use crate::shared::Money;
+
+/// What a provider earned for one delivered message, written when the
+/// delivery is confirmed so earnings history is built from real records
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EarningsEvent {
+    pub id: String,
+    pub message_id: String,
+    pub provider_id: String,
+    pub client_id: String,
+    pub amount: Money,         // including bonuses
+    pub base_amount: Money,    // before the off-peak multiplier
+    pub off_peak_bonus: Money,
+    pub priority: MessagePriority,
+    pub date: String, // local (EARNINGS_UTC_OFFSET_HOURS) day, "%Y-%m-%d"
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub confirmation_seconds: Option<f64>, // dispatch to confirmation, when the dispatch time is known
+    #[serde(default)]
+    pub disputed: bool, // the client reported the message never arrived
+    #[serde(default)]
+    pub clawed_back: bool, // reversed by an upheld dispute; no longer earned
+}
+
+impl EarningsEvent {
+    pub fn new(
+        message_id: String,
+        provider_id: String,
+        client_id: String,
+        amount: Money,
+        off_peak_bonus: Money,
+        priority: MessagePriority,
+        date: chrono::NaiveDate,
+    ) -> Self {
+        Self {
+            id: crate::shared::utils::generate_id(),
+            message_id,
+            provider_id,
+            client_id,
+            amount,
+            base_amount: amount - off_peak_bonus,
+            off_peak_bonus,
+            priority,
+            date: date.format("%Y-%m-%d").to_string(),
+            created_at: crate::shared::utils::now(),
+            confirmation_seconds: None,
+            disputed: false,
+            clawed_back: false,
+        }
+    }
+}
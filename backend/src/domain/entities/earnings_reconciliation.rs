@@ -0,0 +1,98 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// One run of the earnings reconciliation: every provider's delivered
+/// messages, earnings events and counters checked against the ledger
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EarningsReconciliation {
+    pub id: String,
+    pub run_date: String, // YYYY-MM-DD (UTC)
+    pub triggered_by: Option<String>, // admin user id; None for the nightly run
+    pub auto_correct: bool,
+    pub providers_checked: u64,
+    pub discrepancies: Vec<EarningsDiscrepancy>,
+    pub corrected_count: u32,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A provider whose records disagree with the ledger. Counts are deliveries,
+/// amounts are PPT; "expected" is always the ledger's side.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EarningsDiscrepancy {
+    pub provider_id: String,
+    pub kind: EarningsDiscrepancyKind,
+    pub expected_count: Option<u64>,
+    pub recorded_count: Option<u64>,
+    pub expected_amount: Option<f64>,
+    pub recorded_amount: Option<f64>,
+    pub details: String,
+    pub corrected: bool, // the provider record was set to the ledger's figure
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EarningsDiscrepancyKind {
+    UnpostedDeliveries, // delivered messages with no delivery posted to the ledger
+    DeliveryCounter,    // the provider's `total_messages_delivered` drifted
+    EarningsEvents,     // per-delivery earnings events disagree with the postings
+    OpeningBalance,     // `earnings_total` differs from its opening balance posting
+}
+
+impl EarningsReconciliation {
+    pub fn new(triggered_by: Option<String>, auto_correct: bool) -> Self {
+        let now = crate::shared::utils::now();
+        Self {
+            id: crate::shared::utils::generate_id(),
+            run_date: now.format("%Y-%m-%d").to_string(),
+            triggered_by,
+            auto_correct,
+            providers_checked: 0,
+            discrepancies: Vec::new(),
+            corrected_count: 0,
+            created_at: now,
+        }
+    }
+
+    pub fn discrepancy_count(&self) -> usize {
+        self.discrepancies.len()
+    }
+}
+
+impl EarningsDiscrepancy {
+    pub fn counts(
+        provider_id: &str,
+        kind: EarningsDiscrepancyKind,
+        expected: u64,
+        recorded: u64,
+        details: String,
+    ) -> Self {
+        Self {
+            provider_id: provider_id.to_string(),
+            kind,
+            expected_count: Some(expected),
+            recorded_count: Some(recorded),
+            expected_amount: None,
+            recorded_amount: None,
+            details,
+            corrected: false,
+        }
+    }
+
+    pub fn amounts(
+        provider_id: &str,
+        kind: EarningsDiscrepancyKind,
+        expected: f64,
+        recorded: f64,
+        details: String,
+    ) -> Self {
+        Self {
+            provider_id: provider_id.to_string(),
+            kind,
+            expected_count: None,
+            recorded_count: None,
+            expected_amount: Some(expected),
+            recorded_amount: Some(recorded),
+            details,
+            corrected: false,
+        }
+    }
+}
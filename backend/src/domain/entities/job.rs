@@ -1,6 +1,6 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use crate::shared::types::MessageStatus;
+use crate::shared::types::{Carrier, DeploymentCohort, MessageStatus};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Job {
@@ -15,6 +15,65 @@ pub struct Job {
     pub retry_count: u32,
     pub error_message: Option<String>,
     pub fcm_message_id: Option<String>,
+    // Queue ordering, fixed at first enqueue and carried through retries
+    #[serde(default)]
+    pub client_id: String,
+    #[serde(default = "default_priority_score")]
+    pub priority_score: u32,
+    #[serde(default)]
+    pub sequence: u64,
+    #[serde(default)]
+    pub enqueued_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub cohort: DeploymentCohort,
+    #[serde(default)]
+    pub carrier: Option<Carrier>, // recipient carrier, which queue partition it waits in
+    #[serde(default)]
+    pub attempts: Vec<JobAttempt>, // every failed attempt, oldest first
+    // Providers that failed it since it was last (re)queued; retries skip them
+    #[serde(default)]
+    pub attempted_provider_ids: Vec<String>,
+    // The backend instance that last claimed it from the queue
+    #[serde(default)]
+    pub claimed_by: Option<String>,
+    #[serde(default)]
+    pub claimed_at: Option<DateTime<Utc>>,
+    // At-most-once dispatch: a fresh token per attempt, and the attempt that
+    // last got as far as dispatching
+    #[serde(default)]
+    pub attempt_token: String,
+    #[serde(default)]
+    pub dispatch_marker: Option<DispatchMarker>,
+    // Dispatched jobs the device hasn't acknowledged by this time are
+    // assumed lost by FCM and dispatched again
+    #[serde(default)]
+    pub ack_deadline: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub acknowledged_at: Option<DateTime<Utc>>,
+}
+
+/// Written to the job before its FCM dispatch. A job picked up again with
+/// its current attempt already marked was interrupted mid-dispatch, and that
+/// attempt is not sent a second time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DispatchMarker {
+    pub attempt_token: String,
+    pub instance_id: String,
+    pub marked_at: DateTime<Utc>,
+}
+
+/// One failed attempt at a job, kept for the dead-letter queue
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobAttempt {
+    pub retry_count: u32,
+    pub provider_id: String, // as assigned when the attempt failed
+    pub error: String,
+    pub failed_at: DateTime<Utc>,
+}
+
+
+fn default_priority_score() -> u32 {
+    50 // MessagePriority::Normal
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -43,12 +102,43 @@ impl Job {
             retry_count: 0,
             error_message: None,
             fcm_message_id: None,
+            client_id: String::new(),
+            priority_score: default_priority_score(),
+            sequence: 0,
+            enqueued_at: None,
+            cohort: DeploymentCohort::Stable,
+            carrier: None,
+            attempts: Vec::new(),
+            attempted_provider_ids: Vec::new(),
+            claimed_by: None,
+            claimed_at: None,
+            attempt_token: crate::shared::utils::generate_id(),
+            dispatch_marker: None,
+            ack_deadline: None,
+            acknowledged_at: None,
         }
     }
 
-    pub fn mark_dispatched(&mut self, fcm_message_id: String) {
+    /// The marker to record before this attempt is dispatched
+    pub fn dispatch_marker_for(&self, instance_id: &str) -> DispatchMarker {
+        DispatchMarker {
+            attempt_token: self.attempt_token.clone(),
+            instance_id: instance_id.to_string(),
+            marked_at: crate::shared::utils::now(),
+        }
+    }
+
+    /// Handed to FCM; the device has until `ack_deadline` to acknowledge it
+    pub fn mark_dispatched(&mut self, ack_deadline: DateTime<Utc>) {
         self.status = JobStatus::Dispatched;
-        self.fcm_message_id = Some(fcm_message_id);
+        self.ack_deadline = Some(ack_deadline);
+        self.acknowledged_at = None;
+    }
+
+    /// The device received the dispatch and is sending the SMS
+    pub fn acknowledge(&mut self) {
+        self.status = JobStatus::InProgress;
+        self.acknowledged_at = Some(crate::shared::utils::now());
     }
 
     pub fn mark_in_progress(&mut self) {
@@ -61,18 +151,38 @@ impl Job {
         self.completed_at = Some(crate::shared::utils::now());
     }
 
+    pub fn mark_cancelled(&mut self) {
+        self.status = JobStatus::Cancelled;
+        self.completed_at = Some(crate::shared::utils::now());
+    }
+
     pub fn mark_failed(&mut self, error_message: String) {
+        self.record_attempt(&error_message);
         self.status = JobStatus::Failed;
         self.error_message = Some(error_message);
         self.completed_at = Some(crate::shared::utils::now());
     }
 
     pub fn mark_timeout(&mut self) {
+        self.record_attempt("Job execution timeout");
         self.status = JobStatus::Timeout;
         self.error_message = Some("Job execution timeout".to_string());
         self.completed_at = Some(crate::shared::utils::now());
     }
 
+    fn record_attempt(&mut self, error: &str) {
+        self.attempts.push(JobAttempt {
+            retry_count: self.retry_count,
+            provider_id: self.provider_id.clone(),
+            error: error.to_string(),
+            failed_at: crate::shared::utils::now(),
+        });
+        if !self.provider_id.is_empty() && !self.attempted_provider_ids.contains(&self.provider_id)
+        {
+            self.attempted_provider_ids.push(self.provider_id.clone());
+        }
+    }
+
     pub fn is_expired(&self) -> bool {
         crate::shared::utils::now() > self.timeout_at
     }
@@ -81,15 +191,30 @@ impl Job {
         matches!(self.status, JobStatus::Assigned | JobStatus::Dispatched | JobStatus::InProgress)
     }
 
-    pub fn can_retry(&self) -> bool {
-        matches!(self.status, JobStatus::Failed | JobStatus::Timeout) 
-            && self.retry_count < 3
+    /// Whether the failed attempt may be retried, `max_attempts` counting
+    /// the first
+    pub fn can_retry(&self, max_attempts: u32) -> bool {
+        matches!(self.status, JobStatus::Failed | JobStatus::Timeout)
+            && self.retry_count + 1 < max_attempts
     }
 
     pub fn increment_retry(&mut self) {
         self.retry_count += 1;
+        self.attempt_token = crate::shared::utils::generate_id();
+        self.status = JobStatus::Assigned;
+        self.error_message = None;
+        self.timeout_at = crate::shared::utils::now() + chrono::Duration::minutes(10);
+    }
+
+    /// Start over with a fresh retry budget, keeping the attempt history;
+    /// providers that failed it before may be tried again
+    pub fn reset_for_requeue(&mut self) {
+        self.retry_count = 0;
+        self.attempted_provider_ids.clear();
+        self.attempt_token = crate::shared::utils::generate_id();
         self.status = JobStatus::Assigned;
         self.error_message = None;
+        self.completed_at = None;
         self.timeout_at = crate::shared::utils::now() + chrono::Duration::minutes(10);
     }
 }
@@ -0,0 +1,57 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::Job;
+
+/// Seconds the request that stored an entry gets to publish it before the
+/// relay steps in
+const RELAY_GRACE_SECONDS: i64 = 10;
+
+/// Longest wait between relay attempts for one entry
+const MAX_RELAY_BACKOFF_SECONDS: i64 = 300;
+
+/// Intent to put a job on the Redis queue, stored with its message and job
+/// so a failed push is retried instead of leaving the message stranded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboxEntry {
+    pub id: String,
+    pub job_id: String,
+    pub message_id: String,
+    pub attempts: u32, // failed relay attempts
+    pub last_error: Option<String>,
+    pub next_attempt_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl OutboxEntry {
+    pub fn new(job: &Job) -> Self {
+        let now = crate::shared::utils::now();
+        Self {
+            id: crate::shared::utils::generate_id(),
+            job_id: job.id.clone(),
+            message_id: job.message_id.clone(),
+            attempts: 0,
+            last_error: None,
+            next_attempt_at: now + chrono::Duration::seconds(RELAY_GRACE_SECONDS),
+            created_at: now,
+        }
+    }
+
+    /// An entry the relay publishes only after `delay`, for jobs held back
+    /// while the queue is backed up
+    pub fn deferred(job: &Job, delay: chrono::Duration) -> Self {
+        let mut entry = Self::new(job);
+        entry.next_attempt_at = entry.created_at + delay;
+        entry
+    }
+
+    /// Back off exponentially before the relay tries again
+    pub fn record_failure(&mut self, error: String) {
+        self.attempts += 1;
+        self.last_error = Some(error);
+        let backoff = 2_i64
+            .pow(self.attempts.min(9))
+            .min(MAX_RELAY_BACKOFF_SECONDS);
+        self.next_attempt_at = crate::shared::utils::now() + chrono::Duration::seconds(backoff);
+    }
+}
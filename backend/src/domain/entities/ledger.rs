@@ -0,0 +1,453 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::shared::Money;
+
+/// Platform account message and rental fees accrue to
+pub const PLATFORM_FEES_ACCOUNT: &str = "fees";
+/// Platform account for money held at Baray: top-ups in, payouts out
+pub const PLATFORM_BARAY_ACCOUNT: &str = "baray";
+/// Platform account for PPT tokens sent out from the Selendra treasury
+pub const PLATFORM_SELENDRA_ACCOUNT: &str = "selendra";
+/// Platform account referral bonuses are paid from
+pub const PLATFORM_REFERRALS_ACCOUNT: &str = "referrals";
+
+/// A balanced set of PPT postings recorded as one document, so its legs are
+/// written (or not) together. `(kind, reference)` is unique, which makes
+/// posting the same event twice a no-op.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LedgerTransaction {
+    pub id: String,
+    pub kind: LedgerTransactionKind,
+    pub reference: String, // message id, rental charge, top-up id, ...
+    pub postings: Vec<LedgerPosting>,
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub tx_hash: Option<String>, // Selendra transaction that settled it on-chain
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LedgerTransactionKind {
+    MessageDelivery,
+    NumberRental,
+    TopUp,
+    OpeningBalance, // earnings recorded on the provider before the ledger existed
+    Payout,
+    PayoutReversal, // a failed or rejected payout returned to the provider
+    ChainSettlement,
+    ChainSettlementReversal,
+    ReferralBonus,
+    VolumeBonus,
+    QualityBonus,
+    DisputeReversal, // an upheld dispute undoing a message delivery
+}
+
+/// One leg of a transaction. Positive amounts credit the account (PPT the
+/// platform owes its holder), negative amounts debit it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LedgerPosting {
+    pub account_kind: LedgerAccountKind,
+    pub account_id: String, // client user id, provider id, or a platform account
+    pub entry: LedgerEntryKind,
+    pub amount: Money,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum LedgerAccountKind {
+    Client,
+    Provider,
+    Platform,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LedgerEntryKind {
+    ClientCharge,
+    ProviderEarning,
+    PlatformFee,
+    TopUp,
+    OpeningBalance,
+    Payout,
+    PayoutReversal,
+    ChainSettlement,
+    ChainSettlementReversal,
+    ReferralBonus,
+    VolumeBonus,
+    QualityBonus,
+}
+
+impl LedgerPosting {
+    fn new(
+        account_kind: LedgerAccountKind,
+        account_id: &str,
+        entry: LedgerEntryKind,
+        amount: Money,
+    ) -> Self {
+        Self {
+            account_kind,
+            account_id: account_id.to_string(),
+            entry,
+            amount,
+        }
+    }
+}
+
+impl LedgerTransaction {
+    fn new(kind: LedgerTransactionKind, reference: String, postings: Vec<LedgerPosting>) -> Self {
+        Self {
+            id: crate::shared::utils::generate_id(),
+            kind,
+            reference,
+            postings,
+            created_at: crate::shared::utils::now(),
+            tx_hash: None,
+        }
+    }
+
+    /// Client pays `charge`, the provider earns `earnings`, the platform
+    /// keeps the difference (negative when an off-peak boost exceeds it)
+    pub fn message_delivery(
+        message_id: &str,
+        client_id: &str,
+        provider_id: &str,
+        charge: Money,
+        earnings: Money,
+    ) -> Self {
+        Self::new(
+            LedgerTransactionKind::MessageDelivery,
+            message_id.to_string(),
+            Self::split_charge(client_id, provider_id, charge, earnings),
+        )
+    }
+
+    pub fn number_rental(
+        charge_id: &str,
+        client_id: &str,
+        provider_id: &str,
+        rent: Money,
+        provider_earnings: Money,
+    ) -> Self {
+        Self::new(
+            LedgerTransactionKind::NumberRental,
+            charge_id.to_string(),
+            Self::split_charge(client_id, provider_id, rent, provider_earnings),
+        )
+    }
+
+    /// PPT bought through Baray checkout
+    pub fn top_up(topup_id: &str, client_id: &str, credit: Money) -> Self {
+        Self::new(
+            LedgerTransactionKind::TopUp,
+            topup_id.to_string(),
+            vec![
+                LedgerPosting::new(
+                    LedgerAccountKind::Client,
+                    client_id,
+                    LedgerEntryKind::TopUp,
+                    credit,
+                ),
+                LedgerPosting::new(
+                    LedgerAccountKind::Platform,
+                    PLATFORM_BARAY_ACCOUNT,
+                    LedgerEntryKind::TopUp,
+                    -credit,
+                ),
+            ],
+        )
+    }
+
+    /// Carry a provider's pre-ledger `earnings_total` into the ledger
+    pub fn opening_balance(provider_id: &str, amount: Money) -> Self {
+        Self::new(
+            LedgerTransactionKind::OpeningBalance,
+            provider_id.to_string(),
+            vec![
+                LedgerPosting::new(
+                    LedgerAccountKind::Provider,
+                    provider_id,
+                    LedgerEntryKind::OpeningBalance,
+                    amount,
+                ),
+                LedgerPosting::new(
+                    LedgerAccountKind::Platform,
+                    PLATFORM_FEES_ACCOUNT,
+                    LedgerEntryKind::OpeningBalance,
+                    -amount,
+                ),
+            ],
+        )
+    }
+
+    /// PPT withdrawn by a provider, debited when the payout is requested
+    pub fn payout(payout_id: &str, provider_id: &str, amount: Money) -> Self {
+        Self::new(
+            LedgerTransactionKind::Payout,
+            payout_id.to_string(),
+            Self::baray_transfer(provider_id, LedgerEntryKind::Payout, -amount),
+        )
+    }
+
+    /// Return a payout that will not be paid to the provider's balance
+    pub fn payout_reversal(payout_id: &str, provider_id: &str, amount: Money) -> Self {
+        Self::new(
+            LedgerTransactionKind::PayoutReversal,
+            payout_id.to_string(),
+            Self::baray_transfer(provider_id, LedgerEntryKind::PayoutReversal, amount),
+        )
+    }
+
+    /// Bonuses for a referral that paid off, credited to each user's client
+    /// or provider account
+    pub fn referral_bonus(
+        referral_id: &str,
+        referrer: (LedgerAccountKind, &str),
+        referrer_bonus: Money,
+        referee: (LedgerAccountKind, &str),
+        referee_bonus: Money,
+    ) -> Self {
+        Self::new(
+            LedgerTransactionKind::ReferralBonus,
+            referral_id.to_string(),
+            vec![
+                LedgerPosting::new(
+                    referrer.0,
+                    referrer.1,
+                    LedgerEntryKind::ReferralBonus,
+                    referrer_bonus,
+                ),
+                LedgerPosting::new(
+                    referee.0,
+                    referee.1,
+                    LedgerEntryKind::ReferralBonus,
+                    referee_bonus,
+                ),
+                LedgerPosting::new(
+                    LedgerAccountKind::Platform,
+                    PLATFORM_REFERRALS_ACCOUNT,
+                    LedgerEntryKind::ReferralBonus,
+                    -(referrer_bonus + referee_bonus),
+                ),
+            ],
+        )
+    }
+
+    /// A month's volume bonus, paid out of platform fees
+    pub fn volume_bonus(bonus_reference: &str, provider_id: &str, amount: Money) -> Self {
+        Self::new(
+            LedgerTransactionKind::VolumeBonus,
+            bonus_reference.to_string(),
+            Self::fee_funded_bonus(provider_id, LedgerEntryKind::VolumeBonus, amount),
+        )
+    }
+
+    /// A month's quality bonus, paid out of platform fees
+    pub fn quality_bonus(bonus_reference: &str, provider_id: &str, amount: Money) -> Self {
+        Self::new(
+            LedgerTransactionKind::QualityBonus,
+            bonus_reference.to_string(),
+            Self::fee_funded_bonus(provider_id, LedgerEntryKind::QualityBonus, amount),
+        )
+    }
+
+    /// Undo a message delivery for an upheld dispute: the client is refunded
+    /// the charge and the provider's earnings and platform fee are taken
+    /// back. The legs keep their entry kinds so revenue nets out.
+    pub fn dispute_reversal(delivery: &LedgerTransaction) -> Self {
+        Self::new(
+            LedgerTransactionKind::DisputeReversal,
+            delivery.reference.clone(),
+            delivery
+                .postings
+                .iter()
+                .map(|posting| {
+                    LedgerPosting::new(
+                        posting.account_kind,
+                        &posting.account_id,
+                        posting.entry,
+                        -posting.amount,
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    /// Earnings sent to the provider's wallet as PPT tokens
+    pub fn chain_settlement(settlement_id: &str, provider_id: &str, amount: Money) -> Self {
+        Self::new(
+            LedgerTransactionKind::ChainSettlement,
+            settlement_id.to_string(),
+            Self::transfer_out(
+                provider_id,
+                PLATFORM_SELENDRA_ACCOUNT,
+                LedgerEntryKind::ChainSettlement,
+                -amount,
+            ),
+        )
+    }
+
+    pub fn chain_settlement_reversal(
+        settlement_id: &str,
+        provider_id: &str,
+        amount: Money,
+    ) -> Self {
+        Self::new(
+            LedgerTransactionKind::ChainSettlementReversal,
+            settlement_id.to_string(),
+            Self::transfer_out(
+                provider_id,
+                PLATFORM_SELENDRA_ACCOUNT,
+                LedgerEntryKind::ChainSettlementReversal,
+                amount,
+            ),
+        )
+    }
+
+    fn baray_transfer(
+        provider_id: &str,
+        entry: LedgerEntryKind,
+        amount: Money,
+    ) -> Vec<LedgerPosting> {
+        Self::transfer_out(provider_id, PLATFORM_BARAY_ACCOUNT, entry, amount)
+    }
+
+    /// Provider balance moved against a platform account money leaves through
+    fn transfer_out(
+        provider_id: &str,
+        platform_account: &str,
+        entry: LedgerEntryKind,
+        amount: Money,
+    ) -> Vec<LedgerPosting> {
+        vec![
+            LedgerPosting::new(LedgerAccountKind::Provider, provider_id, entry, amount),
+            LedgerPosting::new(
+                LedgerAccountKind::Platform,
+                platform_account,
+                entry,
+                -amount,
+            ),
+        ]
+    }
+
+    /// Provider credited from the fees account, so the bonus shows up as
+    /// negative revenue
+    fn fee_funded_bonus(
+        provider_id: &str,
+        entry: LedgerEntryKind,
+        amount: Money,
+    ) -> Vec<LedgerPosting> {
+        vec![
+            LedgerPosting::new(LedgerAccountKind::Provider, provider_id, entry, amount),
+            LedgerPosting::new(
+                LedgerAccountKind::Platform,
+                PLATFORM_FEES_ACCOUNT,
+                LedgerEntryKind::PlatformFee,
+                -amount,
+            ),
+        ]
+    }
+
+    fn split_charge(
+        client_id: &str,
+        provider_id: &str,
+        charge: Money,
+        earnings: Money,
+    ) -> Vec<LedgerPosting> {
+        vec![
+            LedgerPosting::new(
+                LedgerAccountKind::Client,
+                client_id,
+                LedgerEntryKind::ClientCharge,
+                -charge,
+            ),
+            LedgerPosting::new(
+                LedgerAccountKind::Provider,
+                provider_id,
+                LedgerEntryKind::ProviderEarning,
+                earnings,
+            ),
+            LedgerPosting::new(
+                LedgerAccountKind::Platform,
+                PLATFORM_FEES_ACCOUNT,
+                LedgerEntryKind::PlatformFee,
+                charge - earnings,
+            ),
+        ]
+    }
+
+    /// Postings must sum to exactly zero
+    pub fn is_balanced(&self) -> bool {
+        self.postings
+            .iter()
+            .map(|posting| posting.amount)
+            .sum::<Money>()
+            .is_zero()
+    }
+
+    /// The legs posted to one account
+    pub fn postings_for<'a>(
+        &'a self,
+        account_kind: LedgerAccountKind,
+        account_id: &'a str,
+    ) -> impl Iterator<Item = &'a LedgerPosting> {
+        self.postings.iter().filter(move |posting| {
+            posting.account_kind == account_kind && posting.account_id == account_id
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_delivery_postings_balance() {
+        let transaction =
+            LedgerTransaction::message_delivery(
+                "msg-1",
+                "client-1",
+                "prov-1",
+                Money::from_ppt(0.01),
+                Money::from_ppt(0.008),
+            );
+        assert!(transaction.is_balanced());
+        assert_eq!(transaction.postings.len(), 3);
+
+        let fee: Money = transaction
+            .postings_for(LedgerAccountKind::Platform, PLATFORM_FEES_ACCOUNT)
+            .map(|posting| posting.amount)
+            .sum();
+        assert_eq!(fee, Money::from_ppt(0.002));
+
+        // An off-peak boost past the charge is paid out of platform fees
+        let boosted =
+            LedgerTransaction::message_delivery(
+                "msg-2",
+                "client-1",
+                "prov-1",
+                Money::from_ppt(0.01),
+                Money::from_ppt(0.012),
+            );
+        assert!(boosted.is_balanced());
+
+        let referral = LedgerTransaction::referral_bonus(
+            "ref-1",
+            (LedgerAccountKind::Provider, "prov-1"),
+            Money::from_ppt(5.0),
+            (LedgerAccountKind::Client, "client-2"),
+            Money::from_ppt(2.0),
+        );
+        assert!(referral.is_balanced());
+
+        let reversal = LedgerTransaction::dispute_reversal(&transaction);
+        assert!(reversal.is_balanced());
+        assert_eq!(reversal.reference, "msg-1");
+        let refund: Money = reversal
+            .postings_for(LedgerAccountKind::Client, "client-1")
+            .map(|posting| posting.amount)
+            .sum();
+        assert_eq!(refund, Money::from_ppt(0.01));
+
+        let mut broken = LedgerTransaction::top_up("topup-1", "client-1", Money::from_ppt(5.0));
+        broken.postings[1].amount = Money::from_ppt(-4.0);
+        assert!(!broken.is_balanced());
+    }
+}
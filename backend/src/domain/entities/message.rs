@@ -1,12 +1,15 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use crate::shared::types::{PhoneNumber, Carrier, MessageStatus};
+use crate::domain::errors::{DomainError, DomainResult};
+use crate::shared::types::{PhoneNumber, Carrier, DeploymentCohort, MessageStatus};
+use crate::shared::Money;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
     pub id: String,
     pub client_id: String,
     pub content: String,
+    #[serde(with = "crate::shared::field_encryption::encrypted_phone")]
     pub recipient: PhoneNumber,
     pub recipient_carrier: Carrier,
     pub provider_id: Option<String>,
@@ -18,9 +21,89 @@ pub struct Message {
     pub updated_at: DateTime<Utc>,
     pub scheduled_at: Option<DateTime<Utc>>,
     pub expires_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub tags: Vec<String>, // client-defined labels, normalized
+    #[serde(default)]
+    pub cohort: DeploymentCohort,
+    // Privacy mode: `recipient` holds a masked number, the real one is only
+    // kept encrypted, and the salted hash serves dedup/search
+    #[serde(default)]
+    pub recipient_hash: Option<String>,
+    #[serde(default)]
+    pub recipient_ciphertext: Option<String>,
+    // ETA quoted at submission, kept to check predictions against outcomes
+    #[serde(default)]
+    pub predicted_delivery_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub predicted_delivery_p90_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub dedicated_number_id: Option<String>, // set when sent from one of the client's dedicated numbers
+    #[serde(default)]
+    pub recipient_prefix: Option<String>, // country + area code, for reporting without decrypting
+    #[serde(default)]
+    pub dispatched_at: Option<DateTime<Utc>>, // pushed to the provider's device
+    #[serde(default)]
+    pub dispute: Option<DeliveryDispute>,
+    #[serde(default)]
+    pub segment_cost: Option<Money>, // per segment under the client's pricing plan when sent
+    #[serde(default)]
+    pub surge_multiplier: Option<f64>, // the recipient carrier's surge when sent
+    #[serde(default)]
+    pub cancellation: Option<MessageCancellation>,
+    #[serde(default)]
+    pub ignore_quiet_hours: bool, // the client asked for delivery even in quiet hours
+    #[serde(default)]
+    pub sla_breached_at: Option<DateTime<Utc>>, // found still undelivered past its delivery SLA
+    #[serde(default)]
+    pub deleted_at: Option<DateTime<Utc>>, // soft-deleted; purged after the retention window
 }
 
+/// A cancellation of a message, and for one already pushed to a provider's
+/// device, whether the device confirmed it did not send it
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageCancellation {
+    pub reason: CancellationReason,
+    pub status: CancellationStatus,
+    pub requested_at: DateTime<Utc>,
+    pub resolved_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CancellationReason {
+    Client,  // the client cancelled it
+    Admin,   // an operator cancelled its job
+    Expired, // it expired before being confirmed
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CancellationStatus {
+    Requested,   // sent to the provider's device, waiting for its answer
+    Confirmed,   // never sent: not dispatched yet, or the device dropped it
+    AlreadySent, // the device had sent it; its delivery confirmation stands
+    TimedOut,    // the device never answered; treated as not sent
+}
+
+/// A client's report that a message confirmed as delivered never arrived
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeliveryDispute {
+    pub reason: String,
+    pub disputed_at: DateTime<Utc>,
+    #[serde(default)]
+    pub fraud_confirmed_at: Option<DateTime<Utc>>, // an admin found the confirmation fraudulent
+    #[serde(default)]
+    pub fraud_confirmed_by: Option<String>,
+}
+
+/// Client id for OTP codes sent through the network by the platform itself
+pub const OTP_CLIENT_ID: &str = "system:otp";
+
+/// Limits on client-defined message tags
+pub const MAX_MESSAGE_TAGS: usize = 10;
+pub const MAX_TAG_LENGTH: usize = 32;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum MessagePriority {
     Low,
     Normal,
@@ -65,6 +148,7 @@ impl Message {
     ) -> Self {
         let now = crate::shared::utils::now();
         let recipient_carrier = Carrier::from_phone_number(&recipient);
+        let recipient_prefix = recipient.as_str().chars().take(6).collect();
         
         Self {
             id: crate::shared::utils::generate_id(),
@@ -88,18 +172,120 @@ impl Message {
             updated_at: now,
             scheduled_at: None,
             expires_at: Some(now + chrono::Duration::hours(24)), // 24 hour expiration
+            tags: Vec::new(),
+            cohort: DeploymentCohort::Stable,
+            recipient_hash: None,
+            recipient_ciphertext: None,
+            predicted_delivery_at: None,
+            predicted_delivery_p90_at: None,
+            dedicated_number_id: None,
+            recipient_prefix: Some(recipient_prefix),
+            dispatched_at: None,
+            dispute: None,
+            segment_cost: None,
+            surge_multiplier: None,
+            cancellation: None,
+            ignore_quiet_hours: false,
+            sla_breached_at: None,
+            deleted_at: None,
         }
     }
 
-    pub fn assign_to_provider(&mut self, provider_id: String) {
-        self.provider_id = Some(provider_id);
-        self.status = MessageStatus::Assigned;
-        self.updated_at = crate::shared::utils::now();
+    /// Whether quiet hours hold this message; urgent and OTP messages and
+    /// those sent with the override are delivered at any hour
+    pub fn honors_quiet_hours(&self) -> bool {
+        !self.ignore_quiet_hours
+            && self.priority != MessagePriority::Urgent
+            && self.client_id != OTP_CLIENT_ID
+    }
+
+    /// Keep only the masked recipient plus its hash and ciphertext
+    pub fn protect_recipient(&mut self, recipient_hash: String, recipient_ciphertext: String) {
+        self.recipient = self.recipient.masked();
+        self.recipient_hash = Some(recipient_hash);
+        self.recipient_ciphertext = Some(recipient_ciphertext);
+    }
+
+    pub fn is_recipient_protected(&self) -> bool {
+        self.recipient_ciphertext.is_some()
+    }
+
+    /// Re-derive the carrier from the current prefix table, returning the
+    /// stale value if it changed. Takes the real number, since a protected
+    /// message only stores a masked one.
+    pub fn redetect_carrier(&mut self, recipient: &PhoneNumber) -> Option<Carrier> {
+        let current = Carrier::from_phone_number(recipient);
+        if current == self.recipient_carrier {
+            return None;
+        }
+        Some(std::mem::replace(&mut self.recipient_carrier, current))
+    }
+
+    /// Seconds from submission to delivery, for delivered messages
+    pub fn delivery_latency_seconds(&self) -> Option<i64> {
+        if self.status != MessageStatus::Delivered {
+            return None;
+        }
+        let delivered_at = self
+            .delivery_report
+            .as_ref()
+            .map(|report| report.delivered_at)
+            .unwrap_or(self.updated_at);
+        Some((delivered_at - self.created_at).num_seconds())
+    }
+
+    /// Delivered after the pessimistic end of the quoted ETA
+    pub fn missed_predicted_delivery(&self) -> bool {
+        match (
+            self.delivery_latency_seconds(),
+            self.predicted_delivery_p90_at,
+        ) {
+            (Some(latency), Some(p90_at)) => latency > (p90_at - self.created_at).num_seconds(),
+            _ => false,
+        }
+    }
+
+    /// Whether the lifecycle allows moving from the current status to `to`.
+    /// Re-applying the current status is allowed so repeated reports are harmless.
+    pub fn can_transition_to(&self, to: &MessageStatus) -> bool {
+        use MessageStatus::*;
+        self.status == *to
+            || matches!(
+                (&self.status, to),
+                (Pending, Assigned | Failed | Cancelled)
+                    | (Assigned, Sent | Delivered | Failed | Pending | Cancelled)
+                    | (Sent, Delivered | Failed | Cancelled)
+                    | (Failed, Pending)
+            )
     }
 
-    pub fn mark_sent(&mut self) {
-        self.status = MessageStatus::Sent;
+    /// Move to `to`, rejecting transitions the lifecycle doesn't allow
+    pub fn transition_to(&mut self, to: MessageStatus) -> DomainResult<()> {
+        if !self.can_transition_to(&to) {
+            return Err(DomainError::illegal_transition("message", &self.status, &to));
+        }
+        self.status = to;
         self.updated_at = crate::shared::utils::now();
+        Ok(())
+    }
+
+    pub fn assign_to_provider(&mut self, provider_id: String) -> DomainResult<()> {
+        self.transition_to(MessageStatus::Assigned)?;
+        self.provider_id = Some(provider_id);
+        Ok(())
+    }
+
+    pub fn mark_sent(&mut self) -> DomainResult<()> {
+        self.transition_to(MessageStatus::Sent)?;
+        self.dispatched_at = Some(self.updated_at);
+        Ok(())
+    }
+
+    /// Seconds from dispatch to the provider's confirmation at `confirmed_at`
+    pub fn confirmation_seconds(&self, confirmed_at: DateTime<Utc>) -> Option<f64> {
+        self.dispatched_at.map(|dispatched_at| {
+            (confirmed_at - dispatched_at).num_milliseconds().max(0) as f64 / 1000.0
+        })
     }
 
     pub fn mark_delivered(&mut self, delivery_report: DeliveryReport) {
@@ -120,6 +306,67 @@ impl Message {
         self.updated_at = crate::shared::utils::now();
     }
 
+    /// Cancel the message. One not yet dispatched is cancelled at once; one
+    /// already pushed to a provider's device stays `Sent` until the device
+    /// answers, and `true` is returned so the caller tells it.
+    pub fn request_cancellation(&mut self, reason: CancellationReason) -> DomainResult<bool> {
+        if self.cancellation.is_some() {
+            return Err(DomainError::illegal_transition(
+                "message",
+                &self.status,
+                MessageStatus::Cancelled,
+            ));
+        }
+        let dispatched = self.status == MessageStatus::Sent;
+        if !dispatched {
+            self.transition_to(MessageStatus::Cancelled)?;
+        }
+        let now = crate::shared::utils::now();
+        self.cancellation = Some(MessageCancellation {
+            reason,
+            status: if dispatched {
+                CancellationStatus::Requested
+            } else {
+                CancellationStatus::Confirmed
+            },
+            requested_at: now,
+            resolved_at: (!dispatched).then_some(now),
+        });
+        self.updated_at = now;
+        Ok(dispatched)
+    }
+
+    /// Settle a requested cancellation with the device's answer, or
+    /// `TimedOut`; only `AlreadySent` leaves the message to be confirmed
+    pub fn resolve_cancellation(&mut self, status: CancellationStatus) -> DomainResult<()> {
+        let requested = self
+            .cancellation
+            .as_ref()
+            .is_some_and(|cancellation| cancellation.status == CancellationStatus::Requested);
+        if !requested || status == CancellationStatus::Requested {
+            return Err(DomainError::illegal_transition(
+                "cancellation",
+                self.cancellation.as_ref().map(|c| c.status),
+                status,
+            ));
+        }
+        if status != CancellationStatus::AlreadySent {
+            self.transition_to(MessageStatus::Cancelled)?;
+        }
+        if let Some(cancellation) = self.cancellation.as_mut() {
+            cancellation.status = status;
+            cancellation.resolved_at = Some(crate::shared::utils::now());
+        }
+        Ok(())
+    }
+
+    /// Whether a cancellation is waiting on the provider's device
+    pub fn is_cancel_pending(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .is_some_and(|cancellation| cancellation.status == CancellationStatus::Requested)
+    }
+
     pub fn increment_retry(&mut self) {
         self.metadata.retry_count += 1;
         self.status = MessageStatus::Pending;
@@ -167,9 +414,105 @@ impl Message {
         Ok(())
     }
 
+    /// Lowercase, de-duplicate and validate client tags
+    /// (letters, digits, '-', '_', ':' and '.')
+    pub fn normalize_tags(tags: &[String]) -> Result<Vec<String>, String> {
+        let mut normalized: Vec<String> = Vec::new();
+        for tag in tags {
+            let tag = tag.trim().to_lowercase();
+            if tag.is_empty() || tag.len() > MAX_TAG_LENGTH {
+                return Err(format!("Tags must be 1-{} characters", MAX_TAG_LENGTH));
+            }
+            if !tag
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | ':' | '.'))
+            {
+                return Err(format!("Invalid tag '{}'", tag));
+            }
+            if !normalized.contains(&tag) {
+                normalized.push(tag);
+            }
+        }
+
+        if normalized.len() > MAX_MESSAGE_TAGS {
+            return Err(format!("At most {} tags are allowed per message", MAX_MESSAGE_TAGS));
+        }
+        Ok(normalized)
+    }
+
     pub fn is_deliverable(&self) -> bool {
         matches!(self.status, MessageStatus::Pending | MessageStatus::Assigned)
             && !self.is_expired()
             && self.validate_content().is_ok()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message() -> Message {
+        Message::new(
+            "client-1".to_string(),
+            "Hello".to_string(),
+            PhoneNumber::new("+85512345678".to_string()).unwrap(),
+            MessagePriority::Normal,
+            None,
+            None,
+        )
+    }
+
+    #[test]
+    fn test_undispatched_message_is_cancelled_at_once() {
+        let mut message = message();
+        assert!(!message
+            .request_cancellation(CancellationReason::Client)
+            .unwrap());
+        assert_eq!(message.status, MessageStatus::Cancelled);
+        assert!(!message.is_cancel_pending());
+        assert!(message
+            .request_cancellation(CancellationReason::Client)
+            .is_err());
+    }
+
+    #[test]
+    fn test_dispatched_message_waits_for_the_device() {
+        let mut message = message();
+        message
+            .assign_to_provider("provider-1".to_string())
+            .unwrap();
+        message.mark_sent().unwrap();
+
+        assert!(message
+            .request_cancellation(CancellationReason::Expired)
+            .unwrap());
+        assert_eq!(message.status, MessageStatus::Sent);
+        assert!(message.is_cancel_pending());
+
+        message
+            .resolve_cancellation(CancellationStatus::TimedOut)
+            .unwrap();
+        assert_eq!(message.status, MessageStatus::Cancelled);
+        assert!(message
+            .resolve_cancellation(CancellationStatus::Confirmed)
+            .is_err());
+    }
+
+    #[test]
+    fn test_already_sent_leaves_the_message_to_be_confirmed() {
+        let mut message = message();
+        message
+            .assign_to_provider("provider-1".to_string())
+            .unwrap();
+        message.mark_sent().unwrap();
+        message
+            .request_cancellation(CancellationReason::Client)
+            .unwrap();
+
+        message
+            .resolve_cancellation(CancellationStatus::AlreadySent)
+            .unwrap();
+        assert_eq!(message.status, MessageStatus::Sent);
+        assert!(message.transition_to(MessageStatus::Delivered).is_ok());
+    }
+}
@@ -1,9 +1,85 @@
+pub mod api_client;
+pub mod audit_log;
+pub mod backup;
+pub mod chain_settlement;
+pub mod credential;
+pub mod dead_letter;
+pub mod demand_heatmap;
+pub mod dispute;
+pub mod download_link;
+pub mod earnings_event;
+pub mod earnings_reconciliation;
+pub mod ledger;
+pub mod number_pool;
+pub mod payout;
+pub mod platform_event;
+pub mod pricing_plan;
+pub mod quality_bonus;
+pub mod quality_score;
+pub mod quiet_hours;
+pub mod referral;
+pub mod retention;
+pub mod routing_rule;
+pub mod saved_filter;
+pub mod session;
+pub mod stake;
+pub mod settlement;
+pub mod sla;
+pub mod telegram_link;
+pub mod topup;
 pub mod user;
+pub mod volume_bonus;
 pub mod provider;
 pub mod message;
 pub mod job;
+pub mod job_outbox;
 
-pub use user::User;
-pub use provider::{Provider, Location};
-pub use message::{Message, MessagePriority, MessageMetadata, DeliveryReport, NetworkInfo};
-pub use job::{Job, JobStatus};
+pub use api_client::{ApiClient, API_CLIENT_SCOPES};
+pub use audit_log::AuditLogEntry;
+pub use backup::{BackupCollection, BackupKind, BackupRun, BackupStatus, RestoreDiff};
+pub use chain_settlement::{ChainSettlement, ChainSettlementStatus};
+pub use credential::{CredentialKind, IssuedCredential};
+pub use dead_letter::{DeadLetterJob, DeadLetterStatus};
+pub use demand_heatmap::{DemandHeatmap, HeatmapRow};
+pub use dispute::{Dispute, DisputeStatus};
+pub use download_link::{DownloadLink, DownloadResource};
+pub use earnings_event::EarningsEvent;
+pub use earnings_reconciliation::{
+    EarningsDiscrepancy, EarningsDiscrepancyKind, EarningsReconciliation,
+};
+pub use ledger::{LedgerAccountKind, LedgerEntryKind, LedgerTransaction, LedgerTransactionKind};
+pub use number_pool::{DedicatedNumber, InboundMessage, NumberRentalCharge};
+pub use payout::{
+    Payout, PayoutCurrency, PayoutMethod, PayoutMethodKind, PayoutRun, PayoutStatus, AUTO_APPROVER,
+};
+pub use platform_event::{PlatformEvent, PlatformEventKind};
+pub use pricing_plan::{PricingPlan, PricingPlanKind, VolumeTier};
+pub use quality_bonus::{QualityAssessment, QualityBonus, QualityMetrics, QualityTarget};
+pub use quality_score::{ClientQualityScore, QualityAlert, QualitySla};
+pub use quiet_hours::QuietHours;
+pub use referral::{Referral, ReferralCode, ReferralStatus};
+pub use retention::RetentionReport;
+pub use routing_rule::{
+    parse_condition, CompiledRoutingRule, RoutingAction, RoutingContext, RoutingRule,
+};
+pub use saved_filter::SavedFilter;
+pub use session::{AuthEvent, AuthEventKind, UserSession};
+pub use settlement::{
+    DiscrepancyKind, SettlementDiscrepancy, SettlementLine, SettlementReport, SettlementSource,
+};
+pub use sla::{SlaAttainment, SlaBreach};
+pub use stake::{ProviderStake, StakeSlash, StakeSlashStatus};
+pub use telegram_link::TelegramLink;
+pub use topup::{TopUp, TopUpStatus};
+pub use user::{ClientTier, User};
+pub use volume_bonus::VolumeBonus;
+pub use provider::{
+    CarrierMismatch, DeviceAttestation, DeviceKey, KycSubmission, Location, OnboardingStep, Provider, ProviderOnboarding,
+    ProviderSelfTest, ProviderTier, RecipientRule, SelfTestStatus,
+};
+pub use message::{
+    CancellationReason, CancellationStatus, DeliveryDispute, DeliveryReport, Message,
+    MessageCancellation, MessageMetadata, MessagePriority, NetworkInfo, OTP_CLIENT_ID,
+};
+pub use job::{DispatchMarker, Job, JobAttempt, JobStatus};
+pub use job_outbox::OutboxEntry;
@@ -0,0 +1,118 @@
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::shared::types::{Carrier, PhoneNumber};
+
+/// A provider SIM reserved for one client's traffic and replies.
+///
+/// While assigned, the provider is taken out of the shared pool
+/// (`Provider::dedicated_client_id`) and only relays that client's messages.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DedicatedNumber {
+    pub id: String,
+    pub client_id: String,
+    pub provider_id: String,
+    pub phone: PhoneNumber,
+    pub carrier: Carrier,
+    pub daily_capacity: u32, // messages per day reserved for the client
+    pub monthly_rent: f64,   // PPT tokens, prorated by days assigned
+    pub assigned_by: String,
+    pub assigned_at: DateTime<Utc>,
+    pub released_at: Option<DateTime<Utc>>,
+}
+
+impl DedicatedNumber {
+    pub fn new(
+        client_id: String,
+        provider_id: String,
+        phone: PhoneNumber,
+        carrier: Carrier,
+        daily_capacity: u32,
+        monthly_rent: f64,
+        assigned_by: String,
+    ) -> Self {
+        Self {
+            id: crate::shared::utils::generate_id(),
+            client_id,
+            provider_id,
+            phone,
+            carrier,
+            daily_capacity,
+            monthly_rent,
+            assigned_by,
+            assigned_at: crate::shared::utils::now(),
+            released_at: None,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.released_at.is_none()
+    }
+
+    /// Days of `month` (any date in it) the number was assigned, counting
+    /// the assignment and release days in full
+    pub fn billable_days(&self, month: NaiveDate) -> u32 {
+        let (first, last) = month_bounds(month);
+        let start = self.assigned_at.date_naive().max(first);
+        let end = self
+            .released_at
+            .map(|released| released.date_naive())
+            .unwrap_or(last)
+            .min(last);
+
+        if end < start {
+            0
+        } else {
+            (end - start).num_days() as u32 + 1
+        }
+    }
+
+    /// Rent owed for `month`, prorated by billable days
+    pub fn rent_for(&self, month: NaiveDate) -> f64 {
+        let (first, last) = month_bounds(month);
+        let days_in_month = (last - first).num_days() + 1;
+        self.monthly_rent * self.billable_days(month) as f64 / days_in_month as f64
+    }
+}
+
+/// First and last day of the month containing `date`
+pub fn month_bounds(date: NaiveDate) -> (NaiveDate, NaiveDate) {
+    let first = date.with_day(1).unwrap_or(date);
+    let next = if first.month() == 12 {
+        NaiveDate::from_ymd_opt(first.year() + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(first.year(), first.month() + 1, 1)
+    };
+    let last = next.and_then(|next| next.pred_opt()).unwrap_or(first);
+    (first, last)
+}
+
+/// One number's rent for one month, split between the platform and the SIM's provider
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NumberRentalCharge {
+    pub id: String,
+    pub number_id: String,
+    pub client_id: String,
+    pub provider_id: String,
+    pub period: String, // "YYYY-MM"
+    pub days_billed: u32,
+    pub amount: f64,
+    pub provider_earnings: f64,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An SMS received on a dedicated number, reported by the provider's device
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InboundMessage {
+    pub id: String,
+    pub client_id: String,
+    pub number_id: String,
+    pub provider_id: String,
+    pub to: PhoneNumber,   // the dedicated number
+    pub from: PhoneNumber, // masked for privacy-mode clients
+    pub from_hash: Option<String>,
+    pub content: String,
+    pub in_reply_to: Option<String>, // latest message the client sent this sender from the number
+    pub received_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
@@ -0,0 +1,242 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::settlement::{DiscrepancyKind, SettlementLine};
+use crate::shared::Money;
+
+/// A transfer of provider earnings out through Baray
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Payout {
+    pub id: String,
+    pub provider_id: String,
+    pub run_id: Option<String>, // payout run this transfer was batched in
+    pub amount: f64,            // sent through Baray, in `currency` units; see `native_amount`
+    pub currency: String,
+    pub status: PayoutStatus,
+    pub baray_reference: Option<String>,
+    pub reconciliation_status: ReconciliationStatus,
+    pub reconciled_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    #[serde(default)]
+    pub payout_method_id: Option<String>,
+    #[serde(default)]
+    pub fx_lock: Option<FxLock>,
+    #[serde(default)]
+    pub approved_by: Option<String>,
+    #[serde(default)]
+    pub attempts: u32, // Baray submissions that failed transiently
+    #[serde(default)]
+    pub failure_reason: Option<String>,
+    #[serde(default)]
+    pub review_flags: Vec<String>, // why it was held for admin review
+}
+
+/// Approver recorded on payouts that passed the withdrawal checks
+pub const AUTO_APPROVER: &str = "auto";
+
+/// One pass of the payout processor over approved payouts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PayoutRun {
+    pub id: String,
+    pub submitted_count: u32,
+    pub failed_count: u32,
+    pub deferred_count: u32, // transient Baray errors, retried next run
+    pub settled_count: u32,  // processing payouts Baray finished this run
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+impl PayoutRun {
+    pub fn new() -> Self {
+        Self {
+            id: crate::shared::utils::generate_id(),
+            submitted_count: 0,
+            failed_count: 0,
+            deferred_count: 0,
+            settled_count: 0,
+            started_at: crate::shared::utils::now(),
+            finished_at: None,
+        }
+    }
+}
+
+/// Conversion from PPT earnings into the payout currency, fixed when the
+/// payout is settled so later rate moves don't change what is owed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FxLock {
+    pub native_amount: Money,
+    pub native_currency: String,
+    pub rate: f64, // payout currency units per native unit
+    pub rate_source: String,
+    pub locked_at: DateTime<Utc>,
+}
+
+/// Currency a provider chooses to be paid out in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum PayoutCurrency {
+    Ppt,
+    Khr,
+    Usd,
+}
+
+impl PayoutCurrency {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PayoutCurrency::Ppt => "PPT",
+            PayoutCurrency::Khr => "KHR",
+            PayoutCurrency::Usd => "USD",
+        }
+    }
+
+    /// Round to the currency's smallest unit (riel have no minor unit)
+    pub fn round(&self, amount: f64) -> f64 {
+        let scale = match self {
+            PayoutCurrency::Ppt => 1_000_000.0,
+            PayoutCurrency::Khr => 1.0,
+            PayoutCurrency::Usd => 100.0,
+        };
+        (amount * scale).floor() / scale
+    }
+}
+
+/// Where, and in which currency, a provider wants to be paid
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PayoutMethod {
+    pub id: String,
+    pub kind: PayoutMethodKind,
+    pub account_reference: String, // mobile-money number or wallet address
+    pub currency: PayoutCurrency,
+    #[serde(default)]
+    pub is_default: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PayoutMethodKind {
+    MobileMoney,
+    PptWallet,
+}
+
+impl PayoutMethod {
+    /// Mobile money pays out fiat; wallets receive PPT directly
+    pub fn supports_currency(&self) -> bool {
+        match self.kind {
+            PayoutMethodKind::MobileMoney => self.currency != PayoutCurrency::Ppt,
+            PayoutMethodKind::PptWallet => self.currency == PayoutCurrency::Ppt,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PayoutStatus {
+    Pending,  // flagged by the withdrawal checks (or auto-approval is off), awaiting an admin
+    Approved, // queued for the next payout run
+    Processing,
+    Completed,
+    Failed,
+}
+
+/// Whether a payout has been matched against a Baray settlement report
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReconciliationStatus {
+    Unreconciled,
+    Matched,
+    Discrepancy,
+}
+
+/// Amounts closer than this are considered equal (rounding in Baray reports)
+const AMOUNT_TOLERANCE: f64 = 0.005;
+
+impl Payout {
+    pub fn new(provider_id: String, amount: f64, currency: String, run_id: Option<String>) -> Self {
+        let now = crate::shared::utils::now();
+        Self {
+            id: crate::shared::utils::generate_id(),
+            provider_id,
+            run_id,
+            amount,
+            currency,
+            status: PayoutStatus::Pending,
+            baray_reference: None,
+            reconciliation_status: ReconciliationStatus::Unreconciled,
+            reconciled_at: None,
+            created_at: now,
+            updated_at: now,
+            payout_method_id: None,
+            fx_lock: None,
+            approved_by: None,
+            attempts: 0,
+            failure_reason: None,
+            review_flags: Vec::new(),
+        }
+    }
+
+    /// Settle `native_amount` PPT through `method`, locking the conversion rate
+    pub fn locked(
+        provider_id: String,
+        method: &PayoutMethod,
+        native_amount: Money,
+        rate: f64,
+        rate_source: String,
+    ) -> Self {
+        let amount = method.currency.round(native_amount.to_ppt() * rate);
+        let mut payout = Self::new(
+            provider_id,
+            amount,
+            method.currency.as_str().to_string(),
+            None,
+        );
+        payout.payout_method_id = Some(method.id.clone());
+        payout.fx_lock = Some(FxLock {
+            native_amount,
+            native_currency: PayoutCurrency::Ppt.as_str().to_string(),
+            rate,
+            rate_source,
+            locked_at: payout.created_at,
+        });
+        payout
+    }
+
+    /// Amount drawn from the provider's PPT earnings. Payouts from before
+    /// rates were locked were made in PPT, so their `amount` is it.
+    pub fn native_amount(&self) -> Money {
+        self.fx_lock
+            .as_ref()
+            .map(|lock| lock.native_amount)
+            .unwrap_or_else(|| Money::from_ppt(self.amount))
+    }
+
+    /// Compare against the settlement line Baray reported for this payout
+    pub fn compare_settlement(&self, line: &SettlementLine) -> Option<(DiscrepancyKind, String)> {
+        if !self.currency.eq_ignore_ascii_case(&line.currency) {
+            return Some((
+                DiscrepancyKind::CurrencyMismatch,
+                format!("Recorded {} but Baray settled {}", self.currency, line.currency),
+            ));
+        }
+
+        if (self.amount - line.amount).abs() > AMOUNT_TOLERANCE {
+            return Some((
+                DiscrepancyKind::AmountMismatch,
+                format!("Recorded {:.2} but Baray settled {:.2}", self.amount, line.amount),
+            ));
+        }
+
+        let settled = line.is_settled();
+        let mismatched = match self.status {
+            PayoutStatus::Completed => !settled,
+            PayoutStatus::Failed => settled,
+            PayoutStatus::Pending | PayoutStatus::Approved | PayoutStatus::Processing => false,
+        };
+        if mismatched {
+            return Some((
+                DiscrepancyKind::StatusMismatch,
+                format!("Recorded {:?} but Baray reports '{}'", self.status, line.status),
+            ));
+        }
+
+        None
+    }
+}
@@ -0,0 +1,108 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{Job, Message, Provider};
+use crate::shared::types::MessageStatus;
+
+/// Something that happened on one instance that any instance may react to,
+/// published on the event bus
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlatformEvent {
+    pub id: String,
+    pub kind: PlatformEventKind,
+    pub subject_id: String,        // the message, provider or job it is about
+    pub client_id: Option<String>, // whose message it concerns, if any
+    pub provider_id: Option<String>,
+    pub data: serde_json::Value,
+    #[serde(default)]
+    pub instance_id: String, // where it happened; set when published
+    pub occurred_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PlatformEventKind {
+    #[serde(rename = "message.delivered")]
+    MessageDelivered,
+    #[serde(rename = "message.status_changed")]
+    MessageStatusChanged, // any other status
+    #[serde(rename = "provider.online")]
+    ProviderOnline,
+    #[serde(rename = "job.failed")]
+    JobFailed,
+}
+
+impl PlatformEventKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PlatformEventKind::MessageDelivered => "message.delivered",
+            PlatformEventKind::MessageStatusChanged => "message.status_changed",
+            PlatformEventKind::ProviderOnline => "provider.online",
+            PlatformEventKind::JobFailed => "job.failed",
+        }
+    }
+}
+
+impl PlatformEvent {
+    fn new(
+        kind: PlatformEventKind,
+        subject_id: String,
+        client_id: Option<String>,
+        provider_id: Option<String>,
+        data: serde_json::Value,
+    ) -> Self {
+        Self {
+            id: crate::shared::utils::generate_id(),
+            kind,
+            subject_id,
+            client_id,
+            provider_id,
+            data,
+            instance_id: String::new(),
+            occurred_at: crate::shared::utils::now(),
+        }
+    }
+
+    /// `message.delivered` for a delivery, otherwise `message.status_changed`
+    pub fn message_status(message: &Message) -> Self {
+        let kind = if message.status == MessageStatus::Delivered {
+            PlatformEventKind::MessageDelivered
+        } else {
+            PlatformEventKind::MessageStatusChanged
+        };
+        Self::new(
+            kind,
+            message.id.clone(),
+            Some(message.client_id.clone()),
+            message.provider_id.clone(),
+            serde_json::json!({
+                "status": format!("{:?}", message.status),
+                "changed_at": message.updated_at,
+            }),
+        )
+    }
+
+    pub fn provider_online(provider: &Provider) -> Self {
+        Self::new(
+            PlatformEventKind::ProviderOnline,
+            provider.id.clone(),
+            None,
+            Some(provider.id.clone()),
+            serde_json::json!({ "carrier": format!("{:?}", provider.carrier) }),
+        )
+    }
+
+    /// A job that failed for good, with no retries left
+    pub fn job_failed(job: &Job, message: &Message) -> Self {
+        Self::new(
+            PlatformEventKind::JobFailed,
+            job.id.clone(),
+            Some(message.client_id.clone()),
+            (!job.provider_id.is_empty()).then(|| job.provider_id.clone()),
+            serde_json::json!({
+                "message_id": job.message_id,
+                "error": job.error_message,
+                "attempts": job.retry_count + 1,
+            }),
+        )
+    }
+}
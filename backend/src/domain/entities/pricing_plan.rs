@@ -0,0 +1,153 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::shared::Money;
+
+/// What a client pays per message segment, before the priority multiplier.
+/// Clients without a plan pay the configured `MESSAGE_BASE_COST_PPT`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PricingPlan {
+    pub id: String,
+    pub name: String,
+    pub kind: PricingPlanKind,
+    pub segment_cost: Money, // per segment below the first volume tier
+    #[serde(default)]
+    pub tiers: Vec<VolumeTier>, // ascending by min_monthly_messages
+    #[serde(default)]
+    pub committed_monthly_messages: u64, // CommittedVolume: this volume's tier from day one
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PricingPlanKind {
+    PayAsYouGo,      // flat rate, optionally cheaper as the month's volume grows
+    CommittedVolume, // the tier rate for the committed volume, however much is sent
+    Enterprise,      // a negotiated flat rate
+}
+
+/// A cheaper segment cost once a client has sent `min_monthly_messages`
+/// messages in the calendar month
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VolumeTier {
+    pub min_monthly_messages: u64,
+    pub segment_cost: Money,
+}
+
+impl PricingPlan {
+    pub fn new(
+        name: String,
+        kind: PricingPlanKind,
+        segment_cost: Money,
+        tiers: Vec<VolumeTier>,
+        committed_monthly_messages: u64,
+        created_by: String,
+    ) -> Self {
+        let now = crate::shared::utils::now();
+        Self {
+            id: crate::shared::utils::generate_id(),
+            name,
+            kind,
+            segment_cost,
+            tiers,
+            committed_monthly_messages,
+            created_by,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// Check the rates and tiers make sense for the kind of plan
+    pub fn validate(&self) -> Result<(), String> {
+        let valid_cost = |cost: Money| cost.is_positive();
+        if !valid_cost(self.segment_cost) {
+            return Err("segment_cost must be a positive number of PPT".to_string());
+        }
+        if self.tiers.iter().any(|tier| !valid_cost(tier.segment_cost)) {
+            return Err("Every tier needs a positive segment_cost".to_string());
+        }
+        if self
+            .tiers
+            .windows(2)
+            .any(|pair| pair[0].min_monthly_messages >= pair[1].min_monthly_messages)
+        {
+            return Err("Tiers must be in ascending order of min_monthly_messages".to_string());
+        }
+        match self.kind {
+            PricingPlanKind::PayAsYouGo => Ok(()),
+            PricingPlanKind::CommittedVolume if self.committed_monthly_messages == 0 => {
+                Err("Committed volume plans need committed_monthly_messages".to_string())
+            }
+            PricingPlanKind::CommittedVolume => Ok(()),
+            PricingPlanKind::Enterprise if !self.tiers.is_empty() => {
+                Err("Enterprise plans have a single negotiated rate and no tiers".to_string())
+            }
+            PricingPlanKind::Enterprise => Ok(()),
+        }
+    }
+
+    /// The segment cost for a client that has sent `monthly_messages`
+    /// messages this month
+    pub fn segment_cost_at(&self, monthly_messages: u64) -> Money {
+        let volume = match self.kind {
+            PricingPlanKind::CommittedVolume => {
+                monthly_messages.max(self.committed_monthly_messages)
+            }
+            _ => monthly_messages,
+        };
+        self.tiers
+            .iter()
+            .rev()
+            .find(|tier| volume >= tier.min_monthly_messages)
+            .map(|tier| tier.segment_cost)
+            .unwrap_or(self.segment_cost)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan(kind: PricingPlanKind, committed: u64) -> PricingPlan {
+        PricingPlan::new(
+            "Growth".to_string(),
+            kind,
+            Money::from_ppt(0.01),
+            vec![
+                VolumeTier {
+                    min_monthly_messages: 10_000,
+                    segment_cost: Money::from_ppt(0.008),
+                },
+                VolumeTier {
+                    min_monthly_messages: 100_000,
+                    segment_cost: Money::from_ppt(0.006),
+                },
+            ],
+            committed,
+            "admin".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_segment_cost_by_monthly_volume() {
+        let payg = plan(PricingPlanKind::PayAsYouGo, 0);
+        assert_eq!(payg.segment_cost_at(0), Money::from_ppt(0.01));
+        assert_eq!(payg.segment_cost_at(10_000), Money::from_ppt(0.008));
+        assert_eq!(payg.segment_cost_at(250_000), Money::from_ppt(0.006));
+
+        // Committing to 100k a month prices the first message at the 100k rate
+        let committed = plan(PricingPlanKind::CommittedVolume, 100_000);
+        assert_eq!(committed.segment_cost_at(0), Money::from_ppt(0.006));
+        assert!(committed.validate().is_ok());
+
+        assert!(plan(PricingPlanKind::CommittedVolume, 0)
+            .validate()
+            .is_err());
+        assert!(plan(PricingPlanKind::Enterprise, 0).validate().is_err());
+
+        let mut unordered = plan(PricingPlanKind::PayAsYouGo, 0);
+        unordered.tiers.reverse();
+        assert!(unordered.validate().is_err());
+    }
+}
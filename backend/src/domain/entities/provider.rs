@@ -1,11 +1,15 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use crate::shared::types::{PhoneNumber, Carrier, ProviderStatus};
+use super::payout::PayoutMethod;
+use super::stake::ProviderStake;
+use crate::domain::errors::{DomainError, DomainResult};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Provider {
     pub id: String,
     pub user_id: String,
+    #[serde(with = "crate::shared::field_encryption::encrypted_phone")]
     pub phone: PhoneNumber,
     pub carrier: Carrier,
     pub status: ProviderStatus,
@@ -19,9 +23,209 @@ pub struct Provider {
     pub success_rate: f64,
     pub total_messages_sent: u64,
     pub total_messages_delivered: u64,
-    pub earnings_total: f64,
+    pub earnings_total: f64, // pre-ledger earnings only; the ledger is authoritative
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    #[serde(default)]
+    pub carrier_verified_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub carrier_reverification_required: bool,
+    #[serde(default)]
+    pub carrier_mismatch: Option<CarrierMismatch>,
+    #[serde(default)]
+    pub self_test: Option<ProviderSelfTest>,
+    #[serde(default)]
+    pub recipient_rules: Vec<RecipientRule>,
+    #[serde(default)]
+    pub last_assigned_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub first_heartbeat_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub kyc: Option<KycSubmission>,
+    #[serde(default)]
+    pub payout_methods: Vec<PayoutMethod>,
+    #[serde(default)]
+    pub dedicated_client_id: Option<String>, // rented out as a dedicated number; off the shared pool
+    #[serde(default)]
+    pub device_key: Option<DeviceKey>, // signs delivery confirmations
+    #[serde(default)]
+    pub attestation: Option<DeviceAttestation>, // latest Play Integrity verdict
+    #[serde(default)]
+    pub stake: Option<ProviderStake>, // PPT staked on-chain for higher limits
+    #[serde(default)]
+    pub deleted_at: Option<DateTime<Utc>>, // soft-deleted; purged after the retention window
+}
+
+/// Provider quality tier, ordered lowest to highest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum ProviderTier {
+    Bronze,
+    Silver,
+    Gold,
+}
+
+impl ProviderTier {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "bronze" => Some(ProviderTier::Bronze),
+            "silver" => Some(ProviderTier::Silver),
+            "gold" => Some(ProviderTier::Gold),
+            _ => None,
+        }
+    }
+}
+
+/// Recorded when the SIM carrier reported by the device disagrees with the registered carrier
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CarrierMismatch {
+    pub registered: Carrier,
+    pub detected: Carrier,
+    pub source: String, // "heartbeat", "reverification"
+    pub detected_at: DateTime<Utc>,
+}
+
+/// Provider opt-out for recipients it will not relay to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RecipientRule {
+    /// Never relay to numbers starting with this E.164 prefix
+    DenyPrefix { prefix: String },
+    /// Only relay to numbers on the provider's own carrier
+    OwnCarrierOnly,
+    /// Only relay to Cambodian (+855) numbers
+    DomesticOnly,
+}
+
+impl RecipientRule {
+    pub fn allows(&self, recipient: &PhoneNumber, provider_carrier: &Carrier) -> bool {
+        match self {
+            RecipientRule::DenyPrefix { prefix } => !recipient.as_str().starts_with(prefix.as_str()),
+            RecipientRule::OwnCarrierOnly => {
+                Carrier::from_phone_number(recipient) == *provider_carrier
+            }
+            RecipientRule::DomesticOnly => recipient.as_str().starts_with("+855"),
+        }
+    }
+}
+
+/// Latest loopback send test run from the provider device
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderSelfTest {
+    pub message_id: String,
+    pub recipient: String,
+    pub status: SelfTestStatus,
+    pub requested_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub failure_reason: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SelfTestStatus {
+    Pending,
+    Passed,
+    Failed,
+}
+
+/// Client ID used for messages generated by provider self-tests
+pub const SELF_TEST_CLIENT_ID: &str = "system:provider-self-test";
+
+impl ProviderSelfTest {
+    pub fn new(message_id: String, recipient: String) -> Self {
+        Self {
+            message_id,
+            recipient,
+            status: SelfTestStatus::Pending,
+            requested_at: crate::shared::utils::now(),
+            completed_at: None,
+            failure_reason: None,
+        }
+    }
+
+    /// Pending tests older than the job timeout are considered abandoned
+    pub fn is_in_progress(&self) -> bool {
+        self.status == SelfTestStatus::Pending
+            && (crate::shared::utils::now() - self.requested_at).num_minutes() < 10
+    }
+
+    pub fn complete(&mut self, passed: bool, failure_reason: Option<String>) {
+        self.status = if passed {
+            SelfTestStatus::Passed
+        } else {
+            SelfTestStatus::Failed
+        };
+        self.failure_reason = failure_reason;
+        self.completed_at = Some(crate::shared::utils::now());
+    }
+}
+
+/// Public half of the keypair generated on the provider's device. The private
+/// key never leaves the device, so a stolen access token alone can't confirm
+/// deliveries.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceKey {
+    pub public_key: String,  // SPKI PEM, RSA >= 2048 bits
+    pub fingerprint: String, // SHA-256 of the DER key, hex
+    pub enrolled_at: DateTime<Utc>,
+}
+
+/// Play Integrity verdict for the provider's device. The attestation nonce is
+/// the device key fingerprint, so a verdict can't be lifted from another phone.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceAttestation {
+    pub device_verdicts: Vec<String>, // e.g. "MEETS_DEVICE_INTEGRITY"
+    pub app_verdict: String,          // "PLAY_RECOGNIZED", "UNRECOGNIZED_VERSION", ...
+    pub licensing_verdict: Option<String>,
+    pub passed: bool, // genuine Play-recognized app on a device meeting device integrity
+    pub attested_at: DateTime<Utc>,
+}
+
+/// Identity document submitted by the provider for KYC review
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KycSubmission {
+    pub document_type: String,      // "national_id", "passport"
+    pub document_reference: String, // upload reference, never the document itself
+    pub submitted_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnboardingStep {
+    PhoneVerified,
+    FcmRegistered,
+    FirstHeartbeat,
+    SelfTestPassed,
+    KycSubmitted,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OnboardingStepState {
+    pub step: OnboardingStep,
+    pub completed: bool,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+/// Provider onboarding progress; a provider can only go online once every step is done
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderOnboarding {
+    pub steps: Vec<OnboardingStepState>,
+}
+
+impl ProviderOnboarding {
+    pub fn is_complete(&self) -> bool {
+        self.steps.iter().all(|step| step.completed)
+    }
+
+    pub fn completed_count(&self) -> usize {
+        self.steps.iter().filter(|step| step.completed).count()
+    }
+
+    pub fn pending_steps(&self) -> Vec<OnboardingStep> {
+        self.steps
+            .iter()
+            .filter(|step| !step.completed)
+            .map(|step| step.step)
+            .collect()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -54,14 +258,30 @@ impl Provider {
             earnings_total: 0.0,
             created_at: now,
             updated_at: now,
+            carrier_verified_at: None,
+            carrier_reverification_required: false,
+            carrier_mismatch: None,
+            self_test: None,
+            recipient_rules: Vec::new(),
+            last_assigned_at: None,
+            first_heartbeat_at: None,
+            kyc: None,
+            payout_methods: Vec::new(),
+            dedicated_client_id: None,
+            device_key: None,
+            attestation: None,
+            stake: None,
+            deleted_at: None,
         }
     }
 
     pub fn is_available(&self) -> bool {
         matches!(self.status, ProviderStatus::Online) 
             && self.current_load < 5 // Max concurrent messages
-            && self.messages_sent_today < self.max_daily_messages
+            && self.messages_sent_today < self.daily_limit()
             && self.is_heartbeat_recent()
+            && self.carrier_mismatch.is_none()
+            && self.deleted_at.is_none()
     }
 
     pub fn is_heartbeat_recent(&self) -> bool {
@@ -95,6 +315,34 @@ impl Provider {
         self.updated_at = crate::shared::utils::now();
     }
 
+    /// Messages the provider may send today: its own limit, or the one its
+    /// stake unlocks if higher
+    pub fn daily_limit(&self) -> u32 {
+        self.stake
+            .as_ref()
+            .map_or(self.max_daily_messages, |stake| {
+                self.max_daily_messages.max(stake.max_daily_messages)
+            })
+    }
+
+    /// Whether the provider's stake earns it priority in job assignment
+    pub fn has_stake_priority(&self) -> bool {
+        self.stake.as_ref().is_some_and(|stake| stake.priority)
+    }
+
+    /// Take on a newly assigned job, unless today's quota is used up
+    pub fn record_assignment(&mut self) -> DomainResult<()> {
+        if self.messages_sent_today >= self.daily_limit() {
+            return Err(DomainError::QuotaExceeded {
+                resource: format!("provider {} daily messages", self.id),
+                limit: self.daily_limit() as u64,
+            });
+        }
+        self.increment_load();
+        self.last_assigned_at = Some(crate::shared::utils::now());
+        Ok(())
+    }
+
     pub fn decrement_load(&mut self) {
         if self.current_load > 0 {
             self.current_load -= 1;
@@ -135,4 +383,94 @@ impl Provider {
         self.location = Some(location);
         self.updated_at = crate::shared::utils::now();
     }
+
+    /// Whether the provider's recipient rules permit relaying to this number
+    /// Quality tier derived from reputation, used by routing rules
+    pub fn tier(&self) -> ProviderTier {
+        if self.reputation_score >= 80.0 {
+            ProviderTier::Gold
+        } else if self.reputation_score >= 60.0 {
+            ProviderTier::Silver
+        } else {
+            ProviderTier::Bronze
+        }
+    }
+
+    pub fn accepts_recipient(&self, recipient: &PhoneNumber) -> bool {
+        self.recipient_rules
+            .iter()
+            .all(|rule| rule.allows(recipient, &self.carrier))
+    }
+
+    pub fn has_passed_self_test(&self) -> bool {
+        self.self_test
+            .as_ref()
+            .map(|test| test.status == SelfTestStatus::Passed)
+            .unwrap_or(false)
+    }
+
+    /// Onboarding checklist; phone verification lives on the owning user
+    pub fn onboarding(&self, phone_verified: bool) -> ProviderOnboarding {
+        let self_test_passed_at = self
+            .self_test
+            .as_ref()
+            .filter(|_| self.has_passed_self_test())
+            .and_then(|test| test.completed_at);
+
+        let step = |step, completed: bool, completed_at| OnboardingStepState {
+            step,
+            completed,
+            completed_at: if completed { completed_at } else { None },
+        };
+
+        ProviderOnboarding {
+            steps: vec![
+                step(OnboardingStep::PhoneVerified, phone_verified, None),
+                step(OnboardingStep::FcmRegistered, self.fcm_token.is_some(), None),
+                step(
+                    OnboardingStep::FirstHeartbeat,
+                    self.first_heartbeat_at.is_some() || self.last_heartbeat.is_some(),
+                    self.first_heartbeat_at.or(self.last_heartbeat),
+                ),
+                step(
+                    OnboardingStep::SelfTestPassed,
+                    self_test_passed_at.is_some(),
+                    self_test_passed_at,
+                ),
+                step(
+                    OnboardingStep::KycSubmitted,
+                    self.kyc.is_some(),
+                    self.kyc.as_ref().map(|kyc| kyc.submitted_at),
+                ),
+            ],
+        }
+    }
+
+    /// Whether the registered carrier was last confirmed more than `max_age_days` ago
+    pub fn is_carrier_verification_stale(&self, max_age_days: i64) -> bool {
+        let verified_at = self.carrier_verified_at.unwrap_or(self.created_at);
+        (crate::shared::utils::now() - verified_at).num_days() >= max_age_days
+    }
+
+    pub fn flag_carrier_mismatch(&mut self, detected: Carrier, source: &str) {
+        let now = crate::shared::utils::now();
+        self.carrier_mismatch = Some(CarrierMismatch {
+            registered: self.carrier.clone(),
+            detected,
+            source: source.to_string(),
+            detected_at: now,
+        });
+        self.carrier_reverification_required = true;
+        self.updated_at = now;
+    }
+
+    /// Store the carrier confirmed by re-verification and clear any outstanding flags
+    pub fn confirm_carrier(&mut self, carrier: Carrier) {
+        let now = crate::shared::utils::now();
+        self.carrier = carrier;
+        self.carrier_verified_at = Some(now);
+        self.carrier_reverification_required = false;
+        self.carrier_mismatch = None;
+        self.updated_at = now;
+    }
 }
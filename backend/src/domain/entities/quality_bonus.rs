@@ -0,0 +1,82 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::shared::Money;
+
+/// A provider's delivery record over a period, as the quality bonus sees it
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QualityMetrics {
+    pub delivered: u64,
+    pub failed: u64,
+    pub disputed: u64,
+    pub average_confirmation_seconds: Option<f64>,
+}
+
+impl QualityMetrics {
+    pub fn success_rate(&self) -> Option<f64> {
+        let attempted = self.delivered + self.failed;
+        (attempted > 0).then(|| self.delivered as f64 / attempted as f64 * 100.0)
+    }
+
+    pub fn dispute_rate(&self) -> Option<f64> {
+        (self.delivered > 0).then(|| self.disputed as f64 / self.delivered as f64 * 100.0)
+    }
+}
+
+/// How a provider measures up against each quality target, and the bonus
+/// percent that earns them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QualityAssessment {
+    pub metrics: QualityMetrics,
+    pub eligible: bool, // enough deliveries for the rates to mean something
+    pub min_deliveries: u64,
+    pub bonus_percent: f64,
+    pub targets: Vec<QualityTarget>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QualityTarget {
+    pub metric: String, // "success_rate", "confirmation_seconds", "dispute_rate"
+    pub value: Option<f64>,
+    pub target: f64,
+    pub met: bool,
+    pub bonus_percent: f64, // paid for meeting it
+    pub advice: String,
+}
+
+/// A provider's quality bonus for one month, credited by the daily rollup
+/// once the month is over
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QualityBonus {
+    pub id: String,
+    pub provider_id: String,
+    pub period: String, // local "YYYY-MM"
+    pub assessment: QualityAssessment,
+    pub base_earnings: Money, // the bonus is a percentage of this, before off-peak boosts
+    pub amount: Money,
+    pub created_at: DateTime<Utc>,
+}
+
+impl QualityBonus {
+    pub fn new(
+        provider_id: String,
+        period: String,
+        assessment: QualityAssessment,
+        base_earnings: Money,
+    ) -> Self {
+        Self {
+            id: crate::shared::utils::generate_id(),
+            provider_id,
+            period,
+            amount: base_earnings.percent(assessment.bonus_percent),
+            assessment,
+            base_earnings,
+            created_at: crate::shared::utils::now(),
+        }
+    }
+
+    /// Ledger reference: one bonus per provider per month
+    pub fn reference(&self) -> String {
+        format!("{}:{}", self.provider_id, self.period)
+    }
+}
@@ -0,0 +1,78 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Daily Apdex-style service quality score for a client
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClientQualityScore {
+    pub id: String,
+    pub client_id: String,
+    pub date: String, // YYYY-MM-DD the scored messages were submitted
+    pub total: u32,
+    pub satisfied: u32,  // delivered within the target latency
+    pub tolerating: u32, // delivered within four times the target
+    pub frustrated: u32, // delivered later, failed, or never delivered
+    #[serde(default)]
+    pub eta_missed: u32, // delivered after the p90 ETA quoted at submission
+    pub delivery_rate: f64,
+    pub score: f64, // 0.0 - 1.0
+    pub below_threshold: bool,
+    pub generated_at: DateTime<Utc>,
+}
+
+/// Contractual quality floor agreed with an enterprise client
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QualitySla {
+    pub min_score: f64,
+    pub consecutive_days: u32,
+}
+
+/// Raised when a client's score stays below its SLA floor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QualityAlert {
+    pub id: String,
+    pub client_id: String,
+    pub date: String,
+    pub score: f64,
+    pub min_score: f64,
+    pub consecutive_days: u32,
+    #[serde(default)]
+    pub notified: bool, // the account team's webhook accepted it
+    pub created_at: DateTime<Utc>,
+}
+
+impl ClientQualityScore {
+    pub fn new(client_id: String, date: String) -> Self {
+        Self {
+            id: crate::shared::utils::generate_id(),
+            client_id,
+            date,
+            total: 0,
+            satisfied: 0,
+            tolerating: 0,
+            frustrated: 0,
+            eta_missed: 0,
+            delivery_rate: 0.0,
+            score: 0.0,
+            below_threshold: false,
+            generated_at: crate::shared::utils::now(),
+        }
+    }
+
+    /// Bucket one message by delivery latency; `None` means it was not delivered
+    pub fn record(&mut self, latency_seconds: Option<i64>, target_seconds: i64) {
+        self.total += 1;
+        match latency_seconds {
+            Some(latency) if latency <= target_seconds => self.satisfied += 1,
+            Some(latency) if latency <= target_seconds * 4 => self.tolerating += 1,
+            _ => self.frustrated += 1,
+        }
+    }
+
+    pub fn finalize(&mut self) {
+        if self.total > 0 {
+            let total = self.total as f64;
+            self.delivery_rate = (self.satisfied + self.tolerating) as f64 / total;
+            self.score = (self.satisfied as f64 + self.tolerating as f64 / 2.0) / total;
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use chrono::{DateTime, Duration, NaiveTime, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A daily window, in local time, when messages are held instead of
+/// delivered. `start_hour` is inclusive and `end_hour` exclusive; a window
+/// with `start_hour` after `end_hour` runs past midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuietHours {
+    pub enabled: bool,
+    pub start_hour: u32,
+    pub end_hour: u32,
+}
+
+impl QuietHours {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.start_hour > 23 || self.end_hour > 23 {
+            return Err("Hours must be between 0 and 23".to_string());
+        }
+        if self.start_hour == self.end_hour {
+            return Err("Start and end hour must differ".to_string());
+        }
+        Ok(())
+    }
+
+    /// When the window holding `at` ends, or `None` if `at` is outside it
+    pub fn resumes_at(&self, at: DateTime<Utc>, utc_offset_hours: i32) -> Option<DateTime<Utc>> {
+        if !self.enabled || self.start_hour == self.end_hour {
+            return None;
+        }
+        let offset = Duration::hours(utc_offset_hours as i64);
+        let local = at + offset;
+        let hour = local.hour();
+        let quiet = if self.start_hour < self.end_hour {
+            hour >= self.start_hour && hour < self.end_hour
+        } else {
+            hour >= self.start_hour || hour < self.end_hour
+        };
+        if !quiet {
+            return None;
+        }
+
+        let mut end = local
+            .date_naive()
+            .and_time(NaiveTime::from_hms_opt(self.end_hour, 0, 0)?)
+            .and_utc();
+        if end <= local {
+            end += Duration::days(1);
+        }
+        Some(end - offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn nightly() -> QuietHours {
+        QuietHours {
+            enabled: true,
+            start_hour: 22,
+            end_hour: 7,
+        }
+    }
+
+    #[test]
+    fn test_window_past_midnight_ends_next_morning() {
+        // 23:30 in Phnom Penh (UTC+7)
+        let late = Utc.with_ymd_and_hms(2024, 5, 1, 16, 30, 0).unwrap();
+        assert_eq!(
+            nightly().resumes_at(late, 7),
+            Some(Utc.with_ymd_and_hms(2024, 5, 2, 0, 0, 0).unwrap())
+        );
+
+        // 03:00 local, the same window
+        let early = Utc.with_ymd_and_hms(2024, 5, 1, 20, 0, 0).unwrap();
+        assert_eq!(
+            nightly().resumes_at(early, 7),
+            Some(Utc.with_ymd_and_hms(2024, 5, 2, 0, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_outside_or_disabled_window_does_not_hold() {
+        // 07:00 and 21:59 local
+        let morning = Utc.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap();
+        let evening = Utc.with_ymd_and_hms(2024, 5, 1, 14, 59, 0).unwrap();
+        assert_eq!(nightly().resumes_at(morning, 7), None);
+        assert_eq!(nightly().resumes_at(evening, 7), None);
+
+        let disabled = QuietHours {
+            enabled: false,
+            ..nightly()
+        };
+        let late = Utc.with_ymd_and_hms(2024, 5, 1, 16, 30, 0).unwrap();
+        assert_eq!(disabled.resumes_at(late, 7), None);
+    }
+
+    #[test]
+    fn test_same_day_window() {
+        let lunch = QuietHours {
+            enabled: true,
+            start_hour: 12,
+            end_hour: 14,
+        };
+        let noon = Utc.with_ymd_and_hms(2024, 5, 1, 5, 15, 0).unwrap(); // 12:15 local
+        assert_eq!(
+            lunch.resumes_at(noon, 7),
+            Some(Utc.with_ymd_and_hms(2024, 5, 1, 7, 0, 0).unwrap())
+        );
+        assert!(lunch.validate().is_ok());
+        assert!(QuietHours {
+            end_hour: 24,
+            ..lunch
+        }
+        .validate()
+        .is_err());
+    }
+}
@@ -0,0 +1,94 @@
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Referral codes avoid look-alike characters (0/O, 1/I/L)
+const REFERRAL_CODE_ALPHABET: &[u8] = b"23456789ABCDEFGHJKMNPQRSTUVWXYZ";
+const REFERRAL_CODE_LENGTH: usize = 8;
+
+/// A user's code for inviting others, created the first time they ask for it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReferralCode {
+    pub user_id: String,
+    pub code: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ReferralCode {
+    pub fn new(user_id: String) -> Self {
+        let mut rng = rand::thread_rng();
+        let code = (0..REFERRAL_CODE_LENGTH)
+            .map(|_| REFERRAL_CODE_ALPHABET[rng.gen_range(0..REFERRAL_CODE_ALPHABET.len())] as char)
+            .collect();
+        Self {
+            user_id,
+            code,
+            created_at: crate::shared::utils::now(),
+        }
+    }
+
+    /// Codes are matched case-insensitively, ignoring surrounding whitespace
+    pub fn normalize(code: &str) -> String {
+        code.trim().to_uppercase()
+    }
+}
+
+/// A user who signed up with someone else's code. Both are paid a bonus
+/// once the referee has had enough successful deliveries.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Referral {
+    pub id: String,
+    pub code: String,
+    pub referrer_id: String,
+    pub referee_id: String,
+    pub status: ReferralStatus,
+    pub successful_deliveries: u32,
+    pub required_deliveries: u32,
+    pub referrer_bonus: f64, // PPT, fixed when the referee signs up
+    pub referee_bonus: f64,
+    pub created_at: DateTime<Utc>,
+    pub rewarded_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReferralStatus {
+    Pending,  // waiting on the referee's deliveries
+    Rewarded, // bonuses posted to both ledger accounts
+}
+
+impl ReferralStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReferralStatus::Pending => "pending",
+            ReferralStatus::Rewarded => "rewarded",
+        }
+    }
+}
+
+impl Referral {
+    pub fn new(
+        code: &ReferralCode,
+        referee_id: String,
+        required_deliveries: u32,
+        referrer_bonus: f64,
+        referee_bonus: f64,
+    ) -> Self {
+        Self {
+            id: crate::shared::utils::generate_id(),
+            code: code.code.clone(),
+            referrer_id: code.user_id.clone(),
+            referee_id,
+            status: ReferralStatus::Pending,
+            successful_deliveries: 0,
+            required_deliveries,
+            referrer_bonus,
+            referee_bonus,
+            created_at: crate::shared::utils::now(),
+            rewarded_at: None,
+        }
+    }
+
+    pub fn is_earned(&self) -> bool {
+        self.successful_deliveries >= self.required_deliveries
+    }
+}
@@ -0,0 +1,15 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// What one retention policy deleted, or in a dry run would have
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionReport {
+    pub collection: String,
+    pub retention_days: i64,
+    pub cutoff: DateTime<Utc>, // finished before this
+    pub matched: u64,
+    pub deleted: u64, // 0 in a dry run
+    pub dry_run: bool,
+    #[serde(default)]
+    pub purge: bool, // soft-deleted records, aged from their deletion
+}
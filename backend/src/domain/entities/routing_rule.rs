@@ -0,0 +1,280 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::message::{Message, MessagePriority};
+use super::provider::{Provider, ProviderTier};
+use super::user::ClientTier;
+use crate::shared::types::Carrier;
+
+/// Operator-defined routing override applied before provider selection.
+///
+/// `condition` is a small DSL of `AND`-joined clauses over the message:
+///
+/// ```text
+/// prefix = +85513 AND priority = Urgent
+/// carrier in (smart, cellcard) AND client_tier != standard
+/// ```
+///
+/// Fields are `carrier`, `prefix`, `priority` and `client_tier`; operators
+/// are `=`, `!=` and `in (...)`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoutingRule {
+    pub id: String,
+    pub name: String,
+    pub condition: String,
+    pub action: RoutingAction,
+    pub enabled: bool,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// What a matching rule does to the candidate providers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RoutingAction {
+    /// Only providers at or above this tier
+    MinProviderTier { tier: ProviderTier },
+    /// Only providers on one of these carriers
+    ProviderCarriers { carriers: Vec<Carrier> },
+    /// Never these providers
+    ExcludeProviders { provider_ids: Vec<String> },
+}
+
+impl RoutingAction {
+    pub fn allows(&self, provider: &Provider) -> bool {
+        match self {
+            RoutingAction::MinProviderTier { tier } => provider.tier() >= *tier,
+            RoutingAction::ProviderCarriers { carriers } => carriers.contains(&provider.carrier),
+            RoutingAction::ExcludeProviders { provider_ids } => {
+                !provider_ids.iter().any(|id| id == &provider.id)
+            }
+        }
+    }
+}
+
+/// Message attributes a rule condition can test
+#[derive(Debug, Clone)]
+pub struct RoutingContext {
+    pub carrier: Carrier,
+    pub recipient: String,
+    pub priority: MessagePriority,
+    pub client_tier: ClientTier,
+}
+
+impl RoutingContext {
+    pub fn for_message(message: &Message, client_tier: ClientTier) -> Self {
+        Self {
+            carrier: message.recipient_carrier.clone(),
+            recipient: message.recipient.as_str().to_string(),
+            priority: message.priority.clone(),
+            client_tier,
+        }
+    }
+}
+
+/// One parsed `field op value(s)` clause
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuleClause {
+    Carrier(Vec<Carrier>, bool),
+    Prefix(Vec<String>, bool),
+    Priority(Vec<MessagePriority>, bool),
+    ClientTier(Vec<ClientTier>, bool),
+}
+
+impl RuleClause {
+    fn matches(&self, context: &RoutingContext) -> bool {
+        let (hit, negated) = match self {
+            RuleClause::Carrier(carriers, negated) => {
+                (carriers.contains(&context.carrier), negated)
+            }
+            RuleClause::Prefix(prefixes, negated) => (
+                prefixes
+                    .iter()
+                    .any(|prefix| context.recipient.starts_with(prefix.as_str())),
+                negated,
+            ),
+            RuleClause::Priority(priorities, negated) => {
+                (priorities.contains(&context.priority), negated)
+            }
+            RuleClause::ClientTier(tiers, negated) => {
+                (tiers.contains(&context.client_tier), negated)
+            }
+        };
+        hit != *negated
+    }
+}
+
+/// Parse a rule condition into its clauses (all of which must match)
+pub fn parse_condition(condition: &str) -> Result<Vec<RuleClause>, String> {
+    let condition = condition.trim();
+    if condition.is_empty() {
+        return Err("Condition cannot be empty".to_string());
+    }
+
+    condition
+        .split(" AND ")
+        .map(|clause| parse_clause(clause.trim()))
+        .collect()
+}
+
+fn parse_clause(clause: &str) -> Result<RuleClause, String> {
+    let (field, negated, values) = if let Some((field, values)) = clause.split_once("!=") {
+        (field, true, vec![values.trim()])
+    } else if let Some((field, values)) = clause.split_once('=') {
+        (field, false, vec![values.trim()])
+    } else if let Some((field, values)) = clause.split_once(" in ") {
+        let values = values
+            .trim()
+            .strip_prefix('(')
+            .and_then(|values| values.strip_suffix(')'))
+            .ok_or_else(|| format!("Expected a parenthesised list in '{}'", clause))?;
+        (field, false, values.split(',').map(str::trim).collect())
+    } else {
+        return Err(format!("Expected '=', '!=' or 'in' in '{}'", clause));
+    };
+
+    if values.iter().any(|value| value.is_empty()) {
+        return Err(format!("Missing value in '{}'", clause));
+    }
+
+    match field.trim().to_lowercase().as_str() {
+        "carrier" => values
+            .iter()
+            .map(|value| {
+                Carrier::from_name(value).ok_or_else(|| format!("Unknown carrier '{}'", value))
+            })
+            .collect::<Result<_, _>>()
+            .map(|carriers| RuleClause::Carrier(carriers, negated)),
+        "prefix" => values
+            .iter()
+            .map(|value| {
+                let digits = value.strip_prefix('+').unwrap_or_default();
+                if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+                    Err(format!(
+                        "Invalid prefix '{}': must be '+' followed by digits",
+                        value
+                    ))
+                } else {
+                    Ok(value.to_string())
+                }
+            })
+            .collect::<Result<_, _>>()
+            .map(|prefixes| RuleClause::Prefix(prefixes, negated)),
+        "priority" => values
+            .iter()
+            .map(|value| {
+                parse_priority(value).ok_or_else(|| format!("Unknown priority '{}'", value))
+            })
+            .collect::<Result<_, _>>()
+            .map(|priorities| RuleClause::Priority(priorities, negated)),
+        "client_tier" => values
+            .iter()
+            .map(|value| {
+                ClientTier::from_name(value)
+                    .ok_or_else(|| format!("Unknown client tier '{}'", value))
+            })
+            .collect::<Result<_, _>>()
+            .map(|tiers| RuleClause::ClientTier(tiers, negated)),
+        other => Err(format!(
+            "Unknown field '{}' (expected carrier, prefix, priority or client_tier)",
+            other
+        )),
+    }
+}
+
+fn parse_priority(name: &str) -> Option<MessagePriority> {
+    match name.trim().to_lowercase().as_str() {
+        "low" => Some(MessagePriority::Low),
+        "normal" => Some(MessagePriority::Normal),
+        "high" => Some(MessagePriority::High),
+        "urgent" => Some(MessagePriority::Urgent),
+        _ => None,
+    }
+}
+
+/// A rule with its condition parsed, ready to evaluate
+#[derive(Debug, Clone)]
+pub struct CompiledRoutingRule {
+    pub rule: RoutingRule,
+    clauses: Vec<RuleClause>,
+}
+
+impl CompiledRoutingRule {
+    pub fn compile(rule: RoutingRule) -> Result<Self, String> {
+        let clauses = parse_condition(&rule.condition)?;
+        Ok(Self { rule, clauses })
+    }
+
+    pub fn matches(&self, context: &RoutingContext) -> bool {
+        self.clauses.iter().all(|clause| clause.matches(context))
+    }
+
+    /// Whether evaluating this rule needs the client's tier looked up
+    pub fn uses_client_tier(&self) -> bool {
+        self.clauses
+            .iter()
+            .any(|clause| matches!(clause, RuleClause::ClientTier(..)))
+    }
+}
+
+impl RoutingRule {
+    pub fn new(name: String, condition: String, action: RoutingAction, created_by: String) -> Self {
+        let now = crate::shared::utils::now();
+        Self {
+            id: crate::shared::utils::generate_id(),
+            name,
+            condition,
+            action,
+            enabled: true,
+            created_by,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(recipient: &str, priority: MessagePriority) -> RoutingContext {
+        RoutingContext {
+            carrier: Carrier::Qb,
+            recipient: recipient.to_string(),
+            priority,
+            client_tier: ClientTier::Business,
+        }
+    }
+
+    #[test]
+    fn matches_all_clauses() {
+        let clauses = parse_condition("prefix = +85513 AND priority = Urgent").unwrap();
+        let rule = |ctx: &RoutingContext| clauses.iter().all(|clause| clause.matches(ctx));
+
+        assert!(rule(&context("+85513123456", MessagePriority::Urgent)));
+        assert!(!rule(&context("+85513123456", MessagePriority::Normal)));
+        assert!(!rule(&context("+85512123456", MessagePriority::Urgent)));
+    }
+
+    #[test]
+    fn supports_lists_and_negation() {
+        let clauses =
+            parse_condition("carrier in (qb, smart) AND client_tier != standard").unwrap();
+        let ctx = context("+85513123456", MessagePriority::Low);
+        assert!(clauses.iter().all(|clause| clause.matches(&ctx)));
+
+        let clauses = parse_condition("client_tier != business").unwrap();
+        assert!(!clauses[0].matches(&ctx));
+    }
+
+    #[test]
+    fn rejects_malformed_conditions() {
+        assert!(parse_condition("").is_err());
+        assert!(parse_condition("region = phnom-penh").is_err());
+        assert!(parse_condition("carrier = vodafone").is_err());
+        assert!(parse_condition("prefix = 85513").is_err());
+        assert!(parse_condition("carrier in smart").is_err());
+        assert!(parse_condition("priority").is_err());
+    }
+}
@@ -0,0 +1,29 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A client's named message filter preset
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedFilter {
+    pub id: String,
+    pub client_id: String,
+    pub name: String,
+    pub status: Option<String>,
+    pub tags: Vec<String>, // messages must carry all of these
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl SavedFilter {
+    pub fn new(client_id: String, name: String, status: Option<String>, tags: Vec<String>) -> Self {
+        let now = crate::shared::utils::now();
+        Self {
+            id: crate::shared::utils::generate_id(),
+            client_id,
+            name,
+            status,
+            tags,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
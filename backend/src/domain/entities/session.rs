@@ -0,0 +1,78 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::shared::types::PhoneNumber;
+
+/// A signed-in device: started by an OTP login and kept alive by refreshes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserSession {
+    pub id: String, // the `sid` claim on tokens issued to this device
+    pub user_id: String,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+/// Authentication activity recorded in `auth_events`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthEventKind {
+    OtpVerified,
+    OtpFailed,
+    AccountLocked,
+    TokenRefreshed,
+    RefreshFailed,
+    Logout,
+    LogoutAll,
+    SessionRevoked,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthEvent {
+    pub id: String,
+    pub kind: AuthEventKind,
+    pub user_id: Option<String>,
+    pub phone: Option<String>, // masked; failed logins have no user to attach to
+    pub session_id: Option<String>,
+    pub success: bool,
+    pub reason: Option<String>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl AuthEvent {
+    pub fn new(kind: AuthEventKind, user_id: Option<String>, session_id: Option<String>) -> Self {
+        Self {
+            id: crate::shared::utils::generate_id(),
+            kind,
+            user_id,
+            phone: None,
+            session_id,
+            success: true,
+            reason: None,
+            ip_address: None,
+            user_agent: None,
+            created_at: crate::shared::utils::now(),
+        }
+    }
+
+    pub fn with_client(mut self, ip_address: Option<String>, user_agent: Option<String>) -> Self {
+        self.ip_address = ip_address;
+        self.user_agent = user_agent;
+        self
+    }
+
+    pub fn with_phone(mut self, phone: &PhoneNumber) -> Self {
+        self.phone = Some(phone.masked().as_str().to_string());
+        self
+    }
+
+    pub fn failed(mut self, reason: impl Into<String>) -> Self {
+        self.success = false;
+        self.reason = Some(reason.into());
+        self
+    }
+}
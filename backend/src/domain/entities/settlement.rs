@@ -0,0 +1,116 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// One transfer as reported in a Baray settlement report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettlementLine {
+    pub reference: String,
+    pub amount: f64,
+    pub currency: String,
+    pub status: String,
+    pub settled_at: Option<DateTime<Utc>>,
+}
+
+impl SettlementLine {
+    pub fn is_settled(&self) -> bool {
+        matches!(
+            self.status.to_lowercase().as_str(),
+            "settled" | "completed" | "success" | "paid"
+        )
+    }
+}
+
+/// An imported settlement report and the outcome of matching it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettlementReport {
+    pub id: String,
+    pub settlement_date: String, // YYYY-MM-DD
+    pub source: SettlementSource,
+    pub imported_by: Option<String>,
+    pub line_count: u32,
+    pub matched_count: u32,
+    pub discrepancy_count: u32,
+    pub imported_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SettlementSource {
+    Api,
+    Csv,
+}
+
+/// A difference between our payout records and Baray, awaiting admin review
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettlementDiscrepancy {
+    pub id: String,
+    pub report_id: String,
+    pub payout_id: Option<String>,
+    pub reference: Option<String>,
+    pub kind: DiscrepancyKind,
+    pub expected_amount: Option<f64>,
+    pub reported_amount: Option<f64>,
+    pub details: String,
+    pub status: DiscrepancyStatus,
+    pub resolution_note: Option<String>,
+    pub resolved_by: Option<String>,
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DiscrepancyKind {
+    AmountMismatch,
+    CurrencyMismatch,
+    StatusMismatch,
+    UnknownReference,  // Baray settled a transfer we have no payout for
+    MissingFromReport, // we recorded a payout Baray did not settle
+    LedgerMismatch,    // the ledger disagrees with the payout Baray settled
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DiscrepancyStatus {
+    Open,
+    Resolved,
+}
+
+impl SettlementReport {
+    pub fn new(settlement_date: String, source: SettlementSource, imported_by: Option<String>) -> Self {
+        Self {
+            id: crate::shared::utils::generate_id(),
+            settlement_date,
+            source,
+            imported_by,
+            line_count: 0,
+            matched_count: 0,
+            discrepancy_count: 0,
+            imported_at: crate::shared::utils::now(),
+        }
+    }
+}
+
+impl SettlementDiscrepancy {
+    pub fn new(report_id: String, kind: DiscrepancyKind, details: String) -> Self {
+        Self {
+            id: crate::shared::utils::generate_id(),
+            report_id,
+            payout_id: None,
+            reference: None,
+            kind,
+            expected_amount: None,
+            reported_amount: None,
+            details,
+            status: DiscrepancyStatus::Open,
+            resolution_note: None,
+            resolved_by: None,
+            resolved_at: None,
+            created_at: crate::shared::utils::now(),
+        }
+    }
+
+    pub fn resolve(&mut self, resolved_by: String, note: String) {
+        self.status = DiscrepancyStatus::Resolved;
+        self.resolution_note = Some(note);
+        self.resolved_by = Some(resolved_by);
+        self.resolved_at = Some(crate::shared::utils::now());
+    }
+}
@@ -0,0 +1,31 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::message::MessagePriority;
+use crate::shared::types::MessageStatus;
+
+/// A message found still undelivered past its priority's delivery SLA
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlaBreach {
+    pub id: String,
+    pub message_id: String,
+    pub client_id: String,
+    pub priority: MessagePriority,
+    pub status: MessageStatus, // when the breach was detected
+    pub target_seconds: u64,
+    pub due_at: DateTime<Utc>,
+    pub detected_at: DateTime<Utc>,
+    pub escalated_to: Option<u32>, // priority score its queued job was bumped to
+    pub client_notified: bool,
+}
+
+/// Share of one priority's messages delivered within the SLA over a window
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlaAttainment {
+    pub priority: MessagePriority,
+    pub target_seconds: u64,
+    pub messages: u64, // delivered, failed, or breached while still open
+    pub delivered_within_sla: u64,
+    pub breached: u64,
+    pub attainment_percent: f64,
+}
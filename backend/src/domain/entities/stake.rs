@@ -0,0 +1,66 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// PPT a provider has locked in the staking contract from their wallet, as
+/// last read from the chain, and the limits it unlocks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderStake {
+    pub wallet_address: String,
+    pub amount: f64,             // PPT
+    pub max_daily_messages: u32, // unlocked by the stake tier; 0 below the lowest tier
+    pub priority: bool,          // preferred in job assignment
+    pub synced_at: DateTime<Utc>,
+}
+
+/// Part of a provider's stake taken for a delivery confirmation found to be fraudulent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StakeSlash {
+    pub id: String,
+    pub provider_id: String,
+    pub message_id: String, // the fraudulent confirmation
+    pub wallet_address: String,
+    pub amount: f64, // PPT
+    pub reason: String,
+    pub slashed_by: String,
+    pub status: StakeSlashStatus,
+    pub nonce: Option<u64>,
+    pub tx_hash: Option<String>,
+    pub failure_reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StakeSlashStatus {
+    Pending,   // recorded, not yet broadcast
+    Submitted, // broadcast to the staking contract
+    Failed,    // refused by the node; the stake is untouched
+}
+
+impl StakeSlash {
+    pub fn new(
+        provider_id: String,
+        message_id: String,
+        wallet_address: String,
+        amount: f64,
+        reason: String,
+        slashed_by: String,
+    ) -> Self {
+        let now = crate::shared::utils::now();
+        Self {
+            id: crate::shared::utils::generate_id(),
+            provider_id,
+            message_id,
+            wallet_address,
+            amount,
+            reason,
+            slashed_by,
+            status: StakeSlashStatus::Pending,
+            nonce: None,
+            tx_hash: None,
+            failure_reason: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
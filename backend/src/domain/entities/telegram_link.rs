@@ -0,0 +1,17 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::shared::types::PhoneNumber;
+
+/// A phone number confirmed by sharing it with the PeerPower Telegram bot.
+///
+/// Bots can't message a number directly, so OTPs go to the chat the user
+/// linked by sharing their own contact.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TelegramLink {
+    #[serde(with = "crate::shared::field_encryption::encrypted_phone")]
+    pub phone: PhoneNumber,
+    pub chat_id: i64,
+    pub telegram_user_id: i64,
+    pub linked_at: DateTime<Utc>,
+}
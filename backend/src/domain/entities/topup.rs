@@ -0,0 +1,54 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::payout::PayoutCurrency;
+use crate::shared::Money;
+
+/// A client buying PPT credit through a Baray checkout session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopUp {
+    pub id: String, // also the reference Baray echoes back in webhooks
+    pub user_id: String,
+    pub amount: f64, // what the client pays, in `currency`
+    pub currency: PayoutCurrency,
+    pub credit_ppt: Money, // credited to the wallet once paid
+    pub rate: f64,         // `currency` units per PPT when the session was created
+    pub status: TopUpStatus,
+    pub baray_session_id: Option<String>,
+    pub checkout_url: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub failure_reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TopUpStatus {
+    Pending,
+    Completed,
+    Failed,
+    Expired,
+}
+
+impl TopUp {
+    pub fn new(user_id: String, amount: f64, currency: PayoutCurrency, rate: f64) -> Self {
+        let now = crate::shared::utils::now();
+        Self {
+            id: crate::shared::utils::generate_id(),
+            user_id,
+            amount,
+            currency,
+            credit_ppt: Money::from_ppt(PayoutCurrency::Ppt.round(amount / rate)),
+            rate,
+            status: TopUpStatus::Pending,
+            baray_session_id: None,
+            checkout_url: None,
+            expires_at: None,
+            failure_reason: None,
+            created_at: now,
+            updated_at: now,
+            completed_at: None,
+        }
+    }
+}
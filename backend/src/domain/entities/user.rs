@@ -1,6 +1,8 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use crate::shared::types::{PhoneNumber, Carrier, ProviderStatus};
+use super::quality_score::QualitySla;
+use super::quiet_hours::QuietHours;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct User {
@@ -11,8 +13,40 @@ pub struct User {
     pub reputation_score: f64,
     pub is_provider: bool,
     pub is_verified: bool,
+    #[serde(default)]
+    pub quality_sla: Option<QualitySla>,
+    #[serde(default)]
+    pub tier: ClientTier,
+    #[serde(default)]
+    pub recipient_privacy: bool, // store recipients encrypted, index only a hash
+    #[serde(default)]
+    pub pricing_plan_id: Option<String>, // none: the configured base message cost
+    #[serde(default)]
+    pub quiet_hours: Option<QuietHours>, // none: the system-wide quiet hours
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    #[serde(default)]
+    pub deleted_at: Option<DateTime<Utc>>, // soft-deleted; purged after the retention window
+}
+
+/// Commercial tier of a messaging client
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ClientTier {
+    #[default]
+    Standard,
+    Business,
+    Enterprise,
+}
+
+impl ClientTier {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "standard" => Some(ClientTier::Standard),
+            "business" => Some(ClientTier::Business),
+            "enterprise" => Some(ClientTier::Enterprise),
+            _ => None,
+        }
+    }
 }
 
 impl User {
@@ -26,8 +60,14 @@ impl User {
             reputation_score: 0.0,
             is_provider: false,
             is_verified: false,
+            quality_sla: None,
+            tier: ClientTier::default(),
+            recipient_privacy: false,
+            pricing_plan_id: None,
+            quiet_hours: None,
             created_at: now,
             updated_at: now,
+            deleted_at: None,
         }
     }
 
@@ -0,0 +1,44 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::shared::Money;
+
+/// A provider's bonus for one month's delivery volume, credited by the
+/// daily rollup once the month is over
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VolumeBonus {
+    pub id: String,
+    pub provider_id: String,
+    pub period: String, // local "YYYY-MM"
+    pub deliveries: u64,
+    pub base_earnings: Money, // the bonus is a percentage of this, before off-peak boosts
+    pub bonus_percent: f64,
+    pub amount: Money,
+    pub created_at: DateTime<Utc>,
+}
+
+impl VolumeBonus {
+    pub fn new(
+        provider_id: String,
+        period: String,
+        deliveries: u64,
+        base_earnings: Money,
+        bonus_percent: f64,
+    ) -> Self {
+        Self {
+            id: crate::shared::utils::generate_id(),
+            provider_id,
+            period,
+            deliveries,
+            base_earnings,
+            bonus_percent,
+            amount: base_earnings.percent(bonus_percent),
+            created_at: crate::shared::utils::now(),
+        }
+    }
+
+    /// Ledger reference: one bonus per provider per month
+    pub fn reference(&self) -> String {
+        format!("{}:{}", self.provider_id, self.period)
+    }
+}
@@ -0,0 +1,48 @@
+use thiserror::Error;
+
+use crate::shared::Money;
+
+/// Business-rule failures raised by entities and services.
+///
+/// These carry no transport concerns; `PeerPowerError` wraps them and picks
+/// the HTTP status at the presentation boundary. Callers that need to react
+/// (requeue, compensate, pick another provider) match on the variant instead
+/// of parsing messages.
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum DomainError {
+    #[error("Quota exceeded for {resource}: limit {limit}")]
+    QuotaExceeded { resource: String, limit: u64 },
+
+    #[error("Insufficient balance: requested {requested} PPT, available {available} PPT")]
+    InsufficientBalance { requested: Money, available: Money },
+
+    #[error("Illegal {entity} state transition: {from} -> {to}")]
+    IllegalStateTransition {
+        entity: String,
+        from: String,
+        to: String,
+    },
+}
+
+impl DomainError {
+    pub fn illegal_transition(
+        entity: &str,
+        from: impl std::fmt::Debug,
+        to: impl std::fmt::Debug,
+    ) -> Self {
+        DomainError::IllegalStateTransition {
+            entity: entity.to_string(),
+            from: format!("{:?}", from),
+            to: format!("{:?}", to),
+        }
+    }
+
+    /// Whether the same operation may succeed later without any change
+    /// from the caller (e.g. once a daily quota resets)
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, DomainError::QuotaExceeded { .. })
+    }
+}
+
+/// Result type for domain operations
+pub type DomainResult<T> = std::result::Result<T, DomainError>;
@@ -1,8 +1,10 @@
 pub mod entities;
+pub mod errors;
 pub mod repositories;
 pub mod services;
 
 // Re-export common types
 pub use entities::*;
+pub use errors::*;
 pub use repositories::*;
 pub use services::*;
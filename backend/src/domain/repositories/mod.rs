@@ -10,7 +10,9 @@ pub trait UserRepository: Send + Sync {
     async fn find_by_phone(&self, phone: &PhoneNumber) -> Result<Option<User>>;
     async fn find_by_did(&self, did: &str) -> Result<Option<User>>;
     async fn update(&self, user: &User) -> Result<()>;
+    /// Soft delete: the user is kept, marked deleted, until purged
     async fn delete(&self, id: &str) -> Result<()>;
+    async fn restore(&self, id: &str) -> Result<()>;
 }
 
 #[async_trait]
@@ -23,7 +25,9 @@ pub trait ProviderRepository: Send + Sync {
     async fn update(&self, provider: &Provider) -> Result<()>;
     async fn update_status(&self, id: &str, status: ProviderStatus) -> Result<()>;
     async fn update_heartbeat(&self, id: &str) -> Result<()>;
+    /// Soft delete: the provider is kept, marked deleted, until purged
     async fn delete(&self, id: &str) -> Result<()>;
+    async fn restore(&self, id: &str) -> Result<()>;
     async fn find_stale_providers(&self, minutes: i64) -> Result<Vec<Provider>>;
 }
 
@@ -36,7 +40,9 @@ pub trait MessageRepository: Send + Sync {
     async fn find_by_status(&self, status: &MessageStatus) -> Result<Vec<Message>>;
     async fn update(&self, message: &Message) -> Result<()>;
     async fn update_status(&self, id: &str, status: MessageStatus) -> Result<()>;
+    /// Soft delete: the message is kept, marked deleted, until purged
     async fn delete(&self, id: &str) -> Result<()>;
+    async fn restore(&self, id: &str) -> Result<()>;
     async fn find_expired_messages(&self) -> Result<Vec<Message>>;
     async fn count_by_client_today(&self, client_id: &str) -> Result<i64>;
 }
@@ -1,3 +1,5 @@
+use crate::domain::entities::{ApiClient, User};
+use crate::domain::services::OtpChannelKind;
 use crate::shared::types::PhoneNumber;
 use crate::shared::Result;
 use async_trait::async_trait;
@@ -7,11 +9,59 @@ use serde::{Deserialize, Serialize};
 /// Authentication service for managing user sessions and tokens
 #[async_trait]
 pub trait AuthService: Send + Sync {
-    async fn send_otp(&self, phone: &PhoneNumber) -> Result<String>;
-    async fn verify_otp(&self, phone: &PhoneNumber, otp: &str) -> Result<AuthToken>;
+    /// Send a code over `channel`, falling back to SMS and then a voice call
+    async fn send_otp(&self, phone: &PhoneNumber, channel: OtpChannelKind) -> Result<OtpDispatch>;
+    /// Explicitly re-send the current code, subject to the resend cooldown
+    async fn resend_otp(&self, phone: &PhoneNumber, channel: OtpChannelKind)
+        -> Result<OtpDispatch>;
+    /// Verify a login code and issue tokens for `app` (defaults to the
+    /// provider app for provider accounts, the client app otherwise)
+    async fn verify_otp(
+        &self,
+        phone: &PhoneNumber,
+        otp: &str,
+        app: Option<TokenAudience>,
+    ) -> Result<AuthToken>;
     async fn refresh_token(&self, refresh_token: &str) -> Result<AuthToken>;
-    async fn revoke_token(&self, token: &str) -> Result<()>;
+    /// Invalidate a refresh token, returning the session it belonged to
+    async fn revoke_token(&self, token: &str) -> Result<Option<String>>;
     async fn validate_token(&self, token: &str) -> Result<TokenClaims>;
+    /// Blacklist a single access token until it would have expired
+    async fn revoke_access_token(&self, claims: &TokenClaims) -> Result<()>;
+    /// Revoke every access and refresh token issued to a user so far,
+    /// returning how many refresh tokens were invalidated
+    async fn revoke_all_tokens(&self, user_id: &str) -> Result<usize>;
+    /// Sign out one device: drop its refresh tokens and reject its access
+    /// tokens, returning how many refresh tokens were invalidated
+    async fn revoke_session(&self, user_id: &str, session_id: &str) -> Result<usize>;
+    /// Issue an access token for a machine client (OAuth2 client_credentials)
+    async fn issue_client_token(
+        &self,
+        client: &ApiClient,
+        scopes: &[String],
+    ) -> Result<ClientAccessToken>;
+    /// Issue a short-lived access token that lets an admin act as a user for
+    /// support. It carries the admin in `act` and has no refresh token.
+    async fn issue_impersonation_token(
+        &self,
+        user: &User,
+        admin_id: &str,
+        audience: TokenAudience,
+    ) -> Result<ImpersonationToken>;
+
+    /// Send a one-off verification code for a purpose other than login
+    /// (e.g. provider carrier re-verification)
+    async fn send_verification_code(&self, phone: &PhoneNumber, purpose: &str) -> Result<()>;
+    async fn confirm_verification_code(
+        &self,
+        phone: &PhoneNumber,
+        purpose: &str,
+        code: &str,
+    ) -> Result<()>;
+
+    /// Login code currently outstanding for a phone, with its expiry
+    /// (non-production debug hook only)
+    async fn pending_otp(&self, phone: &PhoneNumber) -> Result<Option<(String, DateTime<Utc>)>>;
 }
 
 /// JWT token response
@@ -22,6 +72,9 @@ pub struct AuthToken {
     pub token_type: String,
     pub expires_in: i64,
     pub user_id: String,
+    pub session_id: String,
+    #[serde(default)]
+    pub new_user: bool, // the OTP just verified created the account
 }
 
 /// JWT token claims
@@ -32,6 +85,124 @@ pub struct TokenClaims {
     pub iat: i64,          // issued at
     pub exp: i64,          // expires at
     pub is_provider: bool, // provider status
+    #[serde(default)]
+    pub jti: String, // unique token ID, used for revocation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>, // space-delimited OAuth scopes (machine clients only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>, // machine client the token was issued to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>, // device session (user logins only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aud: Option<TokenAudience>, // app the token was issued to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>, // deployment that issued the token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub act: Option<String>, // admin acting as the user (support impersonation)
+}
+
+impl TokenClaims {
+    /// Tokens issued before audiences existed carry none and are accepted
+    /// everywhere until they expire
+    pub fn allows(&self, audience: TokenAudience) -> bool {
+        self.aud.map_or(true, |aud| aud == audience)
+    }
+
+    pub fn is_machine_client(&self) -> bool {
+        self.client_id.is_some()
+    }
+
+    pub fn is_impersonation(&self) -> bool {
+        self.act.is_some()
+    }
+
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scope
+            .as_deref()
+            .is_some_and(|scopes| scopes.split_whitespace().any(|granted| granted == scope))
+    }
+}
+
+/// App a token is issued to. A leaked token only works against the
+/// endpoints of its own app: messaging for clients, jobs and earnings for
+/// providers, and the admin API for the admin console.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenAudience {
+    Client,
+    Provider,
+    Admin,
+}
+
+impl TokenAudience {
+    pub const ALL: [TokenAudience; 3] = [
+        TokenAudience::Client,
+        TokenAudience::Provider,
+        TokenAudience::Admin,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenAudience::Client => "client",
+            TokenAudience::Provider => "provider",
+            TokenAudience::Admin => "admin",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|audience| audience.as_str() == name)
+    }
+
+    /// App a login gets when it doesn't ask for one
+    pub fn default_for(is_provider: bool) -> Self {
+        if is_provider {
+            TokenAudience::Provider
+        } else {
+            TokenAudience::Client
+        }
+    }
+}
+
+/// Access token issued through the client_credentials grant
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClientAccessToken {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: i64,
+    pub scope: String,
+}
+
+/// Access token minted for support impersonation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImpersonationToken {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: i64,
+    pub user_id: String,
+    pub impersonated_by: String,
+}
+
+/// Outcome of an OTP send or resend request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OtpDispatch {
+    pub expires_at: DateTime<Utc>,
+    pub resend_available_at: DateTime<Utc>,
+    /// True when the request was folded into a code sent moments earlier
+    pub coalesced: bool,
+    /// Channel the code actually went out on
+    pub channel: OtpChannelKind,
+}
+
+impl OtpDispatch {
+    pub fn expires_in_seconds(&self) -> i64 {
+        (self.expires_at - Utc::now()).num_seconds().max(0)
+    }
+
+    pub fn resend_cooldown_seconds(&self) -> i64 {
+        (self.resend_available_at - Utc::now()).num_seconds().max(0)
+    }
 }
 
 /// OTP verification data
@@ -42,6 +213,10 @@ pub struct OtpData {
     pub created_at: DateTime<Utc>,
     pub expires_at: DateTime<Utc>,
     pub attempts: u32,
+    #[serde(default)]
+    pub last_sent_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub channel: OtpChannelKind,
 }
 
 impl OtpData {
@@ -53,9 +228,20 @@ impl OtpData {
             created_at: now,
             expires_at: now + chrono::Duration::minutes(ttl_minutes),
             attempts: 0,
+            last_sent_at: Some(now),
+            channel: OtpChannelKind::default(),
         }
     }
 
+    /// Seconds since the code was last sent to the phone
+    pub fn seconds_since_sent(&self) -> i64 {
+        (Utc::now() - self.last_sent_at.unwrap_or(self.created_at)).num_seconds()
+    }
+
+    pub fn mark_resent(&mut self) {
+        self.last_sent_at = Some(Utc::now());
+    }
+
     pub fn is_expired(&self) -> bool {
         Utc::now() > self.expires_at
     }
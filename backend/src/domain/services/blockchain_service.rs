@@ -0,0 +1,50 @@
+use async_trait::async_trait;
+use serde::Serialize;
+
+use crate::shared::Result;
+
+/// A transaction signed with the platform key, not necessarily broadcast yet.
+/// Its hash is fixed at signing, so callers can record it before sending.
+#[derive(Debug, Clone)]
+pub struct PreparedTransaction {
+    pub hash: String,
+    pub nonce: u64,
+    pub raw: Vec<u8>,
+}
+
+/// Where a broadcast transaction stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionStatus {
+    Pending,   // not mined, or mined with too few confirmations
+    Confirmed, // mined successfully and buried deep enough
+    Reverted,
+    Dropped, // its nonce was used by another transaction; it will never be mined
+}
+
+/// Sends and watches transactions from the platform's Selendra account.
+///
+/// Implementations own nonce assignment, gas estimation and retries of
+/// transient RPC failures, so settlement and DID features only build call data.
+#[async_trait]
+pub trait BlockchainService: Send + Sync {
+    fn is_configured(&self) -> bool;
+
+    /// The platform account transactions are sent from
+    fn address(&self) -> Option<String>;
+
+    /// Assign the next nonce, estimate gas and sign a call to `to`
+    async fn prepare(&self, to: &str, data: &[u8]) -> Result<PreparedTransaction>;
+
+    /// Sign a transfer of `amount` PPT tokens to `to`
+    async fn prepare_token_transfer(&self, to: &str, amount: f64) -> Result<PreparedTransaction>;
+
+    /// Broadcast a prepared transaction; safe to repeat. A `BlockchainError`
+    /// means the node refused it and its nonce will be reused.
+    async fn broadcast(&self, transaction: &PreparedTransaction) -> Result<()>;
+
+    async fn status(&self, tx_hash: &str, nonce: u64) -> Result<TransactionStatus>;
+
+    /// Read-only contract call, returning the raw result
+    async fn call(&self, to: &str, data: &[u8]) -> Result<Vec<u8>>;
+}
@@ -0,0 +1,42 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::domain::entities::message::MessagePriority;
+use crate::shared::types::Carrier;
+use crate::shared::Result;
+
+/// Network conditions a delivery-time estimate is based on
+#[derive(Debug, Clone, Serialize)]
+pub struct DeliveryFeatures {
+    pub carrier: Carrier,
+    pub priority: MessagePriority,
+    pub queue_depth: u64,      // jobs waiting across all priority queues
+    pub online_providers: u32, // online providers on the recipient's carrier
+    pub carrier_success_rate: Option<f64>, // recent delivered / (delivered + failed)
+    pub carrier_median_latency_seconds: Option<f64>,
+    pub local_hour: u32, // hour of day in Cambodia, 0-23
+}
+
+/// Expected time from submission to delivery
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeliveryEstimate {
+    pub expected_seconds: i64,
+    pub p90_seconds: i64,
+    #[serde(default)]
+    pub model: String,
+}
+
+impl DeliveryEstimate {
+    pub fn expected_at(&self, submitted_at: DateTime<Utc>) -> DateTime<Utc> {
+        submitted_at + chrono::Duration::seconds(self.expected_seconds)
+    }
+}
+
+/// Turns network conditions into a delivery-time estimate
+#[async_trait]
+pub trait DeliveryTimeModel: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    async fn predict(&self, features: &DeliveryFeatures) -> Result<DeliveryEstimate>;
+}
@@ -0,0 +1,14 @@
+use crate::domain::entities::{Message, Provider};
+
+/// Decides which provider a message is dispatched to.
+///
+/// The dispatcher hands over every provider eligible for the message, of
+/// any carrier, with routing rules already applied; a strategy only orders
+/// preferences among them, so it can be tested without a database.
+pub trait DispatchStrategy: Send + Sync {
+    /// Short name for config, logs and metrics
+    fn name(&self) -> &'static str;
+
+    /// Pick one of `candidates` for `message`, if any
+    fn select<'a>(&self, candidates: &'a [Provider], message: &Message) -> Option<&'a Provider>;
+}
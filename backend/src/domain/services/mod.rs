@@ -1,3 +1,11 @@
 pub mod auth_service;
+pub mod blockchain_service;
+pub mod delivery_prediction;
+pub mod dispatch_strategy;
+pub mod otp_channel;
 
 pub use auth_service::*;
+pub use blockchain_service::*;
+pub use delivery_prediction::*;
+pub use dispatch_strategy::*;
+pub use otp_channel::*;
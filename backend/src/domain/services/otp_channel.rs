@@ -0,0 +1,49 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::shared::types::PhoneNumber;
+use crate::shared::Result;
+
+/// Ways an OTP can reach the user
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OtpChannelKind {
+    #[default]
+    Sms,
+    Telegram,
+    Voice,
+}
+
+impl OtpChannelKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OtpChannelKind::Sms => "sms",
+            OtpChannelKind::Telegram => "telegram",
+            OtpChannelKind::Voice => "voice",
+        }
+    }
+
+    /// Channels to try, starting with this one; SMS then a voice call are
+    /// always the fallbacks
+    pub fn fallback_order(self) -> Vec<OtpChannelKind> {
+        let mut order = vec![self];
+        for fallback in [OtpChannelKind::Sms, OtpChannelKind::Voice] {
+            if !order.contains(&fallback) {
+                order.push(fallback);
+            }
+        }
+        order
+    }
+}
+
+/// Delivers OTP codes over one channel
+#[async_trait]
+pub trait OtpChannel: Send + Sync {
+    fn kind(&self) -> OtpChannelKind;
+
+    /// Whether this channel is set up and can reach `phone`
+    async fn can_reach(&self, phone: &PhoneNumber) -> Result<bool>;
+
+    /// Send the code, returning a delivery reference for the logs
+    async fn send_code(&self, phone: &PhoneNumber, code: &str, ttl_minutes: i64) -> Result<String>;
+}
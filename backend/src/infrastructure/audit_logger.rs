@@ -0,0 +1,52 @@
+use mongodb::{Collection, Database};
+use std::future::Future;
+use std::sync::Arc;
+use tracing::error;
+
+use crate::domain::entities::AuditLogEntry;
+use crate::shared::{PeerPowerError, Result};
+
+tokio::task_local! {
+    /// Admin behind the current request when it carries an impersonation token
+    static IMPERSONATOR: String;
+}
+
+/// Run a request on behalf of an impersonating admin; every audit entry it
+/// records is tagged with `impersonated_by`
+pub async fn with_impersonator<F: Future>(admin_id: String, request: F) -> F::Output {
+    IMPERSONATOR.scope(admin_id, request).await
+}
+
+/// Append-only writer for the `audit_log` collection
+pub struct AuditLogger {
+    collection: Collection<AuditLogEntry>,
+}
+
+impl AuditLogger {
+    pub fn new(database: Arc<Database>) -> Self {
+        Self {
+            collection: database.collection("audit_log"),
+        }
+    }
+
+    pub async fn record(&self, mut entry: AuditLogEntry) -> Result<()> {
+        if let Ok(admin_id) = IMPERSONATOR.try_with(String::clone) {
+            entry = entry.with_metadata("impersonated_by", admin_id);
+        }
+        self.collection
+            .insert_one(&entry, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to write audit log entry: {}", e),
+            })?;
+        Ok(())
+    }
+
+    /// Record an entry without failing the calling request
+    pub async fn record_best_effort(&self, entry: AuditLogEntry) {
+        let action = entry.action.clone();
+        if let Err(e) = self.record(entry).await {
+            error!("Failed to record audit event {}: {}", action, e);
+        }
+    }
+}
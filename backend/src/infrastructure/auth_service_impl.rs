@@ -1,24 +1,29 @@
 use async_trait::async_trait;
-use chrono::{Duration, Utc};
-use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use chrono::{DateTime, Duration, Utc};
 use rand::Rng;
 use std::sync::Arc;
 use tracing::{info, warn};
 
 use crate::config::AuthConfig;
-use crate::domain::entities::User;
+use crate::domain::entities::{ApiClient, User};
 use crate::domain::repositories::UserRepository;
-use crate::domain::services::{AuthService, AuthToken, OtpData, TokenClaims};
+use crate::domain::services::{
+    AuthService, AuthToken, ClientAccessToken, ImpersonationToken, OtpChannel, OtpChannelKind,
+    OtpData, OtpDispatch, TokenAudience, TokenClaims,
+};
 use crate::infrastructure::database::RedisConnection;
+use crate::infrastructure::jwt_keys::JwtKeySet;
 use crate::shared::types::PhoneNumber;
 use crate::shared::{PeerPowerError, Result};
 
+const REFRESH_TOKEN_TTL_SECONDS: usize = 30 * 24 * 3600;
+
 pub struct AuthServiceImpl {
     config: AuthConfig,
     redis: Arc<RedisConnection>,
     user_repo: Arc<dyn UserRepository>,
-    encoding_key: EncodingKey,
-    decoding_key: DecodingKey,
+    jwt_keys: Arc<JwtKeySet>,
+    otp_channels: Vec<Arc<dyn OtpChannel>>,
 }
 
 impl AuthServiceImpl {
@@ -26,19 +31,77 @@ impl AuthServiceImpl {
         config: AuthConfig,
         redis: Arc<RedisConnection>,
         user_repo: Arc<dyn UserRepository>,
+        jwt_keys: Arc<JwtKeySet>,
+        otp_channels: Vec<Arc<dyn OtpChannel>>,
     ) -> Self {
-        let encoding_key = EncodingKey::from_secret(config.jwt_secret.as_ref());
-        let decoding_key = DecodingKey::from_secret(config.jwt_secret.as_ref());
-
         Self {
             config,
             redis,
             user_repo,
-            encoding_key,
-            decoding_key,
+            jwt_keys,
+            otp_channels,
         }
     }
 
+    /// Send the code over the preferred channel, falling back through the
+    /// others, and record which one was used
+    async fn deliver_otp(&self, otp_data: &mut OtpData, preferred: OtpChannelKind) -> Result<()> {
+        let ttl_minutes = (otp_data.expires_at - Utc::now()).num_minutes().max(1);
+        let mut last_error = None;
+
+        for kind in preferred.fallback_order() {
+            let Some(channel) = self.otp_channels.iter().find(|c| c.kind() == kind) else {
+                continue;
+            };
+            match channel.can_reach(&otp_data.phone).await {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(e) => {
+                    warn!("Could not check OTP channel {}: {}", kind.as_str(), e);
+                    last_error = Some(e);
+                    continue;
+                }
+            }
+
+            match channel
+                .send_code(&otp_data.phone, &otp_data.code, ttl_minutes)
+                .await
+            {
+                Ok(reference) => {
+                    info!(
+                        "OTP for {} sent via {} ({})",
+                        otp_data.phone.as_str(),
+                        kind.as_str(),
+                        reference
+                    );
+                    metrics::counter!("otp_channel_deliveries_total", "channel" => kind.as_str())
+                        .increment(1);
+                    otp_data.channel = kind;
+                    return Ok(());
+                }
+                Err(e) => {
+                    warn!("OTP delivery via {} failed: {}", kind.as_str(), e);
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        let error = last_error.unwrap_or_else(|| PeerPowerError::ProviderUnavailable {
+            carrier: "any (no OTP channel can reach this number)".to_string(),
+        });
+        // Development builds use a fixed code, so signup works without channels
+        if cfg!(debug_assertions) {
+            warn!(
+                "OTP delivery failed ({}); development code for {} is {}",
+                error,
+                otp_data.phone.as_str(),
+                otp_data.code
+            );
+            return Ok(());
+        }
+        Err(error)
+    }
+
     fn generate_otp(&self) -> String {
         // For development/testing, use a fixed OTP
         if cfg!(debug_assertions) {
@@ -53,6 +116,43 @@ impl AuthServiceImpl {
         format!("otp:{}", phone.as_str())
     }
 
+    fn purpose_otp_key(&self, phone: &PhoneNumber, purpose: &str) -> String {
+        format!("otp:{}:{}", purpose, phone.as_str())
+    }
+
+    fn user_refresh_tokens_key(&self, user_id: &str) -> String {
+        format!("refresh_tokens:{}", user_id)
+    }
+
+    fn refresh_session_key(&self, refresh_token: &str) -> String {
+        format!("refresh_token_session:{}", refresh_token)
+    }
+
+    fn refresh_audience_key(&self, refresh_token: &str) -> String {
+        format!("refresh_token_audience:{}", refresh_token)
+    }
+
+    fn session_refresh_tokens_key(&self, session_id: &str) -> String {
+        format!("session_refresh_tokens:{}", session_id)
+    }
+
+    fn revoked_session_key(&self, session_id: &str) -> String {
+        format!("session_revoked:{}", session_id)
+    }
+
+    fn blacklist_key(&self, jti: &str) -> String {
+        format!("token_blacklist:{}", jti)
+    }
+
+    fn revoked_before_key(&self, user_id: &str) -> String {
+        format!("tokens_revoked_before:{}", user_id)
+    }
+
+    /// Longest lifetime of any access token we issue
+    fn max_access_token_lifetime_seconds(&self) -> i64 {
+        (self.config.jwt_expiration_hours * 3600).max(self.config.client_token_max_lifetime_seconds)
+    }
+
     fn rate_limit_key(&self, phone: &PhoneNumber) -> String {
         format!("rate_limit:otp:{}", phone.as_str())
     }
@@ -77,13 +177,17 @@ impl AuthServiceImpl {
 
     async fn store_otp(&self, otp_data: &OtpData) -> Result<()> {
         let key = self.otp_key(&otp_data.phone);
+        self.store_otp_at(&key, otp_data).await
+    }
+
+    async fn store_otp_at(&self, key: &str, otp_data: &OtpData) -> Result<()> {
         let value = serde_json::to_string(otp_data).map_err(|e| PeerPowerError::Internal {
             message: format!("Failed to serialize OTP data: {}", e),
         })?;
 
         self.redis
             .set(
-                &key,
+                key,
                 &value,
                 Some(self.config.otp_expiration_minutes as usize * 60),
             )
@@ -94,7 +198,11 @@ impl AuthServiceImpl {
 
     async fn get_otp(&self, phone: &PhoneNumber) -> Result<Option<OtpData>> {
         let key = self.otp_key(phone);
-        let value = self.redis.get(&key).await?;
+        self.get_otp_at(&key).await
+    }
+
+    async fn get_otp_at(&self, key: &str) -> Result<Option<OtpData>> {
+        let value = self.redis.get(key).await?;
 
         match value {
             Some(data) => {
@@ -108,13 +216,29 @@ impl AuthServiceImpl {
         }
     }
 
+    fn otp_dispatch(&self, otp_data: &OtpData, coalesced: bool) -> OtpDispatch {
+        let last_sent = otp_data.last_sent_at.unwrap_or(otp_data.created_at);
+        OtpDispatch {
+            expires_at: otp_data.expires_at,
+            resend_available_at: last_sent
+                + Duration::seconds(self.config.otp_resend_cooldown_seconds),
+            coalesced,
+            channel: otp_data.channel,
+        }
+    }
+
     async fn delete_otp(&self, phone: &PhoneNumber) -> Result<()> {
         let key = self.otp_key(phone);
         self.redis.delete(&key).await?;
         Ok(())
     }
 
-    async fn generate_tokens(&self, user: &User) -> Result<AuthToken> {
+    async fn generate_tokens(
+        &self,
+        user: &User,
+        session_id: String,
+        audience: TokenAudience,
+    ) -> Result<AuthToken> {
         let now = Utc::now();
         let exp = now + Duration::hours(self.config.jwt_expiration_hours);
 
@@ -124,22 +248,52 @@ impl AuthServiceImpl {
             iat: now.timestamp(),
             exp: exp.timestamp(),
             is_provider: user.is_provider,
+            jti: crate::shared::utils::generate_id(),
+            scope: None,
+            client_id: None,
+            sid: Some(session_id.clone()),
+            aud: Some(audience),
+            act: None,
+            iss: Some(self.config.jwt_issuer.clone()),
         };
 
-        let access_token =
-            encode(&Header::default(), &claims, &self.encoding_key).map_err(|e| {
-                PeerPowerError::Internal {
-                    message: format!("Failed to generate access token: {}", e),
-                }
-            })?;
+        let access_token = self.jwt_keys.sign(&claims)?;
 
         // Generate refresh token (simple UUID for now)
         let refresh_token = crate::shared::utils::generate_id();
 
-        // Store refresh token in Redis (valid for 30 days)
+        // Store refresh token in Redis (valid for 30 days), indexed per user
+        // so they can all be revoked at once
         let refresh_key = format!("refresh_token:{}", refresh_token);
         self.redis
-            .set(&refresh_key, &user.id, Some(30 * 24 * 3600))
+            .set(&refresh_key, &user.id, Some(REFRESH_TOKEN_TTL_SECONDS))
+            .await?;
+        let user_tokens_key = self.user_refresh_tokens_key(&user.id);
+        self.redis.sadd(&user_tokens_key, &refresh_token).await?;
+        self.redis
+            .expire(&user_tokens_key, REFRESH_TOKEN_TTL_SECONDS)
+            .await?;
+
+        // ...and per device session, so one device can be signed out
+        self.redis
+            .set(
+                &self.refresh_session_key(&refresh_token),
+                &session_id,
+                Some(REFRESH_TOKEN_TTL_SECONDS),
+            )
+            .await?;
+        // ...and remember which app it belongs to, so refreshes stay scoped
+        self.redis
+            .set(
+                &self.refresh_audience_key(&refresh_token),
+                audience.as_str(),
+                Some(REFRESH_TOKEN_TTL_SECONDS),
+            )
+            .await?;
+        let session_tokens_key = self.session_refresh_tokens_key(&session_id);
+        self.redis.sadd(&session_tokens_key, &refresh_token).await?;
+        self.redis
+            .expire(&session_tokens_key, REFRESH_TOKEN_TTL_SECONDS)
             .await?;
 
         Ok(AuthToken {
@@ -148,37 +302,92 @@ impl AuthServiceImpl {
             token_type: "Bearer".to_string(),
             expires_in: self.config.jwt_expiration_hours * 3600,
             user_id: user.id.clone(),
+            session_id,
+            new_user: false,
         })
     }
 }
 
 #[async_trait]
 impl AuthService for AuthServiceImpl {
-    async fn send_otp(&self, phone: &PhoneNumber) -> Result<String> {
+    async fn send_otp(&self, phone: &PhoneNumber, channel: OtpChannelKind) -> Result<OtpDispatch> {
         info!("Sending OTP to phone: {}", phone.as_str());
 
+        // A repeated request right after a send (e.g. double tap) reuses the
+        // outstanding code instead of invalidating it
+        if let Some(existing) = self.get_otp(phone).await? {
+            if !existing.is_expired()
+                && existing.seconds_since_sent() < self.config.otp_coalesce_seconds
+            {
+                info!("Coalescing duplicate OTP request for phone: {}", phone.as_str());
+                return Ok(self.otp_dispatch(&existing, true));
+            }
+        }
+
         // Check rate limiting
         self.check_rate_limit(phone).await?;
 
         // Generate OTP
         let otp_code = self.generate_otp();
-        let otp_data = OtpData::new(
+        let mut otp_data = OtpData::new(
             phone.clone(),
             otp_code.clone(),
             self.config.otp_expiration_minutes,
         );
 
-        // Store OTP in Redis
+        // Only store a code that actually went out, so a retry isn't
+        // coalesced into one the user never received
+        self.deliver_otp(&mut otp_data, channel).await?;
         self.store_otp(&otp_data).await?;
 
-        // TODO: Send actual SMS via provider
-        // For now, just log it (in production, integrate with SMS service)
-        info!("OTP for {}: {}", phone.as_str(), otp_code);
+        Ok(self.otp_dispatch(&otp_data, false))
+    }
+
+    async fn resend_otp(
+        &self,
+        phone: &PhoneNumber,
+        channel: OtpChannelKind,
+    ) -> Result<OtpDispatch> {
+        info!("Resending OTP to phone: {}", phone.as_str());
+
+        let existing = self.get_otp(phone).await?;
+        if let Some(existing) = &existing {
+            let remaining = self.otp_dispatch(existing, false).resend_cooldown_seconds();
+            if remaining > 0 {
+                return Err(PeerPowerError::RateLimitExceeded {
+                    resource: format!("OTP resend (retry in {} seconds)", remaining),
+                });
+            }
+        }
+
+        self.check_rate_limit(phone).await?;
+
+        // Re-send the outstanding code while it is still usable so an earlier
+        // message remains valid; otherwise issue a fresh one
+        let mut otp_data = match existing {
+            Some(mut otp_data) if !otp_data.is_expired() && otp_data.attempts < 3 => {
+                otp_data.mark_resent();
+                otp_data
+            }
+            _ => OtpData::new(
+                phone.clone(),
+                self.generate_otp(),
+                self.config.otp_expiration_minutes,
+            ),
+        };
+
+        self.deliver_otp(&mut otp_data, channel).await?;
+        self.store_otp(&otp_data).await?;
 
-        Ok("OTP sent successfully".to_string())
+        Ok(self.otp_dispatch(&otp_data, false))
     }
 
-    async fn verify_otp(&self, phone: &PhoneNumber, otp: &str) -> Result<AuthToken> {
+    async fn verify_otp(
+        &self,
+        phone: &PhoneNumber,
+        otp: &str,
+        app: Option<TokenAudience>,
+    ) -> Result<AuthToken> {
         info!("Verifying OTP for phone: {}", phone.as_str());
 
         // Get stored OTP
@@ -211,7 +420,14 @@ impl AuthService for AuthServiceImpl {
 
         // Find or create user
         info!("Looking up user by phone: {}", phone.as_str());
+        let mut new_user = false;
         let user = match self.user_repo.find_by_phone(phone).await? {
+            Some(user) if user.deleted_at.is_some() => {
+                // Held for the retention window; only an admin can restore it
+                return Err(PeerPowerError::AuthenticationFailed {
+                    reason: "This account has been deleted".to_string(),
+                });
+            }
             Some(user) => {
                 info!("Found existing user: {}", user.id);
                 // Update user verification status
@@ -226,13 +442,14 @@ impl AuthService for AuthServiceImpl {
             None => {
                 info!("User not found, creating new user");
                 // Create new user
-                let mut new_user = User::new(phone.clone());
-                new_user.verify();
-                info!("Created new user object with ID: {}", new_user.id);
+                let mut created_user = User::new(phone.clone());
+                created_user.verify();
+                info!("Created new user object with ID: {}", created_user.id);
 
-                match self.user_repo.create(&new_user).await {
+                match self.user_repo.create(&created_user).await {
                     Ok(()) => {
-                        info!("Successfully saved user to database: {}", new_user.id);
+                        info!("Successfully saved user to database: {}", created_user.id);
+                        new_user = true;
                     }
                     Err(e) => {
                         warn!("Failed to save user to database: {:?}", e);
@@ -257,8 +474,12 @@ impl AuthService for AuthServiceImpl {
             }
         };
 
-        // Generate tokens
-        let tokens = self.generate_tokens(&user).await?;
+        // Generate tokens for a new device session
+        let audience = app.unwrap_or_else(|| TokenAudience::default_for(user.is_provider));
+        let mut tokens = self
+            .generate_tokens(&user, crate::shared::utils::generate_id(), audience)
+            .await?;
+        tokens.new_user = new_user;
 
         info!("Successfully authenticated user: {}", user.id);
         Ok(tokens)
@@ -279,28 +500,318 @@ impl AuthService for AuthServiceImpl {
             }
         })?;
 
-        // Generate new tokens
-        self.generate_tokens(&user).await
+        // Generate new tokens, staying in the same device session (tokens
+        // from before sessions were tracked start a new one)
+        let session_id = self
+            .redis
+            .get(&self.refresh_session_key(refresh_token))
+            .await?
+            .unwrap_or_else(crate::shared::utils::generate_id);
+        let audience = self
+            .redis
+            .get(&self.refresh_audience_key(refresh_token))
+            .await?
+            .and_then(|name| TokenAudience::from_name(&name))
+            .unwrap_or_else(|| TokenAudience::default_for(user.is_provider));
+        self.generate_tokens(&user, session_id, audience).await
     }
 
-    async fn revoke_token(&self, token: &str) -> Result<()> {
-        // For JWT tokens, we'd typically add them to a blacklist
-        // For simplicity, we'll just delete the refresh token if provided
+    async fn revoke_token(&self, token: &str) -> Result<Option<String>> {
+        // Access tokens are revoked through `revoke_access_token`; this
+        // invalidates a refresh token
         let refresh_key = format!("refresh_token:{}", token);
+        if let Some(user_id) = self.redis.get(&refresh_key).await? {
+            self.redis
+                .srem(&self.user_refresh_tokens_key(&user_id), token)
+                .await?;
+        }
         self.redis.delete(&refresh_key).await?;
-        Ok(())
+        self.redis.delete(&self.refresh_audience_key(token)).await?;
+
+        let session_key = self.refresh_session_key(token);
+        let session_id = self.redis.get(&session_key).await?;
+        if let Some(session_id) = &session_id {
+            self.redis
+                .srem(&self.session_refresh_tokens_key(session_id), token)
+                .await?;
+        }
+        self.redis.delete(&session_key).await?;
+        Ok(session_id)
     }
 
     async fn validate_token(&self, token: &str) -> Result<TokenClaims> {
-        let validation = Validation::new(Algorithm::HS256);
+        let claims: TokenClaims = self.jwt_keys.verify(token)?;
 
-        let token_data =
-            decode::<TokenClaims>(token, &self.decoding_key, &validation).map_err(|e| {
-                PeerPowerError::AuthenticationFailed {
-                    reason: format!("Invalid token: {}", e),
-                }
-            })?;
+        let blacklisted = !claims.jti.is_empty()
+            && self
+                .redis
+                .get(&self.blacklist_key(&claims.jti))
+                .await?
+                .is_some();
+        if blacklisted {
+            return Err(PeerPowerError::AuthenticationFailed {
+                reason: "Token has been revoked".to_string(),
+            });
+        }
+
+        // Device sessions signed out from another device
+        if let Some(session_id) = &claims.sid {
+            if self
+                .redis
+                .get(&self.revoked_session_key(session_id))
+                .await?
+                .is_some()
+            {
+                return Err(PeerPowerError::AuthenticationFailed {
+                    reason: "Session has been revoked".to_string(),
+                });
+            }
+        }
+
+        // "Logout everywhere" cut-off: anything issued at or before it is dead
+        if let Some(revoked_before) = self
+            .redis
+            .get(&self.revoked_before_key(&claims.sub))
+            .await?
+            .and_then(|value| value.parse::<i64>().ok())
+        {
+            if claims.iat <= revoked_before {
+                return Err(PeerPowerError::AuthenticationFailed {
+                    reason: "Token has been revoked".to_string(),
+                });
+            }
+        }
+
+        Ok(claims)
+    }
+
+    async fn revoke_access_token(&self, claims: &TokenClaims) -> Result<()> {
+        if claims.jti.is_empty() {
+            return Err(PeerPowerError::ValidationError {
+                field: "token".to_string(),
+                message: "Token has no ID and cannot be revoked individually".to_string(),
+            });
+        }
+
+        // Keep the entry only as long as the token itself would be accepted
+        let remaining = claims.exp - Utc::now().timestamp();
+        if remaining > 0 {
+            self.redis
+                .set(
+                    &self.blacklist_key(&claims.jti),
+                    "1",
+                    Some(remaining as usize),
+                )
+                .await?;
+        }
+
+        info!(
+            "Revoked access token {} for user {}",
+            claims.jti, claims.sub
+        );
+        Ok(())
+    }
+
+    async fn revoke_all_tokens(&self, user_id: &str) -> Result<usize> {
+        self.redis
+            .set(
+                &self.revoked_before_key(user_id),
+                &Utc::now().timestamp().to_string(),
+                Some(self.max_access_token_lifetime_seconds() as usize),
+            )
+            .await?;
+
+        let user_tokens_key = self.user_refresh_tokens_key(user_id);
+        let refresh_tokens = self.redis.smembers(&user_tokens_key).await?;
+        for refresh_token in &refresh_tokens {
+            self.redis
+                .delete(&format!("refresh_token:{}", refresh_token))
+                .await?;
+        }
+        self.redis.delete(&user_tokens_key).await?;
+
+        info!(
+            "Revoked all tokens for user {} ({} refresh tokens)",
+            user_id,
+            refresh_tokens.len()
+        );
+        Ok(refresh_tokens.len())
+    }
+
+    async fn revoke_session(&self, user_id: &str, session_id: &str) -> Result<usize> {
+        // Outlives any access token the session could still be holding
+        self.redis
+            .set(
+                &self.revoked_session_key(session_id),
+                user_id,
+                Some(self.max_access_token_lifetime_seconds() as usize),
+            )
+            .await?;
+
+        let session_tokens_key = self.session_refresh_tokens_key(session_id);
+        let refresh_tokens = self.redis.smembers(&session_tokens_key).await?;
+        let user_tokens_key = self.user_refresh_tokens_key(user_id);
+        for refresh_token in &refresh_tokens {
+            self.redis
+                .delete(&format!("refresh_token:{}", refresh_token))
+                .await?;
+            self.redis
+                .delete(&self.refresh_session_key(refresh_token))
+                .await?;
+            self.redis.srem(&user_tokens_key, refresh_token).await?;
+        }
+        self.redis.delete(&session_tokens_key).await?;
+
+        info!(
+            "Revoked session {} for user {} ({} refresh tokens)",
+            session_id,
+            user_id,
+            refresh_tokens.len()
+        );
+        Ok(refresh_tokens.len())
+    }
+
+    async fn issue_client_token(
+        &self,
+        client: &ApiClient,
+        scopes: &[String],
+    ) -> Result<ClientAccessToken> {
+        let lifetime = client
+            .token_lifetime_seconds
+            .unwrap_or(self.config.client_token_lifetime_seconds)
+            .clamp(60, self.config.client_token_max_lifetime_seconds.max(60));
+        let now = Utc::now();
+        let scope = scopes.join(" ");
+
+        let claims = TokenClaims {
+            sub: client.owner_user_id.clone(),
+            phone: String::new(),
+            iat: now.timestamp(),
+            exp: (now + Duration::seconds(lifetime)).timestamp(),
+            is_provider: false,
+            jti: crate::shared::utils::generate_id(),
+            scope: Some(scope.clone()),
+            client_id: Some(client.client_id.clone()),
+            sid: None,
+            aud: Some(TokenAudience::Client),
+            act: None,
+            iss: Some(self.config.jwt_issuer.clone()),
+        };
+
+        let access_token = self.jwt_keys.sign(&claims)?;
+
+        info!("Issued access token to API client {}", client.client_id);
+        Ok(ClientAccessToken {
+            access_token,
+            token_type: "Bearer".to_string(),
+            expires_in: lifetime,
+            scope,
+        })
+    }
+
+    async fn issue_impersonation_token(
+        &self,
+        user: &User,
+        admin_id: &str,
+        audience: TokenAudience,
+    ) -> Result<ImpersonationToken> {
+        let lifetime = self
+            .config
+            .impersonation_token_lifetime_seconds
+            .clamp(60, 3600);
+        let now = Utc::now();
+
+        // No session or refresh token: it can't outlive its lifetime, and the
+        // user's "sign out everywhere" still cuts it off
+        let claims = TokenClaims {
+            sub: user.id.clone(),
+            phone: user.phone.as_str().to_string(),
+            iat: now.timestamp(),
+            exp: (now + Duration::seconds(lifetime)).timestamp(),
+            is_provider: user.is_provider,
+            jti: crate::shared::utils::generate_id(),
+            scope: None,
+            client_id: None,
+            sid: None,
+            aud: Some(audience),
+            act: Some(admin_id.to_string()),
+            iss: Some(self.config.jwt_issuer.clone()),
+        };
+
+        let access_token = self.jwt_keys.sign(&claims)?;
+
+        warn!(
+            "Admin {} issued an impersonation token for user {} ({} app)",
+            admin_id,
+            user.id,
+            audience.as_str()
+        );
+        Ok(ImpersonationToken {
+            access_token,
+            token_type: "Bearer".to_string(),
+            expires_in: lifetime,
+            user_id: user.id.clone(),
+            impersonated_by: admin_id.to_string(),
+        })
+    }
+
+    async fn send_verification_code(&self, phone: &PhoneNumber, purpose: &str) -> Result<()> {
+        info!("Sending {} verification code to phone: {}", purpose, phone.as_str());
+
+        self.check_rate_limit(phone).await?;
+
+        let otp_code = self.generate_otp();
+        let mut otp_data = OtpData::new(
+            phone.clone(),
+            otp_code.clone(),
+            self.config.otp_expiration_minutes,
+        );
+        self.deliver_otp(&mut otp_data, OtpChannelKind::Sms).await?;
+
+        let key = self.purpose_otp_key(phone, purpose);
+        self.store_otp_at(&key, &otp_data).await?;
+
+        Ok(())
+    }
+
+    async fn confirm_verification_code(
+        &self,
+        phone: &PhoneNumber,
+        purpose: &str,
+        code: &str,
+    ) -> Result<()> {
+        let key = self.purpose_otp_key(phone, purpose);
+        let mut otp_data =
+            self.get_otp_at(&key)
+                .await?
+                .ok_or_else(|| PeerPowerError::AuthenticationFailed {
+                    reason: "Verification code not found or expired".to_string(),
+                })?;
+
+        otp_data.increment_attempts();
+        self.store_otp_at(&key, &otp_data).await?;
+
+        if !otp_data.is_valid(code) {
+            return Err(PeerPowerError::AuthenticationFailed {
+                reason: if otp_data.is_expired() {
+                    "Verification code has expired".to_string()
+                } else if otp_data.attempts >= 3 {
+                    "Too many invalid attempts".to_string()
+                } else {
+                    "Invalid verification code".to_string()
+                },
+            });
+        }
+
+        self.redis.delete(&key).await?;
+        Ok(())
+    }
 
-        Ok(token_data.claims)
+    async fn pending_otp(&self, phone: &PhoneNumber) -> Result<Option<(String, DateTime<Utc>)>> {
+        Ok(self
+            .get_otp(phone)
+            .await?
+            .filter(|otp_data| !otp_data.is_expired())
+            .map(|otp_data| (otp_data.code, otp_data.expires_at)))
     }
 }
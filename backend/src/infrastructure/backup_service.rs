@@ -0,0 +1,417 @@
+use futures::stream::TryStreamExt;
+use mongodb::bson::{doc, Bson, Document};
+use mongodb::Database;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{error, info};
+
+use crate::domain::entities::{BackupCollection, BackupKind, BackupRun, BackupStatus, RestoreDiff};
+use crate::infrastructure::storage::ObjectStorageClient;
+use crate::shared::{PeerPowerError, Result};
+
+/// Collections covered by backups, with the field that identifies a document
+/// across a restore (`_id` is not preserved)
+pub const BACKUP_COLLECTIONS: &[(&str, &str)] =
+    &[("users", "user_id"), ("providers", "id"), ("payouts", "id")];
+
+/// Snapshots critical collections to encrypted object storage and restores them.
+///
+/// Backups and restores run in the background and are tracked as `BackupRun`
+/// records; only one may be in flight at a time.
+pub struct BackupService {
+    database: Arc<Database>,
+    storage: Arc<ObjectStorageClient>,
+}
+
+impl BackupService {
+    pub fn new(database: Arc<Database>, storage: Arc<ObjectStorageClient>) -> Self {
+        Self { database, storage }
+    }
+
+    pub async fn start_backup(self: &Arc<Self>, requested_by: String) -> Result<BackupRun> {
+        self.ensure_ready().await?;
+
+        let run = BackupRun::new(BackupKind::Backup, requested_by, None);
+        self.insert_run(&run).await?;
+        self.spawn(run.clone());
+        Ok(run)
+    }
+
+    pub async fn start_restore(
+        self: &Arc<Self>,
+        backup_id: &str,
+        collections: &[String],
+        requested_by: String,
+    ) -> Result<BackupRun> {
+        self.ensure_ready().await?;
+
+        let backup = self.completed_backup(backup_id).await?;
+        let mut run = BackupRun::new(BackupKind::Restore, requested_by, Some(backup.id.clone()));
+        run.collections = Self::select_collections(&backup, collections)?;
+        self.insert_run(&run).await?;
+        self.spawn(run.clone());
+        Ok(run)
+    }
+
+    /// Dry run: compare a backup against the live collections without writing
+    pub async fn diff(&self, backup_id: &str, collections: &[String]) -> Result<Vec<RestoreDiff>> {
+        let backup = self.completed_backup(backup_id).await?;
+        let mut diffs = Vec::new();
+
+        for collection in Self::select_collections(&backup, collections)? {
+            let key_field = Self::key_field(&collection.name)?;
+            let snapshot = self.load_snapshot(&collection).await?;
+            let mut current = self.load_current(&collection.name, key_field).await?;
+
+            let mut diff = RestoreDiff {
+                collection: collection.name.clone(),
+                in_backup: snapshot.len() as u64,
+                ..Default::default()
+            };
+            for document in &snapshot {
+                let key = Self::document_key(document, key_field)?;
+                match current.remove(&key) {
+                    None => diff.to_insert += 1,
+                    Some(existing) if existing == *document => diff.unchanged += 1,
+                    Some(_) => diff.to_update += 1,
+                }
+            }
+            diff.only_in_database = current.len() as u64;
+            diffs.push(diff);
+        }
+
+        Ok(diffs)
+    }
+
+    pub async fn find_run(&self, id: &str) -> Result<Option<BackupRun>> {
+        self.runs()
+            .find_one(doc! {"id": id}, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch backup run: {}", e),
+            })
+    }
+
+    pub async fn list_runs(&self, limit: i64) -> Result<Vec<BackupRun>> {
+        self.runs()
+            .find(
+                doc! {},
+                mongodb::options::FindOptions::builder()
+                    .sort(doc! {"created_at": -1})
+                    .limit(limit)
+                    .build(),
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch backup runs: {}", e),
+            })?
+            .try_collect()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read backup runs: {}", e),
+            })
+    }
+
+    fn spawn(self: &Arc<Self>, mut run: BackupRun) {
+        let service = self.clone();
+        tokio::spawn(async move {
+            run.status = BackupStatus::Running;
+            run.started_at = Some(crate::shared::utils::now());
+            if let Err(e) = service.save_run(&run).await {
+                error!("Failed to mark backup run {} as running: {}", run.id, e);
+            }
+
+            let result = match run.kind {
+                BackupKind::Backup => service.run_backup(&mut run).await,
+                BackupKind::Restore => service.run_restore(&run).await,
+            };
+
+            match result {
+                Ok(()) => {
+                    info!("{:?} run {} completed", run.kind, run.id);
+                    run.status = BackupStatus::Completed;
+                }
+                Err(e) => {
+                    error!("{:?} run {} failed: {}", run.kind, run.id, e);
+                    run.status = BackupStatus::Failed;
+                    run.error = Some(e.to_string());
+                }
+            }
+            run.completed_at = Some(crate::shared::utils::now());
+            if let Err(e) = service.save_run(&run).await {
+                error!("Failed to record outcome of backup run {}: {}", run.id, e);
+            }
+        });
+    }
+
+    async fn run_backup(&self, run: &mut BackupRun) -> Result<()> {
+        for (name, _) in BACKUP_COLLECTIONS {
+            let mut cursor = self
+                .database
+                .collection::<Document>(name)
+                .find(doc! {}, None)
+                .await
+                .map_err(|e| PeerPowerError::Database {
+                    message: format!("Failed to read {} for backup: {}", name, e),
+                })?;
+
+            let mut body = Vec::new();
+            let mut documents = 0;
+            while let Some(document) =
+                cursor
+                    .try_next()
+                    .await
+                    .map_err(|e| PeerPowerError::Database {
+                        message: format!("Failed to read {} for backup: {}", name, e),
+                    })?
+            {
+                let line = serde_json::to_vec(&Bson::Document(document).into_canonical_extjson())
+                    .map_err(|e| PeerPowerError::Internal {
+                    message: format!("Failed to serialize {} document: {}", name, e),
+                })?;
+                body.extend_from_slice(&line);
+                body.push(b'\n');
+                documents += 1;
+            }
+
+            let object_key = self.storage.key(&format!("{}/{}.jsonl", run.id, name));
+            let sha256 = hex::encode(Sha256::digest(&body));
+            self.storage
+                .put_object(&object_key, body, "application/x-ndjson")
+                .await?;
+
+            run.collections.push(BackupCollection {
+                name: name.to_string(),
+                object_key,
+                documents,
+                sha256,
+            });
+        }
+
+        // Keep a manifest next to the data so a backup is usable without this database
+        let manifest = serde_json::to_vec_pretty(&run).map_err(|e| PeerPowerError::Internal {
+            message: format!("Failed to serialize backup manifest: {}", e),
+        })?;
+        self.storage
+            .put_object(
+                &self.storage.key(&format!("{}/manifest.json", run.id)),
+                manifest,
+                "application/json",
+            )
+            .await
+    }
+
+    async fn run_restore(&self, run: &BackupRun) -> Result<()> {
+        for collection in &run.collections {
+            let key_field = Self::key_field(&collection.name)?;
+            let snapshot = self.load_snapshot(collection).await?;
+            let target = self.database.collection::<Document>(&collection.name);
+
+            for document in &snapshot {
+                let key = Self::document_key(document, key_field)?;
+                target
+                    .replace_one(
+                        doc! {key_field: &key},
+                        document,
+                        mongodb::options::ReplaceOptions::builder()
+                            .upsert(true)
+                            .build(),
+                    )
+                    .await
+                    .map_err(|e| PeerPowerError::Database {
+                        message: format!("Failed to restore {} {}: {}", collection.name, key, e),
+                    })?;
+            }
+
+            info!(
+                "Restored {} documents into {} from backup {}",
+                snapshot.len(),
+                collection.name,
+                run.source_backup_id.as_deref().unwrap_or("unknown")
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Download a collection snapshot, verify its checksum and decode it
+    async fn load_snapshot(&self, collection: &BackupCollection) -> Result<Vec<Document>> {
+        let body = self.storage.get_object(&collection.object_key).await?;
+        if hex::encode(Sha256::digest(&body)) != collection.sha256 {
+            return Err(PeerPowerError::Internal {
+                message: format!("Checksum mismatch for backup of {}", collection.name),
+            });
+        }
+
+        let text = String::from_utf8(body).map_err(|e| PeerPowerError::Internal {
+            message: format!("Backup of {} is not valid UTF-8: {}", collection.name, e),
+        })?;
+
+        let mut documents = Vec::new();
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let value: serde_json::Value =
+                serde_json::from_str(line).map_err(|e| PeerPowerError::Internal {
+                    message: format!("Invalid backup line in {}: {}", collection.name, e),
+                })?;
+            let mut document = match Bson::try_from(value) {
+                Ok(Bson::Document(document)) => document,
+                _ => {
+                    return Err(PeerPowerError::Internal {
+                        message: format!("Invalid backup document in {}", collection.name),
+                    })
+                }
+            };
+            document.remove("_id");
+            documents.push(document);
+        }
+
+        Ok(documents)
+    }
+
+    async fn load_current(
+        &self,
+        collection: &str,
+        key_field: &str,
+    ) -> Result<HashMap<String, Document>> {
+        let mut cursor = self
+            .database
+            .collection::<Document>(collection)
+            .find(doc! {}, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read {}: {}", collection, e),
+            })?;
+
+        let mut current = HashMap::new();
+        while let Some(mut document) =
+            cursor
+                .try_next()
+                .await
+                .map_err(|e| PeerPowerError::Database {
+                    message: format!("Failed to read {}: {}", collection, e),
+                })?
+        {
+            document.remove("_id");
+            if let Ok(key) = Self::document_key(&document, key_field) {
+                current.insert(key, document);
+            }
+        }
+
+        Ok(current)
+    }
+
+    async fn ensure_ready(&self) -> Result<()> {
+        if !self.storage.is_configured() {
+            return Err(PeerPowerError::Configuration {
+                message: "Backup object storage is not configured".to_string(),
+            });
+        }
+
+        let in_flight = self
+            .runs()
+            .count_documents(
+                doc! {"status": {"$in": [
+                    format!("{:?}", BackupStatus::Pending),
+                    format!("{:?}", BackupStatus::Running),
+                ]}},
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to check backup runs: {}", e),
+            })?;
+
+        if in_flight > 0 {
+            return Err(PeerPowerError::ValidationError {
+                field: "backup".to_string(),
+                message: "Another backup or restore is already running".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    async fn completed_backup(&self, backup_id: &str) -> Result<BackupRun> {
+        let backup = self
+            .find_run(backup_id)
+            .await?
+            .filter(|run| run.kind == BackupKind::Backup)
+            .ok_or_else(|| PeerPowerError::NotFound {
+                resource: format!("Backup with ID: {}", backup_id),
+            })?;
+
+        if backup.status != BackupStatus::Completed {
+            return Err(PeerPowerError::ValidationError {
+                field: "backup_id".to_string(),
+                message: "Only completed backups can be restored".to_string(),
+            });
+        }
+        Ok(backup)
+    }
+
+    /// Collections from the backup to restore; empty selection means all of them
+    fn select_collections(backup: &BackupRun, names: &[String]) -> Result<Vec<BackupCollection>> {
+        if names.is_empty() {
+            return Ok(backup.collections.clone());
+        }
+
+        names
+            .iter()
+            .map(|name| {
+                backup
+                    .collections
+                    .iter()
+                    .find(|collection| &collection.name == name)
+                    .cloned()
+                    .ok_or_else(|| PeerPowerError::ValidationError {
+                        field: "collections".to_string(),
+                        message: format!("Collection {} is not in this backup", name),
+                    })
+            })
+            .collect()
+    }
+
+    fn key_field(collection: &str) -> Result<&'static str> {
+        BACKUP_COLLECTIONS
+            .iter()
+            .find(|(name, _)| *name == collection)
+            .map(|(_, key_field)| *key_field)
+            .ok_or_else(|| PeerPowerError::ValidationError {
+                field: "collections".to_string(),
+                message: format!("Collection {} cannot be restored", collection),
+            })
+    }
+
+    fn document_key(document: &Document, key_field: &str) -> Result<String> {
+        document
+            .get_str(key_field)
+            .map(str::to_string)
+            .map_err(|_| PeerPowerError::Internal {
+                message: format!("Backup document is missing {}", key_field),
+            })
+    }
+
+    async fn insert_run(&self, run: &BackupRun) -> Result<()> {
+        self.runs()
+            .insert_one(run, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to store backup run: {}", e),
+            })?;
+        Ok(())
+    }
+
+    async fn save_run(&self, run: &BackupRun) -> Result<()> {
+        self.runs()
+            .replace_one(doc! {"id": &run.id}, run, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to update backup run: {}", e),
+            })?;
+        Ok(())
+    }
+
+    fn runs(&self) -> mongodb::Collection<BackupRun> {
+        self.database.collection("backup_runs")
+    }
+}
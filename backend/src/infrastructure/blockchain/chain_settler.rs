@@ -0,0 +1,528 @@
+use futures::stream::TryStreamExt;
+use mongodb::bson::{doc, Document};
+use mongodb::options::FindOptions;
+use mongodb::{Collection, Database};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::config::SelendraConfig;
+use crate::domain::entities::{
+    ChainSettlement, ChainSettlementStatus, LedgerAccountKind, LedgerTransaction,
+    LedgerTransactionKind, PayoutMethodKind, Provider,
+};
+use crate::domain::services::{BlockchainService, TransactionStatus};
+use crate::infrastructure::database::RedisConnection;
+use crate::infrastructure::ledger::Ledger;
+use crate::shared::utils::stored_timestamp;
+use crate::shared::{Money, PeerPowerError, Result};
+
+const BATCH_LOCK_KEY: &str = "chain_settlement_lock";
+
+/// What one settlement batch did
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ChainSettlementBatch {
+    pub batch_id: String,
+    pub created: u32,
+    pub submitted: u32,
+    pub confirmed: u32,
+    pub failed: u32,
+}
+
+/// A provider's on-chain settlements, for the app and support
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderSettlementStatus {
+    pub provider_id: String,
+    pub wallet_address: Option<String>, // default PPT wallet; unset means not settled on-chain
+    pub pending_amount: f64,
+    pub submitted_amount: f64,
+    pub confirmed_amount: f64,
+    pub settlements: Vec<ChainSettlement>,
+}
+
+/// Settles provider earnings as PPT token transfers on Selendra.
+///
+/// Providers whose default payout method is a PPT wallet have their ledger
+/// balance swept each batch once it reaches `min_settlement_ppt`. The
+/// amount is debited from the ledger when the settlement is created; the
+/// transfer is signed, its hash recorded on the settlement and the ledger
+/// transaction, and only then broadcast, so a crash mid-send can't lose
+/// track of it. Later batches wait for it to be confirmed. Reverted
+/// transfers, and ones whose nonce was taken by another transaction, are
+/// credited back.
+pub struct ChainSettler {
+    settlements: Collection<ChainSettlement>,
+    providers: Collection<Provider>,
+    blockchain: Arc<dyn BlockchainService>,
+    ledger: Arc<Ledger>,
+    redis: RedisConnection,
+    config: SelendraConfig,
+}
+
+impl ChainSettler {
+    pub fn new(
+        database: Arc<Database>,
+        redis: RedisConnection,
+        blockchain: Arc<dyn BlockchainService>,
+        ledger: Arc<Ledger>,
+        config: SelendraConfig,
+    ) -> Self {
+        Self {
+            settlements: database.collection("chain_settlements"),
+            providers: database.collection("providers"),
+            blockchain,
+            ledger,
+            redis,
+            config,
+        }
+    }
+
+    pub fn start(self: Arc<Self>) {
+        if !self.blockchain.is_configured() {
+            info!("Selendra not configured, on-chain settlement disabled");
+            return;
+        }
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(
+                self.config.settlement_interval_seconds.max(60),
+            ));
+            loop {
+                interval.tick().await;
+                match self.run().await {
+                    Ok(batch) => info!(
+                        "Settlement batch {}: {} created, {} submitted, {} confirmed, {} failed",
+                        batch.batch_id,
+                        batch.created,
+                        batch.submitted,
+                        batch.confirmed,
+                        batch.failed
+                    ),
+                    Err(PeerPowerError::RateLimitExceeded { .. }) => {
+                        info!("Settlement batch already in progress elsewhere, skipping")
+                    }
+                    Err(e) => error!("Settlement batch failed: {}", e),
+                }
+            }
+        });
+    }
+
+    /// Confirm earlier transfers, then settle eligible balances. Only one
+    /// instance runs at a time, which keeps treasury nonces in order.
+    pub async fn run(&self) -> Result<ChainSettlementBatch> {
+        if !self.blockchain.is_configured() {
+            return Err(PeerPowerError::Configuration {
+                message: "Selendra settlement is not configured".to_string(),
+            });
+        }
+        let lock_ttl = self.config.settlement_interval_seconds.max(60) as usize;
+        if !self.redis.acquire_lock(BATCH_LOCK_KEY, lock_ttl).await? {
+            return Err(PeerPowerError::RateLimitExceeded {
+                resource: "Settlement batch already in progress".to_string(),
+            });
+        }
+
+        let mut batch = ChainSettlementBatch {
+            batch_id: crate::shared::utils::generate_id(),
+            ..Default::default()
+        };
+        let result = self.process(&mut batch).await;
+        self.redis.release_lock(BATCH_LOCK_KEY).await?;
+        result?;
+        Ok(batch)
+    }
+
+    async fn process(&self, batch: &mut ChainSettlementBatch) -> Result<()> {
+        self.confirm(batch).await?;
+        self.create(batch).await?;
+        self.submit(batch).await
+    }
+
+    async fn confirm(&self, batch: &mut ChainSettlementBatch) -> Result<()> {
+        for settlement in self.find(ChainSettlementStatus::Submitted).await? {
+            let (Some(tx_hash), Some(nonce)) = (&settlement.tx_hash, settlement.nonce) else {
+                continue;
+            };
+            match self.blockchain.status(tx_hash, nonce).await {
+                Ok(TransactionStatus::Confirmed) => {
+                    if self
+                        .transition(
+                            &settlement.id,
+                            ChainSettlementStatus::Submitted,
+                            confirmed(),
+                        )
+                        .await?
+                    {
+                        batch.confirmed += 1;
+                        metrics::counter!("chain_settlements_total", "status" => "Confirmed")
+                            .increment(1);
+                    }
+                }
+                Ok(TransactionStatus::Reverted) => {
+                    if self.fail(&settlement, "Transfer reverted").await? {
+                        batch.failed += 1;
+                    }
+                }
+                Ok(TransactionStatus::Dropped) => {
+                    if self
+                        .fail(&settlement, "Transaction dropped before being mined")
+                        .await?
+                    {
+                        batch.failed += 1;
+                    }
+                }
+                Ok(TransactionStatus::Pending) => {}
+                Err(e) => warn!("Failed to check transaction {}: {}", tx_hash, e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Debit eligible balances into new pending settlements
+    async fn create(&self, batch: &mut ChainSettlementBatch) -> Result<()> {
+        let in_flight: HashSet<String> = self
+            .settlements
+            .distinct(
+                "provider_id",
+                doc! {"status": {"$in": ["Pending", "Submitted"]}},
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch settlements: {}", e),
+            })?
+            .into_iter()
+            .filter_map(|id| id.as_str().map(str::to_string))
+            .collect();
+
+        let providers: Vec<Provider> = self
+            .providers
+            .find(
+                doc! {
+                    "payout_methods": {"$elemMatch": {"kind": "ppt_wallet", "is_default": true}}
+                },
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch providers: {}", e),
+            })?
+            .try_collect()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read providers: {}", e),
+            })?;
+        let balances = self.ledger.balances(LedgerAccountKind::Provider).await?;
+
+        for provider in providers {
+            if batch.created as i64 >= self.config.settlement_batch_size.max(1) {
+                break;
+            }
+            let eligible = balances
+                .get(&provider.id)
+                .is_some_and(|account| {
+                    account.balance >= Money::from_ppt(self.config.min_settlement_ppt)
+                });
+            if !eligible || in_flight.contains(&provider.id) {
+                continue;
+            }
+            let Some(wallet) = provider
+                .payout_methods
+                .iter()
+                .find(|method| method.is_default && method.kind == PayoutMethodKind::PptWallet)
+            else {
+                continue;
+            };
+
+            // Same lock as payout requests, so the balance can't be spent twice
+            let lock_key = format!("payout_lock:{}", provider.id);
+            if !self.redis.acquire_lock(&lock_key, 30).await? {
+                continue;
+            }
+            let result = self
+                .create_settlement(&batch.batch_id, &provider.id, &wallet.account_reference)
+                .await;
+            self.redis.release_lock(&lock_key).await?;
+            if result? {
+                batch.created += 1;
+            }
+        }
+        Ok(())
+    }
+
+    async fn create_settlement(
+        &self,
+        batch_id: &str,
+        provider_id: &str,
+        wallet_address: &str,
+    ) -> Result<bool> {
+        let balance = self
+            .ledger
+            .balance(LedgerAccountKind::Provider, provider_id)
+            .await?
+            .balance;
+        if balance < Money::from_ppt(self.config.min_settlement_ppt) {
+            return Ok(false);
+        }
+
+        let settlement = ChainSettlement::new(
+            batch_id.to_string(),
+            provider_id.to_string(),
+            wallet_address.to_string(),
+            balance.to_ppt(),
+        );
+        self.settlements
+            .insert_one(&settlement, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create settlement: {}", e),
+            })?;
+        self.ledger
+            .post(&LedgerTransaction::chain_settlement(
+                &settlement.id,
+                provider_id,
+                balance,
+            ))
+            .await?;
+        Ok(true)
+    }
+
+    /// Sign and broadcast pending settlements, oldest first
+    async fn submit(&self, batch: &mut ChainSettlementBatch) -> Result<()> {
+        for mut settlement in self.find(ChainSettlementStatus::Pending).await? {
+            let transaction = match self
+                .blockchain
+                .prepare_token_transfer(&settlement.wallet_address, settlement.amount)
+                .await
+            {
+                Ok(transaction) => transaction,
+                // The node is unreachable; try the rest next batch
+                Err(PeerPowerError::ExternalService { message, .. }) => {
+                    warn!("Selendra unavailable, deferring settlements: {}", message);
+                    return Ok(());
+                }
+                // Refused (e.g. the transfer would revert) or a bad wallet address
+                Err(e) => {
+                    if self.fail(&settlement, &e.to_string()).await? {
+                        batch.failed += 1;
+                    }
+                    continue;
+                }
+            };
+
+            // Record the hash before broadcasting so it's tracked either way
+            if !self
+                .transition(
+                    &settlement.id,
+                    ChainSettlementStatus::Pending,
+                    doc! {
+                        "status": format!("{:?}", ChainSettlementStatus::Submitted),
+                        "nonce": transaction.nonce as i64,
+                        "tx_hash": &transaction.hash,
+                    },
+                )
+                .await?
+            {
+                continue;
+            }
+            settlement.status = ChainSettlementStatus::Submitted;
+            self.ledger
+                .record_tx_hash(
+                    LedgerTransactionKind::ChainSettlement,
+                    &settlement.id,
+                    &transaction.hash,
+                )
+                .await?;
+
+            match self.blockchain.broadcast(&transaction).await {
+                Ok(()) => {
+                    batch.submitted += 1;
+                    metrics::counter!("chain_settlements_total", "status" => "Submitted")
+                        .increment(1);
+                }
+                // Refused by the node, so it will never be mined
+                Err(PeerPowerError::BlockchainError { reason }) => {
+                    if self.fail(&settlement, &reason).await? {
+                        batch.failed += 1;
+                    }
+                }
+                // Unknown whether it went out; receipts or the nonce will tell
+                Err(e) => {
+                    warn!(
+                        "Broadcast of settlement {} ({}) unconfirmed: {}",
+                        settlement.id, transaction.hash, e
+                    );
+                    return Ok(());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Mark a settlement failed and return its PPT to the provider's balance
+    async fn fail(&self, settlement: &ChainSettlement, reason: &str) -> Result<bool> {
+        if !self
+            .transition(
+                &settlement.id,
+                settlement.status,
+                doc! {
+                    "status": format!("{:?}", ChainSettlementStatus::Failed),
+                    "failure_reason": reason,
+                },
+            )
+            .await?
+        {
+            return Ok(false);
+        }
+        warn!("Settlement {} failed: {}", settlement.id, reason);
+        metrics::counter!("chain_settlements_total", "status" => "Failed").increment(1);
+
+        self.ledger
+            .post(&LedgerTransaction::chain_settlement_reversal(
+                &settlement.id,
+                &settlement.provider_id,
+                Money::from_ppt(settlement.amount),
+            ))
+            .await?;
+        Ok(true)
+    }
+
+    async fn transition(
+        &self,
+        settlement_id: &str,
+        from: ChainSettlementStatus,
+        set: Document,
+    ) -> Result<bool> {
+        let result = self
+            .settlements
+            .update_one(
+                doc! {"id": settlement_id, "status": format!("{:?}", from)},
+                transition_update(set),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to update settlement: {}", e),
+            })?;
+        Ok(result.modified_count == 1)
+    }
+
+    async fn find(&self, status: ChainSettlementStatus) -> Result<Vec<ChainSettlement>> {
+        let find_options = FindOptions::builder()
+            .sort(doc! {"created_at": 1})
+            .limit(self.config.settlement_batch_size.max(1))
+            .build();
+        self.settlements
+            .find(doc! {"status": format!("{:?}", status)}, find_options)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch settlements: {}", e),
+            })?
+            .try_collect()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read settlements: {}", e),
+            })
+    }
+
+    /// Totals by status and the most recent settlements for one provider
+    pub async fn provider_status(&self, provider: &Provider) -> Result<ProviderSettlementStatus> {
+        let find_options = FindOptions::builder()
+            .sort(doc! {"created_at": -1})
+            .limit(50)
+            .build();
+        let settlements: Vec<ChainSettlement> = self
+            .settlements
+            .find(doc! {"provider_id": &provider.id}, find_options)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch settlements: {}", e),
+            })?
+            .try_collect()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read settlements: {}", e),
+            })?;
+
+        let mut status = ProviderSettlementStatus {
+            provider_id: provider.id.clone(),
+            wallet_address: provider
+                .payout_methods
+                .iter()
+                .find(|method| method.is_default && method.kind == PayoutMethodKind::PptWallet)
+                .map(|method| method.account_reference.clone()),
+            pending_amount: 0.0,
+            submitted_amount: 0.0,
+            confirmed_amount: 0.0,
+            settlements: Vec::new(),
+        };
+
+        let mut cursor = self
+            .settlements
+            .clone_with_type::<Document>()
+            .aggregate(
+                vec![
+                    doc! {"$match": {"provider_id": &provider.id}},
+                    doc! {"$group": {"_id": "$status", "amount": {"$sum": "$amount"}}},
+                ],
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to aggregate settlements: {}", e),
+            })?;
+        while let Some(total) = cursor
+            .try_next()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read settlement totals: {}", e),
+            })?
+        {
+            let amount = total.get_f64("amount").unwrap_or(0.0);
+            match total.get_str("_id").unwrap_or_default() {
+                "Pending" => status.pending_amount = amount,
+                "Submitted" => status.submitted_amount = amount,
+                "Confirmed" => status.confirmed_amount = amount,
+                _ => {}
+            }
+        }
+
+        status.settlements = settlements;
+        Ok(status)
+    }
+}
+
+fn confirmed() -> Document {
+    doc! {
+        "status": format!("{:?}", ChainSettlementStatus::Confirmed),
+        "confirmed_at": stored_timestamp(crate::shared::utils::now()),
+    }
+}
+
+fn transition_update(mut set: Document) -> Document {
+    set.insert("updated_at", stored_timestamp(crate::shared::utils::now()));
+    doc! {"$set": set}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::test_support::read_back;
+
+    #[test]
+    fn test_confirmed_settlement_reads_back() {
+        let settlement = ChainSettlement::new(
+            "batch-1".to_string(),
+            "provider-1".to_string(),
+            "0x3535353535353535353535353535353535353535".to_string(),
+            12.5,
+        );
+
+        let settled = read_back(&settlement, &transition_update(confirmed()));
+        assert_eq!(settled.status, ChainSettlementStatus::Confirmed);
+        assert!(settled.confirmed_at.is_some());
+        assert!(settled.updated_at >= settlement.updated_at);
+    }
+}
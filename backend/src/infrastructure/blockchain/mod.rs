@@ -1 +1,8 @@
-// Blockchain implementations will be defined here
+// Selendra on-chain settlement and provider stakes
+pub mod chain_settler;
+pub mod selendra_client;
+pub mod staking;
+
+pub use chain_settler::*;
+pub use selendra_client::*;
+pub use staking::*;
@@ -0,0 +1,493 @@
+use async_trait::async_trait;
+use k256::ecdsa::SigningKey;
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Value};
+use sha3::{Digest, Keccak256};
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::config::SelendraConfig;
+use crate::domain::services::{BlockchainService, PreparedTransaction, TransactionStatus};
+use crate::shared::{PeerPowerError, Result};
+
+/// `transfer(address,uint256)`
+const ERC20_TRANSFER_SELECTOR: [u8; 4] = [0xa9, 0x05, 0x9c, 0xbb];
+/// Headroom added to gas estimates, in percent
+const GAS_ESTIMATE_BUFFER_PERCENT: u128 = 20;
+
+/// JSON-RPC client for Selendra's EVM, signing transactions from the
+/// platform key locally (legacy EIP-155 transactions).
+///
+/// Nonces are handed out from a local counter, resynced from the node's
+/// pending count whenever a broadcast fails, so several transactions can be
+/// prepared before the first is mined. Transport failures, 429s and
+/// 5xx responses are retried with backoff; JSON-RPC errors are not.
+pub struct SelendraClient {
+    config: SelendraConfig,
+    client: Client,
+    signing_key: Option<SigningKey>,
+    contract: Option<[u8; 20]>,
+    next_nonce: Mutex<Option<u64>>,
+}
+
+impl SelendraClient {
+    pub fn new(config: SelendraConfig) -> Result<Self> {
+        let signing_key = if config.private_key.is_empty() {
+            None
+        } else {
+            let bytes = hex::decode(config.private_key.trim_start_matches("0x")).map_err(|_| {
+                PeerPowerError::Configuration {
+                    message: "SELENDRA_PRIVATE_KEY must be hex".to_string(),
+                }
+            })?;
+            Some(
+                SigningKey::from_slice(&bytes).map_err(|_| PeerPowerError::Configuration {
+                    message: "SELENDRA_PRIVATE_KEY is not a valid secp256k1 key".to_string(),
+                })?,
+            )
+        };
+        let contract = if config.token_contract_address.is_empty() {
+            None
+        } else {
+            Some(
+                parse_address(&config.token_contract_address).ok_or_else(|| {
+                    PeerPowerError::Configuration {
+                        message: "PPT_CONTRACT_ADDRESS is not a valid address".to_string(),
+                    }
+                })?,
+            )
+        };
+        let client = Client::builder()
+            .timeout(Duration::from_millis(config.rpc_timeout_ms.max(1)))
+            .pool_max_idle_per_host(config.rpc_pool_size)
+            .build()
+            .map_err(|e| PeerPowerError::Configuration {
+                message: format!("Failed to build Selendra HTTP client: {}", e),
+            })?;
+
+        Ok(Self {
+            config,
+            client,
+            signing_key,
+            contract,
+            next_nonce: Mutex::new(None),
+        })
+    }
+
+    fn signing_key(&self) -> Result<&SigningKey> {
+        self.signing_key
+            .as_ref()
+            .ok_or_else(|| PeerPowerError::Configuration {
+                message: "Selendra is not configured".to_string(),
+            })
+    }
+
+    /// One JSON-RPC call, retrying failures that may succeed on a second try
+    async fn rpc(&self, method: &str, params: Value) -> Result<Value> {
+        let mut attempt = 0;
+        loop {
+            match self.rpc_once(method, &params).await {
+                Err(PeerPowerError::ExternalService { message, .. })
+                    if attempt < self.config.rpc_max_retries =>
+                {
+                    attempt += 1;
+                    warn!(
+                        "Selendra {} failed (attempt {}), retrying: {}",
+                        method, attempt, message
+                    );
+                    metrics::counter!("selendra_rpc_retries_total", "method" => method.to_string())
+                        .increment(1);
+                    tokio::time::sleep(Duration::from_millis(200 * 2u64.pow(attempt))).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn rpc_once(&self, method: &str, params: &Value) -> Result<Value> {
+        let transient = |message: String| PeerPowerError::ExternalService {
+            service: "Selendra".to_string(),
+            message,
+        };
+        let response = self
+            .client
+            .post(&self.config.rpc_url)
+            .json(&json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": method,
+                "params": params,
+            }))
+            .send()
+            .await
+            .map_err(|e| transient(format!("{} failed: {}", method, e)))?;
+
+        let status = response.status();
+        if status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+            return Err(transient(format!("{} returned {}", method, status)));
+        }
+        let response: Value = response
+            .json()
+            .await
+            .map_err(|e| transient(format!("Invalid {} response: {}", method, e)))?;
+
+        // An error object means the node answered and refused the call
+        if let Some(error) = response.get("error") {
+            return Err(PeerPowerError::BlockchainError {
+                reason: error
+                    .get("message")
+                    .and_then(Value::as_str)
+                    .unwrap_or("unknown error")
+                    .to_string(),
+            });
+        }
+        Ok(response.get("result").cloned().unwrap_or(Value::Null))
+    }
+
+    async fn transaction_count(&self, block: &str) -> Result<u64> {
+        let address = self
+            .address()
+            .ok_or_else(|| PeerPowerError::Configuration {
+                message: "Selendra is not configured".to_string(),
+            })?;
+        let result = self
+            .rpc("eth_getTransactionCount", json!([address, block]))
+            .await?;
+        parse_quantity(&result).map(|count| count as u64)
+    }
+
+    /// Estimated gas plus headroom, capped at the configured limit
+    async fn estimate_gas(&self, to: &str, data: &[u8]) -> Result<u64> {
+        let estimate = parse_quantity(
+            &self
+                .rpc(
+                    "eth_estimateGas",
+                    json!([{
+                        "from": self.address(),
+                        "to": to,
+                        "data": format!("0x{}", hex::encode(data)),
+                    }]),
+                )
+                .await?,
+        )?;
+        let buffered = estimate * (100 + GAS_ESTIMATE_BUFFER_PERCENT) / 100;
+        Ok(buffered.min(self.config.gas_limit as u128) as u64)
+    }
+}
+
+#[async_trait]
+impl BlockchainService for SelendraClient {
+    fn is_configured(&self) -> bool {
+        self.signing_key.is_some() && self.contract.is_some()
+    }
+
+    fn address(&self) -> Option<String> {
+        self.signing_key
+            .as_ref()
+            .map(|key| format!("0x{}", hex::encode(signer_address(key))))
+    }
+
+    async fn prepare(&self, to: &str, data: &[u8]) -> Result<PreparedTransaction> {
+        let key = self.signing_key()?;
+        let to_address = parse_address(to).ok_or_else(|| PeerPowerError::ValidationError {
+            field: "to".to_string(),
+            message: format!("Invalid address: {}", to),
+        })?;
+        let gas_limit = self.estimate_gas(to, data).await?;
+        let gas_price = parse_quantity(&self.rpc("eth_gasPrice", json!([])).await?)?;
+
+        // Hold the counter until signed so concurrent callers get distinct nonces
+        let mut next_nonce = self.next_nonce.lock().await;
+        let pending = self.transaction_count("pending").await?;
+        let nonce = next_nonce.map_or(pending, |next| next.max(pending));
+        let transaction = sign_legacy(
+            key,
+            self.config.chain_id,
+            nonce,
+            gas_price,
+            gas_limit,
+            &to_address,
+            0,
+            data,
+        )?;
+        *next_nonce = Some(nonce + 1);
+        Ok(transaction)
+    }
+
+    async fn prepare_token_transfer(&self, to: &str, amount: f64) -> Result<PreparedTransaction> {
+        let contract = self.contract.ok_or_else(|| PeerPowerError::Configuration {
+            message: "PPT_CONTRACT_ADDRESS is not set".to_string(),
+        })?;
+        let recipient = parse_address(to).ok_or_else(|| PeerPowerError::ValidationError {
+            field: "wallet_address".to_string(),
+            message: format!("Invalid wallet address: {}", to),
+        })?;
+
+        let mut data = ERC20_TRANSFER_SELECTOR.to_vec();
+        data.extend_from_slice(&[0u8; 12]);
+        data.extend_from_slice(&recipient);
+        data.extend_from_slice(&[0u8; 16]);
+        data.extend_from_slice(&token_units(amount, self.config.token_decimals).to_be_bytes());
+
+        self.prepare(&format!("0x{}", hex::encode(contract)), &data)
+            .await
+    }
+
+    async fn broadcast(&self, transaction: &PreparedTransaction) -> Result<()> {
+        let result = self
+            .rpc(
+                "eth_sendRawTransaction",
+                json!([format!("0x{}", hex::encode(&transaction.raw))]),
+            )
+            .await;
+        match result {
+            Ok(_) => {}
+            // A retried send that had already reached the node
+            Err(PeerPowerError::BlockchainError { reason })
+                if reason.to_lowercase().contains("already known")
+                    || reason.to_lowercase().contains("known transaction") => {}
+            Err(e) => {
+                // The nonce may not have been used; resync before preparing the next one
+                *self.next_nonce.lock().await = None;
+                return Err(e);
+            }
+        }
+        info!("Broadcast Selendra transaction {}", transaction.hash);
+        Ok(())
+    }
+
+    async fn status(&self, tx_hash: &str, nonce: u64) -> Result<TransactionStatus> {
+        // Read the nonce first: if the receipt is missing afterwards, a
+        // higher mined count means another transaction took this nonce
+        let mined = self.transaction_count("latest").await?;
+        let receipt = self
+            .rpc("eth_getTransactionReceipt", json!([tx_hash]))
+            .await?;
+        if receipt.is_null() {
+            return Ok(if nonce < mined {
+                TransactionStatus::Dropped
+            } else {
+                TransactionStatus::Pending
+            });
+        }
+        if receipt.get("status").and_then(Value::as_str) != Some("0x1") {
+            return Ok(TransactionStatus::Reverted);
+        }
+
+        let mined_in = parse_quantity(receipt.get("blockNumber").unwrap_or(&Value::Null))?;
+        let head = parse_quantity(&self.rpc("eth_blockNumber", json!([])).await?)?;
+        Ok(confirmation_status(
+            mined_in,
+            head,
+            self.config.confirmations,
+        ))
+    }
+
+    async fn call(&self, to: &str, data: &[u8]) -> Result<Vec<u8>> {
+        let result = self
+            .rpc(
+                "eth_call",
+                json!([{"to": to, "data": format!("0x{}", hex::encode(data))}, "latest"]),
+            )
+            .await?;
+        let encoded = result.as_str().unwrap_or("0x");
+        hex::decode(encoded.trim_start_matches("0x")).map_err(|e| PeerPowerError::ExternalService {
+            service: "Selendra".to_string(),
+            message: format!("Invalid eth_call result: {}", e),
+        })
+    }
+}
+
+/// Confirmed once the block it was mined in is `confirmations` deep, counting itself
+fn confirmation_status(mined_in: u128, head: u128, confirmations: u64) -> TransactionStatus {
+    if head + 1 >= mined_in + confirmations.max(1) as u128 {
+        TransactionStatus::Confirmed
+    } else {
+        TransactionStatus::Pending
+    }
+}
+
+/// Whether `address` looks like a 0x-prefixed 20-byte EVM address
+pub fn is_wallet_address(address: &str) -> bool {
+    parse_address(address).is_some()
+}
+
+pub(crate) fn parse_address(address: &str) -> Option<[u8; 20]> {
+    let digits = address.strip_prefix("0x")?;
+    hex::decode(digits).ok()?.try_into().ok()
+}
+
+fn parse_quantity(value: &Value) -> Result<u128> {
+    value
+        .as_str()
+        .and_then(|quantity| u128::from_str_radix(quantity.trim_start_matches("0x"), 16).ok())
+        .ok_or_else(|| PeerPowerError::ExternalService {
+            service: "Selendra".to_string(),
+            message: format!("Expected a hex quantity, got {}", value),
+        })
+}
+
+/// PPT amount in the token's smallest unit (PPT is kept to 6 decimals)
+pub(crate) fn token_units(amount: f64, decimals: u32) -> u128 {
+    let micro = (amount * 1_000_000.0).round().max(0.0) as u128;
+    if decimals >= 6 {
+        micro * 10u128.pow(decimals - 6)
+    } else {
+        micro / 10u128.pow(6 - decimals)
+    }
+}
+
+/// PPT amount from the token's smallest unit, rounded to 6 decimals
+pub(crate) fn from_token_units(units: u128, decimals: u32) -> f64 {
+    let micro = if decimals >= 6 {
+        units / 10u128.pow(decimals - 6)
+    } else {
+        units * 10u128.pow(6 - decimals)
+    };
+    micro as f64 / 1_000_000.0
+}
+
+pub(crate) fn keccak256(bytes: &[u8]) -> [u8; 32] {
+    Keccak256::digest(bytes).into()
+}
+
+fn signer_address(key: &SigningKey) -> [u8; 20] {
+    let public_key = key.verifying_key().to_encoded_point(false);
+    let hash = keccak256(&public_key.as_bytes()[1..]);
+    hash[12..].try_into().expect("20-byte slice")
+}
+
+#[allow(clippy::too_many_arguments)]
+fn sign_legacy(
+    key: &SigningKey,
+    chain_id: u64,
+    nonce: u64,
+    gas_price: u128,
+    gas_limit: u64,
+    to: &[u8; 20],
+    value: u128,
+    data: &[u8],
+) -> Result<PreparedTransaction> {
+    let fields = [
+        rlp_uint(nonce as u128),
+        rlp_uint(gas_price),
+        rlp_uint(gas_limit as u128),
+        rlp_bytes(to),
+        rlp_uint(value),
+        rlp_bytes(data),
+    ];
+
+    // EIP-155: the chain id is signed over in place of v, r, s
+    let mut unsigned = fields.to_vec();
+    unsigned.extend([rlp_uint(chain_id as u128), rlp_uint(0), rlp_uint(0)]);
+    let (signature, recovery_id) = key
+        .sign_prehash_recoverable(&keccak256(&rlp_list(&unsigned)))
+        .map_err(|e| PeerPowerError::BlockchainError {
+            reason: format!("Failed to sign transaction: {}", e),
+        })?;
+
+    let v = chain_id as u128 * 2 + 35 + recovery_id.to_byte() as u128;
+    let (r, s) = signature.split_bytes();
+    let mut signed = fields.to_vec();
+    signed.extend([
+        rlp_uint(v),
+        rlp_bytes(strip_zeros(&r)),
+        rlp_bytes(strip_zeros(&s)),
+    ]);
+
+    let raw = rlp_list(&signed);
+    Ok(PreparedTransaction {
+        hash: format!("0x{}", hex::encode(keccak256(&raw))),
+        nonce,
+        raw,
+    })
+}
+
+fn strip_zeros(bytes: &[u8]) -> &[u8] {
+    let start = bytes
+        .iter()
+        .position(|byte| *byte != 0)
+        .unwrap_or(bytes.len());
+    &bytes[start..]
+}
+
+fn rlp_uint(value: u128) -> Vec<u8> {
+    rlp_bytes(strip_zeros(&value.to_be_bytes()))
+}
+
+fn rlp_bytes(bytes: &[u8]) -> Vec<u8> {
+    if bytes.len() == 1 && bytes[0] < 0x80 {
+        return bytes.to_vec();
+    }
+    let mut encoded = rlp_length(bytes.len(), 0x80);
+    encoded.extend_from_slice(bytes);
+    encoded
+}
+
+fn rlp_list(items: &[Vec<u8>]) -> Vec<u8> {
+    let payload = items.concat();
+    let mut encoded = rlp_length(payload.len(), 0xc0);
+    encoded.extend(payload);
+    encoded
+}
+
+fn rlp_length(length: usize, offset: u8) -> Vec<u8> {
+    if length < 56 {
+        return vec![offset + length as u8];
+    }
+    let length_bytes = strip_zeros(&length.to_be_bytes()).to_vec();
+    let mut encoded = vec![offset + 55 + length_bytes.len() as u8];
+    encoded.extend(length_bytes);
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_legacy_matches_eip155_example() {
+        // The worked example from EIP-155
+        let key = SigningKey::from_slice(&[0x46; 32]).unwrap();
+        let signed = sign_legacy(
+            &key,
+            1,
+            9,
+            20_000_000_000,
+            21_000,
+            &[0x35; 20],
+            1_000_000_000_000_000_000,
+            &[],
+        )
+        .unwrap();
+
+        assert_eq!(
+            hex::encode(&signed.raw),
+            "f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a7\
+             6400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a0\
+             67cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83"
+        );
+        assert_eq!(token_units(12.5, 18), 12_500_000_000_000_000_000);
+        assert_eq!(from_token_units(12_500_000_000_000_000_000, 18), 12.5);
+        assert!(is_wallet_address(
+            "0x3535353535353535353535353535353535353535"
+        ));
+        assert!(!is_wallet_address(
+            "3535353535353535353535353535353535353535"
+        ));
+    }
+
+    #[test]
+    fn test_confirmation_depth() {
+        assert_eq!(
+            confirmation_status(100, 100, 1),
+            TransactionStatus::Confirmed
+        );
+        assert_eq!(confirmation_status(100, 101, 3), TransactionStatus::Pending);
+        assert_eq!(
+            confirmation_status(100, 102, 3),
+            TransactionStatus::Confirmed
+        );
+    }
+}
@@ -0,0 +1,404 @@
+use mongodb::bson::{doc, Document};
+use mongodb::{Collection, Database};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::config::{SelendraConfig, StakingConfig};
+use crate::domain::entities::{
+    PayoutMethodKind, Provider, ProviderStake, StakeSlash, StakeSlashStatus,
+};
+use crate::domain::services::BlockchainService;
+use crate::infrastructure::blockchain::selendra_client::{
+    from_token_units, keccak256, parse_address, token_units,
+};
+use crate::shared::utils::stored_timestamp;
+use crate::shared::{PeerPowerError, Result};
+
+/// Provider stakes held by the PPT staking contract.
+///
+/// Providers lock PPT in the contract from the wallet they are paid out to;
+/// the contract is the source of truth and is read on demand with
+/// `stakeOf(address)`. The stake tier raises the provider's daily limit
+/// (capped like admin limits while the device is unattested) and large
+/// enough stakes get priority in job assignment. When an admin confirms a
+/// delivery confirmation was fraudulent, part of the stake is taken with
+/// `slash(address,uint256)` from the platform key.
+pub struct ProviderStaking {
+    providers: Collection<Provider>,
+    slashes: Collection<StakeSlash>,
+    blockchain: Arc<dyn BlockchainService>,
+    config: StakingConfig,
+    contract: String,
+    token_decimals: u32,
+    unattested_max_daily_messages: u32,
+}
+
+impl ProviderStaking {
+    pub fn new(
+        database: Arc<Database>,
+        blockchain: Arc<dyn BlockchainService>,
+        config: StakingConfig,
+        selendra: &SelendraConfig,
+        unattested_max_daily_messages: u32,
+    ) -> Self {
+        Self {
+            providers: database.collection("providers"),
+            slashes: database.collection("stake_slashes"),
+            blockchain,
+            config,
+            contract: selendra.staking_contract_address.clone(),
+            token_decimals: selendra.token_decimals,
+            unattested_max_daily_messages,
+        }
+    }
+
+    pub fn is_configured(&self) -> bool {
+        self.blockchain.is_configured() && parse_address(&self.contract).is_some()
+    }
+
+    fn ensure_configured(&self) -> Result<()> {
+        if self.is_configured() {
+            Ok(())
+        } else {
+            Err(PeerPowerError::Configuration {
+                message: "Provider staking is not configured".to_string(),
+            })
+        }
+    }
+
+    /// Read the provider's stake from the contract and store the limits it unlocks
+    pub async fn sync(&self, provider: &Provider) -> Result<ProviderStake> {
+        self.ensure_configured()?;
+        let wallet = staking_wallet(provider)?;
+
+        let result = self
+            .blockchain
+            .call(
+                &self.contract,
+                &encode_call("stakeOf(address)", &wallet, None)?,
+            )
+            .await?;
+        let units = decode_uint(&result)?;
+        let stake = self.stake_for(
+            provider,
+            wallet,
+            from_token_units(units, self.token_decimals),
+        );
+
+        self.store(&provider.id, &stake).await?;
+        Ok(stake)
+    }
+
+    fn stake_for(&self, provider: &Provider, wallet_address: String, amount: f64) -> ProviderStake {
+        let attested = provider
+            .attestation
+            .as_ref()
+            .is_some_and(|attestation| attestation.passed);
+        let mut max_daily_messages = self.config.daily_limit(amount);
+        if !attested {
+            max_daily_messages = max_daily_messages.min(self.unattested_max_daily_messages);
+        }
+        ProviderStake {
+            wallet_address,
+            amount,
+            max_daily_messages,
+            priority: self.config.has_priority(amount),
+            synced_at: crate::shared::utils::now(),
+        }
+    }
+
+    async fn store(&self, provider_id: &str, stake: &ProviderStake) -> Result<()> {
+        self.providers
+            .update_one(doc! {"id": provider_id}, stake_update(stake)?, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to store stake: {}", e),
+            })?;
+        Ok(())
+    }
+
+    /// Take `slash_percent` of the provider's stake for a fraudulent delivery
+    /// confirmation. Each message is slashed at most once, though a failed
+    /// slash can be retried; the stored stake is reduced right away rather
+    /// than waiting for the transaction to be mined.
+    pub async fn slash(
+        &self,
+        provider: &Provider,
+        message_id: &str,
+        reason: &str,
+        slashed_by: &str,
+    ) -> Result<StakeSlash> {
+        let stake = self.sync(provider).await?;
+        let amount =
+            (stake.amount * self.config.slash_percent / 100.0 * 1_000_000.0).floor() / 1_000_000.0;
+        if amount <= 0.0 {
+            return Err(PeerPowerError::ValidationError {
+                field: "provider_id".to_string(),
+                message: "Provider has no stake to slash".to_string(),
+            });
+        }
+
+        let mut slash = StakeSlash::new(
+            provider.id.clone(),
+            message_id.to_string(),
+            stake.wallet_address.clone(),
+            amount,
+            reason.to_string(),
+            slashed_by.to_string(),
+        );
+        let already_slashed = self
+            .slashes
+            .find_one(
+                doc! {
+                    "message_id": message_id,
+                    "status": {"$ne": format!("{:?}", StakeSlashStatus::Failed)},
+                },
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch slashes: {}", e),
+            })?;
+        if already_slashed.is_some() {
+            return Err(PeerPowerError::ValidationError {
+                field: "message_id".to_string(),
+                message: "The provider's stake was already slashed for this message".to_string(),
+            });
+        }
+        self.slashes
+            .insert_one(&slash, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to record slash: {}", e),
+            })?;
+
+        let data = encode_call(
+            "slash(address,uint256)",
+            &stake.wallet_address,
+            Some(token_units(amount, self.token_decimals)),
+        )?;
+        let transaction = match self.blockchain.prepare(&self.contract, &data).await {
+            Ok(transaction) => transaction,
+            Err(e) => {
+                self.fail(&mut slash, &e.to_string()).await?;
+                return Err(e);
+            }
+        };
+
+        // Record the hash before broadcasting so it's tracked either way
+        slash.status = StakeSlashStatus::Submitted;
+        slash.nonce = Some(transaction.nonce);
+        slash.tx_hash = Some(transaction.hash.clone());
+        self.update(
+            &slash.id,
+            doc! {
+                "status": format!("{:?}", slash.status),
+                "nonce": transaction.nonce as i64,
+                "tx_hash": &transaction.hash,
+            },
+        )
+        .await?;
+
+        match self.blockchain.broadcast(&transaction).await {
+            Ok(()) => {}
+            Err(PeerPowerError::BlockchainError { reason }) => {
+                self.fail(&mut slash, &reason).await?;
+                return Err(PeerPowerError::BlockchainError { reason });
+            }
+            // Unknown whether it went out; the hash is on record to check
+            Err(e) => warn!(
+                "Broadcast of stake slash {} ({}) unconfirmed: {}",
+                slash.id, transaction.hash, e
+            ),
+        }
+        metrics::counter!("stake_slashes_total").increment(1);
+        info!(
+            "Slashed {} PPT from provider {} for message {}",
+            amount, provider.id, message_id
+        );
+
+        let remaining = self.stake_for(
+            provider,
+            stake.wallet_address,
+            (stake.amount - amount).max(0.0),
+        );
+        self.store(&provider.id, &remaining).await?;
+        Ok(slash)
+    }
+
+    async fn fail(&self, slash: &mut StakeSlash, reason: &str) -> Result<()> {
+        warn!("Stake slash {} failed: {}", slash.id, reason);
+        slash.status = StakeSlashStatus::Failed;
+        slash.failure_reason = Some(reason.to_string());
+        self.update(
+            &slash.id,
+            doc! {
+                "status": format!("{:?}", slash.status),
+                "failure_reason": reason,
+            },
+        )
+        .await
+    }
+
+    async fn update(&self, slash_id: &str, set: Document) -> Result<()> {
+        self.slashes
+            .update_one(doc! {"id": slash_id}, slash_update(set), None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to update slash: {}", e),
+            })?;
+        Ok(())
+    }
+}
+
+/// The wallet stakes are read for: the provider's PPT wallet payout method,
+/// the default one if there are several
+fn staking_wallet(provider: &Provider) -> Result<String> {
+    provider
+        .payout_methods
+        .iter()
+        .filter(|method| method.kind == PayoutMethodKind::PptWallet)
+        .max_by_key(|method| method.is_default)
+        .map(|method| method.account_reference.clone())
+        .ok_or_else(|| PeerPowerError::ValidationError {
+            field: "payout_methods".to_string(),
+            message: "Add a PPT wallet payout method to stake from".to_string(),
+        })
+}
+
+/// ABI-encode a call taking an address and, optionally, a uint256
+fn encode_call(signature: &str, address: &str, amount: Option<u128>) -> Result<Vec<u8>> {
+    let address = parse_address(address).ok_or_else(|| PeerPowerError::ValidationError {
+        field: "wallet_address".to_string(),
+        message: format!("Invalid wallet address: {}", address),
+    })?;
+    let mut data = keccak256(signature.as_bytes())[..4].to_vec();
+    data.extend_from_slice(&[0u8; 12]);
+    data.extend_from_slice(&address);
+    if let Some(amount) = amount {
+        data.extend_from_slice(&[0u8; 16]);
+        data.extend_from_slice(&amount.to_be_bytes());
+    }
+    Ok(data)
+}
+
+/// A uint256 return value; stakes above u128 aren't realistic and are refused
+fn decode_uint(result: &[u8]) -> Result<u128> {
+    let invalid = || PeerPowerError::ExternalService {
+        service: "Selendra".to_string(),
+        message: format!("Unexpected stakeOf result: 0x{}", hex::encode(result)),
+    };
+    if result.len() != 32 || result[..16].iter().any(|byte| *byte != 0) {
+        return Err(invalid());
+    }
+    Ok(u128::from_be_bytes(
+        result[16..].try_into().map_err(|_| invalid())?,
+    ))
+}
+
+fn stake_update(stake: &ProviderStake) -> Result<Document> {
+    let stake_bson = mongodb::bson::to_bson(stake).map_err(|e| PeerPowerError::Internal {
+        message: format!("Failed to serialize stake: {}", e),
+    })?;
+    Ok(doc! {"$set": {
+        "stake": stake_bson,
+        "updated_at": stored_timestamp(crate::shared::utils::now()),
+    }})
+}
+
+fn slash_update(mut set: Document) -> Document {
+    set.insert("updated_at", stored_timestamp(crate::shared::utils::now()));
+    doc! {"$set": set}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::test_support::read_back;
+    use crate::shared::types::{Carrier, PhoneNumber};
+
+    #[test]
+    fn test_encode_stake_calls() {
+        let wallet = "0x3535353535353535353535353535353535353535";
+        let stake_of = encode_call("stakeOf(address)", wallet, None).unwrap();
+        assert_eq!(stake_of.len(), 36);
+        assert_eq!(&stake_of[16..], &[0x35; 20]);
+
+        let slash = encode_call("slash(address,uint256)", wallet, Some(5)).unwrap();
+        assert_eq!(slash.len(), 68);
+        assert_eq!(slash[67], 5);
+        assert!(encode_call("stakeOf(address)", "not-a-wallet", None).is_err());
+
+        let mut word = [0u8; 32];
+        word[31] = 42;
+        assert_eq!(decode_uint(&word).unwrap(), 42);
+        word[0] = 1;
+        assert!(decode_uint(&word).is_err());
+    }
+
+    #[test]
+    fn test_stake_tiers() {
+        let config = StakingConfig {
+            tiers: vec![
+                crate::config::StakeTier {
+                    min_stake: 100.0,
+                    max_daily_messages: 200,
+                },
+                crate::config::StakeTier {
+                    min_stake: 500.0,
+                    max_daily_messages: 500,
+                },
+            ],
+            priority_min_stake: 500.0,
+            slash_percent: 10.0,
+        };
+        assert_eq!(config.daily_limit(50.0), 0);
+        assert_eq!(config.daily_limit(100.0), 200);
+        assert_eq!(config.daily_limit(750.0), 500);
+        assert!(!config.has_priority(499.0));
+        assert!(config.has_priority(500.0));
+    }
+
+    #[test]
+    fn test_stored_stake_reads_back() {
+        let provider = Provider::new(
+            "user-1".to_string(),
+            PhoneNumber::new("+85512345678".to_string()).unwrap(),
+            Carrier::Smart,
+        );
+        let stake = ProviderStake {
+            wallet_address: "0x3535353535353535353535353535353535353535".to_string(),
+            amount: 150.0,
+            max_daily_messages: 200,
+            priority: false,
+            synced_at: crate::shared::utils::now(),
+        };
+
+        let staked = read_back(&provider, &stake_update(&stake).unwrap());
+        let stored = staked.stake.unwrap();
+        assert_eq!(stored.max_daily_messages, 200);
+        assert_eq!(stored.synced_at, stake.synced_at);
+    }
+
+    #[test]
+    fn test_failed_slash_reads_back() {
+        let slash = StakeSlash::new(
+            "provider-1".to_string(),
+            "message-1".to_string(),
+            "0x3535353535353535353535353535353535353535".to_string(),
+            5.0,
+            "Fraudulent confirmation".to_string(),
+            "admin-1".to_string(),
+        );
+
+        let failed = read_back(
+            &slash,
+            &slash_update(doc! {
+                "status": format!("{:?}", StakeSlashStatus::Failed),
+                "failure_reason": "Nonce too low",
+            }),
+        );
+        assert_eq!(failed.status, StakeSlashStatus::Failed);
+        assert_eq!(failed.failure_reason.as_deref(), Some("Nonce too low"));
+    }
+}
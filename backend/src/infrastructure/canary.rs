@@ -0,0 +1,126 @@
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+
+use crate::config::InstanceConfig;
+use crate::infrastructure::database::RedisConnection;
+use crate::shared::types::DeploymentCohort;
+use crate::shared::{PeerPowerError, Result};
+
+/// Runtime override for the share of new messages routed to canaries
+const TRAFFIC_PERCENT_KEY: &str = "canary:traffic_percent";
+
+/// Refreshed by every live canary instance; no key means no canary is running
+const CANARY_ALIVE_KEY: &str = "canary:alive";
+const CANARY_ALIVE_TTL_SECONDS: usize = 30;
+
+/// Routes a share of newly submitted messages to canary instances.
+///
+/// The percentage starts from `InstanceConfig` and can be changed at runtime
+/// through Redis so every instance agrees on it. Messages are bucketed by a
+/// hash of their ID, and only routed to the canary cohort while a canary
+/// instance is heartbeating.
+pub struct CanaryRouter {
+    redis: RedisConnection,
+    instance: InstanceConfig,
+}
+
+impl CanaryRouter {
+    pub fn new(redis: RedisConnection, instance: InstanceConfig) -> Self {
+        if instance.canary {
+            info!("Instance {} is running as a canary", instance.id);
+        }
+        Self { redis, instance }
+    }
+
+    pub async fn traffic_percent(&self) -> Result<u8> {
+        Ok(self
+            .redis
+            .get(TRAFFIC_PERCENT_KEY)
+            .await?
+            .and_then(|value| value.parse::<u8>().ok())
+            .unwrap_or(self.instance.canary_traffic_percent)
+            .min(100))
+    }
+
+    pub async fn set_traffic_percent(&self, percent: u8) -> Result<()> {
+        if percent > 100 {
+            return Err(PeerPowerError::ValidationError {
+                field: "traffic_percent".to_string(),
+                message: "Canary traffic must be between 0 and 100 percent".to_string(),
+            });
+        }
+        self.redis
+            .set(TRAFFIC_PERCENT_KEY, &percent.to_string(), None)
+            .await
+    }
+
+    /// Mark this canary instance as alive (no-op on stable instances)
+    pub async fn announce(&self) -> Result<()> {
+        if !self.instance.canary {
+            return Ok(());
+        }
+        self.redis
+            .set(
+                CANARY_ALIVE_KEY,
+                &self.instance.id,
+                Some(CANARY_ALIVE_TTL_SECONDS),
+            )
+            .await
+    }
+
+    pub async fn is_canary_alive(&self) -> Result<bool> {
+        Ok(self.redis.get(CANARY_ALIVE_KEY).await?.is_some())
+    }
+
+    /// Pick the cohort for a newly submitted message
+    pub async fn assign(&self, message_id: &str) -> DeploymentCohort {
+        let percent = match self.traffic_percent().await {
+            Ok(percent) => percent,
+            Err(e) => {
+                warn!("Failed to read canary traffic share, using stable: {}", e);
+                return DeploymentCohort::Stable;
+            }
+        };
+        if percent == 0 || Self::bucket(message_id) >= percent {
+            return DeploymentCohort::Stable;
+        }
+
+        match self.is_canary_alive().await {
+            Ok(true) => DeploymentCohort::Canary,
+            Ok(false) => DeploymentCohort::Stable,
+            Err(e) => {
+                warn!("Failed to check canary liveness, using stable: {}", e);
+                DeploymentCohort::Stable
+            }
+        }
+    }
+
+    /// Cohorts this instance should dispatch; stable instances drain canary
+    /// queues when no canary is alive so nothing is stranded
+    pub async fn cohorts_to_serve(&self) -> Result<Vec<DeploymentCohort>> {
+        if self.instance.canary {
+            return Ok(vec![DeploymentCohort::Canary]);
+        }
+        if self.is_canary_alive().await? {
+            Ok(vec![DeploymentCohort::Stable])
+        } else {
+            Ok(vec![DeploymentCohort::Stable, DeploymentCohort::Canary])
+        }
+    }
+
+    /// Count a message outcome against its cohort for rollout comparison
+    pub fn record_outcome(cohort: DeploymentCohort, outcome: &'static str) {
+        metrics::counter!(
+            "message_outcomes_total",
+            "cohort" => cohort.as_str(),
+            "outcome" => outcome
+        )
+        .increment(1);
+    }
+
+    /// Stable 0..100 bucket for a message ID
+    fn bucket(message_id: &str) -> u8 {
+        let digest = Sha256::digest(message_id.as_bytes());
+        (u16::from_be_bytes([digest[0], digest[1]]) % 100) as u8
+    }
+}
@@ -0,0 +1,391 @@
+use futures::stream::TryStreamExt;
+use mongodb::bson::{doc, Document};
+use mongodb::options::FindOptions;
+use mongodb::{Collection, Database};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::domain::entities::{CancellationReason, CancellationStatus, Job, Message, Provider};
+use crate::infrastructure::database::RedisConnection;
+use crate::infrastructure::messaging::fcm_service::FcmService;
+use crate::shared::types::MessageStatus;
+use crate::shared::utils::stored_timestamp;
+use crate::shared::{PeerPowerError, Result};
+
+const SWEEP_LOCK_KEY: &str = "messages:cancellations:sweep_lock";
+
+/// How often dispatched messages are checked for expiry and unanswered
+/// cancellations
+const SWEEP_INTERVAL_SECONDS: u64 = 30;
+
+/// How long a device gets to answer a cancellation before it is treated as
+/// not sent; as long as a job gets to complete
+const ACK_TIMEOUT_MINUTES: i64 = 10;
+
+/// Messages handled per sweep step at most
+const SWEEP_BATCH_SIZE: i64 = 200;
+
+/// Cancellation of messages, including ones already pushed to a provider.
+///
+/// A message not yet dispatched is cancelled at once. One already on a
+/// provider's device gets a `cancel_dispatch` FCM message and stays `Sent`
+/// until the device answers: if it dropped the message, or never answers
+/// within `ACK_TIMEOUT_MINUTES`, the message is cancelled and the client is
+/// never charged for it; if it had already sent it, its delivery
+/// confirmation is charged as usual. Dispatched messages that expire are
+/// cancelled the same way.
+pub struct CancellationService {
+    messages: Collection<Message>,
+    jobs: Collection<Job>,
+    providers: Collection<Provider>,
+    fcm_service: Arc<dyn FcmService>,
+    redis: RedisConnection,
+}
+
+impl CancellationService {
+    pub fn new(
+        database: Arc<Database>,
+        fcm_service: Arc<dyn FcmService>,
+        redis: RedisConnection,
+    ) -> Self {
+        Self {
+            messages: database.collection("messages"),
+            jobs: database.collection("jobs"),
+            providers: database.collection("providers"),
+            fcm_service,
+            redis,
+        }
+    }
+
+    pub fn start(self: Arc<Self>) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(SWEEP_INTERVAL_SECONDS));
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.sweep().await {
+                    error!("Cancellation sweep failed: {}", e);
+                }
+            }
+        });
+    }
+
+    /// Cancel one of the client's messages
+    pub async fn cancel(&self, client_id: &str, message_id: &str) -> Result<Message> {
+        let mut message = self
+            .find_message(doc! {"id": message_id, "client_id": client_id})
+            .await?;
+        self.request(&mut message, CancellationReason::Client)
+            .await?;
+        Ok(message)
+    }
+
+    /// Cancel any client's message, for an operator
+    pub async fn cancel_as_admin(&self, message_id: &str) -> Result<Message> {
+        let mut message = self.find_message(doc! {"id": message_id}).await?;
+        self.request(&mut message, CancellationReason::Admin)
+            .await?;
+        Ok(message)
+    }
+
+    /// A provider's answer to `cancel_dispatch`: `cancelled` when the
+    /// device dropped the message, false when it had already sent it
+    pub async fn acknowledge(
+        &self,
+        provider_id: &str,
+        message_id: &str,
+        cancelled: bool,
+    ) -> Result<Message> {
+        let mut message = self
+            .find_message(doc! {"id": message_id, "provider_id": provider_id})
+            .await?;
+
+        let status = if cancelled {
+            CancellationStatus::Confirmed
+        } else {
+            CancellationStatus::AlreadySent
+        };
+        self.resolve(&mut message, status).await?;
+        Ok(message)
+    }
+
+    /// Cancel a message the dispatcher pushed to `provider` after the
+    /// client had cancelled it: it goes back to `Sent` with the
+    /// cancellation waiting on the device, as if cancelled after dispatch
+    pub async fn cancel_late_dispatch(&self, message: &Message, provider: &Provider) -> Result<()> {
+        self.messages
+            .update_one(
+                doc! {"id": &message.id, "status": format!("{:?}", MessageStatus::Cancelled)},
+                late_dispatch(message, &provider.id),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to update message: {}", e),
+            })?;
+        self.cancel_job(&message.id).await?;
+        self.notify(provider, &message.id, "client").await;
+        Ok(())
+    }
+
+    async fn request(&self, message: &mut Message, reason: CancellationReason) -> Result<()> {
+        let previous = message.status.clone();
+        let dispatched = message.request_cancellation(reason)?;
+        self.save(message, &previous).await?;
+
+        if dispatched {
+            let provider = match &message.provider_id {
+                Some(provider_id) => self
+                    .providers
+                    .find_one(doc! {"id": provider_id}, None)
+                    .await
+                    .map_err(|e| PeerPowerError::Database {
+                        message: format!("Failed to fetch provider: {}", e),
+                    })?,
+                None => None,
+            };
+            // Without a device to ask, the cancellation times out
+            if let Some(provider) = provider {
+                let reason = format!("{:?}", reason).to_lowercase();
+                self.notify(&provider, &message.id, &reason).await;
+            }
+        } else {
+            self.cancel_job(&message.id).await?;
+        }
+
+        metrics::counter!(
+            "message_cancellations_total",
+            "reason" => format!("{:?}", reason).to_lowercase(),
+            "dispatched" => dispatched.to_string()
+        )
+        .increment(1);
+        info!(
+            "Message {} cancelled ({:?}){}",
+            message.id,
+            reason,
+            if dispatched {
+                ", waiting on the provider"
+            } else {
+                ""
+            }
+        );
+        Ok(())
+    }
+
+    async fn resolve(&self, message: &mut Message, status: CancellationStatus) -> Result<()> {
+        let previous = message.status.clone();
+        message.resolve_cancellation(status)?;
+        self.save(message, &previous).await?;
+        if message.status == MessageStatus::Cancelled {
+            self.cancel_job(&message.id).await?;
+        }
+
+        metrics::counter!(
+            "message_cancellations_resolved_total",
+            "outcome" => format!("{:?}", status).to_lowercase()
+        )
+        .increment(1);
+        info!(
+            "Cancellation of message {} resolved: {:?}",
+            message.id, status
+        );
+        Ok(())
+    }
+
+    /// Tell the device; a failure is left to the acknowledgment timeout
+    async fn notify(&self, provider: &Provider, message_id: &str, reason: &str) {
+        let Some(fcm_token) = provider.fcm_token.as_deref() else {
+            warn!(
+                "Provider {} has no FCM token; cancellation of {} will time out",
+                provider.id, message_id
+            );
+            return;
+        };
+        if let Err(e) = self
+            .fcm_service
+            .send_cancel_dispatch(fcm_token, message_id, reason)
+            .await
+        {
+            warn!(
+                "Failed to send cancellation of {} to provider {}: {}",
+                message_id, provider.id, e
+            );
+        }
+    }
+
+    /// Store the message if it is still in `previous` status, so a delivery
+    /// confirmation that landed meanwhile isn't overwritten
+    async fn save(&self, message: &Message, previous: &MessageStatus) -> Result<()> {
+        let result = self
+            .messages
+            .replace_one(
+                doc! {"id": &message.id, "status": format!("{:?}", previous)},
+                message,
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to update message: {}", e),
+            })?;
+        if result.matched_count == 0 {
+            return Err(PeerPowerError::ValidationError {
+                field: "message".to_string(),
+                message: "The message changed while being cancelled; try again".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    async fn cancel_job(&self, message_id: &str) -> Result<()> {
+        let job = self
+            .jobs
+            .find_one(doc! {"message_id": message_id}, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch job: {}", e),
+            })?;
+        if let Some(mut job) = job {
+            job.mark_cancelled();
+            self.jobs
+                .replace_one(doc! {"id": &job.id}, &job, None)
+                .await
+                .map_err(|e| PeerPowerError::Database {
+                    message: format!("Failed to update job: {}", e),
+                })?;
+        }
+        Ok(())
+    }
+
+    /// Cancel dispatched messages that expired, and time out cancellations
+    /// their devices never answered; one instance at a time
+    pub async fn sweep(&self) -> Result<()> {
+        if !self
+            .redis
+            .acquire_lock(SWEEP_LOCK_KEY, SWEEP_INTERVAL_SECONDS as usize * 2)
+            .await?
+        {
+            return Ok(());
+        }
+        let result = match self.sweep_expired().await {
+            Ok(()) => self.sweep_unanswered().await,
+            failed => failed,
+        };
+        self.redis.release_lock(SWEEP_LOCK_KEY).await?;
+        result
+    }
+
+    async fn sweep_expired(&self) -> Result<()> {
+        let now = stored_timestamp(crate::shared::utils::now());
+        let expired = self
+            .find(doc! {
+                "status": format!("{:?}", MessageStatus::Sent),
+                "cancellation": null,
+                "expires_at": {"$lt": now},
+            })
+            .await?;
+        for mut message in expired {
+            if let Err(e) = self
+                .request(&mut message, CancellationReason::Expired)
+                .await
+            {
+                warn!("Failed to cancel expired message {}: {}", message.id, e);
+            }
+        }
+        Ok(())
+    }
+
+    async fn sweep_unanswered(&self) -> Result<()> {
+        let cutoff = stored_timestamp(
+            crate::shared::utils::now() - chrono::Duration::minutes(ACK_TIMEOUT_MINUTES),
+        );
+        let unanswered = self
+            .find(doc! {
+                "status": format!("{:?}", MessageStatus::Sent),
+                "cancellation.status": "requested",
+                "cancellation.requested_at": {"$lt": cutoff},
+            })
+            .await?;
+        for mut message in unanswered {
+            if let Err(e) = self
+                .resolve(&mut message, CancellationStatus::TimedOut)
+                .await
+            {
+                warn!("Failed to time out cancellation of {}: {}", message.id, e);
+            }
+        }
+        Ok(())
+    }
+
+    async fn find_message(&self, filter: Document) -> Result<Message> {
+        let message_id = filter.get_str("id").unwrap_or_default().to_string();
+        self.messages
+            .find_one(filter, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch message: {}", e),
+            })?
+            .ok_or_else(|| PeerPowerError::NotFound {
+                resource: format!("Message with ID: {}", message_id),
+            })
+    }
+
+    async fn find(&self, filter: Document) -> Result<Vec<Message>> {
+        self.messages
+            .find(
+                filter,
+                FindOptions::builder().limit(SWEEP_BATCH_SIZE).build(),
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to query messages: {}", e),
+            })?
+            .try_collect()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read messages: {}", e),
+            })
+    }
+}
+
+/// The update taking a cancelled `message` back to `Sent` on `provider_id`'s
+/// device with its cancellation requested there
+fn late_dispatch(message: &Message, provider_id: &str) -> Document {
+    doc! {"$set": {
+        "status": format!("{:?}", MessageStatus::Sent),
+        "provider_id": provider_id,
+        "dispatched_at": message.dispatched_at.map(stored_timestamp),
+        "cancellation.status": "requested",
+        "cancellation.requested_at": stored_timestamp(crate::shared::utils::now()),
+        "cancellation.resolved_at": null,
+    }}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entities::MessagePriority;
+    use crate::shared::test_support::read_back;
+    use crate::shared::types::PhoneNumber;
+
+    #[test]
+    fn test_late_dispatch_reads_back() {
+        let mut message = Message::new(
+            "client-1".to_string(),
+            "Hello".to_string(),
+            PhoneNumber::new("+85512345678".to_string()).unwrap(),
+            MessagePriority::Normal,
+            None,
+            None,
+        );
+        message
+            .request_cancellation(CancellationReason::Client)
+            .unwrap();
+
+        let sent = read_back(&message, &late_dispatch(&message, "provider-1"));
+        assert_eq!(sent.status, MessageStatus::Sent);
+        assert_eq!(sent.provider_id.as_deref(), Some("provider-1"));
+        let cancellation = sent.cancellation.unwrap();
+        assert_eq!(cancellation.status, CancellationStatus::Requested);
+        assert!(cancellation.resolved_at.is_none());
+    }
+}
@@ -0,0 +1,71 @@
+use std::sync::Mutex;
+use tracing::warn;
+
+use crate::infrastructure::database::RedisConnection;
+use crate::shared::types::Carrier;
+use crate::shared::Result;
+
+/// Carriers currently held back from dispatch, shared by all instances
+const PAUSED_CARRIERS_KEY: &str = "dispatch:paused_carriers";
+
+/// Kill switch for a carrier: while paused, messages to its numbers stay
+/// queued instead of being dispatched (e.g. during a carrier outage or a
+/// filtering incident). In-flight messages are not recalled.
+///
+/// The paused set last read is kept, so dispatch keeps honoring it while
+/// Redis is unavailable.
+pub struct CarrierKillSwitch {
+    redis: RedisConnection,
+    last_known: Mutex<Vec<String>>,
+}
+
+impl CarrierKillSwitch {
+    pub fn new(redis: RedisConnection) -> Self {
+        Self {
+            redis,
+            last_known: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub async fn pause(&self, carrier: &Carrier) -> Result<()> {
+        self.redis
+            .sadd(PAUSED_CARRIERS_KEY, &format!("{:?}", carrier))
+            .await?;
+        Ok(())
+    }
+
+    pub async fn resume(&self, carrier: &Carrier) -> Result<()> {
+        self.redis
+            .srem(PAUSED_CARRIERS_KEY, &format!("{:?}", carrier))
+            .await?;
+        Ok(())
+    }
+
+    pub async fn paused(&self) -> Result<Vec<String>> {
+        match self.redis.smembers(PAUSED_CARRIERS_KEY).await {
+            Ok(paused) => {
+                if let Ok(mut last_known) = self.last_known.lock() {
+                    last_known.clone_from(&paused);
+                }
+                Ok(paused)
+            }
+            Err(e) if e.is_redis_unavailable() => {
+                warn!(
+                    "Failed to read paused carriers, using the last known: {}",
+                    e
+                );
+                Ok(self
+                    .last_known
+                    .lock()
+                    .map(|last_known| last_known.clone())
+                    .unwrap_or_default())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    pub async fn is_paused(&self, carrier: &Carrier) -> Result<bool> {
+        let name = format!("{:?}", carrier);
+        Ok(self.paused().await?.contains(&name))
+    }
+}
@@ -0,0 +1,158 @@
+use futures::stream::TryStreamExt;
+use mongodb::{Collection, Database};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::domain::entities::Message;
+use crate::infrastructure::recipient_privacy::RecipientVault;
+use crate::shared::types::{Carrier, MessageStatus, PhoneNumber};
+use crate::shared::utils::stored_timestamp;
+use crate::shared::{PeerPowerError, Result};
+
+/// Outcome of re-detecting carriers across queued messages
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CarrierRedetectionReport {
+    pub dry_run: bool,
+    pub scanned: u64,
+    pub corrected: u64,
+    pub undecryptable: u64,
+    pub changes: BTreeMap<String, u64>, // "Smart -> Metfone" => count
+}
+
+/// Keeps `recipient_carrier` in line with the current prefix table.
+///
+/// A message's carrier is detected once at submission, so messages still
+/// queued when the prefix table changes (new ranges, ported numbers) carry
+/// a stale value. Dispatch re-checks each message it picks up, and admins
+/// can sweep the whole queue after a prefix table update.
+pub struct CarrierRedetector {
+    messages: Collection<Message>,
+    vault: Arc<RecipientVault>,
+}
+
+impl CarrierRedetector {
+    pub fn new(database: Arc<Database>, vault: Arc<RecipientVault>) -> Self {
+        Self {
+            messages: database.collection("messages"),
+            vault,
+        }
+    }
+
+    fn record_correction(previous: &Carrier, current: &Carrier, source: &'static str) {
+        metrics::counter!(
+            "recipient_carrier_corrections_total",
+            "from" => format!("{:?}", previous),
+            "to" => format!("{:?}", current),
+            "source" => source
+        )
+        .increment(1);
+    }
+
+    /// Correct a message about to be dispatched; `recipient` is the real number
+    pub async fn correct_at_dispatch(
+        &self,
+        message: &mut Message,
+        recipient: &PhoneNumber,
+    ) -> Result<()> {
+        let Some(previous) = message.redetect_carrier(recipient) else {
+            return Ok(());
+        };
+
+        info!(
+            "Message {} recipient carrier corrected from {:?} to {:?}",
+            message.id, previous, message.recipient_carrier
+        );
+        Self::record_correction(&previous, &message.recipient_carrier, "dispatch");
+        self.store_carrier(message).await
+    }
+
+    /// Re-detect every message still waiting for dispatch
+    pub async fn redetect_queued(&self, dry_run: bool) -> Result<CarrierRedetectionReport> {
+        let mut cursor = self
+            .messages
+            .find(
+                mongodb::bson::doc! {
+                    "status": {"$in": [
+                        format!("{:?}", MessageStatus::Pending),
+                        format!("{:?}", MessageStatus::Assigned),
+                    ]},
+                },
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch queued messages: {}", e),
+            })?;
+
+        let mut report = CarrierRedetectionReport {
+            dry_run,
+            ..Default::default()
+        };
+        while let Some(mut message) =
+            cursor
+                .try_next()
+                .await
+                .map_err(|e| PeerPowerError::Database {
+                    message: format!("Failed to read queued messages: {}", e),
+                })?
+        {
+            report.scanned += 1;
+
+            let recipient = match self.vault.reveal(&message) {
+                Ok(recipient) => recipient,
+                Err(e) => {
+                    warn!("Skipping carrier re-detection: {}", e);
+                    report.undecryptable += 1;
+                    continue;
+                }
+            };
+            let Some(previous) = message.redetect_carrier(&recipient) else {
+                continue;
+            };
+
+            report.corrected += 1;
+            *report
+                .changes
+                .entry(format!("{:?} -> {:?}", previous, message.recipient_carrier))
+                .or_default() += 1;
+            if !dry_run {
+                Self::record_correction(&previous, &message.recipient_carrier, "bulk");
+                self.store_carrier(&message).await?;
+            }
+        }
+
+        info!(
+            "Carrier re-detection {}: {} of {} queued messages corrected",
+            if dry_run { "dry run" } else { "applied" },
+            report.corrected,
+            report.scanned
+        );
+        Ok(report)
+    }
+
+    async fn store_carrier(&self, message: &Message) -> Result<()> {
+        let carrier = mongodb::bson::to_bson(&message.recipient_carrier).map_err(|e| {
+            PeerPowerError::Internal {
+                message: format!("Failed to serialize carrier: {}", e),
+            }
+        })?;
+        self.messages
+            .update_one(
+                mongodb::bson::doc! {"id": &message.id},
+                mongodb::bson::doc! {
+                    "$set": {
+                        "recipient_carrier": carrier,
+                        "updated_at": stored_timestamp(crate::shared::utils::now()),
+                    }
+                },
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to update recipient carrier: {}", e),
+            })?;
+        Ok(())
+    }
+}
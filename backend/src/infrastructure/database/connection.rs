@@ -1,6 +1,9 @@
 use mongodb::{
     bson::{doc, oid::ObjectId, Document},
-    options::{ClientOptions, ResolverConfig},
+    options::{
+        ClientOptions, DatabaseOptions, ReadPreference, ReadPreferenceOptions, ResolverConfig,
+        SelectionCriteria,
+    },
     Client, Collection, Database,
 };
 use std::sync::Arc;
@@ -8,12 +11,18 @@ use tokio::time::Duration;
 use tracing::{info, warn};
 
 use crate::config::DatabaseConfig;
+use crate::infrastructure::database::pool_monitor::MongoPoolMonitor;
 use crate::shared::{PeerPowerError, Result};
 
+/// MongoDB's IllegalOperation code, returned for transactions on a standalone server
+const TRANSACTIONS_UNSUPPORTED_CODE: i32 = 20;
+
 #[derive(Clone)]
 pub struct MongoDatabase {
     client: Arc<Client>,
     database: Arc<Database>,
+    reporting: Arc<Database>, // the same database, read with the reporting read preference
+    pool_monitor: Arc<MongoPoolMonitor>,
 }
 
 impl MongoDatabase {
@@ -39,6 +48,11 @@ impl MongoDatabase {
         // Set application name for monitoring
         client_options.app_name = Some("peerpower-backend".to_string());
 
+        // Pool and command metrics, slow operation logging
+        let pool_monitor = Arc::new(MongoPoolMonitor::new(config));
+        client_options.cmap_event_handler = Some(pool_monitor.clone());
+        client_options.command_event_handler = Some(pool_monitor.clone());
+
         // Create client
         let client = Client::with_options(client_options)
             .map_err(|e| PeerPowerError::Database {
@@ -56,12 +70,45 @@ impl MongoDatabase {
 
         info!("Successfully connected to MongoDB database: {}", config.name);
 
+        let read_preference = Self::reporting_read_preference(config)?;
+        info!("Reporting queries read with {}", read_preference);
+        let reporting = client.database_with_options(
+            &config.name,
+            DatabaseOptions::builder()
+                .selection_criteria(SelectionCriteria::ReadPreference(read_preference))
+                .build(),
+        );
+
+        pool_monitor.clone().start_saturation_watch();
+
         Ok(Self {
             client: Arc::new(client),
             database: Arc::new(database),
+            reporting: Arc::new(reporting),
+            pool_monitor,
         })
     }
 
+    fn reporting_read_preference(config: &DatabaseConfig) -> Result<ReadPreference> {
+        let options = ReadPreferenceOptions::builder()
+            .max_staleness(
+                config
+                    .reporting_max_staleness_seconds
+                    .map(|seconds| Duration::from_secs(seconds.max(90))),
+            )
+            .build();
+        match config.reporting_read_preference.as_str() {
+            "primary" => Ok(ReadPreference::Primary),
+            "primaryPreferred" => Ok(ReadPreference::PrimaryPreferred { options }),
+            "secondary" => Ok(ReadPreference::Secondary { options }),
+            "secondaryPreferred" => Ok(ReadPreference::SecondaryPreferred { options }),
+            "nearest" => Ok(ReadPreference::Nearest { options }),
+            other => Err(PeerPowerError::Configuration {
+                message: format!("Unknown DATABASE_REPORTING_READ_PREFERENCE: {}", other),
+            }),
+        }
+    }
+
     pub fn client(&self) -> &Client {
         &self.client
     }
@@ -70,10 +117,34 @@ impl MongoDatabase {
         &self.database
     }
 
+    /// A handle for read-only reporting queries (admin analytics, earnings
+    /// summaries, rollups), which may be served by a secondary and so lag
+    /// slightly behind writes. Never use it to read something just written.
+    pub fn reporting(&self) -> Self {
+        Self {
+            database: self.reporting.clone(),
+            ..self.clone()
+        }
+    }
+
+    pub fn pool_monitor(&self) -> &MongoPoolMonitor {
+        &self.pool_monitor
+    }
+
     pub fn collection<T>(&self, collection_name: &str) -> Collection<T> {
         self.database.collection(collection_name)
     }
 
+    /// Whether `error` is the server refusing a transaction, as a standalone
+    /// server (one not in a replica set) does
+    pub fn transactions_unsupported(error: &mongodb::error::Error) -> bool {
+        matches!(
+            *error.kind,
+            mongodb::error::ErrorKind::Command(ref command)
+                if command.code == TRANSACTIONS_UNSUPPORTED_CODE
+        )
+    }
+
     pub async fn health_check(&self) -> Result<()> {
         self.database
             .run_command(doc! {"ping": 1}, None)
@@ -184,6 +255,32 @@ impl MongoDatabase {
                 message: format!("Failed to create messages client_id index: {}", e),
             })?;
 
+        // Client tag filters on message history
+        messages_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"client_id": 1, "tags": 1, "created_at": -1})
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create messages tags index: {}", e),
+            })?;
+
+        // Each client's messages this month, for pricing-plan volume tiers
+        messages_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"client_id": 1, "created_at": -1})
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create messages client volume index: {}", e),
+            })?;
+
         // Compound index on status and priority for message dispatch
         messages_collection
             .create_index(
@@ -197,6 +294,24 @@ impl MongoDatabase {
                 message: format!("Failed to create messages dispatch index: {}", e),
             })?;
 
+        // Salted recipient hash for privacy-mode dedup/search
+        messages_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"recipient_hash": 1})
+                    .options(
+                        mongodb::options::IndexOptions::builder()
+                            .sparse(true)
+                            .build(),
+                    )
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create messages recipient hash index: {}", e),
+            })?;
+
         // Index on expires_at for cleanup
         messages_collection
             .create_index(
@@ -257,6 +372,814 @@ impl MongoDatabase {
                 message: format!("Failed to create jobs timeout index: {}", e),
             })?;
 
+        // Dispatched jobs past their acknowledgment deadline
+        jobs_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"status": 1, "ack_deadline": 1})
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create jobs acknowledgment index: {}", e),
+            })?;
+
+        // Audit log indexes
+        let audit_collection: Collection<Document> = self.collection("audit_log");
+
+        // Compound index on resource for per-object access history
+        audit_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"resource_type": 1, "resource_id": 1, "created_at": -1})
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create audit log resource index: {}", e),
+            })?;
+
+        // Index on actor_id for per-user activity
+        audit_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"actor_id": 1, "created_at": -1})
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create audit log actor index: {}", e),
+            })?;
+
+        // Download links collection indexes
+        let download_links_collection: Collection<Document> = self.collection("download_links");
+
+        // Unique index on link id
+        download_links_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"id": 1})
+                    .options(
+                        mongodb::options::IndexOptions::builder()
+                            .unique(true)
+                            .build(),
+                    )
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create download links id index: {}", e),
+            })?;
+
+        // Unique index on heatmap snapshot date
+        let heatmaps_collection: Collection<Document> = self.collection("demand_heatmaps");
+        heatmaps_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"date": 1})
+                    .options(
+                        mongodb::options::IndexOptions::builder()
+                            .unique(true)
+                            .build(),
+                    )
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create demand heatmap date index: {}", e),
+            })?;
+
+        // Payout lookups by id and by Baray reference (settlement matching)
+        let payouts_collection: Collection<Document> = self.collection("payouts");
+        payouts_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"id": 1})
+                    .options(
+                        mongodb::options::IndexOptions::builder()
+                            .unique(true)
+                            .build(),
+                    )
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create payout ID index: {}", e),
+            })?;
+
+        payouts_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"baray_reference": 1})
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create payout reference index: {}", e),
+            })?;
+
+        // Approval queue and payout runs pick payouts by status, oldest first
+        payouts_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"status": 1, "created_at": 1})
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create payout status index: {}", e),
+            })?;
+
+        // Withdrawal checks look at a provider's latest payouts
+        payouts_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"provider_id": 1, "created_at": -1})
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create payout provider index: {}", e),
+            })?;
+
+        // On-chain settlements by status (batches) and per provider
+        let chain_settlements_collection: Collection<Document> =
+            self.collection("chain_settlements");
+        chain_settlements_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"id": 1})
+                    .options(
+                        mongodb::options::IndexOptions::builder()
+                            .unique(true)
+                            .build(),
+                    )
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create chain settlement ID index: {}", e),
+            })?;
+
+        chain_settlements_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"status": 1, "created_at": 1})
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create chain settlement status index: {}", e),
+            })?;
+
+        chain_settlements_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"provider_id": 1, "created_at": -1})
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create chain settlement provider index: {}", e),
+            })?;
+
+        // Stake slashes per provider, and by message to slash each only once
+        let stake_slashes_collection: Collection<Document> = self.collection("stake_slashes");
+        stake_slashes_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"message_id": 1})
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create stake slash message index: {}", e),
+            })?;
+
+        stake_slashes_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"provider_id": 1, "created_at": -1})
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create stake slash provider index: {}", e),
+            })?;
+
+        // Delivery SLA breaches, most recent first
+        self.collection::<Document>("sla_breaches")
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"detected_at": -1})
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create SLA breach index: {}", e),
+            })?;
+
+        // One dispute per message, listed per client and by status for review
+        let disputes_collection: Collection<Document> = self.collection("disputes");
+        disputes_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"message_id": 1})
+                    .options(
+                        mongodb::options::IndexOptions::builder()
+                            .unique(true)
+                            .build(),
+                    )
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create dispute message index: {}", e),
+            })?;
+
+        disputes_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"client_id": 1, "created_at": -1})
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create dispute client index: {}", e),
+            })?;
+
+        disputes_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"status": 1, "created_at": 1})
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create dispute status index: {}", e),
+            })?;
+
+        // Issued credentials by id (the jti), per user and per subject DID
+        let credentials_collection: Collection<Document> = self.collection("credentials");
+        credentials_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"id": 1})
+                    .options(
+                        mongodb::options::IndexOptions::builder()
+                            .unique(true)
+                            .build(),
+                    )
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create credential id index: {}", e),
+            })?;
+
+        credentials_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"user_id": 1, "issued_at": -1})
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create credential user index: {}", e),
+            })?;
+
+        credentials_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"subject_did": 1, "kind": 1})
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create credential subject index: {}", e),
+            })?;
+
+        // One earnings event per delivered message, read back per provider and day
+        let earnings_events_collection: Collection<Document> =
+            self.collection("earnings_events");
+        earnings_events_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"message_id": 1})
+                    .options(
+                        mongodb::options::IndexOptions::builder()
+                            .unique(true)
+                            .build(),
+                    )
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create earnings event message index: {}", e),
+            })?;
+
+        earnings_events_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"provider_id": 1, "date": 1})
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create earnings event provider index: {}", e),
+            })?;
+
+        // Monthly volume bonuses total every provider's events for a month
+        earnings_events_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"date": 1})
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create earnings event date index: {}", e),
+            })?;
+
+        // One volume bonus per provider per month
+        self.collection::<Document>("volume_bonuses")
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"provider_id": 1, "period": 1})
+                    .options(
+                        mongodb::options::IndexOptions::builder()
+                            .unique(true)
+                            .build(),
+                    )
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create volume bonus index: {}", e),
+            })?;
+
+        // One quality bonus per provider per month
+        self.collection::<Document>("quality_bonuses")
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"provider_id": 1, "period": 1})
+                    .options(
+                        mongodb::options::IndexOptions::builder()
+                            .unique(true)
+                            .build(),
+                    )
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create quality bonus index: {}", e),
+            })?;
+
+        // Referral codes by code and owner; one referral per referee
+        let referral_codes_collection: Collection<Document> = self.collection("referral_codes");
+        referral_codes_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"code": 1})
+                    .options(
+                        mongodb::options::IndexOptions::builder()
+                            .unique(true)
+                            .build(),
+                    )
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create referral code index: {}", e),
+            })?;
+
+        referral_codes_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"user_id": 1})
+                    .options(
+                        mongodb::options::IndexOptions::builder()
+                            .unique(true)
+                            .build(),
+                    )
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create referral owner index: {}", e),
+            })?;
+
+        let referrals_collection: Collection<Document> = self.collection("referrals");
+        referrals_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"referee_id": 1})
+                    .options(
+                        mongodb::options::IndexOptions::builder()
+                            .unique(true)
+                            .build(),
+                    )
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create referral referee index: {}", e),
+            })?;
+
+        referrals_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"referrer_id": 1, "created_at": -1})
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create referral referrer index: {}", e),
+            })?;
+
+        // Top-ups per client, newest first
+        let topups_collection: Collection<Document> = self.collection("topups");
+        topups_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"id": 1})
+                    .options(
+                        mongodb::options::IndexOptions::builder()
+                            .unique(true)
+                            .build(),
+                    )
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create top-up ID index: {}", e),
+            })?;
+
+        topups_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"user_id": 1, "created_at": -1})
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create top-up user index: {}", e),
+            })?;
+
+        // Baray webhook events are applied once per event id
+        let baray_events_collection: Collection<Document> = self.collection("baray_events");
+        baray_events_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"id": 1})
+                    .options(
+                        mongodb::options::IndexOptions::builder()
+                            .unique(true)
+                            .build(),
+                    )
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create Baray event ID index: {}", e),
+            })?;
+
+        // One ledger transaction per event, and statements per account
+        let ledger_collection: Collection<Document> = self.collection("ledger");
+        ledger_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"kind": 1, "reference": 1})
+                    .options(
+                        mongodb::options::IndexOptions::builder()
+                            .unique(true)
+                            .build(),
+                    )
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create ledger reference index: {}", e),
+            })?;
+
+        ledger_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {
+                        "postings.account_kind": 1,
+                        "postings.account_id": 1,
+                        "created_at": -1
+                    })
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create ledger account index: {}", e),
+            })?;
+
+        // Open settlement discrepancies for admin review
+        let discrepancies_collection: Collection<Document> =
+            self.collection("settlement_discrepancies");
+        discrepancies_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"status": 1, "created_at": -1})
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create settlement discrepancy index: {}", e),
+            })?;
+
+        // One quality score per client per day
+        let quality_collection: Collection<Document> = self.collection("quality_scores");
+        quality_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"client_id": 1, "date": -1})
+                    .options(
+                        mongodb::options::IndexOptions::builder()
+                            .unique(true)
+                            .build(),
+                    )
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create quality score index: {}", e),
+            })?;
+
+        // Saved filter names are unique per client
+        let saved_filters_collection: Collection<Document> = self.collection("saved_filters");
+        saved_filters_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"client_id": 1, "name": 1})
+                    .options(
+                        mongodb::options::IndexOptions::builder()
+                            .unique(true)
+                            .build(),
+                    )
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create saved filter index: {}", e),
+            })?;
+
+        // Backup runs by id, newest first for the admin listing
+        let backup_runs_collection: Collection<Document> = self.collection("backup_runs");
+        backup_runs_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"id": 1})
+                    .options(
+                        mongodb::options::IndexOptions::builder()
+                            .unique(true)
+                            .build(),
+                    )
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create backup run index: {}", e),
+            })?;
+
+        // OAuth2 machine clients, looked up by client_id on every token request
+        let api_clients_collection: Collection<Document> = self.collection("api_clients");
+        api_clients_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"client_id": 1})
+                    .options(
+                        mongodb::options::IndexOptions::builder()
+                            .unique(true)
+                            .build(),
+                    )
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create API client index: {}", e),
+            })?;
+        api_clients_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"owner_user_id": 1, "created_at": -1})
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create API client owner index: {}", e),
+            })?;
+
+        // Telegram chats linked to phone numbers, looked up per OTP
+        self.collection::<Document>("telegram_links")
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"phone": 1})
+                    .options(
+                        mongodb::options::IndexOptions::builder()
+                            .unique(true)
+                            .build(),
+                    )
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create Telegram link index: {}", e),
+            })?;
+
+        // Device sessions, listed per user by recent use
+        self.collection::<Document>("user_sessions")
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"user_id": 1, "last_used_at": -1})
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create session index: {}", e),
+            })?;
+        self.collection::<Document>("user_sessions")
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"id": 1})
+                    .options(
+                        mongodb::options::IndexOptions::builder()
+                            .unique(true)
+                            .build(),
+                    )
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create session id index: {}", e),
+            })?;
+
+        // Login history per user
+        self.collection::<Document>("auth_events")
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"user_id": 1, "created_at": -1})
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create auth event index: {}", e),
+            })?;
+
+        // Dedicated numbers per client, and one active assignment per SIM
+        self.collection::<Document>("dedicated_numbers")
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"client_id": 1, "released_at": 1})
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create dedicated number index: {}", e),
+            })?;
+        self.collection::<Document>("dedicated_numbers")
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"provider_id": 1, "released_at": 1})
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create dedicated number provider index: {}", e),
+            })?;
+
+        // One rental charge per number per month
+        self.collection::<Document>("number_rentals")
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"number_id": 1, "period": 1})
+                    .options(
+                        mongodb::options::IndexOptions::builder()
+                            .unique(true)
+                            .build(),
+                    )
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create number rental index: {}", e),
+            })?;
+
+        // Replies per client, newest first
+        self.collection::<Document>("inbound_messages")
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"client_id": 1, "received_at": -1})
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create inbound message index: {}", e),
+            })?;
+
+        // Outbox entries the relay has to publish, oldest due first
+        self.collection::<Document>("job_outbox")
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"next_attempt_at": 1})
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create job outbox index: {}", e),
+            })?;
+
+                // Routing rules by id (admin edits)
+        let routing_rules_collection: Collection<Document> = self.collection("routing_rules");
+        routing_rules_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"id": 1})
+                    .options(
+                        mongodb::options::IndexOptions::builder()
+                            .unique(true)
+                            .build(),
+                    )
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create routing rule index: {}", e),
+            })?;
+
+        // Pricing plans by id (client assignments, admin edits)
+        let pricing_plans_collection: Collection<Document> = self.collection("pricing_plans");
+        pricing_plans_collection
+            .create_index(
+                mongodb::IndexModel::builder()
+                    .keys(doc! {"id": 1})
+                    .options(
+                        mongodb::options::IndexOptions::builder()
+                            .unique(true)
+                            .build(),
+                    )
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create pricing plan index: {}", e),
+            })?;
+
         info!("Database indexes created successfully");
         Ok(())
     }
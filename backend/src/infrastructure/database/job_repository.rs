@@ -0,0 +1,165 @@
+use async_trait::async_trait;
+use bson::doc;
+use futures::stream::TryStreamExt;
+use mongodb::options::FindOptions;
+use mongodb::{Collection, Database};
+use std::sync::Arc;
+
+use crate::domain::entities::{Job, JobStatus};
+use crate::domain::repositories::JobRepository;
+use crate::shared::utils::stored_timestamp;
+use crate::shared::{PeerPowerError, Result};
+
+/// Jobs returned per query at most
+const MAX_RESULTS: i64 = 500;
+
+/// Jobs are stored as they serialize, timestamps as RFC 3339 strings
+pub struct MongoJobRepository {
+    collection: Collection<Job>,
+}
+
+impl MongoJobRepository {
+    pub fn new(database: Arc<Database>) -> Self {
+        Self {
+            collection: database.collection("jobs"),
+        }
+    }
+}
+
+#[async_trait]
+impl JobRepository for MongoJobRepository {
+    async fn create(&self, job: &Job) -> Result<()> {
+        self.collection
+            .insert_one(job, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create job: {}", e),
+            })?;
+        Ok(())
+    }
+
+    async fn find_by_id(&self, id: &str) -> Result<Option<Job>> {
+        self.collection
+            .find_one(doc! {"id": id}, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch job: {}", e),
+            })
+    }
+
+    async fn find_by_message_id(&self, message_id: &str) -> Result<Option<Job>> {
+        self.collection
+            .find_one(doc! {"message_id": message_id}, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch job: {}", e),
+            })
+    }
+
+    async fn find_by_provider_id(&self, provider_id: &str) -> Result<Vec<Job>> {
+        self.collection
+            .find(
+                doc! {"provider_id": provider_id},
+                FindOptions::builder()
+                    .sort(doc! {"assigned_at": 1})
+                    .limit(MAX_RESULTS)
+                    .build(),
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to query jobs: {}", e),
+            })?
+            .try_collect()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read jobs: {}", e),
+            })
+    }
+
+    async fn find_active_jobs(&self) -> Result<Vec<Job>> {
+        self.collection
+            .find(
+                doc! {"status": {"$in": [
+                    format!("{:?}", JobStatus::Assigned),
+                    format!("{:?}", JobStatus::Dispatched),
+                    format!("{:?}", JobStatus::InProgress),
+                ]}},
+                FindOptions::builder()
+                    .sort(doc! {"assigned_at": 1})
+                    .limit(MAX_RESULTS)
+                    .build(),
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to query active jobs: {}", e),
+            })?
+            .try_collect()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read active jobs: {}", e),
+            })
+    }
+
+    /// Active jobs past their timeout
+    async fn find_expired_jobs(&self) -> Result<Vec<Job>> {
+        let now = stored_timestamp(crate::shared::utils::now());
+        self.collection
+            .find(
+                doc! {
+                    "status": {"$in": [
+                        format!("{:?}", JobStatus::Assigned),
+                        format!("{:?}", JobStatus::Dispatched),
+                        format!("{:?}", JobStatus::InProgress),
+                    ]},
+                    "timeout_at": {"$lt": now},
+                },
+                FindOptions::builder()
+                    .sort(doc! {"assigned_at": 1})
+                    .limit(MAX_RESULTS)
+                    .build(),
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to query expired jobs: {}", e),
+            })?
+            .try_collect()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read expired jobs: {}", e),
+            })
+    }
+
+    async fn update(&self, job: &Job) -> Result<()> {
+        let result = self
+            .collection
+            .replace_one(doc! {"id": &job.id}, job, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to update job: {}", e),
+            })?;
+
+        if result.matched_count == 0 {
+            return Err(PeerPowerError::NotFound {
+                resource: format!("Job with ID: {}", job.id),
+            });
+        }
+        Ok(())
+    }
+
+    async fn delete(&self, id: &str) -> Result<()> {
+        let result = self
+            .collection
+            .delete_one(doc! {"id": id}, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to delete job: {}", e),
+            })?;
+
+        if result.deleted_count == 0 {
+            return Err(PeerPowerError::NotFound {
+                resource: format!("Job with ID: {}", id),
+            });
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,245 @@
+use async_trait::async_trait;
+use bson::doc;
+use chrono::{DateTime, Utc};
+use futures::stream::TryStreamExt;
+use mongodb::options::FindOptions;
+use mongodb::{Collection, Database};
+use std::sync::Arc;
+
+use crate::domain::entities::Message;
+use crate::domain::repositories::MessageRepository;
+use crate::shared::types::MessageStatus;
+use crate::shared::utils::stored_timestamp;
+use crate::shared::{PeerPowerError, Result};
+
+/// Messages returned when the caller sets no limit
+const DEFAULT_LIMIT: i64 = 100;
+
+/// Messages are stored as they serialize, timestamps as RFC 3339 strings
+pub struct MongoMessageRepository {
+    collection: Collection<Message>,
+}
+
+impl MongoMessageRepository {
+    pub fn new(database: Arc<Database>) -> Self {
+        Self {
+            collection: database.collection("messages"),
+        }
+    }
+
+    /// Mark a message deleted, or with None restore a deleted one
+    async fn set_deleted_at(&self, id: &str, deleted_at: Option<DateTime<Utc>>) -> Result<()> {
+        let current = if deleted_at.is_some() {
+            doc! {"$eq": null}
+        } else {
+            doc! {"$ne": null}
+        };
+        let result = self
+            .collection
+            .update_one(
+                doc! {"id": id, "deleted_at": current},
+                doc! {"$set": {
+                    "deleted_at": deleted_at.map(stored_timestamp),
+                    "updated_at": stored_timestamp(crate::shared::utils::now()),
+                }},
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to update message: {}", e),
+            })?;
+
+        if result.matched_count == 0 {
+            return Err(PeerPowerError::NotFound {
+                resource: format!("Message with ID: {}", id),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl MessageRepository for MongoMessageRepository {
+    async fn create(&self, message: &Message) -> Result<()> {
+        self.collection
+            .insert_one(message, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create message: {}", e),
+            })?;
+        Ok(())
+    }
+
+    async fn find_by_id(&self, id: &str) -> Result<Option<Message>> {
+        self.collection
+            .find_one(doc! {"id": id, "deleted_at": null}, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch message: {}", e),
+            })
+    }
+
+    /// Newest first
+    async fn find_by_client_id(&self, client_id: &str, limit: Option<i64>) -> Result<Vec<Message>> {
+        self.collection
+            .find(
+                doc! {"client_id": client_id, "deleted_at": null},
+                FindOptions::builder()
+                    .sort(doc! {"created_at": -1})
+                    .limit(limit.unwrap_or(DEFAULT_LIMIT))
+                    .build(),
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to query messages: {}", e),
+            })?
+            .try_collect()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read messages: {}", e),
+            })
+    }
+
+    /// Pending messages that are due (not scheduled for later), oldest first
+    async fn find_pending_messages(&self, limit: Option<i64>) -> Result<Vec<Message>> {
+        let now = stored_timestamp(crate::shared::utils::now());
+        self.collection
+            .find(
+                doc! {
+                    "status": format!("{:?}", MessageStatus::Pending),
+                    "$or": [{"scheduled_at": null}, {"scheduled_at": {"$lte": now}}],
+                    "deleted_at": null,
+                },
+                FindOptions::builder()
+                    .sort(doc! {"created_at": 1})
+                    .limit(limit.unwrap_or(DEFAULT_LIMIT))
+                    .build(),
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to query messages: {}", e),
+            })?
+            .try_collect()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read messages: {}", e),
+            })
+    }
+
+    async fn find_by_status(&self, status: &MessageStatus) -> Result<Vec<Message>> {
+        self.collection
+            .find(
+                doc! {"status": format!("{:?}", status), "deleted_at": null},
+                FindOptions::builder()
+                    .sort(doc! {"created_at": 1})
+                    .limit(DEFAULT_LIMIT)
+                    .build(),
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to query messages: {}", e),
+            })?
+            .try_collect()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read messages: {}", e),
+            })
+    }
+
+    async fn update(&self, message: &Message) -> Result<()> {
+        let result = self
+            .collection
+            .replace_one(doc! {"id": &message.id}, message, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to update message: {}", e),
+            })?;
+
+        if result.matched_count == 0 {
+            return Err(PeerPowerError::NotFound {
+                resource: format!("Message with ID: {}", message.id),
+            });
+        }
+        Ok(())
+    }
+
+    async fn update_status(&self, id: &str, status: MessageStatus) -> Result<()> {
+        let result = self
+            .collection
+            .update_one(
+                doc! {"id": id},
+                doc! {"$set": {
+                    "status": format!("{:?}", status),
+                    "updated_at": stored_timestamp(crate::shared::utils::now()),
+                }},
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to update message status: {}", e),
+            })?;
+
+        if result.matched_count == 0 {
+            return Err(PeerPowerError::NotFound {
+                resource: format!("Message with ID: {}", id),
+            });
+        }
+        Ok(())
+    }
+
+    async fn delete(&self, id: &str) -> Result<()> {
+        self.set_deleted_at(id, Some(crate::shared::utils::now()))
+            .await
+    }
+
+    async fn restore(&self, id: &str) -> Result<()> {
+        self.set_deleted_at(id, None).await
+    }
+
+    /// Undelivered messages past their expiry
+    async fn find_expired_messages(&self) -> Result<Vec<Message>> {
+        let now = stored_timestamp(crate::shared::utils::now());
+        self.collection
+            .find(
+                doc! {
+                    "status": {"$in": [
+                        format!("{:?}", MessageStatus::Pending),
+                        format!("{:?}", MessageStatus::Assigned),
+                        format!("{:?}", MessageStatus::Sent),
+                    ]},
+                    "expires_at": {"$lt": now},
+                    "deleted_at": null,
+                },
+                FindOptions::builder().limit(DEFAULT_LIMIT).build(),
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to query messages: {}", e),
+            })?
+            .try_collect()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read messages: {}", e),
+            })
+    }
+
+    /// Messages the client submitted since midnight UTC
+    async fn count_by_client_today(&self, client_id: &str) -> Result<i64> {
+        let midnight = crate::shared::utils::now()
+            .date_naive()
+            .and_hms_opt(0, 0, 0)
+            .unwrap_or_default()
+            .and_utc();
+        let count = self
+            .collection
+            .count_documents(
+                doc! {"client_id": client_id, "created_at": {"$gte": stored_timestamp(midnight)}},
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to count messages: {}", e),
+            })?;
+        Ok(count as i64)
+    }
+}
@@ -1,7 +1,13 @@
 pub mod connection;
+pub mod job_repository;
+pub mod message_repository;
+pub mod pool_monitor;
 pub mod redis;
 pub mod user_repository;
 
 pub use connection::MongoDatabase;
+pub use job_repository::MongoJobRepository;
+pub use message_repository::MongoMessageRepository;
+pub use pool_monitor::MongoPoolMonitor;
 pub use redis::RedisConnection;
 pub use user_repository::MongoUserRepository;
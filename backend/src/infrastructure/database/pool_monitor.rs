@@ -0,0 +1,232 @@
+use mongodb::bson::{Bson, Document};
+use mongodb::event::cmap::{
+    CmapEventHandler, ConnectionCheckedInEvent, ConnectionCheckedOutEvent,
+    ConnectionCheckoutFailedEvent,
+};
+use mongodb::event::command::{
+    CommandEventHandler, CommandFailedEvent, CommandStartedEvent, CommandSucceededEvent,
+};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::config::DatabaseConfig;
+
+/// How often pool saturation is sampled
+const SATURATION_SAMPLE_SECONDS: u64 = 5;
+
+/// Collection and filter shape captured when a command starts, reported if it turns out slow
+struct StartedCommand {
+    collection: String,
+    filter_shape: Option<String>,
+}
+
+/// MongoDB driver event handler that feeds pool and command metrics into the
+/// metrics registry, logs slow operations, and alerts on sustained pool saturation.
+pub struct MongoPoolMonitor {
+    max_pool_size: u32,
+    slow_operation_threshold: Duration,
+    saturation_threshold: f64,
+    saturation_alert_after: Duration,
+    checked_out: AtomicI64,
+    started: Mutex<HashMap<i32, StartedCommand>>,
+}
+
+impl MongoPoolMonitor {
+    pub fn new(config: &DatabaseConfig) -> Self {
+        Self {
+            max_pool_size: config.max_connections.max(1),
+            slow_operation_threshold: Duration::from_millis(config.slow_operation_threshold_ms),
+            saturation_threshold: config.pool_saturation_threshold,
+            saturation_alert_after: Duration::from_secs(config.pool_saturation_alert_seconds),
+            checked_out: AtomicI64::new(0),
+            started: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Fraction of the pool currently checked out
+    pub fn saturation(&self) -> f64 {
+        self.checked_out.load(Ordering::Relaxed).max(0) as f64 / self.max_pool_size as f64
+    }
+
+    /// Sample saturation periodically and alert once it stays above the threshold
+    pub fn start_saturation_watch(self: Arc<Self>) {
+        tokio::spawn(async move {
+            let mut interval =
+                tokio::time::interval(Duration::from_secs(SATURATION_SAMPLE_SECONDS));
+            let mut saturated_since: Option<tokio::time::Instant> = None;
+            let mut alerted = false;
+
+            loop {
+                interval.tick().await;
+
+                let saturation = self.saturation();
+                metrics::gauge!("mongodb_pool_saturation").set(saturation);
+
+                if saturation < self.saturation_threshold {
+                    if alerted {
+                        info!(
+                            "MongoDB connection pool saturation recovered ({:.0}%)",
+                            saturation * 100.0
+                        );
+                    }
+                    saturated_since = None;
+                    alerted = false;
+                    continue;
+                }
+
+                let since = *saturated_since.get_or_insert_with(tokio::time::Instant::now);
+                if !alerted && since.elapsed() >= self.saturation_alert_after {
+                    error!(
+                        "ALERT: MongoDB connection pool saturated at {:.0}% ({} of {} connections) for over {}s",
+                        saturation * 100.0,
+                        self.checked_out.load(Ordering::Relaxed),
+                        self.max_pool_size,
+                        self.saturation_alert_after.as_secs()
+                    );
+                    metrics::counter!("mongodb_pool_saturation_alerts_total").increment(1);
+                    alerted = true;
+                }
+            }
+        });
+    }
+
+    fn record_checked_out(&self, delta: i64) {
+        let checked_out = self.checked_out.fetch_add(delta, Ordering::Relaxed) + delta;
+        metrics::gauge!("mongodb_pool_checked_out").set(checked_out.max(0) as f64);
+    }
+
+    fn finish_command(
+        &self,
+        request_id: i32,
+        command_name: &str,
+        duration: Duration,
+        failed: bool,
+    ) {
+        let started = self
+            .started
+            .lock()
+            .ok()
+            .and_then(|mut started| started.remove(&request_id));
+
+        metrics::histogram!("mongodb_command_duration_seconds", "command" => command_name.to_string())
+            .record(duration.as_secs_f64());
+        if failed {
+            metrics::counter!("mongodb_command_failures_total", "command" => command_name.to_string())
+                .increment(1);
+        }
+
+        if duration < self.slow_operation_threshold {
+            return;
+        }
+
+        let (collection, filter_shape) = match started {
+            Some(started) => (started.collection, started.filter_shape),
+            None => ("unknown".to_string(), None),
+        };
+
+        metrics::counter!("mongodb_slow_operations_total", "collection" => collection.clone())
+            .increment(1);
+        warn!(
+            "Slow MongoDB {} on {} took {}ms (filter: {})",
+            command_name,
+            collection,
+            duration.as_millis(),
+            filter_shape.as_deref().unwrap_or("none")
+        );
+    }
+}
+
+impl CmapEventHandler for MongoPoolMonitor {
+    fn handle_connection_checked_out_event(&self, event: ConnectionCheckedOutEvent) {
+        self.record_checked_out(1);
+        metrics::histogram!("mongodb_pool_wait_seconds").record(event.duration.as_secs_f64());
+    }
+
+    fn handle_connection_checked_in_event(&self, _event: ConnectionCheckedInEvent) {
+        self.record_checked_out(-1);
+    }
+
+    fn handle_connection_checkout_failed_event(&self, event: ConnectionCheckoutFailedEvent) {
+        metrics::counter!("mongodb_pool_checkout_failures_total").increment(1);
+        warn!(
+            "MongoDB connection checkout failed after {}ms: {:?}",
+            event.duration.as_millis(),
+            event.reason
+        );
+    }
+}
+
+impl CommandEventHandler for MongoPoolMonitor {
+    fn handle_command_started_event(&self, event: CommandStartedEvent) {
+        let collection = event
+            .command
+            .get_str(&event.command_name)
+            .or_else(|_| event.command.get_str("collection"))
+            .unwrap_or(&event.db)
+            .to_string();
+
+        if let Ok(mut started) = self.started.lock() {
+            started.insert(
+                event.request_id,
+                StartedCommand {
+                    collection,
+                    filter_shape: filter_shape(&event.command),
+                },
+            );
+        }
+    }
+
+    fn handle_command_succeeded_event(&self, event: CommandSucceededEvent) {
+        self.finish_command(event.request_id, &event.command_name, event.duration, false);
+    }
+
+    fn handle_command_failed_event(&self, event: CommandFailedEvent) {
+        self.finish_command(event.request_id, &event.command_name, event.duration, true);
+    }
+}
+
+/// Query filter of a command with all values masked, e.g. `{ "client_id": "?", "created_at": { "$gte": "?" } }`
+fn filter_shape(command: &Document) -> Option<String> {
+    let first_statement = |key: &str| {
+        command
+            .get_array(key)
+            .ok()
+            .and_then(|statements| statements.first())
+            .and_then(|statement| statement.as_document())
+            .and_then(|statement| statement.get_document("q").ok())
+    };
+    let first_match = || {
+        command
+            .get_array("pipeline")
+            .ok()
+            .and_then(|pipeline| pipeline.first())
+            .and_then(|stage| stage.as_document())
+            .and_then(|stage| stage.get_document("$match").ok())
+    };
+
+    let filter = command
+        .get_document("filter")
+        .ok()
+        .or_else(|| command.get_document("query").ok())
+        .or_else(|| first_statement("updates"))
+        .or_else(|| first_statement("deletes"))
+        .or_else(first_match)?;
+
+    Some(mask_values(&Bson::Document(filter.clone())).to_string())
+}
+
+fn mask_values(value: &Bson) -> Bson {
+    match value {
+        Bson::Document(document) => Bson::Document(
+            document
+                .iter()
+                .map(|(key, value)| (key.clone(), mask_values(value)))
+                .collect(),
+        ),
+        Bson::Array(values) => Bson::Array(values.iter().map(mask_values).collect()),
+        _ => Bson::String("?".to_string()),
+    }
+}
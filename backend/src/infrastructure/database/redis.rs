@@ -1,15 +1,27 @@
-use redis::{Client, Commands, Connection, RedisResult};
+use redis::aio::{MultiplexedConnection, PubSub};
+use redis::{Client, Cmd, FromRedisValue};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::RwLock;
 use tracing::{info, warn};
 
 use crate::config::RedisConfig;
 use crate::shared::{PeerPowerError, Result};
 
+/// Pool of asynchronous Redis connections, shared by every clone.
+///
+/// Up to `REDIS_MAX_CONNECTIONS` multiplexed connections are opened as they
+/// are first needed and handed out round-robin; each carries the commands of
+/// many callers at once, so nothing waits on a lock for its turn. A
+/// connection that fails is dropped and reopened by the next command routed
+/// to it, so commands recover on their own once Redis is back.
 #[derive(Clone)]
 pub struct RedisConnection {
     client: Arc<Client>,
-    connection: Arc<Mutex<Connection>>,
+    connections: Arc<Vec<RwLock<Option<MultiplexedConnection>>>>,
+    next: Arc<AtomicUsize>,
+    timeout: Duration, // to connect, and for a reply
 }
 
 impl RedisConnection {
@@ -22,38 +34,76 @@ impl RedisConnection {
                 message: format!("Failed to create Redis client: {}", e),
             })?;
 
-        let connection = client
-            .get_connection()
-            .map_err(|e| PeerPowerError::ExternalService {
-                service: "Redis".to_string(),
-                message: format!("Failed to connect to Redis: {}", e),
-            })?;
+        let pool = Self {
+            client: Arc::new(client),
+            connections: Arc::new(
+                (0..config.max_connections.max(1))
+                    .map(|_| RwLock::new(None))
+                    .collect(),
+            ),
+            next: Arc::new(AtomicUsize::new(0)),
+            timeout: Duration::from_secs(config.connection_timeout_seconds.max(1)),
+        };
 
         // Test connection
-        let mut conn = connection;
-        redis::cmd("PING").query::<String>(&mut conn).map_err(|e| {
-            PeerPowerError::ExternalService {
-                service: "Redis".to_string(),
-                message: format!("Redis ping failed: {}", e),
-            }
-        })?;
+        pool.query::<String>(&redis::cmd("PING"), "ping").await?;
 
-        info!("Successfully connected to Redis");
+        info!(
+            "Successfully connected to Redis (up to {} connections)",
+            pool.connections.len()
+        );
 
-        Ok(Self {
-            client: Arc::new(client),
-            connection: Arc::new(Mutex::new(conn)),
-        })
+        Ok(pool)
     }
 
-    pub async fn health_check(&self) -> Result<()> {
-        let mut conn = self.connection.lock().await;
-        redis::cmd("PING")
-            .query::<String>(&mut *conn)
+    /// The next connection in turn, opened if it isn't yet
+    async fn connection(&self) -> Result<(usize, MultiplexedConnection)> {
+        let slot = self.next.fetch_add(1, Ordering::Relaxed) % self.connections.len();
+        if let Some(connection) = self.connections[slot].read().await.as_ref() {
+            return Ok((slot, connection.clone()));
+        }
+
+        let mut opened = self.connections[slot].write().await;
+        if let Some(connection) = opened.as_ref() {
+            return Ok((slot, connection.clone()));
+        }
+        let connection = self.open(self.timeout).await?;
+        *opened = Some(connection.clone());
+        Ok((slot, connection))
+    }
+
+    async fn open(&self, response_timeout: Duration) -> Result<MultiplexedConnection> {
+        self.client
+            .get_multiplexed_async_connection_with_timeouts(response_timeout, self.timeout)
+            .await
             .map_err(|e| PeerPowerError::ExternalService {
                 service: "Redis".to_string(),
-                message: format!("Redis health check failed: {}", e),
-            })?;
+                message: format!("Failed to connect to Redis: {}", e),
+            })
+    }
+
+    /// Run `cmd` on the next connection; `name` labels the error
+    async fn query<T: FromRedisValue>(&self, cmd: &Cmd, name: &str) -> Result<T> {
+        let (slot, mut connection) = self.connection().await?;
+        match cmd.query_async(&mut connection).await {
+            Ok(result) => Ok(result),
+            Err(e) => {
+                if e.is_io_error() || e.is_unrecoverable_error() {
+                    warn!("Redis connection {} failed, reopening it: {}", slot, e);
+                    *self.connections[slot].write().await = None;
+                }
+                Err(PeerPowerError::ExternalService {
+                    service: "Redis".to_string(),
+                    message: format!("Redis {} failed: {}", name, e),
+                })
+            }
+        }
+    }
+
+    /// Ping Redis, reopening the connection used if it had failed
+    pub async fn health_check(&self) -> Result<()> {
+        self.query::<String>(&redis::cmd("PING"), "health check")
+            .await?;
         Ok(())
     }
 
@@ -63,87 +113,75 @@ impl RedisConnection {
         value: &str,
         expiration_seconds: Option<usize>,
     ) -> Result<()> {
-        let mut conn = self.connection.lock().await;
-
         if let Some(exp) = expiration_seconds {
-            redis::cmd("SETEX")
-                .arg(key)
-                .arg(exp as u64)
-                .arg(value)
-                .query::<()>(&mut *conn)
-                .map_err(|e| PeerPowerError::ExternalService {
-                    service: "Redis".to_string(),
-                    message: format!("Redis SETEX failed: {}", e),
-                })?;
+            self.query::<()>(
+                redis::cmd("SETEX").arg(key).arg(exp as u64).arg(value),
+                "SETEX",
+            )
+            .await
         } else {
-            redis::cmd("SET")
-                .arg(key)
-                .arg(value)
-                .query::<()>(&mut *conn)
-                .map_err(|e| PeerPowerError::ExternalService {
-                    service: "Redis".to_string(),
-                    message: format!("Redis SET failed: {}", e),
-                })?;
+            self.query::<()>(redis::cmd("SET").arg(key).arg(value), "SET")
+                .await
         }
-
-        Ok(())
     }
 
     pub async fn get(&self, key: &str) -> Result<Option<String>> {
-        let mut conn = self.connection.lock().await;
-
-        let result: Option<String> = redis::cmd("GET").arg(key).query(&mut *conn).map_err(|e| {
-            PeerPowerError::ExternalService {
-                service: "Redis".to_string(),
-                message: format!("Redis GET failed: {}", e),
-            }
-        })?;
+        self.query(redis::cmd("GET").arg(key), "GET").await
+    }
 
-        Ok(result)
+    /// Values of several keys at once, None for each that is missing
+    pub async fn mget(&self, keys: &[String]) -> Result<Vec<Option<String>>> {
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.query(redis::cmd("MGET").arg(keys), "MGET").await
     }
 
-    pub async fn delete(&self, key: &str) -> Result<bool> {
-        let mut conn = self.connection.lock().await;
+    /// Send `payload` to whoever is subscribed to `channel` right now;
+    /// returns how many received it
+    pub async fn publish(&self, channel: &str, payload: &str) -> Result<i64> {
+        self.query(redis::cmd("PUBLISH").arg(channel).arg(payload), "PUBLISH")
+            .await
+    }
 
-        let result: i32 = redis::cmd("DEL").arg(key).query(&mut *conn).map_err(|e| {
-            PeerPowerError::ExternalService {
+    /// A connection of its own subscribed to `channel`; a subscribed
+    /// connection can't carry other commands, so it isn't one of the pool's
+    pub async fn subscribe(&self, channel: &str) -> Result<PubSub> {
+        let subscribe = async {
+            let mut pubsub = self.client.get_async_pubsub().await?;
+            pubsub.subscribe(channel).await?;
+            Ok::<_, redis::RedisError>(pubsub)
+        };
+        let subscribed = tokio::time::timeout(self.timeout, subscribe)
+            .await
+            .map_err(|_| PeerPowerError::ExternalService {
                 service: "Redis".to_string(),
-                message: format!("Redis DEL failed: {}", e),
-            }
-        })?;
+                message: format!("Timed out subscribing to {}", channel),
+            })?;
+        subscribed.map_err(|e| PeerPowerError::ExternalService {
+            service: "Redis".to_string(),
+            message: format!("Failed to subscribe to {}: {}", channel, e),
+        })
+    }
 
+    pub async fn delete(&self, key: &str) -> Result<bool> {
+        let result: i32 = self.query(redis::cmd("DEL").arg(key), "DEL").await?;
         Ok(result > 0)
     }
 
     pub async fn increment(&self, key: &str) -> Result<i64> {
-        let mut conn = self.connection.lock().await;
-
-        let result: i64 = redis::cmd("INCR").arg(key).query(&mut *conn).map_err(|e| {
-            PeerPowerError::ExternalService {
-                service: "Redis".to_string(),
-                message: format!("Redis INCR failed: {}", e),
-            }
-        })?;
+        self.query(redis::cmd("INCR").arg(key), "INCR").await
+    }
 
-        Ok(result)
+    pub async fn expire(&self, key: &str, ttl_seconds: usize) -> Result<bool> {
+        let result: i32 = self
+            .query(redis::cmd("EXPIRE").arg(key).arg(ttl_seconds), "EXPIRE")
+            .await?;
+        Ok(result > 0)
     }
 
     pub async fn acquire_lock(&self, key: &str, ttl_seconds: usize) -> Result<bool> {
-        let mut conn = self.connection.lock().await;
-
-        let result: Option<String> = redis::cmd("SET")
-            .arg(key)
-            .arg("locked")
-            .arg("NX")
-            .arg("EX")
-            .arg(ttl_seconds as u64)
-            .query(&mut *conn)
-            .map_err(|e| PeerPowerError::ExternalService {
-                service: "Redis".to_string(),
-                message: format!("Redis lock acquisition failed: {}", e),
-            })?;
-
-        Ok(result.is_some())
+        self.acquire_owned_lock(key, "locked", ttl_seconds).await
     }
 
     pub async fn release_lock(&self, key: &str) -> Result<()> {
@@ -151,37 +189,81 @@ impl RedisConnection {
         Ok(())
     }
 
-    pub async fn lpush(&self, key: &str, value: &str) -> Result<i64> {
-        let mut conn = self.connection.lock().await;
-
-        let result: i64 = redis::cmd("LPUSH")
-            .arg(key)
-            .arg(value)
-            .query(&mut *conn)
-            .map_err(|e| PeerPowerError::ExternalService {
-                service: "Redis".to_string(),
-                message: format!("Redis LPUSH failed: {}", e),
-            })?;
+    /// Like [`Self::acquire_lock`], recording `owner` as the holder
+    pub async fn acquire_owned_lock(
+        &self,
+        key: &str,
+        owner: &str,
+        ttl_seconds: usize,
+    ) -> Result<bool> {
+        let result: Option<String> = self
+            .query(
+                redis::cmd("SET")
+                    .arg(key)
+                    .arg(owner)
+                    .arg("NX")
+                    .arg("EX")
+                    .arg(ttl_seconds as u64),
+                "lock acquisition",
+            )
+            .await?;
+        Ok(result.is_some())
+    }
 
-        Ok(result)
+    /// Release a lock only if `owner` still holds it, so an expired lock
+    /// since taken by someone else is left alone
+    pub async fn release_owned_lock(&self, key: &str, owner: &str) -> Result<bool> {
+        let released = self
+            .eval_ints(
+                "if redis.call('GET', KEYS[1]) == ARGV[1] then \
+                     return {redis.call('DEL', KEYS[1])} \
+                 end \
+                 return {0}",
+                &[key],
+                &[owner.to_string()],
+            )
+            .await?;
+        Ok(released.first() == Some(&1))
     }
 
-    pub async fn rpop(&self, key: &str) -> Result<Option<String>> {
-        let mut conn = self.connection.lock().await;
+    /// Push back the expiry of a lock `owner` still holds; false if it lost it
+    pub async fn extend_owned_lock(
+        &self,
+        key: &str,
+        owner: &str,
+        ttl_seconds: usize,
+    ) -> Result<bool> {
+        let extended = self
+            .eval_ints(
+                "if redis.call('GET', KEYS[1]) == ARGV[1] then \
+                     return {redis.call('EXPIRE', KEYS[1], ARGV[2])} \
+                 end \
+                 return {0}",
+                &[key],
+                &[owner.to_string(), ttl_seconds.to_string()],
+            )
+            .await?;
+        Ok(extended.first() == Some(&1))
+    }
 
-        let result: Option<String> =
-            redis::cmd("RPOP").arg(key).query(&mut *conn).map_err(|e| {
-                PeerPowerError::ExternalService {
-                    service: "Redis".to_string(),
-                    message: format!("Redis RPOP failed: {}", e),
-                }
-            })?;
+    pub async fn lpush(&self, key: &str, value: &str) -> Result<i64> {
+        self.query(redis::cmd("LPUSH").arg(key).arg(value), "LPUSH")
+            .await
+    }
 
-        Ok(result)
+    /// Keep only the elements from `start` to `stop` (inclusive) of a list
+    pub async fn ltrim(&self, key: &str, start: isize, stop: isize) -> Result<()> {
+        self.query(redis::cmd("LTRIM").arg(key).arg(start).arg(stop), "LTRIM")
+            .await
     }
 
+    /// Block for up to `timeout` seconds on the first non-empty list. Runs on
+    /// a connection of its own, so the pooled connections' other commands
+    /// are not held up behind it.
     pub async fn brpop(&self, keys: &[&str], timeout: usize) -> Result<Option<(String, String)>> {
-        let mut conn = self.connection.lock().await;
+        let mut connection = self
+            .open(self.timeout + Duration::from_secs(timeout as u64))
+            .await?;
 
         let mut cmd = redis::cmd("BRPOP");
         for key in keys {
@@ -189,58 +271,78 @@ impl RedisConnection {
         }
         cmd.arg(timeout as u64);
 
-        let result: Option<(String, String)> =
-            cmd.query(&mut *conn)
-                .map_err(|e| PeerPowerError::ExternalService {
-                    service: "Redis".to_string(),
-                    message: format!("Redis BRPOP failed: {}", e),
-                })?;
-
-        Ok(result)
+        cmd.query_async(&mut connection)
+            .await
+            .map_err(|e| PeerPowerError::ExternalService {
+                service: "Redis".to_string(),
+                message: format!("Redis BRPOP failed: {}", e),
+            })
     }
 
     pub async fn sadd(&self, key: &str, value: &str) -> Result<i64> {
-        let mut conn = self.connection.lock().await;
+        self.query(redis::cmd("SADD").arg(key).arg(value), "SADD")
+            .await
+    }
 
-        let result: i64 = redis::cmd("SADD")
-            .arg(key)
-            .arg(value)
-            .query(&mut *conn)
-            .map_err(|e| PeerPowerError::ExternalService {
-                service: "Redis".to_string(),
-                message: format!("Redis SADD failed: {}", e),
-            })?;
+    pub async fn srem(&self, key: &str, value: &str) -> Result<i64> {
+        self.query(redis::cmd("SREM").arg(key).arg(value), "SREM")
+            .await
+    }
 
-        Ok(result)
+    pub async fn smembers(&self, key: &str) -> Result<Vec<String>> {
+        self.query(redis::cmd("SMEMBERS").arg(key), "SMEMBERS")
+            .await
     }
 
-    pub async fn srem(&self, key: &str, value: &str) -> Result<i64> {
-        let mut conn = self.connection.lock().await;
+    pub async fn zadd(&self, key: &str, member: &str, score: f64) -> Result<i64> {
+        self.query(redis::cmd("ZADD").arg(key).arg(score).arg(member), "ZADD")
+            .await
+    }
 
-        let result: i64 = redis::cmd("SREM")
-            .arg(key)
-            .arg(value)
-            .query(&mut *conn)
-            .map_err(|e| PeerPowerError::ExternalService {
-                service: "Redis".to_string(),
-                message: format!("Redis SREM failed: {}", e),
-            })?;
+    pub async fn zrem(&self, key: &str, member: &str) -> Result<i64> {
+        self.query(redis::cmd("ZREM").arg(key).arg(member), "ZREM")
+            .await
+    }
+
+    /// Run a Lua script atomically, returning its integer array reply
+    pub async fn eval_ints(
+        &self,
+        script: &str,
+        keys: &[&str],
+        args: &[String],
+    ) -> Result<Vec<i64>> {
+        self.query(
+            redis::cmd("EVAL")
+                .arg(script)
+                .arg(keys.len())
+                .arg(keys)
+                .arg(args),
+            "EVAL",
+        )
+        .await
+    }
 
-        Ok(result)
+    pub async fn zcard(&self, key: &str) -> Result<u64> {
+        self.query(redis::cmd("ZCARD").arg(key), "ZCARD").await
     }
 
-    pub async fn smembers(&self, key: &str) -> Result<Vec<String>> {
-        let mut conn = self.connection.lock().await;
+    /// Members by rank, lowest score first (`stop` is inclusive)
+    pub async fn zrange(&self, key: &str, start: isize, stop: isize) -> Result<Vec<String>> {
+        self.query(redis::cmd("ZRANGE").arg(key).arg(start).arg(stop), "ZRANGE")
+            .await
+    }
 
-        let result: Vec<String> =
-            redis::cmd("SMEMBERS")
+    pub async fn zrangebyscore(&self, key: &str, max: f64, limit: usize) -> Result<Vec<String>> {
+        self.query(
+            redis::cmd("ZRANGEBYSCORE")
                 .arg(key)
-                .query(&mut *conn)
-                .map_err(|e| PeerPowerError::ExternalService {
-                    service: "Redis".to_string(),
-                    message: format!("Redis SMEMBERS failed: {}", e),
-                })?;
-
-        Ok(result)
+                .arg("-inf")
+                .arg(max)
+                .arg("LIMIT")
+                .arg(0)
+                .arg(limit),
+            "ZRANGEBYSCORE",
+        )
+        .await
     }
 }
@@ -4,9 +4,11 @@ use mongodb::{Collection, Database};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
-use crate::domain::entities::User;
+use crate::domain::entities::{ClientTier, QualitySla, QuietHours, User};
 use crate::domain::repositories::UserRepository;
+use crate::shared::field_encryption;
 use crate::shared::types::PhoneNumber;
+use crate::shared::utils::stored_timestamp;
 use crate::shared::{PeerPowerError, Result};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,8 +22,20 @@ struct UserDocument {
     pub reputation_score: f64,
     pub is_provider: bool,
     pub is_verified: bool,
+    #[serde(default)]
+    pub quality_sla: Option<QualitySla>,
+    #[serde(default)]
+    pub tier: ClientTier,
+    #[serde(default)]
+    pub recipient_privacy: bool,
+    #[serde(default)]
+    pub pricing_plan_id: Option<String>,
+    #[serde(default)]
+    pub quiet_hours: Option<QuietHours>,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
+    #[serde(default)]
+    pub deleted_at: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 impl From<&User> for UserDocument {
@@ -29,14 +43,20 @@ impl From<&User> for UserDocument {
         Self {
             id: None, // Let MongoDB generate the ObjectId
             user_id: user.id.clone(),
-            phone: user.phone.as_str().to_string(),
+            phone: field_encryption::encrypt_phone(&user.phone),
             did: user.did.clone(),
             evm_address: user.evm_address.clone(),
             reputation_score: user.reputation_score,
             is_provider: user.is_provider,
             is_verified: user.is_verified,
+            quality_sla: user.quality_sla.clone(),
+            tier: user.tier,
+            recipient_privacy: user.recipient_privacy,
+            pricing_plan_id: user.pricing_plan_id.clone(),
+            quiet_hours: user.quiet_hours,
             created_at: user.created_at,
             updated_at: user.updated_at,
+            deleted_at: user.deleted_at,
         }
     }
 }
@@ -45,7 +65,7 @@ impl TryFrom<UserDocument> for User {
     type Error = PeerPowerError;
 
     fn try_from(doc: UserDocument) -> Result<Self> {
-        let phone = PhoneNumber::new(doc.phone)?;
+        let phone = PhoneNumber::new(field_encryption::decrypt_phone(&doc.phone)?)?;
 
         Ok(User {
             id: doc.user_id,
@@ -55,8 +75,14 @@ impl TryFrom<UserDocument> for User {
             reputation_score: doc.reputation_score,
             is_provider: doc.is_provider,
             is_verified: doc.is_verified,
+            quality_sla: doc.quality_sla,
+            tier: doc.tier,
+            recipient_privacy: doc.recipient_privacy,
+            pricing_plan_id: doc.pricing_plan_id,
+            quiet_hours: doc.quiet_hours,
             created_at: doc.created_at,
             updated_at: doc.updated_at,
+            deleted_at: doc.deleted_at,
         })
     }
 }
@@ -71,6 +97,41 @@ impl MongoUserRepository {
             collection: database.collection("users"),
         }
     }
+
+    /// Mark a user deleted, or with None restore a deleted one
+    async fn set_deleted_at(
+        &self,
+        id: &str,
+        deleted_at: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> Result<()> {
+        let now = crate::shared::utils::now();
+        let current = if deleted_at.is_some() {
+            doc! {"$eq": null}
+        } else {
+            doc! {"$ne": null}
+        };
+        let result = self
+            .collection
+            .update_one(
+                doc! {"user_id": id, "deleted_at": current},
+                doc! {"$set": {
+                    "deleted_at": deleted_at.map(stored_timestamp),
+                    "updated_at": stored_timestamp(now),
+                }},
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to update user: {}", e),
+            })?;
+
+        if result.matched_count == 0 {
+            return Err(PeerPowerError::NotFound {
+                resource: format!("User with id: {}", id),
+            });
+        }
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -81,7 +142,7 @@ impl UserRepository for MongoUserRepository {
         tracing::info!(
             "Creating user in database with user_id: {} and phone: {}",
             doc.user_id,
-            doc.phone
+            user.phone.as_str()
         );
 
         let result = self.collection.insert_one(doc, None).await.map_err(|e| {
@@ -109,7 +170,7 @@ impl UserRepository for MongoUserRepository {
         tracing::info!("Looking up user by ID: {}", id);
         let doc = self
             .collection
-            .find_one(doc! {"user_id": id}, None)
+            .find_one(doc! {"user_id": id, "deleted_at": null}, None)
             .await
             .map_err(|e| PeerPowerError::Database {
                 message: format!("Failed to find user by id: {}", e),
@@ -127,11 +188,13 @@ impl UserRepository for MongoUserRepository {
         }
     }
 
+    /// Includes a soft-deleted user, whose number stays taken; callers check
+    /// `deleted_at`
     async fn find_by_phone(&self, phone: &PhoneNumber) -> Result<Option<User>> {
         tracing::info!("Looking up user by phone: {}", phone.as_str());
         let doc = self
             .collection
-            .find_one(doc! {"phone": phone.as_str()}, None)
+            .find_one(doc! {"phone": field_encryption::phone_filter(phone)}, None)
             .await
             .map_err(|e| PeerPowerError::Database {
                 message: format!("Failed to find user by phone: {}", e),
@@ -156,7 +219,7 @@ impl UserRepository for MongoUserRepository {
     async fn find_by_did(&self, did: &str) -> Result<Option<User>> {
         let doc = self
             .collection
-            .find_one(doc! {"did": did}, None)
+            .find_one(doc! {"did": did, "deleted_at": null}, None)
             .await
             .map_err(|e| PeerPowerError::Database {
                 message: format!("Failed to find user by DID: {}", e),
@@ -169,18 +232,7 @@ impl UserRepository for MongoUserRepository {
     }
 
     async fn update(&self, user: &User) -> Result<()> {
-        let doc = UserDocument::from(user);
-
-        let update_doc = doc! {
-            "$set": {
-                "did": &doc.did,
-                "evm_address": &doc.evm_address,
-                "reputation_score": doc.reputation_score,
-                "is_provider": doc.is_provider,
-                "is_verified": doc.is_verified,
-                "updated_at": doc.updated_at
-            }
-        };
+        let update_doc = update_document(user)?;
 
         let result = self
             .collection
@@ -200,20 +252,54 @@ impl UserRepository for MongoUserRepository {
     }
 
     async fn delete(&self, id: &str) -> Result<()> {
-        let result = self
-            .collection
-            .delete_one(doc! {"user_id": id}, None)
+        self.set_deleted_at(id, Some(crate::shared::utils::now()))
             .await
-            .map_err(|e| PeerPowerError::Database {
-                message: format!("Failed to delete user: {}", e),
-            })?;
+    }
 
-        if result.deleted_count == 0 {
-            return Err(PeerPowerError::NotFound {
-                resource: format!("User with id: {}", id),
-            });
+    async fn restore(&self, id: &str) -> Result<()> {
+        self.set_deleted_at(id, None).await
+    }
+}
+
+/// The fields of `user` an update writes
+fn update_document(user: &User) -> Result<Document> {
+    let doc = UserDocument::from(user);
+    let quality_sla = bson::to_bson(&doc.quality_sla).map_err(|e| PeerPowerError::Internal {
+        message: format!("Failed to serialize quality SLA: {}", e),
+    })?;
+
+    Ok(doc! {
+        "$set": {
+            "did": &doc.did,
+            "evm_address": &doc.evm_address,
+            "reputation_score": doc.reputation_score,
+            "is_provider": doc.is_provider,
+            "is_verified": doc.is_verified,
+            "quality_sla": quality_sla,
+            "recipient_privacy": doc.recipient_privacy,
+            "pricing_plan_id": &doc.pricing_plan_id,
+            "updated_at": stored_timestamp(doc.updated_at)
         }
+    })
+}
 
-        Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::test_support::read_back;
+
+    #[test]
+    fn test_updated_user_reads_back() {
+        let user = User::new(PhoneNumber::new("+85512345678".to_string()).unwrap());
+        let mut verified = user.clone();
+        verified.is_verified = true;
+        verified.updated_at = crate::shared::utils::now();
+
+        let stored = read_back(
+            &UserDocument::from(&user),
+            &update_document(&verified).unwrap(),
+        );
+        assert!(stored.is_verified);
+        assert_eq!(stored.updated_at, verified.updated_at);
     }
 }
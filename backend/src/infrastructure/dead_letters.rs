@@ -0,0 +1,300 @@
+use futures::stream::TryStreamExt;
+use mongodb::bson::{doc, Document};
+use mongodb::options::FindOptions;
+use mongodb::{Collection, Database};
+use serde::Serialize;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::domain::entities::{DeadLetterJob, DeadLetterStatus, Job, Message};
+use crate::infrastructure::job_queue::JobQueue;
+use crate::shared::pagination::Cursor;
+use crate::shared::types::MessageStatus;
+use crate::shared::utils::stored_timestamp;
+use crate::shared::{PageRequest, PageResponse, PeerPowerError, Result};
+
+/// What a bulk action on jobs or dead-letter entries did with each id
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct JobBatch {
+    pub processed: Vec<String>,
+    pub skipped: Vec<JobSkip>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobSkip {
+    pub id: String,
+    pub reason: String,
+}
+
+impl JobBatch {
+    pub fn skip(&mut self, id: &str, reason: impl Into<String>) {
+        self.skipped.push(JobSkip {
+            id: id.to_string(),
+            reason: reason.into(),
+        });
+    }
+}
+
+/// Jobs that exhausted their retries, held for admin review instead of
+/// disappearing into a failed status
+pub struct DeadLetterQueue {
+    entries: Collection<DeadLetterJob>,
+    jobs: Collection<Job>,
+    messages: Collection<Message>,
+    job_queue: Arc<JobQueue>,
+}
+
+impl DeadLetterQueue {
+    pub fn new(database: Arc<Database>, job_queue: Arc<JobQueue>) -> Self {
+        Self {
+            entries: database.collection("dead_letter_jobs"),
+            jobs: database.collection("jobs"),
+            messages: database.collection("messages"),
+            job_queue,
+        }
+    }
+
+    /// Record a job that will not be retried again
+    pub async fn capture(&self, job: &Job) -> Result<DeadLetterJob> {
+        let final_error = job
+            .error_message
+            .clone()
+            .unwrap_or_else(|| "Retries exhausted".to_string());
+        let entry = DeadLetterJob::new(job.clone(), final_error);
+
+        self.entries
+            .insert_one(&entry, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to store dead-letter job: {}", e),
+            })?;
+
+        metrics::counter!("jobs_dead_lettered_total").increment(1);
+        warn!(
+            "Job {} for message {} dead-lettered after {} attempts: {}",
+            entry.job_id,
+            entry.message_id,
+            entry.attempts.len(),
+            entry.final_error
+        );
+        Ok(entry)
+    }
+
+    /// One page of the entries in a status, newest first
+    pub async fn by_status(
+        &self,
+        status: DeadLetterStatus,
+        page: &PageRequest,
+    ) -> Result<PageResponse<DeadLetterJob>> {
+        let entries = self
+            .entries
+            .find(
+                page.filter(doc! {"status": format!("{:?}", status)}),
+                FindOptions::builder()
+                    .sort(page.sort())
+                    .limit(page.fetch_limit())
+                    .build(),
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to query dead-letter jobs: {}", e),
+            })?
+            .try_collect()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read dead-letter jobs: {}", e),
+            })?;
+        Ok(page.page(entries, |entry: &DeadLetterJob| {
+            Cursor::new(entry.created_at, &entry.id)
+        }))
+    }
+
+    /// Entries still waiting for an admin
+    pub async fn pending_count(&self) -> Result<u64> {
+        self.entries
+            .count_documents(
+                doc! {"status": format!("{:?}", DeadLetterStatus::Pending)},
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to count dead-letter jobs: {}", e),
+            })
+    }
+
+    pub async fn get(&self, id: &str) -> Result<DeadLetterJob> {
+        self.entries
+            .find_one(doc! {"id": id}, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch dead-letter job: {}", e),
+            })?
+            .ok_or_else(|| PeerPowerError::NotFound {
+                resource: format!("Dead-letter job with ID: {}", id),
+            })
+    }
+
+    /// Put pending entries back on the job queue with a fresh retry budget.
+    /// Entries whose message expired or moved on are skipped and left pending.
+    pub async fn requeue(&self, ids: &[String], resolved_by: &str) -> Result<JobBatch> {
+        let mut batch = JobBatch::default();
+        for id in ids {
+            let entry = match self.pending(id).await? {
+                Ok(entry) => entry,
+                Err(reason) => {
+                    batch.skipped.push(JobSkip {
+                        id: id.clone(),
+                        reason,
+                    });
+                    continue;
+                }
+            };
+
+            let message = self
+                .messages
+                .find_one(doc! {"id": &entry.message_id}, None)
+                .await
+                .map_err(|e| PeerPowerError::Database {
+                    message: format!("Failed to fetch message: {}", e),
+                })?;
+            let mut message = match message {
+                Some(message) if message.is_expired() => {
+                    batch.skipped.push(JobSkip {
+                        id: id.clone(),
+                        reason: "Message has expired".to_string(),
+                    });
+                    continue;
+                }
+                Some(message) => message,
+                None => {
+                    batch.skipped.push(JobSkip {
+                        id: id.clone(),
+                        reason: "Message no longer exists".to_string(),
+                    });
+                    continue;
+                }
+            };
+            if message.transition_to(MessageStatus::Pending).is_err() {
+                batch.skipped.push(JobSkip {
+                    id: id.clone(),
+                    reason: format!("Message is {:?}", message.status).to_lowercase(),
+                });
+                continue;
+            }
+
+            // Claimed before anything is queued, so a concurrent requeue can't double it
+            if !self
+                .resolve(id, DeadLetterStatus::Requeued, resolved_by)
+                .await?
+            {
+                batch.skipped.push(JobSkip {
+                    id: id.clone(),
+                    reason: "Already resolved".to_string(),
+                });
+                continue;
+            }
+
+            let mut job = entry.job;
+            job.reset_for_requeue();
+            self.messages
+                .replace_one(doc! {"id": &message.id}, &message, None)
+                .await
+                .map_err(|e| PeerPowerError::Database {
+                    message: format!("Failed to update message: {}", e),
+                })?;
+            self.jobs
+                .replace_one(doc! {"id": &job.id}, &job, None)
+                .await
+                .map_err(|e| PeerPowerError::Database {
+                    message: format!("Failed to update job: {}", e),
+                })?;
+            self.job_queue.enqueue(&job).await?;
+            batch.processed.push(id.clone());
+        }
+
+        info!(
+            "{} requeued {} dead-letter jobs ({} skipped)",
+            resolved_by,
+            batch.processed.len(),
+            batch.skipped.len()
+        );
+        Ok(batch)
+    }
+
+    /// Drop pending entries; their messages stay failed
+    pub async fn discard(&self, ids: &[String], resolved_by: &str) -> Result<JobBatch> {
+        let mut batch = JobBatch::default();
+        for id in ids {
+            if self
+                .resolve(id, DeadLetterStatus::Discarded, resolved_by)
+                .await?
+            {
+                batch.processed.push(id.clone());
+            } else {
+                batch.skipped.push(JobSkip {
+                    id: id.clone(),
+                    reason: "Not found or already resolved".to_string(),
+                });
+            }
+        }
+
+        info!(
+            "{} discarded {} dead-letter jobs ({} skipped)",
+            resolved_by,
+            batch.processed.len(),
+            batch.skipped.len()
+        );
+        Ok(batch)
+    }
+
+    /// The entry if it is still pending, or why it can't be acted on
+    async fn pending(&self, id: &str) -> Result<std::result::Result<DeadLetterJob, String>> {
+        match self.get(id).await {
+            Ok(entry) if entry.status == DeadLetterStatus::Pending => Ok(Ok(entry)),
+            Ok(_) => Ok(Err("Already resolved".to_string())),
+            Err(PeerPowerError::NotFound { .. }) => Ok(Err("Not found".to_string())),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Move a pending entry to `status`; false if it was not pending
+    async fn resolve(&self, id: &str, status: DeadLetterStatus, resolved_by: &str) -> Result<bool> {
+        let result = self
+            .entries
+            .update_one(
+                doc! {"id": id, "status": format!("{:?}", DeadLetterStatus::Pending)},
+                resolution(status, resolved_by),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to update dead-letter job: {}", e),
+            })?;
+        Ok(result.modified_count == 1)
+    }
+}
+
+fn resolution(status: DeadLetterStatus, resolved_by: &str) -> Document {
+    doc! {"$set": {
+        "status": format!("{:?}", status),
+        "resolved_by": resolved_by,
+        "resolved_at": stored_timestamp(crate::shared::utils::now()),
+    }}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::test_support::read_back;
+
+    #[test]
+    fn test_discarded_entry_reads_back() {
+        let job = Job::new("message-1".to_string(), "provider-1".to_string());
+        let entry = DeadLetterJob::new(job, "Provider unreachable".to_string());
+
+        let discarded = read_back(&entry, &resolution(DeadLetterStatus::Discarded, "admin-1"));
+        assert_eq!(discarded.status, DeadLetterStatus::Discarded);
+        assert_eq!(discarded.resolved_by.as_deref(), Some("admin-1"));
+        assert!(discarded.resolved_at.is_some());
+    }
+}
@@ -0,0 +1,440 @@
+use mongodb::bson::{doc, Document};
+use mongodb::options::UpdateOptions;
+use mongodb::{Client, Collection, Database};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::domain::entities::{EarningsEvent, Job, LedgerTransaction, Message, Provider};
+use crate::infrastructure::database::MongoDatabase;
+use crate::infrastructure::ledger::Ledger;
+use crate::shared::types::MessageStatus;
+use crate::shared::utils::stored_timestamp;
+use crate::shared::{PeerPowerError, Result};
+
+/// Stores what a provider reports about a message it was assigned.
+///
+/// The message's new status, its job's outcome and, for a delivery, its
+/// ledger transaction, earnings event and the provider's delivered count are
+/// written in one MongoDB transaction, and only if the message is still as
+/// it was read; a confirmation racing another writes nothing. A repeated
+/// report of the same status leaves the job and the count alone and only
+/// fills in a missing ledger transaction or earnings event (both keyed on
+/// the message), so a delivery is counted and paid once.
+///
+/// Like [`JobOutbox`](crate::infrastructure::job_outbox::JobOutbox), it falls
+/// back to writing them one after another (message first) against a
+/// standalone server.
+pub struct DeliveryConfirmations {
+    client: Client,
+    messages: Collection<Message>,
+    jobs: Collection<Job>,
+    providers: Collection<Provider>,
+    ledger: Collection<LedgerTransaction>,
+    earnings_events: Collection<EarningsEvent>,
+    transactions_unsupported: AtomicBool,
+}
+
+/// What a delivery adds besides the status: the charge, earning and fee,
+/// and the provider's earnings event
+pub struct DeliveryEarnings {
+    pub transaction: LedgerTransaction,
+    pub event: EarningsEvent,
+}
+
+/// What [`DeliveryConfirmations::record`] did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Recorded {
+    Stale,                  // the message changed in between; nothing was written
+    Saved { posted: bool }, // posted: the delivery's ledger transaction is new
+}
+
+impl DeliveryConfirmations {
+    pub fn new(client: Client, database: Arc<Database>) -> Self {
+        Self {
+            client,
+            messages: database.collection("messages"),
+            jobs: database.collection("jobs"),
+            providers: database.collection("providers"),
+            ledger: database.collection("ledger"),
+            earnings_events: database.collection("earnings_events"),
+            transactions_unsupported: AtomicBool::new(false),
+        }
+    }
+
+    /// Store `message`, read with status `previous` and since moved on by the
+    /// provider's report; `error` is the provider's reason for a failure and
+    /// `earnings` what a delivery pays.
+    pub async fn record(
+        &self,
+        message: &Message,
+        previous: &MessageStatus,
+        error: Option<String>,
+        earnings: Option<&DeliveryEarnings>,
+    ) -> Result<Recorded> {
+        let writes = Writes::new(message, previous, error, earnings)?;
+
+        if !self.transactions_unsupported.load(Ordering::Relaxed) {
+            match self.record_in_transaction(&writes).await {
+                Ok(recorded) => return Ok(recorded),
+                Err(e) if MongoDatabase::transactions_unsupported(&e) => {
+                    warn!(
+                        "MongoDB does not support transactions here; recording confirmations without one"
+                    );
+                    self.transactions_unsupported.store(true, Ordering::Relaxed);
+                }
+                Err(e) => {
+                    return Err(PeerPowerError::Database {
+                        message: format!("Failed to record delivery confirmation: {}", e),
+                    })
+                }
+            }
+        }
+
+        self.record_in_sequence(&writes)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to record delivery confirmation: {}", e),
+            })
+    }
+
+    /// Count a newly posted ledger transaction, once it is stored
+    fn saved(&self, writes: &Writes<'_>, posted: bool) -> Recorded {
+        if posted {
+            if let Some(earnings) = writes.earnings {
+                Ledger::count_posted(&earnings.transaction.kind);
+            }
+        }
+        Recorded::Saved { posted }
+    }
+
+    async fn record_in_transaction(&self, writes: &Writes<'_>) -> mongodb::error::Result<Recorded> {
+        // Dropping the session before the commit aborts the transaction
+        let mut session = self.client.start_session(None).await?;
+        session.start_transaction(None).await?;
+
+        let saved = self
+            .messages
+            .update_one_with_session(
+                writes.message_filter(),
+                writes.message_update(),
+                None,
+                &mut session,
+            )
+            .await?;
+        if saved.matched_count == 0 {
+            session.abort_transaction().await?;
+            return Ok(Recorded::Stale);
+        }
+
+        if !writes.repeated() {
+            let job = self
+                .jobs
+                .find_one_with_session(doc! {"message_id": &writes.message.id}, None, &mut session)
+                .await?;
+            if let Some(job) = writes.job(job) {
+                self.jobs
+                    .replace_one_with_session(doc! {"id": &job.id}, &job, None, &mut session)
+                    .await?;
+            }
+        }
+        let mut posted = false;
+        if let Some((filter, update)) = &writes.posting {
+            posted = self
+                .ledger
+                .update_one_with_session(filter.clone(), update.clone(), upsert(), &mut session)
+                .await?
+                .upserted_id
+                .is_some();
+        }
+        if let Some((filter, update)) = &writes.event {
+            self.earnings_events
+                .update_one_with_session(filter.clone(), update.clone(), upsert(), &mut session)
+                .await?;
+        }
+        if let Some((filter, update)) = writes.provider_update(posted) {
+            self.providers
+                .update_one_with_session(filter, update, None, &mut session)
+                .await?;
+        }
+
+        session.commit_transaction().await?;
+        Ok(self.saved(writes, posted))
+    }
+
+    async fn record_in_sequence(&self, writes: &Writes<'_>) -> mongodb::error::Result<Recorded> {
+        let saved = self
+            .messages
+            .update_one(writes.message_filter(), writes.message_update(), None)
+            .await?;
+        if saved.matched_count == 0 {
+            return Ok(Recorded::Stale);
+        }
+
+        if !writes.repeated() {
+            let job = self
+                .jobs
+                .find_one(doc! {"message_id": &writes.message.id}, None)
+                .await?;
+            if let Some(job) = writes.job(job) {
+                self.jobs
+                    .replace_one(doc! {"id": &job.id}, &job, None)
+                    .await?;
+            }
+        }
+        let mut posted = false;
+        if let Some((filter, update)) = &writes.posting {
+            posted = self
+                .ledger
+                .update_one(filter.clone(), update.clone(), upsert())
+                .await?
+                .upserted_id
+                .is_some();
+        }
+        if let Some((filter, update)) = &writes.event {
+            self.earnings_events
+                .update_one(filter.clone(), update.clone(), upsert())
+                .await?;
+        }
+        if let Some((filter, update)) = writes.provider_update(posted) {
+            self.providers.update_one(filter, update, None).await?;
+        }
+        Ok(self.saved(writes, posted))
+    }
+}
+
+fn upsert() -> UpdateOptions {
+    UpdateOptions::builder().upsert(true).build()
+}
+
+/// The documents one confirmation changes, the same with or without a transaction
+struct Writes<'a> {
+    message: &'a Message,
+    previous: &'a MessageStatus,
+    provider_id: &'a str,
+    error: Option<String>,
+    cancellation: mongodb::bson::Bson,
+    earnings: Option<&'a DeliveryEarnings>,
+    posting: Option<(Document, Document)>, // the ledger transaction's upsert
+    event: Option<(Document, Document)>,   // the earnings event's upsert
+}
+
+impl<'a> Writes<'a> {
+    fn new(
+        message: &'a Message,
+        previous: &'a MessageStatus,
+        error: Option<String>,
+        earnings: Option<&'a DeliveryEarnings>,
+    ) -> Result<Self> {
+        let provider_id =
+            message
+                .provider_id
+                .as_deref()
+                .ok_or_else(|| PeerPowerError::Internal {
+                    message: format!("Message {} has no provider to confirm it", message.id),
+                })?;
+        let cancellation = mongodb::bson::to_bson(&message.cancellation).map_err(|e| {
+            PeerPowerError::Internal {
+                message: format!("Failed to serialize cancellation: {}", e),
+            }
+        })?;
+        let posting = earnings
+            .map(|earnings| Ledger::posting(&earnings.transaction))
+            .transpose()?;
+        let event = earnings
+            .map(|earnings| {
+                mongodb::bson::to_document(&earnings.event)
+                    .map(|event| {
+                        (
+                            doc! {"message_id": &message.id},
+                            doc! {"$setOnInsert": event},
+                        )
+                    })
+                    .map_err(|e| PeerPowerError::Internal {
+                        message: format!("Failed to serialize earnings event: {}", e),
+                    })
+            })
+            .transpose()?;
+        Ok(Self {
+            message,
+            previous,
+            provider_id,
+            error,
+            cancellation,
+            earnings,
+            posting,
+            event,
+        })
+    }
+
+    /// The same status reported again, e.g. a retried delivery report
+    fn repeated(&self) -> bool {
+        *self.previous == self.message.status
+    }
+
+    /// Still assigned to this provider, and not moved on by anyone else
+    fn message_filter(&self) -> Document {
+        doc! {
+            "id": &self.message.id,
+            "provider_id": self.provider_id,
+            "status": format!("{:?}", self.previous),
+        }
+    }
+
+    /// Only the fields a confirmation changes
+    fn message_update(&self) -> Document {
+        doc! {"$set": {
+            "status": format!("{:?}", self.message.status),
+            "updated_at": stored_timestamp(self.message.updated_at),
+            "cancellation": self.cancellation.clone(),
+        }}
+    }
+
+    /// The job with the report's outcome, or None if it is unchanged
+    fn job(&self, job: Option<Job>) -> Option<Job> {
+        let mut job = job?;
+        match self.message.status {
+            MessageStatus::Delivered => job.mark_completed(),
+            MessageStatus::Failed => job.mark_failed(
+                self.error
+                    .clone()
+                    .unwrap_or_else(|| "Delivery failed".to_string()),
+            ),
+            _ => return None,
+        }
+        Some(job)
+    }
+
+    /// A new delivery counted toward the provider; with earnings, only along
+    /// with the ledger transaction that pays it
+    fn provider_update(&self, posted: bool) -> Option<(Document, Document)> {
+        let counted = self.message.status == MessageStatus::Delivered
+            && !self.repeated()
+            && (self.earnings.is_none() || posted);
+        counted.then(|| {
+            (
+                doc! {"id": self.provider_id},
+                doc! {
+                    "$inc": {"total_messages_delivered": 1},
+                    "$set": {"updated_at": stored_timestamp(self.message.updated_at)},
+                },
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entities::MessagePriority;
+    use crate::shared::test_support::{read_back, read_back_from};
+    use crate::shared::types::{Carrier, PhoneNumber};
+    use crate::shared::Money;
+
+    fn delivered() -> Message {
+        let mut message = Message::new(
+            "client-1".to_string(),
+            "Hello".to_string(),
+            PhoneNumber::new("+85512345678".to_string()).unwrap(),
+            MessagePriority::Normal,
+            None,
+            None,
+        );
+        message
+            .assign_to_provider("provider-1".to_string())
+            .unwrap();
+        message.transition_to(MessageStatus::Delivered).unwrap();
+        message
+    }
+
+    #[test]
+    fn test_delivery_is_counted_once() {
+        let message = delivered();
+        let writes = Writes::new(&message, &MessageStatus::Assigned, None, None).unwrap();
+        assert!(writes
+            .job(Some(Job::new(message.id.clone(), "provider-1".to_string())))
+            .is_some());
+        assert!(writes.provider_update(false).is_some());
+
+        // A retried report of the same delivery
+        let writes = Writes::new(&message, &MessageStatus::Delivered, None, None).unwrap();
+        assert!(writes.repeated());
+        assert!(writes.provider_update(true).is_none());
+    }
+
+    #[test]
+    fn test_paid_delivery_is_counted_with_its_ledger_transaction() {
+        let message = delivered();
+        let earnings = DeliveryEarnings {
+            transaction: LedgerTransaction::message_delivery(
+                &message.id,
+                "client-1",
+                "provider-1",
+                Money::from_ppt(0.05),
+                Money::from_ppt(0.04),
+            ),
+            event: EarningsEvent::new(
+                message.id.clone(),
+                "provider-1".to_string(),
+                "client-1".to_string(),
+                Money::from_ppt(0.04),
+                Money::default(),
+                MessagePriority::Normal,
+                chrono::NaiveDate::from_ymd_opt(2024, 5, 1).unwrap(),
+            ),
+        };
+        let writes =
+            Writes::new(&message, &MessageStatus::Assigned, None, Some(&earnings)).unwrap();
+        assert!(writes.posting.is_some() && writes.event.is_some());
+        assert!(writes.provider_update(true).is_some());
+        // Already posted by an earlier attempt that got this far
+        assert!(writes.provider_update(false).is_none());
+    }
+
+    #[test]
+    fn test_confirmed_delivery_reads_back() {
+        let message = delivered();
+        let earnings = DeliveryEarnings {
+            transaction: LedgerTransaction::message_delivery(
+                &message.id,
+                "client-1",
+                "provider-1",
+                Money::from_ppt(0.05),
+                Money::from_ppt(0.04),
+            ),
+            event: EarningsEvent::new(
+                message.id.clone(),
+                "provider-1".to_string(),
+                "client-1".to_string(),
+                Money::from_ppt(0.04),
+                Money::default(),
+                MessagePriority::Normal,
+                chrono::NaiveDate::from_ymd_opt(2024, 5, 1).unwrap(),
+            ),
+        };
+        let writes =
+            Writes::new(&message, &MessageStatus::Assigned, None, Some(&earnings)).unwrap();
+
+        let mut assigned = message.clone();
+        assigned.status = MessageStatus::Assigned;
+        let stored = read_back(&assigned, &writes.message_update());
+        assert_eq!(stored.status, MessageStatus::Delivered);
+        assert_eq!(stored.updated_at, message.updated_at);
+
+        let (filter, update) = writes.event.clone().unwrap();
+        let event: EarningsEvent = read_back_from(filter, &update);
+        assert_eq!(event.message_id, message.id);
+
+        let provider = Provider::new(
+            "user-1".to_string(),
+            PhoneNumber::new("+85512345679".to_string()).unwrap(),
+            Carrier::Smart,
+        );
+        let (_, update) = writes.provider_update(true).unwrap();
+        let counted = read_back(&provider, &update);
+        assert_eq!(
+            counted.total_messages_delivered,
+            provider.total_messages_delivered + 1
+        );
+    }
+}
@@ -0,0 +1,404 @@
+use async_trait::async_trait;
+use chrono::Timelike;
+use futures::stream::TryStreamExt;
+use mongodb::options::FindOptions;
+use mongodb::{Collection, Database};
+use reqwest::Client;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use tracing::warn;
+
+use crate::config::DeliveryModelConfig;
+use crate::domain::entities::message::MessagePriority;
+use crate::domain::entities::provider::SELF_TEST_CLIENT_ID;
+use crate::domain::entities::{Message, Provider};
+use crate::domain::services::{DeliveryEstimate, DeliveryFeatures, DeliveryTimeModel};
+use crate::infrastructure::job_queue::JobQueue;
+use crate::shared::types::{Carrier, MessageStatus, ProviderStatus};
+use crate::shared::utils::stored_timestamp;
+use crate::shared::{PeerPowerError, Result};
+
+/// Carrier latency assumed until enough recent deliveries have been seen
+const DEFAULT_CARRIER_LATENCY_SECONDS: f64 = 20.0;
+
+/// Jobs one provider typically works through per minute
+const JOBS_PER_PROVIDER_PER_MINUTE: f64 = 6.0;
+
+/// Wait assumed when no provider on the carrier is online
+const NO_PROVIDER_WAIT_SECONDS: f64 = 600.0;
+
+/// Extra time a failed attempt costs before the retry lands
+const RETRY_PENALTY_SECONDS: f64 = 90.0;
+
+/// Recent deliveries sampled per carrier, and how far back to look
+const HEALTH_SAMPLE_LIMIT: i64 = 500;
+const HEALTH_WINDOW_MINUTES: i64 = 60;
+
+/// Below this many finished messages the carrier's history is ignored
+const MIN_HEALTH_SAMPLES: usize = 10;
+
+/// How long carrier health is reused before re-sampling
+const HEALTH_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Rule-of-thumb model: carrier latency plus the time to drain the part of
+/// the queue ahead of the message, scaled for the hour of day and padded
+/// for expected retries
+#[derive(Debug, Default)]
+pub struct HeuristicDeliveryModel;
+
+impl HeuristicDeliveryModel {
+    /// Share of the queue a message of this priority waits behind
+    fn queue_share(priority: &MessagePriority) -> f64 {
+        match priority {
+            MessagePriority::Urgent => 0.1,
+            MessagePriority::High => 0.3,
+            MessagePriority::Normal => 0.7,
+            MessagePriority::Low => 1.0,
+        }
+    }
+
+    /// Few providers are awake overnight; evenings and mornings are busiest
+    fn hour_factor(local_hour: u32) -> f64 {
+        match local_hour {
+            0..=5 => 1.5,
+            7..=9 | 17..=20 => 1.25,
+            _ => 1.0,
+        }
+    }
+
+    pub fn estimate(&self, features: &DeliveryFeatures) -> DeliveryEstimate {
+        let latency = features
+            .carrier_median_latency_seconds
+            .unwrap_or(DEFAULT_CARRIER_LATENCY_SECONDS);
+
+        let queue_wait = if features.online_providers == 0 {
+            NO_PROVIDER_WAIT_SECONDS
+        } else {
+            let per_second = features.online_providers as f64 * JOBS_PER_PROVIDER_PER_MINUTE / 60.0;
+            features.queue_depth as f64 * Self::queue_share(&features.priority) / per_second
+        };
+
+        let failure_rate = 1.0 - features.carrier_success_rate.unwrap_or(1.0).clamp(0.0, 1.0);
+        let expected = (latency + queue_wait) * Self::hour_factor(features.local_hour)
+            + failure_rate * RETRY_PENALTY_SECONDS;
+        let p90 = expected * 2.0 + failure_rate * RETRY_PENALTY_SECONDS * 2.0;
+
+        DeliveryEstimate {
+            expected_seconds: expected.round() as i64,
+            p90_seconds: p90.round() as i64,
+            model: self.name().to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl DeliveryTimeModel for HeuristicDeliveryModel {
+    fn name(&self) -> &'static str {
+        "heuristic"
+    }
+
+    async fn predict(&self, features: &DeliveryFeatures) -> Result<DeliveryEstimate> {
+        Ok(self.estimate(features))
+    }
+}
+
+/// External model service: features are POSTed as JSON and an estimate is
+/// returned in the same shape as `DeliveryEstimate`
+pub struct RemoteDeliveryModel {
+    config: DeliveryModelConfig,
+    client: Client,
+}
+
+impl RemoteDeliveryModel {
+    pub fn new(config: DeliveryModelConfig) -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_millis(config.timeout_ms))
+            .build()
+            .unwrap_or_default();
+        Self { config, client }
+    }
+
+    pub fn is_configured(&self) -> bool {
+        !self.config.url.is_empty()
+    }
+}
+
+#[async_trait]
+impl DeliveryTimeModel for RemoteDeliveryModel {
+    fn name(&self) -> &'static str {
+        "remote"
+    }
+
+    async fn predict(&self, features: &DeliveryFeatures) -> Result<DeliveryEstimate> {
+        let mut request = self.client.post(&self.config.url).json(features);
+        if !self.config.api_key.is_empty() {
+            request = request.bearer_auth(&self.config.api_key);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| PeerPowerError::ExternalService {
+                service: "Delivery model".to_string(),
+                message: format!("Prediction request failed: {}", e),
+            })?;
+
+        if !response.status().is_success() {
+            return Err(PeerPowerError::ExternalService {
+                service: "Delivery model".to_string(),
+                message: format!("Model service returned error {}", response.status()),
+            });
+        }
+
+        let mut estimate: DeliveryEstimate =
+            response
+                .json()
+                .await
+                .map_err(|e| PeerPowerError::ExternalService {
+                    service: "Delivery model".to_string(),
+                    message: format!("Failed to parse prediction: {}", e),
+                })?;
+        if estimate.model.is_empty() {
+            estimate.model = self.name().to_string();
+        }
+        Ok(estimate)
+    }
+}
+
+/// Recent delivery record for one carrier
+#[derive(Debug, Clone, Copy, Default)]
+struct CarrierHealth {
+    success_rate: Option<f64>,
+    median_latency_seconds: Option<f64>,
+}
+
+/// Gathers live network conditions and asks the configured model for an
+/// ETA. The external model, when set, is tried first; the heuristic always
+/// answers if it fails, so callers never go without an estimate.
+pub struct DeliveryPredictor {
+    messages: Collection<Message>,
+    providers: Collection<Provider>,
+    job_queue: Arc<JobQueue>,
+    models: Vec<Arc<dyn DeliveryTimeModel>>,
+    fallback: HeuristicDeliveryModel,
+    utc_offset_hours: i32,
+    health_cache: RwLock<HashMap<String, (Instant, CarrierHealth)>>,
+}
+
+impl DeliveryPredictor {
+    pub fn new(
+        database: Arc<Database>,
+        job_queue: Arc<JobQueue>,
+        config: DeliveryModelConfig,
+        utc_offset_hours: i32,
+    ) -> Self {
+        let remote = RemoteDeliveryModel::new(config);
+        let mut models: Vec<Arc<dyn DeliveryTimeModel>> = Vec::new();
+        if remote.is_configured() {
+            models.push(Arc::new(remote));
+        }
+
+        Self {
+            messages: database.collection("messages"),
+            providers: database.collection("providers"),
+            job_queue,
+            models,
+            fallback: HeuristicDeliveryModel,
+            utc_offset_hours,
+            health_cache: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Estimate delivery time for a message submitted now
+    pub async fn estimate(
+        &self,
+        carrier: &Carrier,
+        priority: &MessagePriority,
+    ) -> DeliveryEstimate {
+        let features = self.features(carrier, priority).await;
+
+        for model in &self.models {
+            match model.predict(&features).await {
+                Ok(estimate) => return estimate,
+                Err(e) => warn!(
+                    "Delivery model {} failed, falling back: {}",
+                    model.name(),
+                    e
+                ),
+            }
+        }
+        self.fallback.estimate(&features)
+    }
+
+    /// Compare a delivered message with the ETA it was quoted at submission
+    pub fn record_outcome(&self, message: &Message) {
+        let (Some(latency), Some(predicted_at)) = (
+            message.delivery_latency_seconds(),
+            message.predicted_delivery_at,
+        ) else {
+            return;
+        };
+
+        let carrier = format!("{:?}", message.recipient_carrier);
+        let predicted = (predicted_at - message.created_at).num_seconds();
+        metrics::histogram!("delivery_eta_error_seconds", "carrier" => carrier.clone())
+            .record((latency - predicted) as f64);
+        if message.missed_predicted_delivery() {
+            metrics::counter!("delivery_eta_missed_total", "carrier" => carrier).increment(1);
+        }
+    }
+
+    /// Current features; a failed lookup leaves its feature at a neutral value
+    async fn features(&self, carrier: &Carrier, priority: &MessagePriority) -> DeliveryFeatures {
+        let queue_depth = self.job_queue.depth().await.unwrap_or_else(|e| {
+            warn!("Failed to read queue depth for prediction: {}", e);
+            0
+        });
+        let online_providers = self.online_providers(carrier).await.unwrap_or_else(|e| {
+            warn!("Failed to count online providers for prediction: {}", e);
+            1
+        });
+        let health = self.carrier_health(carrier).await.unwrap_or_else(|e| {
+            warn!("Failed to sample carrier health for prediction: {}", e);
+            CarrierHealth::default()
+        });
+        let local_hour = (crate::shared::utils::now()
+            + chrono::Duration::hours(self.utc_offset_hours as i64))
+        .hour();
+
+        DeliveryFeatures {
+            carrier: carrier.clone(),
+            priority: priority.clone(),
+            queue_depth,
+            online_providers,
+            carrier_success_rate: health.success_rate,
+            carrier_median_latency_seconds: health.median_latency_seconds,
+            local_hour,
+        }
+    }
+
+    async fn online_providers(&self, carrier: &Carrier) -> Result<u32> {
+        let count = self
+            .providers
+            .count_documents(
+                mongodb::bson::doc! {
+                    "carrier": format!("{:?}", carrier),
+                    "status": format!("{:?}", ProviderStatus::Online),
+                    "carrier_mismatch": null,
+                },
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to count providers: {}", e),
+            })?;
+        Ok(count as u32)
+    }
+
+    async fn carrier_health(&self, carrier: &Carrier) -> Result<CarrierHealth> {
+        let key = format!("{:?}", carrier);
+        if let Some((loaded_at, health)) = self.health_cache.read().await.get(&key) {
+            if loaded_at.elapsed() < HEALTH_CACHE_TTL {
+                return Ok(*health);
+            }
+        }
+
+        let since = crate::shared::utils::now() - chrono::Duration::minutes(HEALTH_WINDOW_MINUTES);
+        let options = FindOptions::builder()
+            .sort(mongodb::bson::doc! {"updated_at": -1})
+            .limit(HEALTH_SAMPLE_LIMIT)
+            .build();
+        let recent: Vec<Message> = self
+            .messages
+            .find(
+                mongodb::bson::doc! {
+                    "recipient_carrier": &key,
+                    "updated_at": {"$gte": stored_timestamp(since)},
+                    "status": {"$in": [
+                        format!("{:?}", MessageStatus::Delivered),
+                        format!("{:?}", MessageStatus::Failed),
+                    ]},
+                    "client_id": {"$ne": SELF_TEST_CLIENT_ID},
+                },
+                options,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to sample messages: {}", e),
+            })?
+            .try_collect()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read messages: {}", e),
+            })?;
+
+        let mut health = CarrierHealth::default();
+        if recent.len() >= MIN_HEALTH_SAMPLES {
+            let mut latencies: Vec<i64> = recent
+                .iter()
+                .filter_map(|message| message.delivery_latency_seconds())
+                .collect();
+            health.success_rate = Some(latencies.len() as f64 / recent.len() as f64);
+            if !latencies.is_empty() {
+                latencies.sort_unstable();
+                health.median_latency_seconds = Some(latencies[latencies.len() / 2] as f64);
+            }
+        }
+
+        self.health_cache
+            .write()
+            .await
+            .insert(key, (Instant::now(), health));
+        Ok(health)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn features(queue_depth: u64, online_providers: u32) -> DeliveryFeatures {
+        DeliveryFeatures {
+            carrier: Carrier::Smart,
+            priority: MessagePriority::Normal,
+            queue_depth,
+            online_providers,
+            carrier_success_rate: Some(1.0),
+            carrier_median_latency_seconds: Some(15.0),
+            local_hour: 12,
+        }
+    }
+
+    #[test]
+    fn test_deeper_queue_takes_longer() {
+        let model = HeuristicDeliveryModel;
+        let idle = model.estimate(&features(0, 10));
+        let busy = model.estimate(&features(500, 10));
+
+        assert_eq!(idle.expected_seconds, 15);
+        assert!(busy.expected_seconds > idle.expected_seconds);
+        assert!(busy.p90_seconds >= busy.expected_seconds);
+    }
+
+    #[test]
+    fn test_urgent_skips_most_of_the_queue() {
+        let model = HeuristicDeliveryModel;
+        let normal = model.estimate(&features(500, 10));
+        let mut urgent_features = features(500, 10);
+        urgent_features.priority = MessagePriority::Urgent;
+
+        assert!(model.estimate(&urgent_features).expected_seconds < normal.expected_seconds);
+    }
+
+    #[test]
+    fn test_no_online_providers_and_failures_add_delay() {
+        let model = HeuristicDeliveryModel;
+        let healthy = model.estimate(&features(0, 10));
+        let mut degraded = features(0, 0);
+        degraded.carrier_success_rate = Some(0.5);
+
+        assert!(model.estimate(&degraded).expected_seconds > healthy.expected_seconds + 600);
+    }
+}
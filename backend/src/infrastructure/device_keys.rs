@@ -0,0 +1,188 @@
+use base64::Engine;
+use rsa::pkcs1v15::{Signature, VerifyingKey};
+use rsa::pkcs8::{DecodePublicKey, EncodePublicKey};
+use rsa::signature::Verifier;
+use rsa::traits::PublicKeyParts;
+use rsa::RsaPublicKey;
+use sha2::{Digest, Sha256};
+
+use crate::config::ProviderConfig;
+use crate::domain::entities::DeviceKey;
+use crate::shared::{PeerPowerError, Result};
+
+/// Smallest device key accepted at enrollment
+const MIN_KEY_BITS: usize = 2048;
+
+/// A delivery confirmation as signed by the provider's device
+pub struct SignedConfirmation<'a> {
+    pub message_id: &'a str,
+    pub status: &'a str,
+    pub delivery_time: Option<&'a str>,
+    pub provider_message_id: Option<&'a str>,
+    pub signed_at: Option<i64>,     // unix seconds
+    pub signature: Option<&'a str>, // base64
+}
+
+/// Enrolls provider device keys and checks the signatures on delivery
+/// confirmations.
+///
+/// Devices sign `"{message_id}.{status}.{delivery_time}.{provider_message_id}.{signed_at}"`
+/// (absent fields as empty strings) with RSASSA-PKCS1-v1_5 / SHA-256. The
+/// message id binds a signature to one message, and `signed_at` must fall
+/// within the configured age so old confirmations can't be held back and
+/// replayed later.
+pub struct DeviceKeyVerifier {
+    config: ProviderConfig,
+}
+
+impl DeviceKeyVerifier {
+    pub fn new(config: ProviderConfig) -> Self {
+        Self { config }
+    }
+
+    /// Parse and check a public key submitted by a device
+    pub fn enroll(public_key_pem: &str) -> Result<DeviceKey> {
+        let invalid = |message: &str| PeerPowerError::ValidationError {
+            field: "device_public_key".to_string(),
+            message: message.to_string(),
+        };
+
+        let key = RsaPublicKey::from_public_key_pem(public_key_pem.trim())
+            .map_err(|_| invalid("Device key must be an RSA public key in SPKI PEM format"))?;
+        if key.size() * 8 < MIN_KEY_BITS {
+            return Err(invalid("Device key must be at least 2048 bits"));
+        }
+        let der = key
+            .to_public_key_der()
+            .map_err(|_| invalid("Device key could not be encoded"))?;
+
+        Ok(DeviceKey {
+            public_key: public_key_pem.trim().to_string(),
+            fingerprint: hex::encode(Sha256::digest(der.as_bytes())),
+            enrolled_at: crate::shared::utils::now(),
+        })
+    }
+
+    pub fn signing_payload(confirmation: &SignedConfirmation<'_>, signed_at: i64) -> String {
+        format!(
+            "{}.{}.{}.{}.{}",
+            confirmation.message_id,
+            confirmation.status,
+            confirmation.delivery_time.unwrap_or(""),
+            confirmation.provider_message_id.unwrap_or(""),
+            signed_at
+        )
+    }
+
+    /// Check a confirmation against the provider's device key. Providers that
+    /// haven't enrolled a key pass only while signed confirmations aren't required.
+    pub fn verify(
+        &self,
+        device_key: Option<&DeviceKey>,
+        confirmation: &SignedConfirmation<'_>,
+    ) -> Result<()> {
+        let Some(device_key) = device_key else {
+            if self.config.require_signed_confirmations {
+                return Err(PeerPowerError::AuthenticationFailed {
+                    reason: "No device key enrolled; register this device's key first".to_string(),
+                });
+            }
+            metrics::counter!("delivery_confirmation_unsigned_total").increment(1);
+            return Ok(());
+        };
+
+        Self::verify_signature(
+            device_key,
+            confirmation,
+            self.config.confirmation_max_age_seconds,
+            crate::shared::utils::now().timestamp(),
+        )
+        .map_err(|reason| {
+            metrics::counter!("delivery_confirmation_rejected_total", "reason" => reason)
+                .increment(1);
+            PeerPowerError::AuthenticationFailed {
+                reason: format!("Delivery confirmation rejected: {}", reason),
+            }
+        })
+    }
+
+    fn verify_signature(
+        device_key: &DeviceKey,
+        confirmation: &SignedConfirmation<'_>,
+        max_age_seconds: i64,
+        now: i64,
+    ) -> std::result::Result<(), &'static str> {
+        let signed_at = confirmation.signed_at.ok_or("missing signed_at")?;
+        if (now - signed_at).abs() > max_age_seconds {
+            return Err("signature expired");
+        }
+
+        let signature = confirmation
+            .signature
+            .and_then(|signature| {
+                base64::engine::general_purpose::STANDARD
+                    .decode(signature)
+                    .ok()
+            })
+            .and_then(|bytes| Signature::try_from(bytes.as_slice()).ok())
+            .ok_or("missing or malformed signature")?;
+        let key = RsaPublicKey::from_public_key_pem(&device_key.public_key)
+            .map_err(|_| "stored device key unreadable")?;
+
+        VerifyingKey::<Sha256>::new(key)
+            .verify(
+                Self::signing_payload(confirmation, signed_at).as_bytes(),
+                &signature,
+            )
+            .map_err(|_| "bad signature")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rsa::pkcs1v15::SigningKey;
+    use rsa::pkcs8::LineEnding;
+    use rsa::signature::{SignatureEncoding, Signer};
+    use rsa::RsaPrivateKey;
+
+    #[test]
+    fn test_confirmation_signed_by_device_key() {
+        let private_key = RsaPrivateKey::new(&mut rand::thread_rng(), 2048).unwrap();
+        let pem = RsaPublicKey::from(&private_key)
+            .to_public_key_pem(LineEnding::LF)
+            .unwrap();
+        let device_key = DeviceKeyVerifier::enroll(&pem).unwrap();
+
+        let now = 1_700_000_000;
+        let mut confirmation = SignedConfirmation {
+            message_id: "msg-1",
+            status: "delivered",
+            delivery_time: Some("2024-01-01T00:00:00Z"),
+            provider_message_id: None,
+            signed_at: Some(now - 10),
+            signature: None,
+        };
+        let payload = DeviceKeyVerifier::signing_payload(&confirmation, now - 10);
+        assert_eq!(payload, "msg-1.delivered.2024-01-01T00:00:00Z..1699999990");
+
+        let signature = base64::engine::general_purpose::STANDARD.encode(
+            SigningKey::<Sha256>::new(private_key)
+                .sign(payload.as_bytes())
+                .to_bytes(),
+        );
+        confirmation.signature = Some(&signature);
+        assert!(DeviceKeyVerifier::verify_signature(&device_key, &confirmation, 300, now).is_ok());
+
+        // Too old, or signed for another message
+        assert_eq!(
+            DeviceKeyVerifier::verify_signature(&device_key, &confirmation, 300, now + 600),
+            Err("signature expired")
+        );
+        confirmation.message_id = "msg-2";
+        assert_eq!(
+            DeviceKeyVerifier::verify_signature(&device_key, &confirmation, 300, now),
+            Err("bad signature")
+        );
+    }
+}
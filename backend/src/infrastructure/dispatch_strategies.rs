@@ -0,0 +1,360 @@
+use std::sync::Arc;
+
+use crate::config::DispatchStrategyKind;
+use crate::domain::entities::{Job, Message, Provider};
+use crate::domain::services::DispatchStrategy;
+use crate::infrastructure::provider_selection::{SelectionWeights, WeightedProviderSelection};
+use crate::shared::types::{canonical_province, province_for_number, Carrier};
+
+/// The strategy configured for this instance. Run a canary with a different
+/// one to compare them on the cohort outcome metrics.
+pub fn dispatch_strategy(kind: DispatchStrategyKind) -> Arc<dyn DispatchStrategy> {
+    match kind {
+        DispatchStrategyKind::SameCarrierFirst => Arc::new(SameCarrierFirst::default()),
+        DispatchStrategyKind::GeoAware => Arc::new(GeoAware::default()),
+        DispatchStrategyKind::ReputationWeighted => Arc::new(ReputationWeighted::default()),
+        DispatchStrategyKind::RoundRobin => Arc::new(RoundRobin),
+    }
+}
+
+/// Narrow `candidates` for a retry of `job`: providers that already failed
+/// it are skipped, and until the final attempt an untried provider on the
+/// recipient's carrier is kept to if there is one. The final attempt is
+/// open to every carrier, whatever the strategy prefers.
+pub fn retry_candidates(
+    candidates: Vec<Provider>,
+    job: &Job,
+    recipient_carrier: &Carrier,
+    final_attempt: bool,
+) -> Vec<Provider> {
+    if job.attempted_provider_ids.is_empty() {
+        return candidates;
+    }
+    let untried: Vec<Provider> = candidates
+        .into_iter()
+        .filter(|provider| !job.attempted_provider_ids.contains(&provider.id))
+        .collect();
+    if final_attempt
+        || !untried
+            .iter()
+            .any(|provider| &provider.carrier == recipient_carrier)
+    {
+        return untried;
+    }
+    untried
+        .into_iter()
+        .filter(|provider| &provider.carrier == recipient_carrier)
+        .collect()
+}
+
+/// Providers on the recipient's carrier deliver more reliably, so one of
+/// them is used if any is eligible; the best-scored either way
+#[derive(Debug, Clone, Default)]
+pub struct SameCarrierFirst {
+    scoring: WeightedProviderSelection,
+}
+
+impl SameCarrierFirst {
+    fn pick<'a, I>(&self, candidates: I, message: &Message) -> Option<&'a Provider>
+    where
+        I: Iterator<Item = &'a Provider> + Clone,
+    {
+        self.scoring
+            .best(
+                candidates
+                    .clone()
+                    .filter(|provider| provider.carrier == message.recipient_carrier),
+            )
+            .or_else(|| self.scoring.best(candidates))
+    }
+}
+
+impl DispatchStrategy for SameCarrierFirst {
+    fn name(&self) -> &'static str {
+        "same_carrier_first"
+    }
+
+    fn select<'a>(&self, candidates: &'a [Provider], message: &Message) -> Option<&'a Provider> {
+        self.pick(candidates.iter(), message)
+    }
+}
+
+/// Prefers providers in the recipient's province: the message's region if
+/// the client gave one, else the province of a landline-style area code.
+/// Within and outside the province, same-carrier providers come first.
+#[derive(Debug, Clone, Default)]
+pub struct GeoAware {
+    carrier_first: SameCarrierFirst,
+}
+
+impl GeoAware {
+    fn recipient_province(message: &Message) -> Option<&'static str> {
+        message
+            .metadata
+            .region
+            .as_deref()
+            .and_then(canonical_province)
+            .or_else(|| province_for_number(message.recipient.as_str()))
+    }
+
+    fn provider_province(provider: &Provider) -> Option<&'static str> {
+        provider
+            .location
+            .as_ref()
+            .and_then(|location| location.province.as_deref())
+            .and_then(canonical_province)
+    }
+}
+
+impl DispatchStrategy for GeoAware {
+    fn name(&self) -> &'static str {
+        "geo_aware"
+    }
+
+    fn select<'a>(&self, candidates: &'a [Provider], message: &Message) -> Option<&'a Provider> {
+        if let Some(province) = Self::recipient_province(message) {
+            let nearby = candidates
+                .iter()
+                .filter(move |provider| Self::provider_province(provider) == Some(province));
+            if let Some(provider) = self.carrier_first.pick(nearby, message) {
+                return Some(provider);
+            }
+        }
+        self.carrier_first.pick(candidates.iter(), message)
+    }
+}
+
+/// Best-scored provider of any carrier, with reputation counting for more
+/// than in the default weights
+#[derive(Debug, Clone)]
+pub struct ReputationWeighted {
+    scoring: WeightedProviderSelection,
+}
+
+impl Default for ReputationWeighted {
+    fn default() -> Self {
+        Self {
+            scoring: WeightedProviderSelection::new(SelectionWeights {
+                reputation: 0.6,
+                load: 0.15,
+                remaining_quota: 0.15,
+                assignment_recency: 0.1,
+                stake_priority: 0.2,
+            }),
+        }
+    }
+}
+
+impl DispatchStrategy for ReputationWeighted {
+    fn name(&self) -> &'static str {
+        "reputation_weighted"
+    }
+
+    fn select<'a>(&self, candidates: &'a [Provider], _message: &Message) -> Option<&'a Provider> {
+        self.scoring.best(candidates)
+    }
+}
+
+/// Spreads jobs evenly: the provider of any carrier that has gone longest
+/// without an assignment, never-assigned first
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RoundRobin;
+
+impl DispatchStrategy for RoundRobin {
+    fn name(&self) -> &'static str {
+        "round_robin"
+    }
+
+    fn select<'a>(&self, candidates: &'a [Provider], _message: &Message) -> Option<&'a Provider> {
+        candidates
+            .iter()
+            .min_by_key(|provider| provider.last_assigned_at)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entities::{Location, MessagePriority};
+    use crate::shared::types::{Carrier, PhoneNumber};
+
+    fn provider(id: &str, carrier: Carrier, reputation: f64, province: Option<&str>) -> Provider {
+        let mut provider = Provider::new(
+            "user".to_string(),
+            PhoneNumber::new("+85512345678".to_string()).unwrap(),
+            carrier,
+        );
+        provider.id = id.to_string();
+        provider.reputation_score = reputation;
+        provider.location = province.map(|province| Location {
+            latitude: 0.0,
+            longitude: 0.0,
+            city: None,
+            province: Some(province.to_string()),
+        });
+        provider
+    }
+
+    /// A Cellcard recipient (012 prefix)
+    fn message(region: Option<&str>) -> Message {
+        let mut message = Message::new(
+            "client".to_string(),
+            "Hello".to_string(),
+            PhoneNumber::new("+85512000000".to_string()).unwrap(),
+            MessagePriority::Normal,
+            None,
+            None,
+        );
+        message.metadata.region = region.map(str::to_string);
+        message
+    }
+
+    #[test]
+    fn test_same_carrier_first_prefers_the_recipients_carrier() {
+        let candidates = vec![
+            provider("smart", Carrier::Smart, 95.0, None),
+            provider("cellcard", Carrier::Cellcard, 60.0, None),
+        ];
+        let strategy = SameCarrierFirst::default();
+        assert_eq!(
+            strategy.select(&candidates, &message(None)).unwrap().id,
+            "cellcard"
+        );
+
+        // Falls back to any carrier
+        assert_eq!(
+            strategy
+                .select(&candidates[..1], &message(None))
+                .unwrap()
+                .id,
+            "smart"
+        );
+    }
+
+    #[test]
+    fn test_geo_aware_prefers_the_recipients_province() {
+        let candidates = vec![
+            provider("capital", Carrier::Cellcard, 90.0, Some("Phnom Penh")),
+            provider("siem-reap", Carrier::Smart, 60.0, Some("Siem Reap")),
+        ];
+        let strategy = GeoAware::default();
+        assert_eq!(
+            strategy
+                .select(&candidates, &message(Some("siem reap")))
+                .unwrap()
+                .id,
+            "siem-reap"
+        );
+
+        // No province known, or nobody there: same carrier first
+        assert_eq!(
+            strategy.select(&candidates, &message(None)).unwrap().id,
+            "capital"
+        );
+        assert_eq!(
+            strategy
+                .select(&candidates, &message(Some("Kampot")))
+                .unwrap()
+                .id,
+            "capital"
+        );
+    }
+
+    #[test]
+    fn test_reputation_weighted_ignores_carrier() {
+        let candidates = vec![
+            provider("cellcard", Carrier::Cellcard, 50.0, None),
+            provider("smart", Carrier::Smart, 95.0, None),
+        ];
+        let strategy = ReputationWeighted::default();
+        assert_eq!(
+            strategy.select(&candidates, &message(None)).unwrap().id,
+            "smart"
+        );
+    }
+
+    #[test]
+    fn test_round_robin_picks_the_longest_idle() {
+        let now = crate::shared::utils::now();
+        let mut recent = provider("recent", Carrier::Cellcard, 99.0, None);
+        recent.last_assigned_at = Some(now - chrono::Duration::minutes(1));
+        let mut idle = provider("idle", Carrier::Smart, 10.0, None);
+        idle.last_assigned_at = Some(now - chrono::Duration::hours(3));
+        let candidates = vec![recent, idle];
+
+        assert_eq!(
+            RoundRobin.select(&candidates, &message(None)).unwrap().id,
+            "idle"
+        );
+
+        let never = provider("never", Carrier::Metfone, 10.0, None);
+        let candidates = [candidates, vec![never]].concat();
+        assert_eq!(
+            RoundRobin.select(&candidates, &message(None)).unwrap().id,
+            "never"
+        );
+        assert!(RoundRobin.select(&[], &message(None)).is_none());
+    }
+
+    #[test]
+    fn test_retries_skip_providers_that_failed_the_job() {
+        let pool = || {
+            vec![
+                provider("cellcard-a", Carrier::Cellcard, 90.0, None),
+                provider("cellcard-b", Carrier::Cellcard, 60.0, None),
+                provider("smart", Carrier::Smart, 95.0, None),
+            ]
+        };
+        let ids = |providers: Vec<Provider>| -> Vec<String> {
+            providers.into_iter().map(|provider| provider.id).collect()
+        };
+        let mut job = Job::new("message".to_string(), "cellcard-a".to_string());
+
+        // First attempt: everyone
+        assert_eq!(
+            retry_candidates(pool(), &job, &Carrier::Cellcard, false).len(),
+            3
+        );
+
+        // Failed on cellcard-a: the other Cellcard provider, not a Smart one
+        job.mark_failed("Provider reported failure".to_string());
+        assert_eq!(
+            ids(retry_candidates(pool(), &job, &Carrier::Cellcard, false)),
+            vec!["cellcard-b"]
+        );
+
+        // The final attempt may go to another carrier
+        assert_eq!(
+            ids(retry_candidates(pool(), &job, &Carrier::Cellcard, true)),
+            vec!["cellcard-b", "smart"]
+        );
+
+        // No untried Cellcard provider left: another carrier rather than none
+        job.increment_retry();
+        job.provider_id = "cellcard-b".to_string();
+        job.mark_timeout();
+        assert_eq!(
+            ids(retry_candidates(pool(), &job, &Carrier::Cellcard, false)),
+            vec!["smart"]
+        );
+
+        // An admin requeue starts over
+        job.reset_for_requeue();
+        assert_eq!(
+            retry_candidates(pool(), &job, &Carrier::Cellcard, false).len(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_configured_strategy() {
+        assert_eq!(
+            dispatch_strategy(DispatchStrategyKind::GeoAware).name(),
+            "geo_aware"
+        );
+        assert_eq!(
+            dispatch_strategy(DispatchStrategyKind::SameCarrierFirst).name(),
+            "same_carrier_first"
+        );
+    }
+}
@@ -0,0 +1,326 @@
+use futures::stream::TryStreamExt;
+use mongodb::bson::{doc, Document};
+use mongodb::options::FindOptions;
+use mongodb::{Collection, Database};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::domain::entities::{
+    DeliveryDispute, Dispute, DisputeStatus, EarningsEvent, LedgerAccountKind, LedgerEntryKind,
+    LedgerTransaction, LedgerTransactionKind, Message,
+};
+use crate::infrastructure::ledger::Ledger;
+use crate::shared::types::MessageStatus;
+use crate::shared::utils::stored_timestamp;
+use crate::shared::{Money, PeerPowerError, Result};
+
+/// How long after delivery a client can dispute it
+const DISPUTE_WINDOW_DAYS: i64 = 7;
+
+/// Client disputes of delivered messages and their resolution.
+///
+/// Opening a dispute flags the delivery, which counts against the
+/// provider's quality bonus until an admin rules on it. Upholding it posts
+/// a reversal of the delivery's ledger transaction: the client gets the
+/// charge back and the provider's earnings are clawed back, leaving a
+/// negative balance for later earnings to cover if they were already
+/// withdrawn. Rejecting it clears the flag.
+pub struct DisputeService {
+    disputes: Collection<Dispute>,
+    messages: Collection<Message>,
+    events: Collection<EarningsEvent>,
+    ledger: Arc<Ledger>,
+}
+
+impl DisputeService {
+    pub fn new(database: Arc<Database>, ledger: Arc<Ledger>) -> Self {
+        Self {
+            disputes: database.collection("disputes"),
+            messages: database.collection("messages"),
+            events: database.collection("earnings_events"),
+            ledger,
+        }
+    }
+
+    /// Dispute one of the client's delivered messages
+    pub async fn open(&self, client_id: &str, message_id: &str, reason: &str) -> Result<Dispute> {
+        let message = self
+            .messages
+            .find_one(doc! {"id": message_id, "client_id": client_id}, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch message: {}", e),
+            })?
+            .ok_or_else(|| PeerPowerError::NotFound {
+                resource: format!("Message with ID: {}", message_id),
+            })?;
+
+        let invalid = |message: &str| PeerPowerError::ValidationError {
+            field: "message".to_string(),
+            message: message.to_string(),
+        };
+        if message.status != MessageStatus::Delivered {
+            return Err(invalid("Only delivered messages can be disputed"));
+        }
+        if message.dispute.is_some() {
+            return Err(invalid("This delivery has already been disputed"));
+        }
+        let now = crate::shared::utils::now();
+        if now - message.updated_at > chrono::Duration::days(DISPUTE_WINDOW_DAYS) {
+            return Err(invalid(&format!(
+                "Deliveries can only be disputed within {} days",
+                DISPUTE_WINDOW_DAYS
+            )));
+        }
+
+        let dispute = Dispute::new(
+            message.id.clone(),
+            client_id.to_string(),
+            message.provider_id.clone(),
+            reason.trim().to_string(),
+        );
+        if let Err(e) = self.disputes.insert_one(&dispute, None).await {
+            if e.to_string().contains("duplicate key") {
+                return Err(invalid("This delivery has already been disputed"));
+            }
+            return Err(PeerPowerError::Database {
+                message: format!("Failed to record dispute: {}", e),
+            });
+        }
+
+        let summary = DeliveryDispute {
+            reason: dispute.reason.clone(),
+            disputed_at: dispute.created_at,
+            fraud_confirmed_at: None,
+            fraud_confirmed_by: None,
+        };
+        let summary_doc =
+            mongodb::bson::to_bson(&summary).map_err(|e| PeerPowerError::Internal {
+                message: format!("Failed to serialize dispute: {}", e),
+            })?;
+        self.messages
+            .update_one(
+                doc! {"id": message_id, "dispute": null},
+                doc! {"$set": {"dispute": summary_doc}},
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to record dispute: {}", e),
+            })?;
+        self.flag_event(message_id, true).await?;
+
+        metrics::counter!("delivery_disputes_total").increment(1);
+        Ok(dispute)
+    }
+
+    pub async fn get(&self, dispute_id: &str) -> Result<Dispute> {
+        self.disputes
+            .find_one(doc! {"id": dispute_id}, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch dispute: {}", e),
+            })?
+            .ok_or_else(|| PeerPowerError::NotFound {
+                resource: format!("Dispute with ID: {}", dispute_id),
+            })
+    }
+
+    /// The client's disputes, newest first
+    pub async fn for_client(&self, client_id: &str, limit: i64) -> Result<Vec<Dispute>> {
+        self.find(
+            doc! {"client_id": client_id},
+            doc! {"created_at": -1},
+            limit,
+        )
+        .await
+    }
+
+    /// Disputes in a status, oldest first, for the admin review queue
+    pub async fn by_status(&self, status: DisputeStatus, limit: i64) -> Result<Vec<Dispute>> {
+        self.find(
+            doc! {"status": format!("{:?}", status)},
+            doc! {"created_at": 1},
+            limit,
+        )
+        .await
+    }
+
+    async fn find(&self, filter: Document, sort: Document, limit: i64) -> Result<Vec<Dispute>> {
+        let find_options = FindOptions::builder()
+            .sort(sort)
+            .limit(limit.clamp(1, 500))
+            .build();
+        self.disputes
+            .find(filter, find_options)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch disputes: {}", e),
+            })?
+            .try_collect()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read disputes: {}", e),
+            })
+    }
+
+    /// Rule on an open dispute. Upholding it reverses the delivery in the
+    /// ledger before the ruling is recorded, so a failure part way through
+    /// leaves the dispute open to be resolved again.
+    pub async fn resolve(
+        &self,
+        dispute_id: &str,
+        uphold: bool,
+        note: &str,
+        resolved_by: &str,
+    ) -> Result<Dispute> {
+        let mut dispute = self.get(dispute_id).await?;
+        if dispute.status != DisputeStatus::Open {
+            return Err(PeerPowerError::ValidationError {
+                field: "dispute_id".to_string(),
+                message: format!(
+                    "Dispute was already {}",
+                    format!("{:?}", dispute.status).to_lowercase()
+                ),
+            });
+        }
+
+        if uphold {
+            match self
+                .ledger
+                .find(LedgerTransactionKind::MessageDelivery, &dispute.message_id)
+                .await?
+            {
+                Some(delivery) => {
+                    let reversal = LedgerTransaction::dispute_reversal(&delivery);
+                    let total = |account_kind, entry| -> Money {
+                        reversal
+                            .postings
+                            .iter()
+                            .filter(|posting| {
+                                posting.account_kind == account_kind && posting.entry == entry
+                            })
+                            .map(|posting| posting.amount)
+                            .sum()
+                    };
+                    dispute.refund_amount =
+                        total(LedgerAccountKind::Client, LedgerEntryKind::ClientCharge);
+                    dispute.clawback_amount = -total(
+                        LedgerAccountKind::Provider,
+                        LedgerEntryKind::ProviderEarning,
+                    );
+                    self.ledger.post(&reversal).await?;
+                }
+                // Nothing was charged, e.g. platform OTPs; only the ruling is recorded
+                None => warn!(
+                    "No delivery transaction to reverse for disputed message {}",
+                    dispute.message_id
+                ),
+            }
+            self.events
+                .update_one(
+                    doc! {"message_id": &dispute.message_id},
+                    doc! {"$set": {"clawed_back": true}},
+                    None,
+                )
+                .await
+                .map_err(|e| PeerPowerError::Database {
+                    message: format!("Failed to claw back earnings event: {}", e),
+                })?;
+        } else {
+            self.flag_event(&dispute.message_id, false).await?;
+        }
+
+        let now = crate::shared::utils::now();
+        dispute.status = if uphold {
+            DisputeStatus::Upheld
+        } else {
+            DisputeStatus::Rejected
+        };
+        dispute.resolution_note = Some(note.to_string());
+        dispute.resolved_by = Some(resolved_by.to_string());
+        dispute.resolved_at = Some(now);
+        dispute.updated_at = now;
+        let result = self
+            .disputes
+            .update_one(
+                doc! {"id": dispute_id, "status": format!("{:?}", DisputeStatus::Open)},
+                resolution(&dispute),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to resolve dispute: {}", e),
+            })?;
+        if result.modified_count != 1 {
+            return Err(PeerPowerError::ValidationError {
+                field: "dispute_id".to_string(),
+                message: "Dispute was resolved concurrently".to_string(),
+            });
+        }
+
+        metrics::counter!("disputes_resolved_total", "status" => format!("{:?}", dispute.status))
+            .increment(1);
+        info!(
+            "Dispute {} on message {} {:?} by {}",
+            dispute.id, dispute.message_id, dispute.status, resolved_by
+        );
+        Ok(dispute)
+    }
+
+    /// Count (or stop counting) the delivery against the provider's quality bonus
+    async fn flag_event(&self, message_id: &str, disputed: bool) -> Result<()> {
+        self.events
+            .update_one(
+                doc! {"message_id": message_id},
+                doc! {"$set": {"disputed": disputed}},
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to flag earnings event: {}", e),
+            })?;
+        Ok(())
+    }
+}
+
+/// The update storing how `dispute` was resolved
+fn resolution(dispute: &Dispute) -> Document {
+    doc! {"$set": {
+        "status": format!("{:?}", dispute.status),
+        "refund_amount": dispute.refund_amount,
+        "clawback_amount": dispute.clawback_amount,
+        "resolution_note": &dispute.resolution_note,
+        "resolved_by": &dispute.resolved_by,
+        "resolved_at": dispute.resolved_at.map(stored_timestamp),
+        "updated_at": stored_timestamp(dispute.updated_at),
+    }}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::test_support::read_back;
+
+    #[test]
+    fn test_resolved_dispute_reads_back() {
+        let open = Dispute::new(
+            "message-1".to_string(),
+            "client-1".to_string(),
+            Some("provider-1".to_string()),
+            "Never arrived".to_string(),
+        );
+        let now = crate::shared::utils::now();
+        let mut resolved = open.clone();
+        resolved.status = DisputeStatus::Upheld;
+        resolved.resolution_note = Some("Carrier confirmed no delivery".to_string());
+        resolved.resolved_by = Some("admin-1".to_string());
+        resolved.resolved_at = Some(now);
+        resolved.updated_at = now;
+
+        let stored = read_back(&open, &resolution(&resolved));
+        assert_eq!(stored.status, DisputeStatus::Upheld);
+        assert_eq!(stored.resolved_by.as_deref(), Some("admin-1"));
+        assert_eq!(stored.resolved_at, Some(now));
+    }
+}
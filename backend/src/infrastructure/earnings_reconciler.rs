@@ -0,0 +1,406 @@
+use futures::stream::TryStreamExt;
+use mongodb::bson::{doc, Document};
+use mongodb::{Collection, Database};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::config::ReconciliationConfig;
+use crate::domain::entities::provider::SELF_TEST_CLIENT_ID;
+use crate::domain::entities::{
+    EarningsDiscrepancy, EarningsDiscrepancyKind, EarningsReconciliation, LedgerEntryKind,
+    LedgerTransactionKind, Provider,
+};
+use crate::infrastructure::ledger::{Ledger, PostingTotal};
+use crate::shared::{Money, PeerPowerError, Result};
+
+/// What the provider's own records and the delivery history say, next to
+/// what the ledger says
+#[derive(Debug, Clone, Copy, Default)]
+struct ProviderFigures {
+    delivered_messages: u64,
+    deliveries_counter: u64, // `total_messages_delivered`
+    earnings_total: Money,   // pre-ledger earnings on the provider
+    events: PostingTotal,    // earnings events: count and amount
+    ledger_deliveries: PostingTotal,
+    opening_balance: Option<Money>,
+}
+
+/// Checks delivered messages, earnings events and provider counters against
+/// the ledger, which is authoritative. With auto-correct on, counters that
+/// drifted within the tolerance are set to the ledger's figure; the ledger
+/// itself is never changed, so everything else is left to an admin.
+pub struct EarningsReconciler {
+    messages: Collection<Document>,
+    events: Collection<Document>,
+    providers: Collection<Provider>,
+    reports: Collection<EarningsReconciliation>,
+    ledger: Arc<Ledger>,
+    config: ReconciliationConfig,
+}
+
+impl EarningsReconciler {
+    pub fn new(database: Arc<Database>, ledger: Arc<Ledger>, config: ReconciliationConfig) -> Self {
+        Self {
+            messages: database.collection("messages"),
+            events: database.collection("earnings_events"),
+            providers: database.collection("providers"),
+            reports: database.collection("earnings_reconciliations"),
+            ledger,
+            config,
+        }
+    }
+
+    /// Reconcile every provider and store the report
+    pub async fn run(&self, triggered_by: Option<String>) -> Result<EarningsReconciliation> {
+        let mut report = EarningsReconciliation::new(triggered_by, self.config.auto_correct);
+
+        let delivered = self.delivered_messages().await?;
+        let events = self.earnings_events().await?;
+        let ledger_deliveries = self
+            .ledger
+            .provider_totals(
+                LedgerTransactionKind::MessageDelivery,
+                LedgerEntryKind::ProviderEarning,
+            )
+            .await?;
+        let opening_balances = self
+            .ledger
+            .provider_totals(
+                LedgerTransactionKind::OpeningBalance,
+                LedgerEntryKind::OpeningBalance,
+            )
+            .await?;
+
+        let mut cursor = self
+            .providers
+            .find(doc! {}, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch providers: {}", e),
+            })?;
+        while let Some(provider) = cursor
+            .try_next()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read providers: {}", e),
+            })?
+        {
+            report.providers_checked += 1;
+            let figures = ProviderFigures {
+                delivered_messages: delivered.get(&provider.id).copied().unwrap_or(0),
+                deliveries_counter: provider.total_messages_delivered,
+                earnings_total: Money::from_ppt(provider.earnings_total),
+                events: events.get(&provider.id).copied().unwrap_or_default(),
+                ledger_deliveries: ledger_deliveries
+                    .get(&provider.id)
+                    .copied()
+                    .unwrap_or_default(),
+                opening_balance: opening_balances.get(&provider.id).map(|total| total.amount),
+            };
+
+            for discrepancy in check_provider(&provider.id, &figures, &self.config) {
+                if discrepancy.corrected {
+                    self.correct(&provider.id, &discrepancy, &figures).await?;
+                    report.corrected_count += 1;
+                }
+                warn!(
+                    "Earnings discrepancy for provider {}{}: {}",
+                    provider.id,
+                    if discrepancy.corrected { " (corrected)" } else { "" },
+                    discrepancy.details
+                );
+                report.discrepancies.push(discrepancy);
+            }
+        }
+
+        self.reports
+            .insert_one(&report, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to store earnings reconciliation: {}", e),
+            })?;
+
+        metrics::gauge!("earnings_reconciliation_discrepancies")
+            .set(report.discrepancy_count() as f64);
+        info!(
+            "Reconciled earnings for {} providers: {} discrepancies, {} corrected",
+            report.providers_checked,
+            report.discrepancy_count(),
+            report.corrected_count
+        );
+        Ok(report)
+    }
+
+    /// Recent reconciliation reports, newest first
+    pub async fn recent(&self, limit: i64) -> Result<Vec<EarningsReconciliation>> {
+        self.reports
+            .find(
+                doc! {},
+                mongodb::options::FindOptions::builder()
+                    .sort(doc! {"created_at": -1})
+                    .limit(limit)
+                    .build(),
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch earnings reconciliations: {}", e),
+            })?
+            .try_collect()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read earnings reconciliations: {}", e),
+            })
+    }
+
+    /// Set the provider record to the ledger's figure
+    async fn correct(
+        &self,
+        provider_id: &str,
+        discrepancy: &EarningsDiscrepancy,
+        figures: &ProviderFigures,
+    ) -> Result<()> {
+        let set = match discrepancy.kind {
+            EarningsDiscrepancyKind::DeliveryCounter => doc! {
+                "total_messages_delivered": figures.ledger_deliveries.transactions as i64,
+            },
+            EarningsDiscrepancyKind::OpeningBalance => doc! {
+                "earnings_total": figures.opening_balance.unwrap_or_default().to_ppt(),
+            },
+            _ => return Ok(()),
+        };
+        self.providers
+            .update_one(doc! {"id": provider_id}, doc! {"$set": set}, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to correct provider earnings: {}", e),
+            })?;
+        Ok(())
+    }
+
+    /// Delivered messages per provider, leaving out self-tests (never charged)
+    async fn delivered_messages(&self) -> Result<HashMap<String, u64>> {
+        let pipeline = vec![
+            doc! {"$match": {
+                "status": "Delivered",
+                "provider_id": {"$ne": null},
+                "client_id": {"$ne": SELF_TEST_CLIENT_ID},
+            }},
+            doc! {"$group": {"_id": "$provider_id", "count": {"$sum": 1}}},
+        ];
+        Ok(self
+            .totals_by_provider(&self.messages, pipeline, "delivered messages")
+            .await?
+            .into_iter()
+            .map(|(provider_id, total)| (provider_id, total.transactions))
+            .collect())
+    }
+
+    /// Earnings events per provider, including clawed-back ones: an upheld
+    /// dispute posts a reversal but leaves the delivery posting in place
+    async fn earnings_events(&self) -> Result<HashMap<String, PostingTotal>> {
+        let pipeline = vec![doc! {
+            "$group": {
+                "_id": "$provider_id",
+                "count": {"$sum": 1},
+                "amount": {"$sum": Money::micros_expr("$amount")},
+            }
+        }];
+        self.totals_by_provider(&self.events, pipeline, "earnings events")
+            .await
+    }
+
+    async fn totals_by_provider(
+        &self,
+        collection: &Collection<Document>,
+        pipeline: Vec<Document>,
+        what: &str,
+    ) -> Result<HashMap<String, PostingTotal>> {
+        let mut cursor = collection
+            .aggregate(pipeline, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to aggregate {}: {}", what, e),
+            })?;
+
+        let mut totals = HashMap::new();
+        while let Some(doc) = cursor
+            .try_next()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read {}: {}", what, e),
+            })?
+        {
+            let Ok(provider_id) = doc.get_str("_id") else {
+                continue;
+            };
+            totals.insert(
+                provider_id.to_string(),
+                PostingTotal {
+                    transactions: doc
+                        .get_i64("count")
+                        .or_else(|_| doc.get_i32("count").map(i64::from))
+                        .unwrap_or(0)
+                        .max(0) as u64,
+                    amount: Money::from_field(&doc, "amount"),
+                },
+            );
+        }
+        Ok(totals)
+    }
+}
+
+/// Compare one provider's figures; discrepancies the config allows fixing on
+/// the provider record come back marked `corrected`
+fn check_provider(
+    provider_id: &str,
+    figures: &ProviderFigures,
+    config: &ReconciliationConfig,
+) -> Vec<EarningsDiscrepancy> {
+    let mut discrepancies = Vec::new();
+    let posted = figures.ledger_deliveries;
+
+    if figures.delivered_messages != posted.transactions {
+        discrepancies.push(EarningsDiscrepancy::counts(
+            provider_id,
+            EarningsDiscrepancyKind::UnpostedDeliveries,
+            posted.transactions,
+            figures.delivered_messages,
+            format!(
+                "{} delivered messages but {} deliveries posted to the ledger",
+                figures.delivered_messages, posted.transactions
+            ),
+        ));
+    }
+
+    if figures.deliveries_counter != posted.transactions {
+        let mut discrepancy = EarningsDiscrepancy::counts(
+            provider_id,
+            EarningsDiscrepancyKind::DeliveryCounter,
+            posted.transactions,
+            figures.deliveries_counter,
+            format!(
+                "Delivery counter is {} but {} deliveries are posted",
+                figures.deliveries_counter, posted.transactions
+            ),
+        );
+        discrepancy.corrected = config.auto_correct
+            && figures.deliveries_counter.abs_diff(posted.transactions) <= config.count_tolerance;
+        discrepancies.push(discrepancy);
+    }
+
+    if figures.events.transactions != posted.transactions || figures.events.amount != posted.amount
+    {
+        discrepancies.push(EarningsDiscrepancy::amounts(
+            provider_id,
+            EarningsDiscrepancyKind::EarningsEvents,
+            posted.amount.to_ppt(),
+            figures.events.amount.to_ppt(),
+            format!(
+                "{} earnings events for {} PPT, but {} deliveries for {} PPT posted",
+                figures.events.transactions,
+                figures.events.amount,
+                posted.transactions,
+                posted.amount
+            ),
+        ));
+    }
+
+    match figures.opening_balance {
+        Some(opening) if opening != figures.earnings_total => {
+            let mut discrepancy = EarningsDiscrepancy::amounts(
+                provider_id,
+                EarningsDiscrepancyKind::OpeningBalance,
+                opening.to_ppt(),
+                figures.earnings_total.to_ppt(),
+                format!(
+                    "Pre-ledger earnings are {} PPT but the opening balance posted was {} PPT",
+                    figures.earnings_total, opening
+                ),
+            );
+            discrepancy.corrected = config.auto_correct
+                && (opening - figures.earnings_total).abs()
+                    <= Money::from_ppt(config.amount_tolerance_ppt);
+            discrepancies.push(discrepancy);
+        }
+        None if figures.earnings_total.is_positive() => {
+            discrepancies.push(EarningsDiscrepancy::amounts(
+                provider_id,
+                EarningsDiscrepancyKind::OpeningBalance,
+                0.0,
+                figures.earnings_total.to_ppt(),
+                format!(
+                    "Pre-ledger earnings of {} PPT have no opening balance posted",
+                    figures.earnings_total
+                ),
+            ));
+        }
+        _ => {}
+    }
+
+    discrepancies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(auto_correct: bool) -> ReconciliationConfig {
+        ReconciliationConfig {
+            auto_correct,
+            count_tolerance: 2,
+            amount_tolerance_ppt: 0.05,
+        }
+    }
+
+    fn matching() -> ProviderFigures {
+        let deliveries = PostingTotal {
+            transactions: 10,
+            amount: Money::from_ppt(0.08),
+        };
+        ProviderFigures {
+            delivered_messages: 10,
+            deliveries_counter: 10,
+            earnings_total: Money::from_ppt(1.5),
+            events: deliveries,
+            ledger_deliveries: deliveries,
+            opening_balance: Some(Money::from_ppt(1.5)),
+        }
+    }
+
+    #[test]
+    fn test_matching_figures_have_no_discrepancies() {
+        assert!(check_provider("prov-1", &matching(), &config(true)).is_empty());
+    }
+
+    #[test]
+    fn test_only_counter_drift_within_tolerance_is_corrected() {
+        let mut figures = matching();
+        figures.deliveries_counter = 12;
+        let found = check_provider("prov-1", &figures, &config(true));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].kind, EarningsDiscrepancyKind::DeliveryCounter);
+        assert!(found[0].corrected);
+        assert!(!check_provider("prov-1", &figures, &config(false))[0].corrected);
+
+        figures.deliveries_counter = 13;
+        assert!(!check_provider("prov-1", &figures, &config(true))[0].corrected);
+    }
+
+    #[test]
+    fn test_unposted_deliveries_and_missing_opening_balance_are_only_reported() {
+        let mut figures = matching();
+        figures.delivered_messages = 11;
+        figures.opening_balance = None;
+        let found = check_provider("prov-1", &figures, &config(true));
+        let kinds: Vec<_> = found.iter().map(|d| d.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                EarningsDiscrepancyKind::UnpostedDeliveries,
+                EarningsDiscrepancyKind::OpeningBalance
+            ]
+        );
+        assert!(found.iter().all(|d| !d.corrected));
+    }
+}
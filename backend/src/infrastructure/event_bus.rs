@@ -0,0 +1,103 @@
+use futures::StreamExt;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::domain::entities::PlatformEvent;
+use crate::infrastructure::database::RedisConnection;
+use crate::shared::{PeerPowerError, Result};
+
+const EVENTS_CHANNEL: &str = "events";
+
+/// Wait before subscribing again after losing the subscription
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(5);
+
+/// Carries platform events between instances over Redis pub/sub.
+///
+/// An event published on any instance is received by every instance,
+/// including the publisher's own, and counted in its metrics wherever it
+/// happened. Delivery is at most once: an instance that isn't subscribed
+/// when an event is published (restarting, or cut off from Redis) misses it.
+/// Without Redis, events are still received on the instance that published
+/// them.
+pub struct EventBus {
+    redis: RedisConnection,
+    instance_id: String,
+}
+
+impl EventBus {
+    pub fn new(redis: RedisConnection, instance_id: String) -> Self {
+        Self { redis, instance_id }
+    }
+
+    /// Receive events from Redis on this instance
+    pub fn start(self: Arc<Self>) {
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = self.relay().await {
+                    warn!("Event bus subscription lost: {}", e);
+                }
+                tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+            }
+        });
+    }
+
+    /// Publish `event` to every instance. Best effort: events are
+    /// notifications, so a failure is logged rather than failing the change
+    /// that produced it.
+    pub async fn publish(&self, mut event: PlatformEvent) {
+        event.instance_id = self.instance_id.clone();
+        metrics::counter!("events_published_total", "event" => event.kind.name()).increment(1);
+
+        let payload = match serde_json::to_string(&event) {
+            Ok(payload) => payload,
+            Err(e) => {
+                warn!("Failed to serialize {} event: {}", event.kind.name(), e);
+                return;
+            }
+        };
+        if let Err(e) = self.redis.publish(EVENTS_CHANNEL, &payload).await {
+            warn!(
+                "Failed to publish {} event {}, delivering it locally only: {}",
+                event.kind.name(),
+                event.subject_id,
+                e
+            );
+            self.deliver(event);
+        }
+    }
+
+    async fn relay(&self) -> Result<()> {
+        let mut pubsub = self.redis.subscribe(EVENTS_CHANNEL).await?;
+        info!("Event bus subscribed to {}", EVENTS_CHANNEL);
+
+        let mut messages = pubsub.on_message();
+        while let Some(message) = messages.next().await {
+            let event = message
+                .get_payload::<String>()
+                .map_err(|e| e.to_string())
+                .and_then(|payload| {
+                    serde_json::from_str::<PlatformEvent>(&payload).map_err(|e| e.to_string())
+                });
+            match event {
+                Ok(event) => self.deliver(event),
+                Err(e) => warn!("Discarding malformed event: {}", e),
+            }
+        }
+
+        Err(PeerPowerError::ExternalService {
+            service: "Redis".to_string(),
+            message: "Event subscription closed".to_string(),
+        })
+    }
+
+    fn deliver(&self, event: PlatformEvent) {
+        let origin = if event.instance_id == self.instance_id {
+            "local"
+        } else {
+            "remote"
+        };
+        metrics::counter!("events_received_total", "event" => event.kind.name(), "origin" => origin)
+            .increment(1);
+    }
+}
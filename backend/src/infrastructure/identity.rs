@@ -0,0 +1,606 @@
+use base64::Engine;
+use futures::stream::TryStreamExt;
+use hmac::{Hmac, Mac};
+use k256::ecdsa::signature::{Signer, Verifier};
+use k256::ecdsa::{Signature, SigningKey, VerifyingKey};
+use mongodb::bson::{doc, Document};
+use mongodb::options::FindOptions;
+use mongodb::{Collection, Database};
+use serde::Serialize;
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::sync::Arc;
+use tracing::info;
+
+use crate::config::IdentityConfig;
+use crate::domain::entities::{CredentialKind, IssuedCredential, Provider, User};
+use crate::domain::repositories::UserRepository;
+use crate::shared::utils::stored_timestamp;
+use crate::shared::{PeerPowerError, Result};
+
+/// Multicodec prefix of a compressed secp256k1 public key (0xe7, varint-encoded)
+const SECP256K1_MULTICODEC: [u8; 2] = [0xe7, 0x01];
+const DID_KEY_PREFIX: &str = "did:key:z";
+const VC_CONTEXT: &str = "https://www.w3.org/2018/credentials/v1";
+
+/// Outcome of checking a credential presented to `POST /credentials/verify`
+#[derive(Debug, Clone, Serialize)]
+pub struct CredentialVerification {
+    pub valid: bool,
+    pub reason: Option<String>, // why it isn't valid
+    pub kind: Option<CredentialKind>,
+    pub issuer: Option<String>,
+    pub subject: Option<String>,
+    pub issued_at: Option<i64>,
+    pub expires_at: Option<i64>,
+}
+
+impl CredentialVerification {
+    fn invalid(reason: &str) -> Self {
+        Self {
+            valid: false,
+            reason: Some(reason.to_string()),
+            kind: None,
+            issuer: None,
+            subject: None,
+            issued_at: None,
+            expires_at: None,
+        }
+    }
+}
+
+/// DIDs for users and the verifiable credentials the platform signs for them.
+///
+/// The platform's issuer DID is the `did:key` of `DID_ISSUER_KEY`. Verified
+/// users get a `did:key` whose key is derived from the issuer key and their
+/// id, so no user key material is stored; users who hold their own
+/// secp256k1 `did:key` can register it instead by signing a proof with it.
+/// Credentials are ES256K-signed VC-JWTs: a verified phone at sign-in and a
+/// verified provider at provider registration. They are recorded so they
+/// can be listed, and revoked when the user's DID changes.
+pub struct IdentityService {
+    users: Arc<dyn UserRepository>,
+    credentials: Collection<IssuedCredential>,
+    providers: Collection<Provider>,
+    issuer_key: Option<SigningKey>,
+    config: IdentityConfig,
+}
+
+impl IdentityService {
+    pub fn new(
+        database: Arc<Database>,
+        users: Arc<dyn UserRepository>,
+        config: IdentityConfig,
+    ) -> Result<Self> {
+        let issuer_key = match &config.issuer_key {
+            Some(key) => {
+                let bytes = hex::decode(key.trim_start_matches("0x")).map_err(|_| {
+                    PeerPowerError::Configuration {
+                        message: "DID_ISSUER_KEY must be hex".to_string(),
+                    }
+                })?;
+                Some(
+                    SigningKey::from_slice(&bytes).map_err(|_| PeerPowerError::Configuration {
+                        message: "DID_ISSUER_KEY is not a valid secp256k1 key".to_string(),
+                    })?,
+                )
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            users,
+            credentials: database.collection("credentials"),
+            providers: database.collection("providers"),
+            issuer_key,
+            config,
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.issuer_key.is_some()
+    }
+
+    pub fn issuer_did(&self) -> Option<String> {
+        self.issuer_key
+            .as_ref()
+            .map(|key| did_key(key.verifying_key()))
+    }
+
+    fn issuer_key(&self) -> Result<&SigningKey> {
+        self.issuer_key
+            .as_ref()
+            .ok_or_else(|| PeerPowerError::Configuration {
+                message: "DID issuance is not configured".to_string(),
+            })
+    }
+
+    /// The platform-derived key behind a user's issued `did:key`
+    fn user_key(&self, user_id: &str) -> Result<SigningKey> {
+        let issuer = self.issuer_key()?;
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&issuer.to_bytes()).map_err(|e| {
+            PeerPowerError::Internal {
+                message: format!("Failed to derive user key: {}", e),
+            }
+        })?;
+        mac.update(format!("peerpower-did:{}", user_id).as_bytes());
+        SigningKey::from_slice(&mac.finalize().into_bytes()).map_err(|e| PeerPowerError::Internal {
+            message: format!("Failed to derive user key: {}", e),
+        })
+    }
+
+    /// Give a verified user a DID if they have none, and a verified-phone
+    /// credential for it. Does nothing while issuance isn't configured.
+    pub async fn issue_on_verification(&self, user_id: &str) -> Result<Option<IssuedCredential>> {
+        if !self.is_enabled() {
+            return Ok(None);
+        }
+        let Some(mut user) = self.users.find_by_id(user_id).await? else {
+            return Ok(None);
+        };
+        if !user.is_verified {
+            return Ok(None);
+        }
+        let did = self.ensure_did(&mut user).await?;
+        self.ensure_credential(
+            &user.id,
+            &did,
+            CredentialKind::VerifiedPhone,
+            json!({
+                "phoneVerified": true,
+            }),
+        )
+        .await
+        .map(Some)
+    }
+
+    /// Issue a verified-provider credential to the provider's owner
+    pub async fn issue_provider_credential(
+        &self,
+        provider: &Provider,
+    ) -> Result<Option<IssuedCredential>> {
+        if !self.is_enabled() {
+            return Ok(None);
+        }
+        let Some(mut user) = self.users.find_by_id(&provider.user_id).await? else {
+            return Ok(None);
+        };
+        let did = self.ensure_did(&mut user).await?;
+        self.ensure_credential(
+            &user.id,
+            &did,
+            CredentialKind::VerifiedProvider,
+            provider_claims(provider),
+        )
+        .await
+        .map(Some)
+    }
+
+    async fn ensure_did(&self, user: &mut User) -> Result<String> {
+        if let Some(did) = &user.did {
+            return Ok(did.clone());
+        }
+        let did = did_key(self.user_key(&user.id)?.verifying_key());
+        user.did = Some(did.clone());
+        user.updated_at = crate::shared::utils::now();
+        self.users.update(user).await?;
+        info!("Issued DID {} to user {}", did, user.id);
+        Ok(did)
+    }
+
+    /// The subject's active credential of this kind, issuing one if needed
+    async fn ensure_credential(
+        &self,
+        user_id: &str,
+        subject_did: &str,
+        kind: CredentialKind,
+        claims: Value,
+    ) -> Result<IssuedCredential> {
+        let now = stored_timestamp(crate::shared::utils::now());
+        let existing = self
+            .credentials
+            .find_one(
+                doc! {
+                    "subject_did": subject_did,
+                    "kind": mongodb::bson::to_bson(&kind).unwrap_or_default(),
+                    "revoked_at": null,
+                    "expires_at": {"$gt": &now},
+                },
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch credentials: {}", e),
+            })?;
+        if let Some(credential) = existing {
+            return Ok(credential);
+        }
+
+        let credential = self.sign_credential(user_id, subject_did, kind, claims)?;
+        self.credentials
+            .insert_one(&credential, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to store credential: {}", e),
+            })?;
+        metrics::counter!("credentials_issued_total", "kind" => kind.credential_type())
+            .increment(1);
+        Ok(credential)
+    }
+
+    fn sign_credential(
+        &self,
+        user_id: &str,
+        subject_did: &str,
+        kind: CredentialKind,
+        claims: Value,
+    ) -> Result<IssuedCredential> {
+        let key = self.issuer_key()?;
+        let issuer = did_key(key.verifying_key());
+        let id = crate::shared::utils::generate_id();
+        let issued_at = crate::shared::utils::now();
+        let expires_at = issued_at + chrono::Duration::days(self.config.credential_ttl_days.max(1));
+
+        let mut subject = claims;
+        subject["id"] = json!(subject_did);
+        let payload = json!({
+            "iss": issuer,
+            "sub": subject_did,
+            "jti": format!("urn:uuid:{}", id),
+            "iat": issued_at.timestamp(),
+            "nbf": issued_at.timestamp(),
+            "exp": expires_at.timestamp(),
+            "vc": {
+                "@context": [VC_CONTEXT],
+                "type": ["VerifiableCredential", kind.credential_type()],
+                "credentialSubject": subject,
+            },
+        });
+        let header = json!({
+            "alg": "ES256K",
+            "typ": "JWT",
+            "kid": verification_method_id(&issuer),
+        });
+
+        Ok(IssuedCredential {
+            id,
+            user_id: user_id.to_string(),
+            subject_did: subject_did.to_string(),
+            kind,
+            jwt: sign_jws(key, &header, &payload),
+            issued_at,
+            expires_at,
+            revoked_at: None,
+        })
+    }
+
+    /// Replace the user's DID with one they control. `proof` is an ES256K JWS
+    /// signed by the DID's key over `{"sub": <user id>, "iat": <unix seconds>}`.
+    /// Credentials issued to the old DID are revoked and reissued.
+    pub async fn register_did(&self, user_id: &str, did: &str, proof: &str) -> Result<User> {
+        self.issuer_key()?;
+        let invalid = |message: &str| PeerPowerError::ValidationError {
+            field: "proof".to_string(),
+            message: message.to_string(),
+        };
+
+        let key = resolve_did_key(did)?;
+        let (_, payload) =
+            verify_jws(proof, &key).map_err(|_| invalid("Proof signature is invalid"))?;
+        if payload.get("sub").and_then(Value::as_str) != Some(user_id) {
+            return Err(invalid("Proof must be issued for your user id (sub)"));
+        }
+        let signed_at = payload.get("iat").and_then(Value::as_i64).unwrap_or(0);
+        let age = crate::shared::utils::now().timestamp() - signed_at;
+        if age.abs() > self.config.did_proof_max_age_seconds {
+            return Err(invalid("Proof has expired; sign a fresh one"));
+        }
+
+        if let Some(owner) = self.users.find_by_did(did).await? {
+            if owner.id != user_id {
+                return Err(PeerPowerError::ValidationError {
+                    field: "did".to_string(),
+                    message: "This DID is registered to another account".to_string(),
+                });
+            }
+        }
+
+        let mut user =
+            self.users
+                .find_by_id(user_id)
+                .await?
+                .ok_or_else(|| PeerPowerError::NotFound {
+                    resource: format!("User with ID: {}", user_id),
+                })?;
+        if user.did.as_deref() == Some(did) {
+            return Ok(user);
+        }
+
+        let now = crate::shared::utils::now();
+        self.credentials
+            .update_many(
+                doc! {"user_id": user_id, "revoked_at": null},
+                revocation(now),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to revoke credentials: {}", e),
+            })?;
+
+        user.did = Some(did.to_string());
+        user.updated_at = now;
+        self.users.update(&user).await?;
+
+        if user.is_verified {
+            self.ensure_credential(
+                user_id,
+                did,
+                CredentialKind::VerifiedPhone,
+                json!({
+                    "phoneVerified": true,
+                }),
+            )
+            .await?;
+        }
+        let provider = self
+            .providers
+            .find_one(doc! {"user_id": user_id}, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch provider: {}", e),
+            })?;
+        if let Some(provider) = provider {
+            self.ensure_credential(
+                user_id,
+                did,
+                CredentialKind::VerifiedProvider,
+                provider_claims(&provider),
+            )
+            .await?;
+        }
+        Ok(user)
+    }
+
+    /// Credentials issued to the user, newest first
+    pub async fn list(&self, user_id: &str) -> Result<Vec<IssuedCredential>> {
+        let find_options = FindOptions::builder()
+            .sort(doc! {"issued_at": -1})
+            .limit(50)
+            .build();
+        self.credentials
+            .find(doc! {"user_id": user_id}, find_options)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch credentials: {}", e),
+            })?
+            .try_collect()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read credentials: {}", e),
+            })
+    }
+
+    /// Check a credential's signature, validity period and revocation
+    pub async fn verify(&self, jwt: &str) -> Result<CredentialVerification> {
+        let issuer_did = self
+            .issuer_did()
+            .ok_or_else(|| PeerPowerError::Configuration {
+                message: "DID issuance is not configured".to_string(),
+            })?;
+        let Ok((_, payload)) = verify_jws(jwt, self.issuer_key()?.verifying_key()) else {
+            return Ok(CredentialVerification::invalid(
+                "Not a credential signed by this issuer",
+            ));
+        };
+
+        let kind = payload
+            .pointer("/vc/type")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .find_map(CredentialKind::from_credential_type);
+        let mut verification = CredentialVerification {
+            valid: true,
+            reason: None,
+            kind,
+            issuer: Some(issuer_did),
+            subject: payload
+                .get("sub")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            issued_at: payload.get("iat").and_then(Value::as_i64),
+            expires_at: payload.get("exp").and_then(Value::as_i64),
+        };
+
+        let now = crate::shared::utils::now().timestamp();
+        let id = payload
+            .get("jti")
+            .and_then(Value::as_str)
+            .and_then(|jti| jti.strip_prefix("urn:uuid:"))
+            .unwrap_or_default();
+        let record = self
+            .credentials
+            .find_one(doc! {"id": id}, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch credential: {}", e),
+            })?;
+
+        let reason = if verification.expires_at.is_none_or(|exp| exp <= now) {
+            Some("Credential has expired")
+        } else if record.is_none() {
+            Some("Credential is unknown to the issuer")
+        } else if record.is_some_and(|record| record.revoked_at.is_some()) {
+            Some("Credential has been revoked")
+        } else {
+            None
+        };
+        if let Some(reason) = reason {
+            verification.valid = false;
+            verification.reason = Some(reason.to_string());
+        }
+        Ok(verification)
+    }
+}
+
+fn provider_claims(provider: &Provider) -> Value {
+    json!({
+        "providerId": provider.id,
+        "carrier": format!("{:?}", provider.carrier),
+        "deviceAttested": provider.attestation.as_ref().is_some_and(|a| a.passed),
+    })
+}
+
+/// `did:key` of a secp256k1 public key
+pub fn did_key(key: &VerifyingKey) -> String {
+    let mut bytes = SECP256K1_MULTICODEC.to_vec();
+    bytes.extend_from_slice(key.to_encoded_point(true).as_bytes());
+    format!("{}{}", DID_KEY_PREFIX, bs58::encode(bytes).into_string())
+}
+
+/// The public key a secp256k1 `did:key` stands for
+pub fn resolve_did_key(did: &str) -> Result<VerifyingKey> {
+    let invalid = || PeerPowerError::ValidationError {
+        field: "did".to_string(),
+        message: "Only secp256k1 did:key DIDs are supported".to_string(),
+    };
+    let encoded = did.strip_prefix(DID_KEY_PREFIX).ok_or_else(invalid)?;
+    let bytes = bs58::decode(encoded).into_vec().map_err(|_| invalid())?;
+    let key = bytes
+        .strip_prefix(&SECP256K1_MULTICODEC)
+        .ok_or_else(invalid)?;
+    VerifyingKey::from_sec1_bytes(key).map_err(|_| invalid())
+}
+
+fn verification_method_id(did: &str) -> String {
+    format!("{}#{}", did, did.trim_start_matches("did:key:"))
+}
+
+/// DID document for a `did:key`, derived from the key alone
+pub fn did_document(did: &str) -> Result<Value> {
+    resolve_did_key(did)?;
+    let method_id = verification_method_id(did);
+    Ok(json!({
+        "@context": [
+            "https://www.w3.org/ns/did/v1",
+            "https://w3id.org/security/multikey/v1",
+        ],
+        "id": did,
+        "verificationMethod": [{
+            "id": method_id,
+            "type": "Multikey",
+            "controller": did,
+            "publicKeyMultibase": did.trim_start_matches("did:key:"),
+        }],
+        "authentication": [method_id],
+        "assertionMethod": [method_id],
+    }))
+}
+
+fn base64url(bytes: &[u8]) -> String {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// Compact ES256K JWS (SHA-256, 64-byte r || s signature)
+fn sign_jws(key: &SigningKey, header: &Value, payload: &Value) -> String {
+    let signing_input = format!(
+        "{}.{}",
+        base64url(header.to_string().as_bytes()),
+        base64url(payload.to_string().as_bytes())
+    );
+    let signature: Signature = key.sign(signing_input.as_bytes());
+    format!("{}.{}", signing_input, base64url(&signature.to_bytes()))
+}
+
+/// Header and payload of a compact ES256K JWS, if signed by `key`
+fn verify_jws(token: &str, key: &VerifyingKey) -> Result<(Value, Value)> {
+    let invalid = || PeerPowerError::ValidationError {
+        field: "credential".to_string(),
+        message: "Invalid JWS".to_string(),
+    };
+    let decode = |part: &str| {
+        base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(part)
+            .map_err(|_| invalid())
+    };
+    let mut parts = token.trim().split('.');
+    let (Some(header), Some(payload), Some(signature), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(invalid());
+    };
+
+    let header_json: Value = serde_json::from_slice(&decode(header)?).map_err(|_| invalid())?;
+    if header_json.get("alg").and_then(Value::as_str) != Some("ES256K") {
+        return Err(invalid());
+    }
+    let signature = Signature::from_slice(&decode(signature)?).map_err(|_| invalid())?;
+    key.verify(format!("{}.{}", header, payload).as_bytes(), &signature)
+        .map_err(|_| invalid())?;
+
+    let payload_json: Value = serde_json::from_slice(&decode(payload)?).map_err(|_| invalid())?;
+    Ok((header_json, payload_json))
+}
+
+fn revocation(at: chrono::DateTime<chrono::Utc>) -> Document {
+    doc! {"$set": {"revoked_at": stored_timestamp(at)}}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::test_support::read_back;
+
+    #[test]
+    fn test_did_key_round_trip_and_document() {
+        let key = SigningKey::from_slice(&[0x46; 32]).unwrap();
+        let did = did_key(key.verifying_key());
+        assert!(did.starts_with("did:key:zQ3s"));
+        assert_eq!(&resolve_did_key(&did).unwrap(), key.verifying_key());
+
+        let document = did_document(&did).unwrap();
+        assert_eq!(document["id"], did.as_str());
+        assert!(resolve_did_key("did:web:example.com").is_err());
+        assert!(
+            resolve_did_key("did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK").is_err()
+        );
+    }
+
+    #[test]
+    fn test_jws_signed_by_key_only() {
+        let key = SigningKey::from_slice(&[0x46; 32]).unwrap();
+        let other = SigningKey::from_slice(&[0x47; 32]).unwrap();
+        let token = sign_jws(&key, &json!({"alg": "ES256K"}), &json!({"sub": "user-1"}));
+
+        let (_, payload) = verify_jws(&token, key.verifying_key()).unwrap();
+        assert_eq!(payload["sub"], "user-1");
+        assert!(verify_jws(&token, other.verifying_key()).is_err());
+
+        let tampered = token.replacen(
+            &base64url(json!({"sub": "user-1"}).to_string().as_bytes()),
+            &base64url(json!({"sub": "user-2"}).to_string().as_bytes()),
+            1,
+        );
+        assert!(verify_jws(&tampered, key.verifying_key()).is_err());
+    }
+
+    #[test]
+    fn test_revoked_credential_reads_back() {
+        let issued_at = crate::shared::utils::now();
+        let credential = IssuedCredential {
+            id: "credential-1".to_string(),
+            user_id: "user-1".to_string(),
+            subject_did: "did:key:zQ3s".to_string(),
+            kind: CredentialKind::VerifiedPhone,
+            jwt: "header.payload.signature".to_string(),
+            issued_at,
+            expires_at: issued_at + chrono::Duration::days(365),
+            revoked_at: None,
+        };
+
+        let revoked = read_back(&credential, &revocation(issued_at));
+        assert_eq!(revoked.revoked_at, Some(issued_at));
+    }
+}
@@ -0,0 +1,214 @@
+use chrono::{DateTime, Utc};
+use mongodb::bson::Document;
+use mongodb::{Collection, Database};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+
+use crate::domain::entities::{Job, Message, Provider};
+use crate::infrastructure::database::RedisConnection;
+use crate::shared::types::{Carrier, MessageStatus, ProviderStatus};
+use crate::shared::{PeerPowerError, Result};
+
+/// How long a dry run's confirmation token stays valid
+const CONFIRMATION_TTL_SECONDS: i64 = 300;
+
+/// Job states that still have work outstanding
+const ACTIVE_JOB_STATUSES: [&str; 3] = ["Assigned", "Dispatched", "InProgress"];
+
+/// Blast radius of a bulk admin action
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImpactReport {
+    pub providers: u64,
+    pub online_providers: u64,
+    pub queued_messages: u64,
+    pub active_jobs: u64,
+    pub affected_clients: u64,
+}
+
+/// Issued by a dry run; must be echoed back to execute the same action
+#[derive(Debug, Clone, Serialize)]
+pub struct ActionConfirmation {
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Reports what a bulk action would touch and gates its execution behind a
+/// confirmation token from a prior dry run.
+///
+/// Tokens are single-use, expire after five minutes, and are bound to the
+/// admin and the exact action (a fingerprint of its parameters), so a token
+/// for suspending ten providers can't be replayed to suspend two hundred.
+pub struct ImpactAnalyzer {
+    messages: Collection<Message>,
+    jobs: Collection<Job>,
+    providers: Collection<Provider>,
+    redis: RedisConnection,
+}
+
+impl ImpactAnalyzer {
+    pub fn new(database: Arc<Database>, redis: RedisConnection) -> Self {
+        Self {
+            messages: database.collection("messages"),
+            jobs: database.collection("jobs"),
+            providers: database.collection("providers"),
+            redis,
+        }
+    }
+
+    fn confirmation_key(token: &str) -> String {
+        format!("bulk_action:confirm:{}", token)
+    }
+
+    /// Stable digest of who is doing what, e.g. `["providers.status", "suspended", ids...]`
+    pub fn fingerprint(actor_id: &str, action: &[&str]) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(actor_id.as_bytes());
+        for part in action {
+            hasher.update([0u8]);
+            hasher.update(part.as_bytes());
+        }
+        hex::encode(hasher.finalize())
+    }
+
+    pub async fn issue_confirmation(&self, fingerprint: &str) -> Result<ActionConfirmation> {
+        let token = crate::shared::utils::generate_id();
+        self.redis
+            .set(
+                &Self::confirmation_key(&token),
+                fingerprint,
+                Some(CONFIRMATION_TTL_SECONDS as usize),
+            )
+            .await?;
+
+        Ok(ActionConfirmation {
+            token,
+            expires_at: crate::shared::utils::now()
+                + chrono::Duration::seconds(CONFIRMATION_TTL_SECONDS),
+        })
+    }
+
+    /// Check and spend the token from a dry run of this exact action
+    pub async fn consume_confirmation(&self, token: Option<&str>, fingerprint: &str) -> Result<()> {
+        let rejected = || PeerPowerError::ValidationError {
+            field: "confirmation_token".to_string(),
+            message: "Missing, expired or mismatched token; run with dry_run first".to_string(),
+        };
+
+        let token = token
+            .filter(|token| !token.is_empty())
+            .ok_or_else(rejected)?;
+        let key = Self::confirmation_key(token);
+        match self.redis.get(&key).await? {
+            Some(stored) if stored == fingerprint => {
+                // Another request may have spent it first
+                if self.redis.delete(&key).await? {
+                    Ok(())
+                } else {
+                    Err(rejected())
+                }
+            }
+            _ => Err(rejected()),
+        }
+    }
+
+    /// Messages in flight with these providers, which would be stranded by a status change
+    pub async fn provider_status_impact(&self, provider_ids: &[String]) -> Result<ImpactReport> {
+        let providers = self
+            .count_providers(mongodb::bson::doc! {"id": {"$in": provider_ids}})
+            .await?;
+        let online_providers = self
+            .count_providers(mongodb::bson::doc! {
+                "id": {"$in": provider_ids},
+                "status": format!("{:?}", ProviderStatus::Online),
+            })
+            .await?;
+
+        let mut report = self
+            .message_impact(mongodb::bson::doc! {
+                "provider_id": {"$in": provider_ids},
+                "status": {"$in": [
+                    format!("{:?}", MessageStatus::Assigned),
+                    format!("{:?}", MessageStatus::Sent),
+                ]},
+            })
+            .await?;
+        report.providers = providers;
+        report.online_providers = online_providers;
+        Ok(report)
+    }
+
+    /// Messages waiting to go to this carrier's numbers, which a pause would hold
+    pub async fn carrier_impact(&self, carrier: &Carrier) -> Result<ImpactReport> {
+        let carrier_name = format!("{:?}", carrier);
+        let providers = self
+            .count_providers(mongodb::bson::doc! {"carrier": &carrier_name})
+            .await?;
+        let online_providers = self
+            .count_providers(mongodb::bson::doc! {
+                "carrier": &carrier_name,
+                "status": format!("{:?}", ProviderStatus::Online),
+            })
+            .await?;
+
+        let mut report = self
+            .message_impact(mongodb::bson::doc! {
+                "recipient_carrier": &carrier_name,
+                "status": format!("{:?}", MessageStatus::Pending),
+            })
+            .await?;
+        report.providers = providers;
+        report.online_providers = online_providers;
+        Ok(report)
+    }
+
+    async fn count_providers(&self, filter: Document) -> Result<u64> {
+        self.providers
+            .count_documents(filter, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to count providers: {}", e),
+            })
+    }
+
+    async fn message_impact(&self, filter: Document) -> Result<ImpactReport> {
+        let message_ids = self
+            .messages
+            .distinct("id", filter.clone(), None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to find affected messages: {}", e),
+            })?;
+        let clients = self
+            .messages
+            .distinct("client_id", filter, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to find affected clients: {}", e),
+            })?;
+
+        let active_jobs = if message_ids.is_empty() {
+            0
+        } else {
+            self.jobs
+                .count_documents(
+                    mongodb::bson::doc! {
+                        "message_id": {"$in": &message_ids},
+                        "status": {"$in": ACTIVE_JOB_STATUSES.to_vec()},
+                    },
+                    None,
+                )
+                .await
+                .map_err(|e| PeerPowerError::Database {
+                    message: format!("Failed to count affected jobs: {}", e),
+                })?
+        };
+
+        Ok(ImpactReport {
+            queued_messages: message_ids.len() as u64,
+            active_jobs,
+            affected_clients: clients.len() as u64,
+            ..Default::default()
+        })
+    }
+}
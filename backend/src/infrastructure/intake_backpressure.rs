@@ -0,0 +1,208 @@
+use mongodb::bson::doc;
+use mongodb::options::FindOneOptions;
+use mongodb::{Collection, Database};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::config::{BackpressureConfig, BackpressureMode};
+use crate::domain::entities::{Message, MessagePriority};
+use crate::infrastructure::job_queue::JobQueue;
+use crate::shared::types::MessageStatus;
+use crate::shared::{PeerPowerError, Result};
+
+/// Holds back low-priority messages at intake while the backlog is over its
+/// limits, so it drains instead of growing without bound.
+///
+/// A background task samples the job queue depth and the age of the oldest
+/// `Pending` message. While either is over its limit, `admit` rejects the
+/// configured priorities with `503 INTAKE_THROTTLED` and `Retry-After`, or
+/// in defer mode accepts them but leaves their jobs in the outbox until
+/// `retry_after_seconds` have passed. Other priorities are never held back.
+pub struct IntakeBackpressure {
+    messages: Collection<Message>,
+    job_queue: Arc<JobQueue>,
+    config: BackpressureConfig,
+    pressure: RwLock<Option<&'static str>>, // why intake is held back, if it is
+}
+
+impl IntakeBackpressure {
+    pub fn new(
+        database: Arc<Database>,
+        job_queue: Arc<JobQueue>,
+        config: BackpressureConfig,
+    ) -> Self {
+        Self {
+            messages: database.collection("messages"),
+            job_queue,
+            config,
+            pressure: RwLock::new(None),
+        }
+    }
+
+    /// Which limit a sample is over, if any; `None` signals count as fine
+    pub fn assess(
+        config: &BackpressureConfig,
+        queue_depth: Option<u64>,
+        oldest_pending_seconds: Option<u64>,
+    ) -> Option<&'static str> {
+        if queue_depth.is_some_and(|depth| depth >= config.max_queue_depth) {
+            Some("queue_depth")
+        } else if oldest_pending_seconds.is_some_and(|age| age >= config.max_pending_age_seconds) {
+            Some("pending_age")
+        } else {
+            None
+        }
+    }
+
+    pub fn pressure(&self) -> Option<&'static str> {
+        match self.pressure.read() {
+            Ok(pressure) => *pressure,
+            Err(poisoned) => *poisoned.into_inner(),
+        }
+    }
+
+    /// Whether a message of `priority` may go straight onto the queue:
+    /// `Ok(None)` if so, `Ok(Some(delay))` to defer its job, or an
+    /// `IntakeThrottled` error to reject it
+    pub fn admit(&self, priority: &MessagePriority) -> Result<Option<chrono::Duration>> {
+        if !self.config.enabled {
+            return Ok(None);
+        }
+        let Some(reason) = self.pressure() else {
+            return Ok(None);
+        };
+        let priority = format!("{:?}", priority).to_lowercase();
+        if !self.config.shed_priorities.contains(&priority) {
+            return Ok(None);
+        }
+
+        metrics::counter!(
+            "intake_backpressure_total",
+            "reason" => reason,
+            "priority" => priority,
+            "mode" => format!("{:?}", self.config.mode).to_lowercase()
+        )
+        .increment(1);
+        match self.config.mode {
+            BackpressureMode::Defer => Ok(Some(chrono::Duration::seconds(
+                self.config.retry_after_seconds as i64,
+            ))),
+            BackpressureMode::Reject => Err(PeerPowerError::IntakeThrottled {
+                reason: reason.to_string(),
+                retry_after_seconds: self.config.retry_after_seconds,
+            }),
+        }
+    }
+
+    pub fn start(self: Arc<Self>) {
+        if !self.config.enabled {
+            info!("Intake backpressure disabled");
+            return;
+        }
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(
+                self.config.sample_interval_seconds.max(1),
+            ));
+            loop {
+                interval.tick().await;
+                self.update().await;
+            }
+        });
+    }
+
+    async fn update(&self) {
+        let queue_depth = match self.job_queue.depth().await {
+            Ok(depth) => Some(depth),
+            Err(e) => {
+                warn!("Backpressure could not read the queue depth: {}", e);
+                None
+            }
+        };
+        let oldest_pending_seconds = match self.oldest_pending_seconds().await {
+            Ok(age) => age,
+            Err(e) => {
+                warn!(
+                    "Backpressure could not read the oldest pending message: {}",
+                    e
+                );
+                None
+            }
+        };
+        if let Some(age) = oldest_pending_seconds {
+            metrics::gauge!("messages_oldest_pending_seconds").set(age as f64);
+        }
+
+        let next = Self::assess(&self.config, queue_depth, oldest_pending_seconds);
+        let previous = self.pressure();
+        if let Ok(mut pressure) = self.pressure.write() {
+            *pressure = next;
+        }
+        metrics::gauge!("intake_backpressure_active").set(if next.is_some() { 1.0 } else { 0.0 });
+
+        match (previous, next) {
+            (None, Some(reason)) => warn!(
+                "Intake backpressure on ({}): queue depth {:?}, oldest pending {:?}s",
+                reason, queue_depth, oldest_pending_seconds
+            ),
+            (Some(_), None) => info!("Intake backpressure off"),
+            _ => {}
+        }
+    }
+
+    async fn oldest_pending_seconds(&self) -> Result<Option<u64>> {
+        let oldest = self
+            .messages
+            .find_one(
+                doc! {"status": format!("{:?}", MessageStatus::Pending)},
+                FindOneOptions::builder()
+                    .sort(doc! {"created_at": 1})
+                    .build(),
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to query pending messages: {}", e),
+            })?;
+        Ok(oldest.map(|message| {
+            (crate::shared::utils::now() - message.created_at)
+                .num_seconds()
+                .max(0) as u64
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> BackpressureConfig {
+        BackpressureConfig {
+            enabled: true,
+            sample_interval_seconds: 5,
+            max_queue_depth: 1000,
+            max_pending_age_seconds: 600,
+            shed_priorities: vec!["low".to_string()],
+            mode: BackpressureMode::Reject,
+            retry_after_seconds: 60,
+        }
+    }
+
+    #[test]
+    fn test_either_limit_turns_backpressure_on() {
+        let config = config();
+        assert_eq!(
+            IntakeBackpressure::assess(&config, Some(10), Some(30)),
+            None
+        );
+        assert_eq!(
+            IntakeBackpressure::assess(&config, Some(1000), Some(30)),
+            Some("queue_depth")
+        );
+        assert_eq!(
+            IntakeBackpressure::assess(&config, None, Some(900)),
+            Some("pending_age")
+        );
+        assert_eq!(IntakeBackpressure::assess(&config, None, None), None);
+    }
+}
@@ -0,0 +1,292 @@
+use futures::stream::TryStreamExt;
+use mongodb::bson::{doc, Document};
+use mongodb::options::FindOptions;
+use mongodb::{Collection, Database};
+use serde::Deserialize;
+use std::sync::Arc;
+use tracing::info;
+
+use crate::domain::entities::{DeadLetterStatus, Job, JobStatus, Message};
+use crate::infrastructure::cancellations::CancellationService;
+use crate::infrastructure::dead_letters::JobBatch;
+use crate::infrastructure::job_queue::JobQueue;
+use crate::shared::types::MessageStatus;
+use crate::shared::utils::stored_timestamp;
+use crate::shared::{PeerPowerError, Result};
+
+/// Which jobs to list; age is from when the job was created
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct JobFilter {
+    pub status: Option<JobStatus>,
+    pub provider_id: Option<String>,
+    pub min_age_minutes: Option<i64>,
+    pub max_age_minutes: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+/// Operator tools for jobs the pipeline got stuck on: inspect them, and
+/// requeue, cancel or fail them without touching Redis or Mongo by hand
+pub struct JobAdmin {
+    jobs: Collection<Job>,
+    messages: Collection<Message>,
+    dead_letters: Collection<Document>,
+    job_queue: Arc<JobQueue>,
+    cancellations: Arc<CancellationService>,
+}
+
+impl JobAdmin {
+    pub fn new(
+        database: Arc<Database>,
+        job_queue: Arc<JobQueue>,
+        cancellations: Arc<CancellationService>,
+    ) -> Self {
+        Self {
+            jobs: database.collection("jobs"),
+            messages: database.collection("messages"),
+            dead_letters: database.collection("dead_letter_jobs"),
+            job_queue,
+            cancellations,
+        }
+    }
+
+    /// Jobs matching `filter`, oldest first
+    pub async fn list(&self, filter: &JobFilter) -> Result<Vec<Job>> {
+        let mut query = Document::new();
+        if let Some(status) = &filter.status {
+            query.insert("status", format!("{:?}", status));
+        }
+        if let Some(provider_id) = &filter.provider_id {
+            query.insert("provider_id", provider_id);
+        }
+        let now = crate::shared::utils::now();
+        let mut assigned_at = Document::new();
+        if let Some(minutes) = filter.min_age_minutes {
+            assigned_at.insert(
+                "$lte",
+                stored_timestamp(now - chrono::Duration::minutes(minutes)),
+            );
+        }
+        if let Some(minutes) = filter.max_age_minutes {
+            assigned_at.insert(
+                "$gte",
+                stored_timestamp(now - chrono::Duration::minutes(minutes)),
+            );
+        }
+        if !assigned_at.is_empty() {
+            query.insert("assigned_at", assigned_at);
+        }
+
+        self.jobs
+            .find(
+                query,
+                FindOptions::builder()
+                    .sort(doc! {"assigned_at": 1})
+                    .limit(filter.limit.unwrap_or(100).clamp(1, 500))
+                    .build(),
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to query jobs: {}", e),
+            })?
+            .try_collect()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read jobs: {}", e),
+            })
+    }
+
+    pub async fn get(&self, id: &str) -> Result<Job> {
+        self.jobs
+            .find_one(doc! {"id": id}, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch job: {}", e),
+            })?
+            .ok_or_else(|| PeerPowerError::NotFound {
+                resource: format!("Job with ID: {}", id),
+            })
+    }
+
+    /// Put jobs back on the queue with a fresh retry budget. Only jobs whose
+    /// message has not been dispatched (or has failed) qualify; fail a job
+    /// stuck after dispatch first, so its message is never sent twice.
+    pub async fn requeue(&self, ids: &[String], actor: &str) -> Result<JobBatch> {
+        let mut batch = JobBatch::default();
+        for id in ids {
+            let (mut job, mut message) = match self.open_job(id).await? {
+                Ok(found) => found,
+                Err(reason) => {
+                    batch.skip(id, reason);
+                    continue;
+                }
+            };
+            if message.is_expired() {
+                batch.skip(id, "Message has expired");
+                continue;
+            }
+            if self.is_dead_lettered(id).await? {
+                batch.skip(id, "Dead-lettered; requeue it from the dead-letter queue");
+                continue;
+            }
+            let previous = message.status.clone();
+            if message.transition_to(MessageStatus::Pending).is_err() {
+                batch.skip(id, format!("Message is {:?}", previous).to_lowercase());
+                continue;
+            }
+
+            job.reset_for_requeue();
+            if !self.save_message(&message, &previous).await? {
+                batch.skip(id, "Message changed; try again");
+                continue;
+            }
+            self.save_job(&job).await?;
+            self.job_queue.enqueue(&job).await?;
+            batch.processed.push(id.clone());
+        }
+
+        info!(
+            "{} requeued {} jobs ({} skipped)",
+            actor,
+            batch.processed.len(),
+            batch.skipped.len()
+        );
+        Ok(batch)
+    }
+
+    /// Cancel jobs' messages; ones already dispatched are cancelled with the
+    /// provider's device, like a client cancellation
+    pub async fn cancel(&self, ids: &[String], actor: &str) -> Result<JobBatch> {
+        let mut batch = JobBatch::default();
+        for id in ids {
+            let job = match self.open_job(id).await? {
+                Ok((job, _)) => job,
+                Err(reason) => {
+                    batch.skip(id, reason);
+                    continue;
+                }
+            };
+            match self.cancellations.cancel_as_admin(&job.message_id).await {
+                Ok(_) => batch.processed.push(id.clone()),
+                Err(PeerPowerError::Domain(e)) => batch.skip(id, e.to_string()),
+                Err(PeerPowerError::ValidationError { message, .. }) => batch.skip(id, message),
+                Err(e) => return Err(e),
+            }
+        }
+
+        info!(
+            "{} cancelled {} jobs ({} skipped)",
+            actor,
+            batch.processed.len(),
+            batch.skipped.len()
+        );
+        Ok(batch)
+    }
+
+    /// Fail jobs and their messages for good, without further retries
+    pub async fn force_fail(&self, ids: &[String], reason: &str, actor: &str) -> Result<JobBatch> {
+        let mut batch = JobBatch::default();
+        for id in ids {
+            let (mut job, mut message) = match self.open_job(id).await? {
+                Ok(found) => found,
+                Err(reason) => {
+                    batch.skip(id, reason);
+                    continue;
+                }
+            };
+
+            let error = format!("Failed by {}: {}", actor, reason);
+            let previous = message.status.clone();
+            message.mark_failed(error.clone());
+            job.mark_failed(error);
+            if !self.save_message(&message, &previous).await? {
+                batch.skip(id, "Message changed; try again");
+                continue;
+            }
+            self.save_job(&job).await?;
+            batch.processed.push(id.clone());
+        }
+
+        metrics::counter!("jobs_force_failed_total").increment(batch.processed.len() as u64);
+        info!(
+            "{} force-failed {} jobs ({} skipped)",
+            actor,
+            batch.processed.len(),
+            batch.skipped.len()
+        );
+        Ok(batch)
+    }
+
+    /// The job and its message if neither is finished, or why not
+    async fn open_job(&self, id: &str) -> Result<std::result::Result<(Job, Message), String>> {
+        let job = match self.get(id).await {
+            Ok(job) => job,
+            Err(PeerPowerError::NotFound { .. }) => return Ok(Err("Not found".to_string())),
+            Err(e) => return Err(e),
+        };
+        if matches!(job.status, JobStatus::Completed | JobStatus::Cancelled) {
+            return Ok(Err(format!("Job is {:?}", job.status).to_lowercase()));
+        }
+
+        let message = self
+            .messages
+            .find_one(doc! {"id": &job.message_id}, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch message: {}", e),
+            })?;
+        match message {
+            None => Ok(Err("Message no longer exists".to_string())),
+            Some(message)
+                if matches!(
+                    message.status,
+                    MessageStatus::Delivered | MessageStatus::Cancelled
+                ) =>
+            {
+                Ok(Err(
+                    format!("Message is {:?}", message.status).to_lowercase()
+                ))
+            }
+            Some(message) => Ok(Ok((job, message))),
+        }
+    }
+
+    async fn is_dead_lettered(&self, job_id: &str) -> Result<bool> {
+        let pending = self
+            .dead_letters
+            .count_documents(
+                doc! {"job_id": job_id, "status": format!("{:?}", DeadLetterStatus::Pending)},
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to check dead-letter jobs: {}", e),
+            })?;
+        Ok(pending > 0)
+    }
+
+    /// Store the message if it is still in `previous` status
+    async fn save_message(&self, message: &Message, previous: &MessageStatus) -> Result<bool> {
+        let result = self
+            .messages
+            .replace_one(
+                doc! {"id": &message.id, "status": format!("{:?}", previous)},
+                message,
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to update message: {}", e),
+            })?;
+        Ok(result.matched_count == 1)
+    }
+
+    async fn save_job(&self, job: &Job) -> Result<()> {
+        self.jobs
+            .replace_one(doc! {"id": &job.id}, job, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to update job: {}", e),
+            })?;
+        Ok(())
+    }
+}
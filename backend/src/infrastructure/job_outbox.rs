@@ -0,0 +1,256 @@
+use futures::stream::TryStreamExt;
+use mongodb::bson::doc;
+use mongodb::options::FindOptions;
+use mongodb::{Client, Collection, Database};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::domain::entities::{Job, Message, OutboxEntry};
+use crate::infrastructure::database::{MongoDatabase, RedisConnection};
+use crate::infrastructure::job_queue::JobQueue;
+use crate::shared::utils::stored_timestamp;
+use crate::shared::{PeerPowerError, Result};
+
+const RELAY_LOCK_KEY: &str = "jobs:outbox:relay_lock";
+
+/// How often the relay looks for entries that were not published
+const RELAY_INTERVAL_SECONDS: u64 = 5;
+
+/// Entries published per relay pass at most
+const RELAY_BATCH_SIZE: i64 = 200;
+
+/// Transactional outbox for new jobs.
+///
+/// A message, its job and an `OutboxEntry` are written in one MongoDB
+/// transaction, then the job is pushed to Redis and the entry deleted. If the
+/// push fails the entry stays behind and the relay publishes it with
+/// backoff, so a message is never left `Pending` with nothing queued.
+///
+/// Transactions need a replica set. Against a standalone server the three
+/// documents are written one after another instead (message first, entry
+/// last); Redis failures are still covered, a crash between the writes is not.
+pub struct JobOutbox {
+    client: Client,
+    entries: Collection<OutboxEntry>,
+    messages: Collection<Message>,
+    jobs: Collection<Job>,
+    job_queue: Arc<JobQueue>,
+    redis: RedisConnection,
+    transactions_unsupported: AtomicBool,
+}
+
+impl JobOutbox {
+    pub fn new(
+        client: Client,
+        database: Arc<Database>,
+        redis: RedisConnection,
+        job_queue: Arc<JobQueue>,
+    ) -> Self {
+        Self {
+            client,
+            entries: database.collection("job_outbox"),
+            messages: database.collection("messages"),
+            jobs: database.collection("jobs"),
+            job_queue,
+            redis,
+            transactions_unsupported: AtomicBool::new(false),
+        }
+    }
+
+    pub fn start(self: Arc<Self>) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(RELAY_INTERVAL_SECONDS));
+            loop {
+                interval.tick().await;
+                match self.relay().await {
+                    Ok(0) => {}
+                    Ok(published) => info!("Outbox relay published {} jobs", published),
+                    Err(e) => error!("Outbox relay failed: {}", e),
+                }
+            }
+        });
+    }
+
+    /// Store a new message and its job together with the intent to queue the
+    /// job, then queue it. Only storing can fail; a failed push is logged and
+    /// left to the relay.
+    pub async fn submit(&self, message: &Message, job: &Job) -> Result<()> {
+        let entry = OutboxEntry::new(job);
+        self.store(message, job, &entry).await?;
+
+        if let Err(e) = self.publish(job, &entry).await {
+            warn!(
+                "Failed to queue job {}, the outbox relay will retry: {}",
+                job.id, e
+            );
+            metrics::counter!("job_outbox_publish_failures_total").increment(1);
+        }
+        Ok(())
+    }
+
+    /// Store a new message and its job like `submit`, but leave the job for
+    /// the relay to queue once `delay` has passed
+    pub async fn defer(&self, message: &Message, job: &Job, delay: chrono::Duration) -> Result<()> {
+        let entry = OutboxEntry::deferred(job, delay);
+        self.store(message, job, &entry).await?;
+        metrics::counter!("job_outbox_deferred_total").increment(1);
+        Ok(())
+    }
+
+    async fn store(&self, message: &Message, job: &Job, entry: &OutboxEntry) -> Result<()> {
+        if !self.transactions_unsupported.load(Ordering::Relaxed) {
+            match self.store_in_transaction(message, job, entry).await {
+                Ok(()) => return Ok(()),
+                Err(e) if MongoDatabase::transactions_unsupported(&e) => {
+                    warn!("MongoDB does not support transactions here; writing outbox entries without one");
+                    self.transactions_unsupported.store(true, Ordering::Relaxed);
+                }
+                Err(e) => {
+                    return Err(PeerPowerError::Database {
+                        message: format!("Failed to store message and job: {}", e),
+                    })
+                }
+            }
+        }
+
+        self.messages
+            .insert_one(message, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to store message: {}", e),
+            })?;
+        self.jobs
+            .insert_one(job, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to store job: {}", e),
+            })?;
+        self.entries
+            .insert_one(entry, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to store outbox entry: {}", e),
+            })?;
+        Ok(())
+    }
+
+    async fn store_in_transaction(
+        &self,
+        message: &Message,
+        job: &Job,
+        entry: &OutboxEntry,
+    ) -> mongodb::error::Result<()> {
+        // Dropping the session before the commit aborts the transaction
+        let mut session = self.client.start_session(None).await?;
+        session.start_transaction(None).await?;
+        self.messages
+            .insert_one_with_session(message, None, &mut session)
+            .await?;
+        self.jobs
+            .insert_one_with_session(job, None, &mut session)
+            .await?;
+        self.entries
+            .insert_one_with_session(entry, None, &mut session)
+            .await?;
+        session.commit_transaction().await
+    }
+
+    /// Push the job to Redis and retire its entry
+    async fn publish(&self, job: &Job, entry: &OutboxEntry) -> Result<()> {
+        self.job_queue.enqueue(job).await?;
+        self.entries
+            .delete_one(doc! {"id": &entry.id}, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to delete outbox entry: {}", e),
+            })?;
+        Ok(())
+    }
+
+    /// Publish entries that are due, on one instance at a time; returns how
+    /// many were published
+    pub async fn relay(&self) -> Result<u64> {
+        if !self
+            .redis
+            .acquire_lock(RELAY_LOCK_KEY, RELAY_INTERVAL_SECONDS as usize * 6)
+            .await?
+        {
+            return Ok(0);
+        }
+        let result = self.relay_due().await;
+        self.redis.release_lock(RELAY_LOCK_KEY).await?;
+        result
+    }
+
+    async fn relay_due(&self) -> Result<u64> {
+        let now = stored_timestamp(crate::shared::utils::now());
+        let due: Vec<OutboxEntry> = self
+            .entries
+            .find(
+                doc! {"next_attempt_at": {"$lte": now}},
+                FindOptions::builder()
+                    .sort(doc! {"next_attempt_at": 1})
+                    .limit(RELAY_BATCH_SIZE)
+                    .build(),
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to query outbox: {}", e),
+            })?
+            .try_collect()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read outbox: {}", e),
+            })?;
+
+        let mut published = 0;
+        for mut entry in due {
+            let job = self
+                .jobs
+                .find_one(doc! {"id": &entry.job_id}, None)
+                .await
+                .map_err(|e| PeerPowerError::Database {
+                    message: format!("Failed to fetch job: {}", e),
+                })?;
+            let Some(job) = job else {
+                // Only possible without transactions, if the job write never happened
+                warn!(
+                    "Dropping outbox entry {} for missing job {}",
+                    entry.id, entry.job_id
+                );
+                self.entries
+                    .delete_one(doc! {"id": &entry.id}, None)
+                    .await
+                    .map_err(|e| PeerPowerError::Database {
+                        message: format!("Failed to delete outbox entry: {}", e),
+                    })?;
+                continue;
+            };
+
+            match self.publish(&job, &entry).await {
+                Ok(()) => {
+                    published += 1;
+                    metrics::counter!("job_outbox_relayed_total").increment(1);
+                }
+                Err(e) => {
+                    warn!(
+                        "Outbox relay failed to queue job {} (attempt {}): {}",
+                        job.id,
+                        entry.attempts + 1,
+                        e
+                    );
+                    entry.record_failure(e.to_string());
+                    self.entries
+                        .replace_one(doc! {"id": &entry.id}, &entry, None)
+                        .await
+                        .map_err(|e| PeerPowerError::Database {
+                            message: format!("Failed to update outbox entry: {}", e),
+                        })?;
+                }
+            }
+        }
+        Ok(published)
+    }
+}
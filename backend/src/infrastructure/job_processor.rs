@@ -1,23 +1,187 @@
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use tokio::sync::{watch, Mutex, Semaphore};
+use tokio::task::JoinHandle;
 use tokio::time::{interval, sleep};
-use tracing::{error, info, warn};
+use tracing::{error, info, info_span, warn, Instrument};
 
-use crate::domain::entities::{Job, Message, Provider};
-use crate::infrastructure::messaging::fcm_service::FcmService;
-use crate::shared::types::{MessageStatus, ProviderStatus};
-use crate::shared::{AppState, PeerPowerError, Result};
+use crate::domain::entities::{
+    ClientTier, DedicatedNumber, Job, JobStatus, Message, PlatformEvent, Provider, QuietHours,
+    RoutingContext,
+};
+use crate::infrastructure::canary::CanaryRouter;
+use crate::infrastructure::number_pool::NumberPool;
+use crate::infrastructure::dispatch_strategies::retry_candidates;
+use crate::infrastructure::provider_selection::candidates_pipeline;
+use crate::infrastructure::routing_rules::RoutingDecision;
+use crate::infrastructure::messaging::fcm_batcher::FcmDispatchBatcher;
+use crate::infrastructure::messaging::fcm_service::{FcmService, SmsDispatch};
+use crate::shared::types::{Carrier, DeploymentCohort, MessageStatus, ProviderStatus};
+use crate::shared::utils::stored_timestamp;
+use crate::shared::{AppState, PeerPowerError, Result, RetryClass};
 
-/// Job processor service that handles the job queue
+/// Eligible providers fetched per carrier query for scoring
+const MAX_SELECTION_CANDIDATES: i64 = 50;
+
+/// How long a provider that was just picked is passed over by other workers
+const ROTATION_HOLD_SECONDS: usize = 1;
+
+/// Providers tried per selection before settling for one that was just picked
+const MAX_ROTATION_ATTEMPTS: usize = 5;
+
+/// How often a job held by a paused carrier is looked at again
+const PAUSED_CARRIER_RECHECK_SECONDS: u64 = 60;
+
+/// Longest an idle processor blocks waiting for work; also bounds how late a
+/// job whose retry backoff has elapsed is promoted and picked up
+const IDLE_WAIT_SECONDS: usize = 1;
+
+/// How often dispatched jobs are checked for a missing device acknowledgment
+const ACK_SWEEP_INTERVAL_SECONDS: u64 = 15;
+
+const ACK_SWEEP_LOCK_KEY: &str = "jobs:ack_sweep_lock";
+
+/// Unacknowledged jobs re-dispatched per sweep at most
+const ACK_SWEEP_BATCH_SIZE: i64 = 200;
+
+/// How often a worker looks for pending messages in MongoDB while Redis is down
+const DATABASE_POLL_SECONDS: u64 = 2;
+
+/// Pending messages looked at per MongoDB poll
+const DATABASE_SCAN_BATCH_SIZE: i64 = 20;
+
+/// How long a job claimed through MongoDB is left to its worker; also how
+/// soon one that was retried is picked up again without the delayed queue
+const DATABASE_CLAIM_SECONDS: i64 = 60;
+
+/// How often the queue depth and dead-letter gauges are refreshed
+const METRICS_REFRESH_SECONDS: u64 = 15;
+
+/// How long a worker reuses its view of which carriers can be served
+const CARRIER_AVAILABILITY_TTL: Duration = Duration::from_secs(5);
+
+/// Job processor service that handles the job queue.
+///
+/// `JOB_WORKERS` workers pull from the queues concurrently and hand their
+/// dispatches to one shared `FcmDispatchBatcher`, which groups those bound
+/// for the same provider and keeps at most `FCM_MAX_IN_FLIGHT` requests
+/// open against FCM however many workers run.
+/// Workers only dequeue from the carrier queues someone can currently
+/// deliver, so an undeliverable backlog waits in Redis instead of cycling.
+///
+/// While Redis is unavailable the workers run degraded: they claim jobs of
+/// `Pending` messages straight from MongoDB, oldest first by priority, and
+/// jobs that would wait out a backoff are left for the next MongoDB poll.
+/// The queues take over again once Redis answers.
+///
+/// Once `shutdown` flips to true the workers stop dequeuing and finish the
+/// job in hand; `drain` waits for them before the process exits.
 pub struct JobProcessor {
     app_state: Arc<AppState>,
+    carriers: Arc<CarrierAvailability>,
+    in_flight: Arc<InFlightJobs>,
+    shutdown: watch::Receiver<bool>,
+    workers: Vec<JoinHandle<()>>,
     is_running: bool,
 }
 
+/// Jobs this instance's workers have claimed and not yet finished
+#[derive(Default)]
+struct InFlightJobs {
+    jobs: std::sync::Mutex<HashMap<String, Job>>,
+}
+
+impl InFlightJobs {
+    fn insert(&self, job: &Job) {
+        if let Ok(mut jobs) = self.jobs.lock() {
+            jobs.insert(job.id.clone(), job.clone());
+        }
+    }
+
+    fn remove(&self, job_id: &str) {
+        if let Ok(mut jobs) = self.jobs.lock() {
+            jobs.remove(job_id);
+        }
+    }
+
+    fn take_all(&self) -> Vec<Job> {
+        match self.jobs.lock() {
+            Ok(mut jobs) => jobs.drain().map(|(_, job)| job).collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+}
+
+/// Which carriers' queues are worth dequeuing from, refreshed at most every
+/// `CARRIER_AVAILABILITY_TTL`
+#[derive(Default)]
+struct CarrierAvailability {
+    cached: Mutex<Option<(Instant, Vec<Carrier>)>>,
+}
+
+impl CarrierAvailability {
+    /// Carriers with online providers of their own first, then the rest,
+    /// since dispatch falls back to any carrier's providers. None when no
+    /// provider is online at all; paused carriers are always left out.
+    async fn serviceable(&self, app_state: &AppState) -> Result<Vec<Carrier>> {
+        let mut cached = self.cached.lock().await;
+        if let Some((at, carriers)) = cached.as_ref() {
+            if at.elapsed() < CARRIER_AVAILABILITY_TTL {
+                return Ok(carriers.clone());
+            }
+        }
+
+        let online = Self::online_carriers(app_state).await?;
+        let paused = app_state.carrier_kill_switch.paused().await?;
+        let mut carriers: Vec<Carrier> = Carrier::ALL
+            .into_iter()
+            .filter(|carrier| !paused.contains(&format!("{:?}", carrier)))
+            .collect();
+        if online.is_empty() {
+            carriers.clear();
+        } else {
+            carriers.sort_by_key(|carrier| !online.contains(&format!("{:?}", carrier)));
+        }
+
+        *cached = Some((Instant::now(), carriers.clone()));
+        Ok(carriers)
+    }
+
+    /// Carriers with at least one online provider
+    async fn online_carriers(app_state: &AppState) -> Result<HashSet<String>> {
+        app_state
+            .database
+            .collection::<Provider>("providers")
+            .distinct(
+                "carrier",
+                mongodb::bson::doc! {
+                    "status": format!("{:?}", ProviderStatus::Online),
+                    "carrier_mismatch": null,
+                },
+                None,
+            )
+            .await
+            .map(|carriers| {
+                carriers
+                    .into_iter()
+                    .filter_map(|carrier| carrier.as_str().map(str::to_string))
+                    .collect()
+            })
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to query online carriers: {}", e),
+            })
+    }
+}
+
 impl JobProcessor {
-    pub fn new(app_state: Arc<AppState>) -> Self {
+    pub fn new(app_state: Arc<AppState>, shutdown: watch::Receiver<bool>) -> Self {
         Self {
             app_state,
+            carriers: Arc::new(CarrierAvailability::default()),
+            in_flight: Arc::new(InFlightJobs::default()),
+            shutdown,
+            workers: Vec::new(),
             is_running: false,
         }
     }
@@ -32,10 +196,31 @@ impl JobProcessor {
         self.is_running = true;
         info!("Starting job processor...");
 
-        // Start the main processing loop
+        // Start the dispatch workers
+        let config = &self.app_state.config.jobs;
+        let fcm = Arc::new(FcmDispatchBatcher::start(
+            self.app_state.fcm_service.clone(),
+            Arc::new(Semaphore::new(config.max_in_flight_fcm)),
+            Duration::from_millis(config.fcm_batch_window_ms),
+        ));
+        let workers = config.workers;
+        for worker in 0..workers {
+            let app_state = self.app_state.clone();
+            let fcm = fcm.clone();
+            let carriers = self.carriers.clone();
+            let in_flight = self.in_flight.clone();
+            let shutdown = self.shutdown.clone();
+            self.workers.push(tokio::spawn(
+                Self::process_jobs_loop(app_state, fcm, carriers, in_flight, shutdown)
+                    .instrument(info_span!("job_worker", worker)),
+            ));
+        }
+        info!("Started {} job workers", workers);
+
+        // Start the metrics task
         let app_state = self.app_state.clone();
         tokio::spawn(async move {
-            Self::process_jobs_loop(app_state).await;
+            Self::metrics_loop(app_state).await;
         });
 
         // Start the cleanup task
@@ -44,65 +229,277 @@ impl JobProcessor {
             Self::cleanup_expired_jobs_loop(app_state).await;
         });
 
+        // Start re-dispatching jobs whose devices never acknowledged them
+        let app_state = self.app_state.clone();
+        tokio::spawn(async move {
+            Self::ack_deadline_loop(app_state).await;
+        });
+
+        // Start the carrier re-verification task
+        let app_state = self.app_state.clone();
+        tokio::spawn(async move {
+            Self::carrier_reverification_loop(app_state).await;
+        });
+
         info!("Job processor started successfully");
         Ok(())
     }
 
-    /// Main job processing loop
-    async fn process_jobs_loop(app_state: Arc<AppState>) {
-        let mut interval = interval(Duration::from_secs(5)); // Process every 5 seconds
+    /// Wait, after shutdown was signalled, for the workers to finish the jobs
+    /// they hold. Workers still busy after `JOB_SHUTDOWN_GRACE_SECONDS` are
+    /// stopped and their jobs put back on the queue for another instance;
+    /// a message they already updated is skipped when the job comes round.
+    pub async fn drain(&mut self) {
+        let grace = Duration::from_secs(self.app_state.config.jobs.shutdown_grace_seconds);
+        info!("Draining {} job workers", self.workers.len());
 
-        loop {
-            interval.tick().await;
+        let finished = tokio::time::timeout(
+            grace,
+            futures::future::join_all(self.workers.iter_mut()),
+        )
+        .await;
+        if finished.is_err() {
+            warn!("Job workers still busy after {:?}, stopping them", grace);
+            let busy: Vec<&mut JoinHandle<()>> = self
+                .workers
+                .iter_mut()
+                .filter(|worker| !worker.is_finished())
+                .collect();
+            for worker in &busy {
+                worker.abort();
+            }
+            futures::future::join_all(busy).await;
+        }
+        self.workers.clear();
 
-            if let Err(e) = Self::process_pending_jobs(&app_state).await {
-                error!("Error processing jobs: {}", e);
-                sleep(Duration::from_secs(10)).await; // Back off on error
+        for job in self.in_flight.take_all() {
+            let requeued = self.app_state.job_queue.enqueue(&job).await;
+            let released = self.app_state.job_queue.release(&job.id).await;
+            match requeued.and(released) {
+                Ok(()) => {
+                    metrics::counter!("jobs_requeued_on_shutdown_total").increment(1);
+                    info!("Re-queued in-flight job {} on shutdown", job.id);
+                }
+                Err(e) => error!("Failed to re-queue job {} on shutdown: {}", job.id, e),
             }
         }
+        info!("Job processor drained");
     }
 
-    /// Process pending jobs from the queue
-    async fn process_pending_jobs(app_state: &Arc<AppState>) -> Result<()> {
-        // Get high priority jobs first
-        let priority_queues = [
-            "jobs:queue:priority:3",
-            "jobs:queue:priority:2",
-            "jobs:queue:priority:1",
-            "jobs:queue:priority:0",
-        ];
-
-        for queue_key in &priority_queues {
-            // Try to get a job from this priority queue
-            if let Some(job_data) = app_state.redis.rpop(queue_key).await? {
-                match serde_json::from_str::<Job>(&job_data) {
-                    Ok(job) => {
-                        info!("Processing job: {}", job.id);
-                        if let Err(e) = Self::process_single_job(app_state, job).await {
-                            error!("Failed to process job: {}", e);
-                        }
-                    }
+    /// Main job processing loop: drain the queues back to back, and block
+    /// for new work once they are empty, until shutdown is signalled
+    async fn process_jobs_loop(
+        app_state: Arc<AppState>,
+        fcm: Arc<FcmDispatchBatcher>,
+        carriers: Arc<CarrierAvailability>,
+        in_flight: Arc<InFlightJobs>,
+        mut shutdown: watch::Receiver<bool>,
+    ) {
+        let mut degraded = false;
+        while !*shutdown.borrow() {
+            if degraded {
+                if app_state.redis.health_check().await.is_ok() {
+                    info!("Redis is back, dispatching from the queues again");
+                    metrics::gauge!("job_queue_degraded").set(0.0);
+                    degraded = false;
+                    continue;
+                }
+                match Self::process_next_from_database(&app_state, &fcm, &in_flight).await {
+                    Ok(true) => {}
+                    Ok(false) => Self::pause(DATABASE_POLL_SECONDS, &mut shutdown).await,
                     Err(e) => {
-                        error!("Failed to deserialize job: {}", e);
+                        error!("Error processing jobs from MongoDB: {}", e);
+                        Self::back_off(&mut shutdown).await;
                     }
                 }
-                break; // Process one job at a time
+                continue;
+            }
+
+            match Self::process_next_job(&app_state, &fcm, &carriers, &in_flight).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    if let Err(e) = app_state.job_queue.wait_for_work(IDLE_WAIT_SECONDS).await {
+                        error!("Error waiting for jobs: {}", e);
+                        Self::back_off(&mut shutdown).await;
+                    }
+                }
+                Err(e) if e.is_redis_unavailable() => {
+                    warn!(
+                        "Redis unavailable, dispatching pending messages from MongoDB: {}",
+                        e
+                    );
+                    metrics::gauge!("job_queue_degraded").set(1.0);
+                    degraded = true;
+                }
+                Err(e) => {
+                    error!("Error processing jobs: {}", e);
+                    Self::back_off(&mut shutdown).await;
+                }
             }
         }
+        info!("Job worker stopped");
+    }
 
-        Ok(())
+    /// Pause after an error, cut short by shutdown
+    async fn back_off(shutdown: &mut watch::Receiver<bool>) {
+        Self::pause(10, shutdown).await;
     }
 
-    /// Process a single job
-    async fn process_single_job(app_state: &Arc<AppState>, mut job: Job) -> Result<()> {
-        // Get the message details
-        let messages_collection = app_state.database.collection::<Message>("messages");
-        let mut message = messages_collection
-            .find_one(mongodb::bson::doc! {"id": &job.message_id}, None)
+    async fn pause(seconds: u64, shutdown: &mut watch::Receiver<bool>) {
+        tokio::select! {
+            _ = sleep(Duration::from_secs(seconds)) => {}
+            _ = shutdown.changed() => {}
+        }
+    }
+
+    /// Claim and process the next pending job; false if none was waiting
+    async fn process_next_job(
+        app_state: &Arc<AppState>,
+        fcm: &FcmDispatchBatcher,
+        carriers: &CarrierAvailability,
+        in_flight: &InFlightJobs,
+    ) -> Result<bool> {
+        app_state.canary.announce().await?;
+        let cohorts = app_state.canary.cohorts_to_serve().await?;
+        let carriers = carriers.serviceable(app_state).await?;
+
+        // Highest priority first, in per-client order within a priority
+        let Some(job) = app_state.job_queue.dequeue(&cohorts, &carriers).await? else {
+            return Ok(false);
+        };
+
+        info!("Processing job: {} (client sequence {})", job.id, job.sequence);
+        if let Some(enqueued_at) = job.enqueued_at {
+            metrics::histogram!("job_queue_wait_seconds").record(elapsed_seconds(enqueued_at));
+        }
+        let job_id = job.id.clone();
+        Self::run_job(app_state, fcm, in_flight, job).await;
+        app_state.job_queue.release(&job_id).await?;
+
+        Ok(true)
+    }
+
+    /// Claim and process the job of the next pending message straight from
+    /// MongoDB, for while Redis is down; false if none was waiting. The
+    /// claim is left to expire, as releasing it would need Redis.
+    async fn process_next_from_database(
+        app_state: &Arc<AppState>,
+        fcm: &FcmDispatchBatcher,
+        in_flight: &InFlightJobs,
+    ) -> Result<bool> {
+        let Some(job) = Self::claim_from_database(app_state).await? else {
+            return Ok(false);
+        };
+
+        info!("Processing job {} from MongoDB (Redis unavailable)", job.id);
+        metrics::counter!("jobs_claimed_from_database_total").increment(1);
+        Self::run_job(app_state, fcm, in_flight, job).await;
+        Ok(true)
+    }
+
+    async fn run_job(
+        app_state: &Arc<AppState>,
+        fcm: &FcmDispatchBatcher,
+        in_flight: &InFlightJobs,
+        job: Job,
+    ) {
+        let job_id = job.id.clone();
+        in_flight.insert(&job);
+        let span = info_span!("job", job_id = %job.id, message_id = %job.message_id);
+        if let Err(e) = Self::process_single_job(app_state, fcm, job)
+            .instrument(span)
+            .await
+        {
+            match e.as_domain() {
+                // e.g. the message already moved on; nothing is broken
+                Some(domain) if !domain.is_retryable() => warn!("Skipped job: {}", domain),
+                _ => error!("Failed to process job: {}", e),
+            }
+        }
+        in_flight.remove(&job_id);
+    }
+
+    /// Claim the job of the most urgent, oldest pending message whose job no
+    /// worker claimed within `DATABASE_CLAIM_SECONDS`. Canary instances only
+    /// take canary messages; stable ones take all, as they can't tell
+    /// whether a canary is alive.
+    async fn claim_from_database(app_state: &Arc<AppState>) -> Result<Option<Job>> {
+        let now = crate::shared::utils::now();
+
+        let mut filter = mongodb::bson::doc! {
+            "status": format!("{:?}", MessageStatus::Pending),
+            "$or": [{"scheduled_at": null}, {"scheduled_at": {"$lte": stored_timestamp(now)}}],
+        };
+        if app_state.config.instance.canary {
+            filter.insert("cohort", format!("{:?}", DeploymentCohort::Canary));
+        }
+        let mut pending: Vec<Message> = app_state
+            .database
+            .collection::<Message>("messages")
+            .find(
+                filter,
+                mongodb::options::FindOptions::builder()
+                    .sort(mongodb::bson::doc! {"created_at": 1})
+                    .limit(DATABASE_SCAN_BATCH_SIZE)
+                    .build(),
+            )
             .await
             .map_err(|e| PeerPowerError::Database {
-                message: format!("Failed to fetch message: {}", e),
+                message: format!("Failed to query pending messages: {}", e),
             })?
+            .try_collect()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read pending messages: {}", e),
+            })?;
+        pending.sort_by_key(|message| std::cmp::Reverse(message.get_priority_score()));
+
+        let jobs = app_state.database.collection::<Job>("jobs");
+        let claimable_since =
+            stored_timestamp(now - chrono::Duration::seconds(DATABASE_CLAIM_SECONDS));
+        for message in pending {
+            let claimed = jobs
+                .find_one_and_update(
+                    mongodb::bson::doc! {
+                        "message_id": &message.id,
+                        "status": {"$nin": [
+                            format!("{:?}", JobStatus::Dispatched),
+                            format!("{:?}", JobStatus::Completed),
+                            format!("{:?}", JobStatus::Cancelled),
+                        ]},
+                        "$or": [
+                            {"claimed_at": null},
+                            {"claimed_at": {"$lt": &claimable_since}},
+                        ],
+                    },
+                    claim(&app_state.config.instance.id, now),
+                    mongodb::options::FindOneAndUpdateOptions::builder()
+                        .return_document(mongodb::options::ReturnDocument::After)
+                        .build(),
+                )
+                .await
+                .map_err(|e| PeerPowerError::Database {
+                    message: format!("Failed to claim job: {}", e),
+                })?;
+            if claimed.is_some() {
+                return Ok(claimed);
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Process a single job
+    async fn process_single_job(
+        app_state: &Arc<AppState>,
+        fcm: &FcmDispatchBatcher,
+        mut job: Job,
+    ) -> Result<()> {
+        // Get the message details
+        let mut message = app_state
+            .message_repository
+            .find_by_id(&job.message_id)
+            .await?
             .ok_or_else(|| PeerPowerError::NotFound {
                 resource: format!("Message: {}", job.message_id),
             })?;
@@ -119,6 +516,10 @@ impl JobProcessor {
             message.mark_failed("Message expired".to_string());
             job.mark_failed("Message expired".to_string());
             Self::update_message_and_job(app_state, &message, &job).await?;
+            app_state
+                .events
+                .publish(PlatformEvent::job_failed(&job, &message))
+                .await;
             return Ok(());
         }
 
@@ -131,38 +532,150 @@ impl JobProcessor {
             message.mark_failed(validation_error.clone());
             job.mark_failed(validation_error);
             Self::update_message_and_job(app_state, &message, &job).await?;
+            app_state
+                .events
+                .publish(PlatformEvent::job_failed(&job, &message))
+                .await;
             return Ok(());
         }
 
-        // Find an available provider
-        match Self::find_available_provider(app_state, &message).await? {
+        // Protected recipients are decrypted for dispatch only; `dispatch` is
+        // never written back
+        let recipient = match app_state.recipient_vault.reveal(&message) {
+            Ok(recipient) => recipient,
+            Err(e) => {
+                error!("{}", e);
+                message.mark_failed("Recipient could not be decrypted".to_string());
+                job.mark_failed(e.to_string());
+                Self::update_message_and_job(app_state, &message, &job).await?;
+                app_state
+                    .events
+                    .publish(PlatformEvent::job_failed(&job, &message))
+                    .await;
+                return Ok(());
+            }
+        };
+
+        // The prefix table may have changed since the message was queued
+        app_state
+            .carrier_redetector
+            .correct_at_dispatch(&mut message, &recipient)
+            .await?;
+        let mut dispatch = message.clone();
+        dispatch.recipient = recipient;
+
+        // Carrier paused by an operator: hold the message until it resumes
+        if app_state
+            .carrier_kill_switch
+            .is_paused(&message.recipient_carrier)
+            .await?
+        {
+            info!(
+                "Carrier {:?} is paused, re-queuing job {}",
+                message.recipient_carrier, job.id
+            );
+            metrics::counter!("job_retries_total", "reason" => "carrier_paused").increment(1);
+            app_state
+                .job_queue
+                .requeue(&job, Duration::from_secs(PAUSED_CARRIER_RECHECK_SECONDS))
+                .await?;
+            return Ok(());
+        }
+
+        // Quiet hours: hold non-urgent messages in the delayed queue until they end
+        if let Some(resumes_at) = Self::quiet_hours_end(app_state, &message).await? {
+            info!("Quiet hours until {}, deferring job {}", resumes_at, job.id);
+            metrics::counter!("job_retries_total", "reason" => "quiet_hours").increment(1);
+            let delay = (resumes_at - crate::shared::utils::now())
+                .to_std()
+                .unwrap_or_default();
+            app_state.job_queue.requeue(&job, delay).await?;
+            return Ok(());
+        }
+
+        // Clients with dedicated numbers only send from them; otherwise use the shared pool
+        let dedicated = app_state
+            .number_pool
+            .numbers_for(&message.client_id)
+            .await?;
+        let selected = if dedicated.is_empty() {
+            Self::find_available_provider(app_state, &dispatch, &job).await?
+        } else {
+            match Self::find_dedicated_provider(app_state, &dispatch, &job, &dedicated).await? {
+                Some((provider, number)) => {
+                    message.dedicated_number_id = Some(number.id);
+                    Some(provider)
+                }
+                None => None,
+            }
+        };
+
+        match selected {
             Some(mut provider) => {
+                // The provider may have hit its daily quota since it was selected
+                if let Err(e) = provider.record_assignment() {
+                    info!("Re-queuing job {}: {}", job.id, e);
+                    Self::requeue_job(app_state, &job, RetryClass::Throttled, "daily_quota")
+                        .await?;
+                    return Ok(());
+                }
+                // At most one dispatch per attempt, however often the job is picked up
+                if !Self::mark_dispatching(app_state, &mut job).await? {
+                    warn!(
+                        "Job {} was interrupted while dispatching; not sending that attempt again",
+                        job.id
+                    );
+                    Self::fail_attempt(
+                        app_state,
+                        &mut message,
+                        &mut job,
+                        "Dispatch interrupted; outcome unknown".to_string(),
+                        RetryClass::Transient,
+                        "dispatch_interrupted",
+                    )
+                    .await?;
+                    Self::update_message_and_job(app_state, &message, &job).await?;
+                    return Ok(());
+                }
                 info!("Assigned job {} to provider {}", job.id, provider.id);
 
                 // Use entity methods to update state
-                message.assign_to_provider(provider.id.clone());
+                message.assign_to_provider(provider.id.clone())?;
+                job.provider_id = provider.id.clone();
                 job.mark_in_progress();
-                provider.increment_load();
 
                 // Send FCM notification to provider
-                match Self::send_fcm_notification(app_state, &job, &message, &provider).await {
+                match Self::send_fcm_notification(fcm, &dispatch, &provider).await {
                     Ok(_) => {
                         info!("FCM notification sent for job {}", job.id);
-                        message.mark_sent();
+                        if let Some(claimed_at) = job.claimed_at {
+                            metrics::histogram!("job_dispatch_latency_seconds")
+                                .record(elapsed_seconds(claimed_at));
+                        }
+                        message.mark_sent()?;
+                        job.mark_dispatched(
+                            crate::shared::utils::now()
+                                + chrono::Duration::seconds(
+                                    app_state.config.jobs.dispatch_ack_timeout_seconds as i64,
+                                ),
+                        );
                         provider.record_message_sent();
+                        CanaryRouter::record_outcome(message.cohort, "dispatched");
                     }
                     Err(e) => {
                         error!("Failed to send FCM notification: {}", e);
-                        message.mark_failed(format!("FCM failed: {}", e));
-                        job.mark_failed(format!("FCM failed: {}", e));
+                        CanaryRouter::record_outcome(message.cohort, "dispatch_failed");
                         provider.record_message_failed();
                         provider.decrement_load();
-
-                        // Re-queue for retry if possible
-                        if job.can_retry() {
-                            job.increment_retry();
-                            Self::requeue_job(app_state, &job).await?;
-                        }
+                        Self::fail_attempt(
+                            app_state,
+                            &mut message,
+                            &mut job,
+                            format!("FCM failed: {}", e),
+                            RetryClass::of(&e),
+                            "dispatch_failed",
+                        )
+                        .await?;
                     }
                 }
 
@@ -173,60 +686,153 @@ impl JobProcessor {
                 info!("No available provider for job {}, re-queuing", job.id);
 
                 // Re-queue the job for later processing
-                Self::requeue_job(app_state, &job).await?;
+                Self::requeue_job(app_state, &job, RetryClass::Unavailable, "no_provider").await?;
             }
         }
 
         Ok(())
     }
 
-    /// Find an available provider for the message
+    /// When the quiet hours the message falls in end, if it is held by them:
+    /// the client's own window if it set one, else the system-wide one
+    async fn quiet_hours_end(
+        app_state: &Arc<AppState>,
+        message: &Message,
+    ) -> Result<Option<chrono::DateTime<chrono::Utc>>> {
+        if !message.honors_quiet_hours() {
+            return Ok(None);
+        }
+        let config = &app_state.config.quiet_hours;
+        let client = app_state
+            .user_repository
+            .find_by_id(&message.client_id)
+            .await?;
+        let window = client
+            .and_then(|client| client.quiet_hours)
+            .unwrap_or(QuietHours {
+                enabled: config.enabled,
+                start_hour: config.start_hour,
+                end_hour: config.end_hour,
+            });
+        Ok(window.resumes_at(crate::shared::utils::now(), config.utc_offset_hours))
+    }
+
+    /// Find an available provider for the message, as the configured
+    /// dispatch strategy prefers; a retry goes to a provider that hasn't
+    /// failed the job yet
     async fn find_available_provider(
         app_state: &Arc<AppState>,
         message: &Message,
+        job: &Job,
     ) -> Result<Option<Provider>> {
-        let providers_collection = app_state.database.collection::<Provider>("providers");
+        // Operator routing rules narrow the candidates before selection
+        let routing = Self::routing_decision(app_state, message).await?;
+        routing.record_hits();
 
-        // Try to find a provider with the same carrier as recipient (for better delivery rates)
-        let target_carrier = crate::shared::types::Carrier::from_phone_number(&message.recipient);
+        // Providers on the recipient's carrier come first, for strategies
+        // that prefer them. The cache is tried before MongoDB; its snapshots
+        // lag, so its pick is only used as currently stored.
+        let cached = Self::cached_candidates(app_state, &message.recipient_carrier).await;
+        if let Some(provider) =
+            Self::select_candidate(app_state, cached, message, job, &routing).await?
+        {
+            if let Some(provider) = Self::reload_available(app_state, &provider).await? {
+                return Ok(Some(provider));
+            }
+        }
 
-        let mut cursor = providers_collection
-            .find(
-                mongodb::bson::doc! {
-                    "carrier": format!("{:?}", target_carrier),
-                    "status": format!("{:?}", ProviderStatus::Online),
-                    "current_load": {"$lt": 5}, // Not overloaded
-                    "messages_sent_today": {"$lt": mongodb::bson::doc!{"$field": "max_daily_messages"}},
-                },
-                None,
-            )
+        let candidates =
+            Self::fetch_candidates(app_state, Some(&message.recipient_carrier)).await?;
+        Self::select_candidate(app_state, candidates, message, job, &routing).await
+    }
+
+    /// The candidate to dispatch to, of those the message's routing and
+    /// the job's earlier attempts allow
+    async fn select_candidate(
+        app_state: &Arc<AppState>,
+        candidates: Vec<Provider>,
+        message: &Message,
+        job: &Job,
+        routing: &RoutingDecision,
+    ) -> Result<Option<Provider>> {
+        let candidates: Vec<Provider> = candidates
+            .into_iter()
+            .filter(|provider| {
+                provider.is_available()
+                    && provider.accepts_recipient(&message.recipient)
+                    && routing.allows(provider)
+            })
+            .collect();
+        let final_attempt = job.retry_count + 1 >= app_state.config.retries.jobs.max_attempts;
+        let candidates =
+            retry_candidates(candidates, job, &message.recipient_carrier, final_attempt);
+
+        Self::select_in_rotation(app_state, candidates, message).await
+    }
+
+    /// Cached providers able to take a job, or none if the cache can't say
+    async fn cached_candidates(
+        app_state: &Arc<AppState>,
+        preferred_carrier: &Carrier,
+    ) -> Vec<Provider> {
+        match app_state
+            .provider_cache
+            .candidates(Some(preferred_carrier), MAX_SELECTION_CANDIDATES as usize)
+            .await
+        {
+            Ok(candidates) => {
+                let result = if candidates.is_empty() { "miss" } else { "hit" };
+                metrics::counter!("provider_cache_lookups_total", "result" => result).increment(1);
+                candidates
+            }
+            Err(e) => {
+                warn!("Provider cache unavailable, selecting from MongoDB: {}", e);
+                metrics::counter!("provider_cache_lookups_total", "result" => "error").increment(1);
+                Vec::new()
+            }
+        }
+    }
+
+    /// The stored provider behind a cached pick, if it can still take a job;
+    /// otherwise the pick is dropped from the cache
+    async fn reload_available(
+        app_state: &Arc<AppState>,
+        cached: &Provider,
+    ) -> Result<Option<Provider>> {
+        let stored = app_state
+            .database
+            .collection::<Provider>("providers")
+            .find_one(mongodb::bson::doc! {"id": &cached.id}, None)
             .await
             .map_err(|e| PeerPowerError::Database {
-                message: format!("Failed to query providers: {}", e),
+                message: format!("Failed to fetch provider: {}", e),
             })?;
-
-        // Try to get the first available provider and validate availability
-        while let Some(provider) =
-            cursor
-                .try_next()
-                .await
-                .map_err(|e| PeerPowerError::Database {
-                    message: format!("Failed to fetch provider: {}", e),
-                })?
-        {
-            if provider.is_available() && provider.is_heartbeat_recent() {
-                return Ok(Some(provider));
+        match stored {
+            Some(provider) if provider.is_available() && provider.dedicated_client_id.is_none() => {
+                Ok(Some(provider))
+            }
+            _ => {
+                if let Err(e) = app_state.provider_cache.remove(&cached.id).await {
+                    warn!(
+                        "Failed to drop provider {} from the cache: {}",
+                        cached.id, e
+                    );
+                }
+                Ok(None)
             }
         }
+    }
 
-        // If no same-carrier provider available, try any available provider
-        let mut cursor = providers_collection
-            .find(
-                mongodb::bson::doc! {
-                    "status": format!("{:?}", ProviderStatus::Online),
-                    "current_load": {"$lt": 5},
-                    "messages_sent_today": {"$lt": mongodb::bson::doc!{"$field": "max_daily_messages"}},
-                },
+    /// Providers able to take a job now, those on `preferred_carrier` first
+    async fn fetch_candidates(
+        app_state: &Arc<AppState>,
+        preferred_carrier: Option<&Carrier>,
+    ) -> Result<Vec<Provider>> {
+        let mut cursor = app_state
+            .database
+            .collection::<Provider>("providers")
+            .aggregate(
+                candidates_pipeline(preferred_carrier, MAX_SELECTION_CANDIDATES),
                 None,
             )
             .await
@@ -234,23 +840,143 @@ impl JobProcessor {
                 message: format!("Failed to query providers: {}", e),
             })?;
 
-        if let Some(provider) = cursor
+        let mut candidates = Vec::new();
+        while let Some(doc) = cursor
             .try_next()
             .await
             .map_err(|e| PeerPowerError::Database {
                 message: format!("Failed to fetch provider: {}", e),
             })?
         {
-            return Ok(Some(provider));
+            let provider = mongodb::bson::from_document::<Provider>(doc).map_err(|e| {
+                PeerPowerError::Database {
+                    message: format!("Failed to decode provider: {}", e),
+                }
+            })?;
+            candidates.push(provider);
+        }
+        Ok(candidates)
+    }
+
+    /// Select from `candidates`, passing over any provider another worker
+    /// (on any instance) picked within `ROTATION_HOLD_SECONDS`, so jobs
+    /// dispatched together spread across the pool instead of all landing on
+    /// the top-scored provider. If every provider tried was just picked, the
+    /// best of them is used anyway.
+    async fn select_in_rotation(
+        app_state: &Arc<AppState>,
+        mut candidates: Vec<Provider>,
+        message: &Message,
+    ) -> Result<Option<Provider>> {
+        let strategy = &app_state.dispatch_strategy;
+        let best = strategy.select(&candidates, message).cloned();
+        if best.is_some() {
+            metrics::counter!("dispatch_strategy_selections_total", "strategy" => strategy.name())
+                .increment(1);
+        }
+
+        for _ in 0..MAX_ROTATION_ATTEMPTS {
+            let Some(provider) = strategy.select(&candidates, message) else {
+                break;
+            };
+            let rotation_key = format!("providers:rotation:{}", provider.id);
+            match app_state
+                .redis
+                .acquire_lock(&rotation_key, ROTATION_HOLD_SECONDS)
+                .await
+            {
+                Ok(true) => return Ok(Some(provider.clone())),
+                Ok(false) => {}
+                // No rotation without Redis; the best provider it is
+                Err(e) if e.is_redis_unavailable() => return Ok(best),
+                Err(e) => return Err(e),
+            }
+            let taken = provider.id.clone();
+            candidates.retain(|candidate| candidate.id != taken);
         }
 
+        Ok(best)
+    }
+
+    /// Pick one of the client's dedicated numbers with capacity left today,
+    /// preferring the one the recipient is pinned to, and on a retry one that
+    /// hasn't failed the job yet. Routing rules don't apply: the client has
+    /// contracted for these SIMs.
+    async fn find_dedicated_provider(
+        app_state: &Arc<AppState>,
+        message: &Message,
+        job: &Job,
+        numbers: &[DedicatedNumber],
+    ) -> Result<Option<(Provider, DedicatedNumber)>> {
+        let provider_ids: Vec<&str> = numbers
+            .iter()
+            .map(|number| number.provider_id.as_str())
+            .collect();
+        let providers: Vec<Provider> = app_state
+            .database
+            .collection::<Provider>("providers")
+            .find(mongodb::bson::doc! {"id": {"$in": &provider_ids}}, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to query providers: {}", e),
+            })?
+            .try_collect()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch provider: {}", e),
+            })?;
+
+        // Numbers that already failed the job go last rather than not at all
+        let mut ordered = NumberPool::sticky_order(numbers, message.recipient.as_str());
+        ordered.sort_by_key(|number| job.attempted_provider_ids.contains(&number.provider_id));
+        for number in ordered {
+            let Some(provider) = providers
+                .iter()
+                .find(|provider| provider.id == number.provider_id)
+            else {
+                continue;
+            };
+            if provider.is_available()
+                && provider.messages_sent_today < number.daily_capacity
+                && provider.accepts_recipient(&message.recipient)
+            {
+                return Ok(Some((provider.clone(), number)));
+            }
+        }
         Ok(None)
     }
 
+    /// Evaluate enabled routing rules against a message
+    async fn routing_decision(
+        app_state: &Arc<AppState>,
+        message: &Message,
+    ) -> Result<RoutingDecision> {
+        let rules = app_state.routing_rules.active_rules().await?;
+        if rules.is_empty() {
+            return Ok(RoutingDecision::default());
+        }
+
+        // Only look the client up when a rule actually tests its tier
+        let client_tier = if rules.iter().any(|rule| rule.uses_client_tier()) {
+            app_state
+                .user_repository
+                .find_by_id(&message.client_id)
+                .await?
+                .map(|user| user.tier)
+                .unwrap_or_default()
+        } else {
+            ClientTier::default()
+        };
+
+        Ok(RoutingDecision::evaluate(
+            &rules,
+            &RoutingContext::for_message(message, client_tier),
+        ))
+    }
+
     /// Send FCM notification to provider device
     async fn send_fcm_notification(
-        app_state: &Arc<AppState>,
-        job: &Job,
+        fcm: &FcmDispatchBatcher,
         message: &Message,
         provider: &Provider,
     ) -> Result<()> {
@@ -268,57 +994,106 @@ impl JobProcessor {
             provider.id, message.id
         );
 
-        // Send SMS dispatch request via FCM
+        // Send SMS dispatch request via FCM, batched with others for this provider
+        let dispatch = SmsDispatch {
+            message_id: message.id.clone(),
+            recipient: message.recipient.as_str().to_string(),
+            content: message.content.clone(),
+            priority: format!("{:?}", message.priority),
+        };
+        if let Err(e) = fcm.dispatch(fcm_token, dispatch).await {
+            error!("Failed to send FCM dispatch request: {}", e);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Record the current attempt as dispatching; false if it already was,
+    /// i.e. an earlier pickup of this attempt may have reached the provider
+    async fn mark_dispatching(app_state: &Arc<AppState>, job: &mut Job) -> Result<bool> {
+        let marker = job.dispatch_marker_for(&app_state.config.instance.id);
+        let marker_doc =
+            mongodb::bson::to_document(&marker).map_err(|e| PeerPowerError::Internal {
+                message: format!("Failed to serialize dispatch marker: {}", e),
+            })?;
+
         let result = app_state
-            .fcm_service
-            .send_sms_dispatch_request(
-                fcm_token,
-                &message.id,
-                message.recipient.as_str(),
-                &message.content,
-                &format!("{:?}", message.priority),
+            .database
+            .collection::<Job>("jobs")
+            .update_one(
+                mongodb::bson::doc! {
+                    "id": &job.id,
+                    "dispatch_marker.attempt_token": {"$ne": &job.attempt_token},
+                },
+                mongodb::bson::doc! {"$set": {"dispatch_marker": marker_doc}},
+                None,
             )
-            .await;
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to mark job dispatching: {}", e),
+            })?;
 
-        match result {
-            Ok(response) => {
-                info!("FCM dispatch request sent successfully: {}", response);
-                Ok(())
-            }
-            Err(e) => {
-                error!("Failed to send FCM dispatch request: {}", e);
-                Err(e)
-            }
+        if result.modified_count == 1 {
+            job.dispatch_marker = Some(marker); // kept when the job is saved
+            return Ok(true);
         }
+        Ok(false)
     }
 
-    /// Re-queue a job for retry
-    async fn requeue_job(app_state: &Arc<AppState>, job: &Job) -> Result<()> {
-        // Add delay before retrying (exponential backoff)
-        let delay_seconds = 2_u64.pow(job.retry_count.min(6)); // Max 64 seconds delay
+    /// Fail the current attempt: re-queue with backoff if the job retry
+    /// policy allows another for `class`, otherwise hold the job for an admin
+    async fn fail_attempt(
+        app_state: &Arc<AppState>,
+        message: &mut Message,
+        job: &mut Job,
+        error: String,
+        class: RetryClass,
+        reason: &'static str,
+    ) -> Result<()> {
+        message.mark_failed(error.clone());
+        job.mark_failed(error);
 
-        tokio::spawn({
-            let app_state = app_state.clone();
-            let job = job.clone();
-            async move {
-                sleep(Duration::from_secs(delay_seconds)).await;
+        if job.can_retry(app_state.config.retries.jobs.max_attempts_for(class)) {
+            job.increment_retry();
+            message.increment_retry(); // back to pending, or the retry is skipped
+            Self::requeue_job(app_state, job, class, reason).await
+        } else {
+            app_state.dead_letters.capture(job).await?;
+            app_state
+                .events
+                .publish(PlatformEvent::job_failed(job, message))
+                .await;
+            Ok(())
+        }
+    }
 
-                let job_data = match serde_json::to_string(&job) {
-                    Ok(data) => data,
-                    Err(e) => {
-                        error!("Failed to serialize job for requeue: {}", e);
-                        return;
-                    }
-                };
+    /// Re-queue a job for retry at its original priority. Without Redis it is
+    /// left to the MongoDB poll, which picks it up once its claim expires.
+    async fn requeue_job(
+        app_state: &Arc<AppState>,
+        job: &Job,
+        class: RetryClass,
+        reason: &'static str,
+    ) -> Result<()> {
+        metrics::counter!("job_retries_total", "reason" => reason).increment(1);
 
-                let queue_key = "jobs:queue:priority:0"; // Lower priority for retries
-                if let Err(e) = app_state.redis.lpush(&queue_key, &job_data).await {
-                    error!("Failed to requeue job: {}", e);
-                }
-            }
-        });
+        // Jobs that never got a provider wait as long as a first retry
+        let delay = app_state
+            .config
+            .retries
+            .jobs
+            .delay(job.retry_count + 1, class);
 
-        Ok(())
+        match app_state.job_queue.requeue(job, delay).await {
+            Err(e) if e.is_redis_unavailable() => {
+                warn!(
+                    "Failed to re-queue job {}, leaving it to the MongoDB poll: {}",
+                    job.id, e
+                );
+                Ok(())
+            }
+            result => result,
+        }
     }
 
     /// Update message, job, and provider in database
@@ -328,25 +1103,39 @@ impl JobProcessor {
         job: &Job,
         provider: &Provider,
     ) -> Result<()> {
-        let messages_collection = app_state.database.collection::<Message>("messages");
-        let jobs_collection = app_state.database.collection::<Job>("jobs");
         let providers_collection = app_state.database.collection::<Provider>("providers");
 
-        // Update message
-        messages_collection
-            .replace_one(mongodb::bson::doc! {"id": &message.id}, message, None)
+        // Update message, unless the client cancelled it while it was being dispatched
+        let saved = app_state
+            .database
+            .collection::<Message>("messages")
+            .replace_one(
+                mongodb::bson::doc! {
+                    "id": &message.id,
+                    "status": {"$ne": format!("{:?}", MessageStatus::Cancelled)},
+                },
+                message,
+                None,
+            )
             .await
             .map_err(|e| PeerPowerError::Database {
                 message: format!("Failed to update message: {}", e),
             })?;
 
         // Update job
-        jobs_collection
-            .replace_one(mongodb::bson::doc! {"id": &job.id}, job, None)
-            .await
-            .map_err(|e| PeerPowerError::Database {
-                message: format!("Failed to update job: {}", e),
-            })?;
+        app_state.job_repository.update(job).await?;
+
+        // It reached the device anyway, so the device has to drop it
+        if saved.matched_count == 0 && message.status == MessageStatus::Sent {
+            warn!(
+                "Message {} was cancelled while being dispatched",
+                message.id
+            );
+            app_state
+                .cancellations
+                .cancel_late_dispatch(message, provider)
+                .await?;
+        }
 
         // Update provider
         providers_collection
@@ -355,6 +1144,10 @@ impl JobProcessor {
             .map_err(|e| PeerPowerError::Database {
                 message: format!("Failed to update provider: {}", e),
             })?;
+        // The assignment is stored either way; the cache catches up on the next heartbeat
+        if let Err(e) = app_state.provider_cache.store(provider).await {
+            warn!("Failed to cache provider {}: {}", provider.id, e);
+        }
 
         Ok(())
     }
@@ -365,26 +1158,8 @@ impl JobProcessor {
         message: &Message,
         job: &Job,
     ) -> Result<()> {
-        let messages_collection = app_state.database.collection::<Message>("messages");
-        let jobs_collection = app_state.database.collection::<Job>("jobs");
-
-        // Update message
-        messages_collection
-            .replace_one(mongodb::bson::doc! {"id": &message.id}, message, None)
-            .await
-            .map_err(|e| PeerPowerError::Database {
-                message: format!("Failed to update message: {}", e),
-            })?;
-
-        // Update job
-        jobs_collection
-            .replace_one(mongodb::bson::doc! {"id": &job.id}, job, None)
-            .await
-            .map_err(|e| PeerPowerError::Database {
-                message: format!("Failed to update job: {}", e),
-            })?;
-
-        Ok(())
+        app_state.message_repository.update(message).await?;
+        app_state.job_repository.update(job).await
     }
 
     /// Cleanup expired jobs
@@ -400,10 +1175,29 @@ impl JobProcessor {
         }
     }
 
+    /// Keep the backlog gauges current even when nothing else reads them
+    async fn metrics_loop(app_state: Arc<AppState>) {
+        let mut interval = interval(Duration::from_secs(METRICS_REFRESH_SECONDS));
+
+        loop {
+            interval.tick().await;
+
+            // Sets the per-carrier, per-priority and delayed depth gauges
+            if let Err(e) = app_state.job_queue.depth().await {
+                warn!("Failed to read job queue depth: {}", e);
+            }
+            match app_state.dead_letters.pending_count().await {
+                Ok(pending) => metrics::gauge!("dead_letter_jobs_pending").set(pending as f64),
+                Err(e) => warn!("Failed to count dead-letter jobs: {}", e),
+            }
+        }
+    }
+
     /// Remove expired jobs from the database
     async fn cleanup_expired_jobs(app_state: &Arc<AppState>) -> Result<()> {
         let jobs_collection = app_state.database.collection::<Job>("jobs");
-        let cutoff_time = chrono::Utc::now() - chrono::Duration::hours(24); // 24 hour timeout
+        // 24 hour timeout
+        let cutoff_time = stored_timestamp(chrono::Utc::now() - chrono::Duration::hours(24));
 
         let result = jobs_collection
             .delete_many(
@@ -424,7 +1218,340 @@ impl JobProcessor {
 
         Ok(())
     }
+
+    async fn ack_deadline_loop(app_state: Arc<AppState>) {
+        let mut interval = interval(Duration::from_secs(ACK_SWEEP_INTERVAL_SECONDS));
+
+        loop {
+            interval.tick().await;
+
+            if let Err(e) = Self::redispatch_unacknowledged(&app_state).await {
+                error!("Error re-dispatching unacknowledged jobs: {}", e);
+            }
+        }
+    }
+
+    /// Dispatch again the jobs whose device never acknowledged receipt,
+    /// presumably because FCM dropped the message; one instance at a time
+    async fn redispatch_unacknowledged(app_state: &Arc<AppState>) -> Result<()> {
+        if !app_state
+            .redis
+            .acquire_lock(ACK_SWEEP_LOCK_KEY, ACK_SWEEP_INTERVAL_SECONDS as usize * 2)
+            .await?
+        {
+            return Ok(());
+        }
+
+        let result = Self::redispatch_overdue(app_state).await;
+        app_state.redis.release_lock(ACK_SWEEP_LOCK_KEY).await?;
+        result
+    }
+
+    async fn redispatch_overdue(app_state: &Arc<AppState>) -> Result<()> {
+        let now = stored_timestamp(crate::shared::utils::now());
+        let overdue: Vec<Job> = app_state
+            .database
+            .collection::<Job>("jobs")
+            .find(
+                mongodb::bson::doc! {
+                    "status": format!("{:?}", JobStatus::Dispatched),
+                    "ack_deadline": {"$lt": now},
+                },
+                mongodb::options::FindOptions::builder()
+                    .limit(ACK_SWEEP_BATCH_SIZE)
+                    .build(),
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to query unacknowledged jobs: {}", e),
+            })?
+            .try_collect()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read unacknowledged jobs: {}", e),
+            })?;
+
+        for job in overdue {
+            let job_id = job.id.clone();
+            if let Err(e) = Self::redispatch(app_state, job).await {
+                warn!("Failed to re-dispatch unacknowledged job {}: {}", job_id, e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Take an unacknowledged attempt back from its provider and retry it,
+    /// on whichever provider is then picked
+    async fn redispatch(app_state: &Arc<AppState>, mut job: Job) -> Result<()> {
+        let messages_collection = app_state.database.collection::<Message>("messages");
+        let jobs_collection = app_state.database.collection::<Job>("jobs");
+        let message = app_state
+            .message_repository
+            .find_by_id(&job.message_id)
+            .await?;
+
+        // Confirmed, failed or being cancelled since: the provider did answer
+        let Some(mut message) = message.filter(|message| {
+            message.status == MessageStatus::Sent
+                && message.cancellation.is_none()
+                && message.provider_id.as_deref() == Some(job.provider_id.as_str())
+        }) else {
+            job.ack_deadline = None;
+            jobs_collection
+                .replace_one(
+                    mongodb::bson::doc! {
+                        "id": &job.id,
+                        "status": format!("{:?}", JobStatus::Dispatched),
+                    },
+                    &job,
+                    None,
+                )
+                .await
+                .map_err(|e| PeerPowerError::Database {
+                    message: format!("Failed to update job: {}", e),
+                })?;
+            return Ok(());
+        };
+
+        let provider_id = job.provider_id.clone();
+        let error = "Provider never acknowledged the dispatch".to_string();
+        message.mark_failed(error.clone());
+        job.mark_failed(error);
+        let class = RetryClass::Transient;
+        let retry = job.can_retry(app_state.config.retries.jobs.max_attempts_for(class));
+        if retry {
+            job.increment_retry();
+            message.increment_retry();
+        }
+
+        // Only if the provider still hasn't confirmed it meanwhile
+        let saved = messages_collection
+            .replace_one(
+                mongodb::bson::doc! {
+                    "id": &message.id,
+                    "status": format!("{:?}", MessageStatus::Sent),
+                    "provider_id": &provider_id,
+                },
+                &message,
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to update message: {}", e),
+            })?;
+        if saved.matched_count == 0 {
+            return Ok(());
+        }
+        app_state.job_repository.update(&job).await?;
+
+        warn!(
+            "Job {} was never acknowledged by provider {}; {}",
+            job.id,
+            provider_id,
+            if retry { "re-dispatching" } else { "giving up" }
+        );
+        metrics::counter!("job_dispatch_unacknowledged_total").increment(1);
+
+        // Should the dispatch turn up late, the device drops it instead of sending it twice
+        let provider = app_state
+            .database
+            .collection::<Provider>("providers")
+            .find_one(mongodb::bson::doc! {"id": &provider_id}, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch provider: {}", e),
+            })?;
+        if let Some(fcm_token) = provider.and_then(|provider| provider.fcm_token) {
+            if let Err(e) = app_state
+                .fcm_service
+                .send_cancel_dispatch(&fcm_token, &message.id, "reassigned")
+                .await
+            {
+                warn!(
+                    "Failed to withdraw dispatch of {} from provider {}: {}",
+                    message.id, provider_id, e
+                );
+            }
+        }
+
+        if retry {
+            Self::requeue_job(app_state, &job, class, "dispatch_unacknowledged").await
+        } else {
+            app_state.dead_letters.capture(&job).await?;
+            app_state
+                .events
+                .publish(PlatformEvent::job_failed(&job, &message))
+                .await;
+            Ok(())
+        }
+    }
+
+    /// Periodically request carrier re-verification from providers
+    async fn carrier_reverification_loop(app_state: Arc<AppState>) {
+        let mut interval = interval(Duration::from_secs(3600)); // Every hour
+
+        loop {
+            interval.tick().await;
+
+            if let Err(e) = Self::request_carrier_reverifications(&app_state).await {
+                error!("Error requesting carrier re-verifications: {}", e);
+            }
+        }
+    }
+
+    /// Flag providers whose carrier hasn't been confirmed within the configured interval
+    async fn request_carrier_reverifications(app_state: &Arc<AppState>) -> Result<()> {
+        let max_age_days = app_state.config.providers.carrier_reverify_interval_days;
+        let cutoff = stored_timestamp(chrono::Utc::now() - chrono::Duration::days(max_age_days));
+        let providers_collection = app_state.database.collection::<Provider>("providers");
+
+        let mut cursor = providers_collection
+            .find(
+                mongodb::bson::doc! {
+                    "carrier_reverification_required": {"$ne": true},
+                    "$or": [
+                        {"carrier_verified_at": {"$lt": &cutoff}},
+                        {"carrier_verified_at": null, "created_at": {"$lt": &cutoff}},
+                    ],
+                },
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to query providers for re-verification: {}", e),
+            })?;
+
+        let mut flagged = 0;
+        let mut notify = Vec::new();
+        while let Some(provider) = cursor
+            .try_next()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch provider: {}", e),
+            })?
+        {
+            if !provider.is_carrier_verification_stale(max_age_days) {
+                continue;
+            }
+
+            providers_collection
+                .update_one(
+                    mongodb::bson::doc! {"id": &provider.id},
+                    mongodb::bson::doc! {
+                        "$set": {
+                            "carrier_reverification_required": true,
+                            "updated_at": stored_timestamp(chrono::Utc::now()),
+                        }
+                    },
+                    None,
+                )
+                .await
+                .map_err(|e| PeerPowerError::Database {
+                    message: format!("Failed to flag provider for re-verification: {}", e),
+                })?;
+
+            if let Some(fcm_token) = provider.fcm_token.clone() {
+                notify.push((provider.id.clone(), fcm_token));
+            }
+            flagged += 1;
+        }
+
+        // One multicast for every flagged provider rather than a request each
+        if !notify.is_empty() {
+            let (provider_ids, fcm_tokens): (Vec<String>, Vec<String>) = notify.into_iter().unzip();
+            match app_state
+                .fcm_service
+                .send_provider_status_multicast(&fcm_tokens, "carrier_reverification_required")
+                .await
+            {
+                Ok(results) => {
+                    let mut unregistered = Vec::new();
+                    for (provider_id, result) in provider_ids.iter().zip(results) {
+                        if result.is_success() {
+                            continue;
+                        }
+                        let error = result.error.unwrap_or_default();
+                        warn!(
+                            "Failed to request carrier re-verification from provider {}: {}",
+                            provider_id, error
+                        );
+                        if error == "NotRegistered" || error == "InvalidRegistration" {
+                            unregistered.push(result.fcm_token);
+                        }
+                    }
+                    Self::clear_fcm_tokens(app_state, &unregistered).await?;
+                }
+                Err(e) => warn!(
+                    "Failed to request carrier re-verification from {} providers: {}",
+                    provider_ids.len(),
+                    e
+                ),
+            }
+        }
+
+        if flagged > 0 {
+            info!("Requested carrier re-verification from {} providers", flagged);
+        }
+
+        Ok(())
+    }
+
+    /// Drop tokens FCM reports as no longer registered, so later pushes skip
+    /// those providers instead of failing on them again
+    async fn clear_fcm_tokens(app_state: &Arc<AppState>, fcm_tokens: &[String]) -> Result<()> {
+        if fcm_tokens.is_empty() {
+            return Ok(());
+        }
+
+        let cleared = app_state
+            .database
+            .collection::<Provider>("providers")
+            .update_many(
+                mongodb::bson::doc! {"fcm_token": {"$in": fcm_tokens}},
+                mongodb::bson::doc! {"$set": {"fcm_token": null}},
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to clear unregistered FCM tokens: {}", e),
+            })?;
+
+        info!("Cleared {} unregistered FCM tokens", cleared.modified_count);
+        Ok(())
+    }
+}
+
+/// The update claiming a job for `instance_id`
+fn claim(instance_id: &str, at: chrono::DateTime<chrono::Utc>) -> mongodb::bson::Document {
+    mongodb::bson::doc! {"$set": {
+        "claimed_by": instance_id,
+        "claimed_at": stored_timestamp(at),
+    }}
+}
+
+/// Seconds from `since` until now, for latency histograms
+fn elapsed_seconds(since: chrono::DateTime<chrono::Utc>) -> f64 {
+    (crate::shared::utils::now() - since)
+        .num_milliseconds()
+        .max(0) as f64
+        / 1000.0
 }
 
 /// Extension trait for imports in other modules
 use futures::stream::TryStreamExt;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::test_support::read_back;
+
+    #[test]
+    fn test_claimed_job_reads_back() {
+        let job = Job::new("message-1".to_string(), "provider-1".to_string());
+        let now = crate::shared::utils::now();
+
+        let claimed = read_back(&job, &claim("instance-1", now));
+        assert_eq!(claimed.claimed_by.as_deref(), Some("instance-1"));
+        assert_eq!(claimed.claimed_at, Some(now));
+    }
+}
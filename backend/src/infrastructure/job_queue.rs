@@ -0,0 +1,456 @@
+use serde::Serialize;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::domain::entities::{Job, Message};
+use crate::infrastructure::database::RedisConnection;
+use crate::shared::types::{Carrier, DeploymentCohort};
+use crate::shared::{PeerPowerError, Result};
+
+/// Message priority scores, highest first (see `Message::get_priority_score`)
+const PRIORITY_SCORES: [u32; 4] = [100, 75, 50, 25];
+
+/// Jobs waiting out a retry backoff, scored by the time they become ready
+const DELAYED_QUEUE_KEY: &str = "jobs:queue:delayed";
+
+/// List pushed to on every enqueue; idle processors block on it instead of polling
+const WAKE_KEY: &str = "jobs:queue:wake";
+
+/// Wake-ups kept pending at most; more would only cause empty dequeues
+const MAX_PENDING_WAKEUPS: isize = 1000;
+
+/// Queued jobs inspected per priority when looking for one that keeps client order
+const FIFO_SCAN_LIMIT: isize = 20;
+
+/// A job is never held back for an earlier retry longer than this
+const MAX_FIFO_HOLD_SECONDS: i64 = 120;
+
+/// Delayed jobs moved back into their priority queue per dequeue
+const PROMOTE_BATCH_SIZE: usize = 100;
+
+/// Queued jobs searched for the one being escalated
+const ESCALATE_SCAN_LIMIT: isize = 1000;
+
+/// How long a claim on a job outlives an instance that died processing it
+const CLAIM_TTL_SECONDS: usize = 300;
+
+/// ZCARD of every key passed, in order
+const COUNT_QUEUES_SCRIPT: &str = r#"
+local counts = {}
+for i, key in ipairs(KEYS) do
+    counts[i] = redis.call('ZCARD', key)
+end
+return counts
+"#;
+
+/// Jobs waiting for one recipient carrier, across cohorts and priorities
+#[derive(Debug, Clone, Serialize)]
+pub struct CarrierBacklog {
+    pub carrier: String,
+    pub queued: u64,
+}
+
+/// Redis-backed job queue.
+///
+/// Each recipient carrier and priority has its own sorted set (e.g.
+/// `jobs:queue:smart:75`) scored by the job's original enqueue time, so a
+/// retried job re-enters at its old position rather than behind newer work,
+/// and the dispatcher can leave a carrier's backlog alone while nobody is
+/// online to deliver it. Jobs queued before carriers were recorded on them
+/// stay in the unpartitioned `jobs:queue:priority:<score>` sets.
+///
+/// Jobs also carry a per-client sequence number; the dispatcher holds back a
+/// client's later jobs while an earlier one is waiting out its retry backoff,
+/// keeping each client's messages in approximate FIFO order.
+///
+/// Sorted sets can't be blocked on, so every enqueue also pushes to a wake-up
+/// list that idle processors `BRPOP`; new work is picked up as soon as it
+/// arrives rather than on the next poll.
+///
+/// Instances share the queues. A job is claimed by taking a lock on its id,
+/// held by the instance id, before it is removed from its queue; the lock is
+/// released once the job is processed, so a job that is queued twice (a
+/// retry racing an admin requeue) is never in flight on two workers at once.
+pub struct JobQueue {
+    redis: RedisConnection,
+    instance_id: String,
+}
+
+impl JobQueue {
+    pub fn new(redis: RedisConnection, instance_id: String) -> Self {
+        Self { redis, instance_id }
+    }
+
+    fn claim_key(job_id: &str) -> String {
+        format!("jobs:claim:{}", job_id)
+    }
+
+    fn queue_key(
+        cohort: DeploymentCohort,
+        carrier: Option<&Carrier>,
+        priority_score: u32,
+    ) -> String {
+        let cohort = match cohort {
+            DeploymentCohort::Stable => "",
+            DeploymentCohort::Canary => "canary:",
+        };
+        match carrier {
+            Some(carrier) => format!("jobs:queue:{}{}:{}", cohort, carrier.key(), priority_score),
+            None => format!("jobs:queue:{}priority:{}", cohort, priority_score),
+        }
+    }
+
+    /// Metric label for a priority score
+    fn priority_label(priority_score: u32) -> &'static str {
+        match priority_score {
+            100 => "urgent",
+            75 => "high",
+            50 => "normal",
+            _ => "low",
+        }
+    }
+
+    fn job_queue_key(job: &Job) -> String {
+        Self::queue_key(job.cohort, job.carrier.as_ref(), job.priority_score)
+    }
+
+    fn retrying_key(client_id: &str) -> String {
+        format!("jobs:retrying:{}", client_id)
+    }
+
+    fn serialize(job: &Job) -> Result<String> {
+        serde_json::to_string(job).map_err(|e| PeerPowerError::Internal {
+            message: format!("Failed to serialize job: {}", e),
+        })
+    }
+
+    /// Fix a new job's priority and per-client sequence before it is stored.
+    /// Without Redis the job gets no sequence (0): it is never held behind
+    /// the client's retries, nor holds their later jobs back.
+    pub async fn assign_order(&self, job: &mut Job, message: &Message) -> Result<()> {
+        let sequence = match self
+            .redis
+            .increment(&format!("jobs:sequence:{}", message.client_id))
+            .await
+        {
+            Ok(sequence) => sequence as u64,
+            Err(e) if e.is_redis_unavailable() => {
+                warn!(
+                    "Failed to sequence job {}, queuing it unordered: {}",
+                    job.id, e
+                );
+                0
+            }
+            Err(e) => return Err(e),
+        };
+
+        job.client_id = message.client_id.clone();
+        job.priority_score = message.get_priority_score();
+        job.sequence = sequence;
+        job.enqueued_at = Some(crate::shared::utils::now());
+        job.cohort = message.cohort;
+        job.carrier = Some(message.recipient_carrier.clone());
+
+        Ok(())
+    }
+
+    pub async fn enqueue(&self, job: &Job) -> Result<()> {
+        let job_data = Self::serialize(job)?;
+        self.redis
+            .zadd(&Self::job_queue_key(job), &job_data, Self::order_score(job))
+            .await?;
+
+        self.redis.lpush(WAKE_KEY, &job.id).await?;
+        self.redis.ltrim(WAKE_KEY, 0, MAX_PENDING_WAKEUPS - 1).await?;
+
+        Ok(())
+    }
+
+    /// Move a waiting job up one priority, keeping its place in line; the
+    /// new score, or `None` if it is already urgent or no longer queued
+    pub async fn escalate(&self, job: &Job) -> Result<Option<u32>> {
+        let Some(priority_score) = PRIORITY_SCORES
+            .iter()
+            .rev()
+            .copied()
+            .find(|score| *score > job.priority_score)
+        else {
+            return Ok(None);
+        };
+
+        let queue_key = Self::job_queue_key(job);
+        let queued = self
+            .redis
+            .zrange(&queue_key, 0, ESCALATE_SCAN_LIMIT - 1)
+            .await?;
+        for job_data in queued {
+            let mut queued_job = match serde_json::from_str::<Job>(&job_data) {
+                Ok(queued_job) if queued_job.id == job.id => queued_job,
+                _ => continue,
+            };
+            // Claimed by a dispatcher meanwhile
+            if self.redis.zrem(&queue_key, &job_data).await? == 0 {
+                return Ok(None);
+            }
+
+            queued_job.priority_score = priority_score;
+            self.enqueue(&queued_job).await?;
+            return Ok(Some(priority_score));
+        }
+
+        Ok(None)
+    }
+
+    /// Wait up to `timeout_seconds` for a job to be enqueued; true if one was
+    pub async fn wait_for_work(&self, timeout_seconds: usize) -> Result<bool> {
+        Ok(self
+            .redis
+            .brpop(&[WAKE_KEY], timeout_seconds)
+            .await?
+            .is_some())
+    }
+
+    /// Put a job back after `delay`, keeping its priority and position
+    pub async fn requeue(&self, job: &Job, delay: Duration) -> Result<()> {
+        let ready_at =
+            crate::shared::utils::now() + chrono::Duration::milliseconds(delay.as_millis() as i64);
+        let job_data = Self::serialize(job)?;
+
+        if !job.client_id.is_empty() && job.sequence > 0 {
+            self.redis
+                .zadd(
+                    &Self::retrying_key(&job.client_id),
+                    &job.sequence.to_string(),
+                    job.sequence as f64,
+                )
+                .await?;
+        }
+
+        self.redis
+            .zadd(
+                DELAYED_QUEUE_KEY,
+                &job_data,
+                ready_at.timestamp_millis() as f64,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Claim the next job from the given cohorts' queues for `carriers`,
+    /// highest priority first and in the order given within a priority.
+    /// Carriers left out are not touched; with none, nothing is claimed.
+    pub async fn dequeue(
+        &self,
+        cohorts: &[DeploymentCohort],
+        carriers: &[Carrier],
+    ) -> Result<Option<Job>> {
+        self.promote_delayed().await?;
+        if carriers.is_empty() {
+            return Ok(None);
+        }
+
+        for priority_score in PRIORITY_SCORES {
+            for cohort in cohorts {
+                let queues = carriers
+                    .iter()
+                    .map(Some)
+                    .chain(std::iter::once(None)) // unpartitioned, queued before carriers were recorded
+                    .map(|carrier| Self::queue_key(*cohort, carrier, priority_score));
+                for queue_key in queues {
+                    if let Some(job) = self.claim_from(&queue_key).await? {
+                        return Ok(Some(job));
+                    }
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Jobs waiting in every queue, plus those backing off
+    pub async fn depth(&self) -> Result<u64> {
+        let mut depth = self.redis.zcard(DELAYED_QUEUE_KEY).await?;
+        metrics::gauge!("job_queue_delayed").set(depth as f64);
+        for backlog in self.depth_by_carrier().await? {
+            depth += backlog.queued;
+        }
+        Ok(depth)
+    }
+
+    /// Jobs waiting per recipient carrier (not counting those backing off);
+    /// jobs queued before carriers were recorded are reported as "unassigned"
+    pub async fn depth_by_carrier(&self) -> Result<Vec<CarrierBacklog>> {
+        let carriers: Vec<Option<&Carrier>> = Carrier::ALL.iter().map(Some).chain([None]).collect();
+        let keys: Vec<String> = carriers
+            .iter()
+            .flat_map(|carrier| {
+                PRIORITY_SCORES.into_iter().flat_map(move |priority_score| {
+                    [DeploymentCohort::Stable, DeploymentCohort::Canary]
+                        .map(|cohort| Self::queue_key(cohort, *carrier, priority_score))
+                })
+            })
+            .collect();
+        let key_refs: Vec<&str> = keys.iter().map(String::as_str).collect();
+
+        // One round trip for every queue rather than a ZCARD each
+        let counts = self
+            .redis
+            .eval_ints(COUNT_QUEUES_SCRIPT, &key_refs, &[])
+            .await?;
+        let per_carrier = keys.len() / carriers.len();
+
+        let backlogs = carriers
+            .into_iter()
+            .zip(counts.chunks(per_carrier))
+            .map(|(carrier, counts)| {
+                let carrier = carrier
+                    .map(Carrier::key)
+                    .unwrap_or_else(|| "unassigned".to_string());
+                // Both cohorts' counts for each priority, in `PRIORITY_SCORES` order
+                for (priority_score, cohorts) in PRIORITY_SCORES.iter().zip(counts.chunks(2)) {
+                    let queued = cohorts.iter().sum::<i64>().max(0);
+                    metrics::gauge!(
+                        "job_queue_depth",
+                        "carrier" => carrier.clone(),
+                        "priority" => Self::priority_label(*priority_score)
+                    )
+                    .set(queued as f64);
+                }
+                let queued = counts.iter().sum::<i64>().max(0) as u64;
+                CarrierBacklog { carrier, queued }
+            })
+            .collect();
+        Ok(backlogs)
+    }
+
+    async fn claim_from(&self, queue_key: &str) -> Result<Option<Job>> {
+        let candidates = self.redis.zrange(queue_key, 0, FIFO_SCAN_LIMIT - 1).await?;
+
+        for job_data in candidates {
+            let job = match serde_json::from_str::<Job>(&job_data) {
+                Ok(job) => job,
+                Err(e) => {
+                    warn!("Dropping undecodable job from {}: {}", queue_key, e);
+                    self.redis.zrem(queue_key, &job_data).await?;
+                    continue;
+                }
+            };
+
+            if self.is_held_for_earlier_retry(&job).await? {
+                continue;
+            }
+
+            let claim_key = Self::claim_key(&job.id);
+            if !self
+                .redis
+                .acquire_owned_lock(&claim_key, &self.instance_id, CLAIM_TTL_SECONDS)
+                .await?
+            {
+                continue; // in flight elsewhere; leave this copy queued
+            }
+
+            // Another instance may have claimed it between ZRANGE and ZREM
+            if self.redis.zrem(queue_key, &job_data).await? == 1 {
+                let mut job = job;
+                job.claimed_by = Some(self.instance_id.clone());
+                job.claimed_at = Some(crate::shared::utils::now());
+                return Ok(Some(job));
+            }
+            self.redis
+                .release_owned_lock(&claim_key, &self.instance_id)
+                .await?;
+        }
+
+        Ok(None)
+    }
+
+    /// Give up this instance's claim on a job once it has been processed
+    pub async fn release(&self, job_id: &str) -> Result<()> {
+        self.redis
+            .release_owned_lock(&Self::claim_key(job_id), &self.instance_id)
+            .await?;
+        Ok(())
+    }
+
+    /// Move jobs whose backoff has elapsed back into their priority queue
+    async fn promote_delayed(&self) -> Result<()> {
+        let now = crate::shared::utils::now().timestamp_millis() as f64;
+        let ready = self
+            .redis
+            .zrangebyscore(DELAYED_QUEUE_KEY, now, PROMOTE_BATCH_SIZE)
+            .await?;
+
+        for job_data in ready {
+            if self.redis.zrem(DELAYED_QUEUE_KEY, &job_data).await? == 0 {
+                continue; // promoted by another instance
+            }
+
+            let job = match serde_json::from_str::<Job>(&job_data) {
+                Ok(job) => job,
+                Err(e) => {
+                    warn!("Dropping undecodable delayed job: {}", e);
+                    continue;
+                }
+            };
+
+            self.redis
+                .zadd(
+                    &Self::job_queue_key(&job),
+                    &job_data,
+                    Self::order_score(&job),
+                )
+                .await?;
+
+            if !job.client_id.is_empty() {
+                self.redis
+                    .zrem(
+                        &Self::retrying_key(&job.client_id),
+                        &job.sequence.to_string(),
+                    )
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Whether an earlier job from the same client is still backing off
+    async fn is_held_for_earlier_retry(&self, job: &Job) -> Result<bool> {
+        if job.client_id.is_empty() {
+            return Ok(false);
+        }
+
+        if let Some(enqueued_at) = job.enqueued_at {
+            let waited = crate::shared::utils::now() - enqueued_at;
+            if waited.num_seconds() >= MAX_FIFO_HOLD_SECONDS {
+                return Ok(false);
+            }
+        }
+
+        let earliest = self
+            .redis
+            .zrange(&Self::retrying_key(&job.client_id), 0, 0)
+            .await?
+            .first()
+            .and_then(|sequence| sequence.parse::<u64>().ok());
+
+        match earliest {
+            Some(sequence) if sequence < job.sequence => {
+                info!(
+                    "Holding job {} (sequence {}) behind retrying sequence {} for client {}",
+                    job.id, job.sequence, sequence, job.client_id
+                );
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Queue position: original enqueue time, so retries keep their place
+    fn order_score(job: &Job) -> f64 {
+        job.enqueued_at
+            .map(|at| at.timestamp_millis())
+            .unwrap_or_else(|| job.assigned_at.timestamp_millis()) as f64
+    }
+}
@@ -0,0 +1,261 @@
+use base64::Engine;
+use jsonwebtoken::{
+    decode, decode_header, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
+use rsa::pkcs1::DecodeRsaPrivateKey;
+use rsa::pkcs8::{DecodePrivateKey, DecodePublicKey, EncodePrivateKey, LineEnding};
+use rsa::traits::PublicKeyParts;
+use rsa::{RsaPrivateKey, RsaPublicKey};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use tracing::{info, warn};
+
+use crate::config::AuthConfig;
+use crate::domain::services::TokenAudience;
+use crate::shared::{PeerPowerError, Result};
+
+/// Public half of a signing key, as published at `/.well-known/jwks.json`
+#[derive(Debug, Clone, Serialize)]
+pub struct Jwk {
+    pub kty: &'static str,
+    #[serde(rename = "use")]
+    pub key_use: &'static str,
+    pub alg: &'static str,
+    pub kid: String,
+    pub n: String,
+    pub e: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JwkSet {
+    pub keys: Vec<Jwk>,
+}
+
+/// RS256 keys used to sign and verify access tokens.
+///
+/// Keys are loaded from `JWT_KEYS_DIR`: `<kid>.pem` holds a private key
+/// (PKCS#8 or PKCS#1) and `<kid>.pub.pem` a public key kept only so tokens
+/// signed by a retired key still verify. New tokens are signed with
+/// `JWT_ACTIVE_KID`, or the last private key by name.
+///
+/// To rotate: add the new private key, make it active, and keep the old
+/// key (or just its public half) until every token it signed has expired.
+pub struct JwtKeySet {
+    active_kid: String,
+    encoding_key: EncodingKey,
+    verifying_keys: HashMap<String, DecodingKey>,
+    legacy_hs256: Option<DecodingKey>,
+    jwks: JwkSet,
+    issuer: String,
+    leeway_seconds: u64,
+}
+
+impl JwtKeySet {
+    pub fn load(config: &AuthConfig, is_production: bool) -> Result<Self> {
+        let mut private_keys: Vec<(String, RsaPrivateKey)> = Vec::new();
+        let mut public_keys: Vec<(String, RsaPublicKey)> = Vec::new();
+
+        match &config.jwt_keys_dir {
+            Some(dir) => {
+                let entries =
+                    std::fs::read_dir(dir).map_err(|e| PeerPowerError::Configuration {
+                        message: format!("Failed to read JWT_KEYS_DIR {}: {}", dir, e),
+                    })?;
+                for entry in entries.flatten() {
+                    let file_name = entry.file_name().to_string_lossy().to_string();
+                    let Some(stem) = file_name.strip_suffix(".pem") else {
+                        continue;
+                    };
+                    let pem = std::fs::read_to_string(entry.path()).map_err(|e| {
+                        PeerPowerError::Configuration {
+                            message: format!("Failed to read JWT key {}: {}", file_name, e),
+                        }
+                    })?;
+
+                    match stem.strip_suffix(".pub") {
+                        Some(kid) => {
+                            public_keys.push((kid.to_string(), parse_public_key(&pem, kid)?))
+                        }
+                        None => {
+                            private_keys.push((stem.to_string(), parse_private_key(&pem, stem)?))
+                        }
+                    }
+                }
+            }
+            None if is_production => {
+                return Err(PeerPowerError::Configuration {
+                    message: "JWT_KEYS_DIR is required in production".to_string(),
+                });
+            }
+            None => {
+                warn!("JWT_KEYS_DIR not set; signing tokens with an ephemeral development key");
+                let key = RsaPrivateKey::new(&mut rand::thread_rng(), 2048).map_err(|e| {
+                    PeerPowerError::Internal {
+                        message: format!("Failed to generate development JWT key: {}", e),
+                    }
+                })?;
+                let kid = format!("dev-{}", &crate::shared::utils::generate_id()[..8]);
+                private_keys.push((kid, key));
+            }
+        }
+
+        private_keys.sort_by(|a, b| a.0.cmp(&b.0));
+        let active_kid = match &config.jwt_active_kid {
+            Some(kid) => kid.clone(),
+            None => private_keys
+                .last()
+                .map(|(kid, _)| kid.clone())
+                .ok_or_else(|| PeerPowerError::Configuration {
+                    message: "No JWT private keys found in JWT_KEYS_DIR".to_string(),
+                })?,
+        };
+        let (_, active_key) = private_keys
+            .iter()
+            .find(|(kid, _)| kid == &active_kid)
+            .ok_or_else(|| PeerPowerError::Configuration {
+                message: format!("JWT_ACTIVE_KID {} has no private key", active_kid),
+            })?;
+
+        let active_pem =
+            active_key
+                .to_pkcs8_pem(LineEnding::LF)
+                .map_err(|e| PeerPowerError::Internal {
+                    message: format!("Failed to encode JWT signing key: {}", e),
+                })?;
+        let encoding_key = EncodingKey::from_rsa_pem(active_pem.as_bytes()).map_err(|e| {
+            PeerPowerError::Configuration {
+                message: format!("Invalid JWT signing key {}: {}", active_kid, e),
+            }
+        })?;
+
+        let mut verifying_keys = HashMap::new();
+        let mut jwks = Vec::new();
+        let all_public = private_keys
+            .iter()
+            .map(|(kid, key)| (kid.clone(), key.to_public_key()))
+            .chain(public_keys);
+        for (kid, public_key) in all_public {
+            if verifying_keys.contains_key(&kid) {
+                continue;
+            }
+            let n = base64url(&public_key.n().to_bytes_be());
+            let e = base64url(&public_key.e().to_bytes_be());
+            let decoding_key = DecodingKey::from_rsa_components(&n, &e).map_err(|err| {
+                PeerPowerError::Configuration {
+                    message: format!("Invalid JWT verification key {}: {}", kid, err),
+                }
+            })?;
+            verifying_keys.insert(kid.clone(), decoding_key);
+            jwks.push(Jwk {
+                kty: "RSA",
+                key_use: "sig",
+                alg: "RS256",
+                kid,
+                n,
+                e,
+            });
+        }
+
+        // Tokens signed with the old shared secret stay valid until they expire
+        let legacy_hs256 = config
+            .jwt_secret
+            .as_ref()
+            .filter(|secret| !secret.is_empty())
+            .map(|secret| DecodingKey::from_secret(secret.as_bytes()));
+
+        info!(
+            "Loaded {} JWT verification keys (signing with {}, legacy HS256 {})",
+            verifying_keys.len(),
+            active_kid,
+            if legacy_hs256.is_some() {
+                "accepted"
+            } else {
+                "rejected"
+            }
+        );
+
+        Ok(Self {
+            active_kid,
+            encoding_key,
+            verifying_keys,
+            legacy_hs256,
+            jwks: JwkSet { keys: jwks },
+            issuer: config.jwt_issuer.clone(),
+            leeway_seconds: config.jwt_leeway_seconds,
+        })
+    }
+
+    /// Sign claims with the active key, tagging the token with its `kid`
+    pub fn sign<T: Serialize>(&self, claims: &T) -> Result<String> {
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some(self.active_kid.clone());
+        encode(&header, claims, &self.encoding_key).map_err(|e| PeerPowerError::Internal {
+            message: format!("Failed to sign token: {}", e),
+        })
+    }
+
+    /// Verify a token against the key named by its `kid`
+    pub fn verify<T: DeserializeOwned>(&self, token: &str) -> Result<T> {
+        let invalid = |reason: String| PeerPowerError::AuthenticationFailed {
+            reason: format!("Invalid token: {}", reason),
+        };
+
+        let header = decode_header(token).map_err(|e| invalid(e.to_string()))?;
+        let (key, algorithm) = match header.alg {
+            Algorithm::RS256 => {
+                let kid = header
+                    .kid
+                    .ok_or_else(|| invalid("missing kid".to_string()))?;
+                let key = self
+                    .verifying_keys
+                    .get(&kid)
+                    .ok_or_else(|| invalid(format!("unknown signing key {}", kid)))?;
+                (key, Algorithm::RS256)
+            }
+            Algorithm::HS256 => {
+                let key = self
+                    .legacy_hs256
+                    .as_ref()
+                    .ok_or_else(|| invalid("HS256 tokens are no longer accepted".to_string()))?;
+                (key, Algorithm::HS256)
+            }
+            other => return Err(invalid(format!("unsupported algorithm {:?}", other))),
+        };
+
+        // Tokens naming an audience must name one of our apps, and must have
+        // been issued by this deployment. Legacy HS256 tokens predate `iss`.
+        let mut validation = Validation::new(algorithm);
+        validation.set_audience(&TokenAudience::ALL.map(|audience| audience.as_str()));
+        validation.set_issuer(&[&self.issuer]);
+        if algorithm == Algorithm::RS256 {
+            validation.set_required_spec_claims(&["exp", "iss"]);
+        }
+        validation.leeway = self.leeway_seconds;
+        decode::<T>(token, key, &validation)
+            .map(|data| data.claims)
+            .map_err(|e| invalid(e.to_string()))
+    }
+
+    pub fn jwks(&self) -> &JwkSet {
+        &self.jwks
+    }
+}
+
+fn parse_private_key(pem: &str, kid: &str) -> Result<RsaPrivateKey> {
+    RsaPrivateKey::from_pkcs8_pem(pem)
+        .or_else(|_| RsaPrivateKey::from_pkcs1_pem(pem))
+        .map_err(|e| PeerPowerError::Configuration {
+            message: format!("Invalid RSA private key {}: {}", kid, e),
+        })
+}
+
+fn parse_public_key(pem: &str, kid: &str) -> Result<RsaPublicKey> {
+    RsaPublicKey::from_public_key_pem(pem).map_err(|e| PeerPowerError::Configuration {
+        message: format!("Invalid RSA public key {}: {}", kid, e),
+    })
+}
+
+fn base64url(bytes: &[u8]) -> String {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+}
@@ -0,0 +1,699 @@
+use chrono::{DateTime, Utc};
+use futures::stream::TryStreamExt;
+use mongodb::bson::{doc, Document};
+use mongodb::options::{FindOptions, UpdateOptions};
+use mongodb::{Collection, Cursor, Database};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use tracing::info;
+
+use crate::domain::entities::ledger::PLATFORM_FEES_ACCOUNT;
+use crate::domain::entities::{
+    LedgerAccountKind, LedgerEntryKind, LedgerTransaction, LedgerTransactionKind, Payout,
+    PayoutStatus, Provider,
+};
+use crate::shared::utils::stored_timestamp;
+use crate::shared::{Money, PeerPowerError, Result};
+
+/// What a run of [`Ledger::open_legacy_balances`] posted
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LegacyPostings {
+    pub providers_opened: u64,
+    pub payouts_posted: u64,
+    pub reversals_posted: u64,
+}
+
+/// Platform fees earned over a period, from the postings to the fees account
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RevenueReport {
+    pub since: Option<String>,
+    #[serde(with = "crate::shared::money::ppt")]
+    pub gross_charges: Money,
+    #[serde(with = "crate::shared::money::ppt")]
+    pub provider_earnings: Money,
+    #[serde(with = "crate::shared::money::ppt")]
+    pub platform_fees: Money,
+    pub by_kind: Vec<RevenueLine>,
+    pub by_day: Vec<RevenueDay>, // UTC days, oldest first
+}
+
+/// Revenue from one kind of charge (message deliveries, number rentals)
+#[derive(Debug, Clone, Serialize)]
+pub struct RevenueLine {
+    pub kind: LedgerTransactionKind,
+    pub transactions: u64,
+    #[serde(with = "crate::shared::money::ppt")]
+    pub gross_charges: Money,
+    #[serde(with = "crate::shared::money::ppt")]
+    pub provider_earnings: Money,
+    #[serde(with = "crate::shared::money::ppt")]
+    pub platform_fees: Money,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RevenueDay {
+    pub date: String,
+    #[serde(with = "crate::shared::money::ppt")]
+    pub platform_fees: Money,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AccountBalance {
+    #[serde(with = "crate::shared::money::ppt")]
+    pub balance: Money,
+    #[serde(with = "crate::shared::money::ppt")]
+    pub credits: Money,
+    #[serde(with = "crate::shared::money::ppt")]
+    pub debits: Money, // as a positive number
+}
+
+/// How many transactions of a kind touched an account, and what they posted to it
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PostingTotal {
+    pub transactions: u64,
+    pub amount: Money,
+}
+
+/// One line of an account statement
+#[derive(Debug, Clone, Serialize)]
+pub struct StatementLine {
+    pub transaction_id: String,
+    pub kind: LedgerTransactionKind,
+    pub reference: String,
+    pub entry: LedgerEntryKind,
+    #[serde(with = "crate::shared::money::ppt")]
+    pub amount: Money,
+    pub created_at: String,
+    pub tx_hash: Option<String>,
+}
+
+/// Double-entry record of every PPT movement between clients, providers
+/// and the platform. Balances and statements are computed from the
+/// postings rather than kept as counters on the accounts.
+pub struct Ledger {
+    transactions: Collection<LedgerTransaction>,
+    providers: Collection<Provider>,
+    payouts: Collection<Payout>,
+}
+
+impl Ledger {
+    pub fn new(database: Arc<Database>) -> Self {
+        Self {
+            transactions: database.collection("ledger"),
+            providers: database.collection("providers"),
+            payouts: database.collection("payouts"),
+        }
+    }
+
+    /// Record a transaction; returns false if one with the same kind and
+    /// reference was already posted
+    pub async fn post(&self, transaction: &LedgerTransaction) -> Result<bool> {
+        let (filter, update) = Self::posting(transaction)?;
+        let result = self
+            .transactions
+            .update_one(
+                filter,
+                update,
+                UpdateOptions::builder().upsert(true).build(),
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to post ledger transaction: {}", e),
+            })?;
+
+        let posted = result.upserted_id.is_some();
+        if posted {
+            Self::count_posted(&transaction.kind);
+        }
+        Ok(posted)
+    }
+
+    /// The upsert `post` makes, for a caller posting inside its own MongoDB
+    /// transaction: inserts `transaction` unless one with the same kind and
+    /// reference exists
+    pub fn posting(transaction: &LedgerTransaction) -> Result<(Document, Document)> {
+        if !transaction.is_balanced() {
+            return Err(PeerPowerError::Internal {
+                message: format!(
+                    "Unbalanced {:?} ledger transaction for {}",
+                    transaction.kind, transaction.reference
+                ),
+            });
+        }
+
+        let document =
+            mongodb::bson::to_document(transaction).map_err(|e| PeerPowerError::Internal {
+                message: format!("Failed to serialize ledger transaction: {}", e),
+            })?;
+        Ok((
+            doc! {
+                "kind": format!("{:?}", transaction.kind),
+                "reference": &transaction.reference,
+            },
+            doc! {"$setOnInsert": document},
+        ))
+    }
+
+    pub fn count_posted(kind: &LedgerTransactionKind) {
+        metrics::counter!("ledger_transactions_total", "kind" => format!("{:?}", kind))
+            .increment(1);
+    }
+
+    /// The transaction of a kind posted for `reference`, if any
+    pub async fn find(
+        &self,
+        kind: LedgerTransactionKind,
+        reference: &str,
+    ) -> Result<Option<LedgerTransaction>> {
+        self.transactions
+            .find_one(
+                doc! {"kind": format!("{:?}", kind), "reference": reference},
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch ledger transaction: {}", e),
+            })
+    }
+
+    /// Attach the on-chain transaction that settled a posted transaction
+    pub async fn record_tx_hash(
+        &self,
+        kind: LedgerTransactionKind,
+        reference: &str,
+        tx_hash: &str,
+    ) -> Result<()> {
+        self.transactions
+            .update_one(
+                doc! {"kind": format!("{:?}", kind), "reference": reference},
+                doc! {"$set": {"tx_hash": tx_hash}},
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to record transaction hash: {}", e),
+            })?;
+        Ok(())
+    }
+
+    pub async fn balance(
+        &self,
+        account_kind: LedgerAccountKind,
+        account_id: &str,
+    ) -> Result<AccountBalance> {
+        Ok(self
+            .aggregate_balances(account_kind, Some(account_id))
+            .await?
+            .remove(account_id)
+            .unwrap_or_default())
+    }
+
+    /// Balance of every account of a kind, keyed by account id
+    pub async fn balances(
+        &self,
+        account_kind: LedgerAccountKind,
+    ) -> Result<HashMap<String, AccountBalance>> {
+        self.aggregate_balances(account_kind, None).await
+    }
+
+    async fn aggregate_balances(
+        &self,
+        account_kind: LedgerAccountKind,
+        account_id: Option<&str>,
+    ) -> Result<HashMap<String, AccountBalance>> {
+        let mut account = doc! {"account_kind": format!("{:?}", account_kind)};
+        if let Some(account_id) = account_id {
+            account.insert("account_id", account_id);
+        }
+        let mut posting_filter = Document::new();
+        for (key, value) in &account {
+            posting_filter.insert(format!("postings.{}", key), value.clone());
+        }
+
+        let pipeline = vec![
+            doc! {"$match": {"postings": {"$elemMatch": account}}},
+            doc! {"$unwind": "$postings"},
+            doc! {"$match": posting_filter},
+            doc! {"$set": {"postings.amount": Money::micros_expr("$postings.amount")}},
+            doc! {
+                "$group": {
+                    "_id": "$postings.account_id",
+                    "balance": {"$sum": "$postings.amount"},
+                    "credits": {
+                        "$sum": {"$cond": [{"$gt": ["$postings.amount", 0]}, "$postings.amount", 0_i64]}
+                    },
+                    "debits": {
+                        "$sum": {"$cond": [{"$lt": ["$postings.amount", 0]}, "$postings.amount", 0_i64]}
+                    },
+                }
+            },
+        ];
+
+        let mut cursor = self
+            .transactions
+            .clone_with_type::<Document>()
+            .aggregate(pipeline, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to aggregate ledger balances: {}", e),
+            })?;
+
+        let mut balances = HashMap::new();
+        while let Some(doc) = cursor
+            .try_next()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read ledger balances: {}", e),
+            })?
+        {
+            let Ok(account_id) = doc.get_str("_id") else {
+                continue;
+            };
+            balances.insert(
+                account_id.to_string(),
+                AccountBalance {
+                    balance: Money::from_field(&doc, "balance"),
+                    credits: Money::from_field(&doc, "credits"),
+                    debits: -Money::from_field(&doc, "debits"),
+                },
+            );
+        }
+        Ok(balances)
+    }
+
+    /// Postings to an account, newest first
+    pub async fn statement(
+        &self,
+        account_kind: LedgerAccountKind,
+        account_id: &str,
+        page: u32,
+        limit: u32,
+    ) -> Result<Vec<StatementLine>> {
+        let find_options = FindOptions::builder()
+            .sort(doc! {"created_at": -1})
+            .skip(((page.max(1) - 1) * limit) as u64)
+            .limit(limit as i64)
+            .build();
+        let transactions: Vec<LedgerTransaction> = self
+            .transactions
+            .find(
+                doc! {
+                    "postings": {"$elemMatch": {
+                        "account_kind": format!("{:?}", account_kind),
+                        "account_id": account_id,
+                    }}
+                },
+                find_options,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch ledger transactions: {}", e),
+            })?
+            .try_collect()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read ledger transactions: {}", e),
+            })?;
+
+        Ok(transactions
+            .iter()
+            .flat_map(|transaction| {
+                transaction
+                    .postings_for(account_kind, account_id)
+                    .map(|posting| StatementLine {
+                        transaction_id: transaction.id.clone(),
+                        kind: transaction.kind,
+                        reference: transaction.reference.clone(),
+                        entry: posting.entry,
+                        amount: posting.amount,
+                        created_at: transaction.created_at.to_rfc3339(),
+                        tx_hash: transaction.tx_hash.clone(),
+                    })
+            })
+            .collect())
+    }
+
+    /// Sum of one kind of posting to an account since `since` (or ever)
+    pub async fn entry_total(
+        &self,
+        account_kind: LedgerAccountKind,
+        account_id: &str,
+        entry: LedgerEntryKind,
+        since: Option<DateTime<Utc>>,
+    ) -> Result<Money> {
+        let posting = doc! {
+            "account_kind": format!("{:?}", account_kind),
+            "account_id": account_id,
+            "entry": format!("{:?}", entry),
+        };
+        let created_at = since.map(|since| doc! {"$gte": stored_timestamp(since)});
+        self.posting_total(posting, created_at).await
+    }
+
+    /// Balance of an account from everything posted before `at`
+    pub async fn balance_before(
+        &self,
+        account_kind: LedgerAccountKind,
+        account_id: &str,
+        at: DateTime<Utc>,
+    ) -> Result<Money> {
+        let posting = doc! {
+            "account_kind": format!("{:?}", account_kind),
+            "account_id": account_id,
+        };
+        let created_at = doc! {"$lt": stored_timestamp(at)};
+        self.posting_total(posting, Some(created_at)).await
+    }
+
+    /// Transactions touching an account posted in `[from, to)`, oldest first
+    pub async fn transactions_between(
+        &self,
+        account_kind: LedgerAccountKind,
+        account_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Cursor<LedgerTransaction>> {
+        let find_options = FindOptions::builder().sort(doc! {"created_at": 1}).build();
+        self.transactions
+            .find(
+                doc! {
+                    "postings": {"$elemMatch": {
+                        "account_kind": format!("{:?}", account_kind),
+                        "account_id": account_id,
+                    }},
+                    "created_at": {
+                        "$gte": stored_timestamp(from),
+                        "$lt": stored_timestamp(to),
+                    },
+                },
+                find_options,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch ledger transactions: {}", e),
+            })
+    }
+
+    /// Postings of one entry kind to every provider account from transactions
+    /// of `kind`, keyed by provider id
+    pub async fn provider_totals(
+        &self,
+        kind: LedgerTransactionKind,
+        entry: LedgerEntryKind,
+    ) -> Result<HashMap<String, PostingTotal>> {
+        let posting = doc! {
+            "account_kind": format!("{:?}", LedgerAccountKind::Provider),
+            "entry": format!("{:?}", entry),
+        };
+        let mut posting_filter = Document::new();
+        for (key, value) in &posting {
+            posting_filter.insert(format!("postings.{}", key), value.clone());
+        }
+
+        let pipeline = vec![
+            doc! {"$match": {
+                "kind": format!("{:?}", kind),
+                "postings": {"$elemMatch": posting},
+            }},
+            doc! {"$unwind": "$postings"},
+            doc! {"$match": posting_filter},
+            doc! {
+                "$group": {
+                    "_id": "$postings.account_id",
+                    "transactions": {"$sum": 1},
+                    "amount": {"$sum": Money::micros_expr("$postings.amount")},
+                }
+            },
+        ];
+        let mut cursor = self
+            .transactions
+            .clone_with_type::<Document>()
+            .aggregate(pipeline, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to aggregate provider postings: {}", e),
+            })?;
+
+        let mut totals = HashMap::new();
+        while let Some(doc) = cursor
+            .try_next()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read provider postings: {}", e),
+            })?
+        {
+            let Ok(provider_id) = doc.get_str("_id") else {
+                continue;
+            };
+            totals.insert(
+                provider_id.to_string(),
+                PostingTotal {
+                    transactions: doc
+                        .get_i64("transactions")
+                        .or_else(|_| doc.get_i32("transactions").map(i64::from))
+                        .unwrap_or(0)
+                        .max(0) as u64,
+                    amount: Money::from_field(&doc, "amount"),
+                },
+            );
+        }
+        Ok(totals)
+    }
+
+    /// Sum of the postings matching `posting`, in transactions whose
+    /// `created_at` matches `created_at` if given
+    async fn posting_total(
+        &self,
+        posting: Document,
+        created_at: Option<Document>,
+    ) -> Result<Money> {
+        let mut filter = doc! {"postings": {"$elemMatch": posting.clone()}};
+        if let Some(created_at) = created_at {
+            filter.insert("created_at", created_at);
+        }
+        let mut posting_filter = Document::new();
+        for (key, value) in posting {
+            posting_filter.insert(format!("postings.{}", key), value);
+        }
+
+        let pipeline = vec![
+            doc! {"$match": filter},
+            doc! {"$unwind": "$postings"},
+            doc! {"$match": posting_filter},
+            doc! {"$group": {"_id": null, "total": {"$sum": Money::micros_expr("$postings.amount")}}},
+        ];
+        let mut cursor = self
+            .transactions
+            .clone_with_type::<Document>()
+            .aggregate(pipeline, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to aggregate ledger postings: {}", e),
+            })?;
+        let total = cursor
+            .try_next()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read ledger postings: {}", e),
+            })?
+            .map(|doc| Money::from_field(&doc, "total"))
+            .unwrap_or_default();
+        Ok(total)
+    }
+
+    /// Charges, provider shares and platform fees of every fee-bearing
+    /// transaction since `since` (or ever)
+    pub async fn revenue(&self, since: Option<DateTime<Utc>>) -> Result<RevenueReport> {
+        let mut filter = doc! {
+            "postings": {"$elemMatch": {
+                "account_kind": format!("{:?}", LedgerAccountKind::Platform),
+                "account_id": PLATFORM_FEES_ACCOUNT,
+            }}
+        };
+        let since = since.map(stored_timestamp);
+        if let Some(since) = &since {
+            filter.insert("created_at", doc! {"$gte": since});
+        }
+
+        let pipeline = vec![
+            doc! {"$match": filter},
+            doc! {"$unwind": "$postings"},
+            doc! {
+                "$group": {
+                    "_id": {
+                        "kind": "$kind",
+                        "date": {"$substrCP": ["$created_at", 0, 10]},
+                        "entry": "$postings.entry",
+                    },
+                    "amount": {"$sum": Money::micros_expr("$postings.amount")},
+                    "count": {"$sum": 1},
+                }
+            },
+        ];
+        let mut cursor = self
+            .transactions
+            .clone_with_type::<Document>()
+            .aggregate(pipeline, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to aggregate revenue: {}", e),
+            })?;
+
+        let mut by_kind: BTreeMap<String, RevenueLine> = BTreeMap::new();
+        let mut by_day: BTreeMap<String, Money> = BTreeMap::new();
+        while let Some(doc) = cursor
+            .try_next()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read revenue: {}", e),
+            })?
+        {
+            let Ok(key) = doc.get_document("_id") else {
+                continue;
+            };
+            let (Ok(kind_name), Ok(entry)) = (key.get_str("kind"), key.get_str("entry")) else {
+                continue;
+            };
+            let Ok(kind) = mongodb::bson::from_bson::<LedgerTransactionKind>(kind_name.into())
+            else {
+                continue;
+            };
+            let amount = Money::from_field(&doc, "amount");
+
+            let line = by_kind
+                .entry(kind_name.to_string())
+                .or_insert_with(|| RevenueLine {
+                    kind,
+                    transactions: 0,
+                    gross_charges: Money::ZERO,
+                    provider_earnings: Money::ZERO,
+                    platform_fees: Money::ZERO,
+                });
+            match entry {
+                "ClientCharge" => line.gross_charges -= amount,
+                "ProviderEarning" => line.provider_earnings += amount,
+                "PlatformFee" => {
+                    line.platform_fees += amount;
+                    line.transactions += doc
+                        .get_i64("count")
+                        .or_else(|_| doc.get_i32("count").map(i64::from))
+                        .unwrap_or(0)
+                        .max(0) as u64;
+                    *by_day
+                        .entry(key.get_str("date").unwrap_or_default().to_string())
+                        .or_default() += amount;
+                }
+                _ => {}
+            }
+        }
+
+        let by_kind: Vec<RevenueLine> = by_kind.into_values().collect();
+        Ok(RevenueReport {
+            since,
+            gross_charges: by_kind.iter().map(|line| line.gross_charges).sum(),
+            provider_earnings: by_kind.iter().map(|line| line.provider_earnings).sum(),
+            platform_fees: by_kind.iter().map(|line| line.platform_fees).sum(),
+            by_kind,
+            by_day: by_day
+                .into_iter()
+                .map(|(date, platform_fees)| RevenueDay {
+                    date,
+                    platform_fees,
+                })
+                .collect(),
+        })
+    }
+
+    /// Post opening balances for providers' pre-ledger `earnings_total`, and
+    /// any payout debits or failed-payout reversals missing from the ledger.
+    /// Safe to re-run: every posting is keyed by provider or payout id.
+    pub async fn open_legacy_balances(&self) -> Result<LegacyPostings> {
+        let mut cursor = self
+            .providers
+            .find(doc! {"earnings_total": {"$gt": 0}}, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch providers: {}", e),
+            })?;
+
+        let mut posted = LegacyPostings::default();
+        while let Some(provider) =
+            cursor
+                .try_next()
+                .await
+                .map_err(|e| PeerPowerError::Database {
+                    message: format!("Failed to read providers: {}", e),
+                })?
+        {
+            let transaction = LedgerTransaction::opening_balance(
+                &provider.id,
+                Money::from_ppt(provider.earnings_total),
+            );
+            if self.post(&transaction).await? {
+                posted.providers_opened += 1;
+            }
+        }
+
+        let mut cursor =
+            self.payouts
+                .find(doc! {}, None)
+                .await
+                .map_err(|e| PeerPowerError::Database {
+                    message: format!("Failed to fetch payouts: {}", e),
+                })?;
+        while let Some(payout) = cursor
+            .try_next()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read payouts: {}", e),
+            })?
+        {
+            let amount = payout.native_amount();
+            if self
+                .post(&LedgerTransaction::payout(
+                    &payout.id,
+                    &payout.provider_id,
+                    amount,
+                ))
+                .await?
+            {
+                posted.payouts_posted += 1;
+            }
+            if payout.status == PayoutStatus::Failed
+                && self
+                    .post(&LedgerTransaction::payout_reversal(
+                        &payout.id,
+                        &payout.provider_id,
+                        amount,
+                    ))
+                    .await?
+            {
+                posted.reversals_posted += 1;
+            }
+        }
+
+        info!(
+            "Posted {} opening balances, {} payouts and {} payout reversals to the ledger",
+            posted.providers_opened, posted.payouts_posted, posted.reversals_posted
+        );
+        Ok(posted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::test_support::read_back_from;
+
+    #[test]
+    fn test_posted_transaction_reads_back() {
+        let transaction = LedgerTransaction::top_up("topup-1", "client-1", Money::from_ppt(5.0));
+        let (filter, update) = Ledger::posting(&transaction).unwrap();
+
+        let posted: LedgerTransaction = read_back_from(filter, &update);
+        assert_eq!(posted.id, transaction.id);
+        assert_eq!(posted.created_at, transaction.created_at);
+        assert_eq!(posted.postings.len(), transaction.postings.len());
+    }
+}
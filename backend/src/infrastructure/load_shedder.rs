@@ -0,0 +1,355 @@
+use serde::Serialize;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::config::LoadSheddingConfig;
+use crate::infrastructure::database::{MongoDatabase, RedisConnection};
+use crate::infrastructure::job_queue::JobQueue;
+
+/// How expensive a route is, i.e. how early it is shed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteTier {
+    /// Sending, status, delivery reports, auth and provider liveness; never shed
+    Core,
+    /// Ordinary reads and writes; shed only when critical
+    Standard,
+    /// Analytics, exports, downloads and heavy admin reports; shed first
+    Expensive,
+}
+
+/// Current pressure on the backends
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoadLevel {
+    Normal,
+    Degraded,
+    Critical,
+}
+
+impl LoadLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LoadLevel::Normal => "normal",
+            LoadLevel::Degraded => "degraded",
+            LoadLevel::Critical => "critical",
+        }
+    }
+
+    pub fn sheds(&self, tier: RouteTier) -> bool {
+        match tier {
+            RouteTier::Core => false,
+            RouteTier::Standard => *self >= LoadLevel::Critical,
+            RouteTier::Expensive => *self >= LoadLevel::Degraded,
+        }
+    }
+
+    fn step_down(&self) -> LoadLevel {
+        match self {
+            LoadLevel::Critical => LoadLevel::Degraded,
+            _ => LoadLevel::Normal,
+        }
+    }
+}
+
+/// One sample of the internal signals; `None` latency means the ping failed or timed out
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LoadSignals {
+    pub mongo_latency_ms: Option<u64>,
+    pub redis_latency_ms: Option<u64>,
+    pub queue_depth: Option<u64>,
+    pub pool_saturation: f64,
+}
+
+/// Level and the signal that set it, as reported to clients
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct LoadStatus {
+    pub level: LoadLevel,
+    pub reason: Option<&'static str>,
+}
+
+/// Sheds non-core endpoints while MongoDB or Redis are slow or the queue backs up.
+///
+/// A background task pings both stores, reads the job queue depth and the
+/// Mongo pool saturation every few seconds. Pressure escalates immediately
+/// but only relaxes one level per sample, so a single fast ping during an
+/// incident doesn't flap analytics back on.
+pub struct LoadShedder {
+    database: MongoDatabase,
+    redis: RedisConnection,
+    job_queue: Arc<JobQueue>,
+    config: LoadSheddingConfig,
+    status: RwLock<LoadStatus>,
+}
+
+impl LoadShedder {
+    pub fn new(
+        database: MongoDatabase,
+        redis: RedisConnection,
+        job_queue: Arc<JobQueue>,
+        config: LoadSheddingConfig,
+    ) -> Self {
+        Self {
+            database,
+            redis,
+            job_queue,
+            config,
+            status: RwLock::new(LoadStatus {
+                level: LoadLevel::Normal,
+                reason: None,
+            }),
+        }
+    }
+
+    pub fn route_tier(path: &str) -> RouteTier {
+        let path = path.trim_end_matches('/');
+        let path = path.strip_prefix("/api/v1").unwrap_or(path);
+        let segments: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+
+        match segments.as_slice() {
+            ["auth", ..] | ["webhooks", ..] => RouteTier::Core,
+            ["messages", "send"] => RouteTier::Core,
+            ["messages", "export"] => RouteTier::Expensive,
+            ["messages", "quote" | "filters" | "inbound", ..] => RouteTier::Standard,
+            ["messages", _] | ["messages", _, "delivery" | "received"] => RouteTier::Core,
+            ["providers", _, "heartbeat" | "status"] => RouteTier::Core,
+            // Operators need these to respond to the incident itself
+            ["admin", "carriers", ..] | ["admin", "providers", "bulk-status"] => RouteTier::Core,
+            ["analytics", ..] | ["downloads", ..] | ["earnings", "stats" | "history"] => {
+                RouteTier::Expensive
+            }
+            ["admin", "stats" | "heatmap" | "quality" | "backups" | "settlements", ..]
+            | ["admin", "messages" | "providers"]
+            | ["admin", "messages", "redetect-carriers"] => RouteTier::Expensive,
+            _ => RouteTier::Standard,
+        }
+    }
+
+    /// Level a sample calls for on its own, with the worst signal as the reason
+    pub fn assess(config: &LoadSheddingConfig, signals: &LoadSignals) -> LoadStatus {
+        let latency_level = |latency: Option<u64>| match latency {
+            None => LoadLevel::Critical,
+            Some(ms) if ms >= config.critical_latency_ms => LoadLevel::Critical,
+            Some(ms) if ms >= config.degraded_latency_ms => LoadLevel::Degraded,
+            Some(_) => LoadLevel::Normal,
+        };
+        let queue_level = match signals.queue_depth {
+            Some(depth) if depth >= config.critical_queue_depth => LoadLevel::Critical,
+            Some(depth) if depth >= config.degraded_queue_depth => LoadLevel::Degraded,
+            _ => LoadLevel::Normal,
+        };
+        let pool_level = if signals.pool_saturation >= config.critical_pool_saturation {
+            LoadLevel::Critical
+        } else if signals.pool_saturation >= config.degraded_pool_saturation {
+            LoadLevel::Degraded
+        } else {
+            LoadLevel::Normal
+        };
+
+        let (level, reason) = [
+            (latency_level(signals.mongo_latency_ms), "mongo_latency"),
+            (latency_level(signals.redis_latency_ms), "redis_latency"),
+            (pool_level, "mongo_pool_saturation"),
+            (queue_level, "queue_depth"),
+        ]
+        .into_iter()
+        .fold((LoadLevel::Normal, None), |worst, (level, reason)| {
+            if level > worst.0 {
+                (level, Some(reason))
+            } else {
+                worst
+            }
+        });
+
+        LoadStatus { level, reason }
+    }
+
+    /// Escalate at once, relax one level at a time
+    pub fn next_status(current: LoadStatus, observed: LoadStatus) -> LoadStatus {
+        if observed.level >= current.level {
+            return observed;
+        }
+        let level = current.level.step_down().max(observed.level);
+        LoadStatus {
+            level,
+            reason: if level == LoadLevel::Normal {
+                None
+            } else if level == observed.level {
+                observed.reason
+            } else {
+                current.reason
+            },
+        }
+    }
+
+    pub fn status(&self) -> LoadStatus {
+        match self.status.read() {
+            Ok(status) => *status,
+            Err(poisoned) => *poisoned.into_inner(),
+        }
+    }
+
+    /// The status to reject a request with, if its route is shed right now
+    pub fn check(&self, path: &str) -> Option<LoadStatus> {
+        if !self.config.enabled {
+            return None;
+        }
+        let status = self.status();
+        let tier = Self::route_tier(path);
+        if !status.level.sheds(tier) {
+            return None;
+        }
+
+        metrics::counter!(
+            "load_shed_requests_total",
+            "level" => status.level.as_str(),
+            "tier" => format!("{:?}", tier).to_lowercase()
+        )
+        .increment(1);
+        Some(status)
+    }
+
+    pub fn start(self: Arc<Self>) {
+        if !self.config.enabled {
+            info!("Load shedding disabled");
+            return;
+        }
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(
+                self.config.sample_interval_seconds.max(1),
+            ));
+            loop {
+                interval.tick().await;
+                let signals = self.sample().await;
+                self.update(&signals);
+            }
+        });
+    }
+
+    async fn sample(&self) -> LoadSignals {
+        // A ping slower than the critical threshold is as bad as no answer
+        let timeout = Duration::from_millis(self.config.critical_latency_ms.max(1));
+        let timed = |started: Instant, ok: bool| ok.then(|| started.elapsed().as_millis() as u64);
+
+        let started = Instant::now();
+        let mongo_ok = matches!(
+            tokio::time::timeout(timeout, self.database.health_check()).await,
+            Ok(Ok(()))
+        );
+        let mongo_latency_ms = timed(started, mongo_ok);
+
+        let started = Instant::now();
+        let redis_ok = matches!(
+            tokio::time::timeout(timeout, self.redis.health_check()).await,
+            Ok(Ok(()))
+        );
+        let redis_latency_ms = timed(started, redis_ok);
+
+        let queue_depth = match tokio::time::timeout(timeout, self.job_queue.depth()).await {
+            Ok(Ok(depth)) => Some(depth),
+            _ => None,
+        };
+
+        LoadSignals {
+            mongo_latency_ms,
+            redis_latency_ms,
+            queue_depth,
+            pool_saturation: self.database.pool_monitor().saturation(),
+        }
+    }
+
+    fn update(&self, signals: &LoadSignals) {
+        let observed = Self::assess(&self.config, signals);
+        let previous = self.status();
+        let next = Self::next_status(previous, observed);
+
+        if let Ok(mut status) = self.status.write() {
+            *status = next;
+        }
+        metrics::gauge!("load_shedding_level").set(next.level as u8 as f64);
+
+        if next.level > previous.level {
+            warn!(
+                "Load shedding raised to {} ({}): {:?}",
+                next.level.as_str(),
+                next.reason.unwrap_or("unknown"),
+                signals
+            );
+        } else if next.level < previous.level {
+            info!("Load shedding relaxed to {}", next.level.as_str());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> LoadSheddingConfig {
+        LoadSheddingConfig {
+            enabled: true,
+            sample_interval_seconds: 5,
+            degraded_latency_ms: 250,
+            critical_latency_ms: 1000,
+            degraded_queue_depth: 5000,
+            critical_queue_depth: 20000,
+            degraded_pool_saturation: 0.8,
+            critical_pool_saturation: 0.95,
+        }
+    }
+
+    fn healthy() -> LoadSignals {
+        LoadSignals {
+            mongo_latency_ms: Some(5),
+            redis_latency_ms: Some(1),
+            queue_depth: Some(10),
+            pool_saturation: 0.1,
+        }
+    }
+
+    #[test]
+    fn test_expensive_routes_shed_before_core() {
+        let degraded = LoadLevel::Degraded;
+        assert!(degraded.sheds(LoadShedder::route_tier("/api/v1/analytics/tags")));
+        assert!(degraded.sheds(LoadShedder::route_tier("/messages/export")));
+        assert!(!degraded.sheds(LoadShedder::route_tier("/messages")));
+
+        let critical = LoadLevel::Critical;
+        assert!(critical.sheds(LoadShedder::route_tier("/messages")));
+        for core in [
+            "/messages/send",
+            "/messages/msg-1",
+            "/messages/msg-1/delivery",
+            "/webhooks/delivery/msg-1",
+            "/auth/verify-otp",
+            "/providers/p-1/heartbeat",
+        ] {
+            assert_eq!(LoadShedder::route_tier(core), RouteTier::Core, "{}", core);
+        }
+    }
+
+    #[test]
+    fn test_worst_signal_sets_level_and_recovery_is_gradual() {
+        let normal = LoadShedder::assess(&config(), &healthy());
+        assert_eq!(normal.level, LoadLevel::Normal);
+
+        let pressured = LoadShedder::assess(
+            &config(),
+            &LoadSignals {
+                mongo_latency_ms: Some(400),
+                redis_latency_ms: None,
+                ..healthy()
+            },
+        );
+        assert_eq!(pressured.level, LoadLevel::Critical);
+        assert_eq!(pressured.reason, Some("redis_latency"));
+
+        let relaxed = LoadShedder::next_status(pressured, normal);
+        assert_eq!(relaxed.level, LoadLevel::Degraded);
+        assert_eq!(
+            LoadShedder::next_status(relaxed, normal).level,
+            LoadLevel::Normal
+        );
+    }
+}
@@ -0,0 +1,250 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tracing::warn;
+
+use crate::config::LockoutConfig;
+use crate::infrastructure::database::RedisConnection;
+use crate::shared::{PeerPowerError, Result};
+
+/// What a lockout counter is keyed on
+#[derive(Debug, Clone, Copy)]
+enum Subject {
+    Phone,
+    Ip,
+}
+
+impl Subject {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Subject::Phone => "phone",
+            Subject::Ip => "ip",
+        }
+    }
+}
+
+/// Lockout state of a phone number, for support and the admin console
+#[derive(Debug, Clone, Serialize)]
+pub struct LockoutStatus {
+    pub phone: String,
+    pub recent_failures: i64,
+    pub locked_until: Option<DateTime<Utc>>,
+    pub lock_level: i64, // locks within the level memory; drives the next cooldown
+}
+
+/// Temporary lockout after repeated failed OTP verifications, tracked per
+/// phone and per client IP. Sits on top of the per-OTP attempt limit, which
+/// only caps guesses against a single code. The IP must be one the client
+/// can't choose (`ClientInfo::trusted_ip`), or rotating a forged
+/// X-Forwarded-For would dodge the IP count.
+pub struct LoginLockout {
+    redis: RedisConnection,
+    config: LockoutConfig,
+}
+
+impl LoginLockout {
+    pub fn new(redis: RedisConnection, config: LockoutConfig) -> Self {
+        Self { redis, config }
+    }
+
+    /// Cooldown for the given lock level (1 for the first lock): the base
+    /// cooldown doubled per level, capped at the maximum
+    pub fn cooldown_for(config: &LockoutConfig, level: i64) -> u64 {
+        let doublings = level.saturating_sub(1).clamp(0, 32) as u32;
+        config
+            .base_cooldown_seconds
+            .saturating_mul(2u64.saturating_pow(doublings))
+            .min(config.max_cooldown_seconds)
+    }
+
+    /// Reject the attempt while either the phone or the IP is locked
+    pub async fn check(&self, phone: &str, ip: Option<&str>) -> Result<()> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+
+        let mut retry_after_seconds = self.remaining(Subject::Phone, phone).await?;
+        if let Some(ip) = ip {
+            retry_after_seconds = retry_after_seconds.max(self.remaining(Subject::Ip, ip).await?);
+        }
+
+        if retry_after_seconds > 0 {
+            return Err(PeerPowerError::AccountLocked {
+                retry_after_seconds,
+            });
+        }
+        Ok(())
+    }
+
+    /// Count a failed verification; returns the cooldown in seconds if this
+    /// failure locked the phone or the IP
+    pub async fn record_failure(&self, phone: &str, ip: Option<&str>) -> Result<Option<u64>> {
+        if !self.config.enabled {
+            return Ok(None);
+        }
+
+        let mut locked_for = self
+            .count_failure(Subject::Phone, phone, self.config.phone_max_failures)
+            .await?;
+        if let Some(ip) = ip {
+            if let Some(cooldown) = self
+                .count_failure(Subject::Ip, ip, self.config.ip_max_failures)
+                .await?
+            {
+                locked_for = Some(locked_for.unwrap_or(0).max(cooldown));
+            }
+        }
+        Ok(locked_for)
+    }
+
+    /// Clear the phone's failure count after a successful verification. The
+    /// IP count is left alone, so one valid login can't reset an IP that is
+    /// guessing codes for other numbers.
+    pub async fn record_success(&self, phone: &str) -> Result<()> {
+        self.redis
+            .delete(&Self::failures_key(Subject::Phone, phone))
+            .await?;
+        Ok(())
+    }
+
+    /// Lift a phone's lock and forget its failures and escalation level
+    pub async fn unlock(&self, phone: &str) -> Result<()> {
+        self.redis
+            .delete(&Self::lock_key(Subject::Phone, phone))
+            .await?;
+        self.redis
+            .delete(&Self::level_key(Subject::Phone, phone))
+            .await?;
+        self.redis
+            .delete(&Self::failures_key(Subject::Phone, phone))
+            .await?;
+        Ok(())
+    }
+
+    pub async fn status(&self, phone: &str) -> Result<LockoutStatus> {
+        let recent_failures = self
+            .read_counter(&Self::failures_key(Subject::Phone, phone))
+            .await?;
+        let lock_level = self
+            .read_counter(&Self::level_key(Subject::Phone, phone))
+            .await?;
+        let locked_until = self
+            .locked_until(Subject::Phone, phone)
+            .await?
+            .filter(|until| *until > Utc::now());
+
+        Ok(LockoutStatus {
+            phone: phone.to_string(),
+            recent_failures,
+            locked_until,
+            lock_level,
+        })
+    }
+
+    async fn count_failure(
+        &self,
+        subject: Subject,
+        value: &str,
+        max_failures: i64,
+    ) -> Result<Option<u64>> {
+        let failures_key = Self::failures_key(subject, value);
+        let failures = self.redis.increment(&failures_key).await?;
+        if failures == 1 {
+            self.redis
+                .expire(&failures_key, self.config.failure_window_seconds as usize)
+                .await?;
+        }
+        if failures < max_failures {
+            return Ok(None);
+        }
+
+        let level_key = Self::level_key(subject, value);
+        let level = self.redis.increment(&level_key).await?;
+        self.redis
+            .expire(&level_key, self.config.level_memory_seconds as usize)
+            .await?;
+
+        let cooldown = Self::cooldown_for(&self.config, level);
+        let locked_until = Utc::now() + chrono::Duration::seconds(cooldown as i64);
+        self.redis
+            .set(
+                &Self::lock_key(subject, value),
+                &locked_until.to_rfc3339(),
+                Some(cooldown as usize),
+            )
+            .await?;
+        self.redis.delete(&failures_key).await?;
+
+        warn!(
+            "OTP verification locked for {} after {} failures (level {}, {}s)",
+            subject.as_str(),
+            failures,
+            level,
+            cooldown
+        );
+        metrics::counter!("otp_lockouts_total", "subject" => subject.as_str()).increment(1);
+
+        Ok(Some(cooldown))
+    }
+
+    async fn remaining(&self, subject: Subject, value: &str) -> Result<u64> {
+        Ok(self
+            .locked_until(subject, value)
+            .await?
+            .map(|until| (until - Utc::now()).num_seconds().max(0) as u64)
+            .unwrap_or(0))
+    }
+
+    async fn locked_until(&self, subject: Subject, value: &str) -> Result<Option<DateTime<Utc>>> {
+        Ok(self
+            .redis
+            .get(&Self::lock_key(subject, value))
+            .await?
+            .and_then(|raw| DateTime::parse_from_rfc3339(&raw).ok())
+            .map(|until| until.with_timezone(&Utc)))
+    }
+
+    async fn read_counter(&self, key: &str) -> Result<i64> {
+        Ok(self
+            .redis
+            .get(key)
+            .await?
+            .and_then(|raw| raw.parse().ok())
+            .unwrap_or(0))
+    }
+
+    fn failures_key(subject: Subject, value: &str) -> String {
+        format!("otp_failures:{}:{}", subject.as_str(), value)
+    }
+
+    fn lock_key(subject: Subject, value: &str) -> String {
+        format!("otp_lock:{}:{}", subject.as_str(), value)
+    }
+
+    fn level_key(subject: Subject, value: &str) -> String {
+        format!("otp_lock_level:{}:{}", subject.as_str(), value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cooldown_escalates_and_caps() {
+        let config = LockoutConfig {
+            enabled: true,
+            phone_max_failures: 5,
+            ip_max_failures: 20,
+            failure_window_seconds: 900,
+            base_cooldown_seconds: 300,
+            max_cooldown_seconds: 3600,
+            level_memory_seconds: 86400,
+        };
+
+        assert_eq!(LoginLockout::cooldown_for(&config, 1), 300);
+        assert_eq!(LoginLockout::cooldown_for(&config, 2), 600);
+        assert_eq!(LoginLockout::cooldown_for(&config, 3), 1200);
+        assert_eq!(LoginLockout::cooldown_for(&config, 5), 3600);
+        assert_eq!(LoginLockout::cooldown_for(&config, 100), 3600);
+    }
+}
@@ -0,0 +1,300 @@
+use futures::StreamExt;
+use mongodb::bson::{doc, Bson, Document};
+use mongodb::change_stream::event::ResumeToken;
+use mongodb::options::{ChangeStreamOptions, FullDocumentType};
+use mongodb::{Collection, Database};
+use reqwest::Client;
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::config::ChangeStreamConfig;
+use crate::domain::entities::{Message, PlatformEvent};
+use crate::infrastructure::database::RedisConnection;
+use crate::infrastructure::event_bus::EventBus;
+use crate::infrastructure::webhook_signature::{SIGNATURE_HEADER, TIMESTAMP_HEADER};
+use crate::shared::types::MessageStatus;
+use crate::shared::{PeerPowerError, Result};
+
+/// Held by the instance following the stream, renewed while it does
+const LEADER_LOCK_KEY: &str = "message_changes:lock";
+const LEADER_LOCK_SECONDS: usize = 30;
+const LEADER_RENEW_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Where the last handled change is kept, so whichever instance follows
+/// the stream next picks up after it
+const RESUME_TOKEN_KEY: &str = "message_changes:resume_token";
+
+/// How long a status is remembered as already announced
+const SEEN_SECONDS: usize = 86_400;
+
+/// Wait before trying to follow the stream again
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// How long a client's webhook gets to answer a status notification
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Announces message status changes read from a MongoDB change stream on
+/// `messages`, so a change is announced whichever component wrote it: the
+/// job processor, a provider's confirmation or the public delivery webhook.
+///
+/// One instance at a time follows the stream. Each new status is published
+/// on the event bus (`message.delivered`, or `message.status_changed`) and a
+/// final one is posted to the message's `webhook_url`, signed like SLA
+/// breach notifications. Writes that replace a message without changing its
+/// status are not announced again. Change streams need a replica set; on a
+/// standalone server nothing is announced.
+pub struct MessageChangeFeed {
+    messages: Collection<Message>,
+    redis: RedisConnection,
+    events: Arc<EventBus>,
+    instance_id: String,
+    client: Client,
+    webhook_secret: String, // SLA_WEBHOOK_SECRET
+    config: ChangeStreamConfig,
+}
+
+impl MessageChangeFeed {
+    pub fn new(
+        database: Arc<Database>,
+        redis: RedisConnection,
+        events: Arc<EventBus>,
+        instance_id: String,
+        webhook_secret: String,
+        config: ChangeStreamConfig,
+    ) -> Self {
+        let client = Client::builder()
+            .timeout(WEBHOOK_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+        Self {
+            messages: database.collection("messages"),
+            redis,
+            events,
+            instance_id,
+            client,
+            webhook_secret,
+            config,
+        }
+    }
+
+    pub fn start(self: Arc<Self>) {
+        if !self.config.enabled {
+            info!("Message change stream disabled");
+            return;
+        }
+
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = self.lead().await {
+                    warn!("Message change stream stopped: {}", e);
+                }
+                tokio::time::sleep(RETRY_DELAY).await;
+            }
+        });
+    }
+
+    /// Follow the stream if no other instance is
+    async fn lead(&self) -> Result<()> {
+        if !self
+            .redis
+            .acquire_owned_lock(LEADER_LOCK_KEY, &self.instance_id, LEADER_LOCK_SECONDS)
+            .await?
+        {
+            return Ok(());
+        }
+        let result = self.follow().await;
+        if let Err(e) = self
+            .redis
+            .release_owned_lock(LEADER_LOCK_KEY, &self.instance_id)
+            .await
+        {
+            warn!("Failed to release the message change stream: {}", e);
+        }
+        result
+    }
+
+    /// Updates that set a status, and whole-message replaces, which may
+    /// have changed it
+    fn pipeline() -> Vec<Document> {
+        vec![doc! {"$match": {"$or": [
+            {
+                "operationType": "update",
+                "updateDescription.updatedFields.status": {"$exists": true},
+            },
+            {"operationType": "replace"},
+        ]}}]
+    }
+
+    async fn follow(&self) -> Result<()> {
+        let resume_after = self.saved_resume_token().await;
+        let resuming = resume_after.is_some();
+        let options = ChangeStreamOptions::builder()
+            .full_document(Some(FullDocumentType::UpdateLookup))
+            .resume_after(resume_after)
+            .build();
+        let mut stream = match self.messages.watch(Self::pipeline(), options).await {
+            Ok(stream) => stream,
+            Err(e) => {
+                // The saved position may have aged out of the oplog; start
+                // from now next time
+                if resuming {
+                    self.redis.delete(RESUME_TOKEN_KEY).await?;
+                }
+                return Err(PeerPowerError::Database {
+                    message: format!("Failed to watch messages: {}", e),
+                });
+            }
+        };
+        info!("Following message status changes");
+
+        let mut renew = tokio::time::interval(LEADER_RENEW_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = renew.tick() => {
+                    if !self
+                        .redis
+                        .extend_owned_lock(LEADER_LOCK_KEY, &self.instance_id, LEADER_LOCK_SECONDS)
+                        .await?
+                    {
+                        info!("Another instance took over the message change stream");
+                        return Ok(());
+                    }
+                }
+                change = stream.next() => {
+                    let change = change
+                        .ok_or_else(|| PeerPowerError::Database {
+                            message: "Message change stream closed".to_string(),
+                        })?
+                        .map_err(|e| PeerPowerError::Database {
+                            message: format!("Failed to read message changes: {}", e),
+                        })?;
+                    // None if deleted before it could be looked up
+                    if let Some(message) = change.full_document {
+                        self.announce(&message).await;
+                    }
+                    self.save_resume_token(&change.id).await;
+                }
+            }
+        }
+    }
+
+    async fn announce(&self, message: &Message) {
+        // Once per status per attempt, however many writes leave it there
+        let seen_key = format!(
+            "message_changes:seen:{}:{}:{:?}",
+            message.id, message.metadata.retry_count, message.status
+        );
+        match self.redis.acquire_lock(&seen_key, SEEN_SECONDS).await {
+            Ok(true) => {}
+            Ok(false) => return,
+            Err(e) => warn!(
+                "Announcing {} without checking for repeats: {}",
+                message.id, e
+            ),
+        }
+
+        metrics::counter!(
+            "message_status_changes_total",
+            "status" => format!("{:?}", message.status).to_lowercase()
+        )
+        .increment(1);
+        self.events
+            .publish(PlatformEvent::message_status(message))
+            .await;
+
+        let is_final = matches!(
+            message.status,
+            MessageStatus::Delivered | MessageStatus::Failed | MessageStatus::Cancelled
+        );
+        if self.config.status_webhooks && is_final {
+            self.notify_client(message).await;
+        }
+    }
+
+    /// POST the final status to the message's webhook, signed like SLA
+    /// breach notifications
+    async fn notify_client(&self, message: &Message) {
+        let Some(webhook_url) = message.metadata.webhook_url.as_deref() else {
+            return;
+        };
+        if self.webhook_secret.is_empty() {
+            return;
+        }
+
+        let status = format!("{:?}", message.status).to_lowercase();
+        let body = json!({
+            "event": "message.status_changed",
+            "message_id": message.id,
+            "tracking_id": message.metadata.tracking_id,
+            "client_reference": message.metadata.client_reference,
+            "status": status,
+            "changed_at": message.updated_at.to_rfc3339(),
+        })
+        .to_string();
+        let timestamp = crate::shared::utils::now().timestamp().to_string();
+        let signature = crate::shared::utils::hmac_sha256_hex(
+            &self.webhook_secret,
+            format!("{}.{}", timestamp, body).as_bytes(),
+        );
+
+        let result = match self
+            .client
+            .post(webhook_url)
+            .header("content-type", "application/json")
+            .header(TIMESTAMP_HEADER, timestamp)
+            .header(SIGNATURE_HEADER, format!("sha256={}", signature))
+            .body(body)
+            .send()
+            .await
+        {
+            Ok(response) if response.status().is_success() => "accepted",
+            Ok(response) => {
+                warn!(
+                    "Status webhook for message {} answered {}",
+                    message.id,
+                    response.status()
+                );
+                "rejected"
+            }
+            Err(e) => {
+                warn!("Status webhook for message {} failed: {}", message.id, e);
+                "failed"
+            }
+        };
+        metrics::counter!("status_webhooks_total", "result" => result).increment(1);
+    }
+
+    /// Resume tokens are kept as extended JSON
+    async fn saved_resume_token(&self) -> Option<ResumeToken> {
+        let saved = match self.redis.get(RESUME_TOKEN_KEY).await {
+            Ok(saved) => saved?,
+            Err(e) => {
+                warn!("Failed to read the message change stream position: {}", e);
+                return None;
+            }
+        };
+        let token = serde_json::from_str::<serde_json::Value>(&saved)
+            .ok()
+            .and_then(|json| Bson::try_from(json).ok())
+            .and_then(|bson| mongodb::bson::from_bson(bson).ok());
+        if token.is_none() {
+            warn!("Discarding an unreadable message change stream position");
+        }
+        token
+    }
+
+    async fn save_resume_token(&self, token: &ResumeToken) {
+        let saved = match mongodb::bson::to_bson(token) {
+            Ok(bson) => bson.into_relaxed_extjson().to_string(),
+            Err(e) => {
+                warn!("Failed to serialize message change stream position: {}", e);
+                return;
+            }
+        };
+        if let Err(e) = self.redis.set(RESUME_TOKEN_KEY, &saved, None).await {
+            warn!("Failed to save message change stream position: {}", e);
+        }
+    }
+}
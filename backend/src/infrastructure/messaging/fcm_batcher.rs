@@ -0,0 +1,366 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot, Semaphore};
+use tokio::time::{timeout_at, Instant};
+use tracing::{info, warn};
+
+use crate::infrastructure::messaging::fcm_service::{FcmService, SmsDispatch};
+use crate::shared::{PeerPowerError, Result};
+
+/// Dispatches carried in one FCM data message at most
+const MAX_DISPATCHES_PER_MESSAGE: usize = 10;
+
+/// Serialized bytes of the `dispatches` array in one FCM data message at
+/// most, leaving room under FCM's 4KB payload limit for the other keys.
+/// Long or non-Latin SMS (Khmer is three bytes a character) reach it well
+/// before `MAX_DISPATCHES_PER_MESSAGE`.
+const MAX_BUNDLE_BYTES: usize = 3072;
+
+/// Dispatches buffered ahead of the batching loop before submitters wait
+const QUEUE_CAPACITY: usize = 1024;
+
+struct PendingDispatch {
+    fcm_token: String,
+    dispatch: SmsDispatch,
+    reply: oneshot::Sender<Result<()>>,
+}
+
+/// Coalesces SMS dispatch requests arriving within a short window, so
+/// several jobs for the same provider go out as one FCM message instead of
+/// one HTTP call each. Every caller still gets its own outcome back; a
+/// failed batch fails each job in it.
+///
+/// Each FCM request takes a permit from `permits` while it is open.
+pub struct FcmDispatchBatcher {
+    sender: mpsc::Sender<PendingDispatch>,
+}
+
+impl FcmDispatchBatcher {
+    /// Spawn the batching loop
+    pub fn start(
+        fcm_service: Arc<dyn FcmService>,
+        permits: Arc<Semaphore>,
+        window: Duration,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel(QUEUE_CAPACITY);
+        tokio::spawn(Self::run(fcm_service, permits, window, receiver));
+        Self { sender }
+    }
+
+    /// Send one dispatch, possibly batched with others for the same token,
+    /// and wait for its outcome
+    pub async fn dispatch(&self, fcm_token: &str, dispatch: SmsDispatch) -> Result<()> {
+        let (reply, outcome) = oneshot::channel();
+        self.sender
+            .send(PendingDispatch {
+                fcm_token: fcm_token.to_string(),
+                dispatch,
+                reply,
+            })
+            .await
+            .map_err(|_| Self::stopped())?;
+        outcome.await.map_err(|_| Self::stopped())?
+    }
+
+    async fn run(
+        fcm_service: Arc<dyn FcmService>,
+        permits: Arc<Semaphore>,
+        window: Duration,
+        mut receiver: mpsc::Receiver<PendingDispatch>,
+    ) {
+        while let Some(first) = receiver.recv().await {
+            // Whatever else arrives before the window closes joins the batch
+            let mut pending = vec![first];
+            let deadline = Instant::now() + window;
+            while let Ok(Some(next)) = timeout_at(deadline, receiver.recv()).await {
+                pending.push(next);
+            }
+
+            let mut by_token: HashMap<String, Vec<PendingDispatch>> = HashMap::new();
+            for dispatch in pending {
+                by_token
+                    .entry(dispatch.fcm_token.clone())
+                    .or_default()
+                    .push(dispatch);
+            }
+
+            for (fcm_token, dispatches) in by_token {
+                for bundle in Self::bundle(dispatches, |pending| Self::size_of(&pending.dispatch)) {
+                    tokio::spawn(Self::send(
+                        fcm_service.clone(),
+                        permits.clone(),
+                        fcm_token.clone(),
+                        bundle,
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Split one token's dispatches, in order, into bundles within
+    /// `MAX_DISPATCHES_PER_MESSAGE` and `MAX_BUNDLE_BYTES`; a dispatch too
+    /// large to share a message goes on its own
+    fn bundle<T>(items: Vec<T>, size: impl Fn(&T) -> usize) -> Vec<Vec<T>> {
+        let mut bundles: Vec<Vec<T>> = Vec::new();
+        let mut bytes = 0;
+        for item in items {
+            let item_bytes = size(&item) + 1; // and its separator in the array
+            match bundles.last_mut() {
+                Some(bundle)
+                    if bundle.len() < MAX_DISPATCHES_PER_MESSAGE
+                        && bytes + item_bytes <= MAX_BUNDLE_BYTES =>
+                {
+                    bytes += item_bytes;
+                    bundle.push(item);
+                }
+                _ => {
+                    bytes = item_bytes;
+                    bundles.push(vec![item]);
+                }
+            }
+        }
+        bundles
+    }
+
+    /// Bytes a dispatch takes in the serialized `dispatches` array
+    fn size_of(dispatch: &SmsDispatch) -> usize {
+        serde_json::to_string(dispatch).map_or(0, |json| json.len())
+    }
+
+    /// One FCM request for up to `MAX_DISPATCHES_PER_MESSAGE` dispatches to
+    /// one token; its outcome is handed to every dispatch in it
+    async fn send(
+        fcm_service: Arc<dyn FcmService>,
+        permits: Arc<Semaphore>,
+        fcm_token: String,
+        dispatches: Vec<PendingDispatch>,
+    ) {
+        let (payloads, replies): (Vec<SmsDispatch>, Vec<_>) = dispatches
+            .into_iter()
+            .map(|pending| (pending.dispatch, pending.reply))
+            .unzip();
+
+        let outcome = match permits.acquire().await {
+            Ok(_permit) => match payloads.as_slice() {
+                [single] => {
+                    fcm_service
+                        .send_sms_dispatch_request(
+                            &fcm_token,
+                            &single.message_id,
+                            &single.recipient,
+                            &single.content,
+                            &single.priority,
+                        )
+                        .await
+                }
+                batch => {
+                    metrics::counter!("fcm_dispatch_batches_total").increment(1);
+                    metrics::histogram!("fcm_dispatch_batch_size").record(batch.len() as f64);
+                    fcm_service.send_sms_dispatch_batch(&fcm_token, batch).await
+                }
+            },
+            Err(_) => Err(Self::stopped()),
+        };
+
+        match &outcome {
+            Ok(response) => info!("{}", response),
+            Err(e) => warn!("FCM dispatch of {} messages failed: {}", payloads.len(), e),
+        }
+        for reply in replies {
+            let result = match &outcome {
+                Ok(_) => Ok(()),
+                Err(e) => Err(Self::copy_error(e)),
+            };
+            let _ = reply.send(result); // the job may have been abandoned
+        }
+    }
+
+    /// The batch's error, for each dispatch in it
+    fn copy_error(e: &PeerPowerError) -> PeerPowerError {
+        match e {
+            PeerPowerError::ExternalService { service, message } => {
+                PeerPowerError::ExternalService {
+                    service: service.clone(),
+                    message: message.clone(),
+                }
+            }
+            other => PeerPowerError::ExternalService {
+                service: "FCM".to_string(),
+                message: other.to_string(),
+            },
+        }
+    }
+
+    fn stopped() -> PeerPowerError {
+        PeerPowerError::Internal {
+            message: "FCM dispatch batcher stopped".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infrastructure::messaging::fcm_service::FcmTokenResult;
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+
+    /// Records the size of each request; fails any sent to "bad-token"
+    #[derive(Default)]
+    struct RecordingFcm {
+        requests: Mutex<Vec<(String, usize)>>,
+    }
+
+    impl RecordingFcm {
+        fn record(&self, fcm_token: &str, dispatches: usize) -> Result<String> {
+            self.requests
+                .lock()
+                .unwrap()
+                .push((fcm_token.to_string(), dispatches));
+            if fcm_token == "bad-token" {
+                return Err(PeerPowerError::ExternalService {
+                    service: "FCM".to_string(),
+                    message: "NotRegistered".to_string(),
+                });
+            }
+            Ok("sent".to_string())
+        }
+    }
+
+    #[async_trait]
+    impl FcmService for RecordingFcm {
+        async fn send_sms_dispatch_request(
+            &self,
+            fcm_token: &str,
+            _message_id: &str,
+            _recipient: &str,
+            _content: &str,
+            _priority: &str,
+        ) -> Result<String> {
+            self.record(fcm_token, 1)
+        }
+
+        async fn send_delivery_confirmation_request(
+            &self,
+            _fcm_token: &str,
+            _message_id: &str,
+            _delivery_status: &str,
+        ) -> Result<String> {
+            unimplemented!()
+        }
+
+        async fn send_provider_status_update(
+            &self,
+            _fcm_token: &str,
+            _status: &str,
+        ) -> Result<String> {
+            unimplemented!()
+        }
+
+        async fn send_sms_dispatch_batch(
+            &self,
+            fcm_token: &str,
+            dispatches: &[SmsDispatch],
+        ) -> Result<String> {
+            self.record(fcm_token, dispatches.len())
+        }
+
+        async fn send_provider_status_multicast(
+            &self,
+            _fcm_tokens: &[String],
+            _status: &str,
+        ) -> Result<Vec<FcmTokenResult>> {
+            unimplemented!()
+        }
+
+        async fn send_cancel_dispatch(
+            &self,
+            _fcm_token: &str,
+            _message_id: &str,
+            _reason: &str,
+        ) -> Result<String> {
+            unimplemented!()
+        }
+    }
+
+    fn dispatch(message_id: &str) -> SmsDispatch {
+        SmsDispatch {
+            message_id: message_id.to_string(),
+            recipient: "+85512345678".to_string(),
+            content: "Hello".to_string(),
+            priority: "Normal".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_bundles_split_by_count_and_payload_size() {
+        let short: Vec<SmsDispatch> = (0..12).map(|i| dispatch(&format!("m{}", i))).collect();
+        let sizes: Vec<usize> = FcmDispatchBatcher::bundle(short, FcmDispatchBatcher::size_of)
+            .iter()
+            .map(Vec::len)
+            .collect();
+        assert_eq!(sizes, vec![10, 2]);
+
+        // 160 Khmer characters are 480 bytes of UTF-8 each
+        let khmer: Vec<SmsDispatch> = (0..10)
+            .map(|i| SmsDispatch {
+                content: "ក".repeat(160),
+                ..dispatch(&format!("k{}", i))
+            })
+            .collect();
+        let bundles = FcmDispatchBatcher::bundle(khmer, FcmDispatchBatcher::size_of);
+        assert!(bundles.len() > 1);
+        for bundle in &bundles {
+            let json = serde_json::to_string(bundle).unwrap();
+            assert!(json.len() <= MAX_BUNDLE_BYTES, "{} bytes", json.len());
+        }
+        let ids: Vec<&str> = bundles
+            .iter()
+            .flatten()
+            .map(|dispatch| dispatch.message_id.as_str())
+            .collect();
+        assert_eq!(ids.first(), Some(&"k0"));
+        assert_eq!(ids.len(), 10);
+
+        // Too large to share a message with anything
+        let huge = SmsDispatch {
+            content: "x".repeat(MAX_BUNDLE_BYTES),
+            ..dispatch("huge")
+        };
+        let bundles = FcmDispatchBatcher::bundle(
+            vec![dispatch("a"), huge, dispatch("b")],
+            FcmDispatchBatcher::size_of,
+        );
+        assert_eq!(
+            bundles.iter().map(Vec::len).collect::<Vec<_>>(),
+            vec![1, 1, 1]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dispatches_to_one_token_share_a_request() {
+        let fcm = Arc::new(RecordingFcm::default());
+        let batcher = FcmDispatchBatcher::start(
+            fcm.clone(),
+            Arc::new(Semaphore::new(4)),
+            Duration::from_millis(50),
+        );
+
+        let (a, b, c, d) = tokio::join!(
+            batcher.dispatch("token-1", dispatch("m1")),
+            batcher.dispatch("token-1", dispatch("m2")),
+            batcher.dispatch("token-1", dispatch("m3")),
+            batcher.dispatch("bad-token", dispatch("m4")),
+        );
+        assert!(a.is_ok() && b.is_ok() && c.is_ok());
+        assert!(matches!(d, Err(PeerPowerError::ExternalService { .. })));
+
+        let mut requests = fcm.requests.lock().unwrap().clone();
+        requests.sort();
+        assert_eq!(
+            requests,
+            vec![("bad-token".to_string(), 1), ("token-1".to_string(), 3)]
+        );
+    }
+}
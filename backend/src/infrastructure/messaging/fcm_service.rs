@@ -1,11 +1,11 @@
 use async_trait::async_trait;
-use reqwest::Client;
+use reqwest::{Client, StatusCode};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use tracing::{error, info, warn};
 
 use crate::config::FcmConfig;
-use crate::shared::{PeerPowerError, Result};
+use crate::shared::{PeerPowerError, Result, RetryClass, RetryPolicy};
 
 #[derive(Debug, Serialize)]
 pub struct FcmMessage {
@@ -16,6 +16,15 @@ pub struct FcmMessage {
     pub time_to_live: u32,
 }
 
+/// One payload sent to many registration tokens in a single request
+#[derive(Debug, Serialize)]
+pub struct FcmMulticastMessage {
+    pub registration_ids: Vec<String>,
+    pub data: HashMap<String, String>,
+    pub priority: String,
+    pub time_to_live: u32,
+}
+
 #[derive(Debug, Serialize)]
 pub struct FcmNotification {
     pub title: String,
@@ -40,6 +49,31 @@ pub struct FcmResult {
     pub error: Option<String>,
 }
 
+/// Tokens accepted per multicast request by the FCM legacy HTTP API
+pub const MAX_MULTICAST_TOKENS: usize = 1000;
+
+/// One SMS for a provider to send, as carried in a dispatch batch
+#[derive(Debug, Clone, Serialize)]
+pub struct SmsDispatch {
+    pub message_id: String,
+    pub recipient: String,
+    pub content: String,
+    pub priority: String,
+}
+
+/// Outcome of a multicast for one registration token
+#[derive(Debug, Clone)]
+pub struct FcmTokenResult {
+    pub fcm_token: String,
+    pub error: Option<String>, // FCM's error code, e.g. "NotRegistered"
+}
+
+impl FcmTokenResult {
+    pub fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
 #[async_trait]
 pub trait FcmService: Send + Sync {
     async fn send_sms_dispatch_request(
@@ -59,55 +93,130 @@ pub trait FcmService: Send + Sync {
     ) -> Result<String>;
 
     async fn send_provider_status_update(&self, fcm_token: &str, status: &str) -> Result<String>;
+
+    /// Several dispatches for one provider in a single data message
+    /// (`type` "sms_dispatch_batch", `dispatches` a JSON array)
+    async fn send_sms_dispatch_batch(
+        &self,
+        fcm_token: &str,
+        dispatches: &[SmsDispatch],
+    ) -> Result<String>;
+
+    /// The same status update to many providers, one request per
+    /// `MAX_MULTICAST_TOKENS`; results are in `fcm_tokens` order
+    async fn send_provider_status_multicast(
+        &self,
+        fcm_tokens: &[String],
+        status: &str,
+    ) -> Result<Vec<FcmTokenResult>>;
+
+    /// Ask a provider's device not to send a message it was dispatched
+    /// (`type` "cancel_dispatch"); it answers through the cancellation
+    /// acknowledgment endpoint
+    async fn send_cancel_dispatch(
+        &self,
+        fcm_token: &str,
+        message_id: &str,
+        reason: &str,
+    ) -> Result<String>;
 }
 
 pub struct FcmServiceImpl {
     config: FcmConfig,
     client: Client,
+    retry_policy: RetryPolicy,
 }
 
 impl FcmServiceImpl {
-    pub fn new(config: FcmConfig) -> Self {
+    pub fn new(config: FcmConfig, retry_policy: RetryPolicy) -> Self {
         Self {
             config,
             client: Client::new(),
+            retry_policy,
         }
     }
 
     async fn send_fcm_message(&self, message: FcmMessage) -> Result<FcmResponse> {
         info!("Sending FCM message to: {}", message.to);
+        self.post(&message).await
+    }
 
+    /// One FCM request, retried in place as the FCM retry policy allows
+    async fn post(&self, message: &impl Serialize) -> Result<FcmResponse> {
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            match self.post_once(message).await {
+                Ok(response) => return Ok(response),
+                Err((Some(class), e)) if self.retry_policy.should_retry(attempts, class) => {
+                    let delay = self.retry_policy.delay(attempts, class);
+                    warn!(
+                        "FCM request failed (attempt {}), retrying in {:?}: {}",
+                        attempts, delay, e
+                    );
+                    metrics::counter!(
+                        "fcm_request_retries_total",
+                        "class" => format!("{:?}", class)
+                    )
+                    .increment(1);
+                    tokio::time::sleep(delay).await;
+                }
+                Err((_, e)) => return Err(e),
+            }
+        }
+    }
+
+    /// The error comes with how to retry it, or `None` when FCM may have
+    /// accepted the request and sending it again could deliver it twice
+    async fn post_once(
+        &self,
+        message: &impl Serialize,
+    ) -> std::result::Result<FcmResponse, (Option<RetryClass>, PeerPowerError)> {
+        let failed = |class, detail| {
+            (
+                class,
+                PeerPowerError::ExternalService {
+                    service: "FCM".to_string(),
+                    message: detail,
+                },
+            )
+        };
         let response = self
             .client
             .post(&self.config.fcm_url)
             .header("Authorization", format!("key={}", self.config.server_key))
             .header("Content-Type", "application/json")
-            .json(&message)
+            .json(message)
             .send()
             .await
-            .map_err(|e| PeerPowerError::ExternalService {
-                service: "FCM".to_string(),
-                message: format!("Failed to send FCM request: {}", e),
+            .map_err(|e| {
+                failed(
+                    e.is_connect().then_some(RetryClass::Transient),
+                    format!("Failed to send FCM request: {}", e),
+                )
             })?;
 
         if !response.status().is_success() {
             let status = response.status();
             let body = response.text().await.unwrap_or_default();
             error!("FCM request failed with status {}: {}", status, body);
-            return Err(PeerPowerError::ExternalService {
-                service: "FCM".to_string(),
-                message: format!("FCM returned error {}: {}", status, body),
-            });
+            let class = if status == StatusCode::TOO_MANY_REQUESTS {
+                RetryClass::Throttled
+            } else if status.is_server_error() {
+                RetryClass::Transient
+            } else {
+                RetryClass::Rejected
+            };
+            return Err(failed(
+                Some(class),
+                format!("FCM returned error {}: {}", status, body),
+            ));
         }
 
-        let fcm_response: FcmResponse =
-            response
-                .json()
-                .await
-                .map_err(|e| PeerPowerError::ExternalService {
-                    service: "FCM".to_string(),
-                    message: format!("Failed to parse FCM response: {}", e),
-                })?;
+        let fcm_response: FcmResponse = response
+            .json()
+            .await
+            .map_err(|e| failed(None, format!("Failed to parse FCM response: {}", e)))?;
 
         // Check for errors in the response
         if fcm_response.failure > 0 {
@@ -226,6 +335,124 @@ impl FcmService for FcmServiceImpl {
             })
         }
     }
+
+    async fn send_sms_dispatch_batch(
+        &self,
+        fcm_token: &str,
+        dispatches: &[SmsDispatch],
+    ) -> Result<String> {
+        let urgent = dispatches
+            .iter()
+            .any(|dispatch| dispatch.priority == "High");
+        let mut data = HashMap::new();
+        data.insert("type".to_string(), "sms_dispatch_batch".to_string());
+        data.insert(
+            "dispatches".to_string(),
+            serde_json::to_string(dispatches).map_err(|e| PeerPowerError::Internal {
+                message: format!("Failed to serialize dispatch batch: {}", e),
+            })?,
+        );
+
+        let message = FcmMessage {
+            to: fcm_token.to_string(),
+            data,
+            notification: Some(FcmNotification {
+                title: "New SMS Requests".to_string(),
+                body: format!("Send {} SMS", dispatches.len()),
+                icon: Some("ic_sms".to_string()),
+                sound: Some("default".to_string()),
+            }),
+            priority: if urgent { "high" } else { "normal" }.to_string(),
+            time_to_live: 300, // 5 minutes
+        };
+
+        let response = self.send_fcm_message(message).await?;
+
+        if response.success > 0 {
+            Ok(format!(
+                "FCM batch of {} dispatches sent successfully",
+                dispatches.len()
+            ))
+        } else {
+            Err(PeerPowerError::ExternalService {
+                service: "FCM".to_string(),
+                message: "Failed to deliver FCM dispatch batch".to_string(),
+            })
+        }
+    }
+
+    async fn send_provider_status_multicast(
+        &self,
+        fcm_tokens: &[String],
+        status: &str,
+    ) -> Result<Vec<FcmTokenResult>> {
+        let mut data = HashMap::new();
+        data.insert("type".to_string(), "status_update".to_string());
+        data.insert("status".to_string(), status.to_string());
+
+        let mut results = Vec::with_capacity(fcm_tokens.len());
+        for tokens in fcm_tokens.chunks(MAX_MULTICAST_TOKENS) {
+            let message = FcmMulticastMessage {
+                registration_ids: tokens.to_vec(),
+                data: data.clone(),
+                priority: "normal".to_string(),
+                time_to_live: 120, // 2 minutes
+            };
+            info!("Sending FCM status multicast to {} tokens", tokens.len());
+            let response = self.post(&message).await?;
+            results.extend(token_results(tokens, &response));
+        }
+
+        Ok(results)
+    }
+
+    async fn send_cancel_dispatch(
+        &self,
+        fcm_token: &str,
+        message_id: &str,
+        reason: &str,
+    ) -> Result<String> {
+        let mut data = HashMap::new();
+        data.insert("type".to_string(), "cancel_dispatch".to_string());
+        data.insert("message_id".to_string(), message_id.to_string());
+        data.insert("reason".to_string(), reason.to_string());
+
+        let message = FcmMessage {
+            to: fcm_token.to_string(),
+            data,
+            notification: None,
+            priority: "high".to_string(), // has to beat the queued send
+            time_to_live: 300, // 5 minutes
+        };
+
+        let response = self.send_fcm_message(message).await?;
+
+        if response.success > 0 {
+            Ok(format!("Cancellation sent for message {}", message_id))
+        } else {
+            Err(PeerPowerError::ExternalService {
+                service: "FCM".to_string(),
+                message: "Failed to deliver cancellation".to_string(),
+            })
+        }
+    }
+}
+
+/// Pair each token with its entry in a multicast response; FCM returns
+/// results in request order, and a missing entry counts as a failure
+fn token_results(fcm_tokens: &[String], response: &FcmResponse) -> Vec<FcmTokenResult> {
+    let results = response.results.as_deref().unwrap_or_default();
+    fcm_tokens
+        .iter()
+        .enumerate()
+        .map(|(i, fcm_token)| FcmTokenResult {
+            fcm_token: fcm_token.clone(),
+            error: match results.get(i) {
+                Some(result) => result.error.clone(),
+                None => Some("MissingResult".to_string()),
+            },
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -260,4 +487,33 @@ mod tests {
         assert!(json.contains("test_token"));
         assert!(json.contains("normal"));
     }
+
+    #[test]
+    fn test_token_results_map_partial_failure_in_order() {
+        let tokens = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let response = FcmResponse {
+            multicast_id: Some(1),
+            success: 1,
+            failure: 1,
+            canonical_ids: 0,
+            results: Some(vec![
+                FcmResult {
+                    message_id: Some("m1".to_string()),
+                    registration_id: None,
+                    error: None,
+                },
+                FcmResult {
+                    message_id: None,
+                    registration_id: None,
+                    error: Some("NotRegistered".to_string()),
+                },
+            ]),
+        };
+
+        let results = token_results(&tokens, &response);
+        assert!(results[0].is_success());
+        assert_eq!(results[1].fcm_token, "b");
+        assert_eq!(results[1].error.as_deref(), Some("NotRegistered"));
+        assert_eq!(results[2].error.as_deref(), Some("MissingResult"));
+    }
 }
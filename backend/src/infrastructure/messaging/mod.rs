@@ -1,2 +1,7 @@
 // Messaging implementations
+pub mod fcm_batcher;
 pub mod fcm_service;
+pub mod otp_sms;
+pub mod otp_telegram;
+pub mod otp_voice;
+pub mod sms_gateway;
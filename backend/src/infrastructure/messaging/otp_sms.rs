@@ -0,0 +1,116 @@
+use async_trait::async_trait;
+use mongodb::Database;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::domain::entities::{Job, Message, MessagePriority, Provider, OTP_CLIENT_ID};
+use crate::domain::services::{OtpChannel, OtpChannelKind};
+use crate::infrastructure::job_outbox::JobOutbox;
+use crate::infrastructure::job_queue::JobQueue;
+use crate::infrastructure::messaging::sms_gateway::SmsGatewayClient;
+use crate::shared::types::PhoneNumber;
+use crate::shared::{PeerPowerError, Result};
+
+/// Delivers OTP codes through the PeerPower network itself.
+///
+/// The code goes through the normal message/job pipeline as an urgent
+/// message from the `system:otp` client, expiring with the code. When no
+/// provider is online the external SMS gateway is used instead, so signup
+/// doesn't depend on the network being warm.
+pub struct SmsOtpChannel {
+    database: Arc<Database>,
+    job_queue: Arc<JobQueue>,
+    job_outbox: Arc<JobOutbox>,
+    sms_gateway: Arc<SmsGatewayClient>,
+}
+
+impl SmsOtpChannel {
+    pub fn new(
+        database: Arc<Database>,
+        job_queue: Arc<JobQueue>,
+        job_outbox: Arc<JobOutbox>,
+        sms_gateway: Arc<SmsGatewayClient>,
+    ) -> Self {
+        Self {
+            database,
+            job_queue,
+            job_outbox,
+            sms_gateway,
+        }
+    }
+
+    async fn has_online_providers(&self) -> Result<bool> {
+        let online = self
+            .database
+            .collection::<Provider>("providers")
+            .count_documents(mongodb::bson::doc! {"status": "Online"}, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to count online providers: {}", e),
+            })?;
+        Ok(online > 0)
+    }
+
+    async fn enqueue(
+        &self,
+        phone: &PhoneNumber,
+        content: String,
+        ttl_minutes: i64,
+    ) -> Result<String> {
+        let mut message = Message::new(
+            OTP_CLIENT_ID.to_string(),
+            content,
+            phone.clone(),
+            MessagePriority::Urgent,
+            None,
+            None,
+        );
+        // Never deliver a code after it stops working
+        message.expires_at = Some(message.created_at + chrono::Duration::minutes(ttl_minutes));
+
+        let mut job = Job::new(message.id.clone(), "pending-assignment".to_string());
+        self.job_queue.assign_order(&mut job, &message).await?;
+
+        self.job_outbox.submit(&message, &job).await?;
+
+        info!("Queued OTP message {} for {}", message.id, phone.as_str());
+        metrics::counter!("otp_deliveries_total", "route" => "network").increment(1);
+        Ok(format!("message {}", message.id))
+    }
+}
+
+#[async_trait]
+impl OtpChannel for SmsOtpChannel {
+    fn kind(&self) -> OtpChannelKind {
+        OtpChannelKind::Sms
+    }
+
+    async fn can_reach(&self, _phone: &PhoneNumber) -> Result<bool> {
+        Ok(self.sms_gateway.is_configured() || self.has_online_providers().await?)
+    }
+
+    async fn send_code(&self, phone: &PhoneNumber, code: &str, ttl_minutes: i64) -> Result<String> {
+        let content = format!(
+            "Your PeerPower verification code is {}. It expires in {} minutes.",
+            code, ttl_minutes
+        );
+
+        if self.has_online_providers().await? {
+            return self.enqueue(phone, content, ttl_minutes).await;
+        }
+
+        if !self.sms_gateway.is_configured() {
+            return Err(PeerPowerError::ProviderUnavailable {
+                carrier: "any (no providers online and no SMS gateway configured)".to_string(),
+            });
+        }
+
+        warn!(
+            "No providers online, sending OTP to {} via SMS gateway",
+            phone.as_str()
+        );
+        let reference = self.sms_gateway.send_sms(phone, &content).await?;
+        metrics::counter!("otp_deliveries_total", "route" => "gateway").increment(1);
+        Ok(format!("gateway {}", reference))
+    }
+}
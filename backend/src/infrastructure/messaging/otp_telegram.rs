@@ -0,0 +1,181 @@
+use async_trait::async_trait;
+use mongodb::options::UpdateOptions;
+use mongodb::{Collection, Database};
+use reqwest::Client;
+use serde_json::json;
+use std::sync::Arc;
+use tracing::{error, info};
+
+use crate::config::TelegramConfig;
+use crate::domain::entities::TelegramLink;
+use crate::domain::services::{OtpChannel, OtpChannelKind};
+use crate::shared::field_encryption;
+use crate::shared::types::PhoneNumber;
+use crate::shared::utils::stored_timestamp;
+use crate::shared::{PeerPowerError, Result};
+
+/// Sends OTPs as Telegram bot messages to users who linked their number
+pub struct TelegramOtpChannel {
+    config: TelegramConfig,
+    client: Client,
+    links: Collection<TelegramLink>,
+}
+
+impl TelegramOtpChannel {
+    pub fn new(config: TelegramConfig, database: Arc<Database>) -> Self {
+        Self {
+            config,
+            client: Client::new(),
+            links: database.collection("telegram_links"),
+        }
+    }
+
+    pub fn is_configured(&self) -> bool {
+        !self.config.bot_token.is_empty()
+    }
+
+    pub fn verify_webhook_secret(&self, secret: Option<&str>) -> bool {
+        !self.config.webhook_secret.is_empty()
+            && secret.is_some_and(|secret| secret == self.config.webhook_secret)
+    }
+
+    /// Remember which chat to send `phone`'s codes to
+    pub async fn link(
+        &self,
+        phone: &PhoneNumber,
+        chat_id: i64,
+        telegram_user_id: i64,
+    ) -> Result<()> {
+        self.links
+            .update_one(
+                mongodb::bson::doc! {"phone": field_encryption::phone_filter(phone)},
+                linking(phone, chat_id, telegram_user_id),
+                UpdateOptions::builder().upsert(true).build(),
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to save Telegram link: {}", e),
+            })?;
+
+        info!("Linked Telegram chat {} to {}", chat_id, phone.as_str());
+        Ok(())
+    }
+
+    async fn find_link(&self, phone: &PhoneNumber) -> Result<Option<TelegramLink>> {
+        self.links
+            .find_one(
+                mongodb::bson::doc! {"phone": field_encryption::phone_filter(phone)},
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch Telegram link: {}", e),
+            })
+    }
+
+    /// Post a bot message, optionally with a reply keyboard
+    pub async fn send_text(
+        &self,
+        chat_id: i64,
+        text: &str,
+        reply_markup: Option<serde_json::Value>,
+    ) -> Result<String> {
+        let mut body = json!({"chat_id": chat_id, "text": text});
+        if let Some(reply_markup) = reply_markup {
+            body["reply_markup"] = reply_markup;
+        }
+
+        let response = self
+            .client
+            .post(format!(
+                "{}/bot{}/sendMessage",
+                self.config.api_url, self.config.bot_token
+            ))
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| PeerPowerError::ExternalService {
+                service: "Telegram".to_string(),
+                message: format!("Failed to send message: {}", e),
+            })?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            error!(
+                "Telegram sendMessage failed with status {}: {}",
+                status, body
+            );
+            return Err(PeerPowerError::ExternalService {
+                service: "Telegram".to_string(),
+                message: format!("Telegram returned error {}: {}", status, body),
+            });
+        }
+
+        let body: serde_json::Value =
+            response
+                .json()
+                .await
+                .map_err(|e| PeerPowerError::ExternalService {
+                    service: "Telegram".to_string(),
+                    message: format!("Failed to parse response: {}", e),
+                })?;
+
+        Ok(body["result"]["message_id"].to_string())
+    }
+}
+
+#[async_trait]
+impl OtpChannel for TelegramOtpChannel {
+    fn kind(&self) -> OtpChannelKind {
+        OtpChannelKind::Telegram
+    }
+
+    async fn can_reach(&self, phone: &PhoneNumber) -> Result<bool> {
+        Ok(self.is_configured() && self.find_link(phone).await?.is_some())
+    }
+
+    async fn send_code(&self, phone: &PhoneNumber, code: &str, ttl_minutes: i64) -> Result<String> {
+        let link = self
+            .find_link(phone)
+            .await?
+            .ok_or_else(|| PeerPowerError::NotFound {
+                resource: format!("Telegram link for {}", phone.as_str()),
+            })?;
+
+        let text = format!(
+            "Your PeerPower verification code is {}. It expires in {} minutes. Never share it with anyone.",
+            code, ttl_minutes
+        );
+        let message_id = self.send_text(link.chat_id, &text, None).await?;
+        Ok(format!("telegram message {}", message_id))
+    }
+}
+
+fn linking(phone: &PhoneNumber, chat_id: i64, telegram_user_id: i64) -> mongodb::bson::Document {
+    mongodb::bson::doc! {
+        "$set": {
+            "phone": field_encryption::encrypt_phone(phone),
+            "chat_id": chat_id,
+            "telegram_user_id": telegram_user_id,
+            "linked_at": stored_timestamp(crate::shared::utils::now()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::test_support::read_back_from;
+
+    #[test]
+    fn test_link_reads_back() {
+        let phone = PhoneNumber::new("+85512345678".to_string()).unwrap();
+        let link: TelegramLink = read_back_from(
+            mongodb::bson::doc! {"phone": field_encryption::phone_filter(&phone)},
+            &linking(&phone, 42, 7),
+        );
+        assert_eq!(link.phone, phone);
+        assert_eq!(link.chat_id, 42);
+    }
+}
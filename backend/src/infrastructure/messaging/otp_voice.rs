@@ -0,0 +1,108 @@
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+use crate::config::VoiceGatewayConfig;
+use crate::domain::services::{OtpChannel, OtpChannelKind};
+use crate::shared::types::PhoneNumber;
+use crate::shared::{PeerPowerError, Result};
+
+#[derive(Debug, Serialize)]
+struct VoiceCallRequest<'a> {
+    to: &'a str,
+    text: String,
+    language: &'a str,
+    repeat: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct VoiceCallResponse {
+    id: Option<String>,
+}
+
+/// Reads the code out in a text-to-speech call, for when texts are delayed
+pub struct VoiceOtpChannel {
+    config: VoiceGatewayConfig,
+    client: Client,
+}
+
+impl VoiceOtpChannel {
+    pub fn new(config: VoiceGatewayConfig) -> Self {
+        Self {
+            config,
+            client: Client::new(),
+        }
+    }
+
+    pub fn is_configured(&self) -> bool {
+        !self.config.url.is_empty() && !self.config.api_key.is_empty()
+    }
+}
+
+#[async_trait]
+impl OtpChannel for VoiceOtpChannel {
+    fn kind(&self) -> OtpChannelKind {
+        OtpChannelKind::Voice
+    }
+
+    async fn can_reach(&self, _phone: &PhoneNumber) -> Result<bool> {
+        Ok(self.is_configured())
+    }
+
+    async fn send_code(
+        &self,
+        phone: &PhoneNumber,
+        code: &str,
+        _ttl_minutes: i64,
+    ) -> Result<String> {
+        info!("Calling {} with OTP", phone.as_str());
+
+        // Digits spaced out so the speech engine reads them one by one
+        let digits = code
+            .chars()
+            .map(String::from)
+            .collect::<Vec<_>>()
+            .join(", ");
+        let response = self
+            .client
+            .post(&self.config.url)
+            .bearer_auth(&self.config.api_key)
+            .json(&VoiceCallRequest {
+                to: phone.as_str(),
+                text: format!("Your PeerPower verification code is {}.", digits),
+                language: &self.config.language,
+                repeat: 2,
+            })
+            .send()
+            .await
+            .map_err(|e| PeerPowerError::ExternalService {
+                service: "Voice gateway".to_string(),
+                message: format!("Failed to place call: {}", e),
+            })?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            error!(
+                "Voice gateway request failed with status {}: {}",
+                status, body
+            );
+            return Err(PeerPowerError::ExternalService {
+                service: "Voice gateway".to_string(),
+                message: format!("Voice gateway returned error {}: {}", status, body),
+            });
+        }
+
+        let body: VoiceCallResponse =
+            response
+                .json()
+                .await
+                .map_err(|e| PeerPowerError::ExternalService {
+                    service: "Voice gateway".to_string(),
+                    message: format!("Failed to parse gateway response: {}", e),
+                })?;
+
+        Ok(format!("call {}", body.id.unwrap_or_default()))
+    }
+}
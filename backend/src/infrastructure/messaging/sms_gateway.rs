@@ -0,0 +1,88 @@
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+use crate::config::SmsGatewayConfig;
+use crate::shared::types::PhoneNumber;
+use crate::shared::{PeerPowerError, Result};
+
+#[derive(Debug, Serialize)]
+struct GatewaySmsRequest<'a> {
+    to: &'a str,
+    from: &'a str,
+    text: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+struct GatewaySmsResponse {
+    id: Option<String>,
+}
+
+/// HTTP client for a conventional A2P SMS gateway, used only when the
+/// PeerPower network itself can't carry a message
+pub struct SmsGatewayClient {
+    config: SmsGatewayConfig,
+    client: Client,
+}
+
+impl SmsGatewayClient {
+    pub fn new(config: SmsGatewayConfig) -> Self {
+        Self {
+            config,
+            client: Client::new(),
+        }
+    }
+
+    pub fn is_configured(&self) -> bool {
+        !self.config.url.is_empty() && !self.config.api_key.is_empty()
+    }
+
+    /// Send a text message, returning the gateway's message reference
+    pub async fn send_sms(&self, to: &PhoneNumber, text: &str) -> Result<String> {
+        if !self.is_configured() {
+            return Err(PeerPowerError::Configuration {
+                message: "SMS gateway is not configured".to_string(),
+            });
+        }
+
+        info!("Sending SMS to {} via external gateway", to.as_str());
+
+        let response = self
+            .client
+            .post(&self.config.url)
+            .bearer_auth(&self.config.api_key)
+            .json(&GatewaySmsRequest {
+                to: to.as_str(),
+                from: &self.config.sender_id,
+                text,
+            })
+            .send()
+            .await
+            .map_err(|e| PeerPowerError::SmsDeliveryFailed {
+                reason: format!("SMS gateway request failed: {}", e),
+            })?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            error!(
+                "SMS gateway request failed with status {}: {}",
+                status, body
+            );
+            return Err(PeerPowerError::SmsDeliveryFailed {
+                reason: format!("SMS gateway returned error {}: {}", status, body),
+            });
+        }
+
+        let body: GatewaySmsResponse =
+            response
+                .json()
+                .await
+                .map_err(|e| PeerPowerError::ExternalService {
+                    service: "SMS gateway".to_string(),
+                    message: format!("Failed to parse gateway response: {}", e),
+                })?;
+
+        Ok(body.id.unwrap_or_default())
+    }
+}
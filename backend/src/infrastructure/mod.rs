@@ -1,14 +1,103 @@
+pub mod audit_logger;
 pub mod auth_service_impl;
+pub mod backup_service;
 pub mod blockchain;
+pub mod cancellations;
+pub mod canary;
+pub mod carrier_pause;
+pub mod carrier_redetection;
 pub mod database;
+pub mod dead_letters;
+pub mod delivery_confirmations;
+pub mod delivery_prediction;
+pub mod device_keys;
+pub mod dispatch_strategies;
+pub mod disputes;
+pub mod earnings_reconciler;
+pub mod event_bus;
+pub mod identity;
+pub mod impact_analysis;
+pub mod intake_backpressure;
+pub mod job_admin;
+pub mod job_outbox;
 pub mod job_processor;
+pub mod job_queue;
+pub mod jwt_keys;
+pub mod ledger;
+pub mod load_shedder;
+pub mod login_lockout;
+pub mod message_changes;
 pub mod messaging;
+pub mod number_pool;
+pub mod otp_challenge;
 pub mod payments;
+pub mod phone_backfill;
+pub mod play_integrity;
+pub mod pricing;
+pub mod pricing_plans;
+pub mod provider_cache;
+pub mod provider_selection;
+pub mod quality_bonuses;
+pub mod rate_limiter;
+pub mod recipient_privacy;
+pub mod referrals;
+pub mod retention;
+pub mod rollup_task;
+pub mod routing_rules;
+pub mod session_store;
+pub mod sla_monitor;
+pub mod storage;
+pub mod surge_pricing;
+pub mod volume_bonuses;
+pub mod webhook_signature;
 
 // Re-export common types
+pub use audit_logger::*;
 pub use auth_service_impl::*;
+pub use backup_service::*;
 pub use blockchain::*;
+pub use cancellations::*;
+pub use canary::*;
+pub use carrier_pause::*;
+pub use carrier_redetection::*;
 pub use database::*;
+pub use dead_letters::*;
+pub use delivery_prediction::*;
+pub use device_keys::*;
+pub use dispatch_strategies::*;
+pub use disputes::*;
+pub use earnings_reconciler::*;
+pub use event_bus::*;
+pub use identity::*;
+pub use impact_analysis::*;
+pub use intake_backpressure::*;
+pub use job_admin::*;
+pub use job_outbox::*;
 pub use job_processor::*;
+pub use job_queue::*;
+pub use jwt_keys::*;
+pub use ledger::*;
+pub use load_shedder::*;
+pub use login_lockout::*;
+pub use message_changes::*;
 pub use messaging::*;
+pub use number_pool::*;
+pub use otp_challenge::*;
 pub use payments::*;
+pub use phone_backfill::*;
+pub use play_integrity::*;
+pub use pricing::*;
+pub use pricing_plans::*;
+pub use provider_selection::*;
+pub use quality_bonuses::*;
+pub use rate_limiter::*;
+pub use recipient_privacy::*;
+pub use referrals::*;
+pub use rollup_task::*;
+pub use routing_rules::*;
+pub use session_store::*;
+pub use sla_monitor::*;
+pub use storage::*;
+pub use surge_pricing::*;
+pub use volume_bonuses::*;
+pub use webhook_signature::*;
@@ -0,0 +1,666 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use futures::stream::TryStreamExt;
+use mongodb::bson::Document;
+use mongodb::options::{FindOneAndUpdateOptions, FindOptions, ReturnDocument, UpdateOptions};
+use mongodb::{Collection, Database};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use tracing::info;
+
+use crate::config::NumberPoolConfig;
+use crate::domain::entities::number_pool::month_bounds;
+use crate::domain::entities::{
+    DedicatedNumber, InboundMessage, LedgerTransaction, Message, NumberRentalCharge, Provider,
+};
+use crate::infrastructure::ledger::Ledger;
+use crate::shared::field_encryption;
+use crate::shared::types::PhoneNumber;
+use crate::shared::utils::stored_timestamp;
+use crate::shared::{Money, PeerPowerError, Result};
+
+/// How long the dispatcher reuses loaded assignments before re-reading them
+const NUMBER_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// A client's dedicated numbers and how much of today's capacity is left
+#[derive(Debug, Clone, Serialize)]
+pub struct NumberPoolCapacity {
+    pub client_id: String,
+    pub numbers: Vec<DedicatedNumber>,
+    pub daily_capacity: u64,
+    pub sent_today: u64,
+    pub remaining_today: u64,
+}
+
+/// Dedicated numbers: provider SIMs reserved for one client so its traffic
+/// goes out from, and replies come back to, a stable set of numbers.
+///
+/// Active assignments are cached briefly since every dispatch checks them;
+/// assign/release call `invalidate` so they apply on this instance at once.
+pub struct NumberPool {
+    numbers: Collection<DedicatedNumber>,
+    rentals: Collection<NumberRentalCharge>,
+    inbound: Collection<InboundMessage>,
+    providers: Collection<Provider>,
+    messages: Collection<Message>,
+    ledger: Arc<Ledger>,
+    config: NumberPoolConfig,
+    cache: RwLock<Option<(Instant, Arc<Vec<DedicatedNumber>>)>>,
+}
+
+impl NumberPool {
+    pub fn new(database: Arc<Database>, ledger: Arc<Ledger>, config: NumberPoolConfig) -> Self {
+        Self {
+            numbers: database.collection("dedicated_numbers"),
+            rentals: database.collection("number_rentals"),
+            inbound: database.collection("inbound_messages"),
+            providers: database.collection("providers"),
+            messages: database.collection("messages"),
+            ledger,
+            config,
+            cache: RwLock::new(None),
+        }
+    }
+
+    pub async fn invalidate(&self) {
+        *self.cache.write().await = None;
+    }
+
+    async fn active_numbers(&self) -> Result<Arc<Vec<DedicatedNumber>>> {
+        if let Some((loaded_at, numbers)) = self.cache.read().await.as_ref() {
+            if loaded_at.elapsed() < NUMBER_CACHE_TTL {
+                return Ok(numbers.clone());
+            }
+        }
+
+        let numbers = Arc::new(
+            self.find_numbers(mongodb::bson::doc! {"released_at": null})
+                .await?,
+        );
+        *self.cache.write().await = Some((Instant::now(), numbers.clone()));
+        Ok(numbers)
+    }
+
+    /// The client's active dedicated numbers; empty means it uses the shared pool
+    pub async fn numbers_for(&self, client_id: &str) -> Result<Vec<DedicatedNumber>> {
+        Ok(self
+            .active_numbers()
+            .await?
+            .iter()
+            .filter(|number| number.client_id == client_id)
+            .cloned()
+            .collect())
+    }
+
+    /// Try the number this recipient is pinned to first, so replies keep
+    /// going to the same number, then the rest in assignment order
+    pub fn sticky_order(numbers: &[DedicatedNumber], recipient: &str) -> Vec<DedicatedNumber> {
+        let mut ordered = numbers.to_vec();
+        ordered.sort_by(|a, b| a.assigned_at.cmp(&b.assigned_at).then(a.id.cmp(&b.id)));
+        if ordered.is_empty() {
+            return ordered;
+        }
+
+        let digest = Sha256::digest(recipient.as_bytes());
+        let mut prefix = [0u8; 8];
+        prefix.copy_from_slice(&digest[..8]);
+        let pinned = (u64::from_be_bytes(prefix) % ordered.len() as u64) as usize;
+        ordered.rotate_left(pinned);
+        ordered
+    }
+
+    /// Reserve a provider's SIM for a client, taking it off the shared pool
+    pub async fn assign(
+        &self,
+        client_id: &str,
+        provider_id: &str,
+        daily_capacity: Option<u32>,
+        monthly_rent: Option<f64>,
+        assigned_by: &str,
+    ) -> Result<DedicatedNumber> {
+        let provider = self
+            .providers
+            .find_one(mongodb::bson::doc! {"id": provider_id}, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch provider: {}", e),
+            })?
+            .ok_or_else(|| PeerPowerError::NotFound {
+                resource: format!("Provider with ID: {}", provider_id),
+            })?;
+
+        let daily_capacity = daily_capacity.unwrap_or(provider.max_daily_messages);
+        if daily_capacity == 0 || daily_capacity > provider.max_daily_messages {
+            return Err(PeerPowerError::ValidationError {
+                field: "daily_capacity".to_string(),
+                message: format!(
+                    "Must be between 1 and the provider's daily limit ({})",
+                    provider.max_daily_messages
+                ),
+            });
+        }
+        let monthly_rent = monthly_rent.unwrap_or(self.config.default_monthly_rent);
+        if !monthly_rent.is_finite() || monthly_rent < 0.0 {
+            return Err(PeerPowerError::ValidationError {
+                field: "monthly_rent".to_string(),
+                message: "Monthly rent cannot be negative".to_string(),
+            });
+        }
+
+        // Claim the SIM atomically so two clients can't both be assigned it
+        let claimed = self
+            .providers
+            .update_one(
+                mongodb::bson::doc! {"id": provider_id, "dedicated_client_id": null},
+                mongodb::bson::doc! {
+                    "$set": {
+                        "dedicated_client_id": client_id,
+                        "updated_at": stored_timestamp(crate::shared::utils::now()),
+                    }
+                },
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to reserve provider: {}", e),
+            })?;
+        if claimed.modified_count == 0 {
+            return Err(PeerPowerError::ValidationError {
+                field: "provider_id".to_string(),
+                message: "Provider is already a dedicated number".to_string(),
+            });
+        }
+
+        let number = DedicatedNumber::new(
+            client_id.to_string(),
+            provider.id.clone(),
+            provider.phone.clone(),
+            provider.carrier.clone(),
+            daily_capacity,
+            monthly_rent,
+            assigned_by.to_string(),
+        );
+        self.numbers
+            .insert_one(&number, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to store dedicated number: {}", e),
+            })?;
+        self.invalidate().await;
+
+        info!(
+            "Provider {} assigned as dedicated number {} for client {}",
+            provider.id, number.id, client_id
+        );
+        Ok(number)
+    }
+
+    /// End an assignment and return the SIM to the shared pool
+    pub async fn release(&self, number_id: &str) -> Result<DedicatedNumber> {
+        let number = self
+            .numbers
+            .find_one_and_update(
+                mongodb::bson::doc! {"id": number_id, "released_at": null},
+                released(),
+                FindOneAndUpdateOptions::builder()
+                    .return_document(ReturnDocument::After)
+                    .build(),
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to release dedicated number: {}", e),
+            })?
+            .ok_or_else(|| PeerPowerError::NotFound {
+                resource: format!("Active dedicated number: {}", number_id),
+            })?;
+
+        self.providers
+            .update_one(
+                mongodb::bson::doc! {"id": &number.provider_id},
+                mongodb::bson::doc! {
+                    "$set": {
+                        "dedicated_client_id": null,
+                        "updated_at": stored_timestamp(crate::shared::utils::now()),
+                    }
+                },
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to return provider to the pool: {}", e),
+            })?;
+        self.invalidate().await;
+
+        Ok(number)
+    }
+
+    /// Numbers matching an admin listing filter, newest first
+    pub async fn list(
+        &self,
+        client_id: Option<&str>,
+        include_released: bool,
+    ) -> Result<Vec<DedicatedNumber>> {
+        let mut filter = mongodb::bson::doc! {};
+        if let Some(client_id) = client_id {
+            filter.insert("client_id", client_id);
+        }
+        if !include_released {
+            filter.insert("released_at", mongodb::bson::Bson::Null);
+        }
+        self.find_numbers(filter).await
+    }
+
+    pub async fn find_active_by_provider(
+        &self,
+        provider_id: &str,
+    ) -> Result<Option<DedicatedNumber>> {
+        self.numbers
+            .find_one(
+                mongodb::bson::doc! {"provider_id": provider_id, "released_at": null},
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch dedicated number: {}", e),
+            })
+    }
+
+    /// A client's numbers with today's usage against their reserved capacity
+    pub async fn capacity(&self, client_id: &str) -> Result<NumberPoolCapacity> {
+        let numbers = self
+            .find_numbers(mongodb::bson::doc! {"client_id": client_id, "released_at": null})
+            .await?;
+        let provider_ids: Vec<&str> = numbers
+            .iter()
+            .map(|number| number.provider_id.as_str())
+            .collect();
+
+        let providers: Vec<Provider> = if provider_ids.is_empty() {
+            Vec::new()
+        } else {
+            self.providers
+                .find(mongodb::bson::doc! {"id": {"$in": &provider_ids}}, None)
+                .await
+                .map_err(|e| PeerPowerError::Database {
+                    message: format!("Failed to fetch providers: {}", e),
+                })?
+                .try_collect()
+                .await
+                .map_err(|e| PeerPowerError::Database {
+                    message: format!("Failed to read providers: {}", e),
+                })?
+        };
+
+        let mut daily_capacity = 0u64;
+        let mut sent_today = 0u64;
+        for number in &numbers {
+            let sent = providers
+                .iter()
+                .find(|provider| provider.id == number.provider_id)
+                .map(|provider| provider.messages_sent_today.min(number.daily_capacity))
+                .unwrap_or(0);
+            daily_capacity += number.daily_capacity as u64;
+            sent_today += sent as u64;
+        }
+
+        Ok(NumberPoolCapacity {
+            client_id: client_id.to_string(),
+            numbers,
+            daily_capacity,
+            sent_today,
+            remaining_today: daily_capacity - sent_today,
+        })
+    }
+
+    /// Store a reply received on a dedicated number, linked to the latest
+    /// message the client sent that sender from it
+    pub async fn record_inbound(
+        &self,
+        number: &DedicatedNumber,
+        from: PhoneNumber,
+        from_hash: Option<String>,
+        content: String,
+        received_at: DateTime<Utc>,
+    ) -> Result<InboundMessage> {
+        let mut filter = mongodb::bson::doc! {
+            "client_id": &number.client_id,
+            "dedicated_number_id": &number.id,
+        };
+        match &from_hash {
+            Some(hash) => filter.insert("recipient_hash", hash),
+            None => filter.insert("recipient", field_encryption::phone_filter(&from)),
+        };
+        let in_reply_to = self
+            .messages
+            .find_one(
+                filter,
+                mongodb::options::FindOneOptions::builder()
+                    .sort(mongodb::bson::doc! {"created_at": -1})
+                    .build(),
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to match reply to a message: {}", e),
+            })?
+            .map(|message| message.id);
+
+        let inbound = InboundMessage {
+            id: crate::shared::utils::generate_id(),
+            client_id: number.client_id.clone(),
+            number_id: number.id.clone(),
+            provider_id: number.provider_id.clone(),
+            to: number.phone.clone(),
+            from,
+            from_hash,
+            content,
+            in_reply_to,
+            received_at,
+            created_at: crate::shared::utils::now(),
+        };
+        self.inbound
+            .insert_one(&inbound, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to store inbound message: {}", e),
+            })?;
+
+        metrics::counter!("inbound_messages_total").increment(1);
+        Ok(inbound)
+    }
+
+    /// Replies received by the client, newest first
+    pub async fn list_inbound(
+        &self,
+        client_id: &str,
+        number_id: Option<&str>,
+        page: u32,
+        limit: u32,
+    ) -> Result<Vec<InboundMessage>> {
+        let mut filter = mongodb::bson::doc! {"client_id": client_id};
+        if let Some(number_id) = number_id {
+            filter.insert("number_id", number_id);
+        }
+        let options = FindOptions::builder()
+            .sort(mongodb::bson::doc! {"received_at": -1})
+            .skip(((page.max(1) - 1) * limit) as u64)
+            .limit(limit as i64)
+            .build();
+
+        self.inbound
+            .find(filter, options)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch inbound messages: {}", e),
+            })?
+            .try_collect()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read inbound messages: {}", e),
+            })
+    }
+
+    /// Charge rent for every number assigned during the month containing
+    /// `month`, posting the client charge and provider share to the ledger.
+    ///
+    /// Charges are keyed by number and period, so re-running a month only
+    /// bills numbers that weren't billed yet. Returns the new charges.
+    pub async fn bill_month(&self, month: NaiveDate) -> Result<Vec<NumberRentalCharge>> {
+        let (first, _) = month_bounds(month);
+        let period = first.format("%Y-%m").to_string();
+        let numbers = self.find_numbers(billing_filter(month)).await?;
+
+        let mut charges = Vec::new();
+        for number in numbers {
+            let Some(charge) = rental_charge(&number, month, self.config.provider_rent_share)
+            else {
+                continue;
+            };
+
+            let (filter, update) = charge_insertion(&charge)?;
+            let result = self
+                .rentals
+                .update_one(
+                    filter,
+                    update,
+                    UpdateOptions::builder().upsert(true).build(),
+                )
+                .await
+                .map_err(|e| PeerPowerError::Database {
+                    message: format!("Failed to store rental charge: {}", e),
+                })?;
+            if result.upserted_id.is_none() {
+                continue; // already billed
+            }
+
+            self.ledger
+                .post(&LedgerTransaction::number_rental(
+                    &format!("{}:{}", number.id, period),
+                    &charge.client_id,
+                    &charge.provider_id,
+                    Money::from_ppt(charge.amount),
+                    Money::from_ppt(charge.provider_earnings),
+                ))
+                .await?;
+            charges.push(charge);
+        }
+
+        if !charges.is_empty() {
+            info!(
+                "Billed {} dedicated number rentals for {}",
+                charges.len(),
+                period
+            );
+        }
+        Ok(charges)
+    }
+
+    /// Rental charges, optionally for one period ("YYYY-MM") or client
+    pub async fn rentals(
+        &self,
+        period: Option<&str>,
+        client_id: Option<&str>,
+    ) -> Result<Vec<NumberRentalCharge>> {
+        let mut filter = mongodb::bson::doc! {};
+        if let Some(period) = period {
+            filter.insert("period", period);
+        }
+        if let Some(client_id) = client_id {
+            filter.insert("client_id", client_id);
+        }
+        let options = FindOptions::builder()
+            .sort(mongodb::bson::doc! {"period": -1, "client_id": 1})
+            .build();
+
+        self.rentals
+            .find(filter, options)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch rental charges: {}", e),
+            })?
+            .try_collect()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read rental charges: {}", e),
+            })
+    }
+
+    async fn find_numbers(&self, filter: Document) -> Result<Vec<DedicatedNumber>> {
+        let options = FindOptions::builder()
+            .sort(mongodb::bson::doc! {"assigned_at": -1})
+            .build();
+        self.numbers
+            .find(filter, options)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch dedicated numbers: {}", e),
+            })?
+            .try_collect()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read dedicated numbers: {}", e),
+            })
+    }
+}
+
+/// The update ending a number's assignment now
+fn released() -> Document {
+    mongodb::bson::doc! {
+        "$set": {"released_at": stored_timestamp(crate::shared::utils::now())}
+    }
+}
+
+/// The upsert storing `charge` unless its number was already billed for the period
+fn charge_insertion(charge: &NumberRentalCharge) -> Result<(Document, Document)> {
+    let document = mongodb::bson::to_document(charge).map_err(|e| PeerPowerError::Internal {
+        message: format!("Failed to serialize rental charge: {}", e),
+    })?;
+    Ok((
+        mongodb::bson::doc! {"number_id": &charge.number_id, "period": &charge.period},
+        mongodb::bson::doc! {"$setOnInsert": document},
+    ))
+}
+
+/// Numbers assigned at some point during the month containing `month`
+fn billing_filter(month: NaiveDate) -> Document {
+    let (first, last) = month_bounds(month);
+    let period_start = first.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+    let period_end = last.and_hms_opt(23, 59, 59).unwrap_or_default().and_utc();
+    mongodb::bson::doc! {
+        "assigned_at": {"$lte": stored_timestamp(period_end)},
+        "$or": [
+            {"released_at": null},
+            {"released_at": {"$gte": stored_timestamp(period_start)}},
+        ],
+    }
+}
+
+/// The rent `number` owes for the month containing `month`; none if it
+/// wasn't assigned on any day of it
+fn rental_charge(
+    number: &DedicatedNumber,
+    month: NaiveDate,
+    provider_rent_share: f64,
+) -> Option<NumberRentalCharge> {
+    let (first, _) = month_bounds(month);
+    let days_billed = number.billable_days(first);
+    if days_billed == 0 {
+        return None;
+    }
+
+    let amount = number.rent_for(first);
+    Some(NumberRentalCharge {
+        id: crate::shared::utils::generate_id(),
+        number_id: number.id.clone(),
+        client_id: number.client_id.clone(),
+        provider_id: number.provider_id.clone(),
+        period: first.format("%Y-%m").to_string(),
+        days_billed,
+        amount,
+        provider_earnings: amount * provider_rent_share,
+        created_at: crate::shared::utils::now(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::test_support::{matches, read_back, read_back_from};
+    use crate::shared::types::Carrier;
+    use chrono::TimeZone;
+
+    fn number(assigned_at: DateTime<Utc>, released_at: Option<DateTime<Utc>>) -> DedicatedNumber {
+        let mut number = DedicatedNumber::new(
+            "client".to_string(),
+            crate::shared::utils::generate_id(),
+            PhoneNumber::new("+85512345678".to_string()).unwrap(),
+            Carrier::Cellcard,
+            100,
+            30.0,
+            "admin".to_string(),
+        );
+        number.assigned_at = assigned_at;
+        number.released_at = released_at;
+        number
+    }
+
+    #[test]
+    fn test_rent_is_prorated_by_days_assigned() {
+        let september = NaiveDate::from_ymd_opt(2026, 9, 15).unwrap();
+
+        let whole_month = number(Utc.with_ymd_and_hms(2026, 8, 1, 0, 0, 0).unwrap(), None);
+        assert_eq!(whole_month.billable_days(september), 30);
+        assert!((whole_month.rent_for(september) - 30.0).abs() < 1e-9);
+
+        let mid_month = number(
+            Utc.with_ymd_and_hms(2026, 9, 11, 8, 0, 0).unwrap(),
+            Some(Utc.with_ymd_and_hms(2026, 9, 20, 17, 0, 0).unwrap()),
+        );
+        assert_eq!(mid_month.billable_days(september), 10);
+        assert!((mid_month.rent_for(september) - 10.0).abs() < 1e-9);
+
+        let released_before = number(
+            Utc.with_ymd_and_hms(2026, 7, 1, 0, 0, 0).unwrap(),
+            Some(Utc.with_ymd_and_hms(2026, 8, 31, 0, 0, 0).unwrap()),
+        );
+        assert_eq!(released_before.billable_days(september), 0);
+    }
+
+    #[test]
+    fn test_month_bills_an_assigned_number() {
+        let september = NaiveDate::from_ymd_opt(2026, 9, 15).unwrap();
+        let stored = |number: &DedicatedNumber| mongodb::bson::to_document(number).unwrap();
+
+        let assigned = number(Utc.with_ymd_and_hms(2026, 9, 11, 8, 0, 0).unwrap(), None);
+        assert!(matches(&stored(&assigned), &billing_filter(september)));
+        let charge = rental_charge(&assigned, september, 0.5).unwrap();
+        assert_eq!(charge.period, "2026-09");
+        assert_eq!(charge.days_billed, 20);
+        assert!((charge.amount - 20.0).abs() < 1e-9);
+        assert!((charge.provider_earnings - 10.0).abs() < 1e-9);
+
+        let released_before = number(
+            Utc.with_ymd_and_hms(2026, 7, 1, 0, 0, 0).unwrap(),
+            Some(Utc.with_ymd_and_hms(2026, 8, 31, 0, 0, 0).unwrap()),
+        );
+        assert!(!matches(
+            &stored(&released_before),
+            &billing_filter(september)
+        ));
+        assert!(rental_charge(&released_before, september, 0.5).is_none());
+    }
+
+    #[test]
+    fn test_released_number_and_its_charge_read_back() {
+        let september = NaiveDate::from_ymd_opt(2026, 9, 15).unwrap();
+        let assigned = number(Utc.with_ymd_and_hms(2026, 9, 11, 8, 0, 0).unwrap(), None);
+
+        let released = read_back(&assigned, &released());
+        assert!(released.released_at.is_some());
+
+        let charge = rental_charge(&assigned, september, 0.5).unwrap();
+        let (filter, update) = charge_insertion(&charge).unwrap();
+        let stored: NumberRentalCharge = read_back_from(filter, &update);
+        assert_eq!(stored.created_at, charge.created_at);
+    }
+
+    #[test]
+    fn test_sticky_order_pins_recipient_to_one_number() {
+        let numbers: Vec<DedicatedNumber> = (1..=3)
+            .map(|day| number(Utc.with_ymd_and_hms(2026, 9, day, 0, 0, 0).unwrap(), None))
+            .collect();
+
+        let first = NumberPool::sticky_order(&numbers, "+85512000001");
+        let again = NumberPool::sticky_order(&numbers, "+85512000001");
+        assert_eq!(first.len(), 3);
+        assert_eq!(first[0].id, again[0].id);
+
+        let mut reversed = numbers.clone();
+        reversed.reverse();
+        assert_eq!(
+            NumberPool::sticky_order(&reversed, "+85512000001")[0].id,
+            first[0].id
+        );
+    }
+}
@@ -0,0 +1,171 @@
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::config::OtpChallengeConfig;
+use crate::infrastructure::database::RedisConnection;
+use crate::shared::{PeerPowerError, Result};
+
+/// Proof-of-work puzzle handed out before an OTP can be sent
+#[derive(Debug, Clone, Serialize)]
+pub struct OtpChallenge {
+    pub challenge_id: String,
+    pub prefix: String,
+    pub difficulty_bits: u32,
+    pub algorithm: &'static str,
+    pub expires_in_seconds: u64,
+}
+
+/// Solution sent along with `send-otp` / `resend-otp`
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChallengeSolution {
+    pub challenge_id: String,
+    pub solution: String,
+}
+
+/// Guards OTP sending with a proof-of-work challenge.
+///
+/// Clients fetch a challenge and search for a `solution` such that
+/// `SHA-256("{prefix}:{solution}")` starts with `difficulty_bits` zero bits,
+/// which costs a phone a second or so but makes bulk OTP requests expensive.
+/// Each challenge is single-use. IPs that signed in successfully recently, or
+/// are configured as trusted, skip the challenge.
+pub struct OtpChallenger {
+    redis: RedisConnection,
+    config: OtpChallengeConfig,
+}
+
+impl OtpChallenger {
+    pub fn new(redis: RedisConnection, config: OtpChallengeConfig) -> Self {
+        Self { redis, config }
+    }
+
+    pub async fn issue(&self) -> Result<OtpChallenge> {
+        let mut prefix = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut prefix);
+        let prefix = hex::encode(prefix);
+        let challenge_id = uuid::Uuid::new_v4().to_string();
+
+        self.redis
+            .set(
+                &format!("otp_challenge:{}", challenge_id),
+                &format!("{}:{}", prefix, self.config.difficulty_bits),
+                Some(self.config.ttl_seconds as usize),
+            )
+            .await?;
+
+        Ok(OtpChallenge {
+            challenge_id,
+            prefix,
+            difficulty_bits: self.config.difficulty_bits,
+            algorithm: "sha256",
+            expires_in_seconds: self.config.ttl_seconds,
+        })
+    }
+
+    /// Let the request through if its IP is trusted or it carries a valid,
+    /// unused solution
+    pub async fn require(
+        &self,
+        ip: Option<&str>,
+        solution: Option<&ChallengeSolution>,
+    ) -> Result<()> {
+        if !self.config.enabled || self.is_trusted(ip).await? {
+            return Ok(());
+        }
+
+        let solution = solution.ok_or_else(|| PeerPowerError::ChallengeRequired {
+            reason: "solve a challenge from /api/v1/auth/challenge and send it as `challenge`"
+                .to_string(),
+        })?;
+        let rejected = |reason: &str| {
+            metrics::counter!("otp_challenge_rejected_total").increment(1);
+            PeerPowerError::ChallengeRequired {
+                reason: reason.to_string(),
+            }
+        };
+
+        let key = format!("otp_challenge:{}", solution.challenge_id);
+        let stored = self
+            .redis
+            .get(&key)
+            .await?
+            .ok_or_else(|| rejected("challenge expired or already used"))?;
+        // Deleting claims the challenge, so concurrent requests can't share one solution
+        if !self.redis.delete(&key).await? {
+            return Err(rejected("challenge expired or already used"));
+        }
+
+        let (prefix, difficulty_bits) = stored
+            .rsplit_once(':')
+            .and_then(|(prefix, bits)| Some((prefix, bits.parse().ok()?)))
+            .ok_or_else(|| rejected("challenge expired or already used"))?;
+        if !Self::solves(prefix, &solution.solution, difficulty_bits) {
+            return Err(rejected("incorrect challenge solution"));
+        }
+        Ok(())
+    }
+
+    /// Exempt an IP after a successful sign-in from it
+    pub async fn trust(&self, ip: Option<&str>) -> Result<()> {
+        if let Some(ip) = ip.filter(|_| self.config.enabled) {
+            self.redis
+                .set(
+                    &format!("otp_trusted_ip:{}", ip),
+                    "1",
+                    Some((self.config.trust_days * 86400) as usize),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn is_trusted(&self, ip: Option<&str>) -> Result<bool> {
+        let Some(ip) = ip else {
+            return Ok(false);
+        };
+        if self.config.trusted_ips.iter().any(|trusted| trusted == ip) {
+            return Ok(true);
+        }
+        Ok(self
+            .redis
+            .get(&format!("otp_trusted_ip:{}", ip))
+            .await?
+            .is_some())
+    }
+
+    pub fn solves(prefix: &str, solution: &str, difficulty_bits: u32) -> bool {
+        let digest = Sha256::digest(format!("{}:{}", prefix, solution).as_bytes());
+        Self::leading_zero_bits(&digest) >= difficulty_bits
+    }
+
+    fn leading_zero_bits(bytes: &[u8]) -> u32 {
+        let mut bits = 0;
+        for byte in bytes {
+            if *byte == 0 {
+                bits += 8;
+            } else {
+                return bits + byte.leading_zeros();
+            }
+        }
+        bits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_challenge_solution_checks_leading_zero_bits() {
+        assert_eq!(OtpChallenger::leading_zero_bits(&[0, 0, 0x1f]), 19);
+        assert_eq!(OtpChallenger::leading_zero_bits(&[0x80]), 0);
+
+        let solution = (0u64..)
+            .map(|n| n.to_string())
+            .find(|candidate| OtpChallenger::solves("abc", candidate, 8))
+            .unwrap();
+        assert!(OtpChallenger::solves("abc", &solution, 8));
+        assert!(!OtpChallenger::solves("abc", &solution, 256));
+    }
+}
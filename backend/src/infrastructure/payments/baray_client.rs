@@ -0,0 +1,279 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+use crate::config::BarayConfig;
+use crate::domain::entities::{PayoutCurrency, SettlementLine};
+use crate::shared::{PeerPowerError, Result};
+
+#[derive(Debug, Deserialize)]
+struct SettlementReportResponse {
+    data: Vec<SettlementLine>,
+}
+
+#[derive(Debug, Serialize)]
+struct CreateCheckoutSessionRequest<'a> {
+    reference: &'a str,
+    amount: f64,
+    currency: &'static str,
+    description: &'a str,
+    return_url: &'a str,
+}
+
+/// Hosted checkout page a client pays a top-up through
+#[derive(Debug, Clone, Deserialize)]
+pub struct CheckoutSession {
+    pub id: String,
+    pub checkout_url: String,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+struct CreateDisbursementRequest<'a> {
+    reference: &'a str,
+    amount: f64,
+    currency: &'a str,
+    destination: &'a str, // mobile-money number
+}
+
+/// A transfer out to a provider's mobile-money account
+#[derive(Debug, Clone, Deserialize)]
+pub struct Disbursement {
+    pub id: String,
+    pub status: String,
+    #[serde(default)]
+    pub failure_reason: Option<String>,
+}
+
+impl Disbursement {
+    /// Final outcome: `Some(true)` paid, `Some(false)` failed, `None` still in flight
+    pub fn outcome(&self) -> Option<bool> {
+        match self.status.to_lowercase().as_str() {
+            "completed" | "succeeded" | "success" | "paid" | "settled" => Some(true),
+            "failed" | "rejected" | "cancelled" | "canceled" | "returned" => Some(false),
+            _ => None,
+        }
+    }
+}
+
+/// What Baray made of a disbursement request
+#[derive(Debug, Clone)]
+pub enum DisbursementResult {
+    Accepted(Disbursement),
+    /// Refused outright (bad destination, limits); retrying won't help
+    Rejected(String),
+}
+
+/// HTTP client for the Baray payments API
+pub struct BarayClient {
+    config: BarayConfig,
+    client: Client,
+}
+
+impl BarayClient {
+    pub fn new(config: BarayConfig) -> Self {
+        Self {
+            config,
+            client: Client::new(),
+        }
+    }
+
+    pub fn is_configured(&self) -> bool {
+        !self.config.api_key.is_empty()
+    }
+
+    /// Open a checkout session; Baray echoes `reference` back in its webhooks
+    pub async fn create_checkout_session(
+        &self,
+        reference: &str,
+        amount: f64,
+        currency: PayoutCurrency,
+        description: &str,
+    ) -> Result<CheckoutSession> {
+        info!(
+            "Creating Baray checkout session for {} ({} {})",
+            reference,
+            amount,
+            currency.as_str()
+        );
+
+        let response = self
+            .client
+            .post(format!("{}/v1/checkout/sessions", self.config.base_url))
+            .bearer_auth(&self.config.api_key)
+            .header("Idempotency-Key", reference)
+            .json(&CreateCheckoutSessionRequest {
+                reference,
+                amount,
+                currency: currency.as_str(),
+                description,
+                return_url: &self.config.checkout_return_url,
+            })
+            .send()
+            .await
+            .map_err(|e| PeerPowerError::ExternalService {
+                service: "Baray".to_string(),
+                message: format!("Failed to create checkout session: {}", e),
+            })?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            error!(
+                "Baray checkout request failed with status {}: {}",
+                status, body
+            );
+            return Err(PeerPowerError::ExternalService {
+                service: "Baray".to_string(),
+                message: format!("Baray returned error {}: {}", status, body),
+            });
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| PeerPowerError::ExternalService {
+                service: "Baray".to_string(),
+                message: format!("Failed to parse checkout session: {}", e),
+            })
+    }
+
+    /// Send a payout; `reference` doubles as the idempotency key, so a retried
+    /// request after a timeout cannot pay twice
+    pub async fn create_disbursement(
+        &self,
+        reference: &str,
+        amount: f64,
+        currency: &str,
+        destination: &str,
+    ) -> Result<DisbursementResult> {
+        info!(
+            "Creating Baray disbursement for {} ({} {})",
+            reference, amount, currency
+        );
+
+        let response = self
+            .client
+            .post(format!("{}/v1/disbursements", self.config.base_url))
+            .bearer_auth(&self.config.api_key)
+            .header("Idempotency-Key", reference)
+            .json(&CreateDisbursementRequest {
+                reference,
+                amount,
+                currency,
+                destination,
+            })
+            .send()
+            .await
+            .map_err(|e| PeerPowerError::ExternalService {
+                service: "Baray".to_string(),
+                message: format!("Failed to create disbursement: {}", e),
+            })?;
+
+        let status = response.status();
+        if status.is_client_error() && status != reqwest::StatusCode::TOO_MANY_REQUESTS {
+            let body = response.text().await.unwrap_or_default();
+            error!(
+                "Baray rejected disbursement {} ({}): {}",
+                reference, status, body
+            );
+            return Ok(DisbursementResult::Rejected(format!(
+                "Baray rejected the payout ({}): {}",
+                status, body
+            )));
+        }
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            error!(
+                "Baray disbursement request failed with status {}: {}",
+                status, body
+            );
+            return Err(PeerPowerError::ExternalService {
+                service: "Baray".to_string(),
+                message: format!("Baray returned error {}: {}", status, body),
+            });
+        }
+
+        response
+            .json()
+            .await
+            .map(DisbursementResult::Accepted)
+            .map_err(|e| PeerPowerError::ExternalService {
+                service: "Baray".to_string(),
+                message: format!("Failed to parse disbursement: {}", e),
+            })
+    }
+
+    /// Current state of a disbursement created earlier
+    pub async fn get_disbursement(&self, disbursement_id: &str) -> Result<Disbursement> {
+        let response = self
+            .client
+            .get(format!(
+                "{}/v1/disbursements/{}",
+                self.config.base_url, disbursement_id
+            ))
+            .bearer_auth(&self.config.api_key)
+            .send()
+            .await
+            .map_err(|e| PeerPowerError::ExternalService {
+                service: "Baray".to_string(),
+                message: format!("Failed to fetch disbursement: {}", e),
+            })?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(PeerPowerError::ExternalService {
+                service: "Baray".to_string(),
+                message: format!("Baray returned error {}: {}", status, body),
+            });
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| PeerPowerError::ExternalService {
+                service: "Baray".to_string(),
+                message: format!("Failed to parse disbursement: {}", e),
+            })
+    }
+
+    /// Fetch the settlement report for a single settlement day
+    pub async fn fetch_settlement_report(&self, date: NaiveDate) -> Result<Vec<SettlementLine>> {
+        info!("Fetching Baray settlement report for {}", date);
+
+        let response = self
+            .client
+            .get(format!("{}/v1/settlements", self.config.base_url))
+            .query(&[("date", date.format("%Y-%m-%d").to_string())])
+            .bearer_auth(&self.config.api_key)
+            .send()
+            .await
+            .map_err(|e| PeerPowerError::ExternalService {
+                service: "Baray".to_string(),
+                message: format!("Failed to fetch settlement report: {}", e),
+            })?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            error!("Baray settlement request failed with status {}: {}", status, body);
+            return Err(PeerPowerError::ExternalService {
+                service: "Baray".to_string(),
+                message: format!("Baray returned error {}: {}", status, body),
+            });
+        }
+
+        let report: SettlementReportResponse =
+            response
+                .json()
+                .await
+                .map_err(|e| PeerPowerError::ExternalService {
+                    service: "Baray".to_string(),
+                    message: format!("Failed to parse settlement report: {}", e),
+                })?;
+
+        Ok(report.data)
+    }
+}
@@ -0,0 +1,92 @@
+use chrono::{DateTime, Utc};
+use mongodb::bson::doc;
+use mongodb::{Collection, Database};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use super::{BarayEvent, Disbursement};
+use crate::shared::{PeerPowerError, Result};
+
+/// `data` of `disbursement.*` events
+#[derive(Debug, Clone, Deserialize)]
+pub struct BarayDisbursementData {
+    #[serde(flatten)]
+    pub disbursement: Disbursement,
+    pub reference: String, // our payout id
+}
+
+/// A Baray event id we have taken delivery of
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessedBarayEvent {
+    pub id: String,
+    pub event_type: String,
+    pub received_at: DateTime<Utc>,
+}
+
+/// Baray event ids already handled, so a redelivered webhook is acknowledged
+/// without being applied twice.
+///
+/// An event is claimed before it is applied and released again if applying
+/// it fails, leaving Baray's retry to be processed as a first delivery. The
+/// top-up and payout updates are idempotent on their own; this keeps
+/// redeliveries from reaching them at all.
+pub struct BarayWebhookEvents {
+    events: Collection<ProcessedBarayEvent>,
+}
+
+impl BarayWebhookEvents {
+    pub fn new(database: Arc<Database>) -> Self {
+        Self {
+            events: database.collection("baray_events"),
+        }
+    }
+
+    /// Record the event; false if it was delivered before
+    pub async fn claim(&self, event: &BarayEvent) -> Result<bool> {
+        let processed = ProcessedBarayEvent {
+            id: event.id.clone(),
+            event_type: event.event_type.clone(),
+            received_at: crate::shared::utils::now(),
+        };
+        match self.events.insert_one(&processed, None).await {
+            Ok(_) => Ok(true),
+            Err(e) if e.to_string().contains("duplicate key") => {
+                metrics::counter!("baray_webhook_duplicates_total").increment(1);
+                Ok(false)
+            }
+            Err(e) => Err(PeerPowerError::Database {
+                message: format!("Failed to record Baray event: {}", e),
+            }),
+        }
+    }
+
+    /// Forget a claimed event whose processing failed, so a retry applies it
+    pub async fn release(&self, event_id: &str) -> Result<()> {
+        self.events
+            .delete_one(doc! {"id": event_id}, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to release Baray event: {}", e),
+            })?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disbursement_event_data() {
+        let event: BarayEvent = serde_json::from_str(
+            r#"{"id":"evt_2","type":"disbursement.failed","data":{"id":"dsb_1","reference":"payout-1","status":"failed","failure_reason":"Account closed"}}"#,
+        )
+        .unwrap();
+        let data: BarayDisbursementData = event.data().unwrap();
+
+        assert_eq!(data.reference, "payout-1");
+        assert_eq!(data.disbursement.id, "dsb_1");
+        assert_eq!(data.disbursement.outcome(), Some(false));
+        assert!(event.data::<super::super::BarayPaymentData>().is_err());
+    }
+}
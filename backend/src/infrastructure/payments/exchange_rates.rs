@@ -0,0 +1,247 @@
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::warn;
+
+use crate::config::ExchangeRateConfig;
+use crate::domain::entities::PayoutCurrency;
+use crate::infrastructure::database::RedisConnection;
+use crate::shared::{PeerPowerError, Result};
+
+/// Rates fetched from the source (or configuration), expiring after the cache TTL
+const RATE_CACHE_PREFIX: &str = "fx:ppt:";
+/// Admin-set rates, which win over the source until cleared
+const RATE_OVERRIDE_PREFIX: &str = "fx:override:ppt:";
+
+/// A PPT conversion rate as of a point in time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FxRate {
+    pub base: String,
+    pub quote: String,
+    pub rate: f64,
+    pub source: String, // "config", the source URL, or "override:{admin id}"
+    pub as_of: DateTime<Utc>,
+}
+
+/// A PPT amount in the currencies providers and clients actually use
+#[derive(Debug, Clone, Serialize)]
+pub struct FiatEquivalent {
+    pub khr: f64,
+    pub usd: f64,
+}
+
+/// PPT exchange rates for top-ups, payouts and displayed fiat equivalents.
+///
+/// Rates come from an admin override if one is set, otherwise from the
+/// configured source URL (falling back to the configured rates if it is
+/// unset or unreachable), cached in Redis so every instance quotes alike.
+pub struct ExchangeRates {
+    config: ExchangeRateConfig,
+    redis: RedisConnection,
+    client: Client,
+}
+
+impl ExchangeRates {
+    pub fn new(config: ExchangeRateConfig, redis: RedisConnection) -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_millis(config.source_timeout_ms))
+            .build()
+            .unwrap_or_default();
+        Self {
+            config,
+            redis,
+            client,
+        }
+    }
+
+    /// Current rate from PPT into `currency`
+    pub async fn ppt_rate(&self, currency: PayoutCurrency) -> Result<FxRate> {
+        if currency == PayoutCurrency::Ppt {
+            return Ok(Self::rate(currency, 1.0, "fixed".to_string()));
+        }
+
+        for prefix in [RATE_OVERRIDE_PREFIX, RATE_CACHE_PREFIX] {
+            if let Some(rate) = self.stored(prefix, currency).await {
+                return Ok(rate);
+            }
+        }
+
+        let rate = if self.config.source_url.is_empty() {
+            self.configured(currency)
+        } else {
+            match self.fetch(currency).await {
+                Ok(rate) => rate,
+                Err(e) => {
+                    warn!(
+                        "Exchange rate source failed, using configured PPT/{} rate: {}",
+                        currency.as_str(),
+                        e
+                    );
+                    self.configured(currency)
+                }
+            }
+        };
+        Self::validate(currency, rate.rate)?;
+
+        if let Ok(json) = serde_json::to_string(&rate) {
+            if let Err(e) = self
+                .redis
+                .set(
+                    &format!("{}{}", RATE_CACHE_PREFIX, currency.as_str()),
+                    &json,
+                    Some(self.config.cache_ttl_seconds.max(1) as usize),
+                )
+                .await
+            {
+                warn!("Failed to cache PPT/{} rate: {}", currency.as_str(), e);
+            }
+        }
+        Ok(rate)
+    }
+
+    /// `ppt` in riel and dollars, or `None` if a rate is unavailable (a
+    /// missing display value shouldn't fail the request carrying it)
+    pub async fn fiat_equivalent(&self, ppt: f64) -> Option<FiatEquivalent> {
+        let khr = self.ppt_rate(PayoutCurrency::Khr).await;
+        let usd = self.ppt_rate(PayoutCurrency::Usd).await;
+        match (khr, usd) {
+            (Ok(khr), Ok(usd)) => Some(FiatEquivalent {
+                khr: (ppt * khr.rate).round(),
+                usd: (ppt * usd.rate * 100.0).round() / 100.0,
+            }),
+            (Err(e), _) | (_, Err(e)) => {
+                warn!("No fiat equivalent for {} PPT: {}", ppt, e);
+                None
+            }
+        }
+    }
+
+    /// Current KHR and USD rates, for the admin view
+    pub async fn current(&self) -> Result<Vec<FxRate>> {
+        Ok(vec![
+            self.ppt_rate(PayoutCurrency::Khr).await?,
+            self.ppt_rate(PayoutCurrency::Usd).await?,
+        ])
+    }
+
+    /// Pin the rate for `currency` until the override is cleared
+    pub async fn set_override(
+        &self,
+        currency: PayoutCurrency,
+        rate: f64,
+        admin_id: &str,
+    ) -> Result<FxRate> {
+        if currency == PayoutCurrency::Ppt {
+            return Err(PeerPowerError::ValidationError {
+                field: "currency".to_string(),
+                message: "Rates can be set for KHR or USD".to_string(),
+            });
+        }
+        Self::validate(currency, rate).map_err(|_| PeerPowerError::ValidationError {
+            field: "rate".to_string(),
+            message: "Rate must be a positive number".to_string(),
+        })?;
+
+        let rate = Self::rate(currency, rate, format!("override:{}", admin_id));
+        let json = serde_json::to_string(&rate).map_err(|e| PeerPowerError::Internal {
+            message: format!("Failed to serialize exchange rate: {}", e),
+        })?;
+        self.redis
+            .set(
+                &format!("{}{}", RATE_OVERRIDE_PREFIX, currency.as_str()),
+                &json,
+                None,
+            )
+            .await?;
+        Ok(rate)
+    }
+
+    /// Drop the override and the cached rate, so the next lookup goes to the source
+    pub async fn clear_override(&self, currency: PayoutCurrency) -> Result<bool> {
+        let cleared = self
+            .redis
+            .delete(&format!("{}{}", RATE_OVERRIDE_PREFIX, currency.as_str()))
+            .await?;
+        self.redis
+            .delete(&format!("{}{}", RATE_CACHE_PREFIX, currency.as_str()))
+            .await?;
+        Ok(cleared)
+    }
+
+    async fn stored(&self, prefix: &str, currency: PayoutCurrency) -> Option<FxRate> {
+        match self
+            .redis
+            .get(&format!("{}{}", prefix, currency.as_str()))
+            .await
+        {
+            Ok(value) => value.and_then(|json| serde_json::from_str(&json).ok()),
+            Err(e) => {
+                warn!("Failed to read PPT/{} rate: {}", currency.as_str(), e);
+                None
+            }
+        }
+    }
+
+    /// The source answers with PPT rates keyed by currency, e.g. `{"KHR": 4100, "USD": 1.0}`
+    async fn fetch(&self, currency: PayoutCurrency) -> Result<FxRate> {
+        let unavailable = |message: String| PeerPowerError::ExternalService {
+            service: "Exchange rates".to_string(),
+            message,
+        };
+        let response = self
+            .client
+            .get(&self.config.source_url)
+            .send()
+            .await
+            .map_err(|e| unavailable(format!("Rate request failed: {}", e)))?;
+        if !response.status().is_success() {
+            return Err(unavailable(format!(
+                "Rate source returned {}",
+                response.status()
+            )));
+        }
+        let rates: HashMap<String, f64> = response
+            .json()
+            .await
+            .map_err(|e| unavailable(format!("Failed to parse rates: {}", e)))?;
+
+        let rate = rates
+            .get(currency.as_str())
+            .copied()
+            .ok_or_else(|| unavailable(format!("No {} rate in response", currency.as_str())))?;
+        Ok(Self::rate(currency, rate, self.config.source_url.clone()))
+    }
+
+    fn configured(&self, currency: PayoutCurrency) -> FxRate {
+        let rate = match currency {
+            PayoutCurrency::Ppt => 1.0,
+            PayoutCurrency::Khr => self.config.ppt_khr,
+            PayoutCurrency::Usd => self.config.ppt_usd,
+        };
+        Self::rate(currency, rate, "config".to_string())
+    }
+
+    fn rate(currency: PayoutCurrency, rate: f64, source: String) -> FxRate {
+        FxRate {
+            base: PayoutCurrency::Ppt.as_str().to_string(),
+            quote: currency.as_str().to_string(),
+            rate,
+            source,
+            as_of: crate::shared::utils::now(),
+        }
+    }
+
+    fn validate(currency: PayoutCurrency, rate: f64) -> Result<()> {
+        if !rate.is_finite() || rate <= 0.0 {
+            return Err(PeerPowerError::Configuration {
+                message: format!(
+                    "No valid PPT/{} exchange rate configured",
+                    currency.as_str()
+                ),
+            });
+        }
+        Ok(())
+    }
+}
@@ -1 +1,16 @@
-// Payment implementations will be defined here
+// Payment implementations
+pub mod baray_client;
+pub mod baray_webhooks;
+pub mod exchange_rates;
+pub mod payout_processor;
+pub mod settlement_reconciler;
+pub mod topups;
+pub mod withdrawal_checks;
+
+pub use baray_client::*;
+pub use baray_webhooks::*;
+pub use exchange_rates::*;
+pub use payout_processor::*;
+pub use settlement_reconciler::*;
+pub use topups::*;
+pub use withdrawal_checks::*;
@@ -0,0 +1,516 @@
+use futures::stream::TryStreamExt;
+use mongodb::bson::{doc, Document};
+use mongodb::options::FindOptions;
+use mongodb::{Collection, Database};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use super::{BarayClient, Disbursement, DisbursementResult};
+use crate::config::PayoutConfig;
+use crate::domain::entities::{
+    LedgerTransaction, Payout, PayoutCurrency, PayoutMethodKind, PayoutRun, PayoutStatus, Provider,
+};
+use crate::infrastructure::database::RedisConnection;
+use crate::infrastructure::ledger::Ledger;
+use crate::shared::utils::{now, stored_timestamp};
+use crate::shared::{PeerPowerError, Result};
+
+const RUN_LOCK_KEY: &str = "payout_run_lock";
+
+/// Pays approved payouts out through Baray disbursements.
+///
+/// Providers' requests that pass the withdrawal checks are approved when
+/// they are made; flagged ones wait as `Pending` for an admin. Each
+/// run first polls Baray for payouts still `Processing`, then claims up to
+/// `batch_size` approved ones and submits them, using the payout id as the
+/// idempotency key. Rejected and failed payouts are credited back to the
+/// provider with a ledger reversal; transient errors leave the payout
+/// approved for the next run until `max_attempts` is reached.
+///
+/// PPT-wallet payouts do not go through Baray and are left for manual handling.
+pub struct PayoutProcessor {
+    payouts: Collection<Payout>,
+    runs: Collection<PayoutRun>,
+    providers: Collection<Provider>,
+    baray: Arc<BarayClient>,
+    ledger: Arc<Ledger>,
+    redis: RedisConnection,
+    config: PayoutConfig,
+}
+
+impl PayoutProcessor {
+    pub fn new(
+        database: Arc<Database>,
+        redis: RedisConnection,
+        baray: Arc<BarayClient>,
+        ledger: Arc<Ledger>,
+        config: PayoutConfig,
+    ) -> Self {
+        Self {
+            payouts: database.collection("payouts"),
+            runs: database.collection("payout_runs"),
+            providers: database.collection("providers"),
+            baray,
+            ledger,
+            redis,
+            config,
+        }
+    }
+
+    pub fn start(self: Arc<Self>) {
+        if !self.baray.is_configured() {
+            info!("Baray not configured, automated payouts disabled");
+            return;
+        }
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(
+                self.config.batch_interval_seconds.max(60),
+            ));
+            loop {
+                interval.tick().await;
+                match self.run().await {
+                    Ok(run) => info!(
+                        "Payout run {}: {} submitted, {} settled, {} failed, {} deferred",
+                        run.id,
+                        run.submitted_count,
+                        run.settled_count,
+                        run.failed_count,
+                        run.deferred_count
+                    ),
+                    Err(PeerPowerError::RateLimitExceeded { .. }) => {
+                        info!("Payout run already in progress elsewhere, skipping")
+                    }
+                    Err(e) => error!("Payout run failed: {}", e),
+                }
+            }
+        });
+    }
+
+    /// Queue pending payouts for the next run; returns how many were approved
+    pub async fn approve(&self, payout_ids: &[String], admin_id: &str) -> Result<u64> {
+        let result = self
+            .payouts
+            .update_many(
+                doc! {
+                    "id": {"$in": payout_ids},
+                    "status": format!("{:?}", PayoutStatus::Pending),
+                },
+                approval(admin_id),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to approve payouts: {}", e),
+            })?;
+        Ok(result.modified_count)
+    }
+
+    /// Refuse a payout that has not been sent yet, returning the PPT to the provider
+    pub async fn reject(&self, payout_id: &str, reason: &str) -> Result<Payout> {
+        let payout = self
+            .payouts
+            .find_one(doc! {"id": payout_id}, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch payout: {}", e),
+            })?
+            .ok_or_else(|| PeerPowerError::NotFound {
+                resource: format!("Payout: {}", payout_id),
+            })?;
+
+        if !self
+            .fail(
+                &payout,
+                &[PayoutStatus::Pending, PayoutStatus::Approved],
+                reason,
+            )
+            .await?
+        {
+            return Err(PeerPowerError::ValidationError {
+                field: "status".to_string(),
+                message: format!(
+                    "Payout is {:?} and can no longer be rejected",
+                    payout.status
+                ),
+            });
+        }
+
+        let mut payout = payout;
+        payout.status = PayoutStatus::Failed;
+        payout.failure_reason = Some(reason.to_string());
+        Ok(payout)
+    }
+
+    /// Settle a payout from a Baray `disbursement.*` webhook instead of
+    /// waiting for the next run to poll it. Returns the payout if this call
+    /// settled it; `None` if it was already settled or Baray is still working.
+    pub async fn apply_disbursement_event(
+        &self,
+        payout_id: &str,
+        disbursement: &Disbursement,
+    ) -> Result<Option<Payout>> {
+        let mut payout = self
+            .payouts
+            .find_one(doc! {"id": payout_id}, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch payout: {}", e),
+            })?
+            .ok_or_else(|| PeerPowerError::NotFound {
+                resource: format!("Payout: {}", payout_id),
+            })?;
+
+        // The event can beat the run recording the disbursement id
+        match payout.baray_reference.as_deref() {
+            Some(reference) if reference != disbursement.id => {
+                warn!(
+                    "Disbursement {} names payout {} but it was sent as {}",
+                    disbursement.id, payout.id, reference
+                );
+                return Err(PeerPowerError::ValidationError {
+                    field: "id".to_string(),
+                    message: "Disbursement does not match the payout".to_string(),
+                });
+            }
+            Some(_) => {}
+            None => {
+                self.transition(
+                    &payout.id,
+                    &[PayoutStatus::Processing],
+                    doc! {"baray_reference": &disbursement.id},
+                )
+                .await?;
+            }
+        }
+
+        if !self.settle(&payout, disbursement).await? {
+            return Ok(None);
+        }
+        payout.baray_reference = Some(disbursement.id.clone());
+        match disbursement.outcome() {
+            Some(true) => payout.status = PayoutStatus::Completed,
+            _ => {
+                payout.status = PayoutStatus::Failed;
+                payout.failure_reason = disbursement
+                    .failure_reason
+                    .clone()
+                    .or_else(|| Some(format!("Baray reported '{}'", disbursement.status)));
+            }
+        }
+        Ok(Some(payout))
+    }
+
+    /// Settle in-flight payouts, then submit a batch of approved ones.
+    /// Only one instance runs at a time.
+    pub async fn run(&self) -> Result<PayoutRun> {
+        if !self.baray.is_configured() {
+            return Err(PeerPowerError::Configuration {
+                message: "Baray payments are not configured".to_string(),
+            });
+        }
+        let lock_ttl = self.config.batch_interval_seconds.max(60) as usize;
+        if !self.redis.acquire_lock(RUN_LOCK_KEY, lock_ttl).await? {
+            return Err(PeerPowerError::RateLimitExceeded {
+                resource: "Payout run already in progress".to_string(),
+            });
+        }
+
+        let mut run = PayoutRun::new();
+        let result = self.process(&mut run).await;
+        self.redis.release_lock(RUN_LOCK_KEY).await?;
+        result?;
+
+        run.finished_at = Some(now());
+        self.runs
+            .insert_one(&run, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to record payout run: {}", e),
+            })?;
+        Ok(run)
+    }
+
+    async fn process(&self, run: &mut PayoutRun) -> Result<()> {
+        for payout in self
+            .find(doc! {
+                "status": format!("{:?}", PayoutStatus::Processing),
+                "baray_reference": {"$ne": null},
+            })
+            .await?
+        {
+            match self
+                .baray
+                .get_disbursement(payout.baray_reference.as_deref().unwrap_or_default())
+                .await
+            {
+                Ok(disbursement) => {
+                    if self.settle(&payout, &disbursement).await? {
+                        run.settled_count += 1;
+                    }
+                }
+                Err(e) => warn!("Failed to refresh payout {}: {}", payout.id, e),
+            }
+        }
+
+        for payout in self
+            .find(doc! {
+                "status": format!("{:?}", PayoutStatus::Approved),
+                "currency": {"$ne": PayoutCurrency::Ppt.as_str()},
+            })
+            .await?
+        {
+            self.submit(payout, run).await?;
+        }
+        Ok(())
+    }
+
+    async fn find(&self, filter: Document) -> Result<Vec<Payout>> {
+        let find_options = FindOptions::builder()
+            .sort(doc! {"created_at": 1})
+            .limit(self.config.batch_size.max(1))
+            .build();
+        self.payouts
+            .find(filter, find_options)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch payouts: {}", e),
+            })?
+            .try_collect()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read payouts: {}", e),
+            })
+    }
+
+    async fn submit(&self, payout: Payout, run: &mut PayoutRun) -> Result<()> {
+        // Claim it so a concurrent reject can't refund a payout being sent
+        if !self
+            .transition(
+                &payout.id,
+                &[PayoutStatus::Approved],
+                doc! {
+                    "status": format!("{:?}", PayoutStatus::Processing),
+                    "run_id": &run.id,
+                },
+            )
+            .await?
+        {
+            return Ok(());
+        }
+
+        let Some(destination) = self.destination(&payout).await? else {
+            self.fail(
+                &payout,
+                &[PayoutStatus::Processing],
+                "No mobile-money payout method on file",
+            )
+            .await?;
+            run.failed_count += 1;
+            return Ok(());
+        };
+
+        match self
+            .baray
+            .create_disbursement(&payout.id, payout.amount, &payout.currency, &destination)
+            .await
+        {
+            Ok(DisbursementResult::Accepted(disbursement)) => {
+                self.transition(
+                    &payout.id,
+                    &[PayoutStatus::Processing],
+                    doc! {"baray_reference": &disbursement.id},
+                )
+                .await?;
+                run.submitted_count += 1;
+                metrics::counter!("payouts_submitted_total").increment(1);
+                if self.settle(&payout, &disbursement).await? {
+                    run.settled_count += 1;
+                }
+            }
+            Ok(DisbursementResult::Rejected(reason)) => {
+                self.fail(&payout, &[PayoutStatus::Processing], &reason)
+                    .await?;
+                run.failed_count += 1;
+            }
+            Err(e) => {
+                let attempts = payout.attempts + 1;
+                if attempts >= self.config.max_attempts {
+                    self.fail(
+                        &payout,
+                        &[PayoutStatus::Processing],
+                        &format!("Gave up after {} attempts: {}", attempts, e),
+                    )
+                    .await?;
+                    run.failed_count += 1;
+                } else {
+                    warn!(
+                        "Payout {} deferred (attempt {}): {}",
+                        payout.id, attempts, e
+                    );
+                    self.transition(
+                        &payout.id,
+                        &[PayoutStatus::Processing],
+                        doc! {
+                            "status": format!("{:?}", PayoutStatus::Approved),
+                            "attempts": attempts,
+                            "failure_reason": e.to_string(),
+                        },
+                    )
+                    .await?;
+                    run.deferred_count += 1;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Record Baray's final outcome, if there is one yet
+    async fn settle(&self, payout: &Payout, disbursement: &Disbursement) -> Result<bool> {
+        match disbursement.outcome() {
+            Some(true) => {
+                let completed = self
+                    .transition(
+                        &payout.id,
+                        &[PayoutStatus::Processing],
+                        doc! {"status": format!("{:?}", PayoutStatus::Completed)},
+                    )
+                    .await?;
+                if completed {
+                    metrics::counter!("payouts_settled_total", "status" => "Completed")
+                        .increment(1);
+                }
+                Ok(completed)
+            }
+            Some(false) => {
+                let reason = disbursement
+                    .failure_reason
+                    .clone()
+                    .unwrap_or_else(|| format!("Baray reported '{}'", disbursement.status));
+                self.fail(payout, &[PayoutStatus::Processing], &reason)
+                    .await
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Mark a payout failed and credit its PPT back to the provider
+    async fn fail(&self, payout: &Payout, from: &[PayoutStatus], reason: &str) -> Result<bool> {
+        if !self
+            .transition(
+                &payout.id,
+                from,
+                doc! {
+                    "status": format!("{:?}", PayoutStatus::Failed),
+                    "failure_reason": reason,
+                },
+            )
+            .await?
+        {
+            return Ok(false);
+        }
+        metrics::counter!("payouts_settled_total", "status" => "Failed").increment(1);
+        warn!("Payout {} failed: {}", payout.id, reason);
+
+        // Reversals are keyed by payout id; if this errors, re-running the
+        // ledger opening balances posts it
+        self.ledger
+            .post(&LedgerTransaction::payout_reversal(
+                &payout.id,
+                &payout.provider_id,
+                payout.native_amount(),
+            ))
+            .await?;
+        Ok(true)
+    }
+
+    async fn transition(
+        &self,
+        payout_id: &str,
+        from: &[PayoutStatus],
+        set: Document,
+    ) -> Result<bool> {
+        let mut set = set;
+        set.insert("updated_at", stored_timestamp(now()));
+        let from: Vec<String> = from.iter().map(|status| format!("{:?}", status)).collect();
+        let result = self
+            .payouts
+            .update_one(
+                doc! {"id": payout_id, "status": {"$in": from}},
+                doc! {"$set": set},
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to update payout: {}", e),
+            })?;
+        Ok(result.modified_count == 1)
+    }
+
+    /// Mobile-money account the payout was requested to
+    async fn destination(&self, payout: &Payout) -> Result<Option<String>> {
+        let Some(method_id) = &payout.payout_method_id else {
+            return Ok(None);
+        };
+        let provider = self
+            .providers
+            .find_one(doc! {"id": &payout.provider_id}, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch provider: {}", e),
+            })?;
+
+        Ok(provider.and_then(|provider| {
+            provider
+                .payout_methods
+                .into_iter()
+                .find(|method| {
+                    &method.id == method_id && method.kind == PayoutMethodKind::MobileMoney
+                })
+                .map(|method| method.account_reference)
+        }))
+    }
+}
+
+/// Update moving pending payouts to approved
+fn approval(admin_id: &str) -> Document {
+    doc! {
+        "$set": {
+            "status": format!("{:?}", PayoutStatus::Approved),
+            "approved_by": admin_id,
+            "updated_at": stored_timestamp(now()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::test_support::read_back;
+
+    #[test]
+    fn test_disbursement_outcome_from_baray_status() {
+        let disbursement = |status: &str| Disbursement {
+            id: "dsb_1".to_string(),
+            status: status.to_string(),
+            failure_reason: None,
+        };
+
+        assert_eq!(disbursement("Completed").outcome(), Some(true));
+        assert_eq!(disbursement("paid").outcome(), Some(true));
+        assert_eq!(disbursement("rejected").outcome(), Some(false));
+        assert_eq!(disbursement("returned").outcome(), Some(false));
+        assert_eq!(disbursement("pending").outcome(), None);
+        assert_eq!(disbursement("processing").outcome(), None);
+    }
+
+    #[test]
+    fn test_approved_payout_reads_back() {
+        let payout = Payout::new("provider-1".to_string(), 25.0, "USD".to_string(), None);
+        let approved = read_back(&payout, &approval("admin-1"));
+        assert_eq!(approved.status, PayoutStatus::Approved);
+        assert_eq!(approved.approved_by.as_deref(), Some("admin-1"));
+        assert!(approved.updated_at >= payout.updated_at);
+    }
+}
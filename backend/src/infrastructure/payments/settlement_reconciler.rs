@@ -0,0 +1,298 @@
+use chrono::NaiveDate;
+use futures::stream::TryStreamExt;
+use mongodb::{Collection, Database};
+use std::collections::HashSet;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::domain::entities::{
+    DiscrepancyKind, LedgerAccountKind, LedgerTransactionKind, Payout, PayoutStatus,
+    SettlementDiscrepancy, SettlementLine, SettlementReport, SettlementSource,
+};
+use crate::infrastructure::ledger::Ledger;
+use crate::shared::utils::stored_timestamp;
+use crate::shared::{Money, PeerPowerError, Result};
+
+/// Matches Baray settlement reports against recorded payouts, and each
+/// matched payout against its ledger transaction
+pub struct SettlementReconciler {
+    payouts: Collection<Payout>,
+    reports: Collection<SettlementReport>,
+    discrepancies: Collection<SettlementDiscrepancy>,
+    ledger: Arc<Ledger>,
+}
+
+impl SettlementReconciler {
+    pub fn new(database: Arc<Database>, ledger: Arc<Ledger>) -> Self {
+        Self {
+            payouts: database.collection("payouts"),
+            reports: database.collection("settlement_reports"),
+            discrepancies: database.collection("settlement_discrepancies"),
+            ledger,
+        }
+    }
+
+    /// Match every reported line to a payout, flag differences and record the report
+    pub async fn reconcile(
+        &self,
+        settlement_date: NaiveDate,
+        source: SettlementSource,
+        lines: Vec<SettlementLine>,
+        imported_by: Option<String>,
+    ) -> Result<SettlementReport> {
+        let mut report = SettlementReport::new(
+            settlement_date.format("%Y-%m-%d").to_string(),
+            source,
+            imported_by,
+        );
+        report.line_count = lines.len() as u32;
+
+        let mut seen_references = HashSet::new();
+        for line in &lines {
+            seen_references.insert(line.reference.clone());
+
+            let payout = self
+                .payouts
+                .find_one(mongodb::bson::doc! {"baray_reference": &line.reference}, None)
+                .await
+                .map_err(|e| PeerPowerError::Database {
+                    message: format!("Failed to fetch payout: {}", e),
+                })?;
+
+            let Some(payout) = payout else {
+                let mut discrepancy = SettlementDiscrepancy::new(
+                    report.id.clone(),
+                    DiscrepancyKind::UnknownReference,
+                    format!("No payout recorded for Baray reference {}", line.reference),
+                );
+                discrepancy.reference = Some(line.reference.clone());
+                discrepancy.reported_amount = Some(line.amount);
+                if self.flag(discrepancy).await? {
+                    report.discrepancy_count += 1;
+                }
+                continue;
+            };
+
+            let mismatch = match payout.compare_settlement(line) {
+                Some(mismatch) => Some(mismatch),
+                None => self.compare_ledger(&payout, line).await?,
+            };
+            match mismatch {
+                Some((kind, details)) => {
+                    let mut discrepancy =
+                        SettlementDiscrepancy::new(report.id.clone(), kind, details);
+                    discrepancy.payout_id = Some(payout.id.clone());
+                    discrepancy.reference = Some(line.reference.clone());
+                    discrepancy.expected_amount = Some(payout.amount);
+                    discrepancy.reported_amount = Some(line.amount);
+                    if self.flag(discrepancy).await? {
+                        report.discrepancy_count += 1;
+                    }
+                    self.set_reconciliation(&payout.id, "Discrepancy").await?;
+                }
+                None => {
+                    report.matched_count += 1;
+                    self.set_reconciliation(&payout.id, "Matched").await?;
+                }
+            }
+        }
+
+        report.discrepancy_count += self
+            .flag_missing_payouts(&report, settlement_date, &seen_references)
+            .await?;
+
+        self.reports
+            .insert_one(&report, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to store settlement report: {}", e),
+            })?;
+
+        info!(
+            "Reconciled Baray settlement report for {}: {} lines, {} matched, {} discrepancies",
+            report.settlement_date, report.line_count, report.matched_count, report.discrepancy_count
+        );
+        Ok(report)
+    }
+
+    /// Completed payouts from the settlement day that Baray did not report
+    async fn flag_missing_payouts(
+        &self,
+        report: &SettlementReport,
+        settlement_date: NaiveDate,
+        seen_references: &HashSet<String>,
+    ) -> Result<u32> {
+        let day_start = settlement_date.and_hms_opt(0, 0, 0).unwrap().and_utc();
+        let day_end = stored_timestamp(day_start + chrono::Duration::days(1));
+        let day_start = stored_timestamp(day_start);
+
+        let mut cursor = self
+            .payouts
+            .find(
+                mongodb::bson::doc! {
+                    "status": format!("{:?}", PayoutStatus::Completed),
+                    "baray_reference": {"$ne": null},
+                    "created_at": {"$gte": day_start, "$lt": day_end}
+                },
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to query payouts: {}", e),
+            })?;
+
+        let mut flagged = 0;
+        while let Some(payout) = cursor.try_next().await.map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to iterate payouts: {}", e),
+        })? {
+            let reference = payout.baray_reference.clone().unwrap_or_default();
+            if seen_references.contains(&reference) {
+                continue;
+            }
+
+            let mut discrepancy = SettlementDiscrepancy::new(
+                report.id.clone(),
+                DiscrepancyKind::MissingFromReport,
+                format!("Payout {} not present in Baray settlement report", payout.id),
+            );
+            discrepancy.payout_id = Some(payout.id.clone());
+            discrepancy.reference = Some(reference);
+            discrepancy.expected_amount = Some(payout.amount);
+            if self.flag(discrepancy).await? {
+                flagged += 1;
+            }
+            self.set_reconciliation(&payout.id, "Discrepancy").await?;
+        }
+
+        Ok(flagged)
+    }
+
+    /// Check the payout's ledger transaction: it must exist, debit the
+    /// provider what the payout drew from their earnings, and not have been
+    /// reversed if Baray says the transfer went through
+    async fn compare_ledger(
+        &self,
+        payout: &Payout,
+        line: &SettlementLine,
+    ) -> Result<Option<(DiscrepancyKind, String)>> {
+        let Some(transaction) = self
+            .ledger
+            .find(LedgerTransactionKind::Payout, &payout.id)
+            .await?
+        else {
+            return Ok(Some((
+                DiscrepancyKind::LedgerMismatch,
+                format!("Payout {} has no ledger transaction", payout.id),
+            )));
+        };
+
+        let debited = transaction
+            .postings
+            .iter()
+            .filter(|posting| {
+                posting.account_kind == LedgerAccountKind::Provider
+                    && posting.account_id == payout.provider_id
+            })
+            .map(|posting| -posting.amount)
+            .sum::<Money>();
+        let drawn = payout.native_amount();
+        if debited != drawn {
+            return Ok(Some((
+                DiscrepancyKind::LedgerMismatch,
+                format!(
+                    "Ledger debited {} PPT but the payout drew {} PPT",
+                    debited, drawn
+                ),
+            )));
+        }
+
+        if line.is_settled()
+            && self
+                .ledger
+                .find(LedgerTransactionKind::PayoutReversal, &payout.id)
+                .await?
+                .is_some()
+        {
+            return Ok(Some((
+                DiscrepancyKind::LedgerMismatch,
+                format!(
+                    "Baray settled payout {} but the ledger returned it to the provider",
+                    payout.id
+                ),
+            )));
+        }
+
+        Ok(None)
+    }
+
+    /// Store a discrepancy unless the same one is already open (re-imported report)
+    async fn flag(&self, discrepancy: SettlementDiscrepancy) -> Result<bool> {
+        let existing = self
+            .discrepancies
+            .find_one(
+                mongodb::bson::doc! {
+                    "reference": discrepancy.reference.clone(),
+                    "payout_id": discrepancy.payout_id.clone(),
+                    "kind": format!("{:?}", discrepancy.kind),
+                    "status": "Open"
+                },
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to check settlement discrepancies: {}", e),
+            })?;
+        if existing.is_some() {
+            return Ok(false);
+        }
+
+        warn!("Settlement discrepancy: {}", discrepancy.details);
+        self.discrepancies
+            .insert_one(&discrepancy, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to store settlement discrepancy: {}", e),
+            })?;
+        Ok(true)
+    }
+
+    async fn set_reconciliation(&self, payout_id: &str, status: &str) -> Result<()> {
+        let now = stored_timestamp(chrono::Utc::now());
+        self.payouts
+            .update_one(
+                mongodb::bson::doc! {"id": payout_id},
+                mongodb::bson::doc! {
+                    "$set": {
+                        "reconciliation_status": status,
+                        "reconciled_at": &now,
+                        "updated_at": &now,
+                    }
+                },
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to update payout reconciliation: {}", e),
+            })?;
+        Ok(())
+    }
+}
+
+/// Parse a settlement report exported from the Baray dashboard
+/// (columns: reference, amount, currency, status, settled_at)
+pub fn parse_settlement_csv(data: &str) -> Result<Vec<SettlementLine>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(data.as_bytes());
+
+    reader
+        .deserialize::<SettlementLine>()
+        .enumerate()
+        .map(|(index, row)| {
+            row.map_err(|e| PeerPowerError::ValidationError {
+                field: "csv".to_string(),
+                message: format!("Invalid settlement row {}: {}", index + 1, e),
+            })
+        })
+        .collect()
+}
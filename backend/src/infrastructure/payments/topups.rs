@@ -0,0 +1,402 @@
+use axum::http::HeaderMap;
+use mongodb::{Collection, Database};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use super::{BarayClient, ExchangeRates};
+use crate::config::BarayConfig;
+use crate::domain::entities::{LedgerTransaction, PayoutCurrency, TopUp, TopUpStatus};
+use crate::infrastructure::ledger::Ledger;
+use crate::shared::utils::{stored_timestamp, verify_hmac_sha256_hex};
+use crate::shared::{PeerPowerError, Result};
+
+pub const BARAY_TIMESTAMP_HEADER: &str = "x-baray-timestamp";
+pub const BARAY_SIGNATURE_HEADER: &str = "x-baray-signature";
+
+/// Event Baray posts to `/webhooks/baray`; `data` depends on the type
+#[derive(Debug, Clone, Deserialize)]
+pub struct BarayEvent {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub data: serde_json::Value,
+}
+
+impl BarayEvent {
+    /// The event's `data` as the shape its type carries
+    pub fn data<T: DeserializeOwned>(&self) -> Result<T> {
+        T::deserialize(&self.data).map_err(|e| PeerPowerError::InvalidPayload {
+            message: format!("Invalid data for Baray event {}: {}", self.event_type, e),
+        })
+    }
+}
+
+/// `data` of `checkout.*` events
+#[derive(Debug, Clone, Deserialize)]
+pub struct BarayPaymentData {
+    pub session_id: String,
+    pub reference: String, // our top-up id
+    pub amount: f64,
+    pub currency: PayoutCurrency,
+    #[serde(default)]
+    pub failure_reason: Option<String>,
+}
+
+/// Client top-ups through Baray checkout.
+///
+/// A top-up fixes the PPT it will credit when the session is created. Baray
+/// reports the outcome through a webhook signed with `BARAY_WEBHOOK_SECRET`
+/// over `"{timestamp}.{body}"`. The credit is posted to the ledger keyed by
+/// top-up id, so redelivered events credit the client once.
+pub struct TopUpService {
+    topups: Collection<TopUp>,
+    baray: Arc<BarayClient>,
+    exchange_rates: Arc<ExchangeRates>,
+    ledger: Arc<Ledger>,
+    config: BarayConfig,
+}
+
+impl TopUpService {
+    pub fn new(
+        database: Arc<Database>,
+        baray: Arc<BarayClient>,
+        exchange_rates: Arc<ExchangeRates>,
+        ledger: Arc<Ledger>,
+        config: BarayConfig,
+    ) -> Self {
+        Self {
+            topups: database.collection("topups"),
+            baray,
+            exchange_rates,
+            ledger,
+            config,
+        }
+    }
+
+    /// Record a pending top-up and open its Baray checkout session
+    pub async fn create(
+        &self,
+        user_id: &str,
+        amount: f64,
+        currency: PayoutCurrency,
+    ) -> Result<TopUp> {
+        if !self.baray.is_configured() {
+            return Err(PeerPowerError::Configuration {
+                message: "Baray payments are not configured".to_string(),
+            });
+        }
+
+        let rate = self.exchange_rates.ppt_rate(currency).await?;
+        let mut topup = TopUp::new(
+            user_id.to_string(),
+            currency.round(amount),
+            currency,
+            rate.rate,
+        );
+        self.topups
+            .insert_one(&topup, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create top-up: {}", e),
+            })?;
+
+        let session = match self
+            .baray
+            .create_checkout_session(
+                &topup.id,
+                topup.amount,
+                currency,
+                &format!("PeerPower credit: {} PPT", topup.credit_ppt),
+            )
+            .await
+        {
+            Ok(session) => session,
+            Err(e) => {
+                self.finish(&topup.id, TopUpStatus::Failed, Some(e.to_string()))
+                    .await?;
+                return Err(e);
+            }
+        };
+
+        topup.baray_session_id = Some(session.id);
+        topup.checkout_url = Some(session.checkout_url);
+        topup.expires_at = session.expires_at;
+        topup.updated_at = crate::shared::utils::now();
+        self.topups
+            .update_one(
+                mongodb::bson::doc! {"id": &topup.id},
+                mongodb::bson::doc! {
+                    "$set": {
+                        "baray_session_id": &topup.baray_session_id,
+                        "checkout_url": &topup.checkout_url,
+                        "expires_at": topup.expires_at.map(stored_timestamp),
+                        "updated_at": stored_timestamp(topup.updated_at),
+                    }
+                },
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to update top-up: {}", e),
+            })?;
+
+        metrics::counter!("topups_created_total", "currency" => currency.as_str()).increment(1);
+        Ok(topup)
+    }
+
+    /// Check the signature headers against the raw webhook body
+    pub fn verify_webhook(&self, headers: &HeaderMap, body: &[u8]) -> Result<()> {
+        Self::verify_signature(
+            &self.config,
+            headers,
+            body,
+            crate::shared::utils::now().timestamp(),
+        )
+    }
+
+    fn verify_signature(
+        config: &BarayConfig,
+        headers: &HeaderMap,
+        body: &[u8],
+        now: i64,
+    ) -> Result<()> {
+        let reject = |reason: &'static str, detail: &str| {
+            metrics::counter!("baray_webhook_rejected_total", "reason" => reason).increment(1);
+            PeerPowerError::AuthenticationFailed {
+                reason: detail.to_string(),
+            }
+        };
+        if config.webhook_secret.is_empty() {
+            return Err(reject("unconfigured", "Baray webhooks are not configured"));
+        }
+
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+        };
+        let (Some(timestamp), Some(signature)) = (
+            header(BARAY_TIMESTAMP_HEADER),
+            header(BARAY_SIGNATURE_HEADER),
+        ) else {
+            return Err(reject("unsigned", "Missing Baray signature headers"));
+        };
+
+        let signed_at: i64 = timestamp
+            .parse()
+            .map_err(|_| reject("bad_timestamp", "Invalid Baray webhook timestamp"))?;
+        if (now - signed_at).abs() > config.webhook_max_skew_seconds {
+            return Err(reject(
+                "stale",
+                "Baray webhook timestamp outside the allowed window",
+            ));
+        }
+
+        let mut payload = format!("{}.", timestamp).into_bytes();
+        payload.extend_from_slice(body);
+        let signature = signature.strip_prefix("sha256=").unwrap_or(signature);
+        if !verify_hmac_sha256_hex(&config.webhook_secret, &payload, signature) {
+            return Err(reject("bad_signature", "Invalid Baray webhook signature"));
+        }
+        Ok(())
+    }
+
+    /// Apply a verified payment event, returning the top-up if this call
+    /// settled it (`None` for redeliveries and unrelated events)
+    pub async fn apply_event(&self, event: &BarayEvent) -> Result<Option<TopUp>> {
+        let data: BarayPaymentData = event.data()?;
+        let topup = self
+            .topups
+            .find_one(mongodb::bson::doc! {"id": &data.reference}, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch top-up: {}", e),
+            })?
+            .ok_or_else(|| PeerPowerError::NotFound {
+                resource: format!("Top-up with reference: {}", data.reference),
+            })?;
+
+        if topup.baray_session_id.as_deref() != Some(data.session_id.as_str()) {
+            warn!(
+                "Baray event {} names session {} but top-up {} has {:?}",
+                event.id, data.session_id, topup.id, topup.baray_session_id
+            );
+            return Err(PeerPowerError::ValidationError {
+                field: "session_id".to_string(),
+                message: "Checkout session does not match the top-up".to_string(),
+            });
+        }
+
+        let (status, failure_reason) = match event.event_type.as_str() {
+            "checkout.completed" => {
+                if data.currency != topup.currency
+                    || topup.currency.round(data.amount) != topup.amount
+                {
+                    warn!(
+                        "Top-up {} paid {} {} but expected {} {}",
+                        topup.id,
+                        data.amount,
+                        data.currency.as_str(),
+                        topup.amount,
+                        topup.currency.as_str()
+                    );
+                    metrics::counter!("topup_amount_mismatch_total").increment(1);
+                    (
+                        TopUpStatus::Failed,
+                        Some(format!(
+                            "Paid {} {} instead of {} {}",
+                            data.amount,
+                            data.currency.as_str(),
+                            topup.amount,
+                            topup.currency.as_str()
+                        )),
+                    )
+                } else {
+                    (TopUpStatus::Completed, None)
+                }
+            }
+            "checkout.failed" => (
+                TopUpStatus::Failed,
+                data.failure_reason
+                    .clone()
+                    .or_else(|| Some("Payment failed".to_string())),
+            ),
+            "checkout.expired" => (TopUpStatus::Expired, None),
+            other => {
+                info!("Ignoring Baray event {} of type {}", event.id, other);
+                return Ok(None);
+            }
+        };
+
+        if topup.status != TopUpStatus::Pending {
+            info!(
+                "Top-up {} already settled, ignoring event {}",
+                topup.id, event.id
+            );
+            return Ok(None);
+        }
+        // Credit before marking the top-up settled: if the status update
+        // fails, Baray's retry finds it still pending and the posting is a no-op
+        if status == TopUpStatus::Completed
+            && self
+                .ledger
+                .post(&LedgerTransaction::top_up(
+                    &topup.id,
+                    &topup.user_id,
+                    topup.credit_ppt,
+                ))
+                .await?
+        {
+            info!(
+                "Credited {} PPT to {} for top-up {}",
+                topup.credit_ppt, topup.user_id, topup.id
+            );
+        }
+        if !self.finish(&topup.id, status, failure_reason).await? {
+            return Ok(None);
+        }
+        metrics::counter!("topups_settled_total", "status" => format!("{:?}", status)).increment(1);
+
+        let mut topup = topup;
+        topup.status = status;
+        Ok(Some(topup))
+    }
+
+    /// Move a pending top-up to its final status; false if it was already settled
+    async fn finish(
+        &self,
+        topup_id: &str,
+        status: TopUpStatus,
+        failure_reason: Option<String>,
+    ) -> Result<bool> {
+        let result = self
+            .topups
+            .update_one(
+                mongodb::bson::doc! {"id": topup_id, "status": "Pending"},
+                finished(status, failure_reason),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to update top-up: {}", e),
+            })?;
+        Ok(result.modified_count == 1)
+    }
+}
+
+/// Update settling a pending top-up
+fn finished(status: TopUpStatus, failure_reason: Option<String>) -> mongodb::bson::Document {
+    let now = stored_timestamp(crate::shared::utils::now());
+    let completed_at = (status == TopUpStatus::Completed).then(|| now.clone());
+    mongodb::bson::doc! {
+        "$set": {
+            "status": format!("{:?}", status),
+            "failure_reason": failure_reason,
+            "completed_at": completed_at,
+            "updated_at": now,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::test_support::read_back;
+    use crate::shared::utils::hmac_sha256_hex;
+
+    const NOW: i64 = 1_800_000_000;
+
+    #[test]
+    fn test_baray_webhook_signature_covers_timestamp_and_body() {
+        let config = BarayConfig {
+            api_key: "key".to_string(),
+            webhook_secret: "whsec".to_string(),
+            base_url: "https://api.baray.io".to_string(),
+            checkout_return_url: "https://peerpower.app/topup/complete".to_string(),
+            webhook_max_skew_seconds: 300,
+        };
+        let body = br#"{"id":"evt_1","type":"checkout.completed"}"#;
+        let signed = |timestamp: i64, body: &[u8]| {
+            let mut payload = format!("{}.", timestamp).into_bytes();
+            payload.extend_from_slice(body);
+            let mut headers = HeaderMap::new();
+            headers.insert(BARAY_TIMESTAMP_HEADER, timestamp.into());
+            headers.insert(
+                BARAY_SIGNATURE_HEADER,
+                hmac_sha256_hex("whsec", &payload).parse().unwrap(),
+            );
+            headers
+        };
+
+        let headers = signed(NOW - 10, body);
+        assert!(TopUpService::verify_signature(&config, &headers, body, NOW).is_ok());
+        assert!(TopUpService::verify_signature(
+            &config,
+            &headers,
+            br#"{"id":"evt_1","type":"checkout.failed"}"#,
+            NOW
+        )
+        .is_err());
+        assert!(TopUpService::verify_signature(&config, &headers, body, NOW + 400).is_err());
+        assert!(TopUpService::verify_signature(&config, &HeaderMap::new(), body, NOW).is_err());
+    }
+
+    #[test]
+    fn test_completed_topup_reads_back() {
+        let topup = TopUp::new("user-1".to_string(), 4000.0, PayoutCurrency::Khr, 4000.0);
+        let completed = read_back(&topup, &finished(TopUpStatus::Completed, None));
+        assert_eq!(completed.status, TopUpStatus::Completed);
+        assert!(completed.completed_at.is_some());
+
+        let failed = read_back(
+            &topup,
+            &finished(TopUpStatus::Failed, Some("declined".to_string())),
+        );
+        assert!(failed.completed_at.is_none());
+        assert_eq!(failed.failure_reason.as_deref(), Some("declined"));
+    }
+}
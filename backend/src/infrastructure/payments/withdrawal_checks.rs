@@ -0,0 +1,132 @@
+use chrono::{DateTime, Duration, Utc};
+
+use crate::config::PayoutConfig;
+use crate::domain::entities::{Payout, PayoutStatus};
+use crate::shared::{Money, PeerPowerError, Result};
+
+/// Review flags a withdrawal can be held with
+pub const FLAG_VELOCITY: &str = "velocity";
+pub const FLAG_LARGE_AMOUNT: &str = "large_amount";
+
+/// Check a withdrawal of `amount` PPT against the payout limits.
+///
+/// Breaking a hard limit (minimum, daily cap, new-provider hold) is an
+/// error. Otherwise the result lists the reasons, if any, the payout should
+/// wait for an admin instead of being approved automatically. `recent` is
+/// the provider's payouts from at least the last 24 hours.
+pub fn check_withdrawal(
+    config: &PayoutConfig,
+    provider_created_at: DateTime<Utc>,
+    amount: Money,
+    recent: &[Payout],
+    now: DateTime<Utc>,
+) -> Result<Vec<&'static str>> {
+    if !amount.is_positive() || amount < Money::from_ppt(config.min_payout_ppt) {
+        return Err(PeerPowerError::ValidationError {
+            field: "amount".to_string(),
+            message: format!("Minimum payout is {} PPT", config.min_payout_ppt),
+        });
+    }
+
+    let hold_ends = provider_created_at + Duration::days(config.new_provider_hold_days);
+    if now < hold_ends {
+        return Err(PeerPowerError::ValidationError {
+            field: "amount".to_string(),
+            message: format!(
+                "Earnings of new providers are held for {} days; withdrawals open {}",
+                config.new_provider_hold_days,
+                hold_ends.format("%Y-%m-%d %H:%M UTC")
+            ),
+        });
+    }
+
+    // Refused and failed payouts were returned to the balance, so they don't count
+    let counted = |since: DateTime<Utc>| {
+        recent
+            .iter()
+            .filter(move |payout| payout.status != PayoutStatus::Failed)
+            .filter(move |payout| payout.created_at >= since)
+    };
+    let daily_cap = Money::from_ppt(config.daily_cap_ppt);
+    let withdrawn_today: Money = counted(now - Duration::hours(24))
+        .map(|payout| payout.native_amount())
+        .sum();
+    if withdrawn_today + amount > daily_cap {
+        return Err(PeerPowerError::ValidationError {
+            field: "amount".to_string(),
+            message: format!(
+                "Withdrawals are capped at {} PPT a day; {} PPT left for now",
+                config.daily_cap_ppt,
+                (daily_cap - withdrawn_today).max(Money::ZERO)
+            ),
+        });
+    }
+
+    let mut flags = Vec::new();
+    let window = now - Duration::minutes(config.velocity_window_minutes);
+    if counted(window).count() >= config.velocity_max_requests as usize {
+        flags.push(FLAG_VELOCITY);
+    }
+    if amount > Money::from_ppt(config.review_threshold_ppt) {
+        flags.push(FLAG_LARGE_AMOUNT);
+    }
+    Ok(flags)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> PayoutConfig {
+        PayoutConfig {
+            batch_interval_seconds: 900,
+            batch_size: 50,
+            max_attempts: 3,
+            auto_approve: true,
+            min_payout_ppt: 1.0,
+            daily_cap_ppt: 500.0,
+            velocity_max_requests: 2,
+            velocity_window_minutes: 60,
+            review_threshold_ppt: 100.0,
+            new_provider_hold_days: 7,
+        }
+    }
+
+    fn payout(amount: f64, minutes_ago: i64, now: DateTime<Utc>) -> Payout {
+        let mut payout = Payout::new("prov-1".to_string(), amount, "PPT".to_string(), None);
+        payout.created_at = now - Duration::minutes(minutes_ago);
+        payout
+    }
+
+    #[test]
+    fn test_withdrawal_limits_and_review_flags() {
+        let config = config();
+        let now = Utc::now();
+        let registered = now - Duration::days(30);
+        let ppt = Money::from_ppt;
+
+        assert!(check_withdrawal(&config, registered, ppt(0.5), &[], now).is_err());
+        assert!(check_withdrawal(&config, now - Duration::days(2), ppt(10.0), &[], now).is_err());
+        assert!(check_withdrawal(&config, registered, ppt(10.0), &[], now)
+            .unwrap()
+            .is_empty());
+
+        // 450 PPT already out today; the cap ignores failed payouts and older ones
+        let mut failed = payout(300.0, 30, now);
+        failed.status = PayoutStatus::Failed;
+        let recent = vec![payout(450.0, 600, now), failed, payout(400.0, 60 * 30, now)];
+        assert!(check_withdrawal(&config, registered, ppt(60.0), &recent, now).is_err());
+        assert!(
+            check_withdrawal(&config, registered, ppt(50.0), &recent, now)
+                .unwrap()
+                .is_empty()
+        );
+
+        // Third request within the hour, and a large one
+        let burst = vec![payout(5.0, 10, now), payout(5.0, 20, now)];
+        assert_eq!(
+            check_withdrawal(&config, registered, ppt(150.0), &burst, now).unwrap(),
+            vec![FLAG_VELOCITY, FLAG_LARGE_AMOUNT]
+        );
+    }
+}
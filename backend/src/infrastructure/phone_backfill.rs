@@ -0,0 +1,116 @@
+use futures::stream::TryStreamExt;
+use mongodb::bson::{doc, Document};
+use mongodb::Database;
+use serde::Serialize;
+use std::sync::Arc;
+use tracing::info;
+
+use crate::shared::field_encryption;
+use crate::shared::types::PhoneNumber;
+use crate::shared::{PeerPowerError, Result};
+
+/// Collections and fields holding phone numbers that are stored encrypted
+const ENCRYPTED_FIELDS: &[(&str, &str)] = &[
+    ("users", "phone"),
+    ("providers", "phone"),
+    ("messages", "recipient"),
+    ("telegram_links", "phone"),
+];
+
+#[derive(Debug, Default, Serialize)]
+pub struct PhoneBackfillReport {
+    pub users: u64,
+    pub providers: u64,
+    pub messages: u64,
+    pub telegram_links: u64,
+}
+
+/// Encrypts phone numbers written before `PHONE_ENCRYPTION_KEY` was set.
+/// Safe to re-run: already encrypted values are skipped, and lookups match
+/// both forms until it has finished.
+pub struct PhoneEncryptionBackfill {
+    database: Arc<Database>,
+}
+
+impl PhoneEncryptionBackfill {
+    pub fn new(database: Arc<Database>) -> Self {
+        Self { database }
+    }
+
+    pub async fn run(&self) -> Result<PhoneBackfillReport> {
+        if !field_encryption::is_enabled() {
+            return Err(PeerPowerError::Configuration {
+                message: "PHONE_ENCRYPTION_KEY must be set to encrypt stored phone numbers"
+                    .to_string(),
+            });
+        }
+
+        let mut report = PhoneBackfillReport::default();
+        for (collection, field) in ENCRYPTED_FIELDS {
+            let encrypted = self.encrypt_collection(collection, field).await?;
+            match *collection {
+                "users" => report.users = encrypted,
+                "providers" => report.providers = encrypted,
+                "messages" => report.messages = encrypted,
+                _ => report.telegram_links = encrypted,
+            }
+            info!("Encrypted {} phone numbers in {}", encrypted, collection);
+        }
+        Ok(report)
+    }
+
+    async fn encrypt_collection(&self, collection_name: &str, field: &str) -> Result<u64> {
+        let collection = self.database.collection::<Document>(collection_name);
+        let mut cursor = collection
+            .find(
+                doc! { field: {"$type": "string", "$not": {"$regex": "^enc:"}} },
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!(
+                    "Failed to scan {} for phone numbers: {}",
+                    collection_name, e
+                ),
+            })?;
+
+        let mut encrypted = 0;
+        while let Some(document) =
+            cursor
+                .try_next()
+                .await
+                .map_err(|e| PeerPowerError::Database {
+                    message: format!("Failed to read {}: {}", collection_name, e),
+                })?
+        {
+            let (Ok(id), Ok(plaintext)) = (document.get_object_id("_id"), document.get_str(field))
+            else {
+                continue;
+            };
+
+            let mut set = doc! {
+                field: field_encryption::encrypt_phone(&PhoneNumber::from_stored(
+                    plaintext.to_string(),
+                )),
+            };
+            // Reporting groups messages by prefix, which it can no longer read off the recipient
+            if collection_name == "messages" && !document.contains_key("recipient_prefix") {
+                set.insert(
+                    "recipient_prefix",
+                    plaintext.chars().take(6).collect::<String>(),
+                );
+            }
+
+            // Only if unchanged since it was read, so a concurrent write isn't overwritten
+            let result = collection
+                .update_one(doc! {"_id": id, field: plaintext}, doc! {"$set": set}, None)
+                .await
+                .map_err(|e| PeerPowerError::Database {
+                    message: format!("Failed to encrypt phone in {}: {}", collection_name, e),
+                })?;
+            encrypted += result.modified_count;
+        }
+
+        Ok(encrypted)
+    }
+}
@@ -0,0 +1,373 @@
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+use crate::config::PlayIntegrityConfig;
+use crate::domain::entities::{DeviceAttestation, DeviceKey};
+use crate::shared::{PeerPowerError, Result};
+
+const PLAY_INTEGRITY_SCOPE: &str = "https://www.googleapis.com/auth/playintegrity";
+
+/// Google service account credentials, as downloaded from the cloud console
+#[derive(Debug, Clone, Deserialize)]
+struct ServiceAccountKey {
+    client_email: String,
+    private_key: String,
+    token_uri: String,
+}
+
+#[derive(Debug, Serialize)]
+struct AssertionClaims<'a> {
+    iss: &'a str,
+    scope: &'a str,
+    aud: &'a str,
+    iat: i64,
+    exp: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct OAuthTokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DecodeIntegrityTokenResponse {
+    token_payload_external: IntegrityPayload,
+}
+
+/// Decoded integrity verdict, trimmed to the fields we check
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct IntegrityPayload {
+    pub request_details: RequestDetails,
+    pub app_integrity: AppIntegrity,
+    pub device_integrity: DeviceIntegrity,
+    pub account_details: AccountDetails,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RequestDetails {
+    pub request_package_name: String,
+    pub nonce: String,
+    pub timestamp_millis: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AppIntegrity {
+    pub app_recognition_verdict: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DeviceIntegrity {
+    pub device_recognition_verdict: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AccountDetails {
+    pub app_licensing_verdict: Option<String>,
+}
+
+/// Verifies Play Integrity tokens from the provider app.
+///
+/// The app requests a token with the device key fingerprint as its nonce
+/// and sends it with registration. Tokens are decoded through Google's
+/// `decodeIntegrityToken` API using the configured service account; the
+/// verdict is stored on the provider whether or not it passed, and only a
+/// passing verdict lets admins raise the daily limit past the unattested cap.
+pub struct PlayIntegrityVerifier {
+    config: PlayIntegrityConfig,
+    client: Client,
+    service_account: Option<ServiceAccountKey>,
+    access_token: RwLock<Option<(String, Instant)>>,
+}
+
+impl PlayIntegrityVerifier {
+    pub fn new(config: PlayIntegrityConfig) -> Result<Self> {
+        let service_account = match &config.service_account_key_file {
+            Some(path) => {
+                let raw =
+                    std::fs::read_to_string(path).map_err(|e| PeerPowerError::Configuration {
+                        message: format!(
+                            "Failed to read PLAY_INTEGRITY_SERVICE_ACCOUNT_FILE: {}",
+                            e
+                        ),
+                    })?;
+                let key = serde_json::from_str::<ServiceAccountKey>(&raw).map_err(|e| {
+                    PeerPowerError::Configuration {
+                        message: format!("Invalid Play Integrity service account key: {}", e),
+                    }
+                })?;
+                if config.package_name.is_empty() {
+                    return Err(PeerPowerError::Configuration {
+                        message: "PLAY_INTEGRITY_PACKAGE_NAME is required for device attestation"
+                            .to_string(),
+                    });
+                }
+                Some(key)
+            }
+            None => None,
+        };
+
+        let client = Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .unwrap_or_default();
+
+        Ok(Self {
+            config,
+            client,
+            service_account,
+            access_token: RwLock::new(None),
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.service_account.is_some()
+    }
+
+    pub fn unattested_max_daily_messages(&self) -> u32 {
+        self.config.unattested_max_daily_messages
+    }
+
+    /// Attestation at registration: checked when a token is sent, and
+    /// required when configured so
+    pub async fn attest_registration(
+        &self,
+        integrity_token: Option<&str>,
+        device_key: &DeviceKey,
+    ) -> Result<Option<DeviceAttestation>> {
+        if !self.is_enabled() {
+            return Ok(None);
+        }
+        match integrity_token.filter(|token| !token.is_empty()) {
+            Some(token) => self.verify(token, device_key).await.map(Some),
+            None if self.config.required_at_registration => Err(PeerPowerError::ValidationError {
+                field: "integrity_token".to_string(),
+                message: "A Play Integrity token is required to register a device".to_string(),
+            }),
+            None => Ok(None),
+        }
+    }
+
+    /// Decode a token and turn it into a verdict for this device
+    pub async fn verify(
+        &self,
+        integrity_token: &str,
+        device_key: &DeviceKey,
+    ) -> Result<DeviceAttestation> {
+        if !self.is_enabled() {
+            return Err(PeerPowerError::Configuration {
+                message: "Play Integrity attestation is not configured".to_string(),
+            });
+        }
+
+        let url = format!(
+            "https://playintegrity.googleapis.com/v1/{}:decodeIntegrityToken",
+            self.config.package_name
+        );
+        let response = self
+            .client
+            .post(&url)
+            .bearer_auth(self.access_token().await?)
+            .json(&serde_json::json!({ "integrity_token": integrity_token }))
+            .send()
+            .await?;
+
+        if response.status().is_client_error() {
+            metrics::counter!("play_integrity_rejected_total", "reason" => "undecodable")
+                .increment(1);
+            return Err(PeerPowerError::ValidationError {
+                field: "integrity_token".to_string(),
+                message: "Play Integrity token could not be decoded".to_string(),
+            });
+        }
+        if !response.status().is_success() {
+            return Err(PeerPowerError::ExternalService {
+                service: "play_integrity".to_string(),
+                message: format!("decodeIntegrityToken returned {}", response.status()),
+            });
+        }
+        let decoded: DecodeIntegrityTokenResponse = response.json().await?;
+
+        let attestation = Self::evaluate(
+            &decoded.token_payload_external,
+            &self.config,
+            &device_key.fingerprint,
+            crate::shared::utils::now().timestamp_millis(),
+        )
+        .map_err(|reason| {
+            metrics::counter!("play_integrity_rejected_total", "reason" => reason).increment(1);
+            PeerPowerError::ValidationError {
+                field: "integrity_token".to_string(),
+                message: format!("Play Integrity token rejected: {}", reason),
+            }
+        })?;
+
+        if !attestation.passed {
+            warn!(
+                "Device {} failed attestation: app {}, device {:?}",
+                device_key.fingerprint, attestation.app_verdict, attestation.device_verdicts
+            );
+        }
+        metrics::counter!("play_integrity_verdicts_total", "passed" => attestation.passed.to_string())
+            .increment(1);
+        Ok(attestation)
+    }
+
+    /// Check that a decoded token was requested for this app and device
+    /// recently, and summarize its verdict
+    pub fn evaluate(
+        payload: &IntegrityPayload,
+        config: &PlayIntegrityConfig,
+        fingerprint: &str,
+        now_millis: i64,
+    ) -> std::result::Result<DeviceAttestation, &'static str> {
+        let details = &payload.request_details;
+        if details.request_package_name != config.package_name {
+            return Err("wrong package");
+        }
+        if details.nonce != fingerprint {
+            return Err("nonce does not match the device key");
+        }
+        let requested_at: i64 = details
+            .timestamp_millis
+            .parse()
+            .map_err(|_| "missing timestamp")?;
+        if (now_millis - requested_at).abs() > config.max_token_age_seconds * 1000 {
+            return Err("token expired");
+        }
+
+        let device_verdicts = payload.device_integrity.device_recognition_verdict.clone();
+        let app_verdict = payload.app_integrity.app_recognition_verdict.clone();
+        let passed = app_verdict == "PLAY_RECOGNIZED"
+            && device_verdicts.iter().any(|verdict| {
+                verdict == "MEETS_DEVICE_INTEGRITY" || verdict == "MEETS_STRONG_INTEGRITY"
+            });
+
+        Ok(DeviceAttestation {
+            device_verdicts,
+            app_verdict,
+            licensing_verdict: payload.account_details.app_licensing_verdict.clone(),
+            passed,
+            attested_at: crate::shared::utils::now(),
+        })
+    }
+
+    /// OAuth access token for the service account, reused until shortly before it expires
+    async fn access_token(&self) -> Result<String> {
+        if let Some((token, expires_at)) = self.access_token.read().await.as_ref() {
+            if Instant::now() < *expires_at {
+                return Ok(token.clone());
+            }
+        }
+
+        let account =
+            self.service_account
+                .as_ref()
+                .ok_or_else(|| PeerPowerError::Configuration {
+                    message: "Play Integrity attestation is not configured".to_string(),
+                })?;
+        let now = crate::shared::utils::now().timestamp();
+        let claims = AssertionClaims {
+            iss: &account.client_email,
+            scope: PLAY_INTEGRITY_SCOPE,
+            aud: &account.token_uri,
+            iat: now,
+            exp: now + 3600,
+        };
+        let key = EncodingKey::from_rsa_pem(account.private_key.as_bytes()).map_err(|e| {
+            PeerPowerError::Configuration {
+                message: format!("Invalid Play Integrity service account key: {}", e),
+            }
+        })?;
+        let assertion = encode(&Header::new(Algorithm::RS256), &claims, &key).map_err(|e| {
+            PeerPowerError::Internal {
+                message: format!("Failed to sign service account assertion: {}", e),
+            }
+        })?;
+
+        let response = self
+            .client
+            .post(&account.token_uri)
+            .form(&[
+                ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+                ("assertion", assertion.as_str()),
+            ])
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(PeerPowerError::ExternalService {
+                service: "play_integrity".to_string(),
+                message: format!(
+                    "Service account token exchange returned {}",
+                    response.status()
+                ),
+            });
+        }
+        let token: OAuthTokenResponse = response.json().await?;
+
+        info!("Refreshed Play Integrity access token");
+        let expires_at = Instant::now() + Duration::from_secs(token.expires_in.saturating_sub(60));
+        *self.access_token.write().await = Some((token.access_token.clone(), expires_at));
+        Ok(token.access_token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_integrity_verdict_bound_to_app_and_device() {
+        let config = PlayIntegrityConfig {
+            package_name: "app.peerpower.provider".to_string(),
+            service_account_key_file: None,
+            required_at_registration: false,
+            max_token_age_seconds: 600,
+            unattested_max_daily_messages: 50,
+        };
+        let now = 1_700_000_000_000;
+        let mut payload = IntegrityPayload {
+            request_details: RequestDetails {
+                request_package_name: "app.peerpower.provider".to_string(),
+                nonce: "ab12".to_string(),
+                timestamp_millis: (now - 5_000).to_string(),
+            },
+            app_integrity: AppIntegrity {
+                app_recognition_verdict: "PLAY_RECOGNIZED".to_string(),
+            },
+            device_integrity: DeviceIntegrity {
+                device_recognition_verdict: vec!["MEETS_DEVICE_INTEGRITY".to_string()],
+            },
+            ..Default::default()
+        };
+
+        let attestation = PlayIntegrityVerifier::evaluate(&payload, &config, "ab12", now).unwrap();
+        assert!(attestation.passed);
+
+        // Emulators only meet basic integrity: recorded, but not passed
+        payload.device_integrity.device_recognition_verdict =
+            vec!["MEETS_BASIC_INTEGRITY".to_string()];
+        let attestation = PlayIntegrityVerifier::evaluate(&payload, &config, "ab12", now).unwrap();
+        assert!(!attestation.passed);
+
+        assert_eq!(
+            PlayIntegrityVerifier::evaluate(&payload, &config, "other-key", now).unwrap_err(),
+            "nonce does not match the device key"
+        );
+        assert_eq!(
+            PlayIntegrityVerifier::evaluate(&payload, &config, "ab12", now + 3_600_000)
+                .unwrap_err(),
+            "token expired"
+        );
+    }
+}
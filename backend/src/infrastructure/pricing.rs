@@ -0,0 +1,106 @@
+use crate::config::PricingConfig;
+use crate::domain::entities::{MessagePriority, OTP_CLIENT_ID};
+use crate::shared::Money;
+
+/// Messages are priced per SMS segment
+const SEGMENT_LENGTH: f64 = 160.0;
+
+/// What a message costs the client and the provider's share of it
+#[derive(Debug, Clone, Copy)]
+pub struct MessagePrice {
+    pub charge: f64,
+    pub provider_earnings: f64, // before any time-of-day boost
+}
+
+impl MessagePrice {
+    /// Price a message at `segment_cost` PPT per segment, the client's
+    /// pricing-plan rate (see `PricingPlans::rate_for`)
+    pub fn new(
+        config: &PricingConfig,
+        segment_cost: Money,
+        content: &str,
+        priority: &MessagePriority,
+        message_type: &str,
+    ) -> Self {
+        let segments = (content.len() as f64 / SEGMENT_LENGTH).ceil();
+        let priority_multiplier = match priority {
+            MessagePriority::Low => 0.8,
+            MessagePriority::Normal => 1.0,
+            MessagePriority::High => 1.5,
+            MessagePriority::Urgent => 2.0,
+        };
+        let charge = segment_cost.to_ppt() * segments * priority_multiplier;
+        let fee_percent = config.fee_percent(message_type, priority_key(priority));
+
+        Self {
+            charge,
+            provider_earnings: charge * (100.0 - fee_percent) / 100.0,
+        }
+    }
+
+    /// Scale the charge, and so the provider's share, by a surge multiplier
+    pub fn surged(self, multiplier: f64) -> Self {
+        Self {
+            charge: self.charge * multiplier,
+            provider_earnings: self.provider_earnings * multiplier,
+        }
+    }
+}
+
+/// Message type fee overrides are keyed on: platform OTPs or client traffic
+pub fn message_type(client_id: &str) -> &'static str {
+    if client_id == OTP_CLIENT_ID {
+        "otp"
+    } else {
+        "standard"
+    }
+}
+
+fn priority_key(priority: &MessagePriority) -> &'static str {
+    match priority {
+        MessagePriority::Low => "low",
+        MessagePriority::Normal => "normal",
+        MessagePriority::High => "high",
+        MessagePriority::Urgent => "urgent",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_fee_overrides_by_type_then_priority() {
+        let mut config = PricingConfig {
+            base_message_cost: 0.01,
+            platform_fee_percent: 20.0,
+            fee_percent_overrides: HashMap::new(),
+        };
+        let cost = Money::from_ppt(0.01);
+        let price = MessagePrice::new(&config, cost, "hello", &MessagePriority::Normal, "standard");
+        assert!((price.charge - 0.01).abs() < 1e-12);
+        assert!((price.provider_earnings - 0.008).abs() < 1e-12);
+
+        // A cheaper plan rate lowers the charge; the fee split is unchanged
+        let discounted = MessagePrice::new(
+            &config,
+            Money::from_ppt(0.005),
+            "hello",
+            &MessagePriority::Normal,
+            "standard",
+        );
+        assert!((discounted.provider_earnings - 0.004).abs() < 1e-12);
+
+        config
+            .fee_percent_overrides
+            .insert("urgent".to_string(), 30.0);
+        config.fee_percent_overrides.insert("otp".to_string(), 10.0);
+        let urgent =
+            MessagePrice::new(&config, cost, "hello", &MessagePriority::Urgent, "standard");
+        assert!((urgent.provider_earnings - 0.014).abs() < 1e-12);
+
+        let otp = MessagePrice::new(&config, cost, "hello", &MessagePriority::Urgent, "otp");
+        assert!((otp.provider_earnings - 0.018).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,203 @@
+use futures::stream::TryStreamExt;
+use mongodb::bson::{doc, Document};
+use mongodb::options::{FindOneAndUpdateOptions, FindOptions, ReturnDocument};
+use mongodb::{Collection, Database};
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::config::PricingConfig;
+use crate::domain::entities::number_pool::month_bounds;
+use crate::domain::entities::PricingPlan;
+use crate::shared::utils::stored_timestamp;
+use crate::shared::{Money, PeerPowerError, Result};
+
+/// Pricing plans and the segment cost they give each client.
+///
+/// Tiered plans are priced on the client's messages so far this calendar
+/// month (UTC), counted when a message is sent; the cost is then kept on
+/// the message so confirming it later charges what was quoted.
+pub struct PricingPlans {
+    plans: Collection<PricingPlan>,
+    messages: Collection<Document>,
+    default_segment_cost: Money,
+}
+
+/// A client's plan and the segment cost it gives them right now
+#[derive(Debug, Clone)]
+pub struct ClientRate {
+    pub plan: Option<PricingPlan>, // none: pay-as-you-go at the default rate
+    pub monthly_messages: u64,
+    pub segment_cost: Money,
+}
+
+impl PricingPlans {
+    pub fn new(database: Arc<Database>, config: &PricingConfig) -> Self {
+        Self {
+            plans: database.collection("pricing_plans"),
+            messages: database.collection("messages"),
+            default_segment_cost: Money::from_ppt(config.base_message_cost),
+        }
+    }
+
+    pub async fn create(&self, plan: &PricingPlan) -> Result<()> {
+        self.plans
+            .insert_one(plan, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to create pricing plan: {}", e),
+            })?;
+        Ok(())
+    }
+
+    /// Replace a plan's rates. Clients on it pay the new rates for messages
+    /// sent from now on.
+    pub async fn update(&self, plan_id: &str, plan: &PricingPlan) -> Result<PricingPlan> {
+        self.plans
+            .find_one_and_update(
+                doc! {"id": plan_id},
+                rates(plan)?,
+                FindOneAndUpdateOptions::builder()
+                    .return_document(ReturnDocument::After)
+                    .build(),
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to update pricing plan: {}", e),
+            })?
+            .ok_or_else(|| PeerPowerError::NotFound {
+                resource: format!("Pricing plan with ID: {}", plan_id),
+            })
+    }
+
+    pub async fn get(&self, plan_id: &str) -> Result<PricingPlan> {
+        self.plans
+            .find_one(doc! {"id": plan_id}, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch pricing plan: {}", e),
+            })?
+            .ok_or_else(|| PeerPowerError::NotFound {
+                resource: format!("Pricing plan with ID: {}", plan_id),
+            })
+    }
+
+    /// All plans, oldest first
+    pub async fn list(&self) -> Result<Vec<PricingPlan>> {
+        let find_options = FindOptions::builder().sort(doc! {"created_at": 1}).build();
+        self.plans
+            .find(doc! {}, find_options)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch pricing plans: {}", e),
+            })?
+            .try_collect()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read pricing plans: {}", e),
+            })
+    }
+
+    /// The segment cost for the client's next message under `plan_id`.
+    /// A plan that has since been deleted falls back to the default rate.
+    pub async fn rate_for(&self, client_id: &str, plan_id: Option<&str>) -> Result<ClientRate> {
+        let plan = match plan_id {
+            Some(plan_id) => {
+                let plan = self
+                    .plans
+                    .find_one(doc! {"id": plan_id}, None)
+                    .await
+                    .map_err(|e| PeerPowerError::Database {
+                        message: format!("Failed to fetch pricing plan: {}", e),
+                    })?;
+                if plan.is_none() {
+                    warn!(
+                        "Client {} is on missing pricing plan {}; using the default rate",
+                        client_id, plan_id
+                    );
+                }
+                plan
+            }
+            None => None,
+        };
+
+        let Some(plan) = plan else {
+            return Ok(ClientRate {
+                plan: None,
+                monthly_messages: 0,
+                segment_cost: self.default_segment_cost,
+            });
+        };
+        let monthly_messages = if plan.tiers.is_empty() {
+            0
+        } else {
+            self.monthly_messages(client_id).await?
+        };
+        Ok(ClientRate {
+            segment_cost: plan.segment_cost_at(monthly_messages),
+            monthly_messages,
+            plan: Some(plan),
+        })
+    }
+
+    /// Messages the client has sent since the start of the calendar month
+    async fn monthly_messages(&self, client_id: &str) -> Result<u64> {
+        let (first, _) = month_bounds(chrono::Utc::now().date_naive());
+        let since = stored_timestamp(first.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc());
+        self.messages
+            .count_documents(
+                doc! {"client_id": client_id, "created_at": {"$gte": since}},
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to count monthly messages: {}", e),
+            })
+    }
+}
+
+/// The update giving a stored plan `plan`'s name and rates
+fn rates(plan: &PricingPlan) -> Result<Document> {
+    let kind = mongodb::bson::to_bson(&plan.kind).map_err(|e| PeerPowerError::Internal {
+        message: format!("Failed to serialize plan kind: {}", e),
+    })?;
+    let tiers = mongodb::bson::to_bson(&plan.tiers).map_err(|e| PeerPowerError::Internal {
+        message: format!("Failed to serialize plan tiers: {}", e),
+    })?;
+    Ok(doc! {"$set": {
+        "name": &plan.name,
+        "kind": kind,
+        "segment_cost": plan.segment_cost,
+        "tiers": tiers,
+        "committed_monthly_messages": plan.committed_monthly_messages as i64,
+        "updated_at": stored_timestamp(plan.updated_at),
+    }})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entities::{PricingPlanKind, VolumeTier};
+    use crate::shared::test_support::read_back;
+
+    #[test]
+    fn test_updated_plan_reads_back() {
+        let plan = PricingPlan::new(
+            "Starter".to_string(),
+            PricingPlanKind::PayAsYouGo,
+            Money::from_ppt(0.05),
+            Vec::new(),
+            0,
+            "admin-1".to_string(),
+        );
+        let mut rates_changed = plan.clone();
+        rates_changed.tiers = vec![VolumeTier {
+            min_monthly_messages: 1000,
+            segment_cost: Money::from_ppt(0.04),
+        }];
+        rates_changed.updated_at = crate::shared::utils::now();
+
+        let updated = read_back(&plan, &rates(&rates_changed).unwrap());
+        assert_eq!(updated.tiers, rates_changed.tiers);
+        assert_eq!(updated.updated_at, rates_changed.updated_at);
+    }
+}
@@ -0,0 +1,133 @@
+use tracing::warn;
+
+use crate::domain::entities::Provider;
+use crate::infrastructure::database::RedisConnection;
+use crate::shared::types::{Carrier, ProviderStatus};
+use crate::shared::{PeerPowerError, Result};
+
+/// How long a provider's snapshot outlives its last write; a provider that
+/// stops sending heartbeats drops out of the cache on its own after this
+/// (as long as `Provider::is_heartbeat_recent` tolerates)
+const SNAPSHOT_TTL_SECONDS: usize = 300;
+
+/// Redis view of the providers that can take jobs, so dispatch doesn't
+/// query MongoDB for candidates on every job.
+///
+/// Each online provider is a member of `providers:active:<carrier>` and has
+/// a JSON snapshot at `providers:cache:<id>`. Heartbeats, status changes,
+/// carrier changes and assignments write the provider through; anything
+/// else that changes providers in bulk removes them, and their next
+/// heartbeat puts them back. Dispatch falls back to MongoDB while the cache
+/// has no candidates.
+pub struct ProviderCache {
+    redis: RedisConnection,
+}
+
+impl ProviderCache {
+    pub fn new(redis: RedisConnection) -> Self {
+        Self { redis }
+    }
+
+    fn carrier_key(carrier: &Carrier) -> String {
+        format!("providers:active:{:?}", carrier)
+    }
+
+    fn snapshot_key(provider_id: &str) -> String {
+        format!("providers:cache:{}", provider_id)
+    }
+
+    /// Write `provider` through: cached while online on its carrier,
+    /// removed otherwise
+    pub async fn store(&self, provider: &Provider) -> Result<()> {
+        if provider.status != ProviderStatus::Online || provider.carrier_mismatch.is_some() {
+            return self.remove(&provider.id).await;
+        }
+
+        let snapshot = serde_json::to_string(provider).map_err(|e| PeerPowerError::Internal {
+            message: format!("Failed to serialize provider: {}", e),
+        })?;
+        self.redis
+            .set(
+                &Self::snapshot_key(&provider.id),
+                &snapshot,
+                Some(SNAPSHOT_TTL_SECONDS),
+            )
+            .await?;
+        for carrier in Carrier::ALL {
+            if carrier == provider.carrier {
+                self.redis
+                    .sadd(&Self::carrier_key(&carrier), &provider.id)
+                    .await?;
+            } else {
+                self.redis
+                    .srem(&Self::carrier_key(&carrier), &provider.id)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Drop a provider from the cache, whichever carrier it was cached under
+    pub async fn remove(&self, provider_id: &str) -> Result<()> {
+        for carrier in Carrier::ALL {
+            self.redis
+                .srem(&Self::carrier_key(&carrier), provider_id)
+                .await?;
+        }
+        self.redis.delete(&Self::snapshot_key(provider_id)).await?;
+        Ok(())
+    }
+
+    /// Up to `limit` cached providers able to take a job, the same ones
+    /// `candidates_pipeline` would return: those on `preferred_carrier`
+    /// first, then the longest idle
+    pub async fn candidates(
+        &self,
+        preferred_carrier: Option<&Carrier>,
+        limit: usize,
+    ) -> Result<Vec<Provider>> {
+        let mut members = Vec::new();
+        for carrier in Carrier::ALL {
+            for provider_id in self.redis.smembers(&Self::carrier_key(&carrier)).await? {
+                members.push((carrier.clone(), provider_id));
+            }
+        }
+        if members.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let keys: Vec<String> = members
+            .iter()
+            .map(|(_, provider_id)| Self::snapshot_key(provider_id))
+            .collect();
+        let snapshots = self.redis.mget(&keys).await?;
+
+        let mut candidates = Vec::new();
+        for ((carrier, provider_id), snapshot) in members.into_iter().zip(snapshots) {
+            let Some(snapshot) = snapshot else {
+                // Expired without a heartbeat since
+                self.redis
+                    .srem(&Self::carrier_key(&carrier), &provider_id)
+                    .await?;
+                continue;
+            };
+            match serde_json::from_str::<Provider>(&snapshot) {
+                Ok(provider) => {
+                    if provider.is_available() && provider.dedicated_client_id.is_none() {
+                        candidates.push(provider);
+                    }
+                }
+                Err(e) => warn!("Discarding cached provider {}: {}", provider_id, e),
+            }
+        }
+
+        candidates.sort_by(|a, b| {
+            let preferred = |provider: &Provider| Some(&provider.carrier) == preferred_carrier;
+            preferred(b)
+                .cmp(&preferred(a))
+                .then_with(|| a.last_assigned_at.cmp(&b.last_assigned_at))
+        });
+        candidates.truncate(limit);
+        Ok(candidates)
+    }
+}
@@ -0,0 +1,331 @@
+use chrono::{DateTime, Utc};
+use mongodb::bson::{doc, Bson, Document};
+
+use crate::domain::entities::Provider;
+use crate::shared::types::{Carrier, ProviderStatus};
+
+/// Concurrent jobs a provider can hold (mirrors `Provider::is_available`)
+const MAX_CONCURRENT_LOAD: f64 = 5.0;
+
+/// Minutes after which a provider counts as fully rested since its last assignment
+const REST_WINDOW_MINUTES: f64 = 10.0;
+
+/// Aggregation expression for a provider's effective daily limit, the same
+/// as `Provider::daily_limit`: its own limit, or what its stake unlocks if higher
+pub fn daily_limit_expr() -> Bson {
+    Bson::Document(doc! {
+        "$max": ["$max_daily_messages", {"$ifNull": ["$stake.max_daily_messages", 0_i64]}]
+    })
+}
+
+/// Pipeline fetching up to `limit` dispatch candidates of any carrier:
+/// online, not overloaded, not flagged for a SIM/carrier change, not rented
+/// out as a dedicated number, and under their daily limit. Providers on
+/// `preferred_carrier` come first, so a large pool can't crowd them out,
+/// then the longest idle. The limit is another field of the same document,
+/// so it is compared with `$expr`; a plain query filter can only compare
+/// against constants.
+pub fn candidates_pipeline(preferred_carrier: Option<&Carrier>, limit: i64) -> Vec<Document> {
+    let preferred = match preferred_carrier {
+        Some(carrier) => Bson::Document(doc! {"$eq": ["$carrier", format!("{:?}", carrier)]}),
+        None => Bson::Boolean(false),
+    };
+
+    vec![
+        doc! {"$match": {
+            "status": format!("{:?}", ProviderStatus::Online),
+            "current_load": {"$lt": MAX_CONCURRENT_LOAD as i64},
+            "carrier_mismatch": null,
+            "dedicated_client_id": null,
+            "deleted_at": null,
+        }},
+        doc! {"$match": {"$expr": {"$lt": ["$messages_sent_today", daily_limit_expr()]}}},
+        doc! {"$addFields": {"preferred_carrier": preferred}},
+        doc! {"$sort": {"preferred_carrier": -1, "last_assigned_at": 1}},
+        doc! {"$limit": limit},
+        doc! {"$project": {"preferred_carrier": 0}},
+    ]
+}
+
+/// Relative weight of each selection signal
+#[derive(Debug, Clone)]
+pub struct SelectionWeights {
+    pub reputation: f64,
+    pub load: f64,
+    pub remaining_quota: f64,
+    pub assignment_recency: f64,
+    pub stake_priority: f64, // added on top for providers whose stake earns priority
+}
+
+impl Default for SelectionWeights {
+    fn default() -> Self {
+        Self {
+            reputation: 0.35,
+            load: 0.25,
+            remaining_quota: 0.25,
+            assignment_recency: 0.15,
+            stake_priority: 0.2,
+        }
+    }
+}
+
+/// Scores candidates on reputation, spare capacity, remaining daily quota and
+/// time since their last assignment, plus a boost for staked providers, and
+/// picks the highest score; ties go to the least recently assigned
+#[derive(Debug, Clone, Default)]
+pub struct WeightedProviderSelection {
+    weights: SelectionWeights,
+}
+
+impl WeightedProviderSelection {
+    pub fn new(weights: SelectionWeights) -> Self {
+        Self { weights }
+    }
+
+    /// Highest-scored of `candidates`; ties go to the least recently assigned
+    pub fn best<'a>(
+        &self,
+        candidates: impl IntoIterator<Item = &'a Provider>,
+    ) -> Option<&'a Provider> {
+        let now = crate::shared::utils::now();
+        candidates
+            .into_iter()
+            .map(|provider| (provider, self.score(provider, now)))
+            .max_by(|(a, a_score), (b, b_score)| {
+                a_score
+                    .total_cmp(b_score)
+                    // Never assigned sorts first, then the longest idle
+                    .then_with(|| b.last_assigned_at.cmp(&a.last_assigned_at))
+            })
+            .map(|(provider, _)| provider)
+    }
+
+    /// Combined score in 0.0 - 1.0 (for equal-sum weights), before the stake boost
+    pub fn score(&self, provider: &Provider, now: DateTime<Utc>) -> f64 {
+        let reputation = (provider.reputation_score / 100.0).clamp(0.0, 1.0);
+
+        let load = 1.0 - (provider.current_load as f64 / MAX_CONCURRENT_LOAD).clamp(0.0, 1.0);
+
+        let daily_limit = provider.daily_limit();
+        let remaining_quota = if daily_limit > 0 {
+            let remaining = daily_limit.saturating_sub(provider.messages_sent_today);
+            remaining as f64 / daily_limit as f64
+        } else {
+            0.0
+        };
+
+        // Providers that haven't been used recently get a turn
+        let assignment_recency = match provider.last_assigned_at {
+            Some(assigned_at) => {
+                let idle_minutes = (now - assigned_at).num_seconds().max(0) as f64 / 60.0;
+                (idle_minutes / REST_WINDOW_MINUTES).min(1.0)
+            }
+            None => 1.0,
+        };
+
+        let stake_priority = if provider.has_stake_priority() { 1.0 } else { 0.0 };
+
+        self.weights.reputation * reputation
+            + self.weights.load * load
+            + self.weights.remaining_quota * remaining_quota
+            + self.weights.assignment_recency * assignment_recency
+            + self.weights.stake_priority * stake_priority
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entities::ProviderStake;
+    use crate::shared::types::{Carrier, PhoneNumber};
+
+    fn provider(reputation: f64, load: u32, sent_today: u32) -> Provider {
+        let mut provider = Provider::new(
+            "user".to_string(),
+            PhoneNumber::new("+85512345678".to_string()).unwrap(),
+            Carrier::Cellcard,
+        );
+        provider.reputation_score = reputation;
+        provider.current_load = load;
+        provider.messages_sent_today = sent_today;
+        provider
+    }
+
+    #[test]
+    fn test_prefers_higher_reputation_when_otherwise_equal() {
+        let candidates = vec![provider(40.0, 0, 0), provider(90.0, 0, 0)];
+        let strategy = WeightedProviderSelection::default();
+
+        let selected = strategy.best(&candidates).unwrap();
+        assert_eq!(selected.reputation_score, 90.0);
+    }
+
+    #[test]
+    fn test_prefers_idle_provider_over_busy_one() {
+        let candidates = vec![provider(80.0, 4, 45), provider(70.0, 0, 5)];
+        let strategy = WeightedProviderSelection::default();
+
+        let selected = strategy.best(&candidates).unwrap();
+        assert_eq!(selected.current_load, 0);
+    }
+
+    #[test]
+    fn test_recent_assignment_lowers_score() {
+        let strategy = WeightedProviderSelection::default();
+        let now = crate::shared::utils::now();
+        let rested = provider(60.0, 0, 0);
+        let mut just_used = provider(60.0, 0, 0);
+        just_used.last_assigned_at = Some(now);
+
+        assert!(strategy.score(&rested, now) > strategy.score(&just_used, now));
+    }
+
+    #[test]
+    fn test_tie_goes_to_least_recently_assigned() {
+        let now = crate::shared::utils::now();
+        let mut earlier = provider(60.0, 0, 0);
+        earlier.last_assigned_at = Some(now - chrono::Duration::hours(2));
+        let mut later = provider(60.0, 0, 0);
+        later.last_assigned_at = Some(now - chrono::Duration::hours(1));
+        let candidates = vec![earlier.clone(), later];
+        let strategy = WeightedProviderSelection::default();
+
+        // Both are fully rested, so only the rotation tie-break separates them
+        let selected = strategy.best(&candidates).unwrap();
+        assert_eq!(selected.last_assigned_at, earlier.last_assigned_at);
+    }
+
+    #[test]
+    fn test_stake_priority_outranks_slightly_better_reputation() {
+        let mut staked = provider(70.0, 0, 0);
+        staked.stake = Some(ProviderStake {
+            wallet_address: "0x3535353535353535353535353535353535353535".to_string(),
+            amount: 500.0,
+            max_daily_messages: 200,
+            priority: true,
+            synced_at: crate::shared::utils::now(),
+        });
+        let candidates = vec![provider(80.0, 0, 0), staked];
+        let strategy = WeightedProviderSelection::default();
+
+        let selected = strategy.best(&candidates).unwrap();
+        assert!(selected.has_stake_priority());
+        assert_eq!(selected.daily_limit(), 200);
+    }
+
+    /// Evaluate the pipeline's `$expr` stage against a stored provider, for
+    /// the handful of operators it uses
+    fn passes_daily_limit(provider: &Provider) -> bool {
+        fn eval(expr: &Bson, doc: &Document) -> Option<i64> {
+            match expr {
+                Bson::String(path) => {
+                    let mut value = doc;
+                    let mut fields = path.trim_start_matches('$').split('.').peekable();
+                    while let Some(field) = fields.next() {
+                        if fields.peek().is_none() {
+                            return match value.get(field)? {
+                                Bson::Int32(n) => Some(*n as i64),
+                                Bson::Int64(n) => Some(*n),
+                                _ => None,
+                            };
+                        }
+                        value = value.get_document(field).ok()?;
+                    }
+                    None
+                }
+                Bson::Int32(n) => Some(*n as i64),
+                Bson::Int64(n) => Some(*n),
+                Bson::Document(op) => {
+                    let (name, args) = op.iter().next()?;
+                    let args = args.as_array()?;
+                    match name.as_str() {
+                        "$max" => args.iter().filter_map(|arg| eval(arg, doc)).max(),
+                        "$ifNull" => eval(&args[0], doc).or_else(|| eval(&args[1], doc)),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            }
+        }
+
+        let stored = mongodb::bson::to_document(provider).unwrap();
+        let stage = candidates_pipeline(None, 50)
+            .into_iter()
+            .find_map(|stage| {
+                stage
+                    .get_document("$match")
+                    .ok()?
+                    .get_document("$expr")
+                    .ok()
+                    .cloned()
+            })
+            .expect("pipeline compares the daily limit with $expr");
+        let args = stage.get_array("$lt").unwrap();
+        eval(&args[0], &stored).unwrap() < eval(&args[1], &stored).unwrap()
+    }
+
+    fn stake(max_daily_messages: u32) -> ProviderStake {
+        ProviderStake {
+            wallet_address: "0x3535353535353535353535353535353535353535".to_string(),
+            amount: 500.0,
+            max_daily_messages,
+            priority: false,
+            synced_at: crate::shared::utils::now(),
+        }
+    }
+
+    #[test]
+    fn test_pipeline_excludes_providers_at_their_daily_limit() {
+        let mut exhausted = provider(90.0, 0, 0);
+        exhausted.messages_sent_today = exhausted.max_daily_messages;
+        let mut over = provider(90.0, 0, 0);
+        over.messages_sent_today = exhausted.max_daily_messages + 3;
+        let mut last_one = provider(90.0, 0, 0);
+        last_one.messages_sent_today = last_one.max_daily_messages - 1;
+
+        assert!(!passes_daily_limit(&exhausted));
+        assert!(!passes_daily_limit(&over));
+        assert!(passes_daily_limit(&last_one));
+        assert!(passes_daily_limit(&provider(90.0, 0, 0)));
+    }
+
+    #[test]
+    fn test_pipeline_daily_limit_counts_the_stake() {
+        // Own limit used up, but the stake unlocks more
+        let mut staked = provider(90.0, 0, 0);
+        staked.messages_sent_today = staked.max_daily_messages;
+        staked.stake = Some(stake(staked.max_daily_messages + 100));
+        assert!(passes_daily_limit(&staked));
+
+        // A stake below the provider's own limit doesn't lower it
+        let mut small_stake = provider(90.0, 0, 0);
+        small_stake.messages_sent_today = small_stake.max_daily_messages - 1;
+        small_stake.stake = Some(stake(0));
+        assert!(passes_daily_limit(&small_stake));
+
+        staked.messages_sent_today = staked.daily_limit();
+        assert!(!passes_daily_limit(&staked));
+    }
+
+    #[test]
+    fn test_pipeline_filters_with_expr_not_field_documents() {
+        let pipeline = candidates_pipeline(Some(&Carrier::Smart), 50);
+        let rendered = format!("{:?}", pipeline);
+        assert!(!rendered.contains("$field"));
+        assert!(rendered.contains("Smart"));
+        assert!(pipeline[0]
+            .get_document("$match")
+            .unwrap()
+            .get("carrier")
+            .is_none());
+        assert!(pipeline
+            .iter()
+            .any(|stage| stage.get_i64("$limit").ok() == Some(50)));
+    }
+
+    #[test]
+    fn test_no_candidates() {
+        let strategy = WeightedProviderSelection::default();
+        assert!(strategy.best(&[]).is_none());
+    }
+}
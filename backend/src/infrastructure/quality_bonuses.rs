@@ -0,0 +1,344 @@
+use chrono::NaiveDate;
+use futures::stream::TryStreamExt;
+use mongodb::bson::{doc, Document};
+use mongodb::options::UpdateOptions;
+use mongodb::{Collection, Database};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::info;
+
+use crate::config::{EarningsConfig, QualityBonusConfig};
+use crate::domain::entities::number_pool::month_bounds;
+use crate::domain::entities::{
+    LedgerTransaction, QualityAssessment, QualityBonus, QualityMetrics, QualityTarget,
+};
+use crate::infrastructure::ledger::Ledger;
+use crate::shared::utils::stored_timestamp;
+use crate::shared::{Money, PeerPowerError, Result};
+
+/// Monthly quality bonuses: a percentage on top of a provider's earnings for
+/// each quality target (success rate, confirmation latency, dispute rate)
+/// they met in the month.
+///
+/// Deliveries, disputes and latencies come from the earnings events and
+/// failures from the provider's messages, all on the local calendar. Each
+/// bonus is posted to the ledger keyed by provider and month.
+pub struct QualityBonuses {
+    events: Collection<Document>,
+    messages: Collection<Document>,
+    bonuses: Collection<QualityBonus>,
+    ledger: Arc<Ledger>,
+    config: EarningsConfig,
+}
+
+impl QualityBonuses {
+    pub fn new(database: Arc<Database>, ledger: Arc<Ledger>, config: EarningsConfig) -> Self {
+        Self {
+            events: database.collection("earnings_events"),
+            messages: database.collection("messages"),
+            bonuses: database.collection("quality_bonuses"),
+            ledger,
+            config,
+        }
+    }
+
+    /// The provider's standing for the current local month so far
+    pub async fn month_to_date(&self, provider_id: &str) -> Result<QualityAssessment> {
+        let today = self.config.local_date(crate::shared::utils::now());
+        let (metrics, _) = self
+            .month_metrics(today, Some(provider_id))
+            .await?
+            .remove(provider_id)
+            .unwrap_or_default();
+        Ok(assess(metrics, &self.config.quality_bonus))
+    }
+
+    /// Credit the bonuses earned in the month containing `month`
+    pub async fn credit_month(&self, month: NaiveDate) -> Result<Vec<QualityBonus>> {
+        let period = month_bounds(month).0.format("%Y-%m").to_string();
+
+        let mut credited = Vec::new();
+        for (provider_id, (measured, base_earnings)) in self.month_metrics(month, None).await? {
+            let assessment = assess(measured, &self.config.quality_bonus);
+            if assessment.bonus_percent <= 0.0 || !base_earnings.is_positive() {
+                continue;
+            }
+
+            let bonus = QualityBonus::new(provider_id, period.clone(), assessment, base_earnings);
+            // The ledger is the record of payment; the bonus document explains it
+            let posted = self
+                .ledger
+                .post(&LedgerTransaction::quality_bonus(
+                    &bonus.reference(),
+                    &bonus.provider_id,
+                    bonus.amount,
+                ))
+                .await?;
+            self.store(&bonus).await?;
+            if posted {
+                metrics::counter!("quality_bonuses_total").increment(1);
+                credited.push(bonus);
+            }
+        }
+
+        if !credited.is_empty() {
+            info!(
+                "Credited {} quality bonuses for {} ({} PPT)",
+                credited.len(),
+                period,
+                credited.iter().map(|bonus| bonus.amount).sum::<Money>()
+            );
+        }
+        Ok(credited)
+    }
+
+    /// Quality metrics and base earnings per provider for a local month
+    async fn month_metrics(
+        &self,
+        month: NaiveDate,
+        provider_id: Option<&str>,
+    ) -> Result<HashMap<String, (QualityMetrics, Money)>> {
+        let (first, last) = month_bounds(month);
+        let mut event_filter = doc! {"date": {
+            "$gte": first.format("%Y-%m-%d").to_string(),
+            "$lte": last.format("%Y-%m-%d").to_string(),
+        }};
+        if let Some(provider_id) = provider_id {
+            event_filter.insert("provider_id", provider_id);
+        }
+        let pipeline = vec![
+            doc! {"$match": event_filter},
+            doc! {
+                "$group": {
+                    "_id": "$provider_id",
+                    "delivered": {"$sum": 1},
+                    "disputed": {"$sum": {"$cond": ["$disputed", 1, 0]}},
+                    "confirmation_seconds": {"$avg": "$confirmation_seconds"},
+                    "base_earnings": {"$sum": Money::micros_expr("$base_amount")},
+                }
+            },
+        ];
+        let mut providers = HashMap::new();
+        let mut cursor =
+            self.events
+                .aggregate(pipeline, None)
+                .await
+                .map_err(|e| PeerPowerError::Database {
+                    message: format!("Failed to aggregate delivery quality: {}", e),
+                })?;
+        while let Some(doc) = cursor
+            .try_next()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read delivery quality: {}", e),
+            })?
+        {
+            let Ok(provider_id) = doc.get_str("_id") else {
+                continue;
+            };
+            let metrics = QualityMetrics {
+                delivered: count(&doc, "delivered"),
+                failed: 0,
+                disputed: count(&doc, "disputed"),
+                average_confirmation_seconds: doc.get_f64("confirmation_seconds").ok(),
+            };
+            let base_earnings = Money::from_field(&doc, "base_earnings");
+            providers.insert(provider_id.to_string(), (metrics, base_earnings));
+        }
+
+        let start = self.config.local_day_start(first);
+        let end = self
+            .config
+            .local_day_start(last + chrono::Duration::days(1));
+        let mut failed_filter = doc! {
+            "status": "Failed",
+            "provider_id": {"$ne": null},
+            "updated_at": {"$gte": stored_timestamp(start), "$lt": stored_timestamp(end)},
+        };
+        if let Some(provider_id) = provider_id {
+            failed_filter.insert("provider_id", provider_id);
+        }
+        let pipeline = vec![
+            doc! {"$match": failed_filter},
+            doc! {"$group": {"_id": "$provider_id", "failed": {"$sum": 1}}},
+        ];
+        let mut cursor = self.messages.aggregate(pipeline, None).await.map_err(|e| {
+            PeerPowerError::Database {
+                message: format!("Failed to aggregate failed messages: {}", e),
+            }
+        })?;
+        while let Some(doc) = cursor
+            .try_next()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read failed messages: {}", e),
+            })?
+        {
+            let Ok(provider_id) = doc.get_str("_id") else {
+                continue;
+            };
+            providers
+                .entry(provider_id.to_string())
+                .or_insert_with(|| (QualityMetrics::default(), Money::ZERO))
+                .0
+                .failed = count(&doc, "failed");
+        }
+        Ok(providers)
+    }
+
+    async fn store(&self, bonus: &QualityBonus) -> Result<()> {
+        let (filter, update) = insertion(bonus)?;
+        self.bonuses
+            .update_one(
+                filter,
+                update,
+                UpdateOptions::builder().upsert(true).build(),
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to store quality bonus: {}", e),
+            })?;
+        Ok(())
+    }
+}
+
+/// The upsert storing `bonus` unless the provider already has one for its period
+fn insertion(bonus: &QualityBonus) -> Result<(Document, Document)> {
+    let document = mongodb::bson::to_document(bonus).map_err(|e| PeerPowerError::Internal {
+        message: format!("Failed to serialize quality bonus: {}", e),
+    })?;
+    Ok((
+        doc! {"provider_id": &bonus.provider_id, "period": &bonus.period},
+        doc! {"$setOnInsert": document},
+    ))
+}
+
+/// Which quality targets `metrics` meets, and the bonus percent that earns.
+/// Nothing is met below the minimum number of deliveries.
+pub fn assess(metrics: QualityMetrics, config: &QualityBonusConfig) -> QualityAssessment {
+    let eligible = metrics.delivered >= config.min_deliveries;
+    let target =
+        |metric: &str, value: Option<f64>, target: f64, met: bool, advice: &str| QualityTarget {
+            metric: metric.to_string(),
+            value,
+            target,
+            met: eligible && met,
+            bonus_percent: config.percent_per_target,
+            advice: advice.to_string(),
+        };
+
+    let success_rate = metrics.success_rate();
+    let confirmation_seconds = metrics.average_confirmation_seconds;
+    let dispute_rate = metrics.dispute_rate();
+    let targets = vec![
+        target(
+            "success_rate",
+            success_rate,
+            config.success_rate_target,
+            success_rate.is_some_and(|rate| rate >= config.success_rate_target),
+            "Keep airtime and signal up so dispatched messages go out",
+        ),
+        target(
+            "confirmation_seconds",
+            confirmation_seconds,
+            config.confirmation_seconds_target,
+            confirmation_seconds
+                .is_some_and(|seconds| seconds <= config.confirmation_seconds_target),
+            "Keep the app running so deliveries are confirmed as soon as they are sent",
+        ),
+        target(
+            "dispute_rate",
+            dispute_rate,
+            config.dispute_rate_target,
+            dispute_rate.is_some_and(|rate| rate <= config.dispute_rate_target),
+            "Only confirm messages your phone reports as delivered",
+        ),
+    ];
+
+    QualityAssessment {
+        bonus_percent: targets
+            .iter()
+            .filter(|target| target.met)
+            .map(|target| target.bonus_percent)
+            .sum(),
+        metrics,
+        eligible,
+        min_deliveries: config.min_deliveries,
+        targets,
+    }
+}
+
+fn count(doc: &Document, key: &str) -> u64 {
+    doc.get_i64(key)
+        .or_else(|_| doc.get_i32(key).map(i64::from))
+        .unwrap_or(0)
+        .max(0) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::test_support::read_back_from;
+
+    #[test]
+    fn test_bonus_paid_per_target_met() {
+        let config = QualityBonusConfig {
+            percent_per_target: 2.0,
+            min_deliveries: 50,
+            success_rate_target: 95.0,
+            confirmation_seconds_target: 60.0,
+            dispute_rate_target: 1.0,
+        };
+        let metrics = QualityMetrics {
+            delivered: 200,
+            failed: 5,
+            disputed: 4,
+            average_confirmation_seconds: Some(30.0),
+        };
+
+        // 97.6% delivered and fast confirmations, but 2% disputed
+        let assessment = assess(metrics.clone(), &config);
+        assert!(assessment.eligible);
+        assert_eq!(assessment.bonus_percent, 4.0);
+        assert!(!assessment.targets[2].met);
+
+        // Too few deliveries to judge
+        let quiet = assess(
+            QualityMetrics {
+                delivered: 10,
+                ..metrics
+            },
+            &config,
+        );
+        assert!(!quiet.eligible);
+        assert_eq!(quiet.bonus_percent, 0.0);
+    }
+
+    #[test]
+    fn test_stored_bonus_reads_back() {
+        let config = QualityBonusConfig {
+            percent_per_target: 2.0,
+            min_deliveries: 50,
+            success_rate_target: 95.0,
+            confirmation_seconds_target: 60.0,
+            dispute_rate_target: 1.0,
+        };
+        let metrics = QualityMetrics {
+            delivered: 200,
+            failed: 0,
+            disputed: 0,
+            average_confirmation_seconds: Some(30.0),
+        };
+        let bonus = QualityBonus::new(
+            "provider-1".to_string(),
+            "2024-05".to_string(),
+            assess(metrics, &config),
+            Money::from_ppt(40.0),
+        );
+
+        let (filter, update) = insertion(&bonus).unwrap();
+        let stored: QualityBonus = read_back_from(filter, &update);
+        assert_eq!(stored.id, bonus.id);
+        assert_eq!(stored.created_at, bonus.created_at);
+    }
+}
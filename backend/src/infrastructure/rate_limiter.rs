@@ -0,0 +1,171 @@
+use tracing::warn;
+
+use crate::config::RateLimitConfig;
+use crate::infrastructure::database::RedisConnection;
+use crate::shared::Result;
+
+/// Refill the bucket for the time elapsed, then try to take one token.
+/// Returns `{allowed, retry_after_ms}`.
+const TOKEN_BUCKET_SCRIPT: &str = r#"
+local capacity = tonumber(ARGV[1])
+local refill_per_ms = tonumber(ARGV[2])
+local now = tonumber(ARGV[3])
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'ts')
+local tokens = tonumber(bucket[1]) or capacity
+local ts = tonumber(bucket[2]) or now
+tokens = math.min(capacity, tokens + math.max(0, now - ts) * refill_per_ms)
+local allowed = 0
+local retry_ms = 0
+if tokens >= 1 then
+  tokens = tokens - 1
+  allowed = 1
+else
+  retry_ms = math.ceil((1 - tokens) / refill_per_ms)
+end
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'ts', now)
+redis.call('PEXPIRE', KEYS[1], math.ceil(capacity / refill_per_ms))
+return {allowed, retry_ms}
+"#;
+
+/// Bucket a request path is charged to, and its per-minute budget
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RouteLimit {
+    pub route: &'static str,
+    pub per_minute: u32,
+}
+
+/// Outcome of charging a request to its bucket
+#[derive(Debug, Clone, Copy)]
+pub enum RateDecision {
+    Allowed,
+    Limited { retry_after_seconds: u64 },
+}
+
+/// Per-IP, per-route token buckets kept in Redis so every instance shares them.
+///
+/// Each bucket holds a minute's worth of requests and refills continuously.
+/// If Redis is unavailable requests are let through; the phone-number OTP
+/// limit in the auth service still applies.
+pub struct RateLimiter {
+    redis: RedisConnection,
+    config: RateLimitConfig,
+}
+
+impl RateLimiter {
+    pub fn new(redis: RedisConnection, config: RateLimitConfig) -> Self {
+        Self { redis, config }
+    }
+
+    pub fn route_limit(config: &RateLimitConfig, path: &str) -> RouteLimit {
+        let path = path.trim_end_matches('/');
+        if path.ends_with("/auth/send-otp") || path.ends_with("/auth/resend-otp") {
+            RouteLimit {
+                route: "send_otp",
+                per_minute: config.send_otp_per_minute,
+            }
+        } else if path.ends_with("/auth/verify-otp") {
+            RouteLimit {
+                route: "verify_otp",
+                per_minute: config.verify_otp_per_minute,
+            }
+        } else if path.ends_with("/messages/send") {
+            RouteLimit {
+                route: "send_message",
+                per_minute: config.send_message_per_minute,
+            }
+        } else if path
+            .strip_prefix("/api/v1")
+            .unwrap_or(path)
+            .starts_with("/admin/")
+        {
+            RouteLimit {
+                route: "admin",
+                per_minute: config.admin_per_minute,
+            }
+        } else {
+            RouteLimit {
+                route: "default",
+                per_minute: config.default_per_minute,
+            }
+        }
+    }
+
+    /// Charge one request from `ip` against the bucket for `path`
+    pub async fn check(&self, ip: &str, path: &str) -> RateDecision {
+        if !self.config.enabled {
+            return RateDecision::Allowed;
+        }
+
+        let limit = Self::route_limit(&self.config, path);
+        match self.take_token(ip, limit).await {
+            Ok(decision) => {
+                if let RateDecision::Limited { .. } = decision {
+                    metrics::counter!("rate_limited_requests_total", "route" => limit.route)
+                        .increment(1);
+                }
+                decision
+            }
+            Err(e) => {
+                warn!("Rate limiter unavailable, allowing request: {}", e);
+                RateDecision::Allowed
+            }
+        }
+    }
+
+    async fn take_token(&self, ip: &str, limit: RouteLimit) -> Result<RateDecision> {
+        let key = format!("rate_limit:ip:{}:{}", limit.route, ip);
+        let capacity = limit.per_minute.max(1);
+        let refill_per_ms = capacity as f64 / 60_000.0;
+        let now_ms = crate::shared::utils::now().timestamp_millis();
+
+        let reply = self
+            .redis
+            .eval_ints(
+                TOKEN_BUCKET_SCRIPT,
+                &[&key],
+                &[
+                    capacity.to_string(),
+                    refill_per_ms.to_string(),
+                    now_ms.to_string(),
+                ],
+            )
+            .await?;
+
+        Ok(match reply.as_slice() {
+            [0, retry_ms, ..] => RateDecision::Limited {
+                retry_after_seconds: ((*retry_ms).max(0) as u64).div_ceil(1000).max(1),
+            },
+            _ => RateDecision::Allowed,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> RateLimitConfig {
+        RateLimitConfig {
+            enabled: true,
+            default_per_minute: 120,
+            send_otp_per_minute: 5,
+            verify_otp_per_minute: 10,
+            send_message_per_minute: 60,
+            admin_per_minute: 30,
+        }
+    }
+
+    #[test]
+    fn test_route_limit_classifies_sensitive_routes() {
+        let config = config();
+        let route = |path: &str| RateLimiter::route_limit(&config, path);
+
+        assert_eq!(route("/api/v1/auth/send-otp").per_minute, 5);
+        assert_eq!(route("/auth/resend-otp").route, "send_otp");
+        assert_eq!(route("/auth/verify-otp/").per_minute, 10);
+        assert_eq!(route("/messages/send").per_minute, 60);
+        assert_eq!(route("/messages").route, "default");
+        assert_eq!(route("/api/v1/admin/stats").route, "admin");
+        assert_eq!(route("/admin/jobs").per_minute, 30);
+    }
+}
@@ -0,0 +1,120 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::config::PrivacyConfig;
+use crate::domain::entities::Message;
+use crate::shared::types::PhoneNumber;
+use crate::shared::{PeerPowerError, Result};
+
+const NONCE_LEN: usize = 12;
+
+/// Encrypts recipients for clients in privacy mode.
+///
+/// Protected messages store only a masked number, an AES-256-GCM ciphertext
+/// and a salted HMAC of the number for dedup/search. `reveal` is only meant
+/// for the dispatch path, which needs the real number to hand to a provider.
+pub struct RecipientVault {
+    cipher: Option<Aes256Gcm>,
+    hash_salt: String,
+}
+
+impl RecipientVault {
+    pub fn new(config: &PrivacyConfig) -> Result<Self> {
+        let cipher = match config.recipient_encryption_key.as_deref() {
+            Some(key) => {
+                let key = hex::decode(key.trim())
+                    .ok()
+                    .filter(|bytes| bytes.len() == 32)
+                    .ok_or_else(|| PeerPowerError::Configuration {
+                        message: "RECIPIENT_ENCRYPTION_KEY must be 64 hex characters".to_string(),
+                    })?;
+                Some(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)))
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            cipher,
+            hash_salt: config.recipient_hash_salt.clone(),
+        })
+    }
+
+    pub fn is_configured(&self) -> bool {
+        self.cipher.is_some() && !self.hash_salt.is_empty()
+    }
+
+    /// Salted hash used to match a number without storing it
+    pub fn hash(&self, phone: &PhoneNumber) -> String {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(self.hash_salt.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(phone.as_str().as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    /// Replace the message's recipient with its masked, encrypted form
+    pub fn protect(&self, message: &mut Message) -> Result<()> {
+        let cipher = self
+            .cipher
+            .as_ref()
+            .filter(|_| self.is_configured())
+            .ok_or_else(|| PeerPowerError::Configuration {
+                message:
+                    "Recipient privacy requires RECIPIENT_ENCRYPTION_KEY and RECIPIENT_HASH_SALT"
+                        .to_string(),
+            })?;
+
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, message.recipient.as_str().as_bytes())
+            .map_err(|e| PeerPowerError::Internal {
+                message: format!("Failed to encrypt recipient: {}", e),
+            })?;
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+
+        let hash = self.hash(&message.recipient);
+        message.protect_recipient(
+            hash,
+            base64::engine::general_purpose::STANDARD.encode(sealed),
+        );
+        Ok(())
+    }
+
+    /// The number to actually send to (dispatch path only)
+    pub fn reveal(&self, message: &Message) -> Result<PhoneNumber> {
+        let Some(sealed) = &message.recipient_ciphertext else {
+            return Ok(message.recipient.clone());
+        };
+
+        let cipher = self
+            .cipher
+            .as_ref()
+            .ok_or_else(|| PeerPowerError::Configuration {
+                message: "RECIPIENT_ENCRYPTION_KEY is required to dispatch protected messages"
+                    .to_string(),
+            })?;
+        let undecryptable = |reason: String| PeerPowerError::Internal {
+            message: format!(
+                "Failed to decrypt recipient of message {}: {}",
+                message.id, reason
+            ),
+        };
+
+        let sealed = base64::engine::general_purpose::STANDARD
+            .decode(sealed)
+            .map_err(|e| undecryptable(e.to_string()))?;
+        if sealed.len() <= NONCE_LEN {
+            return Err(undecryptable("ciphertext too short".to_string()));
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let plaintext = cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|e| undecryptable(e.to_string()))?;
+        let number = String::from_utf8(plaintext).map_err(|e| undecryptable(e.to_string()))?;
+
+        PhoneNumber::new(number)
+    }
+}
@@ -0,0 +1,254 @@
+use futures::stream::TryStreamExt;
+use mongodb::bson::{doc, Document};
+use mongodb::options::{FindOneAndUpdateOptions, FindOptions, ReturnDocument};
+use mongodb::{Collection, Database};
+use std::sync::Arc;
+use tracing::info;
+
+use crate::config::ReferralConfig;
+use crate::domain::entities::{
+    LedgerAccountKind, LedgerTransaction, Provider, Referral, ReferralCode,
+};
+use crate::infrastructure::ledger::Ledger;
+use crate::shared::utils::stored_timestamp;
+use crate::shared::{Money, PeerPowerError, Result};
+
+/// Attempts at drawing an unused code before giving up
+const CODE_ATTEMPTS: usize = 5;
+
+/// Referral codes, signup attribution and bonuses.
+///
+/// A referral is recorded when a new user verifies their phone with a code.
+/// Each delivered message the referee sent (as a client) or delivered (as a
+/// provider) counts toward the payout; once enough have succeeded, both
+/// users are credited through the ledger, keyed by referral id.
+pub struct ReferralService {
+    codes: Collection<ReferralCode>,
+    referrals: Collection<Referral>,
+    providers: Collection<Provider>,
+    ledger: Arc<Ledger>,
+    config: ReferralConfig,
+}
+
+impl ReferralService {
+    pub fn new(database: Arc<Database>, ledger: Arc<Ledger>, config: ReferralConfig) -> Self {
+        Self {
+            codes: database.collection("referral_codes"),
+            referrals: database.collection("referrals"),
+            providers: database.collection("providers"),
+            ledger,
+            config,
+        }
+    }
+
+    pub fn config(&self) -> &ReferralConfig {
+        &self.config
+    }
+
+    /// The user's referral code, created on first use
+    pub async fn code_for(&self, user_id: &str) -> Result<ReferralCode> {
+        if let Some(code) = self.find_code(doc! {"user_id": user_id}).await? {
+            return Ok(code);
+        }
+
+        for _ in 0..CODE_ATTEMPTS {
+            let code = ReferralCode::new(user_id.to_string());
+            match self.codes.insert_one(&code, None).await {
+                Ok(_) => return Ok(code),
+                // Either the code is taken, or a concurrent request created this user's
+                Err(e) if e.to_string().contains("duplicate key") => {
+                    if let Some(code) = self.find_code(doc! {"user_id": user_id}).await? {
+                        return Ok(code);
+                    }
+                }
+                Err(e) => {
+                    return Err(PeerPowerError::Database {
+                        message: format!("Failed to create referral code: {}", e),
+                    })
+                }
+            }
+        }
+        Err(PeerPowerError::Internal {
+            message: "Could not find an unused referral code".to_string(),
+        })
+    }
+
+    /// Record that `referee_id` signed up with `code`
+    pub async fn attribute(&self, code: &str, referee_id: &str) -> Result<Referral> {
+        let code = self
+            .find_code(doc! {"code": ReferralCode::normalize(code)})
+            .await?
+            .ok_or_else(|| PeerPowerError::ValidationError {
+                field: "referral_code".to_string(),
+                message: "Unknown referral code".to_string(),
+            })?;
+        if code.user_id == referee_id {
+            return Err(PeerPowerError::ValidationError {
+                field: "referral_code".to_string(),
+                message: "You can't use your own referral code".to_string(),
+            });
+        }
+
+        let referral = Referral::new(
+            &code,
+            referee_id.to_string(),
+            self.config.required_deliveries,
+            self.config.referrer_bonus,
+            self.config.referee_bonus,
+        );
+        // Unique on referee, so a user is only ever referred once
+        self.referrals
+            .insert_one(&referral, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to record referral: {}", e),
+            })?;
+
+        info!(
+            "User {} referred by {} ({})",
+            referee_id, referral.referrer_id, referral.code
+        );
+        Ok(referral)
+    }
+
+    /// Count a successful delivery toward the user's pending referral, paying
+    /// the bonuses once it reaches the required number
+    pub async fn record_delivery(&self, user_id: &str) -> Result<()> {
+        let referral = self
+            .referrals
+            .find_one_and_update(
+                doc! {"referee_id": user_id, "status": "Pending"},
+                doc! {"$inc": {"successful_deliveries": 1}},
+                FindOneAndUpdateOptions::builder()
+                    .return_document(ReturnDocument::After)
+                    .build(),
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to count referral delivery: {}", e),
+            })?;
+
+        match referral {
+            Some(referral) if referral.is_earned() => self.reward(&referral).await,
+            _ => Ok(()),
+        }
+    }
+
+    /// Referrals the user made, newest first, and the one that brought them in
+    pub async fn for_user(&self, user_id: &str) -> Result<(Vec<Referral>, Option<Referral>)> {
+        let made = self
+            .referrals
+            .find(
+                doc! {"referrer_id": user_id},
+                FindOptions::builder()
+                    .sort(doc! {"created_at": -1})
+                    .limit(200)
+                    .build(),
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch referrals: {}", e),
+            })?
+            .try_collect()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read referrals: {}", e),
+            })?;
+        let referred_by = self
+            .referrals
+            .find_one(doc! {"referee_id": user_id}, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch referral: {}", e),
+            })?;
+        Ok((made, referred_by))
+    }
+
+    async fn reward(&self, referral: &Referral) -> Result<()> {
+        let referrer = self.bonus_account(&referral.referrer_id).await?;
+        let referee = self.bonus_account(&referral.referee_id).await?;
+
+        // Posting first is safe to repeat; the status change only records it
+        self.ledger
+            .post(&LedgerTransaction::referral_bonus(
+                &referral.id,
+                (referrer.0, &referrer.1),
+                Money::from_ppt(referral.referrer_bonus),
+                (referee.0, &referee.1),
+                Money::from_ppt(referral.referee_bonus),
+            ))
+            .await?;
+        let result = self
+            .referrals
+            .update_one(
+                doc! {"id": &referral.id, "status": "Pending"},
+                rewarded(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to mark referral rewarded: {}", e),
+            })?;
+
+        if result.modified_count == 1 {
+            info!(
+                "Referral {} rewarded: {} PPT to {}, {} PPT to {}",
+                referral.id,
+                referral.referrer_bonus,
+                referral.referrer_id,
+                referral.referee_bonus,
+                referral.referee_id
+            );
+            metrics::counter!("referral_bonuses_total").increment(1);
+        }
+        Ok(())
+    }
+
+    /// Providers are paid into their earnings, everyone else into their client wallet
+    async fn bonus_account(&self, user_id: &str) -> Result<(LedgerAccountKind, String)> {
+        let provider = self
+            .providers
+            .find_one(doc! {"user_id": user_id}, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch provider: {}", e),
+            })?;
+        Ok(match provider {
+            Some(provider) => (LedgerAccountKind::Provider, provider.id),
+            None => (LedgerAccountKind::Client, user_id.to_string()),
+        })
+    }
+
+    async fn find_code(&self, filter: mongodb::bson::Document) -> Result<Option<ReferralCode>> {
+        self.codes
+            .find_one(filter, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch referral code: {}", e),
+            })
+    }
+}
+
+fn rewarded() -> Document {
+    doc! {"$set": {
+        "status": "Rewarded",
+        "rewarded_at": stored_timestamp(crate::shared::utils::now()),
+    }}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entities::ReferralStatus;
+    use crate::shared::test_support::read_back;
+
+    #[test]
+    fn test_rewarded_referral_reads_back() {
+        let code = ReferralCode::new("referrer-1".to_string());
+        let referral = Referral::new(&code, "referee-1".to_string(), 5, 10.0, 5.0);
+
+        let rewarded = read_back(&referral, &rewarded());
+        assert_eq!(rewarded.status, ReferralStatus::Rewarded);
+        assert!(rewarded.rewarded_at.is_some());
+    }
+}
@@ -0,0 +1,219 @@
+use chrono::{DateTime, Utc};
+use mongodb::bson::{doc, Document};
+use mongodb::Database;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info};
+
+use crate::config::RetentionConfig;
+use crate::domain::entities::{JobStatus, RetentionReport};
+use crate::infrastructure::database::RedisConnection;
+use crate::shared::types::MessageStatus;
+use crate::shared::utils::stored_timestamp;
+use crate::shared::{PeerPowerError, Result};
+
+const RETENTION_LOCK_KEY: &str = "retention:lock";
+
+/// How long one run may hold the lock
+const RETENTION_LOCK_SECONDS: usize = 3600;
+
+/// Deletes records once they are past their collection's retention window.
+///
+/// Only finished records are eligible: messages that were delivered, failed
+/// or cancelled (and not disputed), jobs that completed, failed, timed out or
+/// were cancelled, and login history. Each is aged from when it finished.
+/// Users, providers and messages an admin soft-deleted are purged once
+/// their own window has passed since the deletion. In a dry run nothing is
+/// deleted and the reports say what would have been.
+pub struct RetentionTask {
+    database: Arc<Database>,
+    redis: RedisConnection,
+    config: RetentionConfig,
+}
+
+/// One collection's window, and the filter for what it applies to
+struct Policy {
+    collection: &'static str,
+    days: i64,
+    finished_at: &'static str,
+    filter: Document,
+    purge: bool, // of soft-deleted records
+}
+
+impl RetentionTask {
+    pub fn new(database: Arc<Database>, redis: RedisConnection, config: RetentionConfig) -> Self {
+        Self {
+            database,
+            redis,
+            config,
+        }
+    }
+
+    pub fn start(self: Arc<Self>) {
+        if !self.config.enabled {
+            info!("Data retention disabled");
+            return;
+        }
+
+        tokio::spawn(async move {
+            let mut interval =
+                tokio::time::interval(Duration::from_secs(self.config.interval_hours * 3600));
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.run_scheduled().await {
+                    error!("Data retention run failed: {}", e);
+                }
+            }
+        });
+    }
+
+    fn policies(&self) -> Vec<Policy> {
+        vec![
+            Policy {
+                collection: "messages",
+                days: self.config.message_days,
+                finished_at: "updated_at",
+                filter: doc! {
+                    "status": {"$in": [
+                        format!("{:?}", MessageStatus::Delivered),
+                        format!("{:?}", MessageStatus::Failed),
+                        format!("{:?}", MessageStatus::Cancelled),
+                    ]},
+                    "dispute": null, // kept as evidence
+                },
+                purge: false,
+            },
+            Policy {
+                collection: "jobs",
+                days: self.config.job_days,
+                finished_at: "completed_at",
+                filter: doc! {
+                    "status": {"$in": [
+                        format!("{:?}", JobStatus::Completed),
+                        format!("{:?}", JobStatus::Failed),
+                        format!("{:?}", JobStatus::Timeout),
+                        format!("{:?}", JobStatus::Cancelled),
+                    ]},
+                },
+                purge: false,
+            },
+            Policy {
+                collection: "auth_events",
+                days: self.config.auth_event_days,
+                finished_at: "created_at",
+                filter: doc! {},
+                purge: false,
+            },
+            Policy {
+                collection: "users",
+                days: self.config.deleted_days,
+                finished_at: "deleted_at",
+                filter: doc! {},
+                purge: true,
+            },
+            Policy {
+                collection: "providers",
+                days: self.config.deleted_days,
+                finished_at: "deleted_at",
+                filter: doc! {},
+                purge: true,
+            },
+            Policy {
+                collection: "messages",
+                days: self.config.deleted_days,
+                finished_at: "deleted_at",
+                filter: doc! {"dispute": null},
+                purge: true,
+            },
+        ]
+    }
+
+    /// A scheduled run, dry or not as configured; one instance at a time
+    async fn run_scheduled(&self) -> Result<()> {
+        if !self
+            .redis
+            .acquire_lock(RETENTION_LOCK_KEY, RETENTION_LOCK_SECONDS)
+            .await?
+        {
+            return Ok(());
+        }
+        let result = self.run(self.config.dry_run).await;
+        self.redis.release_lock(RETENTION_LOCK_KEY).await?;
+
+        for report in result? {
+            let aged_from = if report.purge { "deleted" } else { "finished" };
+            if report.dry_run {
+                info!(
+                    "Retention dry run: {} {} {} before {} would be deleted",
+                    report.matched, report.collection, aged_from, report.cutoff
+                );
+            } else if report.deleted > 0 {
+                info!(
+                    "Retention deleted {} {} {} before {}",
+                    report.deleted, report.collection, aged_from, report.cutoff
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply every policy with a window, or in a dry run only count what they
+    /// would delete
+    pub async fn run(&self, dry_run: bool) -> Result<Vec<RetentionReport>> {
+        let now = crate::shared::utils::now();
+        let mut reports = Vec::new();
+        for policy in self.policies() {
+            if policy.days <= 0 {
+                continue; // kept forever
+            }
+            reports.push(self.apply(policy, now, dry_run).await?);
+        }
+        Ok(reports)
+    }
+
+    async fn apply(
+        &self,
+        policy: Policy,
+        now: DateTime<Utc>,
+        dry_run: bool,
+    ) -> Result<RetentionReport> {
+        let cutoff = now - chrono::Duration::days(policy.days);
+        let mut filter = policy.filter;
+        filter.insert(policy.finished_at, doc! {"$lt": stored_timestamp(cutoff)});
+
+        let collection = self.database.collection::<Document>(policy.collection);
+        let matched = collection
+            .count_documents(filter.clone(), None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to count expired {}: {}", policy.collection, e),
+            })?;
+
+        let deleted = if dry_run || matched == 0 {
+            0
+        } else {
+            let result = collection.delete_many(filter, None).await.map_err(|e| {
+                PeerPowerError::Database {
+                    message: format!("Failed to delete expired {}: {}", policy.collection, e),
+                }
+            })?;
+            metrics::counter!(
+                "retention_deleted_total",
+                "collection" => policy.collection,
+                "purge" => policy.purge.to_string()
+            )
+            .increment(result.deleted_count);
+            result.deleted_count
+        };
+
+        Ok(RetentionReport {
+            collection: policy.collection.to_string(),
+            retention_days: policy.days,
+            cutoff,
+            matched,
+            deleted,
+            dry_run,
+            purge: policy.purge,
+        })
+    }
+}
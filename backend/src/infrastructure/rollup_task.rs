@@ -0,0 +1,520 @@
+use chrono::Datelike;
+use futures::stream::TryStreamExt;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::interval;
+use tracing::{error, info, warn};
+
+use crate::domain::entities::demand_heatmap::UNKNOWN_PROVINCE;
+use crate::domain::entities::provider::SELF_TEST_CLIENT_ID;
+use crate::domain::entities::{
+    ClientQualityScore, DemandHeatmap, HeatmapRow, Message, QualityAlert, SettlementSource,
+};
+use crate::shared::types::MessageStatus;
+use crate::shared::types::{canonical_province, province_for_number};
+use crate::shared::utils::stored_timestamp;
+use crate::shared::{AppState, PeerPowerError, Result};
+
+/// Trailing window of message demand included in each heatmap snapshot
+const HEATMAP_WINDOW_DAYS: i64 = 30;
+
+/// How long the account team's webhook gets to answer a quality alert
+const ALERT_WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Daily rollup task producing reporting snapshots
+pub struct RollupTask {
+    app_state: Arc<AppState>,
+}
+
+impl RollupTask {
+    pub fn new(app_state: Arc<AppState>) -> Self {
+        Self { app_state }
+    }
+
+    /// Start the daily rollup loop (runs once immediately)
+    pub fn start(self) {
+        tokio::spawn(async move {
+            let mut interval = interval(Duration::from_secs(24 * 3600));
+
+            loop {
+                interval.tick().await;
+                Self::run_daily_rollups(&self.app_state).await;
+            }
+        });
+    }
+
+    /// Each step logs its own failure, so one failing doesn't skip the rest
+    async fn run_daily_rollups(app_state: &Arc<AppState>) {
+        info!("Running daily rollups");
+        if let Err(e) = Self::refresh_demand_heatmap(app_state).await {
+            error!("Error refreshing demand heatmap: {}", e);
+        }
+
+        let yesterday = (chrono::Utc::now() - chrono::Duration::days(1)).date_naive();
+        if let Err(e) = Self::compute_quality_scores(app_state, yesterday).await {
+            error!("Error computing quality scores: {}", e);
+        }
+
+        if let Err(e) = Self::import_baray_settlements(app_state).await {
+            error!("Error importing Baray settlement report: {}", e);
+        }
+
+        // Provider earnings against the ledger; discrepancies are stored for admins
+        if let Err(e) = app_state.earnings_reconciler.run(None).await {
+            error!("Error reconciling provider earnings: {}", e);
+        }
+
+        // Last month's dedicated number rent; a no-op once it has been billed
+        let last_month = chrono::Utc::now()
+            .date_naive()
+            .with_day(1)
+            .and_then(|d| d.pred_opt());
+        if let Some(last_month) = last_month {
+            if let Err(e) = app_state.number_pool.bill_month(last_month).await {
+                error!("Error billing dedicated number rentals: {}", e);
+            }
+        }
+
+        // Last month's volume and quality bonuses, by local earnings date; a
+        // no-op once credited
+        let earnings_config = &app_state.config.earnings;
+        let last_month = earnings_config
+            .local_date(chrono::Utc::now())
+            .with_day(1)
+            .and_then(|d| d.pred_opt());
+        if let Some(last_month) = last_month {
+            if let Err(e) = app_state.volume_bonuses.credit_month(last_month).await {
+                error!("Error crediting volume bonuses: {}", e);
+            }
+            if let Err(e) = app_state.quality_bonuses.credit_month(last_month).await {
+                error!("Error crediting quality bonuses: {}", e);
+            }
+        }
+    }
+
+    /// Pull yesterday's Baray settlement report and reconcile it against payouts
+    async fn import_baray_settlements(app_state: &Arc<AppState>) -> Result<()> {
+        if !app_state.baray_client.is_configured() {
+            return Ok(());
+        }
+
+        let date = (chrono::Utc::now() - chrono::Duration::days(1)).date_naive();
+        let lines = app_state.baray_client.fetch_settlement_report(date).await?;
+        app_state
+            .settlement_reconciler
+            .reconcile(date, SettlementSource::Api, lines, None)
+            .await?;
+        Ok(())
+    }
+
+    /// Rebuild today's demand heatmap snapshot, replacing any existing one
+    pub async fn refresh_demand_heatmap(app_state: &Arc<AppState>) -> Result<DemandHeatmap> {
+        let heatmap = Self::build_demand_heatmap(app_state).await?;
+
+        app_state
+            .database
+            .collection::<DemandHeatmap>("demand_heatmaps")
+            .replace_one(
+                mongodb::bson::doc! {"date": &heatmap.date},
+                &heatmap,
+                mongodb::options::ReplaceOptions::builder()
+                    .upsert(true)
+                    .build(),
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to store demand heatmap: {}", e),
+            })?;
+
+        info!(
+            "Demand heatmap for {} refreshed ({} provinces)",
+            heatmap.date,
+            heatmap.rows.len()
+        );
+        Ok(heatmap)
+    }
+
+    /// Score each client's messages submitted on `date` and raise SLA alerts
+    pub async fn compute_quality_scores(
+        app_state: &Arc<AppState>,
+        date: chrono::NaiveDate,
+    ) -> Result<Vec<ClientQualityScore>> {
+        let day_start = date.and_hms_opt(0, 0, 0).unwrap().and_utc();
+        let day_end = stored_timestamp(day_start + chrono::Duration::days(1));
+        let day_start = stored_timestamp(day_start);
+        let date_key = date.format("%Y-%m-%d").to_string();
+        let target_seconds = app_state.config.quality.apdex_target_seconds;
+
+        let mut cursor = app_state
+            .database
+            .collection::<Message>("messages")
+            .find(
+                mongodb::bson::doc! {
+                    "created_at": {"$gte": day_start, "$lt": day_end},
+                    "client_id": {"$ne": SELF_TEST_CLIENT_ID},
+                },
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to query messages: {}", e),
+            })?;
+
+        let mut scores: HashMap<String, ClientQualityScore> = HashMap::new();
+        while let Some(message) = cursor
+            .try_next()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read messages: {}", e),
+            })?
+        {
+            // Client-cancelled messages say nothing about our service
+            if message.status == MessageStatus::Cancelled {
+                continue;
+            }
+
+            let score = scores.entry(message.client_id.clone()).or_insert_with(|| {
+                ClientQualityScore::new(message.client_id.clone(), date_key.clone())
+            });
+            score.record(message.delivery_latency_seconds(), target_seconds);
+            if message.missed_predicted_delivery() {
+                score.eta_missed += 1;
+            }
+        }
+
+        let scores_collection = app_state
+            .database
+            .collection::<ClientQualityScore>("quality_scores");
+        let mut results = Vec::with_capacity(scores.len());
+        for (_, mut score) in scores {
+            score.finalize();
+
+            let sla = app_state
+                .user_repository
+                .find_by_id(&score.client_id)
+                .await?
+                .and_then(|user| user.quality_sla);
+            if let Some(sla) = &sla {
+                score.below_threshold = score.score < sla.min_score;
+            }
+
+            scores_collection
+                .replace_one(
+                    mongodb::bson::doc! {"client_id": &score.client_id, "date": &score.date},
+                    &score,
+                    mongodb::options::ReplaceOptions::builder()
+                        .upsert(true)
+                        .build(),
+                )
+                .await
+                .map_err(|e| PeerPowerError::Database {
+                    message: format!("Failed to store quality score: {}", e),
+                })?;
+
+            if let Some(sla) = sla {
+                if score.below_threshold {
+                    Self::check_quality_breach(app_state, &score, sla.min_score, sla.consecutive_days)
+                        .await?;
+                }
+            }
+            results.push(score);
+        }
+
+        info!("Computed quality scores for {} clients on {}", results.len(), date_key);
+        Ok(results)
+    }
+
+    /// Alert once when a client's below-threshold streak reaches the agreed number of days
+    async fn check_quality_breach(
+        app_state: &Arc<AppState>,
+        score: &ClientQualityScore,
+        min_score: f64,
+        consecutive_days: u32,
+    ) -> Result<()> {
+        let consecutive_days = consecutive_days.max(1);
+
+        // Latest scores up to and including this one, plus the day before the streak
+        let recent: Vec<ClientQualityScore> = app_state
+            .database
+            .collection::<ClientQualityScore>("quality_scores")
+            .find(
+                mongodb::bson::doc! {
+                    "client_id": &score.client_id,
+                    "date": {"$lte": &score.date},
+                },
+                mongodb::options::FindOptions::builder()
+                    .sort(mongodb::bson::doc! {"date": -1})
+                    .limit(consecutive_days as i64 + 1)
+                    .build(),
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to query quality scores: {}", e),
+            })?
+            .try_collect()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read quality scores: {}", e),
+            })?;
+
+        if below_threshold_streak(&recent) != consecutive_days as usize {
+            return Ok(());
+        }
+
+        let alerts_collection = app_state
+            .database
+            .collection::<QualityAlert>("quality_alerts");
+        let already_raised = alerts_collection
+            .find_one(
+                mongodb::bson::doc! {"client_id": &score.client_id, "date": &score.date},
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to check quality alerts: {}", e),
+            })?
+            .is_some();
+        if already_raised {
+            return Ok(());
+        }
+
+        warn!(
+            "Client {} quality score {:.3} below SLA {:.3} for {} consecutive days",
+            score.client_id, score.score, min_score, consecutive_days
+        );
+
+        let mut alert = QualityAlert {
+            id: crate::shared::utils::generate_id(),
+            client_id: score.client_id.clone(),
+            date: score.date.clone(),
+            score: score.score,
+            min_score,
+            consecutive_days,
+            notified: false,
+            created_at: chrono::Utc::now(),
+        };
+        alert.notified = Self::notify_account_team(app_state, &alert).await;
+        alerts_collection
+            .insert_one(&alert, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to store quality alert: {}", e),
+            })?;
+
+        Ok(())
+    }
+
+    /// POST the alert to the account team's webhook, if one is configured.
+    /// `text` makes it readable as a chat message (e.g. a Slack incoming
+    /// webhook); the rest is for anything parsing it. True if accepted.
+    async fn notify_account_team(app_state: &Arc<AppState>, alert: &QualityAlert) -> bool {
+        let Some(webhook_url) = app_state.config.quality.alert_webhook_url.as_deref() else {
+            return false;
+        };
+
+        let text = format!(
+            "Client {} quality score {:.3} below its SLA of {:.3} for {} days, through {}",
+            alert.client_id, alert.score, alert.min_score, alert.consecutive_days, alert.date
+        );
+        let body = serde_json::json!({
+            "event": "client.quality_breach",
+            "text": text,
+            "alert": alert,
+        });
+        let sent = reqwest::Client::new()
+            .post(webhook_url)
+            .timeout(ALERT_WEBHOOK_TIMEOUT)
+            .json(&body)
+            .send()
+            .await;
+
+        match sent {
+            Ok(response) if response.status().is_success() => true,
+            Ok(response) => {
+                warn!(
+                    "Quality alert webhook for client {} answered {}",
+                    alert.client_id,
+                    response.status()
+                );
+                false
+            }
+            Err(e) => {
+                warn!(
+                    "Quality alert webhook for client {} failed: {}",
+                    alert.client_id, e
+                );
+                false
+            }
+        }
+    }
+
+    /// Aggregate message destinations and provider supply by province
+    async fn build_demand_heatmap(app_state: &Arc<AppState>) -> Result<DemandHeatmap> {
+        let now = chrono::Utc::now();
+        let since = stored_timestamp(now - chrono::Duration::days(HEATMAP_WINDOW_DAYS));
+        let mut rows: BTreeMap<String, HeatmapRow> = BTreeMap::new();
+
+        // Demand: group by destination prefix (area code) and any explicit region
+        let demand_pipeline = vec![
+            mongodb::bson::doc! {"$match": {"created_at": {"$gte": since}}},
+            mongodb::bson::doc! {
+                "$group": {
+                    "_id": {
+                        // Stored recipients may be encrypted; older messages predate the prefix
+                        "prefix": {"$ifNull": [
+                            "$recipient_prefix",
+                            {"$substrCP": ["$recipient", 0, 6]}
+                        ]},
+                        "region": "$metadata.region",
+                    },
+                    "messages": {"$sum": 1},
+                    "delivered": {
+                        "$sum": {"$cond": [{"$eq": ["$status", "Delivered"]}, 1, 0]}
+                    },
+                }
+            },
+        ];
+
+        let mut cursor = app_state
+            .reporting
+            .collection::<mongodb::bson::Document>("messages")
+            .aggregate(demand_pipeline, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to aggregate message demand: {}", e),
+            })?;
+
+        while let Some(doc) = cursor
+            .try_next()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read message demand: {}", e),
+            })?
+        {
+            let key = doc.get_document("_id").ok();
+            let region = key
+                .and_then(|k| k.get_str("region").ok())
+                .and_then(canonical_province);
+            let prefix_province = key
+                .and_then(|k| k.get_str("prefix").ok())
+                .and_then(province_for_number);
+            let province = region
+                .or(prefix_province)
+                .unwrap_or(UNKNOWN_PROVINCE)
+                .to_string();
+
+            let row = rows
+                .entry(province.clone())
+                .or_insert_with(|| HeatmapRow::new(province));
+            row.messages += count_field(&doc, "messages");
+            row.messages_delivered += count_field(&doc, "delivered");
+        }
+
+        // Supply: group providers by their registered province
+        let supply_pipeline = vec![mongodb::bson::doc! {
+            "$group": {
+                "_id": "$location.province",
+                "total": {"$sum": 1},
+                "online": {
+                    "$sum": {"$cond": [{"$eq": ["$status", "Online"]}, 1, 0]}
+                },
+            }
+        }];
+
+        let mut cursor = app_state
+            .reporting
+            .collection::<mongodb::bson::Document>("providers")
+            .aggregate(supply_pipeline, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to aggregate provider supply: {}", e),
+            })?;
+
+        while let Some(doc) = cursor
+            .try_next()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read provider supply: {}", e),
+            })?
+        {
+            let province = doc
+                .get_str("_id")
+                .ok()
+                .and_then(canonical_province)
+                .unwrap_or(UNKNOWN_PROVINCE)
+                .to_string();
+
+            let row = rows
+                .entry(province.clone())
+                .or_insert_with(|| HeatmapRow::new(province));
+            row.providers_total += count_field(&doc, "total");
+            row.providers_online += count_field(&doc, "online");
+        }
+
+        let mut rows: Vec<HeatmapRow> = rows.into_values().collect();
+        for row in rows.iter_mut() {
+            row.finalize();
+        }
+        // Most under-supplied provinces first
+        rows.sort_by(|a, b| b.demand_per_provider.total_cmp(&a.demand_per_provider));
+
+        Ok(DemandHeatmap {
+            id: crate::shared::utils::generate_id(),
+            date: now.format("%Y-%m-%d").to_string(),
+            window_days: HEATMAP_WINDOW_DAYS,
+            rows,
+            generated_at: now,
+        })
+    }
+}
+
+/// `$sum` results come back as i32 or i64 depending on magnitude
+fn count_field(doc: &mongodb::bson::Document, field: &str) -> u64 {
+    doc.get_i64(field)
+        .or_else(|_| doc.get_i32(field).map(i64::from))
+        .unwrap_or(0)
+        .max(0) as u64
+}
+
+/// How many below-threshold scores run back over consecutive days from the
+/// first of `recent` (newest first); a day without a score ends the streak
+fn below_threshold_streak(recent: &[ClientQualityScore]) -> usize {
+    let mut streak = 0;
+    let mut expected: Option<chrono::NaiveDate> = None;
+    for score in recent {
+        let Ok(date) = chrono::NaiveDate::parse_from_str(&score.date, "%Y-%m-%d") else {
+            break;
+        };
+        if !score.below_threshold || expected.is_some_and(|expected| date != expected) {
+            break;
+        }
+        streak += 1;
+        expected = date.pred_opt();
+    }
+    streak
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_streak_needs_consecutive_days() {
+        let score = |date: &str, below_threshold: bool| {
+            let mut score = ClientQualityScore::new("client-1".to_string(), date.to_string());
+            score.below_threshold = below_threshold;
+            score
+        };
+
+        let consecutive = [score("2026-09-10", true), score("2026-09-09", true)];
+        assert_eq!(below_threshold_streak(&consecutive), 2);
+
+        // No score on the 9th: the 8th is not part of the streak
+        let gap = [score("2026-09-10", true), score("2026-09-08", true)];
+        assert_eq!(below_threshold_streak(&gap), 1);
+
+        let recovered = [score("2026-09-10", true), score("2026-09-09", false)];
+        assert_eq!(below_threshold_streak(&recovered), 1);
+    }
+}
@@ -0,0 +1,103 @@
+use futures::stream::TryStreamExt;
+use mongodb::{Collection, Database};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use tracing::warn;
+
+use crate::domain::entities::{CompiledRoutingRule, Provider, RoutingContext, RoutingRule};
+use crate::shared::{PeerPowerError, Result};
+
+/// How long the dispatcher reuses loaded rules before re-reading them
+const RULE_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// Loads enabled routing rules and evaluates them per message.
+///
+/// Rules are cached briefly so the dispatcher doesn't hit Mongo per job;
+/// admin changes call `invalidate` so they apply on this instance at once.
+pub struct RoutingRuleEngine {
+    rules: Collection<RoutingRule>,
+    cache: RwLock<Option<(Instant, Arc<Vec<CompiledRoutingRule>>)>>,
+}
+
+/// Rules that matched a message, and the constraints they impose
+#[derive(Debug, Default)]
+pub struct RoutingDecision {
+    pub matched: Vec<RoutingRule>,
+}
+
+impl RoutingDecision {
+    pub fn evaluate(rules: &[CompiledRoutingRule], context: &RoutingContext) -> Self {
+        Self {
+            matched: rules
+                .iter()
+                .filter(|rule| rule.matches(context))
+                .map(|rule| rule.rule.clone())
+                .collect(),
+        }
+    }
+
+    /// Whether `provider` satisfies every matched rule
+    pub fn allows(&self, provider: &Provider) -> bool {
+        self.matched.iter().all(|rule| rule.action.allows(provider))
+    }
+
+    /// Count a hit for each matched rule
+    pub fn record_hits(&self) {
+        for rule in &self.matched {
+            metrics::counter!("routing_rule_hits_total", "rule_id" => rule.id.clone()).increment(1);
+        }
+    }
+}
+
+impl RoutingRuleEngine {
+    pub fn new(database: Arc<Database>) -> Self {
+        Self {
+            rules: database.collection("routing_rules"),
+            cache: RwLock::new(None),
+        }
+    }
+
+    /// Enabled rules, oldest first
+    pub async fn active_rules(&self) -> Result<Arc<Vec<CompiledRoutingRule>>> {
+        if let Some((loaded_at, rules)) = self.cache.read().await.as_ref() {
+            if loaded_at.elapsed() < RULE_CACHE_TTL {
+                return Ok(rules.clone());
+            }
+        }
+
+        let find_options = mongodb::options::FindOptions::builder()
+            .sort(mongodb::bson::doc! {"created_at": 1})
+            .build();
+        let rules: Vec<RoutingRule> = self
+            .rules
+            .find(mongodb::bson::doc! {"enabled": true}, find_options)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch routing rules: {}", e),
+            })?
+            .try_collect()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read routing rules: {}", e),
+            })?;
+
+        let compiled: Vec<CompiledRoutingRule> = rules
+            .into_iter()
+            .filter_map(|rule| {
+                let rule_id = rule.id.clone();
+                CompiledRoutingRule::compile(rule)
+                    .map_err(|e| warn!("Skipping invalid routing rule {}: {}", rule_id, e))
+                    .ok()
+            })
+            .collect();
+
+        let compiled = Arc::new(compiled);
+        *self.cache.write().await = Some((Instant::now(), compiled.clone()));
+        Ok(compiled)
+    }
+
+    pub async fn invalidate(&self) {
+        *self.cache.write().await = None;
+    }
+}
@@ -0,0 +1,176 @@
+use futures::stream::TryStreamExt;
+use mongodb::bson::{doc, Document};
+use mongodb::options::{FindOneAndUpdateOptions, FindOptions, ReturnDocument, UpdateOptions};
+use mongodb::{Collection, Database};
+use std::sync::Arc;
+use tracing::error;
+
+use crate::domain::entities::{AuthEvent, UserSession};
+use crate::shared::utils::{now, stored_timestamp};
+use crate::shared::{PeerPowerError, Result};
+
+/// Sessions unused for longer than this have no live refresh token left
+const SESSION_IDLE_DAYS: i64 = 30;
+
+/// Device sessions (`user_sessions`) and the append-only `auth_events` log
+pub struct SessionStore {
+    sessions: Collection<UserSession>,
+    events: Collection<AuthEvent>,
+}
+
+impl SessionStore {
+    pub fn new(database: Arc<Database>) -> Self {
+        Self {
+            sessions: database.collection("user_sessions"),
+            events: database.collection("auth_events"),
+        }
+    }
+
+    /// Record an auth event without failing the calling request
+    pub async fn record_event(&self, event: AuthEvent) {
+        if let Err(e) = self.events.insert_one(&event, None).await {
+            error!("Failed to record auth event {:?}: {}", event.kind, e);
+        }
+    }
+
+    /// Mark a session used from this client, creating it on first use
+    /// (including sessions from tokens issued before sessions were tracked)
+    pub async fn touch(
+        &self,
+        session_id: &str,
+        user_id: &str,
+        ip_address: Option<String>,
+        user_agent: Option<String>,
+    ) -> Result<()> {
+        self.sessions
+            .update_one(
+                doc! {"id": session_id, "user_id": user_id},
+                touched(ip_address, user_agent),
+                UpdateOptions::builder().upsert(true).build(),
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to update session: {}", e),
+            })?;
+        Ok(())
+    }
+
+    /// Signed-in devices, most recently used first
+    pub async fn list_active(&self, user_id: &str) -> Result<Vec<UserSession>> {
+        let idle_cutoff = stored_timestamp(now() - chrono::Duration::days(SESSION_IDLE_DAYS));
+        let options = FindOptions::builder()
+            .sort(doc! {"last_used_at": -1})
+            .build();
+
+        self.sessions
+            .find(
+                doc! {
+                    "user_id": user_id,
+                    "revoked_at": null,
+                    "last_used_at": {"$gte": idle_cutoff},
+                },
+                options,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch sessions: {}", e),
+            })?
+            .try_collect()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read sessions: {}", e),
+            })
+    }
+
+    pub async fn find_active(
+        &self,
+        user_id: &str,
+        session_id: &str,
+    ) -> Result<Option<UserSession>> {
+        self.sessions
+            .find_one(
+                doc! {
+                    "id": session_id,
+                    "user_id": user_id,
+                    "revoked_at": null,
+                },
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch session: {}", e),
+            })
+    }
+
+    /// Close a session, returning it if it was still open
+    pub async fn end(&self, session_id: &str) -> Result<Option<UserSession>> {
+        self.sessions
+            .find_one_and_update(
+                doc! {"id": session_id, "revoked_at": null},
+                revoked(),
+                FindOneAndUpdateOptions::builder()
+                    .return_document(ReturnDocument::After)
+                    .build(),
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to end session: {}", e),
+            })
+    }
+
+    pub async fn end_all(&self, user_id: &str) -> Result<u64> {
+        let result = self
+            .sessions
+            .update_many(
+                doc! {"user_id": user_id, "revoked_at": null},
+                revoked(),
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to end sessions: {}", e),
+            })?;
+        Ok(result.modified_count)
+    }
+}
+
+/// Update marking a session used, with the fields it is created with on first use
+fn touched(ip_address: Option<String>, user_agent: Option<String>) -> Document {
+    let now = stored_timestamp(now());
+    doc! {
+        "$set": {
+            "last_used_at": &now,
+            "ip_address": ip_address,
+            "user_agent": user_agent,
+        },
+        "$setOnInsert": {
+            "created_at": &now,
+            "revoked_at": null,
+        }
+    }
+}
+
+fn revoked() -> Document {
+    doc! {"$set": {"revoked_at": stored_timestamp(now())}}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::test_support::{read_back, read_back_from};
+
+    #[test]
+    fn test_session_reads_back_after_touch_and_revoke() {
+        let session: UserSession = read_back_from(
+            doc! {"id": "session-1", "user_id": "user-1"},
+            &touched(Some("198.51.100.7".to_string()), None),
+        );
+        assert_eq!(session.ip_address.as_deref(), Some("198.51.100.7"));
+        assert!(session.revoked_at.is_none());
+
+        let touched_again = read_back(&session, &touched(None, Some("app/2.0".to_string())));
+        assert_eq!(touched_again.created_at, session.created_at);
+
+        assert!(read_back(&session, &revoked()).revoked_at.is_some());
+    }
+}
@@ -0,0 +1,480 @@
+use futures::stream::TryStreamExt;
+use mongodb::bson::{doc, Document};
+use mongodb::options::FindOptions;
+use mongodb::{Collection, Database};
+use reqwest::Client;
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::config::SlaConfig;
+use crate::domain::entities::{Job, Message, MessagePriority, SlaAttainment, SlaBreach};
+use crate::infrastructure::database::RedisConnection;
+use crate::infrastructure::job_queue::JobQueue;
+use crate::infrastructure::webhook_signature::{SIGNATURE_HEADER, TIMESTAMP_HEADER};
+use crate::shared::types::MessageStatus;
+use crate::shared::utils::stored_timestamp;
+use crate::shared::{PeerPowerError, Result};
+
+const SWEEP_LOCK_KEY: &str = "messages:sla:sweep_lock";
+
+/// Messages checked per priority per sweep at most
+const SWEEP_BATCH_SIZE: i64 = 200;
+
+/// How long a client's webhook gets to answer a breach notification
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+const PRIORITIES: [MessagePriority; 4] = [
+    MessagePriority::Urgent,
+    MessagePriority::High,
+    MessagePriority::Normal,
+    MessagePriority::Low,
+];
+
+/// Delivery SLA tracking per message priority.
+///
+/// Every message is due `SLA_<PRIORITY>_SECONDS` after it was submitted, or
+/// after its scheduled time. A sweep finds messages still open past their due
+/// time and escalates each once: it is marked breached, its job moves up one
+/// priority if it is still waiting in the queue, the breach is recorded for
+/// admins, and the client's webhook is told if the message has one. The
+/// message keeps the priority it was priced at.
+pub struct SlaMonitor {
+    messages: Collection<Message>,
+    jobs: Collection<Job>,
+    breaches: Collection<SlaBreach>,
+    job_queue: Arc<JobQueue>,
+    redis: RedisConnection,
+    client: Client,
+    config: SlaConfig,
+}
+
+impl SlaMonitor {
+    pub fn new(
+        database: Arc<Database>,
+        job_queue: Arc<JobQueue>,
+        redis: RedisConnection,
+        config: SlaConfig,
+    ) -> Self {
+        let client = Client::builder()
+            .timeout(WEBHOOK_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+        Self {
+            messages: database.collection("messages"),
+            jobs: database.collection("jobs"),
+            breaches: database.collection("sla_breaches"),
+            job_queue,
+            redis,
+            client,
+            config,
+        }
+    }
+
+    pub fn start(self: Arc<Self>) {
+        if !self.config.enabled {
+            info!("SLA monitor disabled");
+            return;
+        }
+
+        tokio::spawn(async move {
+            let mut interval =
+                tokio::time::interval(Duration::from_secs(self.config.check_interval_seconds));
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.sweep().await {
+                    error!("SLA sweep failed: {}", e);
+                }
+            }
+        });
+    }
+
+    /// Delivery time promised for a priority
+    pub fn target_seconds(config: &SlaConfig, priority: &MessagePriority) -> u64 {
+        match priority {
+            MessagePriority::Urgent => config.urgent_seconds,
+            MessagePriority::High => config.high_seconds,
+            MessagePriority::Normal => config.normal_seconds,
+            MessagePriority::Low => config.low_seconds,
+        }
+    }
+
+    /// Escalate open messages past their SLA; one instance at a time
+    pub async fn sweep(&self) -> Result<()> {
+        if !self
+            .redis
+            .acquire_lock(
+                SWEEP_LOCK_KEY,
+                self.config.check_interval_seconds as usize * 2,
+            )
+            .await?
+        {
+            return Ok(());
+        }
+        let mut result = Ok(());
+        for priority in &PRIORITIES {
+            result = self.sweep_priority(priority).await;
+            if result.is_err() {
+                break;
+            }
+        }
+        self.redis.release_lock(SWEEP_LOCK_KEY).await?;
+        result
+    }
+
+    async fn sweep_priority(&self, priority: &MessagePriority) -> Result<()> {
+        let target_seconds = Self::target_seconds(&self.config, priority);
+        let cutoff = stored_timestamp(
+            crate::shared::utils::now() - chrono::Duration::seconds(target_seconds as i64),
+        );
+        let overdue: Vec<Message> = self
+            .messages
+            .find(
+                doc! {
+                    "status": {"$in": [
+                        format!("{:?}", MessageStatus::Pending),
+                        format!("{:?}", MessageStatus::Assigned),
+                        format!("{:?}", MessageStatus::Sent),
+                    ]},
+                    "priority": format!("{:?}", priority),
+                    "sla_breached_at": null,
+                    "created_at": {"$lt": &cutoff},
+                    "$or": [{"scheduled_at": null}, {"scheduled_at": {"$lt": &cutoff}}],
+                },
+                FindOptions::builder()
+                    .sort(doc! {"created_at": 1})
+                    .limit(SWEEP_BATCH_SIZE)
+                    .build(),
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to query overdue messages: {}", e),
+            })?
+            .try_collect()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read overdue messages: {}", e),
+            })?;
+
+        for message in overdue {
+            if let Err(e) = self.escalate(&message, target_seconds).await {
+                warn!("Failed to escalate SLA breach of {}: {}", message.id, e);
+            }
+        }
+        Ok(())
+    }
+
+    async fn escalate(&self, message: &Message, target_seconds: u64) -> Result<()> {
+        let detected_at = crate::shared::utils::now();
+        // Only the first sweep to see the message escalates it
+        let marked = self
+            .messages
+            .update_one(
+                doc! {"id": &message.id, "sla_breached_at": null},
+                doc! {"$set": {"sla_breached_at": stored_timestamp(detected_at)}},
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to mark SLA breach: {}", e),
+            })?;
+        if marked.modified_count == 0 {
+            return Ok(());
+        }
+
+        let escalated_to = match message.status {
+            MessageStatus::Pending => self.bump_job(&message.id).await?,
+            _ => None,
+        };
+
+        let due_at = message.scheduled_at.unwrap_or(message.created_at)
+            + chrono::Duration::seconds(target_seconds as i64);
+        let mut breach = SlaBreach {
+            id: crate::shared::utils::generate_id(),
+            message_id: message.id.clone(),
+            client_id: message.client_id.clone(),
+            priority: message.priority.clone(),
+            status: message.status.clone(),
+            target_seconds,
+            due_at,
+            detected_at,
+            escalated_to,
+            client_notified: false,
+        };
+        breach.client_notified = self.notify_client(message, &breach).await;
+
+        self.breaches
+            .insert_one(&breach, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to record SLA breach: {}", e),
+            })?;
+
+        metrics::counter!(
+            "message_sla_breaches_total",
+            "priority" => format!("{:?}", message.priority).to_lowercase()
+        )
+        .increment(1);
+        warn!(
+            "ALERT: message {} ({:?}, client {}) missed its {}s delivery SLA while {:?}{}",
+            message.id,
+            message.priority,
+            message.client_id,
+            target_seconds,
+            message.status,
+            escalated_to
+                .map(|score| format!("; job escalated to priority {}", score))
+                .unwrap_or_default()
+        );
+        Ok(())
+    }
+
+    /// Move the message's queued job up one priority
+    async fn bump_job(&self, message_id: &str) -> Result<Option<u32>> {
+        let job = self
+            .jobs
+            .find_one(doc! {"message_id": message_id}, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch job: {}", e),
+            })?;
+        let Some(job) = job else {
+            return Ok(None);
+        };
+
+        let escalated_to = self.job_queue.escalate(&job).await?;
+        if let Some(priority_score) = escalated_to {
+            self.jobs
+                .update_one(
+                    doc! {"id": &job.id},
+                    doc! {"$set": {"priority_score": priority_score as i64}},
+                    None,
+                )
+                .await
+                .map_err(|e| PeerPowerError::Database {
+                    message: format!("Failed to update job: {}", e),
+                })?;
+        }
+        Ok(escalated_to)
+    }
+
+    /// POST the breach to the message's webhook, signed like our inbound
+    /// webhooks: HMAC-SHA256 of `"{timestamp}.{body}"`. True if accepted.
+    async fn notify_client(&self, message: &Message, breach: &SlaBreach) -> bool {
+        let Some(webhook_url) = message.metadata.webhook_url.as_deref() else {
+            return false;
+        };
+        if self.config.webhook_secret.is_empty() {
+            return false;
+        }
+
+        let body = json!({
+            "event": "message.sla_breached",
+            "message_id": message.id,
+            "tracking_id": message.metadata.tracking_id,
+            "client_reference": message.metadata.client_reference,
+            "priority": breach.priority,
+            "status": breach.status,
+            "due_at": breach.due_at.to_rfc3339(),
+            "detected_at": breach.detected_at.to_rfc3339(),
+        })
+        .to_string();
+        let timestamp = breach.detected_at.timestamp().to_string();
+        let signature = crate::shared::utils::hmac_sha256_hex(
+            &self.config.webhook_secret,
+            format!("{}.{}", timestamp, body).as_bytes(),
+        );
+
+        match self
+            .client
+            .post(webhook_url)
+            .header("content-type", "application/json")
+            .header(TIMESTAMP_HEADER, timestamp)
+            .header(SIGNATURE_HEADER, format!("sha256={}", signature))
+            .body(body)
+            .send()
+            .await
+        {
+            Ok(response) if response.status().is_success() => true,
+            Ok(response) => {
+                warn!(
+                    "SLA webhook for message {} answered {}",
+                    message.id,
+                    response.status()
+                );
+                false
+            }
+            Err(e) => {
+                warn!("SLA webhook for message {} failed: {}", message.id, e);
+                false
+            }
+        }
+    }
+
+    /// SLA attainment per priority for messages submitted in the last `days`
+    pub async fn attainment(&self, days: i64) -> Result<Vec<SlaAttainment>> {
+        let since = stored_timestamp(crate::shared::utils::now() - chrono::Duration::days(days));
+        let mut report = Vec::new();
+        for priority in PRIORITIES {
+            let target_seconds = Self::target_seconds(&self.config, &priority);
+            let totals = self
+                .messages
+                .clone_with_type::<Document>()
+                .aggregate(
+                    Self::attainment_pipeline(&priority, target_seconds, &since),
+                    None,
+                )
+                .await
+                .map_err(|e| PeerPowerError::Database {
+                    message: format!("Failed to aggregate SLA attainment: {}", e),
+                })?
+                .try_next()
+                .await
+                .map_err(|e| PeerPowerError::Database {
+                    message: format!("Failed to read SLA attainment: {}", e),
+                })?
+                .unwrap_or_default();
+
+            let count = |field: &str| {
+                totals
+                    .get_i64(field)
+                    .or_else(|_| totals.get_i32(field).map(i64::from))
+                    .unwrap_or_default()
+                    .max(0) as u64
+            };
+            let messages = count("messages");
+            let delivered_within_sla = count("within");
+            report.push(SlaAttainment {
+                priority,
+                target_seconds,
+                messages,
+                delivered_within_sla,
+                breached: count("breached"),
+                attainment_percent: Self::percent(delivered_within_sla, messages),
+            });
+        }
+        Ok(report)
+    }
+
+    /// Finished or breached messages of one priority since `since`, counted
+    /// with those delivered within `target_seconds` of being due to start
+    fn attainment_pipeline(
+        priority: &MessagePriority,
+        target_seconds: u64,
+        since: &str,
+    ) -> Vec<Document> {
+        let delivered = format!("{:?}", MessageStatus::Delivered);
+        vec![
+            doc! {"$match": {
+                "priority": format!("{:?}", priority),
+                "created_at": {"$gte": since},
+                "$or": [
+                    {"status": {"$in": [&delivered, format!("{:?}", MessageStatus::Failed)]}},
+                    {"sla_breached_at": {"$ne": null}},
+                ],
+                "status": {"$ne": format!("{:?}", MessageStatus::Cancelled)},
+            }},
+            doc! {"$addFields": {
+                "sla_latency_ms": {"$subtract": [
+                    {"$toDate": {"$ifNull": ["$delivery_report.delivered_at", "$updated_at"]}},
+                    {"$toDate": {"$ifNull": ["$scheduled_at", "$created_at"]}},
+                ]},
+            }},
+            doc! {"$group": {
+                "_id": null,
+                "messages": {"$sum": 1},
+                "within": {"$sum": {"$cond": [
+                    {"$and": [
+                        {"$eq": ["$status", &delivered]},
+                        {"$lte": ["$sla_latency_ms", target_seconds as i64 * 1000]},
+                    ]},
+                    1,
+                    0,
+                ]}},
+                "breached": {"$sum": {"$cond": [
+                    {"$ne": [{"$ifNull": ["$sla_breached_at", null]}, null]},
+                    1,
+                    0,
+                ]}},
+            }},
+        ]
+    }
+
+    /// Share met, as a percentage; nothing due counts as fully met
+    fn percent(met: u64, total: u64) -> f64 {
+        if total == 0 {
+            return 100.0;
+        }
+        (met as f64 / total as f64 * 1000.0).round() / 10.0
+    }
+
+    /// Most recent breaches first
+    pub async fn breaches(&self, limit: i64) -> Result<Vec<SlaBreach>> {
+        self.breaches
+            .find(
+                doc! {},
+                FindOptions::builder()
+                    .sort(doc! {"detected_at": -1})
+                    .limit(limit)
+                    .build(),
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch SLA breaches: {}", e),
+            })?
+            .try_collect()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read SLA breaches: {}", e),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> SlaConfig {
+        SlaConfig {
+            enabled: true,
+            check_interval_seconds: 30,
+            urgent_seconds: 120,
+            high_seconds: 300,
+            normal_seconds: 900,
+            low_seconds: 3600,
+            webhook_secret: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_targets_follow_priority() {
+        let config = config();
+        assert_eq!(
+            SlaMonitor::target_seconds(&config, &MessagePriority::Urgent),
+            120
+        );
+        assert_eq!(
+            SlaMonitor::target_seconds(&config, &MessagePriority::Low),
+            3600
+        );
+    }
+
+    #[test]
+    fn test_attainment_percent() {
+        assert_eq!(SlaMonitor::percent(0, 0), 100.0);
+        assert_eq!(SlaMonitor::percent(2, 3), 66.7);
+        assert_eq!(SlaMonitor::percent(0, 4), 0.0);
+    }
+
+    #[test]
+    fn test_attainment_pipeline_counts_against_the_target() {
+        let pipeline =
+            SlaMonitor::attainment_pipeline(&MessagePriority::High, 300, "2026-01-01T00:00:00Z");
+        let group = pipeline[2].get_document("$group").unwrap();
+        let within = group.get_document("within").unwrap().to_string();
+        assert!(within.contains("300000"));
+        let matched = pipeline[0].get_document("$match").unwrap();
+        assert_eq!(matched.get_str("priority").unwrap(), "High");
+    }
+}
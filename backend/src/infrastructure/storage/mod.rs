@@ -0,0 +1,4 @@
+// Object storage implementations
+pub mod object_storage;
+
+pub use object_storage::*;
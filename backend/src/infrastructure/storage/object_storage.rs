@@ -0,0 +1,202 @@
+use hmac::{Hmac, Mac};
+use reqwest::{Client, Method};
+use sha2::{Digest, Sha256};
+use tracing::{error, info};
+
+use crate::config::BackupConfig;
+use crate::shared::{PeerPowerError, Result};
+
+/// Minimal S3-compatible object storage client (path-style, SigV4-signed).
+///
+/// Every object is written with server-side encryption: SSE-KMS when a key
+/// is configured, SSE-S3 otherwise.
+pub struct ObjectStorageClient {
+    config: BackupConfig,
+    client: Client,
+}
+
+impl ObjectStorageClient {
+    pub fn new(config: BackupConfig) -> Self {
+        Self {
+            config,
+            client: Client::new(),
+        }
+    }
+
+    pub fn is_configured(&self) -> bool {
+        !self.config.bucket.is_empty()
+            && !self.config.access_key.is_empty()
+            && !self.config.secret_key.is_empty()
+    }
+
+    /// Object key under the configured prefix
+    pub fn key(&self, path: &str) -> String {
+        format!("{}/{}", self.config.prefix.trim_end_matches('/'), path)
+    }
+
+    pub async fn put_object(&self, key: &str, body: Vec<u8>, content_type: &str) -> Result<()> {
+        let mut headers = vec![("content-type".to_string(), content_type.to_string())];
+        match &self.config.kms_key_id {
+            Some(kms_key_id) => {
+                headers.push((
+                    "x-amz-server-side-encryption".to_string(),
+                    "aws:kms".to_string(),
+                ));
+                headers.push((
+                    "x-amz-server-side-encryption-aws-kms-key-id".to_string(),
+                    kms_key_id.clone(),
+                ));
+            }
+            None => headers.push((
+                "x-amz-server-side-encryption".to_string(),
+                "AES256".to_string(),
+            )),
+        }
+
+        let size = body.len();
+        self.send(Method::PUT, key, headers, body).await?;
+        info!("Uploaded {} ({} bytes) to object storage", key, size);
+        Ok(())
+    }
+
+    pub async fn get_object(&self, key: &str) -> Result<Vec<u8>> {
+        let response = self.send(Method::GET, key, Vec::new(), Vec::new()).await?;
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|e| PeerPowerError::ExternalService {
+                service: "ObjectStorage".to_string(),
+                message: format!("Failed to read {}: {}", key, e),
+            })?;
+        Ok(bytes.to_vec())
+    }
+
+    async fn send(
+        &self,
+        method: Method,
+        key: &str,
+        mut headers: Vec<(String, String)>,
+        body: Vec<u8>,
+    ) -> Result<reqwest::Response> {
+        if !self.is_configured() {
+            return Err(PeerPowerError::Configuration {
+                message: "Backup object storage is not configured".to_string(),
+            });
+        }
+
+        // Keys are generated internally from [a-z0-9/._-], so no URI encoding is needed
+        let path = format!("/{}/{}", self.config.bucket, key);
+        let url = format!("{}{}", self.config.endpoint.trim_end_matches('/'), path);
+        let host = reqwest::Url::parse(&url)
+            .ok()
+            .and_then(|url| {
+                url.host_str().map(|host| match url.port() {
+                    Some(port) => format!("{}:{}", host, port),
+                    None => host.to_string(),
+                })
+            })
+            .ok_or_else(|| PeerPowerError::Configuration {
+                message: format!("Invalid object storage endpoint: {}", self.config.endpoint),
+            })?;
+
+        let now = chrono::Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let payload_hash = hex::encode(Sha256::digest(&body));
+
+        headers.push(("host".to_string(), host));
+        headers.push(("x-amz-content-sha256".to_string(), payload_hash.clone()));
+        headers.push(("x-amz-date".to_string(), amz_date.clone()));
+        headers.sort();
+
+        let authorization =
+            self.authorization(method.as_str(), &path, &headers, &payload_hash, now);
+
+        let mut request = self.client.request(method.clone(), &url);
+        for (name, value) in headers.iter().filter(|(name, _)| name != "host") {
+            request = request.header(name.as_str(), value.as_str());
+        }
+        let response = request
+            .header("authorization", authorization)
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| PeerPowerError::ExternalService {
+                service: "ObjectStorage".to_string(),
+                message: format!("{} {} failed: {}", method, key, e),
+            })?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            error!(
+                "Object storage {} {} failed with status {}: {}",
+                method, key, status, body
+            );
+            return Err(PeerPowerError::ExternalService {
+                service: "ObjectStorage".to_string(),
+                message: format!("{} {} returned {}", method, key, status),
+            });
+        }
+
+        Ok(response)
+    }
+
+    /// AWS Signature Version 4 `Authorization` header
+    fn authorization(
+        &self,
+        method: &str,
+        path: &str,
+        headers: &[(String, String)],
+        payload_hash: &str,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> String {
+        let date = now.format("%Y%m%d").to_string();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let scope = format!("{}/{}/s3/aws4_request", date, self.config.region);
+
+        let canonical_headers: String = headers
+            .iter()
+            .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+            .collect();
+        let signed_headers = headers
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>()
+            .join(";");
+
+        let canonical_request = format!(
+            "{}\n{}\n\n{}\n{}\n{}",
+            method, path, canonical_headers, signed_headers, payload_hash
+        );
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+
+        let signing_key = [
+            date.as_str(),
+            self.config.region.as_str(),
+            "s3",
+            "aws4_request",
+        ]
+        .iter()
+        .fold(
+            format!("AWS4{}", self.config.secret_key).into_bytes(),
+            |key, part| hmac_sha256(&key, part.as_bytes()),
+        );
+        let signature = hex::encode(hmac_sha256(&signing_key, string_to_sign.as_bytes()));
+
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.config.access_key, scope, signed_headers, signature
+        )
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
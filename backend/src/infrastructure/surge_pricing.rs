@@ -0,0 +1,214 @@
+use chrono::{DateTime, Utc};
+use futures::stream::TryStreamExt;
+use mongodb::bson::{doc, Document};
+use mongodb::{Collection, Database};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::{error, info};
+
+use crate::config::SurgePricingConfig;
+use crate::shared::types::{Carrier, MessageStatus, ProviderStatus};
+use crate::shared::{PeerPowerError, Result};
+
+/// Supply and demand on one carrier and the multiplier they give
+#[derive(Debug, Clone, Serialize)]
+pub struct CarrierSurge {
+    pub carrier: String,
+    pub queued_messages: u64,
+    pub online_providers: u64,
+    pub multiplier: f64,
+    pub calculated_at: DateTime<Utc>,
+}
+
+/// Surge multipliers per recipient carrier, recalculated periodically from
+/// the messages waiting for a provider and the providers online.
+///
+/// A message is quoted and charged at its carrier's multiplier when it is
+/// sent; the provider's share scales with the charge, so surges pay the
+/// providers who pick up the extra traffic. Each instance recalculates on
+/// its own from the same Mongo counts.
+pub struct SurgePricing {
+    config: SurgePricingConfig,
+    messages: Collection<Document>,
+    providers: Collection<Document>,
+    current: RwLock<HashMap<String, CarrierSurge>>,
+}
+
+impl SurgePricing {
+    pub fn new(database: Arc<Database>, config: SurgePricingConfig) -> Self {
+        Self {
+            config,
+            messages: database.collection("messages"),
+            providers: database.collection("providers"),
+            current: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// The carrier's multiplier from the last recalculation; 1.0 when surge
+    /// pricing is off or nothing has been calculated yet
+    pub fn multiplier(&self, carrier: &Carrier) -> f64 {
+        if !self.config.enabled {
+            return 1.0;
+        }
+        let current = match self.current.read() {
+            Ok(current) => current,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        current
+            .get(&format!("{:?}", carrier))
+            .map(|surge| surge.multiplier)
+            .unwrap_or(1.0)
+    }
+
+    pub fn start(self: Arc<Self>) {
+        if !self.config.enabled {
+            info!("Surge pricing disabled");
+            return;
+        }
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(
+                self.config.recalc_interval_seconds.max(1),
+            ));
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.recalculate().await {
+                    error!("Failed to recalculate surge pricing: {}", e);
+                }
+            }
+        });
+    }
+
+    /// Count queued messages and online providers per carrier and replace
+    /// the multipliers
+    pub async fn recalculate(&self) -> Result<Vec<CarrierSurge>> {
+        let queued = Self::count_by(
+            &self.messages,
+            doc! {"status": format!("{:?}", MessageStatus::Pending)},
+            "$recipient_carrier",
+        )
+        .await?;
+        let online = Self::count_by(
+            &self.providers,
+            doc! {
+                "status": format!("{:?}", ProviderStatus::Online),
+                "carrier_mismatch": null,
+            },
+            "$carrier",
+        )
+        .await?;
+
+        let now = crate::shared::utils::now();
+        let surges: Vec<CarrierSurge> = Carrier::ALL
+            .iter()
+            .map(|carrier| {
+                let key = format!("{:?}", carrier);
+                let queued_messages = queued.get(&key).copied().unwrap_or(0);
+                let online_providers = online.get(&key).copied().unwrap_or(0);
+                let multiplier = surge_multiplier(&self.config, queued_messages, online_providers);
+                metrics::gauge!("surge_multiplier", "carrier" => key.to_lowercase())
+                    .set(multiplier);
+                CarrierSurge {
+                    carrier: key.to_lowercase(),
+                    queued_messages,
+                    online_providers,
+                    multiplier,
+                    calculated_at: now,
+                }
+            })
+            .collect();
+
+        let mut current = match self.current.write() {
+            Ok(current) => current,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        *current = Carrier::ALL
+            .iter()
+            .map(|carrier| format!("{:?}", carrier))
+            .zip(surges.iter().cloned())
+            .collect();
+        Ok(surges)
+    }
+
+    /// Documents matching `filter`, counted per value of `field`
+    async fn count_by(
+        collection: &Collection<Document>,
+        filter: Document,
+        field: &str,
+    ) -> Result<HashMap<String, u64>> {
+        let pipeline = vec![
+            doc! {"$match": filter},
+            doc! {"$group": {"_id": field, "count": {"$sum": 1}}},
+        ];
+        let mut cursor =
+            collection
+                .aggregate(pipeline, None)
+                .await
+                .map_err(|e| PeerPowerError::Database {
+                    message: format!("Failed to count for surge pricing: {}", e),
+                })?;
+
+        let mut counts = HashMap::new();
+        while let Some(row) = cursor
+            .try_next()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read surge pricing counts: {}", e),
+            })?
+        {
+            if let Ok(key) = row.get_str("_id") {
+                let count = row
+                    .get_i32("count")
+                    .map(i64::from)
+                    .or_else(|_| row.get_i64("count"))
+                    .unwrap_or(0);
+                counts.insert(key.to_string(), count.max(0) as u64);
+            }
+        }
+        Ok(counts)
+    }
+}
+
+/// How far demand outruns supply, bounded by the configured multipliers:
+/// 1.0 at `target_queue_per_provider` queued messages per online provider,
+/// proportionally more above it. Messages queued with nobody online to
+/// take them price at the maximum.
+pub fn surge_multiplier(config: &SurgePricingConfig, queued: u64, online: u64) -> f64 {
+    let min = config.min_multiplier.max(0.0);
+    let max = config.max_multiplier.max(min);
+    let pressure = match (queued, online) {
+        (0, 0) => 1.0,
+        (_, 0) => max,
+        _ => queued as f64 / (online as f64 * config.target_queue_per_provider.max(1.0)),
+    };
+    pressure.clamp(min, max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_surge_multiplier_bounds() {
+        let config = SurgePricingConfig {
+            enabled: true,
+            min_multiplier: 1.0,
+            max_multiplier: 2.0,
+            target_queue_per_provider: 10.0,
+            recalc_interval_seconds: 60,
+        };
+        assert_eq!(surge_multiplier(&config, 0, 0), 1.0);
+        assert_eq!(surge_multiplier(&config, 20, 4), 1.0); // under target: no discount by default
+        assert_eq!(surge_multiplier(&config, 60, 4), 1.5);
+        assert_eq!(surge_multiplier(&config, 500, 4), 2.0);
+        assert_eq!(surge_multiplier(&config, 1, 0), 2.0);
+
+        let discounting = SurgePricingConfig {
+            min_multiplier: 0.8,
+            ..config
+        };
+        assert_eq!(surge_multiplier(&discounting, 0, 10), 0.8);
+    }
+}
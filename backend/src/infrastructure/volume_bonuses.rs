@@ -0,0 +1,219 @@
+use chrono::NaiveDate;
+use futures::stream::TryStreamExt;
+use mongodb::bson::{doc, Document};
+use mongodb::options::UpdateOptions;
+use mongodb::{Collection, Database};
+use std::sync::Arc;
+use tracing::info;
+
+use crate::config::EarningsConfig;
+use crate::domain::entities::number_pool::month_bounds;
+use crate::domain::entities::{EarningsEvent, LedgerTransaction, VolumeBonus};
+use crate::infrastructure::ledger::Ledger;
+use crate::shared::{Money, PeerPowerError, Result};
+
+/// Monthly volume bonuses: a percentage on top of a provider's earnings for
+/// the month, by how many deliveries they made in it.
+///
+/// Months are counted from the per-delivery earnings events, on the local
+/// calendar. Each bonus is posted to the ledger keyed by provider and month,
+/// so crediting a month again only fills in what is missing.
+pub struct VolumeBonuses {
+    events: Collection<EarningsEvent>,
+    bonuses: Collection<VolumeBonus>,
+    ledger: Arc<Ledger>,
+    config: EarningsConfig,
+}
+
+impl VolumeBonuses {
+    pub fn new(database: Arc<Database>, ledger: Arc<Ledger>, config: EarningsConfig) -> Self {
+        Self {
+            events: database.collection("earnings_events"),
+            bonuses: database.collection("volume_bonuses"),
+            ledger,
+            config,
+        }
+    }
+
+    /// Credit the bonuses earned in the month containing `month`
+    pub async fn credit_month(&self, month: NaiveDate) -> Result<Vec<VolumeBonus>> {
+        if self.config.volume_bonus_tiers.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let (first, last) = month_bounds(month);
+        let period = first.format("%Y-%m").to_string();
+        let pipeline = vec![
+            doc! {"$match": {
+                "date": {
+                    "$gte": first.format("%Y-%m-%d").to_string(),
+                    "$lte": last.format("%Y-%m-%d").to_string(),
+                },
+                "clawed_back": {"$ne": true},
+            }},
+            doc! {
+                "$group": {
+                    "_id": "$provider_id",
+                    "deliveries": {"$sum": 1},
+                    "base_earnings": {"$sum": Money::micros_expr("$base_amount")},
+                }
+            },
+        ];
+        let mut cursor = self
+            .events
+            .clone_with_type::<Document>()
+            .aggregate(pipeline, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to aggregate monthly deliveries: {}", e),
+            })?;
+
+        let mut credited = Vec::new();
+        while let Some(doc) = cursor
+            .try_next()
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to read monthly deliveries: {}", e),
+            })?
+        {
+            let Ok(provider_id) = doc.get_str("_id") else {
+                continue;
+            };
+            let deliveries = doc
+                .get_i64("deliveries")
+                .or_else(|_| doc.get_i32("deliveries").map(i64::from))
+                .unwrap_or(0)
+                .max(0) as u64;
+            let bonus_percent = self.config.volume_bonus_percent(deliveries);
+            let base_earnings = Money::from_field(&doc, "base_earnings");
+            if bonus_percent <= 0.0 || !base_earnings.is_positive() {
+                continue;
+            }
+
+            let bonus = VolumeBonus::new(
+                provider_id.to_string(),
+                period.clone(),
+                deliveries,
+                base_earnings,
+                bonus_percent,
+            );
+            // The ledger is the record of payment; the bonus document explains it
+            let posted = self
+                .ledger
+                .post(&LedgerTransaction::volume_bonus(
+                    &bonus.reference(),
+                    &bonus.provider_id,
+                    bonus.amount,
+                ))
+                .await?;
+            self.store(&bonus).await?;
+            if posted {
+                metrics::counter!("volume_bonuses_total").increment(1);
+                credited.push(bonus);
+            }
+        }
+
+        if !credited.is_empty() {
+            info!(
+                "Credited {} volume bonuses for {} ({} PPT)",
+                credited.len(),
+                period,
+                credited.iter().map(|bonus| bonus.amount).sum::<Money>()
+            );
+        }
+        Ok(credited)
+    }
+
+    async fn store(&self, bonus: &VolumeBonus) -> Result<()> {
+        let (filter, update) = insertion(bonus)?;
+        self.bonuses
+            .update_one(
+                filter,
+                update,
+                UpdateOptions::builder().upsert(true).build(),
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to store volume bonus: {}", e),
+            })?;
+        Ok(())
+    }
+}
+
+/// The upsert storing `bonus` unless the provider already has one for its period
+fn insertion(bonus: &VolumeBonus) -> Result<(Document, Document)> {
+    let document = mongodb::bson::to_document(bonus).map_err(|e| PeerPowerError::Internal {
+        message: format!("Failed to serialize volume bonus: {}", e),
+    })?;
+    Ok((
+        doc! {"provider_id": &bonus.provider_id, "period": &bonus.period},
+        doc! {"$setOnInsert": document},
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{QualityBonusConfig, VolumeBonusTier};
+    use crate::shared::test_support::read_back_from;
+
+    #[test]
+    fn test_highest_tier_reached_sets_the_bonus() {
+        let config = EarningsConfig {
+            off_peak_multiplier: 1.0,
+            off_peak_start_hour: 1,
+            off_peak_end_hour: 6,
+            utc_offset_hours: 7,
+            volume_bonus_tiers: vec![
+                VolumeBonusTier {
+                    min_deliveries: 500,
+                    bonus_percent: 10.0,
+                },
+                VolumeBonusTier {
+                    min_deliveries: 2000,
+                    bonus_percent: 15.0,
+                },
+            ],
+            quality_bonus: QualityBonusConfig {
+                percent_per_target: 2.0,
+                min_deliveries: 50,
+                success_rate_target: 95.0,
+                confirmation_seconds_target: 60.0,
+                dispute_rate_target: 1.0,
+            },
+        };
+        assert_eq!(config.volume_bonus_percent(499), 0.0);
+        assert_eq!(config.volume_bonus_percent(500), 10.0);
+        assert_eq!(config.volume_bonus_percent(2500), 15.0);
+
+        let bonus = VolumeBonus::new(
+            "prov-1".to_string(),
+            "2026-09".to_string(),
+            600,
+            Money::from_ppt(4.8),
+            10.0,
+        );
+        assert_eq!(bonus.amount, Money::from_ppt(0.48));
+        assert_eq!(bonus.reference(), "prov-1:2026-09");
+        assert!(
+            LedgerTransaction::volume_bonus(&bonus.reference(), "prov-1", bonus.amount)
+                .is_balanced()
+        );
+    }
+
+    #[test]
+    fn test_stored_bonus_reads_back() {
+        let bonus = VolumeBonus::new(
+            "provider-1".to_string(),
+            "2024-05".to_string(),
+            600,
+            Money::from_ppt(30.0),
+            10.0,
+        );
+
+        let (filter, update) = insertion(&bonus).unwrap();
+        let stored: VolumeBonus = read_back_from(filter, &update);
+        assert_eq!(stored.id, bonus.id);
+        assert_eq!(stored.created_at, bonus.created_at);
+    }
+}
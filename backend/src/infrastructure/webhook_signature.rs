@@ -0,0 +1,199 @@
+use axum::http::HeaderMap;
+
+use crate::config::DeliveryWebhookConfig;
+use crate::infrastructure::database::RedisConnection;
+use crate::shared::utils::verify_hmac_sha256_hex;
+use crate::shared::{PeerPowerError, Result};
+
+pub const INTEGRATION_HEADER: &str = "x-peerpower-integration";
+pub const TIMESTAMP_HEADER: &str = "x-peerpower-timestamp";
+pub const NONCE_HEADER: &str = "x-peerpower-nonce";
+pub const SIGNATURE_HEADER: &str = "x-peerpower-signature";
+
+/// Longest nonce accepted, to bound the Redis keys it creates
+const MAX_NONCE_LENGTH: usize = 64;
+
+/// Verifies signed calls to the delivery webhook.
+///
+/// Each integration signs `"{timestamp}.{nonce}.{message_id}.{body}"` with
+/// its own secret (HMAC-SHA256, hex, optionally prefixed `sha256=`). Calls
+/// older than the allowed skew are rejected, and each nonce is accepted once
+/// per integration within that window, so a captured call can't be replayed.
+pub struct WebhookVerifier {
+    redis: RedisConnection,
+    config: DeliveryWebhookConfig,
+}
+
+impl WebhookVerifier {
+    pub fn new(redis: RedisConnection, config: DeliveryWebhookConfig) -> Self {
+        Self { redis, config }
+    }
+
+    pub fn signing_payload(timestamp: &str, nonce: &str, message_id: &str, body: &[u8]) -> Vec<u8> {
+        let mut payload = format!("{}.{}.{}.", timestamp, nonce, message_id).into_bytes();
+        payload.extend_from_slice(body);
+        payload
+    }
+
+    /// Check the signature headers against the raw body, returning the integration id
+    pub async fn verify(
+        &self,
+        headers: &HeaderMap,
+        message_id: &str,
+        body: &[u8],
+    ) -> Result<String> {
+        let (integration, timestamp, nonce) = Self::verify_signature(
+            &self.config,
+            headers,
+            message_id,
+            body,
+            crate::shared::utils::now().timestamp(),
+        )?;
+
+        // Remember the nonce for as long as its timestamp could still be accepted
+        let nonce_key = format!("webhook_nonce:{}:{}", integration, nonce);
+        let ttl = (self.config.max_skew_seconds.max(1) * 2) as usize;
+        if !self.redis.acquire_lock(&nonce_key, ttl).await? {
+            metrics::counter!("delivery_webhook_rejected_total", "reason" => "replay").increment(1);
+            return Err(PeerPowerError::AuthenticationFailed {
+                reason: format!("Webhook nonce already used (timestamp {})", timestamp),
+            });
+        }
+
+        Ok(integration)
+    }
+
+    /// Everything except the nonce check; returns `(integration, timestamp, nonce)`
+    fn verify_signature(
+        config: &DeliveryWebhookConfig,
+        headers: &HeaderMap,
+        message_id: &str,
+        body: &[u8],
+        now: i64,
+    ) -> Result<(String, i64, String)> {
+        let reject = |reason: &'static str, detail: &str| {
+            metrics::counter!("delivery_webhook_rejected_total", "reason" => reason).increment(1);
+            PeerPowerError::AuthenticationFailed {
+                reason: detail.to_string(),
+            }
+        };
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+        };
+
+        let (Some(integration), Some(timestamp), Some(nonce), Some(signature)) = (
+            header(INTEGRATION_HEADER),
+            header(TIMESTAMP_HEADER),
+            header(NONCE_HEADER),
+            header(SIGNATURE_HEADER),
+        ) else {
+            return Err(reject("unsigned", "Missing webhook signature headers"));
+        };
+
+        let secret = config
+            .secrets
+            .get(integration)
+            .ok_or_else(|| reject("unknown_integration", "Unknown webhook integration"))?;
+
+        if nonce.len() > MAX_NONCE_LENGTH {
+            return Err(reject("bad_nonce", "Webhook nonce is too long"));
+        }
+        let signed_at: i64 = timestamp
+            .parse()
+            .map_err(|_| reject("bad_timestamp", "Invalid webhook timestamp"))?;
+        if (now - signed_at).abs() > config.max_skew_seconds {
+            return Err(reject(
+                "stale",
+                "Webhook timestamp outside the allowed window",
+            ));
+        }
+
+        let signature = signature.strip_prefix("sha256=").unwrap_or(signature);
+        let payload = Self::signing_payload(timestamp, nonce, message_id, body);
+        if !verify_hmac_sha256_hex(secret, &payload, signature) {
+            return Err(reject("bad_signature", "Invalid webhook signature"));
+        }
+
+        Ok((integration.to_string(), signed_at, nonce.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::utils::hmac_sha256_hex;
+
+    const NOW: i64 = 1_800_000_000;
+
+    fn config() -> DeliveryWebhookConfig {
+        DeliveryWebhookConfig {
+            secrets: [("gateway".to_string(), "s3cret".to_string())].into(),
+            max_skew_seconds: 300,
+        }
+    }
+
+    fn signed_headers(timestamp: i64, message_id: &str, body: &[u8]) -> HeaderMap {
+        let timestamp = timestamp.to_string();
+        let payload = WebhookVerifier::signing_payload(&timestamp, "n-1", message_id, body);
+        let mut headers = HeaderMap::new();
+        headers.insert(INTEGRATION_HEADER, "gateway".parse().unwrap());
+        headers.insert(TIMESTAMP_HEADER, timestamp.parse().unwrap());
+        headers.insert(NONCE_HEADER, "n-1".parse().unwrap());
+        headers.insert(
+            SIGNATURE_HEADER,
+            format!("sha256={}", hmac_sha256_hex("s3cret", &payload))
+                .parse()
+                .unwrap(),
+        );
+        headers
+    }
+
+    #[test]
+    fn test_accepts_valid_signature() {
+        let body = br#"{"status":"delivered"}"#;
+        let headers = signed_headers(NOW - 10, "msg-1", body);
+
+        let (integration, _, nonce) =
+            WebhookVerifier::verify_signature(&config(), &headers, "msg-1", body, NOW).unwrap();
+        assert_eq!(integration, "gateway");
+        assert_eq!(nonce, "n-1");
+    }
+
+    #[test]
+    fn test_rejects_tampered_stale_and_unsigned_calls() {
+        let body = br#"{"status":"delivered"}"#;
+        let headers = signed_headers(NOW, "msg-1", body);
+
+        // Signature is bound to the message and the body
+        assert!(
+            WebhookVerifier::verify_signature(&config(), &headers, "msg-2", body, NOW).is_err()
+        );
+        assert!(WebhookVerifier::verify_signature(
+            &config(),
+            &headers,
+            "msg-1",
+            br#"{"status":"failed"}"#,
+            NOW
+        )
+        .is_err());
+
+        // Outside the replay window
+        assert!(
+            WebhookVerifier::verify_signature(&config(), &headers, "msg-1", body, NOW + 301)
+                .is_err()
+        );
+
+        assert!(WebhookVerifier::verify_signature(
+            &config(),
+            &HeaderMap::new(),
+            "msg-1",
+            body,
+            NOW
+        )
+        .is_err());
+    }
+}
@@ -5,28 +5,31 @@ mod presentation;
 mod shared;
 
 use axum::{
-    extract::State,
+    extract::{DefaultBodyLimit, State},
     http::StatusCode,
     middleware,
     response::Json,
-    routing::{get, post, put},
+    routing::{delete, get, post, put},
     Router,
 };
 use serde_json::{json, Value};
 use std::sync::Arc;
 use tower::ServiceBuilder;
 use tower_http::{
-    cors::CorsLayer,
     request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
     trace::TraceLayer,
 };
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 use crate::presentation::handlers::{
-    admin_handlers, auth_handlers, earnings_handlers, message_handlers, provider_handlers,
-    user_handlers,
+    admin_handlers, auth_handlers, debug_handlers, dispute_handlers, download_handlers,
+    earnings_handlers, identity_handlers, job_handlers, ledger_handlers, message_handlers,
+    number_handlers, payment_handlers, payout_handlers, pricing_handlers, provider_handlers,
+    referral_handlers, user_handlers,
+};
+use crate::presentation::middleware::{
+    admin_guard, auth_middleware, cors, load_shedding, rate_limit, request_guard,
 };
-use crate::presentation::middleware::auth_middleware;
 
 use crate::config::AppConfig;
 use crate::shared::{AppState, Result};
@@ -45,8 +48,9 @@ async fn main() -> Result<()> {
     tracing::info!("Instance ID: {}", config.instance.id);
     tracing::info!("Region: {}", config.instance.region);
 
-    // Build the application
-    let app = build_app(config).await?;
+    // Build the application; the job processor watches for shutdown alongside the server
+    let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(false);
+    let (app, mut job_processor) = build_app(config, shutdown_rx).await?;
 
     // Start the server
     let listener = tokio::net::TcpListener::bind(&bind_addr)
@@ -57,32 +61,85 @@ async fn main() -> Result<()> {
 
     tracing::info!("Server listening on {}", bind_addr);
 
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal())
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+    )
+        .with_graceful_shutdown(async move {
+            shutdown_signal().await;
+            let _ = shutdown_tx.send(true);
+        })
         .await
         .map_err(|e| shared::PeerPowerError::Internal {
             message: format!("Server error: {}", e),
         })?;
 
+    // Let in-flight jobs finish (or go back on the queue) before exiting
+    job_processor.drain().await;
+
     tracing::info!("Server shutdown complete");
     Ok(())
 }
 
-async fn build_app(config: AppConfig) -> Result<Router> {
+async fn build_app(
+    config: AppConfig,
+    shutdown: tokio::sync::watch::Receiver<bool>,
+) -> Result<(Router, crate::infrastructure::JobProcessor)> {
+    // Install the metrics registry before any connections start recording
+    let metrics_handle = metrics_exporter_prometheus::PrometheusBuilder::new()
+        .install_recorder()
+        .map_err(|e| shared::PeerPowerError::Configuration {
+            message: format!("Failed to install metrics recorder: {}", e),
+        })?;
+
+    // Fail fast on a bad CORS policy, before connecting to anything
+    let cors_layer = cors::cors_layer(&config)?;
+
     // Create shared application state with database connections
     let app_state = Arc::new(AppState::new(config).await?);
 
     // Auth routes (public)
     let auth_routes = Router::new()
+        .route("/challenge", post(auth_handlers::issue_otp_challenge))
         .route("/send-otp", post(auth_handlers::send_otp))
+        .route("/resend-otp", post(auth_handlers::resend_otp))
         .route("/verify-otp", post(auth_handlers::verify_otp))
         .route("/refresh", post(auth_handlers::refresh_token))
-        .route("/logout", post(auth_handlers::logout));
+        .route("/logout", post(auth_handlers::logout))
+        // OAuth2 client_credentials grant for machine clients
+        .route("/token", post(auth_handlers::token))
+        // Logout everywhere needs the caller's token, unlike the rest of /auth
+        .route(
+            "/logout-all",
+            post(auth_handlers::logout_all).route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                auth_middleware::auth_middleware::<axum::body::Body>,
+            )),
+        );
 
     // Protected API routes (require authentication)
     let protected_routes = Router::new()
         .route("/users/profile", get(user_handlers::get_user_profile))
         .route("/users/profile", put(user_handlers::update_user_profile))
+        .route("/users/quiet-hours", put(user_handlers::update_quiet_hours))
+        .route("/users/did", put(identity_handlers::register_did))
+        .route(
+            "/users/credentials",
+            get(identity_handlers::list_credentials),
+        )
+        .route(
+            "/users/api-clients",
+            get(user_handlers::list_api_clients).post(user_handlers::create_api_client),
+        )
+        .route(
+            "/users/api-clients/:client_id/revoke",
+            post(user_handlers::revoke_api_client),
+        )
+        .route("/users/sessions", get(user_handlers::list_sessions))
+        .route(
+            "/users/sessions/:session_id/revoke",
+            post(user_handlers::revoke_session),
+        )
         .route(
             "/providers/register",
             post(provider_handlers::register_provider),
@@ -100,13 +157,110 @@ async fn build_app(config: AppConfig) -> Result<Router> {
             "/providers/:id/status",
             put(provider_handlers::update_provider_status),
         )
+        .route(
+            "/providers/:id/carrier-verification",
+            post(provider_handlers::start_carrier_verification),
+        )
+        .route(
+            "/providers/:id/carrier-verification/confirm",
+            post(provider_handlers::confirm_carrier_verification),
+        )
+        .route(
+            "/providers/:id/self-test",
+            post(provider_handlers::run_self_test),
+        )
+        .route(
+            "/providers/:id/onboarding",
+            get(provider_handlers::get_onboarding_status),
+        )
+        .route("/providers/:id/kyc", post(provider_handlers::submit_kyc))
+        .route(
+            "/providers/:id/device-key",
+            put(provider_handlers::enroll_device_key),
+        )
+        .route(
+            "/providers/:id/attestation",
+            put(provider_handlers::attest_device),
+        )
+        .route("/providers/:id/stake", get(provider_handlers::get_stake))
+        .route(
+            "/providers/:id/stake/sync",
+            post(provider_handlers::sync_stake),
+        )
+        .route(
+            "/providers/:id/payout-methods",
+            get(payout_handlers::get_payout_methods).put(payout_handlers::update_payout_methods),
+        )
+        .route(
+            "/providers/:id/payouts",
+            get(payout_handlers::list_payouts).post(payout_handlers::request_payout),
+        )
+        .route(
+            "/providers/:id/payouts/:payout_id",
+            get(payout_handlers::get_payout),
+        )
+        .route(
+            "/providers/:id/settlements",
+            get(payout_handlers::get_settlement_status),
+        )
+        .route(
+            "/providers/:id/statement",
+            get(ledger_handlers::get_provider_statement),
+        )
+        .route(
+            "/providers/:id/recipient-rules",
+            get(provider_handlers::get_recipient_rules)
+                .put(provider_handlers::update_recipient_rules),
+        )
+        .route(
+            "/providers/:id/inbound",
+            post(number_handlers::report_inbound_message),
+        )
         .route("/messages/send", post(message_handlers::send_message))
+        .route("/messages/quote", post(message_handlers::quote_message))
+        .route(
+            "/messages/export",
+            post(download_handlers::create_message_export),
+        )
+        .route(
+            "/messages/filters",
+            get(message_handlers::list_saved_filters).post(message_handlers::create_saved_filter),
+        )
+        .route(
+            "/messages/filters/:id",
+            get(message_handlers::get_saved_filter).delete(message_handlers::delete_saved_filter),
+        )
+        .route(
+            "/messages/inbound",
+            get(number_handlers::list_inbound_messages),
+        )
+        .route("/numbers", get(number_handlers::get_my_numbers))
         .route("/messages/:id", get(message_handlers::get_message_status))
+        .route(
+            "/messages/:id/dispute",
+            post(dispute_handlers::dispute_delivery),
+        )
+        .route("/messages/:id/cancel", post(message_handlers::cancel_message))
+        .route("/disputes", get(dispute_handlers::list_disputes))
+        .route("/disputes/:id", get(dispute_handlers::get_dispute))
         .route("/messages", get(message_handlers::list_messages))
+        .route(
+            "/analytics/quality",
+            get(message_handlers::get_quality_scores),
+        )
+        .route("/analytics/tags", get(message_handlers::get_tag_analytics))
         .route(
             "/messages/:message_id/delivery",
             post(message_handlers::confirm_delivery),
         )
+        .route(
+            "/messages/:message_id/received",
+            post(message_handlers::acknowledge_dispatch),
+        )
+        .route(
+            "/messages/:message_id/cancellation",
+            post(message_handlers::acknowledge_cancellation),
+        )
         .route(
             "/earnings/summary",
             get(earnings_handlers::get_provider_earnings),
@@ -116,59 +270,357 @@ async fn build_app(config: AppConfig) -> Result<Router> {
             get(earnings_handlers::get_earnings_history),
         )
         .route(
-            "/earnings/stats",
-            get(earnings_handlers::get_system_earnings_stats),
+            "/earnings/statements/:year/:month",
+            get(earnings_handlers::get_earnings_statement),
+        )
+        .route(
+            "/downloads/:id/revoke",
+            post(download_handlers::revoke_download_link),
+        )
+        .route("/payments/topup", post(payment_handlers::create_topup))
+        .route("/payments/topups", get(payment_handlers::list_topups))
+        .route(
+            "/payments/topups/:id",
+            get(payment_handlers::get_topup),
+        )
+        .route("/payments/wallet", get(payment_handlers::get_wallet))
+        .route("/pricing", get(pricing_handlers::get_pricing))
+        .route("/referrals", get(referral_handlers::get_referrals))
+        .route(
+            "/payments/statement",
+            get(ledger_handlers::get_client_statement),
         )
-        .route("/admin/stats", get(admin_handlers::get_system_stats))
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            auth_middleware::auth_middleware::<axum::body::Body>,
+        ));
+
+    // Admin API routes, with their own chain: authentication, the admin
+    // role check, then an audit entry per request (and a stricter rate limit)
+    let admin_routes = Router::new()
+        .route("/stats", get(admin_handlers::get_system_stats))
         .route(
-            "/admin/providers",
+            "/providers",
             get(admin_handlers::get_provider_performance),
         )
         .route(
-            "/admin/messages",
+            "/providers/bulk-status",
+            post(admin_handlers::bulk_update_provider_status),
+        )
+        .route(
+            "/providers/:id/daily-limit",
+            put(admin_handlers::update_provider_daily_limit),
+        )
+        .route(
+            "/providers/:id/device-key",
+            delete(admin_handlers::reset_provider_device_key),
+        )
+        .route("/providers/:id", delete(admin_handlers::delete_provider))
+        .route(
+            "/providers/:id/restore",
+            post(admin_handlers::restore_provider),
+        )
+        .route(
+            "/carriers/paused",
+            get(admin_handlers::list_paused_carriers),
+        )
+        .route(
+            "/carriers/:carrier/pause",
+            put(admin_handlers::update_carrier_pause),
+        )
+        .route("/users/:id", delete(admin_handlers::delete_user))
+        .route(
+            "/users/:id/restore",
+            post(admin_handlers::restore_user),
+        )
+        .route(
+            "/users/:id/impersonate",
+            post(admin_handlers::impersonate_user),
+        )
+        .route(
+            "/auth/lockouts/:phone",
+            get(admin_handlers::get_login_lockout),
+        )
+        .route(
+            "/auth/lockouts/:phone/unlock",
+            post(admin_handlers::unlock_login),
+        )
+        .route(
+            "/numbers",
+            get(number_handlers::list_dedicated_numbers)
+                .post(number_handlers::assign_dedicated_number),
+        )
+        .route(
+            "/numbers/:id/release",
+            post(number_handlers::release_dedicated_number),
+        )
+        .route(
+            "/numbers/rentals",
+            get(number_handlers::list_number_rentals),
+        )
+        .route(
+            "/numbers/rentals/bill",
+            post(number_handlers::run_number_billing),
+        )
+        .route(
+            "/messages",
             get(admin_handlers::get_message_analytics),
         )
+        .route(
+            "/maintenance/encrypt-phones",
+            post(admin_handlers::encrypt_stored_phones),
+        )
+        .route(
+            "/ledger/accounts/:kind/:account_id",
+            get(ledger_handlers::get_account_statement),
+        )
+        .route(
+            "/ledger/opening-balances",
+            post(ledger_handlers::open_legacy_balances),
+        )
+        .route(
+            "/ledger/reconciliations",
+            get(ledger_handlers::list_earnings_reconciliations),
+        )
+        .route(
+            "/ledger/reconciliations/run",
+            post(ledger_handlers::run_earnings_reconciliation),
+        )
+        .route("/revenue", get(ledger_handlers::get_revenue_report))
+        .route(
+            "/earnings/stats",
+            get(earnings_handlers::get_system_earnings_stats),
+        )
+        .route("/payouts", get(payout_handlers::list_admin_payouts))
+        .route("/payouts/approve", post(payout_handlers::approve_payouts))
+        .route("/payouts/run", post(payout_handlers::run_payouts))
+        .route("/payouts/:id/reject", post(payout_handlers::reject_payout))
+        .route("/exchange-rates", get(payment_handlers::get_exchange_rates))
+        .route(
+            "/exchange-rates/:currency",
+            put(payment_handlers::override_exchange_rate)
+                .delete(payment_handlers::clear_exchange_rate_override),
+        )
+        .route(
+            "/chain-settlements/run",
+            post(payout_handlers::run_chain_settlements),
+        )
+        .route(
+            "/messages/redetect-carriers",
+            post(admin_handlers::redetect_recipient_carriers),
+        )
+        .route("/heatmap", get(admin_handlers::get_demand_heatmap))
+        .route("/quality", get(admin_handlers::get_quality_scores))
+        .route(
+            "/quality/alerts",
+            get(admin_handlers::list_quality_alerts),
+        )
+        .route("/sla", get(admin_handlers::get_sla_attainment))
+        .route("/sla/breaches", get(admin_handlers::list_sla_breaches))
+        .route("/retention/run", post(admin_handlers::run_retention))
+        .route(
+            "/messages/:id",
+            get(admin_handlers::get_message_details).delete(admin_handlers::delete_message),
+        )
+        .route(
+            "/messages/:id/restore",
+            post(admin_handlers::restore_message),
+        )
+        .route("/jobs", get(job_handlers::list_jobs))
+        .route("/jobs/requeue", post(job_handlers::requeue_jobs))
+        .route("/jobs/cancel", post(job_handlers::cancel_jobs))
+        .route("/jobs/fail", post(job_handlers::fail_jobs))
+        .route("/jobs/queues", get(job_handlers::queue_depth_by_carrier))
+        .route("/jobs/dead-letters", get(job_handlers::list_dead_letters))
+        .route(
+            "/jobs/dead-letters/requeue",
+            post(job_handlers::requeue_dead_letters),
+        )
+        .route(
+            "/jobs/dead-letters/discard",
+            post(job_handlers::discard_dead_letters),
+        )
+        .route("/jobs/dead-letters/:id", get(job_handlers::get_dead_letter))
+        .route("/jobs/:id", get(job_handlers::get_job))
+        .route("/disputes", get(dispute_handlers::list_admin_disputes))
+        .route(
+            "/disputes/:id/resolve",
+            post(dispute_handlers::resolve_dispute),
+        )
+        .route(
+            "/messages/:id/dispute/confirm-fraud",
+            post(admin_handlers::confirm_fraudulent_delivery),
+        )
+        .route(
+            "/clients/:id/quality-sla",
+            put(admin_handlers::update_client_quality_sla),
+        )
+        .route(
+            "/clients/:id/pricing-plan",
+            put(pricing_handlers::assign_client_pricing_plan),
+        )
+        .route(
+            "/pricing-plans",
+            get(pricing_handlers::list_pricing_plans).post(pricing_handlers::create_pricing_plan),
+        )
+        .route(
+            "/pricing-plans/:id",
+            put(pricing_handlers::update_pricing_plan),
+        )
+        .route(
+            "/clients/:id/recipient-privacy",
+            put(admin_handlers::update_client_recipient_privacy),
+        )
+        .route(
+            "/canary",
+            get(admin_handlers::get_canary_status).put(admin_handlers::update_canary_traffic),
+        )
+        .route(
+            "/backups",
+            get(admin_handlers::list_backups).post(admin_handlers::create_backup),
+        )
+        .route("/backups/:id", get(admin_handlers::get_backup))
+        .route(
+            "/routing-rules",
+            get(admin_handlers::list_routing_rules).post(admin_handlers::create_routing_rule),
+        )
+        .route(
+            "/routing-rules/evaluate",
+            post(admin_handlers::evaluate_routing_rules),
+        )
+        .route(
+            "/routing-rules/:id",
+            put(admin_handlers::update_routing_rule).delete(admin_handlers::delete_routing_rule),
+        )
+        .route(
+            "/backups/:id/restore",
+            post(admin_handlers::restore_backup),
+        )
+        .route(
+            "/settlements/import",
+            post(admin_handlers::import_settlement_report),
+        )
+        .route(
+            "/settlements/discrepancies",
+            get(admin_handlers::list_settlement_discrepancies),
+        )
+        .route(
+            "/settlements/discrepancies/:id/resolve",
+            post(admin_handlers::resolve_settlement_discrepancy),
+        )
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            admin_guard::admin_audit_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            admin_guard::admin_guard_middleware,
+        ))
         .layer(middleware::from_fn_with_state(
             app_state.clone(),
             auth_middleware::auth_middleware::<axum::body::Body>,
         ));
 
     // Public webhook routes (no authentication required)
-    let webhook_routes = Router::new().route(
-        "/webhooks/delivery/:message_id",
-        post(message_handlers::delivery_webhook),
-    );
+    let webhook_routes = Router::new()
+        .route(
+            "/webhooks/delivery/:message_id",
+            post(message_handlers::delivery_webhook),
+        )
+        .route("/webhooks/telegram", post(auth_handlers::telegram_webhook))
+        .route("/webhooks/baray", post(payment_handlers::baray_webhook))
+        // Signed download links (authorized by HMAC signature, not JWT)
+        .route("/downloads/:id", get(download_handlers::download))
+        // DID resolution and credential checks for third parties
+        .route("/dids/:did", get(identity_handlers::resolve_did))
+        .route(
+            "/credentials/verify",
+            post(identity_handlers::verify_credential),
+        )
+        // Smoke-test login outside production (authorized by DEBUG_OTP_TOKEN)
+        .route("/debug/otp/:phone", get(debug_handlers::get_pending_otp));
 
     // API v1 routes
     let api_v1 = Router::new()
         .nest("/auth", auth_routes)
+        .nest("/admin", admin_routes)
         .nest("/", protected_routes)
-        .nest("/", webhook_routes);
+        .nest("/", webhook_routes)
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            rate_limit::rate_limit_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            load_shedding::load_shedding_middleware,
+        ));
 
     // Build the main router
     let app = Router::new()
         .route("/health", get(health_check))
         .route("/ready", get(readiness_check))
+        .route(
+            "/metrics",
+            get(move || std::future::ready(metrics_handle.render())),
+        )
+        .route("/.well-known/jwks.json", get(auth_handlers::jwks))
         .route("/", get(root_handler))
         .nest("/api/v1", api_v1)
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            request_guard::request_guard_middleware,
+        ))
+        .layer(DefaultBodyLimit::max(
+            app_state.config.server.max_body_bytes,
+        ))
         .layer(
             ServiceBuilder::new()
                 .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
                 .layer(PropagateRequestIdLayer::x_request_id())
                 .layer(TraceLayer::new_for_http())
-                .layer(CorsLayer::permissive()), // TODO: Configure CORS properly for production
+                .layer(cors_layer),
         )
         .with_state(app_state.clone());
 
     // Start the job processor
-    let mut job_processor = crate::infrastructure::JobProcessor::new(app_state.clone());
-    tokio::spawn(async move {
-        if let Err(e) = job_processor.start().await {
-            tracing::error!("Failed to start job processor: {}", e);
-        }
-    });
+    let mut job_processor = crate::infrastructure::JobProcessor::new(app_state.clone(), shutdown);
+    if let Err(e) = job_processor.start().await {
+        tracing::error!("Failed to start job processor: {}", e);
+    }
+
+    // Start relaying events published by other instances
+    app_state.events.clone().start();
+
+    // Start announcing message status changes, whoever wrote them
+    app_state.message_changes.clone().start();
+
+    // Start cancelling expired dispatches and timing out unanswered cancellations
+    app_state.cancellations.clone().start();
+
+    // Start escalating messages that miss their delivery SLA
+    app_state.sla_monitor.clone().start();
+    app_state.retention.clone().start();
+
+    // Start relaying outbox entries whose jobs never reached the queue
+    app_state.job_outbox.clone().start();
+
+    // Start the daily reporting rollups
+    crate::infrastructure::RollupTask::new(app_state.clone()).start();
+
+    // Start sending approved payouts through Baray
+    app_state.payout_processor.clone().start();
+
+    // Start settling PPT-wallet earnings on Selendra
+    app_state.chain_settler.clone().start();
+
+    // Start sampling backend latency for load shedding
+    app_state.load_shedder.clone().start();
+    app_state.intake_backpressure.clone().start();
+
+    // Start recalculating per-carrier surge multipliers
+    app_state.surge_pricing.clone().start();
 
-    Ok(app)
+    Ok((app, job_processor))
 }
 
 /// Health check endpoint - always returns healthy if the service is running
@@ -211,6 +663,8 @@ async fn readiness_check(
         "checks": {
             "database": db_status,
             "redis": redis_status,
+            "load_shedding": state.load_shedder.status(),
+            "intake_backpressure": state.intake_backpressure.pressure(),
             "external_services": "ok"  // TODO: Check FCM, Baray, etc.
         }
     }));
@@ -231,6 +685,7 @@ async fn root_handler() -> Json<Value> {
         "endpoints": {
             "health": "/health",
             "ready": "/ready",
+            "metrics": "/metrics",
             "api_v1": "/api/v1"
         },
         "documentation": "https://docs.peerpower.network"
@@ -1,19 +1,152 @@
 use axum::{
-    extract::{Query, State},
-    response::Json,
+    extract::{Path, Query, State},
+    http::{
+        header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+        StatusCode,
+    },
+    response::{IntoResponse, Json, Response},
 };
 use futures::stream::TryStreamExt;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tracing::info;
 
-use crate::domain::entities::{Message, Provider};
-use crate::presentation::handlers::message_handlers::AuthenticatedUser;
-use crate::shared::{AppState, PeerPowerError, Result};
+use crate::domain::entities::message::MessagePriority;
+use crate::domain::entities::{
+    AuditLogEntry, BackupRun, ClientQualityScore, RestoreDiff, DemandHeatmap, Message, Provider, QualityAlert, QualitySla,
+    SettlementDiscrepancy, SettlementReport, SettlementSource,
+    ClientTier, CompiledRoutingRule, RoutingAction, RoutingContext, RoutingRule, parse_condition,
+    LedgerAccountKind, RetentionReport, SlaAttainment, SlaBreach, StakeSlash,
+};
+use crate::domain::services::{ImpersonationToken, TokenAudience};
+use crate::infrastructure::carrier_redetection::CarrierRedetectionReport;
+use crate::infrastructure::impact_analysis::{ActionConfirmation, ImpactAnalyzer, ImpactReport};
+use crate::infrastructure::login_lockout::LockoutStatus;
+use crate::infrastructure::payments::parse_settlement_csv;
+use crate::infrastructure::phone_backfill::PhoneBackfillReport;
+use crate::infrastructure::routing_rules::RoutingDecision;
+use crate::infrastructure::RollupTask;
+use crate::presentation::middleware::{AdminUser, ClientInfo};
+use crate::shared::types::{Carrier, MessageStatus, PhoneNumber, ProviderStatus};
+use crate::shared::utils::{csv_field, stored_timestamp};
+use crate::shared::{AppState, Money, PeerPowerError, Result};
 
 #[derive(Debug, Deserialize)]
 pub struct AdminStatsQuery {
     pub period: Option<String>, // "today", "week", "month", "all"
+    pub tag: Option<String>,    // message analytics only
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HeatmapQuery {
+    pub date: Option<String>,   // YYYY-MM-DD, defaults to the latest snapshot
+    pub format: Option<String>, // "json" (default) or "csv"
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QualityScoreListQuery {
+    pub date: Option<String>, // YYYY-MM-DD, defaults to the latest scored day
+    pub below_threshold: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SlaQuery {
+    pub days: Option<i64>,  // attainment window, default 7, at most 90
+    pub limit: Option<i64>, // breaches listed, default 100
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RetentionRunRequest {
+    pub dry_run: Option<bool>, // defaults to true
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SettlementImportQuery {
+    pub date: String, // settlement day, YYYY-MM-DD
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DiscrepancyListQuery {
+    pub status: Option<String>, // "open" (default) or "resolved"
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResolveDiscrepancyRequest {
+    pub note: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateDailyLimitRequest {
+    pub max_daily_messages: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DailyLimitResponse {
+    pub provider_id: String,
+    pub max_daily_messages: u32,
+    pub attested: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConfirmFraudRequest {
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConfirmFraudResponse {
+    pub message_id: String,
+    pub provider_id: String,
+    pub fraud_confirmed_at: String,
+    pub slash: Option<StakeSlash>, // unset when staking is off or the provider has no stake
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImpersonateUserRequest {
+    pub reason: String,             // e.g. the support ticket being worked
+    pub app: Option<TokenAudience>, // defaults to the user's own app
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CanaryStatusQuery {
+    pub hours: Option<i64>, // comparison window, default 24
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateCanaryRequest {
+    pub traffic_percent: u8,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CanaryStatusResponse {
+    pub traffic_percent: u8,
+    pub canary_alive: bool,
+    pub window_hours: i64,
+    pub cohorts: Vec<CohortOutcomeEntry>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CohortOutcomeEntry {
+    pub cohort: String,
+    pub total_messages: u64,
+    pub delivered_messages: u64,
+    pub failed_messages: u64,
+    pub success_rate: f64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RestoreBackupRequest {
+    #[serde(default)]
+    pub collections: Vec<String>, // empty restores every collection in the backup
+    pub dry_run: Option<bool>,    // defaults to true
+    pub confirm: Option<String>,  // must repeat the backup id to actually restore
+}
+
+#[derive(Debug, Serialize)]
+pub struct RestoreBackupResponse {
+    pub dry_run: bool,
+    pub diff: Vec<RestoreDiff>,
+    pub restore_run: Option<BackupRun>,
 }
 
 #[derive(Debug, Serialize)]
@@ -58,7 +191,7 @@ pub struct MessageStatsEntry {
 pub async fn get_system_stats(
     State(app_state): State<Arc<AppState>>,
     Query(params): Query<AdminStatsQuery>,
-    AuthenticatedUser(_user_id): AuthenticatedUser, // TODO: Add admin role validation
+    AdminUser(_user_id): AdminUser,
 ) -> Result<Json<SystemStatsResponse>> {
     info!("Getting system statistics");
 
@@ -72,8 +205,8 @@ pub async fn get_system_stats(
             let end = today.and_hms_opt(23, 59, 59).unwrap().and_utc();
             Some(mongodb::bson::doc! {
                 "created_at": {
-                    "$gte": start,
-                    "$lte": end
+                    "$gte": stored_timestamp(start),
+                    "$lte": stored_timestamp(end)
                 }
             })
         }
@@ -82,8 +215,8 @@ pub async fn get_system_stats(
             let week_ago = now - chrono::Duration::days(7);
             Some(mongodb::bson::doc! {
                 "created_at": {
-                    "$gte": week_ago,
-                    "$lte": now
+                    "$gte": stored_timestamp(week_ago),
+                    "$lte": stored_timestamp(now)
                 }
             })
         }
@@ -92,8 +225,8 @@ pub async fn get_system_stats(
             let month_ago = now - chrono::Duration::days(30);
             Some(mongodb::bson::doc! {
                 "created_at": {
-                    "$gte": month_ago,
-                    "$lte": now
+                    "$gte": stored_timestamp(month_ago),
+                    "$lte": stored_timestamp(now)
                 }
             })
         }
@@ -102,7 +235,7 @@ pub async fn get_system_stats(
 
     // Get user count
     let users_collection = app_state
-        .database
+        .reporting
         .collection::<mongodb::bson::Document>("users");
     let total_users = users_collection
         .count_documents(mongodb::bson::doc! {}, None)
@@ -112,7 +245,7 @@ pub async fn get_system_stats(
         })?;
 
     // Get provider stats
-    let providers_collection = app_state.database.collection::<Provider>("providers");
+    let providers_collection = app_state.reporting.collection::<Provider>("providers");
     let total_providers = providers_collection
         .count_documents(mongodb::bson::doc! {}, None)
         .await
@@ -133,7 +266,7 @@ pub async fn get_system_stats(
         })?;
 
     // Get message stats
-    let messages_collection = app_state.database.collection::<Message>("messages");
+    let messages_collection = app_state.reporting.collection::<Message>("messages");
     let message_filter = date_filter.unwrap_or_else(|| mongodb::bson::doc! {});
 
     let total_messages = messages_collection
@@ -168,26 +301,15 @@ pub async fn get_system_stats(
         0.0
     };
 
-    // Get total earnings (aggregate from providers)
-    let earnings_pipeline = vec![mongodb::bson::doc! {
-        "$group": {
-            "_id": null,
-            "total_earnings": { "$sum": "$earnings_total" }
-        }
-    }];
-
-    let mut earnings_cursor = providers_collection
-        .aggregate(earnings_pipeline, None)
-        .await
-        .map_err(|e| PeerPowerError::Database {
-            message: format!("Failed to aggregate earnings: {}", e),
-        })?;
-
-    let total_earnings_distributed = if let Ok(Some(doc)) = earnings_cursor.try_next().await {
-        doc.get_f64("total_earnings").unwrap_or(0.0)
-    } else {
-        0.0
-    };
+    // Get total earnings credited to providers in the ledger
+    let total_earnings_distributed = app_state
+        .ledger
+        .balances(LedgerAccountKind::Provider)
+        .await?
+        .values()
+        .map(|balance| balance.credits)
+        .sum::<Money>()
+        .to_ppt();
 
     // Calculate average message cost
     let average_message_cost = if total_messages > 0 {
@@ -215,11 +337,11 @@ pub async fn get_system_stats(
 pub async fn get_provider_performance(
     State(app_state): State<Arc<AppState>>,
     Query(params): Query<AdminStatsQuery>,
-    AuthenticatedUser(_user_id): AuthenticatedUser, // TODO: Add admin role validation
+    AdminUser(_user_id): AdminUser,
 ) -> Result<Json<Vec<ProviderStatsEntry>>> {
     info!("Getting provider performance stats");
 
-    let providers_collection = app_state.database.collection::<Provider>("providers");
+    let providers_collection = app_state.reporting.collection::<Provider>("providers");
     let mut cursor = providers_collection
         .find(mongodb::bson::doc! {}, None)
         .await
@@ -227,6 +349,11 @@ pub async fn get_provider_performance(
             message: format!("Failed to fetch providers: {}", e),
         })?;
 
+    let earnings = app_state
+        .ledger
+        .balances(LedgerAccountKind::Provider)
+        .await?;
+
     let mut provider_stats = Vec::new();
     while let Ok(Some(provider)) = cursor.try_next().await {
         provider_stats.push(ProviderStatsEntry {
@@ -237,7 +364,10 @@ pub async fn get_provider_performance(
             status: format!("{:?}", provider.status),
             messages_delivered: provider.total_messages_delivered,
             success_rate: provider.success_rate,
-            total_earnings: provider.earnings_total,
+            total_earnings: earnings
+                .get(&provider.id)
+                .map(|balance| balance.credits.to_ppt())
+                .unwrap_or(0.0),
             last_active: provider
                 .last_heartbeat
                 .map(|dt| dt.to_rfc3339())
@@ -255,7 +385,7 @@ pub async fn get_provider_performance(
 pub async fn get_message_analytics(
     State(app_state): State<Arc<AppState>>,
     Query(params): Query<AdminStatsQuery>,
-    AuthenticatedUser(_user_id): AuthenticatedUser, // TODO: Add admin role validation
+    AdminUser(_user_id): AdminUser,
 ) -> Result<Json<Vec<MessageStatsEntry>>> {
     info!("Getting message analytics");
 
@@ -267,19 +397,22 @@ pub async fn get_message_analytics(
     };
 
     let mut analytics = Vec::new();
-    let messages_collection = app_state.database.collection::<Message>("messages");
+    let messages_collection = app_state.reporting.collection::<Message>("messages");
 
     for i in 0..days_back {
         let date = chrono::Utc::now() - chrono::Duration::days(i);
         let start_of_day = date.date_naive().and_hms_opt(0, 0, 0).unwrap().and_utc();
         let end_of_day = date.date_naive().and_hms_opt(23, 59, 59).unwrap().and_utc();
 
-        let day_filter = mongodb::bson::doc! {
+        let mut day_filter = mongodb::bson::doc! {
             "created_at": {
-                "$gte": start_of_day,
-                "$lte": end_of_day
+                "$gte": stored_timestamp(start_of_day),
+                "$lte": stored_timestamp(end_of_day)
             }
         };
+        if let Some(tag) = &params.tag {
+            day_filter.insert("tags", tag.trim().to_lowercase());
+        }
 
         let total_messages = messages_collection
             .count_documents(day_filter.clone(), None)
@@ -306,7 +439,8 @@ pub async fn get_message_analytics(
             0.0
         };
 
-        let total_cost = total_messages as f64 * 0.01; // Simplified cost calculation
+        // Approximate: one normal-priority segment per message
+        let total_cost = total_messages as f64 * app_state.config.pricing.base_message_cost;
 
         analytics.push(MessageStatsEntry {
             date: date.format("%Y-%m-%d").to_string(),
@@ -323,3 +457,1964 @@ pub async fn get_message_analytics(
 
     Ok(Json(analytics))
 }
+
+/// Message as shown to operators; privacy-mode recipients stay masked
+#[derive(Debug, Serialize)]
+pub struct AdminMessageView {
+    pub id: String,
+    pub client_id: String,
+    pub recipient: String,
+    pub recipient_carrier: String,
+    pub recipient_protected: bool,
+    pub status: String,
+    pub priority: String,
+    pub provider_id: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Look up a single message (admin only)
+pub async fn get_message_details(
+    State(app_state): State<Arc<AppState>>,
+    Path(message_id): Path<String>,
+    AdminUser(_user_id): AdminUser,
+) -> Result<Json<AdminMessageView>> {
+    let message = app_state
+        .database
+        .collection::<Message>("messages")
+        .find_one(mongodb::bson::doc! {"id": &message_id}, None)
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch message: {}", e),
+        })?
+        .ok_or_else(|| PeerPowerError::NotFound {
+            resource: format!("Message with ID: {}", message_id),
+        })?;
+
+    Ok(Json(AdminMessageView {
+        recipient: message.recipient.as_str().to_string(),
+        recipient_carrier: format!("{:?}", message.recipient_carrier),
+        recipient_protected: message.is_recipient_protected(),
+        status: format!("{:?}", message.status),
+        priority: format!("{:?}", message.priority),
+        provider_id: message.provider_id,
+        created_at: message.created_at.to_rfc3339(),
+        updated_at: message.updated_at.to_rfc3339(),
+        id: message.id,
+        client_id: message.client_id,
+    }))
+}
+
+/// Confirm that a disputed delivery confirmation was fraudulent (admin only).
+/// The provider's stake, if any, is slashed for it.
+pub async fn confirm_fraudulent_delivery(
+    State(app_state): State<Arc<AppState>>,
+    Path(message_id): Path<String>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+    Json(request): Json<ConfirmFraudRequest>,
+) -> Result<Json<ConfirmFraudResponse>> {
+    let reason = request.reason.trim();
+    if reason.is_empty() {
+        return Err(PeerPowerError::ValidationError {
+            field: "reason".to_string(),
+            message: "A reason is required to confirm fraud".to_string(),
+        });
+    }
+
+    let messages_collection = app_state.database.collection::<Message>("messages");
+    let message = messages_collection
+        .find_one(mongodb::bson::doc! {"id": &message_id}, None)
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch message: {}", e),
+        })?
+        .ok_or_else(|| PeerPowerError::NotFound {
+            resource: format!("Message with ID: {}", message_id),
+        })?;
+
+    let invalid = |message: &str| PeerPowerError::ValidationError {
+        field: "message".to_string(),
+        message: message.to_string(),
+    };
+    let Some(dispute) = message.dispute.as_ref() else {
+        return Err(invalid("Only disputed deliveries can be confirmed as fraud"));
+    };
+    if dispute.fraud_confirmed_at.is_some() {
+        return Err(invalid("Fraud was already confirmed for this delivery"));
+    }
+    let Some(provider_id) = message.provider_id.clone() else {
+        return Err(invalid("The message has no provider"));
+    };
+
+    let provider = app_state
+        .database
+        .collection::<Provider>("providers")
+        .find_one(mongodb::bson::doc! {"id": &provider_id}, None)
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch provider: {}", e),
+        })?
+        .ok_or_else(|| PeerPowerError::NotFound {
+            resource: format!("Provider with ID: {}", provider_id),
+        })?;
+
+    // Slash before recording the confirmation so a failed slash can be retried;
+    // a provider without a wallet or stake simply has nothing to slash
+    let slash = if app_state.staking.is_configured() {
+        match app_state
+            .staking
+            .slash(&provider, &message_id, reason, &user_id)
+            .await
+        {
+            Ok(slash) => Some(slash),
+            Err(PeerPowerError::ValidationError { message, .. }) => {
+                info!("No stake slashed for message {}: {}", message_id, message);
+                None
+            }
+            Err(e) => return Err(e),
+        }
+    } else {
+        None
+    };
+
+    let now = crate::shared::utils::now();
+    let result = messages_collection
+        .update_one(
+            mongodb::bson::doc! {"id": &message_id, "dispute.fraud_confirmed_at": null},
+            mongodb::bson::doc! {
+                "$set": {
+                    "dispute.fraud_confirmed_at": stored_timestamp(now),
+                    "dispute.fraud_confirmed_by": &user_id,
+                }
+            },
+            None,
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to record fraud confirmation: {}", e),
+        })?;
+    if result.modified_count != 1 {
+        return Err(invalid("Fraud was already confirmed for this delivery"));
+    }
+
+    let mut entry = AuditLogEntry::new(
+        Some(user_id),
+        "message.fraud_confirmed",
+        "message",
+        &message_id,
+    )
+    .with_client(client.ip, client.user_agent)
+    .with_metadata("provider_id", provider_id.clone())
+    .with_metadata("reason", reason);
+    if let Some(slash) = &slash {
+        entry = entry
+            .with_metadata("slashed_ppt", slash.amount.to_string())
+            .with_metadata("tx_hash", slash.tx_hash.clone().unwrap_or_default());
+    }
+    app_state.audit_logger.record_best_effort(entry).await;
+    metrics::counter!("fraudulent_confirmations_total").increment(1);
+
+    Ok(Json(ConfirmFraudResponse {
+        message_id,
+        provider_id,
+        fraud_confirmed_at: now.to_rfc3339(),
+        slash,
+    }))
+}
+
+/// Get the provincial demand vs. provider supply heatmap (admin only)
+pub async fn get_demand_heatmap(
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<HeatmapQuery>,
+    AdminUser(_user_id): AdminUser,
+) -> Result<Response> {
+    info!("Getting demand heatmap");
+
+    let heatmaps_collection = app_state
+        .database
+        .collection::<DemandHeatmap>("demand_heatmaps");
+
+    let heatmap = match params.date {
+        Some(date) => heatmaps_collection
+            .find_one(mongodb::bson::doc! {"date": &date}, None)
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch demand heatmap: {}", e),
+            })?
+            .ok_or_else(|| PeerPowerError::NotFound {
+                resource: format!("Demand heatmap for {}", date),
+            })?,
+        None => {
+            let latest = heatmaps_collection
+                .find_one(
+                    mongodb::bson::doc! {},
+                    mongodb::options::FindOneOptions::builder()
+                        .sort(mongodb::bson::doc! {"date": -1})
+                        .build(),
+                )
+                .await
+                .map_err(|e| PeerPowerError::Database {
+                    message: format!("Failed to fetch demand heatmap: {}", e),
+                })?;
+
+            match latest {
+                Some(heatmap) => heatmap,
+                // The rollup hasn't run yet on a fresh deployment
+                None => RollupTask::refresh_demand_heatmap(&app_state).await?,
+            }
+        }
+    };
+
+    if params.format.as_deref() == Some("csv") {
+        let mut csv = String::from(
+            "province,messages,messages_delivered,providers_total,providers_online,demand_per_provider\n",
+        );
+        for row in &heatmap.rows {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{:.2}\n",
+                csv_field(&row.province),
+                row.messages,
+                row.messages_delivered,
+                row.providers_total,
+                row.providers_online,
+                row.demand_per_provider
+            ));
+        }
+
+        return Ok((
+            [
+                (CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+                (
+                    CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"demand-heatmap-{}.csv\"", heatmap.date),
+                ),
+            ],
+            csv,
+        )
+            .into_response());
+    }
+
+    Ok(Json(heatmap).into_response())
+}
+
+/// Import a Baray settlement report and reconcile it against payouts (admin only).
+/// The request body is the CSV export; an empty body fetches the report from the Baray API.
+pub async fn import_settlement_report(
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<SettlementImportQuery>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+    body: String,
+) -> Result<Json<SettlementReport>> {
+    let date = chrono::NaiveDate::parse_from_str(&params.date, "%Y-%m-%d").map_err(|_| {
+        PeerPowerError::ValidationError {
+            field: "date".to_string(),
+            message: "Date must be formatted as YYYY-MM-DD".to_string(),
+        }
+    })?;
+
+    let (source, lines) = if body.trim().is_empty() {
+        if !app_state.baray_client.is_configured() {
+            return Err(PeerPowerError::Configuration {
+                message: "Baray API key is not configured; upload the settlement CSV instead"
+                    .to_string(),
+            });
+        }
+        (
+            SettlementSource::Api,
+            app_state.baray_client.fetch_settlement_report(date).await?,
+        )
+    } else {
+        (SettlementSource::Csv, parse_settlement_csv(&body)?)
+    };
+
+    info!(
+        "Importing Baray settlement report for {} ({} lines, {:?})",
+        date,
+        lines.len(),
+        source
+    );
+
+    let report = app_state
+        .settlement_reconciler
+        .reconcile(date, source, lines, Some(user_id.clone()))
+        .await?;
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(
+                Some(user_id),
+                "settlement_report.imported",
+                "settlement_report",
+                &report.id,
+            )
+            .with_client(client.ip, client.user_agent)
+            .with_metadata("settlement_date", report.settlement_date.clone())
+            .with_metadata("discrepancies", report.discrepancy_count.to_string()),
+        )
+        .await;
+
+    Ok(Json(report))
+}
+
+/// List settlement discrepancies awaiting review (admin only)
+pub async fn list_settlement_discrepancies(
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<DiscrepancyListQuery>,
+    AdminUser(_user_id): AdminUser,
+) -> Result<Json<Vec<SettlementDiscrepancy>>> {
+    let status = match params.status.as_deref() {
+        None | Some("open") => "Open",
+        Some("resolved") => "Resolved",
+        Some(_) => {
+            return Err(PeerPowerError::ValidationError {
+                field: "status".to_string(),
+                message: "Status must be 'open' or 'resolved'".to_string(),
+            })
+        }
+    };
+
+    let cursor = app_state
+        .database
+        .collection::<SettlementDiscrepancy>("settlement_discrepancies")
+        .find(
+            mongodb::bson::doc! {"status": status},
+            mongodb::options::FindOptions::builder()
+                .sort(mongodb::bson::doc! {"created_at": -1})
+                .limit(params.limit.unwrap_or(100).clamp(1, 500))
+                .build(),
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to query settlement discrepancies: {}", e),
+        })?;
+
+    let discrepancies = cursor
+        .try_collect()
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to read settlement discrepancies: {}", e),
+        })?;
+
+    Ok(Json(discrepancies))
+}
+
+/// Mark a settlement discrepancy as reviewed (admin only)
+pub async fn resolve_settlement_discrepancy(
+    State(app_state): State<Arc<AppState>>,
+    Path(discrepancy_id): Path<String>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+    axum::Json(request): axum::Json<ResolveDiscrepancyRequest>,
+) -> Result<Json<SettlementDiscrepancy>> {
+    if request.note.trim().is_empty() {
+        return Err(PeerPowerError::ValidationError {
+            field: "note".to_string(),
+            message: "A resolution note is required".to_string(),
+        });
+    }
+
+    let collection = app_state
+        .database
+        .collection::<SettlementDiscrepancy>("settlement_discrepancies");
+    let mut discrepancy = collection
+        .find_one(mongodb::bson::doc! {"id": &discrepancy_id}, None)
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch settlement discrepancy: {}", e),
+        })?
+        .ok_or_else(|| PeerPowerError::NotFound {
+            resource: format!("Settlement discrepancy with ID: {}", discrepancy_id),
+        })?;
+
+    discrepancy.resolve(user_id.clone(), request.note);
+
+    collection
+        .update_one(
+            mongodb::bson::doc! {"id": &discrepancy.id},
+            discrepancy_resolution(&discrepancy),
+            None,
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to resolve settlement discrepancy: {}", e),
+        })?;
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(
+                Some(user_id),
+                "settlement_discrepancy.resolved",
+                "settlement_discrepancy",
+                &discrepancy.id,
+            )
+            .with_client(client.ip, client.user_agent)
+            .with_metadata("kind", format!("{:?}", discrepancy.kind)),
+        )
+        .await;
+
+    Ok(Json(discrepancy))
+}
+
+/// Per-client quality scores for a day (admin only)
+pub async fn get_quality_scores(
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QualityScoreListQuery>,
+    AdminUser(_user_id): AdminUser,
+) -> Result<Json<Vec<ClientQualityScore>>> {
+    let scores_collection = app_state
+        .database
+        .collection::<ClientQualityScore>("quality_scores");
+
+    let date = match params.date {
+        Some(date) => date,
+        None => {
+            let latest = scores_collection
+                .find_one(
+                    mongodb::bson::doc! {},
+                    mongodb::options::FindOneOptions::builder()
+                        .sort(mongodb::bson::doc! {"date": -1})
+                        .build(),
+                )
+                .await
+                .map_err(|e| PeerPowerError::Database {
+                    message: format!("Failed to fetch quality scores: {}", e),
+                })?;
+            match latest {
+                Some(score) => score.date,
+                None => return Ok(Json(Vec::new())),
+            }
+        }
+    };
+
+    let mut filter = mongodb::bson::doc! {"date": &date};
+    if let Some(below_threshold) = params.below_threshold {
+        filter.insert("below_threshold", below_threshold);
+    }
+
+    let cursor = scores_collection
+        .find(
+            filter,
+            mongodb::options::FindOptions::builder()
+                .sort(mongodb::bson::doc! {"score": 1}) // Worst first
+                .build(),
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch quality scores: {}", e),
+        })?;
+
+    let scores = cursor
+        .try_collect()
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to read quality scores: {}", e),
+        })?;
+
+    Ok(Json(scores))
+}
+
+/// Recent contractual quality alerts (admin only)
+pub async fn list_quality_alerts(
+    State(app_state): State<Arc<AppState>>,
+    AdminUser(_user_id): AdminUser,
+) -> Result<Json<Vec<QualityAlert>>> {
+    let cursor = app_state
+        .database
+        .collection::<QualityAlert>("quality_alerts")
+        .find(
+            mongodb::bson::doc! {},
+            mongodb::options::FindOptions::builder()
+                .sort(mongodb::bson::doc! {"created_at": -1})
+                .limit(100)
+                .build(),
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch quality alerts: {}", e),
+        })?;
+
+    let alerts = cursor
+        .try_collect()
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to read quality alerts: {}", e),
+        })?;
+
+    Ok(Json(alerts))
+}
+
+/// Delivery SLA attainment per priority over recent days (admin only)
+pub async fn get_sla_attainment(
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<SlaQuery>,
+    AdminUser(_user_id): AdminUser,
+) -> Result<Json<Vec<SlaAttainment>>> {
+    let days = params.days.unwrap_or(7).clamp(1, 90);
+    Ok(Json(app_state.sla_monitor.attainment(days).await?))
+}
+
+/// Recent delivery SLA breaches and how they were escalated (admin only)
+pub async fn list_sla_breaches(
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<SlaQuery>,
+    AdminUser(_user_id): AdminUser,
+) -> Result<Json<Vec<SlaBreach>>> {
+    let limit = params.limit.unwrap_or(100).clamp(1, 500);
+    Ok(Json(app_state.sla_monitor.breaches(limit).await?))
+}
+
+/// Delete records past their retention window now, or in a dry run (the
+/// default) report how many would be deleted (admin only)
+pub async fn run_retention(
+    State(app_state): State<Arc<AppState>>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+    axum::Json(request): axum::Json<RetentionRunRequest>,
+) -> Result<Json<Vec<RetentionReport>>> {
+    let dry_run = request.dry_run.unwrap_or(true);
+    let reports = app_state.retention.run(dry_run).await?;
+
+    if !dry_run {
+        let deleted: u64 = reports.iter().map(|report| report.deleted).sum();
+        app_state
+            .audit_logger
+            .record_best_effort(
+                AuditLogEntry::new(Some(user_id), "retention.run", "retention", "manual")
+                    .with_client(client.ip, client.user_agent)
+                    .with_metadata("deleted", deleted.to_string()),
+            )
+            .await;
+    }
+
+    Ok(Json(reports))
+}
+
+/// Set or clear the contractual quality floor for a client (admin only)
+pub async fn update_client_quality_sla(
+    State(app_state): State<Arc<AppState>>,
+    Path(client_id): Path<String>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+    axum::Json(sla): axum::Json<Option<QualitySla>>,
+) -> Result<Json<Option<QualitySla>>> {
+    if let Some(sla) = &sla {
+        if !(0.0..=1.0).contains(&sla.min_score) || sla.consecutive_days == 0 {
+            return Err(PeerPowerError::ValidationError {
+                field: "sla".to_string(),
+                message: "min_score must be between 0 and 1 and consecutive_days at least 1"
+                    .to_string(),
+            });
+        }
+    }
+
+    let mut user = app_state
+        .user_repository
+        .find_by_id(&client_id)
+        .await?
+        .ok_or_else(|| PeerPowerError::NotFound {
+            resource: format!("User with ID: {}", client_id),
+        })?;
+
+    user.quality_sla = sla.clone();
+    user.updated_at = chrono::Utc::now();
+    app_state.user_repository.update(&user).await?;
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(Some(user_id), "quality_sla.updated", "user", &client_id)
+                .with_client(client.ip, client.user_agent)
+                .with_metadata(
+                    "min_score",
+                    sla.as_ref()
+                        .map(|s| s.min_score.to_string())
+                        .unwrap_or_else(|| "none".to_string()),
+                ),
+        )
+        .await;
+
+    Ok(Json(sla))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RecipientPrivacyRequest {
+    pub enabled: bool,
+}
+
+/// Turn recipient privacy mode on or off for a client (admin only).
+/// Applies to messages sent from now on; stored messages are not rewritten.
+pub async fn update_client_recipient_privacy(
+    State(app_state): State<Arc<AppState>>,
+    Path(client_id): Path<String>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+    axum::Json(request): axum::Json<RecipientPrivacyRequest>,
+) -> Result<Json<serde_json::Value>> {
+    if request.enabled && !app_state.recipient_vault.is_configured() {
+        return Err(PeerPowerError::ValidationError {
+            field: "enabled".to_string(),
+            message: "Recipient privacy is not configured on this server".to_string(),
+        });
+    }
+
+    let mut user = app_state
+        .user_repository
+        .find_by_id(&client_id)
+        .await?
+        .ok_or_else(|| PeerPowerError::NotFound {
+            resource: format!("User with ID: {}", client_id),
+        })?;
+
+    user.recipient_privacy = request.enabled;
+    user.updated_at = chrono::Utc::now();
+    app_state.user_repository.update(&user).await?;
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(
+                Some(user_id),
+                "recipient_privacy.updated",
+                "user",
+                &client_id,
+            )
+            .with_client(client.ip, client.user_agent)
+            .with_metadata("enabled", request.enabled.to_string()),
+        )
+        .await;
+
+    Ok(Json(serde_json::json!({
+        "client_id": client_id,
+        "recipient_privacy": request.enabled
+    })))
+}
+
+/// Canary rollout state with per-cohort delivery outcomes (admin only)
+pub async fn get_canary_status(
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<CanaryStatusQuery>,
+    AdminUser(_user_id): AdminUser,
+) -> Result<Json<CanaryStatusResponse>> {
+    let hours = params.hours.unwrap_or(24).clamp(1, 24 * 30);
+    let since = stored_timestamp(chrono::Utc::now() - chrono::Duration::hours(hours));
+
+    let pipeline = vec![
+        mongodb::bson::doc! {"$match": {"created_at": {"$gte": since}}},
+        mongodb::bson::doc! {
+            "$group": {
+                "_id": {"$ifNull": ["$cohort", "Stable"]},
+                "total": {"$sum": 1},
+                "delivered": {"$sum": {"$cond": [{"$eq": ["$status", "Delivered"]}, 1, 0]}},
+                "failed": {"$sum": {"$cond": [{"$eq": ["$status", "Failed"]}, 1, 0]}},
+            }
+        },
+        mongodb::bson::doc! {"$sort": {"_id": 1}},
+    ];
+
+    let mut cursor = app_state
+        .reporting
+        .collection::<mongodb::bson::Document>("messages")
+        .aggregate(pipeline, None)
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to aggregate cohort outcomes: {}", e),
+        })?;
+
+    let count = |doc: &mongodb::bson::Document, field: &str| {
+        doc.get_i64(field)
+            .or_else(|_| doc.get_i32(field).map(i64::from))
+            .unwrap_or(0)
+            .max(0) as u64
+    };
+
+    let mut cohorts = Vec::new();
+    while let Some(doc) = cursor
+        .try_next()
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to read cohort outcomes: {}", e),
+        })?
+    {
+        let total_messages = count(&doc, "total");
+        let delivered_messages = count(&doc, "delivered");
+        cohorts.push(CohortOutcomeEntry {
+            cohort: doc.get_str("_id").unwrap_or("Stable").to_lowercase(),
+            total_messages,
+            delivered_messages,
+            failed_messages: count(&doc, "failed"),
+            success_rate: if total_messages > 0 {
+                (delivered_messages as f64 / total_messages as f64) * 100.0
+            } else {
+                0.0
+            },
+        });
+    }
+
+    Ok(Json(CanaryStatusResponse {
+        traffic_percent: app_state.canary.traffic_percent().await?,
+        canary_alive: app_state.canary.is_canary_alive().await?,
+        window_hours: hours,
+        cohorts,
+    }))
+}
+
+/// Change the share of new messages routed to canary instances (admin only)
+pub async fn update_canary_traffic(
+    State(app_state): State<Arc<AppState>>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+    axum::Json(request): axum::Json<UpdateCanaryRequest>,
+) -> Result<Json<serde_json::Value>> {
+    let previous = app_state.canary.traffic_percent().await?;
+    app_state
+        .canary
+        .set_traffic_percent(request.traffic_percent)
+        .await?;
+
+    info!(
+        "Canary traffic changed from {}% to {}%",
+        previous, request.traffic_percent
+    );
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(Some(user_id), "canary.traffic_updated", "deployment", "canary")
+                .with_client(client.ip, client.user_agent)
+                .with_metadata("previous_percent", previous.to_string())
+                .with_metadata("traffic_percent", request.traffic_percent.to_string()),
+        )
+        .await;
+
+    Ok(Json(serde_json::json!({
+        "traffic_percent": request.traffic_percent,
+        "previous_percent": previous,
+        "timestamp": chrono::Utc::now().to_rfc3339()
+    })))
+}
+
+/// Snapshot critical collections to object storage in the background (admin only)
+pub async fn create_backup(
+    State(app_state): State<Arc<AppState>>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+) -> Result<Json<BackupRun>> {
+    let run = app_state.backup_service.start_backup(user_id.clone()).await?;
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(Some(user_id), "backup.started", "backup", &run.id)
+                .with_client(client.ip, client.user_agent),
+        )
+        .await;
+
+    Ok(Json(run))
+}
+
+/// Recent backup and restore runs (admin only)
+pub async fn list_backups(
+    State(app_state): State<Arc<AppState>>,
+    AdminUser(_user_id): AdminUser,
+) -> Result<Json<Vec<BackupRun>>> {
+    Ok(Json(app_state.backup_service.list_runs(50).await?))
+}
+
+/// Status of a backup or restore run (admin only)
+pub async fn get_backup(
+    State(app_state): State<Arc<AppState>>,
+    Path(backup_id): Path<String>,
+    AdminUser(_user_id): AdminUser,
+) -> Result<Json<BackupRun>> {
+    let run = app_state
+        .backup_service
+        .find_run(&backup_id)
+        .await?
+        .ok_or_else(|| PeerPowerError::NotFound {
+            resource: format!("Backup with ID: {}", backup_id),
+        })?;
+
+    Ok(Json(run))
+}
+
+/// Diff a backup against live data and, when confirmed, restore it (admin only)
+pub async fn restore_backup(
+    State(app_state): State<Arc<AppState>>,
+    Path(backup_id): Path<String>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+    axum::Json(request): axum::Json<RestoreBackupRequest>,
+) -> Result<Json<RestoreBackupResponse>> {
+    let dry_run = request.dry_run.unwrap_or(true);
+    let diff = app_state
+        .backup_service
+        .diff(&backup_id, &request.collections)
+        .await?;
+
+    if dry_run {
+        return Ok(Json(RestoreBackupResponse {
+            dry_run,
+            diff,
+            restore_run: None,
+        }));
+    }
+
+    // Overwriting live data must be confirmed explicitly
+    if request.confirm.as_deref() != Some(backup_id.as_str()) {
+        return Err(PeerPowerError::ValidationError {
+            field: "confirm".to_string(),
+            message: "Set confirm to the backup id to run a restore".to_string(),
+        });
+    }
+
+    let run = app_state
+        .backup_service
+        .start_restore(&backup_id, &request.collections, user_id.clone())
+        .await?;
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(Some(user_id), "backup.restore_started", "backup", &backup_id)
+                .with_client(client.ip, client.user_agent)
+                .with_metadata("restore_run_id", run.id.clone())
+                .with_metadata(
+                    "collections",
+                    run.collections
+                        .iter()
+                        .map(|collection| collection.name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(","),
+                ),
+        )
+        .await;
+
+    Ok(Json(RestoreBackupResponse {
+        dry_run,
+        diff,
+        restore_run: Some(run),
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RoutingRuleRequest {
+    pub name: String,
+    pub condition: String,
+    pub action: RoutingAction,
+    #[serde(default = "default_rule_enabled")]
+    pub enabled: bool,
+}
+
+fn default_rule_enabled() -> bool {
+    true
+}
+
+impl RoutingRuleRequest {
+    fn validate(&self) -> Result<()> {
+        if self.name.trim().is_empty() {
+            return Err(PeerPowerError::ValidationError {
+                field: "name".to_string(),
+                message: "Rule name is required".to_string(),
+            });
+        }
+        parse_condition(&self.condition).map_err(|message| PeerPowerError::ValidationError {
+            field: "condition".to_string(),
+            message,
+        })?;
+        let empty_action = match &self.action {
+            RoutingAction::MinProviderTier { .. } => false,
+            RoutingAction::ProviderCarriers { carriers } => carriers.is_empty(),
+            RoutingAction::ExcludeProviders { provider_ids } => provider_ids.is_empty(),
+        };
+        if empty_action {
+            return Err(PeerPowerError::ValidationError {
+                field: "action".to_string(),
+                message: "Rule action needs at least one value".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// The update giving a stored rule this request's fields
+    fn update(&self) -> Result<mongodb::bson::Document> {
+        let action =
+            mongodb::bson::to_bson(&self.action).map_err(|e| PeerPowerError::Internal {
+                message: format!("Failed to serialize routing action: {}", e),
+            })?;
+        Ok(mongodb::bson::doc! {
+            "$set": {
+                "name": self.name.trim(),
+                "condition": self.condition.trim(),
+                "action": action,
+                "enabled": self.enabled,
+                "updated_at": stored_timestamp(chrono::Utc::now()),
+            }
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EvaluateRoutingRequest {
+    pub recipient: String,
+    pub priority: Option<MessagePriority>,
+    pub client_id: Option<String>,
+    pub client_tier: Option<ClientTier>, // overrides the client's stored tier
+    pub rules: Option<Vec<RoutingRuleRequest>>, // evaluate these instead of the saved rules
+}
+
+#[derive(Debug, Serialize)]
+pub struct EvaluateRoutingResponse {
+    pub recipient_carrier: String,
+    pub client_tier: ClientTier,
+    pub matched_rules: Vec<RoutingRule>,
+    pub online_providers: usize,
+    pub eligible_providers: usize, // online providers left after the matched rules
+}
+
+/// List routing rules (admin only)
+pub async fn list_routing_rules(
+    State(app_state): State<Arc<AppState>>,
+    AdminUser(_user_id): AdminUser,
+) -> Result<Json<Vec<RoutingRule>>> {
+    let find_options = mongodb::options::FindOptions::builder()
+        .sort(mongodb::bson::doc! {"created_at": 1})
+        .build();
+    let rules: Vec<RoutingRule> = app_state
+        .database
+        .collection::<RoutingRule>("routing_rules")
+        .find(mongodb::bson::doc! {}, find_options)
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch routing rules: {}", e),
+        })?
+        .try_collect()
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to read routing rules: {}", e),
+        })?;
+
+    Ok(Json(rules))
+}
+
+/// Create a routing rule (admin only)
+pub async fn create_routing_rule(
+    State(app_state): State<Arc<AppState>>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+    axum::Json(request): axum::Json<RoutingRuleRequest>,
+) -> Result<Json<RoutingRule>> {
+    request.validate()?;
+
+    let mut rule = RoutingRule::new(
+        request.name.trim().to_string(),
+        request.condition.trim().to_string(),
+        request.action,
+        user_id.clone(),
+    );
+    rule.enabled = request.enabled;
+
+    app_state
+        .database
+        .collection::<RoutingRule>("routing_rules")
+        .insert_one(&rule, None)
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to create routing rule: {}", e),
+        })?;
+    app_state.routing_rules.invalidate().await;
+
+    info!("Routing rule {} created: {}", rule.id, rule.condition);
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(
+                Some(user_id),
+                "routing_rule.created",
+                "routing_rule",
+                &rule.id,
+            )
+            .with_client(client.ip, client.user_agent)
+            .with_metadata("condition", rule.condition.clone()),
+        )
+        .await;
+
+    Ok(Json(rule))
+}
+
+/// Replace a routing rule's definition (admin only)
+pub async fn update_routing_rule(
+    State(app_state): State<Arc<AppState>>,
+    Path(rule_id): Path<String>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+    axum::Json(request): axum::Json<RoutingRuleRequest>,
+) -> Result<Json<RoutingRule>> {
+    request.validate()?;
+
+    let rule = app_state
+        .database
+        .collection::<RoutingRule>("routing_rules")
+        .find_one_and_update(
+            mongodb::bson::doc! {"id": &rule_id},
+            request.update()?,
+            mongodb::options::FindOneAndUpdateOptions::builder()
+                .return_document(mongodb::options::ReturnDocument::After)
+                .build(),
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to update routing rule: {}", e),
+        })?
+        .ok_or_else(|| PeerPowerError::NotFound {
+            resource: format!("Routing rule: {}", rule_id),
+        })?;
+    app_state.routing_rules.invalidate().await;
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(
+                Some(user_id),
+                "routing_rule.updated",
+                "routing_rule",
+                &rule.id,
+            )
+            .with_client(client.ip, client.user_agent)
+            .with_metadata("condition", rule.condition.clone())
+            .with_metadata("enabled", rule.enabled.to_string()),
+        )
+        .await;
+
+    Ok(Json(rule))
+}
+
+/// Delete a routing rule (admin only)
+pub async fn delete_routing_rule(
+    State(app_state): State<Arc<AppState>>,
+    Path(rule_id): Path<String>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+) -> Result<StatusCode> {
+    let result = app_state
+        .database
+        .collection::<RoutingRule>("routing_rules")
+        .delete_one(mongodb::bson::doc! {"id": &rule_id}, None)
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to delete routing rule: {}", e),
+        })?;
+
+    if result.deleted_count == 0 {
+        return Err(PeerPowerError::NotFound {
+            resource: format!("Routing rule: {}", rule_id),
+        });
+    }
+    app_state.routing_rules.invalidate().await;
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(
+                Some(user_id),
+                "routing_rule.deleted",
+                "routing_rule",
+                &rule_id,
+            )
+            .with_client(client.ip, client.user_agent),
+        )
+        .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Dry-run the routing rules against a hypothetical message (admin only)
+pub async fn evaluate_routing_rules(
+    State(app_state): State<Arc<AppState>>,
+    AdminUser(_user_id): AdminUser,
+    axum::Json(request): axum::Json<EvaluateRoutingRequest>,
+) -> Result<Json<EvaluateRoutingResponse>> {
+    let recipient = PhoneNumber::new(request.recipient)?;
+
+    let client_tier = match (request.client_tier, &request.client_id) {
+        (Some(tier), _) => tier,
+        (None, Some(client_id)) => app_state
+            .user_repository
+            .find_by_id(client_id)
+            .await?
+            .map(|user| user.tier)
+            .unwrap_or_default(),
+        (None, None) => ClientTier::default(),
+    };
+
+    let rules = match request.rules {
+        Some(candidates) => {
+            let mut compiled = Vec::with_capacity(candidates.len());
+            for candidate in candidates {
+                candidate.validate()?;
+                let rule = RoutingRule::new(
+                    candidate.name,
+                    candidate.condition,
+                    candidate.action,
+                    String::new(),
+                );
+                compiled.push(CompiledRoutingRule::compile(rule).map_err(|message| {
+                    PeerPowerError::ValidationError {
+                        field: "condition".to_string(),
+                        message,
+                    }
+                })?);
+            }
+            Arc::new(compiled)
+        }
+        None => app_state.routing_rules.active_rules().await?,
+    };
+
+    let context = RoutingContext {
+        carrier: Carrier::from_phone_number(&recipient),
+        recipient: recipient.as_str().to_string(),
+        priority: request.priority.unwrap_or(MessagePriority::Normal),
+        client_tier,
+    };
+    let decision = RoutingDecision::evaluate(&rules, &context);
+
+    let online: Vec<Provider> = app_state
+        .database
+        .collection::<Provider>("providers")
+        .find(
+            mongodb::bson::doc! {"status": format!("{:?}", ProviderStatus::Online)},
+            None,
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to query providers: {}", e),
+        })?
+        .try_collect()
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch providers: {}", e),
+        })?;
+    let eligible = online
+        .iter()
+        .filter(|provider| decision.allows(provider))
+        .count();
+
+    Ok(Json(EvaluateRoutingResponse {
+        recipient_carrier: format!("{:?}", context.carrier),
+        client_tier,
+        matched_rules: decision.matched,
+        online_providers: online.len(),
+        eligible_providers: eligible,
+    }))
+}
+
+/// Outcome of a bulk action: the impact, plus a confirmation token on dry runs
+#[derive(Debug, Serialize)]
+pub struct BulkActionResponse {
+    pub dry_run: bool,
+    pub impact: ImpactReport,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confirmation: Option<ActionConfirmation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BulkProviderStatusRequest {
+    pub provider_ids: Vec<String>,
+    pub status: String, // online, offline, busy, suspended
+    #[serde(default)]
+    pub dry_run: bool,
+    pub confirmation_token: Option<String>,
+}
+
+/// Most providers changed by one bulk status request
+const MAX_BULK_PROVIDERS: usize = 1000;
+
+/// Change the status of many providers at once (admin only).
+/// A dry run reports the in-flight messages, jobs and clients affected and
+/// returns the token required to execute the same change.
+pub async fn bulk_update_provider_status(
+    State(app_state): State<Arc<AppState>>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+    axum::Json(request): axum::Json<BulkProviderStatusRequest>,
+) -> Result<Json<BulkActionResponse>> {
+    if request.provider_ids.is_empty() || request.provider_ids.len() > MAX_BULK_PROVIDERS {
+        return Err(PeerPowerError::ValidationError {
+            field: "provider_ids".to_string(),
+            message: format!("Provide between 1 and {} provider IDs", MAX_BULK_PROVIDERS),
+        });
+    }
+    let status = match request.status.to_lowercase().as_str() {
+        "online" => ProviderStatus::Online,
+        "offline" => ProviderStatus::Offline,
+        "busy" => ProviderStatus::Busy,
+        "suspended" => ProviderStatus::Suspended,
+        _ => {
+            return Err(PeerPowerError::ValidationError {
+                field: "status".to_string(),
+                message: "Invalid status value. Must be one of: online, offline, busy, suspended"
+                    .to_string(),
+            })
+        }
+    };
+
+    let mut provider_ids = request.provider_ids.clone();
+    provider_ids.sort();
+    provider_ids.dedup();
+    let status_name = format!("{:?}", status);
+    let mut action = vec!["providers.status", status_name.as_str()];
+    action.extend(provider_ids.iter().map(String::as_str));
+    let fingerprint = ImpactAnalyzer::fingerprint(&user_id, &action);
+
+    let impact = app_state
+        .impact_analyzer
+        .provider_status_impact(&provider_ids)
+        .await?;
+
+    if request.dry_run {
+        let confirmation = app_state
+            .impact_analyzer
+            .issue_confirmation(&fingerprint)
+            .await?;
+        return Ok(Json(BulkActionResponse {
+            dry_run: true,
+            impact,
+            confirmation: Some(confirmation),
+            updated: None,
+        }));
+    }
+
+    app_state
+        .impact_analyzer
+        .consume_confirmation(request.confirmation_token.as_deref(), &fingerprint)
+        .await?;
+
+    let result = app_state
+        .database
+        .collection::<Provider>("providers")
+        .update_many(
+            mongodb::bson::doc! {"id": {"$in": &provider_ids}},
+            mongodb::bson::doc! {
+                "$set": {
+                    "status": &status_name,
+                    "updated_at": stored_timestamp(chrono::Utc::now()),
+                }
+            },
+            None,
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to update provider status: {}", e),
+        })?;
+
+    // Online providers are cached again on their next heartbeat
+    for provider_id in &provider_ids {
+        app_state.provider_cache.remove(provider_id).await?;
+    }
+
+    info!(
+        "Bulk status change to {} for {} providers",
+        status_name, result.modified_count
+    );
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(Some(user_id), "providers.bulk_status", "provider", "bulk")
+                .with_client(client.ip, client.user_agent)
+                .with_metadata("status", status_name.clone())
+                .with_metadata("provider_ids", provider_ids.join(","))
+                .with_metadata("queued_messages", impact.queued_messages.to_string()),
+        )
+        .await;
+
+    Ok(Json(BulkActionResponse {
+        dry_run: false,
+        impact,
+        confirmation: None,
+        updated: Some(result.modified_count),
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CarrierPauseRequest {
+    pub paused: bool,
+    #[serde(default)]
+    pub dry_run: bool,
+    pub confirmation_token: Option<String>,
+}
+
+/// Carriers whose messages are currently held back (admin only)
+pub async fn list_paused_carriers(
+    State(app_state): State<Arc<AppState>>,
+    AdminUser(_user_id): AdminUser,
+) -> Result<Json<serde_json::Value>> {
+    let paused = app_state.carrier_kill_switch.paused().await?;
+    Ok(Json(serde_json::json!({ "paused_carriers": paused })))
+}
+
+/// Pause or resume dispatch to a carrier's numbers (admin only).
+/// Requires a dry run first, which reports the queued messages held or released.
+pub async fn update_carrier_pause(
+    State(app_state): State<Arc<AppState>>,
+    Path(carrier): Path<String>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+    axum::Json(request): axum::Json<CarrierPauseRequest>,
+) -> Result<Json<BulkActionResponse>> {
+    let carrier = Carrier::from_name(&carrier).ok_or_else(|| PeerPowerError::ValidationError {
+        field: "carrier".to_string(),
+        message: "Unknown carrier. Must be one of: smart, metfone, cellcard, qb".to_string(),
+    })?;
+
+    let carrier_name = format!("{:?}", carrier);
+    let fingerprint = ImpactAnalyzer::fingerprint(
+        &user_id,
+        &[
+            "carrier.pause",
+            carrier_name.as_str(),
+            if request.paused { "pause" } else { "resume" },
+        ],
+    );
+    let impact = app_state.impact_analyzer.carrier_impact(&carrier).await?;
+
+    if request.dry_run {
+        let confirmation = app_state
+            .impact_analyzer
+            .issue_confirmation(&fingerprint)
+            .await?;
+        return Ok(Json(BulkActionResponse {
+            dry_run: true,
+            impact,
+            confirmation: Some(confirmation),
+            updated: None,
+        }));
+    }
+
+    app_state
+        .impact_analyzer
+        .consume_confirmation(request.confirmation_token.as_deref(), &fingerprint)
+        .await?;
+
+    if request.paused {
+        app_state.carrier_kill_switch.pause(&carrier).await?;
+    } else {
+        app_state.carrier_kill_switch.resume(&carrier).await?;
+    }
+
+    info!(
+        "Carrier {} {} ({} queued messages)",
+        carrier_name,
+        if request.paused { "paused" } else { "resumed" },
+        impact.queued_messages
+    );
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(
+                Some(user_id),
+                if request.paused {
+                    "carrier.paused"
+                } else {
+                    "carrier.resumed"
+                },
+                "carrier",
+                &carrier_name,
+            )
+            .with_client(client.ip, client.user_agent)
+            .with_metadata("queued_messages", impact.queued_messages.to_string()),
+        )
+        .await;
+
+    Ok(Json(BulkActionResponse {
+        dry_run: false,
+        impact,
+        confirmation: None,
+        updated: Some(1),
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CarrierRedetectionRequest {
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// Re-detect the recipient carrier of every queued message against the
+/// current prefix table, e.g. after new ranges or ported numbers (admin only)
+pub async fn redetect_recipient_carriers(
+    State(app_state): State<Arc<AppState>>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+    axum::Json(request): axum::Json<CarrierRedetectionRequest>,
+) -> Result<Json<CarrierRedetectionReport>> {
+    let report = app_state
+        .carrier_redetector
+        .redetect_queued(request.dry_run)
+        .await?;
+
+    if !request.dry_run {
+        app_state
+            .audit_logger
+            .record_best_effort(
+                AuditLogEntry::new(
+                    Some(user_id),
+                    "messages.carriers_redetected",
+                    "messages",
+                    "queued",
+                )
+                .with_client(client.ip, client.user_agent)
+                .with_metadata("scanned", report.scanned.to_string())
+                .with_metadata("corrected", report.corrected.to_string()),
+            )
+            .await;
+    }
+
+    Ok(Json(report))
+}
+
+/// Failed-verification count and lock state for a phone (admin only)
+pub async fn get_login_lockout(
+    State(app_state): State<Arc<AppState>>,
+    Path(phone): Path<String>,
+    AdminUser(_user_id): AdminUser,
+) -> Result<Json<LockoutStatus>> {
+    let phone = PhoneNumber::new(phone)?;
+    let status = app_state.login_lockout.status(phone.as_str()).await?;
+    Ok(Json(status))
+}
+
+/// Lift an OTP lockout early, e.g. after support has verified the owner (admin only)
+pub async fn unlock_login(
+    State(app_state): State<Arc<AppState>>,
+    Path(phone): Path<String>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+) -> Result<Json<LockoutStatus>> {
+    let phone = PhoneNumber::new(phone)?;
+    let previous = app_state.login_lockout.status(phone.as_str()).await?;
+    app_state.login_lockout.unlock(phone.as_str()).await?;
+
+    info!("OTP lockout lifted for {}", phone.as_str());
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(
+                Some(user_id),
+                "auth.lockout_lifted",
+                "phone",
+                phone.as_str(),
+            )
+            .with_client(client.ip, client.user_agent)
+            .with_metadata("was_locked", previous.locked_until.is_some().to_string())
+            .with_metadata("lock_level", previous.lock_level.to_string()),
+        )
+        .await;
+
+    let status = app_state.login_lockout.status(phone.as_str()).await?;
+    Ok(Json(status))
+}
+
+/// Clear a provider's device key so a replacement device can enroll (admin only).
+/// Until it does, the provider can't confirm deliveries.
+pub async fn reset_provider_device_key(
+    State(app_state): State<Arc<AppState>>,
+    Path(provider_id): Path<String>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+) -> Result<StatusCode> {
+    let providers_collection = app_state.database.collection::<Provider>("providers");
+    let provider = providers_collection
+        .find_one(mongodb::bson::doc! {"id": &provider_id}, None)
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch provider: {}", e),
+        })?
+        .ok_or_else(|| PeerPowerError::NotFound {
+            resource: format!("Provider with ID: {}", provider_id),
+        })?;
+
+    providers_collection
+        .update_one(
+            mongodb::bson::doc! {"id": &provider_id},
+            mongodb::bson::doc! {
+                "$set": {
+                    "device_key": null,
+                    "updated_at": stored_timestamp(chrono::Utc::now()),
+                }
+            },
+            None,
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to reset device key: {}", e),
+        })?;
+
+    info!("Device key reset for provider {}", provider_id);
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(
+                Some(user_id),
+                "provider.device_key_reset",
+                "provider",
+                &provider_id,
+            )
+            .with_client(client.ip, client.user_agent)
+            .with_metadata(
+                "fingerprint",
+                provider
+                    .device_key
+                    .map(|key| key.fingerprint)
+                    .unwrap_or_default(),
+            ),
+        )
+        .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Set a provider's daily message limit (admin only). Limits above the
+/// unattested cap need a passing Play Integrity verdict on the device.
+pub async fn update_provider_daily_limit(
+    State(app_state): State<Arc<AppState>>,
+    Path(provider_id): Path<String>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+    Json(request): Json<UpdateDailyLimitRequest>,
+) -> Result<Json<DailyLimitResponse>> {
+    let providers_collection = app_state.database.collection::<Provider>("providers");
+    let provider = providers_collection
+        .find_one(mongodb::bson::doc! {"id": &provider_id}, None)
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch provider: {}", e),
+        })?
+        .ok_or_else(|| PeerPowerError::NotFound {
+            resource: format!("Provider with ID: {}", provider_id),
+        })?;
+
+    let unattested_cap = app_state.play_integrity.unattested_max_daily_messages();
+    let attested = provider
+        .attestation
+        .as_ref()
+        .is_some_and(|attestation| attestation.passed);
+    if request.max_daily_messages > unattested_cap && !attested {
+        return Err(PeerPowerError::ValidationError {
+            field: "max_daily_messages".to_string(),
+            message: format!(
+                "Limits above {} need a device that passed Play Integrity attestation",
+                unattested_cap
+            ),
+        });
+    }
+
+    providers_collection
+        .update_one(
+            mongodb::bson::doc! {"id": &provider_id},
+            mongodb::bson::doc! {
+                "$set": {
+                    "max_daily_messages": request.max_daily_messages,
+                    "updated_at": stored_timestamp(chrono::Utc::now()),
+                }
+            },
+            None,
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to update daily limit: {}", e),
+        })?;
+
+    info!(
+        "Daily limit for provider {} set to {}",
+        provider_id, request.max_daily_messages
+    );
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(
+                Some(user_id),
+                "provider.daily_limit_updated",
+                "provider",
+                &provider_id,
+            )
+            .with_client(client.ip, client.user_agent)
+            .with_metadata("previous", provider.max_daily_messages.to_string())
+            .with_metadata("limit", request.max_daily_messages.to_string())
+            .with_metadata("attested", attested.to_string()),
+        )
+        .await;
+
+    Ok(Json(DailyLimitResponse {
+        provider_id,
+        max_daily_messages: request.max_daily_messages,
+        attested,
+    }))
+}
+
+/// Encrypt phone numbers stored before `PHONE_ENCRYPTION_KEY` was set (admin only)
+pub async fn encrypt_stored_phones(
+    State(app_state): State<Arc<AppState>>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+) -> Result<Json<PhoneBackfillReport>> {
+    let report = app_state.phone_backfill.run().await?;
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(
+                Some(user_id),
+                "privacy.phones_encrypted",
+                "database",
+                "phones",
+            )
+            .with_client(client.ip, client.user_agent)
+            .with_metadata("users", report.users.to_string())
+            .with_metadata("providers", report.providers.to_string())
+            .with_metadata("messages", report.messages.to_string())
+            .with_metadata("telegram_links", report.telegram_links.to_string()),
+        )
+        .await;
+
+    Ok(Json(report))
+}
+
+/// Mint a short-lived token to act as a user while reproducing a support
+/// issue (admin only). Requests made with it are audited under the admin.
+pub async fn impersonate_user(
+    State(app_state): State<Arc<AppState>>,
+    Path(target_user_id): Path<String>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+    Json(request): Json<ImpersonateUserRequest>,
+) -> Result<Json<ImpersonationToken>> {
+    let reason = request.reason.trim();
+    if reason.is_empty() {
+        return Err(PeerPowerError::ValidationError {
+            field: "reason".to_string(),
+            message: "A reason is required to impersonate a user".to_string(),
+        });
+    }
+
+    let user = app_state
+        .user_repository
+        .find_by_id(&target_user_id)
+        .await?
+        .ok_or_else(|| PeerPowerError::NotFound {
+            resource: format!("User with ID: {}", target_user_id),
+        })?;
+    let audience = request
+        .app
+        .unwrap_or_else(|| TokenAudience::default_for(user.is_provider));
+    if audience == TokenAudience::Admin {
+        return Err(PeerPowerError::ValidationError {
+            field: "app".to_string(),
+            message: "Admin console access can't be impersonated".to_string(),
+        });
+    }
+
+    let token = app_state
+        .auth_service
+        .issue_impersonation_token(&user, &user_id, audience)
+        .await?;
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(
+                Some(user_id),
+                "support.impersonation_started",
+                "user",
+                &user.id,
+            )
+            .with_client(client.ip, client.user_agent)
+            .with_metadata("reason", reason)
+            .with_metadata("app", audience.as_str())
+            .with_metadata("expires_in", token.expires_in.to_string()),
+        )
+        .await;
+
+    Ok(Json(token))
+}
+
+/// Soft-delete a user (admin only): they can no longer sign in, their
+/// tokens and sessions are revoked, and retention purges the account once
+/// its window has passed unless it is restored first
+pub async fn delete_user(
+    State(app_state): State<Arc<AppState>>,
+    Path(target_user_id): Path<String>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+) -> Result<StatusCode> {
+    app_state.user_repository.delete(&target_user_id).await?;
+    let revoked_refresh_tokens = app_state
+        .auth_service
+        .revoke_all_tokens(&target_user_id)
+        .await?;
+    app_state.sessions.end_all(&target_user_id).await?;
+
+    info!("User {} deleted", target_user_id);
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(Some(user_id), "user.deleted", "user", &target_user_id)
+                .with_client(client.ip, client.user_agent)
+                .with_metadata(
+                    "revoked_refresh_tokens",
+                    revoked_refresh_tokens.to_string(),
+                ),
+        )
+        .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Restore a soft-deleted user before retention purges it (admin only).
+/// They sign in again with a new OTP.
+pub async fn restore_user(
+    State(app_state): State<Arc<AppState>>,
+    Path(target_user_id): Path<String>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+) -> Result<StatusCode> {
+    app_state.user_repository.restore(&target_user_id).await?;
+
+    info!("User {} restored", target_user_id);
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(Some(user_id), "user.restored", "user", &target_user_id)
+                .with_client(client.ip, client.user_agent),
+        )
+        .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Soft-delete a provider (admin only). It goes offline, stops receiving
+/// jobs and is purged by retention unless restored first.
+pub async fn delete_provider(
+    State(app_state): State<Arc<AppState>>,
+    Path(provider_id): Path<String>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+) -> Result<StatusCode> {
+    set_provider_deleted(&app_state, &provider_id, true).await?;
+    app_state.provider_cache.remove(&provider_id).await?;
+
+    info!("Provider {} deleted", provider_id);
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(Some(user_id), "provider.deleted", "provider", &provider_id)
+                .with_client(client.ip, client.user_agent),
+        )
+        .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Restore a soft-deleted provider (admin only). It stays offline until
+/// its next heartbeat.
+pub async fn restore_provider(
+    State(app_state): State<Arc<AppState>>,
+    Path(provider_id): Path<String>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+) -> Result<StatusCode> {
+    set_provider_deleted(&app_state, &provider_id, false).await?;
+
+    info!("Provider {} restored", provider_id);
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(Some(user_id), "provider.restored", "provider", &provider_id)
+                .with_client(client.ip, client.user_agent),
+        )
+        .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Mark a provider deleted (taking it offline), or restore a deleted one
+async fn set_provider_deleted(
+    app_state: &AppState,
+    provider_id: &str,
+    deleted: bool,
+) -> Result<()> {
+    let now = stored_timestamp(crate::shared::utils::now());
+    let (filter, update) = if deleted {
+        (
+            mongodb::bson::doc! {"id": provider_id, "deleted_at": null},
+            mongodb::bson::doc! {"$set": {
+                "deleted_at": &now,
+                "status": format!("{:?}", ProviderStatus::Offline),
+                "updated_at": &now,
+            }},
+        )
+    } else {
+        (
+            mongodb::bson::doc! {"id": provider_id, "deleted_at": {"$ne": null}},
+            mongodb::bson::doc! {"$set": {"deleted_at": null, "updated_at": &now}},
+        )
+    };
+
+    let result = app_state
+        .database
+        .collection::<Provider>("providers")
+        .update_one(filter, update, None)
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to update provider: {}", e),
+        })?;
+
+    if result.matched_count == 0 {
+        return Err(PeerPowerError::NotFound {
+            resource: format!("Provider with ID: {}", provider_id),
+        });
+    }
+    Ok(())
+}
+
+/// Soft-delete a finished message (admin only). Messages still in flight
+/// have to be cancelled first.
+pub async fn delete_message(
+    State(app_state): State<Arc<AppState>>,
+    Path(message_id): Path<String>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+) -> Result<StatusCode> {
+    let message = app_state
+        .message_repository
+        .find_by_id(&message_id)
+        .await?
+        .ok_or_else(|| PeerPowerError::NotFound {
+            resource: format!("Message with ID: {}", message_id),
+        })?;
+    if !matches!(
+        message.status,
+        MessageStatus::Delivered | MessageStatus::Failed | MessageStatus::Cancelled
+    ) {
+        return Err(PeerPowerError::ValidationError {
+            field: "status".to_string(),
+            message: format!(
+                "Only finished messages can be deleted; this one is {:?}",
+                message.status
+            ),
+        });
+    }
+
+    app_state.message_repository.delete(&message_id).await?;
+
+    info!("Message {} deleted", message_id);
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(Some(user_id), "message.deleted", "message", &message_id)
+                .with_client(client.ip, client.user_agent)
+                .with_metadata("client_id", message.client_id),
+        )
+        .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Restore a soft-deleted message before retention purges it (admin only)
+pub async fn restore_message(
+    State(app_state): State<Arc<AppState>>,
+    Path(message_id): Path<String>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+) -> Result<StatusCode> {
+    app_state.message_repository.restore(&message_id).await?;
+
+    info!("Message {} restored", message_id);
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(Some(user_id), "message.restored", "message", &message_id)
+                .with_client(client.ip, client.user_agent),
+        )
+        .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// The update storing how `discrepancy` was resolved
+fn discrepancy_resolution(discrepancy: &SettlementDiscrepancy) -> mongodb::bson::Document {
+    mongodb::bson::doc! {
+        "$set": {
+            "status": format!("{:?}", discrepancy.status),
+            "resolution_note": discrepancy.resolution_note.clone(),
+            "resolved_by": discrepancy.resolved_by.clone(),
+            "resolved_at": discrepancy.resolved_at.map(stored_timestamp),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entities::DiscrepancyKind;
+    use crate::shared::test_support::read_back;
+
+    #[test]
+    fn test_resolved_discrepancy_reads_back() {
+        let discrepancy = SettlementDiscrepancy::new(
+            "report-1".to_string(),
+            DiscrepancyKind::AmountMismatch,
+            "Settled 9.50 for a 10.00 payout".to_string(),
+        );
+        let mut resolved = discrepancy.clone();
+        resolved.resolve(
+            "admin-1".to_string(),
+            "Fee deducted by the bank".to_string(),
+        );
+
+        let stored = read_back(&discrepancy, &discrepancy_resolution(&resolved));
+        assert_eq!(stored.status, resolved.status);
+        assert_eq!(stored.resolved_at, resolved.resolved_at);
+    }
+
+    #[test]
+    fn test_updated_routing_rule_reads_back() {
+        let rule = RoutingRule::new(
+            "Smart only".to_string(),
+            "carrier == smart".to_string(),
+            RoutingAction::ProviderCarriers {
+                carriers: vec![Carrier::Smart],
+            },
+            "admin-1".to_string(),
+        );
+        let request = RoutingRuleRequest {
+            name: " Smart and Metfone ".to_string(),
+            condition: "carrier == smart".to_string(),
+            action: RoutingAction::ProviderCarriers {
+                carriers: vec![Carrier::Smart, Carrier::Metfone],
+            },
+            enabled: false,
+        };
+
+        let updated = read_back(&rule, &request.update().unwrap());
+        assert_eq!(updated.name, "Smart and Metfone");
+        assert!(!updated.enabled);
+        assert!(updated.updated_at >= rule.updated_at);
+    }
+}
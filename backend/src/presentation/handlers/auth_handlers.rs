@@ -1,17 +1,29 @@
-use axum::{extract::State, http::StatusCode, response::Json, Json as JsonExtractor};
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Json, Response},
+    Extension, Form, Json as JsonExtractor,
+};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tracing::{info, warn};
 use validator::Validate;
 
-use crate::domain::services::AuthService;
+use crate::domain::entities::{ApiClient, AuditLogEntry, AuthEvent, AuthEventKind};
+use crate::domain::services::{AuthService, OtpChannelKind, TokenAudience, TokenClaims};
+use crate::infrastructure::otp_challenge::{ChallengeSolution, OtpChallenge};
+use crate::presentation::middleware::ClientInfo;
 use crate::shared::types::PhoneNumber;
+use crate::shared::utils::stored_timestamp;
 use crate::shared::{AppState, PeerPowerError, Result};
 
 #[derive(Debug, Deserialize, Validate)]
 pub struct SendOtpRequest {
     #[validate(length(min = 10, max = 15, message = "Invalid phone number length"))]
     pub phone: String,
+    #[serde(default)]
+    pub channel: OtpChannelKind, // falls back to SMS, then a voice call
+    pub challenge: Option<ChallengeSolution>, // required from untrusted IPs; see `issue_otp_challenge`
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -20,12 +32,17 @@ pub struct VerifyOtpRequest {
     pub phone: String,
     #[validate(length(equal = 6, message = "OTP must be 6 digits"))]
     pub otp: String,
+    pub app: Option<TokenAudience>, // app the tokens are for; see `TokenAudience::default_for`
+    pub referral_code: Option<String>, // only applied when this creates the account
 }
 
 #[derive(Debug, Serialize)]
 pub struct SendOtpResponse {
     pub message: String,
     pub expires_in_minutes: i64,
+    pub expires_in_seconds: i64,
+    pub resend_cooldown_seconds: i64,
+    pub channel: OtpChannelKind,
 }
 
 #[derive(Debug, Serialize)]
@@ -45,31 +62,90 @@ pub struct UserInfo {
     pub is_verified: bool,
 }
 
+/// Proof-of-work challenge to solve before requesting an OTP from an untrusted IP
+pub async fn issue_otp_challenge(
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<OtpChallenge>> {
+    let challenge = app_state.otp_challenger.issue().await?;
+    Ok(Json(challenge))
+}
+
 /// Send OTP to phone number
 pub async fn send_otp(
     State(app_state): State<Arc<AppState>>,
+    client: ClientInfo,
     JsonExtractor(request): JsonExtractor<SendOtpRequest>,
 ) -> Result<Json<SendOtpResponse>> {
     // Validate request
     request.validate()?;
 
+    // Every OTP costs SMS quota, so unknown IPs pay for it with some work first
+    app_state
+        .otp_challenger
+        .require(client.trusted_ip.as_deref(), request.challenge.as_ref())
+        .await?;
+
     // Parse phone number
     let phone = PhoneNumber::new(request.phone)?;
 
     info!("OTP request for phone: {}", phone.as_str());
 
     // Send OTP
-    app_state.auth_service.send_otp(&phone).await?;
+    let dispatch = app_state
+        .auth_service
+        .send_otp(&phone, request.channel)
+        .await?;
+
+    let message = if dispatch.coalesced {
+        "OTP already sent".to_string()
+    } else {
+        "OTP sent successfully".to_string()
+    };
 
     Ok(Json(SendOtpResponse {
-        message: "OTP sent successfully".to_string(),
-        expires_in_minutes: 5, // TODO: Get from config
+        message,
+        expires_in_minutes: app_state.config.auth.otp_expiration_minutes,
+        expires_in_seconds: dispatch.expires_in_seconds(),
+        resend_cooldown_seconds: dispatch.resend_cooldown_seconds(),
+        channel: dispatch.channel,
+    }))
+}
+
+/// Re-send the current OTP once the resend cooldown has elapsed
+pub async fn resend_otp(
+    State(app_state): State<Arc<AppState>>,
+    client: ClientInfo,
+    JsonExtractor(request): JsonExtractor<SendOtpRequest>,
+) -> Result<Json<SendOtpResponse>> {
+    request.validate()?;
+
+    app_state
+        .otp_challenger
+        .require(client.trusted_ip.as_deref(), request.challenge.as_ref())
+        .await?;
+
+    let phone = PhoneNumber::new(request.phone)?;
+
+    info!("OTP resend request for phone: {}", phone.as_str());
+
+    let dispatch = app_state
+        .auth_service
+        .resend_otp(&phone, request.channel)
+        .await?;
+
+    Ok(Json(SendOtpResponse {
+        message: "OTP resent successfully".to_string(),
+        expires_in_minutes: app_state.config.auth.otp_expiration_minutes,
+        expires_in_seconds: dispatch.expires_in_seconds(),
+        resend_cooldown_seconds: dispatch.resend_cooldown_seconds(),
+        channel: dispatch.channel,
     }))
 }
 
 /// Verify OTP and authenticate user
 pub async fn verify_otp(
     State(app_state): State<Arc<AppState>>,
+    client: ClientInfo,
     JsonExtractor(request): JsonExtractor<VerifyOtpRequest>,
 ) -> Result<Json<AuthResponse>> {
     // Validate request
@@ -80,11 +156,127 @@ pub async fn verify_otp(
 
     info!("OTP verification for phone: {}", phone.as_str());
 
+    // Locked phones and IPs are turned away before the code is even checked
+    app_state
+        .login_lockout
+        .check(phone.as_str(), client.trusted_ip.as_deref())
+        .await?;
+
     // Verify OTP and get tokens
-    let auth_token = app_state
+    let auth_token = match app_state
         .auth_service
-        .verify_otp(&phone, &request.otp)
+        .verify_otp(&phone, &request.otp, request.app)
+        .await
+    {
+        Ok(auth_token) => auth_token,
+        Err(e) => {
+            app_state
+                .sessions
+                .record_event(
+                    AuthEvent::new(AuthEventKind::OtpFailed, None, None)
+                        .with_client(client.ip.clone(), client.user_agent.clone())
+                        .with_phone(&phone)
+                        .failed(e.to_string()),
+                )
+                .await;
+
+            // Only wrong or missing codes count toward a lockout, not outages
+            if matches!(e, PeerPowerError::AuthenticationFailed { .. }) {
+                let locked_for = app_state
+                    .login_lockout
+                    .record_failure(phone.as_str(), client.trusted_ip.as_deref())
+                    .await?;
+                if let Some(retry_after_seconds) = locked_for {
+                    app_state
+                        .sessions
+                        .record_event(
+                            AuthEvent::new(AuthEventKind::AccountLocked, None, None)
+                                .with_client(client.ip, client.user_agent)
+                                .with_phone(&phone)
+                                .failed(format!("locked for {}s", retry_after_seconds)),
+                        )
+                        .await;
+                    return Err(PeerPowerError::AccountLocked {
+                        retry_after_seconds,
+                    });
+                }
+            }
+            return Err(e);
+        }
+    };
+
+    app_state
+        .login_lockout
+        .record_success(phone.as_str())
         .await?;
+    app_state.otp_challenger.trust(client.trusted_ip.as_deref()).await?;
+
+    app_state
+        .sessions
+        .touch(
+            &auth_token.session_id,
+            &auth_token.user_id,
+            client.ip.clone(),
+            client.user_agent.clone(),
+        )
+        .await?;
+    app_state
+        .sessions
+        .record_event(
+            AuthEvent::new(
+                AuthEventKind::OtpVerified,
+                Some(auth_token.user_id.clone()),
+                Some(auth_token.session_id.clone()),
+            )
+            .with_client(client.ip.clone(), client.user_agent.clone())
+            .with_phone(&phone),
+        )
+        .await;
+
+    // Referral codes count at signup only; a bad one shouldn't block the login
+    if let Some(code) = request
+        .referral_code
+        .as_deref()
+        .filter(|_| auth_token.new_user)
+    {
+        match app_state
+            .referrals
+            .attribute(code, &auth_token.user_id)
+            .await
+        {
+            Ok(referral) => {
+                app_state
+                    .audit_logger
+                    .record_best_effort(
+                        AuditLogEntry::new(
+                            Some(auth_token.user_id.clone()),
+                            "referral.attributed",
+                            "referral",
+                            &referral.id,
+                        )
+                        .with_client(client.ip, client.user_agent)
+                        .with_metadata("referrer_id", referral.referrer_id),
+                    )
+                    .await;
+            }
+            Err(e) => warn!(
+                "Referral code {} not applied for {}: {}",
+                code, auth_token.user_id, e
+            ),
+        }
+    }
+
+    // The DID and phone credential can be issued again at the next login
+    if let Err(e) = app_state
+        .identity
+        .issue_on_verification(&auth_token.user_id)
+        .await
+    {
+        warn!(
+            "Phone credential not issued for {}: {}",
+            auth_token.user_id, e
+        );
+    }
 
     // TODO: Get user info from token claims or user repository
     let user_info = UserInfo {
@@ -111,15 +303,51 @@ pub struct RefreshTokenRequest {
 /// Refresh access token
 pub async fn refresh_token(
     State(app_state): State<Arc<AppState>>,
+    client: ClientInfo,
     JsonExtractor(request): JsonExtractor<RefreshTokenRequest>,
 ) -> Result<Json<AuthResponse>> {
     info!("Token refresh request");
 
     // Refresh token
-    let auth_token = app_state
+    let auth_token = match app_state
         .auth_service
         .refresh_token(&request.refresh_token)
+        .await
+    {
+        Ok(auth_token) => auth_token,
+        Err(e) => {
+            app_state
+                .sessions
+                .record_event(
+                    AuthEvent::new(AuthEventKind::RefreshFailed, None, None)
+                        .with_client(client.ip, client.user_agent)
+                        .failed(e.to_string()),
+                )
+                .await;
+            return Err(e);
+        }
+    };
+
+    app_state
+        .sessions
+        .touch(
+            &auth_token.session_id,
+            &auth_token.user_id,
+            client.ip.clone(),
+            client.user_agent.clone(),
+        )
         .await?;
+    app_state
+        .sessions
+        .record_event(
+            AuthEvent::new(
+                AuthEventKind::TokenRefreshed,
+                Some(auth_token.user_id.clone()),
+                Some(auth_token.session_id.clone()),
+            )
+            .with_client(client.ip, client.user_agent),
+        )
+        .await;
 
     // TODO: Get user info
     let user_info = UserInfo {
@@ -141,14 +369,367 @@ pub async fn refresh_token(
 /// Logout user (revoke tokens)
 pub async fn logout(
     State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    client: ClientInfo,
     JsonExtractor(request): JsonExtractor<RefreshTokenRequest>,
 ) -> Result<StatusCode> {
     info!("Logout request");
 
-    app_state
+    let session_id = app_state
         .auth_service
         .revoke_token(&request.refresh_token)
         .await?;
+    let session = match &session_id {
+        Some(session_id) => app_state.sessions.end(session_id).await?,
+        None => None,
+    };
+    app_state
+        .sessions
+        .record_event(
+            AuthEvent::new(
+                AuthEventKind::Logout,
+                session.map(|session| session.user_id),
+                session_id,
+            )
+            .with_client(client.ip, client.user_agent),
+        )
+        .await;
+
+    // Also kill the presented access token so it can't be used until expiry
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if let Some(access_token) = bearer {
+        if let Ok(claims) = app_state.auth_service.validate_token(access_token).await {
+            if !claims.jti.is_empty() {
+                app_state.auth_service.revoke_access_token(&claims).await?;
+            }
+        }
+    }
 
     Ok(StatusCode::NO_CONTENT)
 }
+
+#[derive(Debug, Serialize)]
+pub struct LogoutAllResponse {
+    pub message: String,
+    pub revoked_refresh_tokens: usize,
+}
+
+/// Logout everywhere: revoke every outstanding access and refresh token
+/// issued to the caller, including machine-client tokens (protected route)
+pub async fn logout_all(
+    Extension(claims): Extension<TokenClaims>,
+    State(app_state): State<Arc<AppState>>,
+    client: ClientInfo,
+) -> Result<Json<LogoutAllResponse>> {
+    info!("Logout-everywhere request for user: {}", claims.sub);
+
+    let revoked_refresh_tokens = app_state
+        .auth_service
+        .revoke_all_tokens(&claims.sub)
+        .await?;
+    app_state.sessions.end_all(&claims.sub).await?;
+    app_state
+        .sessions
+        .record_event(
+            AuthEvent::new(
+                AuthEventKind::LogoutAll,
+                Some(claims.sub.clone()),
+                claims.sid.clone(),
+            )
+            .with_client(client.ip.clone(), client.user_agent.clone()),
+        )
+        .await;
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(
+                Some(claims.sub.clone()),
+                "auth.logout_all",
+                "user",
+                &claims.sub,
+            )
+            .with_client(client.ip, client.user_agent)
+            .with_metadata("revoked_refresh_tokens", revoked_refresh_tokens.to_string()),
+        )
+        .await;
+
+    Ok(Json(LogoutAllResponse {
+        message: "All sessions have been revoked".to_string(),
+        revoked_refresh_tokens,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TokenRequest {
+    pub grant_type: String,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    pub scope: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: i64,
+    pub scope: String,
+}
+
+/// OAuth2 error response (RFC 6749 section 5.2)
+#[derive(Debug)]
+pub struct OAuthError {
+    status: StatusCode,
+    error: &'static str,
+    description: String,
+}
+
+impl OAuthError {
+    fn new(status: StatusCode, error: &'static str, description: impl Into<String>) -> Self {
+        Self {
+            status,
+            error,
+            description: description.into(),
+        }
+    }
+
+    fn invalid_client() -> Self {
+        Self::new(
+            StatusCode::UNAUTHORIZED,
+            "invalid_client",
+            "Client authentication failed",
+        )
+    }
+}
+
+impl From<PeerPowerError> for OAuthError {
+    fn from(error: PeerPowerError) -> Self {
+        warn!("Token request failed: {}", error);
+        Self::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "server_error",
+            "Unable to issue token",
+        )
+    }
+}
+
+impl IntoResponse for OAuthError {
+    fn into_response(self) -> Response {
+        let mut response = (
+            self.status,
+            Json(serde_json::json!({
+                "error": self.error,
+                "error_description": self.description,
+            })),
+        )
+            .into_response();
+        response
+            .headers_mut()
+            .insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+        if self.status == StatusCode::UNAUTHORIZED {
+            response.headers_mut().insert(
+                header::WWW_AUTHENTICATE,
+                HeaderValue::from_static("Basic realm=\"peerpower\""),
+            );
+        }
+        response
+    }
+}
+
+/// OAuth2 token endpoint for machine clients (client_credentials grant).
+///
+/// Client credentials may be sent with HTTP Basic auth or in the form body.
+pub async fn token(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Form(request): Form<TokenRequest>,
+) -> std::result::Result<Response, OAuthError> {
+    if request.grant_type != "client_credentials" {
+        return Err(OAuthError::new(
+            StatusCode::BAD_REQUEST,
+            "unsupported_grant_type",
+            "Only the client_credentials grant is supported",
+        ));
+    }
+
+    let (client_id, client_secret) = match basic_credentials(&headers) {
+        Some(credentials) => credentials,
+        None => match (request.client_id, request.client_secret) {
+            (Some(client_id), Some(client_secret)) => (client_id, client_secret),
+            _ => return Err(OAuthError::invalid_client()),
+        },
+    };
+
+    let clients = app_state.database.collection::<ApiClient>("api_clients");
+    let client = clients
+        .find_one(mongodb::bson::doc! {"client_id": &client_id}, None)
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch API client: {}", e),
+        })?
+        .filter(|client| !client.is_revoked())
+        .ok_or_else(OAuthError::invalid_client)?;
+
+    if !crate::shared::utils::verify_password(&client_secret, &client.secret_hash)? {
+        warn!("Invalid secret presented for API client {}", client_id);
+        return Err(OAuthError::invalid_client());
+    }
+
+    let scopes = client
+        .grant_scopes(request.scope.as_deref())
+        .ok_or_else(|| {
+            OAuthError::new(
+                StatusCode::BAD_REQUEST,
+                "invalid_scope",
+                "Requested scope exceeds the scopes granted to this client",
+            )
+        })?;
+
+    let issued = app_state
+        .auth_service
+        .issue_client_token(&client, &scopes)
+        .await?;
+
+    if let Err(e) = clients
+        .update_one(
+            mongodb::bson::doc! {"client_id": &client.client_id},
+            mongodb::bson::doc! {"$set": {"last_used_at": stored_timestamp(chrono::Utc::now())}},
+            None,
+        )
+        .await
+    {
+        warn!("Failed to record API client use for {}: {}", client_id, e);
+    }
+
+    let mut response = Json(TokenResponse {
+        access_token: issued.access_token,
+        token_type: issued.token_type,
+        expires_in: issued.expires_in,
+        scope: issued.scope,
+    })
+    .into_response();
+    response
+        .headers_mut()
+        .insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    Ok(response)
+}
+
+/// `client_id:client_secret` from an HTTP Basic `Authorization` header
+fn basic_credentials(headers: &HeaderMap) -> Option<(String, String)> {
+    use base64::Engine;
+
+    let encoded = headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Basic ")?;
+    let decoded = base64::engine::general_purpose::STANDARD
+        .decode(encoded.trim())
+        .ok()?;
+    let decoded = String::from_utf8(decoded).ok()?;
+    let (client_id, client_secret) = decoded.split_once(':')?;
+    Some((client_id.to_string(), client_secret.to_string()))
+}
+
+/// Public keys for verifying access tokens (`/.well-known/jwks.json`)
+pub async fn jwks(State(app_state): State<Arc<AppState>>) -> Response {
+    let mut response = Json(app_state.jwt_keys.jwks().clone()).into_response();
+    response.headers_mut().insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static("public, max-age=300"),
+    );
+    response
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TelegramUpdate {
+    pub message: Option<TelegramMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TelegramMessage {
+    pub chat: TelegramChat,
+    pub from: Option<TelegramUser>,
+    pub contact: Option<TelegramContact>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TelegramChat {
+    pub id: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TelegramUser {
+    pub id: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TelegramContact {
+    pub phone_number: String,
+    pub user_id: Option<i64>,
+}
+
+/// Telegram bot webhook: links a chat to a phone number once the user
+/// shares their own contact, so OTPs can be sent there
+pub async fn telegram_webhook(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    JsonExtractor(update): JsonExtractor<TelegramUpdate>,
+) -> Result<Json<serde_json::Value>> {
+    let secret = headers
+        .get("x-telegram-bot-api-secret-token")
+        .and_then(|value| value.to_str().ok());
+    if !app_state.telegram.verify_webhook_secret(secret) {
+        return Err(PeerPowerError::AuthenticationFailed {
+            reason: "Invalid Telegram webhook secret".to_string(),
+        });
+    }
+
+    let Some(message) = update.message else {
+        return Ok(Json(serde_json::json!({"ok": true})));
+    };
+    let chat_id = message.chat.id;
+    let sender_id = message.from.map(|user| user.id);
+
+    let reply = match message.contact {
+        // Only a user's own contact proves they own the number
+        Some(contact) if contact.user_id.is_some() && contact.user_id == sender_id => {
+            match PhoneNumber::new(contact.phone_number) {
+                Ok(phone) => {
+                    app_state
+                        .telegram
+                        .link(&phone, chat_id, sender_id.unwrap_or_default())
+                        .await?;
+                    format!(
+                        "Linked {}. Choose Telegram when signing in to PeerPower to get your codes here.",
+                        phone.as_str()
+                    )
+                }
+                Err(_) => "Only Cambodian phone numbers are supported.".to_string(),
+            }
+        }
+        Some(_) => "Please share your own contact, not someone else's.".to_string(),
+        None => {
+            let keyboard = serde_json::json!({
+                "keyboard": [[{"text": "Share my phone number", "request_contact": true}]],
+                "one_time_keyboard": true,
+                "resize_keyboard": true
+            });
+            app_state
+                .telegram
+                .send_text(
+                    chat_id,
+                    "Share your phone number to receive PeerPower sign-in codes here.",
+                    Some(keyboard),
+                )
+                .await?;
+            return Ok(Json(serde_json::json!({"ok": true})));
+        }
+    };
+
+    app_state.telegram.send_text(chat_id, &reply, None).await?;
+    Ok(Json(serde_json::json!({"ok": true})))
+}
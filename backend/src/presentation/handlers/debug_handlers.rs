@@ -0,0 +1,72 @@
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    response::Json,
+};
+use serde::Serialize;
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::shared::types::PhoneNumber;
+use crate::shared::utils::{hmac_sha256_hex, verify_hmac_sha256_hex};
+use crate::shared::{AppState, PeerPowerError, Result};
+
+pub const DEBUG_TOKEN_HEADER: &str = "x-peerpower-debug-token";
+
+#[derive(Debug, Serialize)]
+pub struct PendingOtpResponse {
+    pub phone: String,
+    pub otp: String,
+    pub expires_at: String,
+}
+
+/// Outstanding login code for a phone, so the staging smoke test can sign in
+/// without a real handset. Only served outside production when
+/// `DEBUG_OTP_TOKEN` is set; otherwise the route reads as not found.
+pub async fn get_pending_otp(
+    State(app_state): State<Arc<AppState>>,
+    Path(phone): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<PendingOtpResponse>> {
+    let not_found = || PeerPowerError::NotFound {
+        resource: "Route".to_string(),
+    };
+
+    let Some(expected) = app_state.config.auth.debug_otp_token.as_deref() else {
+        return Err(not_found());
+    };
+    if app_state.config.is_production() {
+        return Err(not_found());
+    }
+
+    // Compare through an HMAC so the check takes the same time for any token
+    let provided = headers
+        .get(DEBUG_TOKEN_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    if !verify_hmac_sha256_hex(
+        provided,
+        phone.as_bytes(),
+        &hmac_sha256_hex(expected, phone.as_bytes()),
+    ) {
+        warn!("Rejected OTP debug hook call with an invalid token");
+        return Err(PeerPowerError::AuthenticationFailed {
+            reason: "Invalid debug token".to_string(),
+        });
+    }
+
+    let phone = PhoneNumber::new(phone)?;
+    let (otp, expires_at) = app_state
+        .auth_service
+        .pending_otp(&phone)
+        .await?
+        .ok_or_else(|| PeerPowerError::NotFound {
+            resource: format!("Pending OTP for {}", phone.masked().as_str()),
+        })?;
+
+    Ok(Json(PendingOtpResponse {
+        phone: phone.as_str().to_string(),
+        otp,
+        expires_at: expires_at.to_rfc3339(),
+    }))
+}
@@ -0,0 +1,191 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::Json,
+    Json as JsonExtractor,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use validator::Validate;
+
+use crate::domain::entities::{AuditLogEntry, Dispute, DisputeStatus};
+use crate::presentation::middleware::{AdminUser, ClientInfo, ClientUser};
+use crate::shared::{AppState, PeerPowerError, Result};
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct DisputeDeliveryRequest {
+    #[validate(length(min = 1, max = 500, message = "Reason must be 1-500 characters"))]
+    pub reason: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DisputeListQuery {
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AdminDisputeQuery {
+    pub status: Option<DisputeStatus>, // defaults to Open, i.e. the review queue
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DisputeDecision {
+    Uphold, // refund the client, claw back the provider's earnings
+    Reject,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResolveDisputeRequest {
+    pub decision: DisputeDecision,
+    pub note: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DisputeResponse {
+    pub id: String,
+    pub message_id: String,
+    pub provider_id: Option<String>,
+    pub reason: String,
+    pub status: String,
+    pub refund_amount: f64, // PPT
+    pub clawback_amount: f64,
+    pub resolution_note: Option<String>,
+    pub disputed_at: String,
+    pub resolved_at: Option<String>,
+}
+
+impl From<&Dispute> for DisputeResponse {
+    fn from(dispute: &Dispute) -> Self {
+        Self {
+            id: dispute.id.clone(),
+            message_id: dispute.message_id.clone(),
+            provider_id: dispute.provider_id.clone(),
+            reason: dispute.reason.clone(),
+            status: format!("{:?}", dispute.status),
+            refund_amount: dispute.refund_amount.to_ppt(),
+            clawback_amount: dispute.clawback_amount.to_ppt(),
+            resolution_note: dispute.resolution_note.clone(),
+            disputed_at: dispute.created_at.to_rfc3339(),
+            resolved_at: dispute.resolved_at.map(|at| at.to_rfc3339()),
+        }
+    }
+}
+
+/// Report that a message confirmed as delivered never arrived. Disputes
+/// count against the provider's quality bonus for the month unless an
+/// admin rejects them.
+pub async fn dispute_delivery(
+    State(app_state): State<Arc<AppState>>,
+    Path(message_id): Path<String>,
+    ClientUser(user_id): ClientUser,
+    client: ClientInfo,
+    JsonExtractor(request): JsonExtractor<DisputeDeliveryRequest>,
+) -> Result<Json<DisputeResponse>> {
+    request.validate()?;
+
+    let dispute = app_state
+        .disputes
+        .open(&user_id, &message_id, &request.reason)
+        .await?;
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(Some(user_id), "message.disputed", "message", &message_id)
+                .with_client(client.ip, client.user_agent)
+                .with_metadata("dispute_id", dispute.id.clone())
+                .with_metadata(
+                    "provider_id",
+                    dispute.provider_id.clone().unwrap_or_default(),
+                ),
+        )
+        .await;
+
+    Ok(Json(DisputeResponse::from(&dispute)))
+}
+
+/// The client's disputes, newest first
+pub async fn list_disputes(
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<DisputeListQuery>,
+    ClientUser(user_id): ClientUser,
+) -> Result<Json<Vec<DisputeResponse>>> {
+    let disputes = app_state
+        .disputes
+        .for_client(&user_id, params.limit.unwrap_or(50))
+        .await?;
+    Ok(Json(disputes.iter().map(DisputeResponse::from).collect()))
+}
+
+pub async fn get_dispute(
+    State(app_state): State<Arc<AppState>>,
+    Path(dispute_id): Path<String>,
+    ClientUser(user_id): ClientUser,
+) -> Result<Json<DisputeResponse>> {
+    let dispute = app_state.disputes.get(&dispute_id).await?;
+    if dispute.client_id != user_id {
+        return Err(PeerPowerError::NotFound {
+            resource: format!("Dispute with ID: {}", dispute_id),
+        });
+    }
+    Ok(Json(DisputeResponse::from(&dispute)))
+}
+
+/// Disputes by status, oldest first, for the review queue (admin only)
+pub async fn list_admin_disputes(
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<AdminDisputeQuery>,
+    AdminUser(_user_id): AdminUser,
+) -> Result<Json<Vec<DisputeResponse>>> {
+    let disputes = app_state
+        .disputes
+        .by_status(
+            params.status.unwrap_or(DisputeStatus::Open),
+            params.limit.unwrap_or(100),
+        )
+        .await?;
+    Ok(Json(disputes.iter().map(DisputeResponse::from).collect()))
+}
+
+/// Uphold or reject an open dispute (admin only). Upholding refunds the
+/// client and claws back the provider's earnings through the ledger.
+pub async fn resolve_dispute(
+    State(app_state): State<Arc<AppState>>,
+    Path(dispute_id): Path<String>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+    JsonExtractor(request): JsonExtractor<ResolveDisputeRequest>,
+) -> Result<Json<DisputeResponse>> {
+    let note = request.note.trim();
+    if note.is_empty() {
+        return Err(PeerPowerError::ValidationError {
+            field: "note".to_string(),
+            message: "A note is required to resolve a dispute".to_string(),
+        });
+    }
+
+    let dispute = app_state
+        .disputes
+        .resolve(
+            &dispute_id,
+            matches!(request.decision, DisputeDecision::Uphold),
+            note,
+            &user_id,
+        )
+        .await?;
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(Some(user_id), "dispute.resolved", "dispute", &dispute.id)
+                .with_client(client.ip, client.user_agent)
+                .with_metadata("message_id", dispute.message_id.clone())
+                .with_metadata("status", format!("{:?}", dispute.status))
+                .with_metadata("refund_amount", dispute.refund_amount.to_string())
+                .with_metadata("clawback_amount", dispute.clawback_amount.to_string()),
+        )
+        .await;
+
+    Ok(Json(DisputeResponse::from(&dispute)))
+}
@@ -0,0 +1,354 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{
+        header::{CACHE_CONTROL, CONTENT_DISPOSITION, CONTENT_TYPE},
+        StatusCode,
+    },
+    response::{IntoResponse, Json, Response},
+    Json as JsonExtractor,
+};
+use chrono::{DateTime, Utc};
+use futures::stream::TryStreamExt;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::domain::entities::{AuditLogEntry, DownloadLink, DownloadResource, Message};
+use crate::presentation::middleware::{ClientInfo, ClientUser};
+use crate::shared::utils::{csv_field, hmac_sha256_hex, stored_timestamp, verify_hmac_sha256_hex};
+use crate::shared::{AppState, PeerPowerError, Result};
+
+#[derive(Debug, Deserialize)]
+pub struct MessageExportRequest {
+    pub status: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DownloadLinkResponse {
+    pub link_id: String,
+    pub url: String,
+    pub expires_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DownloadQuery {
+    pub expires: i64,
+    pub signature: String,
+}
+
+/// Create a signed download link for the caller's message history
+pub async fn create_message_export(
+    State(app_state): State<Arc<AppState>>,
+    ClientUser(user_id): ClientUser,
+    client: ClientInfo,
+    JsonExtractor(export_request): JsonExtractor<MessageExportRequest>,
+) -> Result<Json<DownloadLinkResponse>> {
+    let resource = DownloadResource::MessageExport {
+        status: export_request.status,
+        from: export_request.from,
+        to: export_request.to,
+    };
+
+    let link = create_download_link(&app_state, &user_id, resource, &client).await?;
+    Ok(Json(link))
+}
+
+/// Persist a download link, audit its creation, and return the signed URL
+pub async fn create_download_link(
+    app_state: &Arc<AppState>,
+    owner_id: &str,
+    resource: DownloadResource,
+    client: &ClientInfo,
+) -> Result<DownloadLinkResponse> {
+    let link = DownloadLink::new(
+        owner_id.to_string(),
+        resource,
+        app_state.config.downloads.link_ttl_seconds,
+    );
+
+    app_state
+        .database
+        .collection::<DownloadLink>("download_links")
+        .insert_one(&link, None)
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to store download link: {}", e),
+        })?;
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(
+                Some(owner_id.to_string()),
+                "download_link.created",
+                "download_link",
+                &link.id,
+            )
+            .with_client(client.ip.clone(), client.user_agent.clone())
+            .with_metadata("file_name", link.file_name()),
+        )
+        .await;
+
+    let signature = hmac_sha256_hex(
+        &app_state.config.downloads.link_secret,
+        link.signing_payload().as_bytes(),
+    );
+
+    info!("Created download link {} for user {}", link.id, owner_id);
+
+    Ok(DownloadLinkResponse {
+        url: format!(
+            "/api/v1/downloads/{}?expires={}&signature={}",
+            link.id,
+            link.expires_at.timestamp(),
+            signature
+        ),
+        link_id: link.id,
+        expires_at: link.expires_at.to_rfc3339(),
+    })
+}
+
+/// Serve a signed download link (public; authorized by the signature)
+pub async fn download(
+    State(app_state): State<Arc<AppState>>,
+    Path(link_id): Path<String>,
+    Query(query): Query<DownloadQuery>,
+    client: ClientInfo,
+) -> Result<Response> {
+    let links_collection = app_state.database.collection::<DownloadLink>("download_links");
+    let link = links_collection
+        .find_one(mongodb::bson::doc! {"id": &link_id}, None)
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch download link: {}", e),
+        })?
+        .ok_or_else(|| PeerPowerError::NotFound {
+            resource: format!("Download link: {}", link_id),
+        })?;
+
+    let signature_valid = query.expires == link.expires_at.timestamp()
+        && verify_hmac_sha256_hex(
+            &app_state.config.downloads.link_secret,
+            link.signing_payload().as_bytes(),
+            &query.signature,
+        );
+
+    let denial = if !signature_valid {
+        Some("invalid_signature")
+    } else if link.is_revoked() {
+        Some("revoked")
+    } else if link.is_expired() {
+        Some("expired")
+    } else {
+        None
+    };
+
+    if let Some(reason) = denial {
+        warn!("Rejected download of link {}: {}", link.id, reason);
+        app_state
+            .audit_logger
+            .record_best_effort(
+                AuditLogEntry::new(
+                    Some(link.owner_id.clone()),
+                    "download_link.denied",
+                    "download_link",
+                    &link.id,
+                )
+                .with_client(client.ip.clone(), client.user_agent.clone())
+                .with_metadata("reason", reason),
+            )
+            .await;
+
+        return Err(PeerPowerError::AuthenticationFailed {
+            reason: "Download link is invalid, expired, or revoked".to_string(),
+        });
+    }
+
+    let body = match &link.resource {
+        DownloadResource::MessageExport { status, from, to } => {
+            export_messages_csv(&app_state, &link.owner_id, status.as_deref(), *from, *to).await?
+        }
+    };
+
+    links_collection
+        .update_one(
+            mongodb::bson::doc! {"id": &link.id},
+            mongodb::bson::doc! {"$inc": {"download_count": 1}},
+            None,
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to update download link: {}", e),
+        })?;
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(
+                Some(link.owner_id.clone()),
+                "download_link.accessed",
+                "download_link",
+                &link.id,
+            )
+            .with_client(client.ip, client.user_agent)
+            .with_metadata("file_name", link.file_name()),
+        )
+        .await;
+
+    info!("Served download link {}", link.id);
+
+    Ok((
+        [
+            (CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (
+                CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", link.file_name()),
+            ),
+            (CACHE_CONTROL, "no-store".to_string()),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+/// Revoke an outstanding download link (owner only)
+pub async fn revoke_download_link(
+    State(app_state): State<Arc<AppState>>,
+    Path(link_id): Path<String>,
+    ClientUser(user_id): ClientUser,
+    client: ClientInfo,
+) -> Result<StatusCode> {
+    let result = app_state
+        .database
+        .collection::<DownloadLink>("download_links")
+        .update_one(
+            mongodb::bson::doc! {
+                "id": &link_id,
+                "owner_id": &user_id,
+                "revoked_at": null
+            },
+            revocation(),
+            None,
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to revoke download link: {}", e),
+        })?;
+
+    if result.matched_count == 0 {
+        return Err(PeerPowerError::NotFound {
+            resource: format!("Download link: {}", link_id),
+        });
+    }
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(
+                Some(user_id.clone()),
+                "download_link.revoked",
+                "download_link",
+                &link_id,
+            )
+            .with_client(client.ip, client.user_agent),
+        )
+        .await;
+
+    info!("Download link {} revoked by user {}", link_id, user_id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Render a client's message history as CSV
+async fn export_messages_csv(
+    app_state: &Arc<AppState>,
+    client_id: &str,
+    status: Option<&str>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+) -> Result<String> {
+    let mut filter = mongodb::bson::doc! {"client_id": client_id};
+    if let Some(status) = status {
+        filter.insert("status", status);
+    }
+    let mut created_at = mongodb::bson::Document::new();
+    if let Some(from) = from {
+        created_at.insert("$gte", stored_timestamp(from));
+    }
+    if let Some(to) = to {
+        created_at.insert("$lte", stored_timestamp(to));
+    }
+    if !created_at.is_empty() {
+        filter.insert("created_at", created_at);
+    }
+
+    let mut find_options = mongodb::options::FindOptions::default();
+    find_options.sort = Some(mongodb::bson::doc! {"created_at": -1});
+
+    let mut cursor = app_state
+        .database
+        .collection::<Message>("messages")
+        .find(filter, find_options)
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch messages for export: {}", e),
+        })?;
+
+    let mut csv = String::from(
+        "message_id,created_at,updated_at,recipient,recipient_carrier,status,priority,provider_id\n",
+    );
+    while let Some(message) = cursor
+        .try_next()
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to iterate messages for export: {}", e),
+        })?
+    {
+        let row = [
+            message.id,
+            message.created_at.to_rfc3339(),
+            message.updated_at.to_rfc3339(),
+            message.recipient.as_str().to_string(),
+            format!("{:?}", message.recipient_carrier),
+            format!("{:?}", message.status),
+            format!("{:?}", message.priority),
+            message.provider_id.unwrap_or_default(),
+        ];
+        csv.push_str(
+            &row.iter()
+                .map(|field| csv_field(field))
+                .collect::<Vec<_>>()
+                .join(","),
+        );
+        csv.push('\n');
+    }
+
+    Ok(csv)
+}
+
+fn revocation() -> mongodb::bson::Document {
+    mongodb::bson::doc! {"$set": {"revoked_at": stored_timestamp(Utc::now())}}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::test_support::read_back;
+
+    #[test]
+    fn test_revoked_link_reads_back() {
+        let link = DownloadLink::new(
+            "client-1".to_string(),
+            DownloadResource::MessageExport {
+                status: None,
+                from: Some(Utc::now() - chrono::Duration::days(7)),
+                to: None,
+            },
+            3600,
+        );
+
+        let revoked = read_back(&link, &revocation());
+        assert!(revoked.revoked_at.is_some());
+    }
+}
@@ -1,15 +1,23 @@
 use axum::{
-    extract::{Query, State},
-    response::Json,
+    body::Body,
+    extract::{Path, Query, State},
+    http::header::{CACHE_CONTROL, CONTENT_DISPOSITION, CONTENT_TYPE},
+    response::{IntoResponse, Json, Response},
 };
-use futures::stream::TryStreamExt;
+use futures::stream::{StreamExt, TryStreamExt};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::sync::Arc;
-use tracing::info;
+use tracing::{info, warn};
 
-use crate::domain::entities::Provider;
-use crate::presentation::handlers::message_handlers::AuthenticatedUser;
-use crate::shared::{AppState, PeerPowerError, Result};
+use crate::domain::entities::number_pool::month_bounds;
+use crate::domain::entities::{
+    AuditLogEntry, LedgerAccountKind, LedgerEntryKind, Provider, QualityAssessment,
+};
+use crate::infrastructure::payments::FiatEquivalent;
+use crate::presentation::middleware::{AdminUser, ClientInfo, ProviderUser};
+use crate::shared::utils::{csv_field, stored_timestamp};
+use crate::shared::{AppState, Money, PeerPowerError, Result};
 
 #[derive(Debug, Deserialize)]
 pub struct EarningsQuery {
@@ -18,14 +26,22 @@ pub struct EarningsQuery {
     pub limit: Option<u32>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct StatementQuery {
+    pub format: Option<String>, // only "csv" for now
+}
+
 #[derive(Debug, Serialize)]
 pub struct EarningsResponse {
     pub provider_id: String,
     pub total_earnings: f64,
+    pub total_earnings_fiat: Option<FiatEquivalent>,
     pub messages_delivered: u64,
     pub success_rate: f64,
     pub period: String,
     pub earnings_breakdown: EarningsBreakdown,
+    pub off_peak: OffPeakWindow,
+    pub quality: QualityAssessment, // this month so far, against next month's bonus targets
 }
 
 #[derive(Debug, Serialize)]
@@ -34,6 +50,17 @@ pub struct EarningsBreakdown {
     pub priority_bonus: f64,
     pub volume_bonus: f64,
     pub quality_bonus: f64,
+    pub off_peak_bonus: f64,
+}
+
+/// Current off-peak incentive so providers know when coverage pays more
+#[derive(Debug, Serialize)]
+pub struct OffPeakWindow {
+    pub multiplier: f64,
+    pub start_hour: u32,
+    pub end_hour: u32,
+    pub utc_offset_hours: i32,
+    pub active_now: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -44,6 +71,19 @@ pub struct EarningsHistoryEntry {
     pub success_rate: f64,
 }
 
+#[derive(Debug, Clone, Default)]
+struct DailyEarnings {
+    earnings: Money,
+    delivered: u32,
+    failed: u32,
+}
+
+/// Just enough of a failed message to place it on a day
+#[derive(Debug, Deserialize)]
+struct FailedAt {
+    updated_at: chrono::DateTime<chrono::Utc>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct EarningsHistoryResponse {
     pub provider_id: String,
@@ -56,7 +96,7 @@ pub struct EarningsHistoryResponse {
 pub async fn get_provider_earnings(
     State(app_state): State<Arc<AppState>>,
     Query(params): Query<EarningsQuery>,
-    AuthenticatedUser(user_id): AuthenticatedUser,
+    ProviderUser(user_id): ProviderUser,
 ) -> Result<Json<EarningsResponse>> {
     info!("Getting earnings for user: {}", user_id);
 
@@ -105,15 +145,15 @@ pub async fn get_provider_earnings(
         message_filter.insert(
             "updated_at",
             mongodb::bson::doc! {
-                "$gte": start,
-                "$lte": end
+                "$gte": stored_timestamp(start),
+                "$lte": stored_timestamp(end)
             },
         );
     }
 
     // Get delivered messages count and calculate earnings
     let messages_collection = app_state
-        .database
+        .reporting
         .collection::<crate::domain::entities::Message>("messages");
     let delivered_count = messages_collection
         .count_documents(message_filter.clone(), None)
@@ -122,13 +162,36 @@ pub async fn get_provider_earnings(
             message: format!("Failed to count delivered messages: {}", e),
         })? as u64;
 
-    // Calculate total earnings (simplified - in real system this would be more complex)
+    // Lifetime earnings and bonuses credited in the period come from the ledger
+    let lifetime_earnings = app_state
+        .ledger
+        .balance(LedgerAccountKind::Provider, &provider.id)
+        .await?
+        .credits;
+    let volume_bonus = app_state
+        .ledger
+        .entry_total(
+            LedgerAccountKind::Provider,
+            &provider.id,
+            LedgerEntryKind::VolumeBonus,
+            start_date,
+        )
+        .await?;
+    let quality_bonus = app_state
+        .ledger
+        .entry_total(
+            LedgerAccountKind::Provider,
+            &provider.id,
+            LedgerEntryKind::QualityBonus,
+            start_date,
+        )
+        .await?;
     let total_earnings = if period == "all" {
-        provider.earnings_total
+        lifetime_earnings
     } else {
         // For time-based periods, we'd need to aggregate from message history
-        // For now, use a simplified calculation
-        delivered_count as f64 * 0.008 // Average earnings per message
+        // For now, use a simplified calculation: average earnings per message, plus bonuses
+        Money::from_ppt(delivered_count as f64 * 0.008) + volume_bonus + quality_bonus
     };
 
     // Calculate success rate
@@ -148,21 +211,82 @@ pub async fn get_provider_earnings(
         0.0
     };
 
-    // Create earnings breakdown (simplified)
+    // Off-peak bonus is recorded exactly on each delivery's earnings event
+    let mut off_peak_filter =
+        mongodb::bson::doc! {"provider_id": &provider.id, "clawed_back": {"$ne": true}};
+    if let Some(start) = start_date {
+        off_peak_filter.insert(
+            "created_at",
+            mongodb::bson::doc! {"$gte": stored_timestamp(start)},
+        );
+    }
+    let off_peak_bonus = app_state
+        .reporting
+        .collection::<mongodb::bson::Document>("earnings_events")
+        .aggregate(
+            vec![
+                mongodb::bson::doc! {"$match": off_peak_filter},
+                mongodb::bson::doc! {
+                    "$group": {
+                        "_id": null,
+                        "off_peak_bonus": {"$sum": Money::micros_expr("$off_peak_bonus")},
+                    }
+                },
+            ],
+            None,
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to aggregate off-peak bonus: {}", e),
+        })?
+        .try_next()
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to read off-peak bonus: {}", e),
+        })?
+        .map(|doc| Money::from_field(&doc, "off_peak_bonus"))
+        .unwrap_or_default()
+        .min(total_earnings);
+    let standard_earnings =
+        (total_earnings - off_peak_bonus - volume_bonus - quality_bonus).max(Money::ZERO);
+    let base_earnings = standard_earnings.percent(90.0);
+
+    // Create earnings breakdown (base and priority split simplified; bonuses are exact)
     let earnings_breakdown = EarningsBreakdown {
-        base_earnings: total_earnings * 0.8,
-        priority_bonus: total_earnings * 0.1,
-        volume_bonus: total_earnings * 0.05,
-        quality_bonus: total_earnings * 0.05,
+        base_earnings: base_earnings.to_ppt(),
+        priority_bonus: (standard_earnings - base_earnings).to_ppt(),
+        volume_bonus: volume_bonus.to_ppt(),
+        quality_bonus: quality_bonus.to_ppt(),
+        off_peak_bonus: off_peak_bonus.to_ppt(),
     };
 
+    let earnings_config = &app_state.config.earnings;
+    let off_peak = OffPeakWindow {
+        multiplier: earnings_config.off_peak_multiplier,
+        start_hour: earnings_config.off_peak_start_hour,
+        end_hour: earnings_config.off_peak_end_hour,
+        utc_offset_hours: earnings_config.utc_offset_hours,
+        active_now: earnings_config.is_off_peak(chrono::Utc::now()),
+    };
+
+    let quality = app_state
+        .quality_bonuses
+        .month_to_date(&provider.id)
+        .await?;
+
     Ok(Json(EarningsResponse {
         provider_id: provider.id,
-        total_earnings,
+        total_earnings: total_earnings.to_ppt(),
+        total_earnings_fiat: app_state
+            .exchange_rates
+            .fiat_equivalent(total_earnings.to_ppt())
+            .await,
         messages_delivered: delivered_count,
         success_rate,
         period,
         earnings_breakdown,
+        off_peak,
+        quality,
     }))
 }
 
@@ -170,7 +294,7 @@ pub async fn get_provider_earnings(
 pub async fn get_earnings_history(
     State(app_state): State<Arc<AppState>>,
     Query(params): Query<EarningsQuery>,
-    AuthenticatedUser(user_id): AuthenticatedUser,
+    ProviderUser(user_id): ProviderUser,
 ) -> Result<Json<EarningsHistoryResponse>> {
     info!("Getting earnings history for user: {}", user_id);
 
@@ -187,56 +311,332 @@ pub async fn get_earnings_history(
         })?;
 
     let period = params.period.unwrap_or_else(|| "month".to_string());
-
-    // For now, create sample earnings history
-    // In a real system, this would aggregate from a detailed earnings log table
-    let mut history = Vec::new();
-    let days_back = match period.as_str() {
-        "week" => 7,
-        "month" => 30,
-        _ => 30,
+    let earnings_config = &app_state.config.earnings;
+    let now = chrono::Utc::now();
+    let today = earnings_config.local_date(now);
+    let since = match period.as_str() {
+        "today" => Some(today),
+        "week" => Some(today - chrono::Duration::days(6)),
+        "all" => None,
+        _ => Some(today - chrono::Duration::days(29)),
     };
 
-    for i in 0..days_back {
-        let date = chrono::Utc::now() - chrono::Duration::days(i);
-        let daily_messages = (provider.total_messages_delivered / days_back as u64).max(1);
-        let daily_earnings = daily_messages as f64 * 0.008;
-        let daily_success_rate = 95.0 + (i as f64 * 0.1); // Simulate variation
-
-        history.push(EarningsHistoryEntry {
-            date: date.format("%Y-%m-%d").to_string(),
-            messages_count: daily_messages as u32,
-            earnings: daily_earnings,
-            success_rate: daily_success_rate.min(100.0),
-        });
+    // Earnings and delivered counts per local day, from the per-delivery records
+    let mut event_filter =
+        mongodb::bson::doc! {"provider_id": &provider.id, "clawed_back": {"$ne": true}};
+    if let Some(since) = since {
+        event_filter.insert(
+            "date",
+            mongodb::bson::doc! {"$gte": since.format("%Y-%m-%d").to_string()},
+        );
+    }
+    let pipeline = vec![
+        mongodb::bson::doc! {"$match": event_filter},
+        mongodb::bson::doc! {
+            "$group": {
+                "_id": "$date",
+                "earnings": {"$sum": Money::micros_expr("$amount")},
+                "messages": {"$sum": 1},
+            }
+        },
+    ];
+    let mut cursor = app_state
+        .reporting
+        .collection::<mongodb::bson::Document>("earnings_events")
+        .aggregate(pipeline, None)
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to aggregate earnings history: {}", e),
+        })?;
+
+    let mut days: BTreeMap<chrono::NaiveDate, DailyEarnings> = BTreeMap::new();
+    while let Some(doc) = cursor
+        .try_next()
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to read earnings history: {}", e),
+        })?
+    {
+        let Some(date) = doc
+            .get_str("_id")
+            .ok()
+            .and_then(|date| chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+        else {
+            continue;
+        };
+        let day = days.entry(date).or_default();
+        day.earnings = Money::from_field(&doc, "earnings");
+        day.delivered = doc
+            .get_i64("messages")
+            .or_else(|_| doc.get_i32("messages").map(i64::from))
+            .unwrap_or(0)
+            .max(0) as u32;
+    }
+
+    // Failed deliveries in the same window, for the daily success rate
+    let mut failed_filter = mongodb::bson::doc! {
+        "provider_id": &provider.id,
+        "status": "Failed"
+    };
+    if let Some(since) = since {
+        let since_utc = since.and_hms_opt(0, 0, 0).unwrap().and_utc()
+            - chrono::Duration::hours(earnings_config.utc_offset_hours as i64);
+        failed_filter.insert(
+            "updated_at",
+            mongodb::bson::doc! {"$gte": stored_timestamp(since_utc)},
+        );
+    }
+    let mut failed = app_state
+        .reporting
+        .collection::<FailedAt>("messages")
+        .find(
+            failed_filter,
+            mongodb::options::FindOptions::builder()
+                .projection(mongodb::bson::doc! {"updated_at": 1})
+                .build(),
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch failed messages: {}", e),
+        })?;
+    while let Some(message) = failed
+        .try_next()
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to read failed messages: {}", e),
+        })?
+    {
+        days.entry(earnings_config.local_date(message.updated_at))
+            .or_default()
+            .failed += 1;
     }
 
-    // Reverse to show oldest first
-    history.reverse();
+    // One entry per day in the period, oldest first, including quiet days
+    let first = since
+        .or_else(|| days.keys().next().copied())
+        .unwrap_or(today);
+    let history: Vec<EarningsHistoryEntry> = first
+        .iter_days()
+        .take_while(|date| *date <= today)
+        .map(|date| {
+            let day = days.get(&date).cloned().unwrap_or_default();
+            let attempted = day.delivered + day.failed;
+            EarningsHistoryEntry {
+                date: date.format("%Y-%m-%d").to_string(),
+                messages_count: day.delivered,
+                earnings: day.earnings.to_ppt(),
+                success_rate: if attempted > 0 {
+                    day.delivered as f64 / attempted as f64 * 100.0
+                } else {
+                    0.0
+                },
+            }
+        })
+        .collect();
 
-    let total_earnings: f64 = history.iter().map(|h| h.earnings).sum();
+    let total_earnings: Money = days
+        .range(first..=today)
+        .map(|(_, day)| day.earnings)
+        .sum();
 
     Ok(Json(EarningsHistoryResponse {
         provider_id: provider.id,
         period,
-        total_earnings,
+        total_earnings: total_earnings.to_ppt(),
         history,
     }))
 }
 
+/// Itemized statement of the provider's ledger account for one local
+/// calendar month, streamed as CSV: the opening balance, every earning,
+/// bonus and payout posted in the month with the running balance, and the
+/// closing balance
+pub async fn get_earnings_statement(
+    State(app_state): State<Arc<AppState>>,
+    Path((year, month)): Path<(i32, u32)>,
+    Query(params): Query<StatementQuery>,
+    ProviderUser(user_id): ProviderUser,
+    client: ClientInfo,
+) -> Result<Response> {
+    if params
+        .format
+        .as_deref()
+        .is_some_and(|format| format != "csv")
+    {
+        return Err(PeerPowerError::ValidationError {
+            field: "format".to_string(),
+            message: "Statements are only available as csv".to_string(),
+        });
+    }
+    let (first, last) = chrono::NaiveDate::from_ymd_opt(year, month, 1)
+        .map(month_bounds)
+        .ok_or_else(|| PeerPowerError::ValidationError {
+            field: "month".to_string(),
+            message: format!("{}-{} is not a valid month", year, month),
+        })?;
+    let earnings_config = &app_state.config.earnings;
+    let from = earnings_config.local_day_start(first);
+    let to = earnings_config.local_day_start(last + chrono::Duration::days(1));
+    if from > chrono::Utc::now() {
+        return Err(PeerPowerError::ValidationError {
+            field: "month".to_string(),
+            message: "No statement for a month that hasn't started".to_string(),
+        });
+    }
+
+    let provider = app_state
+        .database
+        .collection::<Provider>("providers")
+        .find_one(mongodb::bson::doc! {"user_id": &user_id}, None)
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch provider: {}", e),
+        })?
+        .ok_or_else(|| PeerPowerError::NotFound {
+            resource: format!("Provider for user: {}", user_id),
+        })?;
+
+    let opening = app_state
+        .ledger
+        .balance_before(LedgerAccountKind::Provider, &provider.id, from)
+        .await?;
+    let cursor = app_state
+        .ledger
+        .transactions_between(LedgerAccountKind::Provider, &provider.id, from, to)
+        .await?;
+
+    let period = first.format("%Y-%m").to_string();
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(
+                Some(user_id),
+                "earnings.statement_exported",
+                "provider",
+                &provider.id,
+            )
+            .with_client(client.ip, client.user_agent)
+            .with_metadata("period", period.clone()),
+        )
+        .await;
+
+    let header = format!(
+        "date,transaction_id,kind,reference,entry,amount,balance\n{}",
+        statement_row(
+            &from.to_rfc3339(),
+            "",
+            "",
+            "",
+            "OpeningBalance",
+            None,
+            opening
+        )
+    );
+    // One chunk per ledger transaction, so long months are never held in memory
+    let provider_id = provider.id.clone();
+    let rows = futures::stream::unfold(Some((cursor, opening)), move |state| {
+        let provider_id = provider_id.clone();
+        async move {
+            let (mut cursor, mut balance) = state?;
+            match cursor.try_next().await {
+                Ok(Some(transaction)) => {
+                    let mut chunk = String::new();
+                    for posting in
+                        transaction.postings_for(LedgerAccountKind::Provider, &provider_id)
+                    {
+                        balance += posting.amount;
+                        chunk.push_str(&statement_row(
+                            &transaction.created_at.to_rfc3339(),
+                            &transaction.id,
+                            &format!("{:?}", transaction.kind),
+                            &transaction.reference,
+                            &format!("{:?}", posting.entry),
+                            Some(posting.amount),
+                            balance,
+                        ));
+                    }
+                    Some((Ok(chunk), Some((cursor, balance))))
+                }
+                Ok(None) => Some((
+                    Ok(statement_row(
+                        &to.to_rfc3339(),
+                        "",
+                        "",
+                        "",
+                        "ClosingBalance",
+                        None,
+                        balance,
+                    )),
+                    None,
+                )),
+                Err(e) => Some((Err(e), None)),
+            }
+        }
+    });
+    let statement_id = provider.id.clone();
+    let body = futures::stream::once(async { Ok(header) })
+        .chain(rows)
+        .inspect_err(move |e| warn!("Earnings statement for {} cut short: {}", statement_id, e));
+
+    info!(
+        "Streaming {} earnings statement for provider {}",
+        period, provider.id
+    );
+
+    Ok((
+        [
+            (CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (
+                CONTENT_DISPOSITION,
+                format!("attachment; filename=\"earnings-statement-{}.csv\"", period),
+            ),
+            (CACHE_CONTROL, "no-store".to_string()),
+        ],
+        Body::from_stream(body),
+    )
+        .into_response())
+}
+
+fn statement_row(
+    date: &str,
+    transaction_id: &str,
+    kind: &str,
+    reference: &str,
+    entry: &str,
+    amount: Option<Money>,
+    balance: Money,
+) -> String {
+    let amount = amount.map(|amount| amount.to_string()).unwrap_or_default();
+    let fields = [
+        date,
+        transaction_id,
+        kind,
+        reference,
+        entry,
+        &amount,
+        &balance.to_string(),
+    ];
+    let mut row = fields
+        .iter()
+        .map(|field| csv_field(field))
+        .collect::<Vec<_>>()
+        .join(",");
+    row.push('\n');
+    row
+}
+
 /// Get system-wide earnings statistics (admin endpoint)
 pub async fn get_system_earnings_stats(
     State(app_state): State<Arc<AppState>>,
-    AuthenticatedUser(_user_id): AuthenticatedUser, // TODO: Add admin role check
+    AdminUser(_user_id): AdminUser,
 ) -> Result<Json<serde_json::Value>> {
     info!("Getting system earnings statistics");
 
     // Get total earnings across all providers
-    let providers_collection = app_state.database.collection::<Provider>("providers");
+    let providers_collection = app_state.reporting.collection::<Provider>("providers");
     let pipeline = vec![mongodb::bson::doc! {
         "$group": {
             "_id": null,
-            "total_earnings": { "$sum": "$earnings_total" },
             "total_messages": { "$sum": "$total_messages_delivered" },
             "total_providers": { "$sum": 1 },
             "avg_success_rate": { "$avg": "$success_rate" }
@@ -250,9 +650,18 @@ pub async fn get_system_earnings_stats(
             message: format!("Failed to aggregate earnings: {}", e),
         })?;
 
+    let total_earnings = app_state
+        .ledger
+        .balances(LedgerAccountKind::Provider)
+        .await?
+        .values()
+        .map(|balance| balance.credits)
+        .sum::<Money>()
+        .to_ppt();
+
     let stats = if let Ok(Some(doc)) = cursor.try_next().await {
         serde_json::json!({
-            "total_earnings": doc.get_f64("total_earnings").unwrap_or(0.0),
+            "total_earnings": total_earnings,
             "total_messages_delivered": doc.get_i64("total_messages").unwrap_or(0),
             "total_active_providers": doc.get_i32("total_providers").unwrap_or(0),
             "average_success_rate": doc.get_f64("avg_success_rate").unwrap_or(0.0),
@@ -260,7 +669,7 @@ pub async fn get_system_earnings_stats(
         })
     } else {
         serde_json::json!({
-            "total_earnings": 0.0,
+            "total_earnings": total_earnings,
             "total_messages_delivered": 0,
             "total_active_providers": 0,
             "average_success_rate": 0.0,
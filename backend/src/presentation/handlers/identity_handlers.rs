@@ -0,0 +1,123 @@
+use axum::{
+    extract::{Path, State},
+    response::Json,
+    Json as JsonExtractor,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+use tracing::info;
+
+use crate::domain::entities::{AuditLogEntry, CredentialKind, IssuedCredential};
+use crate::infrastructure::identity::{did_document, CredentialVerification};
+use crate::presentation::handlers::user_handlers::AuthenticatedUser;
+use crate::presentation::middleware::ClientInfo;
+use crate::shared::{AppState, Result};
+
+#[derive(Debug, Serialize)]
+pub struct CredentialResponse {
+    pub id: String,
+    pub kind: CredentialKind,
+    pub subject_did: String,
+    pub jwt: String,
+    pub issued_at: String,
+    pub expires_at: String,
+    pub revoked_at: Option<String>,
+    pub active: bool,
+}
+
+impl From<&IssuedCredential> for CredentialResponse {
+    fn from(credential: &IssuedCredential) -> Self {
+        Self {
+            id: credential.id.clone(),
+            kind: credential.kind,
+            subject_did: credential.subject_did.clone(),
+            jwt: credential.jwt.clone(),
+            issued_at: credential.issued_at.to_rfc3339(),
+            expires_at: credential.expires_at.to_rfc3339(),
+            revoked_at: credential.revoked_at.map(|at| at.to_rfc3339()),
+            active: credential.is_active(crate::shared::utils::now()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CredentialsResponse {
+    pub did: Option<String>,
+    pub issuer: Option<String>,
+    pub credentials: Vec<CredentialResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RegisterDidRequest {
+    pub did: String,
+    pub proof: String, // JWS signed by the DID's key over {"sub": user id, "iat": unix seconds}
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyCredentialRequest {
+    pub credential: String,
+}
+
+/// Resolve a `did:key` to its DID document (public)
+pub async fn resolve_did(Path(did): Path<String>) -> Result<Json<Value>> {
+    Ok(Json(did_document(&did)?))
+}
+
+/// Check a credential issued by the platform (public, for third parties)
+pub async fn verify_credential(
+    State(app_state): State<Arc<AppState>>,
+    JsonExtractor(request): JsonExtractor<VerifyCredentialRequest>,
+) -> Result<Json<CredentialVerification>> {
+    Ok(Json(app_state.identity.verify(&request.credential).await?))
+}
+
+/// The caller's DID and the credentials issued to them
+pub async fn list_credentials(
+    State(app_state): State<Arc<AppState>>,
+    AuthenticatedUser(user_id): AuthenticatedUser,
+) -> Result<Json<CredentialsResponse>> {
+    let user = app_state.user_repository.find_by_id(&user_id).await?;
+    let credentials = app_state.identity.list(&user_id).await?;
+
+    Ok(Json(CredentialsResponse {
+        did: user.and_then(|user| user.did),
+        issuer: app_state.identity.issuer_did(),
+        credentials: credentials.iter().map(CredentialResponse::from).collect(),
+    }))
+}
+
+/// Replace the caller's DID with a `did:key` they hold the key for
+pub async fn register_did(
+    State(app_state): State<Arc<AppState>>,
+    AuthenticatedUser(user_id): AuthenticatedUser,
+    client: ClientInfo,
+    JsonExtractor(request): JsonExtractor<RegisterDidRequest>,
+) -> Result<Json<CredentialsResponse>> {
+    let user = app_state
+        .identity
+        .register_did(&user_id, &request.did, &request.proof)
+        .await?;
+    info!("User {} registered DID {}", user_id, request.did);
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(
+                Some(user_id.clone()),
+                "user.did_registered",
+                "user",
+                &user_id,
+            )
+            .with_client(client.ip, client.user_agent)
+            .with_metadata("did", request.did),
+        )
+        .await;
+
+    let credentials = app_state.identity.list(&user_id).await?;
+    Ok(Json(CredentialsResponse {
+        did: user.did,
+        issuer: app_state.identity.issuer_did(),
+        credentials: credentials.iter().map(CredentialResponse::from).collect(),
+    }))
+}
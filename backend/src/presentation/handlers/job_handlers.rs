@@ -0,0 +1,231 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::Json,
+    Json as JsonExtractor,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::domain::entities::{AuditLogEntry, DeadLetterJob, DeadLetterStatus, Job};
+use crate::infrastructure::dead_letters::JobBatch;
+use crate::infrastructure::job_admin::JobFilter;
+use crate::infrastructure::job_queue::CarrierBacklog;
+use crate::presentation::middleware::{AdminUser, ClientInfo};
+use crate::shared::{AppState, PageRequest, PageResponse, PeerPowerError, Result};
+
+/// Jobs or entries acted on per bulk request at most
+const MAX_JOB_BATCH: usize = 500;
+
+#[derive(Debug, Deserialize)]
+pub struct DeadLetterListQuery {
+    pub status: Option<DeadLetterStatus>, // defaults to Pending
+    pub cursor: Option<String>,           // next_cursor of the previous page
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct JobBatchRequest {
+    pub ids: Vec<String>,
+}
+
+impl JobBatchRequest {
+    fn validate(&self) -> Result<()> {
+        validate_ids(&self.ids)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ForceFailRequest {
+    pub ids: Vec<String>,
+    pub reason: String,
+}
+
+impl ForceFailRequest {
+    fn validate(&self) -> Result<()> {
+        validate_ids(&self.ids)?;
+        if self.reason.trim().is_empty() {
+            return Err(PeerPowerError::ValidationError {
+                field: "reason".to_string(),
+                message: "A reason is required".to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+fn validate_ids(ids: &[String]) -> Result<()> {
+    if ids.is_empty() || ids.len() > MAX_JOB_BATCH {
+        return Err(PeerPowerError::ValidationError {
+            field: "ids".to_string(),
+            message: format!("Between 1 and {} ids are required", MAX_JOB_BATCH),
+        });
+    }
+    Ok(())
+}
+
+/// Jobs waiting per recipient carrier (admin only)
+pub async fn queue_depth_by_carrier(
+    State(app_state): State<Arc<AppState>>,
+    AdminUser(_user_id): AdminUser,
+) -> Result<Json<Vec<CarrierBacklog>>> {
+    Ok(Json(app_state.job_queue.depth_by_carrier().await?))
+}
+
+/// Jobs by status, provider and age, oldest first (admin only)
+pub async fn list_jobs(
+    State(app_state): State<Arc<AppState>>,
+    Query(filter): Query<JobFilter>,
+    AdminUser(_user_id): AdminUser,
+) -> Result<Json<Vec<Job>>> {
+    Ok(Json(app_state.job_admin.list(&filter).await?))
+}
+
+/// One job with its attempt history (admin only)
+pub async fn get_job(
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    AdminUser(_user_id): AdminUser,
+) -> Result<Json<Job>> {
+    Ok(Json(app_state.job_admin.get(&id).await?))
+}
+
+/// Put stuck jobs back on the queue with a fresh retry budget (admin only)
+pub async fn requeue_jobs(
+    State(app_state): State<Arc<AppState>>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+    JsonExtractor(request): JsonExtractor<JobBatchRequest>,
+) -> Result<Json<JobBatch>> {
+    request.validate()?;
+    let batch = app_state.job_admin.requeue(&request.ids, &user_id).await?;
+    record_batch(&app_state, user_id, client, "jobs.requeued", "jobs", &batch).await;
+    Ok(Json(batch))
+}
+
+/// Cancel jobs and their messages (admin only)
+pub async fn cancel_jobs(
+    State(app_state): State<Arc<AppState>>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+    JsonExtractor(request): JsonExtractor<JobBatchRequest>,
+) -> Result<Json<JobBatch>> {
+    request.validate()?;
+    let batch = app_state.job_admin.cancel(&request.ids, &user_id).await?;
+    record_batch(
+        &app_state,
+        user_id,
+        client,
+        "jobs.cancelled",
+        "jobs",
+        &batch,
+    )
+    .await;
+    Ok(Json(batch))
+}
+
+/// Fail jobs and their messages without further retries (admin only)
+pub async fn fail_jobs(
+    State(app_state): State<Arc<AppState>>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+    JsonExtractor(request): JsonExtractor<ForceFailRequest>,
+) -> Result<Json<JobBatch>> {
+    request.validate()?;
+    let batch = app_state
+        .job_admin
+        .force_fail(&request.ids, request.reason.trim(), &user_id)
+        .await?;
+    record_batch(&app_state, user_id, client, "jobs.failed", "jobs", &batch).await;
+    Ok(Json(batch))
+}
+
+/// Dead-lettered jobs by status, newest first (admin only)
+pub async fn list_dead_letters(
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<DeadLetterListQuery>,
+    AdminUser(_user_id): AdminUser,
+) -> Result<Json<PageResponse<DeadLetterJob>>> {
+    let page = PageRequest::new(params.cursor.as_deref(), params.limit)?;
+    Ok(Json(
+        app_state
+            .dead_letters
+            .by_status(params.status.unwrap_or(DeadLetterStatus::Pending), &page)
+            .await?,
+    ))
+}
+
+/// One dead-lettered job with its payload and attempt history (admin only)
+pub async fn get_dead_letter(
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    AdminUser(_user_id): AdminUser,
+) -> Result<Json<DeadLetterJob>> {
+    Ok(Json(app_state.dead_letters.get(&id).await?))
+}
+
+/// Put dead-lettered jobs back on the queue with a fresh retry budget (admin only)
+pub async fn requeue_dead_letters(
+    State(app_state): State<Arc<AppState>>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+    JsonExtractor(request): JsonExtractor<JobBatchRequest>,
+) -> Result<Json<JobBatch>> {
+    request.validate()?;
+    let batch = app_state
+        .dead_letters
+        .requeue(&request.ids, &user_id)
+        .await?;
+    record_batch(
+        &app_state,
+        user_id,
+        client,
+        "dead_letters.requeued",
+        "dead_letter_jobs",
+        &batch,
+    )
+    .await;
+    Ok(Json(batch))
+}
+
+/// Drop dead-lettered jobs, leaving their messages failed (admin only)
+pub async fn discard_dead_letters(
+    State(app_state): State<Arc<AppState>>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+    JsonExtractor(request): JsonExtractor<JobBatchRequest>,
+) -> Result<Json<JobBatch>> {
+    request.validate()?;
+    let batch = app_state
+        .dead_letters
+        .discard(&request.ids, &user_id)
+        .await?;
+    record_batch(
+        &app_state,
+        user_id,
+        client,
+        "dead_letters.discarded",
+        "dead_letter_jobs",
+        &batch,
+    )
+    .await;
+    Ok(Json(batch))
+}
+
+async fn record_batch(
+    app_state: &AppState,
+    user_id: String,
+    client: ClientInfo,
+    action: &str,
+    resource: &str,
+    batch: &JobBatch,
+) {
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(Some(user_id), action, resource, "batch")
+                .with_client(client.ip, client.user_agent)
+                .with_metadata("ids", batch.processed.join(","))
+                .with_metadata("skipped", batch.skipped.len().to_string()),
+        )
+        .await;
+}
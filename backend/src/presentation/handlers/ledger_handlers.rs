@@ -0,0 +1,237 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::info;
+
+use crate::domain::entities::{
+    AuditLogEntry, EarningsReconciliation, LedgerAccountKind, Provider,
+};
+use crate::infrastructure::ledger::{AccountBalance, LegacyPostings, RevenueReport, StatementLine};
+use crate::infrastructure::payments::FiatEquivalent;
+use crate::presentation::middleware::{AdminUser, ClientInfo, ClientUser, ProviderUser};
+use crate::shared::{AppState, PeerPowerError, Result};
+
+#[derive(Debug, Deserialize)]
+pub struct StatementQuery {
+    pub page: Option<u32>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RevenueQuery {
+    pub period: Option<String>, // "today", "week", "month", "all"
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReconciliationListQuery {
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StatementResponse {
+    pub account_kind: LedgerAccountKind,
+    pub account_id: String,
+    pub currency: &'static str,
+    pub balance: AccountBalance,
+    pub balance_fiat: Option<FiatEquivalent>,
+    pub entries: Vec<StatementLine>,
+    pub page: u32,
+    pub limit: u32,
+}
+
+async fn statement(
+    app_state: &AppState,
+    account_kind: LedgerAccountKind,
+    account_id: String,
+    params: StatementQuery,
+) -> Result<StatementResponse> {
+    let page = params.page.unwrap_or(1).max(1);
+    let limit = params.limit.unwrap_or(50).clamp(1, 200);
+
+    let balance = app_state.ledger.balance(account_kind, &account_id).await?;
+    let entries = app_state
+        .ledger
+        .statement(account_kind, &account_id, page, limit)
+        .await?;
+
+    let balance_fiat = app_state
+        .exchange_rates
+        .fiat_equivalent(balance.balance.to_ppt())
+        .await;
+
+    Ok(StatementResponse {
+        account_kind,
+        account_id,
+        currency: "PPT",
+        balance,
+        balance_fiat,
+        entries,
+        page,
+        limit,
+    })
+}
+
+/// The provider's earnings balance and ledger postings, newest first
+pub async fn get_provider_statement(
+    State(app_state): State<Arc<AppState>>,
+    Path(provider_id): Path<String>,
+    Query(params): Query<StatementQuery>,
+    ProviderUser(user_id): ProviderUser,
+) -> Result<Json<StatementResponse>> {
+    app_state
+        .database
+        .collection::<Provider>("providers")
+        .find_one(
+            mongodb::bson::doc! {
+                "id": &provider_id,
+                "user_id": &user_id
+            },
+            None,
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch provider: {}", e),
+        })?
+        .ok_or_else(|| PeerPowerError::NotFound {
+            resource: format!("Provider with ID: {}", provider_id),
+        })?;
+
+    Ok(Json(
+        statement(&app_state, LedgerAccountKind::Provider, provider_id, params).await?,
+    ))
+}
+
+/// The client's credit balance with its top-ups and message charges
+pub async fn get_client_statement(
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<StatementQuery>,
+    ClientUser(user_id): ClientUser,
+) -> Result<Json<StatementResponse>> {
+    Ok(Json(
+        statement(&app_state, LedgerAccountKind::Client, user_id, params).await?,
+    ))
+}
+
+/// Any ledger account's statement, including the platform's (admin only)
+pub async fn get_account_statement(
+    State(app_state): State<Arc<AppState>>,
+    Path((account_kind, account_id)): Path<(String, String)>,
+    Query(params): Query<StatementQuery>,
+    AdminUser(_user_id): AdminUser,
+) -> Result<Json<StatementResponse>> {
+    let account_kind = match account_kind.as_str() {
+        "client" => LedgerAccountKind::Client,
+        "provider" => LedgerAccountKind::Provider,
+        "platform" => LedgerAccountKind::Platform,
+        _ => {
+            return Err(PeerPowerError::ValidationError {
+                field: "account_kind".to_string(),
+                message: "Account kind must be client, provider or platform".to_string(),
+            })
+        }
+    };
+
+    Ok(Json(
+        statement(&app_state, account_kind, account_id, params).await?,
+    ))
+}
+
+/// Platform fees earned, by kind of charge and by day (admin only)
+pub async fn get_revenue_report(
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<RevenueQuery>,
+    AdminUser(_user_id): AdminUser,
+) -> Result<Json<RevenueReport>> {
+    let now = chrono::Utc::now();
+    let since = match params.period.as_deref().unwrap_or("month") {
+        "today" => Some(now.date_naive().and_hms_opt(0, 0, 0).unwrap().and_utc()),
+        "week" => Some(now - chrono::Duration::days(7)),
+        "month" => Some(now - chrono::Duration::days(30)),
+        "all" => None,
+        _ => {
+            return Err(PeerPowerError::ValidationError {
+                field: "period".to_string(),
+                message: "Period must be today, week, month or all".to_string(),
+            })
+        }
+    };
+
+    Ok(Json(app_state.ledger.revenue(since).await?))
+}
+
+/// Carry providers' pre-ledger earnings and payouts into the ledger (admin only)
+pub async fn open_legacy_balances(
+    State(app_state): State<Arc<AppState>>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+) -> Result<Json<LegacyPostings>> {
+    let posted = app_state.ledger.open_legacy_balances().await?;
+    info!(
+        "Admin {} posted {} ledger opening balances and {} payouts",
+        user_id, posted.providers_opened, posted.payouts_posted
+    );
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(
+                Some(user_id),
+                "ledger.opening_balances_posted",
+                "ledger",
+                "providers",
+            )
+            .with_client(client.ip, client.user_agent)
+            .with_metadata("providers_opened", posted.providers_opened.to_string())
+            .with_metadata("payouts_posted", posted.payouts_posted.to_string())
+            .with_metadata("reversals_posted", posted.reversals_posted.to_string()),
+        )
+        .await;
+
+    Ok(Json(posted))
+}
+
+/// Recent earnings reconciliation reports with their discrepancies (admin only)
+pub async fn list_earnings_reconciliations(
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<ReconciliationListQuery>,
+    AdminUser(_user_id): AdminUser,
+) -> Result<Json<Vec<EarningsReconciliation>>> {
+    Ok(Json(
+        app_state
+            .earnings_reconciler
+            .recent(params.limit.unwrap_or(30).clamp(1, 100))
+            .await?,
+    ))
+}
+
+/// Reconcile provider earnings against the ledger now, outside the nightly run (admin only)
+pub async fn run_earnings_reconciliation(
+    State(app_state): State<Arc<AppState>>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+) -> Result<Json<EarningsReconciliation>> {
+    let report = app_state
+        .earnings_reconciler
+        .run(Some(user_id.clone()))
+        .await?;
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(
+                Some(user_id),
+                "ledger.earnings_reconciled",
+                "earnings_reconciliation",
+                &report.id,
+            )
+            .with_client(client.ip, client.user_agent)
+            .with_metadata("discrepancies", report.discrepancy_count().to_string())
+            .with_metadata("corrected", report.corrected_count.to_string()),
+        )
+        .await;
+
+    Ok(Json(report))
+}
@@ -1,44 +1,34 @@
-use async_trait::async_trait;
 use axum::{
-    extract::{FromRequestParts, Path, Query, State},
-    http::{request::Parts, StatusCode},
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
     response::Json,
     Json as JsonExtractor,
 };
 use futures::stream::TryStreamExt;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use tracing::info;
+use tracing::{info, warn};
 use validator::Validate;
 
 use crate::domain::entities::message::MessagePriority;
-use crate::domain::entities::{Job, Message};
-use crate::domain::services::TokenClaims;
-use crate::shared::types::PhoneNumber;
-use crate::shared::{AppState, PeerPowerError, Result};
-
-/// Extractor for authenticated user ID
-pub struct AuthenticatedUser(pub String);
-
-#[async_trait]
-impl<S> FromRequestParts<S> for AuthenticatedUser
-where
-    S: Send + Sync,
-{
-    type Rejection = StatusCode;
-
-    async fn from_request_parts(
-        parts: &mut Parts,
-        _state: &S,
-    ) -> std::result::Result<Self, Self::Rejection> {
-        let claims = parts
-            .extensions
-            .get::<TokenClaims>()
-            .ok_or(StatusCode::UNAUTHORIZED)?;
-
-        Ok(AuthenticatedUser(claims.sub.clone()))
-    }
-}
+use crate::domain::entities::provider::SELF_TEST_CLIENT_ID;
+use crate::domain::entities::{
+    AuditLogEntry, CancellationStatus, ClientQualityScore, EarningsEvent, Job, JobStatus,
+    LedgerTransaction, Message, MessageCancellation, SavedFilter,
+};
+use crate::infrastructure::canary::CanaryRouter;
+use crate::infrastructure::delivery_confirmations::{DeliveryEarnings, Recorded};
+use crate::infrastructure::device_keys::SignedConfirmation;
+use crate::infrastructure::payments::FiatEquivalent;
+use crate::infrastructure::pricing::{self, MessagePrice};
+use crate::presentation::handlers::provider_handlers::record_self_test_result;
+use crate::presentation::middleware::{ClientInfo, ClientUser, ProviderUser};
+use crate::shared::field_encryption;
+use crate::shared::pagination::Cursor;
+use crate::shared::types::{MessageStatus, PhoneNumber};
+use crate::shared::utils::stored_timestamp;
+use crate::shared::{AppState, Money, PageRequest, PageResponse, PeerPowerError, Result};
 
 #[derive(Debug, Deserialize, Validate)]
 pub struct SendMessageRequest {
@@ -52,6 +42,10 @@ pub struct SendMessageRequest {
     pub content: String,
     pub priority: Option<MessagePriority>,
     pub carrier_preference: Option<String>, // smart, metfone, cellcard
+    pub tags: Option<Vec<String>>,
+    pub ignore_quiet_hours: Option<bool>, // deliver even during quiet hours
+    #[validate(length(max = 2048, message = "Webhook URL is too long"))]
+    pub webhook_url: Option<String>, // https endpoint told of SLA breaches and final statuses
 }
 
 #[derive(Debug, Serialize)]
@@ -60,26 +54,102 @@ pub struct SendMessageResponse {
     pub job_id: String,
     pub status: String,
     pub estimated_delivery_time: String,
+    pub estimated_delivery_seconds: i64,
+    pub cost_estimate: f64, // In PPT tokens
+    pub cost_estimate_fiat: Option<FiatEquivalent>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct MessageQuoteRequest {
+    #[validate(length(min = 10, max = 15, message = "Invalid recipient phone number"))]
+    pub recipient: String,
+    #[validate(length(
+        min = 1,
+        max = 500,
+        message = "Message content must be 1-500 characters"
+    ))]
+    pub content: String,
+    pub priority: Option<MessagePriority>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MessageQuoteResponse {
+    pub carrier: String,
     pub cost_estimate: f64, // In PPT tokens
+    pub cost_estimate_fiat: Option<FiatEquivalent>,
+    pub surge_multiplier: f64, // the carrier's current demand surge, included in cost_estimate
+    pub estimated_delivery_time: String,
+    pub estimated_delivery_seconds: i64,
+    pub estimated_delivery_p90_seconds: i64,
+    pub model: String,
 }
 
 #[derive(Debug, Serialize)]
 pub struct MessageStatusResponse {
     pub message_id: String,
-    pub job_id: String,
+    pub job_id: Option<String>, // none once retention deleted the finished job
     pub status: String,
     pub provider_id: Option<String>,
     pub created_at: String,
     pub updated_at: String,
     pub delivery_attempts: u32,
     pub last_error: Option<String>,
+    pub tags: Vec<String>,
+    pub cancellation: Option<MessageCancellation>,
+}
+
+impl MessageStatusResponse {
+    fn new(message: Message, job: Option<Job>) -> Self {
+        let (job_id, delivery_attempts, last_error) = match job {
+            Some(job) => (Some(job.id), job.retry_count, job.error_message),
+            None => (None, 0, None),
+        };
+        Self {
+            message_id: message.id,
+            job_id,
+            status: format!("{:?}", message.status).to_lowercase(),
+            provider_id: message.provider_id,
+            created_at: message.created_at.to_rfc3339(),
+            updated_at: message.updated_at.to_rfc3339(),
+            delivery_attempts,
+            last_error,
+            tags: message.tags,
+            cancellation: message.cancellation,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
 pub struct MessageListQuery {
-    pub page: Option<u32>,
-    pub limit: Option<u32>,
+    pub cursor: Option<String>, // next_cursor of the previous page
+    pub limit: Option<i64>,
     pub status: Option<String>,
+    pub tags: Option<String>,      // comma-separated; messages must carry all
+    pub filter_id: Option<String>, // apply a saved filter preset
+    pub recipient: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct SavedFilterRequest {
+    #[validate(length(min = 1, max = 64, message = "Filter name must be 1-64 characters"))]
+    pub name: String,
+    pub status: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TagAnalyticsQuery {
+    pub days: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TagStatsEntry {
+    pub tag: String,
+    pub total_messages: u64,
+    pub delivered_messages: u64,
+    pub failed_messages: u64,
+    pub success_rate: f64,
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -88,6 +158,8 @@ pub struct DeliveryConfirmationRequest {
     pub delivery_time: Option<String>, // ISO 8601 timestamp
     pub error_message: Option<String>,
     pub provider_message_id: Option<String>,
+    pub signed_at: Option<i64>,    // unix seconds, when the device signed
+    pub signature: Option<String>, // base64, by the provider's device key
 }
 
 #[derive(Debug, Serialize)]
@@ -98,10 +170,32 @@ pub struct DeliveryConfirmationResponse {
     pub provider_earnings: Option<f64>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct CancellationAckRequest {
+    pub cancelled: bool, // false if the device had already sent the message
+}
+
+#[derive(Debug, Serialize)]
+pub struct CancellationResponse {
+    pub message_id: String,
+    pub status: String,
+    pub cancellation: Option<MessageCancellation>,
+}
+
+impl From<Message> for CancellationResponse {
+    fn from(message: Message) -> Self {
+        Self {
+            message_id: message.id,
+            status: format!("{:?}", message.status).to_lowercase(),
+            cancellation: message.cancellation,
+        }
+    }
+}
+
 /// Submit SMS job for delivery
 pub async fn send_message(
     State(app_state): State<Arc<AppState>>,
-    AuthenticatedUser(user_id): AuthenticatedUser,
+    ClientUser(user_id): ClientUser,
     JsonExtractor(send_request): JsonExtractor<SendMessageRequest>,
 ) -> Result<Json<SendMessageResponse>> {
     // Validate request
@@ -126,60 +220,103 @@ pub async fn send_message(
         });
     }
 
+    // While the backlog is over its limits, low priorities are rejected or deferred
+    let deferral = app_state.intake_backpressure.admit(&priority)?;
+
+    if let Some(webhook_url) = &send_request.webhook_url {
+        if !webhook_url.starts_with("https://") {
+            return Err(PeerPowerError::ValidationError {
+                field: "webhook_url".to_string(),
+                message: "Webhook URL must use https".to_string(),
+            });
+        }
+    }
+
     // Create message ID (remove if not needed)
 
+    let tags = Message::normalize_tags(send_request.tags.as_deref().unwrap_or_default())
+        .map_err(|message| PeerPowerError::ValidationError {
+            field: "tags".to_string(),
+            message,
+        })?;
+
     // Create message entity using constructor
-    let message = Message::new(
+    let mut message = Message::new(
         user_id.clone(),
         send_request.content.clone(),
         recipient.clone(),
         priority,
         None, // client_reference
-        None, // webhook_url
+        send_request.webhook_url.clone(),
+    );
+    message.tags = tags;
+    message.ignore_quiet_hours = send_request.ignore_quiet_hours.unwrap_or(false);
+    message.cohort = app_state.canary.assign(&message.id).await;
+
+    // Quote the ETA now so it can be checked against the actual delivery
+    let mut estimate = app_state
+        .delivery_predictor
+        .estimate(&message.recipient_carrier, &message.priority)
+        .await;
+    if let Some(delay) = deferral {
+        estimate.expected_seconds += delay.num_seconds();
+        estimate.p90_seconds += delay.num_seconds();
+    }
+    message.predicted_delivery_at = Some(estimate.expected_at(message.created_at));
+    message.predicted_delivery_p90_at =
+        Some(message.created_at + chrono::Duration::seconds(estimate.p90_seconds));
+
+    // Privacy-mode clients never have the raw recipient stored
+    let client = app_state.user_repository.find_by_id(&user_id).await?;
+    if client.as_ref().is_some_and(|client| client.recipient_privacy) {
+        app_state.recipient_vault.protect(&mut message)?;
+    }
+
+    // Priced now at the client's plan rate, so confirmation charges what was quoted
+    let rate = app_state
+        .pricing_plans
+        .rate_for(
+            &user_id,
+            client
+                .as_ref()
+                .and_then(|client| client.pricing_plan_id.as_deref()),
+        )
+        .await?;
+    message.segment_cost = Some(rate.segment_cost);
+    message.surge_multiplier = Some(
+        app_state
+            .surge_pricing
+            .multiplier(&message.recipient_carrier),
     );
 
     // For now, use a placeholder provider_id - in a real system this would be assigned by the job scheduler
     let placeholder_provider_id = "pending-assignment".to_string();
 
     // Create job entity using constructor
-    let job = Job::new(message.id.clone(), placeholder_provider_id);
-
-    // Store message and job in database
-    let messages_collection = app_state.database.collection::<Message>("messages");
-    let jobs_collection = app_state.database.collection::<Job>("jobs");
-
-    messages_collection
-        .insert_one(&message, None)
-        .await
-        .map_err(|e| PeerPowerError::Database {
-            message: format!("Failed to store message: {}", e),
-        })?;
-
-    jobs_collection
-        .insert_one(&job, None)
-        .await
-        .map_err(|e| PeerPowerError::Database {
-            message: format!("Failed to store job: {}", e),
-        })?;
-
-    // TODO: Add job to Redis queue for processing
-    let priority_score = message.get_priority_score();
-    let queue_key = format!("jobs:queue:priority:{}", priority_score);
-    let job_data = serde_json::to_string(&job).map_err(|e| PeerPowerError::Internal {
-        message: format!("Failed to serialize job: {}", e),
-    })?;
-
-    app_state
-        .redis
-        .lpush(&queue_key, &job_data)
-        .await
-        .map_err(|e| PeerPowerError::Database {
-            message: format!("Failed to queue job: {}", e),
-        })?;
+    let mut job = Job::new(message.id.clone(), placeholder_provider_id);
+    app_state.job_queue.assign_order(&mut job, &message).await?;
+
+    // Store message and job with an outbox entry, so the job reaches the
+    // queue even if the Redis push below fails
+    match deferral {
+        Some(delay) => app_state.job_outbox.defer(&message, &job, delay).await?,
+        None => app_state.job_outbox.submit(&message, &job).await?,
+    }
 
-    // Calculate estimated delivery time and cost
-    let estimated_delivery = chrono::Utc::now() + chrono::Duration::minutes(5);
-    let cost_estimate = calculate_message_cost(&send_request.content, &message.priority);
+    // Calculate cost
+    let cost_estimate = MessagePrice::new(
+        &app_state.config.pricing,
+        rate.segment_cost,
+        &send_request.content,
+        &message.priority,
+        pricing::message_type(&user_id),
+    )
+    .surged(message.surge_multiplier.unwrap_or(1.0))
+    .charge;
+    let cost_estimate_fiat = app_state
+        .exchange_rates
+        .fiat_equivalent(cost_estimate)
+        .await;
 
     info!(
         "Message {} queued successfully for user {}",
@@ -189,9 +326,67 @@ pub async fn send_message(
     Ok(Json(SendMessageResponse {
         message_id: message.id,
         job_id: job.id,
-        status: "queued".to_string(),
-        estimated_delivery_time: estimated_delivery.to_rfc3339(),
+        status: if deferral.is_some() {
+            "deferred".to_string()
+        } else {
+            "queued".to_string()
+        },
+        estimated_delivery_time: estimate.expected_at(message.created_at).to_rfc3339(),
+        estimated_delivery_seconds: estimate.expected_seconds,
         cost_estimate,
+        cost_estimate_fiat,
+    }))
+}
+
+/// Quote cost and expected delivery time without sending
+pub async fn quote_message(
+    State(app_state): State<Arc<AppState>>,
+    ClientUser(user_id): ClientUser,
+    JsonExtractor(quote_request): JsonExtractor<MessageQuoteRequest>,
+) -> Result<Json<MessageQuoteResponse>> {
+    quote_request.validate()?;
+
+    let recipient = PhoneNumber::new(quote_request.recipient)?;
+    let carrier = crate::shared::types::Carrier::from_phone_number(&recipient);
+    let priority = quote_request.priority.unwrap_or(MessagePriority::Normal);
+
+    let estimate = app_state
+        .delivery_predictor
+        .estimate(&carrier, &priority)
+        .await;
+    let client = app_state.user_repository.find_by_id(&user_id).await?;
+    let rate = app_state
+        .pricing_plans
+        .rate_for(
+            &user_id,
+            client
+                .as_ref()
+                .and_then(|client| client.pricing_plan_id.as_deref()),
+        )
+        .await?;
+    let surge_multiplier = app_state.surge_pricing.multiplier(&carrier);
+    let cost_estimate = MessagePrice::new(
+        &app_state.config.pricing,
+        rate.segment_cost,
+        &quote_request.content,
+        &priority,
+        pricing::message_type(&user_id),
+    )
+    .surged(surge_multiplier)
+    .charge;
+
+    Ok(Json(MessageQuoteResponse {
+        carrier: format!("{:?}", carrier).to_lowercase(),
+        cost_estimate,
+        cost_estimate_fiat: app_state
+            .exchange_rates
+            .fiat_equivalent(cost_estimate)
+            .await,
+        surge_multiplier,
+        estimated_delivery_time: estimate.expected_at(chrono::Utc::now()).to_rfc3339(),
+        estimated_delivery_seconds: estimate.expected_seconds,
+        estimated_delivery_p90_seconds: estimate.p90_seconds,
+        model: estimate.model,
     }))
 }
 
@@ -199,110 +394,357 @@ pub async fn send_message(
 pub async fn get_message_status(
     State(app_state): State<Arc<AppState>>,
     Path(message_id): Path<String>,
-    AuthenticatedUser(user_id): AuthenticatedUser,
+    ClientUser(user_id): ClientUser,
 ) -> Result<Json<MessageStatusResponse>> {
-    // Find message
-    let messages_collection = app_state.database.collection::<Message>("messages");
-    let message = messages_collection
-        .find_one(
-            mongodb::bson::doc! {
-                "id": &message_id,
-                "client_id": &user_id
-            },
-            None,
-        )
-        .await
-        .map_err(|e| PeerPowerError::Database {
-            message: format!("Failed to fetch message: {}", e),
-        })?
+    // Find message; another client's message is reported as missing
+    let message = app_state
+        .message_repository
+        .find_by_id(&message_id)
+        .await?
+        .filter(|message| message.client_id == user_id)
         .ok_or_else(|| PeerPowerError::NotFound {
             resource: format!("Message with ID: {}", message_id),
         })?;
 
-    // Find associated job
-    let jobs_collection = app_state.database.collection::<Job>("jobs");
-    let job = jobs_collection
-        .find_one(mongodb::bson::doc! {"message_id": &message_id}, None)
-        .await
-        .map_err(|e| PeerPowerError::Database {
-            message: format!("Failed to fetch job: {}", e),
-        })?
-        .ok_or_else(|| PeerPowerError::NotFound {
-            resource: format!("Job for message: {}", message_id),
-        })?;
+    // Find associated job, unless retention has deleted it
+    let job = app_state
+        .job_repository
+        .find_by_message_id(&message_id)
+        .await?;
 
-    Ok(Json(MessageStatusResponse {
-        message_id: message.id,
-        job_id: job.id,
-        status: format!("{:?}", message.status).to_lowercase(),
-        provider_id: message.provider_id,
-        created_at: message.created_at.to_rfc3339(),
-        updated_at: message.updated_at.to_rfc3339(),
-        delivery_attempts: job.retry_count,
-        last_error: job.error_message,
-    }))
+    Ok(Json(MessageStatusResponse::new(message, job)))
 }
 
-/// List user's messages with pagination
+/// List user's messages, newest first, a page at a time
 pub async fn list_messages(
     State(app_state): State<Arc<AppState>>,
     Query(params): Query<MessageListQuery>,
-    AuthenticatedUser(user_id): AuthenticatedUser,
-) -> Result<Json<Vec<MessageStatusResponse>>> {
-    let page = params.page.unwrap_or(1).max(1);
-    let limit = params.limit.unwrap_or(20).min(100).max(1);
-    let skip = (page - 1) * limit;
+    ClientUser(user_id): ClientUser,
+) -> Result<Json<PageResponse<MessageStatusResponse>>> {
+    let page = PageRequest::new(params.cursor.as_deref(), params.limit)?;
+
+    // Start from a saved preset if requested; explicit parameters take precedence
+    let (mut status, mut tags) = (None, Vec::new());
+    if let Some(filter_id) = &params.filter_id {
+        let preset = find_saved_filter(&app_state, &user_id, filter_id).await?;
+        status = preset.status;
+        tags = preset.tags;
+    }
+    if params.status.is_some() {
+        status = params.status;
+    }
+    if let Some(tag_list) = &params.tags {
+        tags = parse_tag_list(tag_list);
+    }
 
     // Build query filter
-    let mut filter = mongodb::bson::doc! {"client_id": &user_id};
-    if let Some(status) = params.status {
+    let mut filter = mongodb::bson::doc! {"client_id": &user_id, "deleted_at": null};
+    if let Some(status) = status {
         filter.insert("status", status);
     }
+    if !tags.is_empty() {
+        filter.insert("tags", mongodb::bson::doc! {"$all": tags});
+    }
+    if let Some(recipient) = params.recipient {
+        let recipient = PhoneNumber::new(recipient)?;
+        // Protected messages can only be matched through the salted hash
+        filter.insert(
+            "$or",
+            vec![
+                mongodb::bson::doc! {"recipient": field_encryption::phone_filter(&recipient)},
+                mongodb::bson::doc! {"recipient_hash": app_state.recipient_vault.hash(&recipient)},
+            ],
+        );
+    }
 
-    // Create find options with pagination and sorting
-    let mut find_options = mongodb::options::FindOptions::default();
-    find_options.skip = Some(skip as u64);
-    find_options.limit = Some(limit as i64);
-    find_options.sort = Some(mongodb::bson::doc! {"created_at": -1}); // Most recent first
-
-    // Find messages with pagination
-    let messages_collection = app_state.database.collection::<Message>("messages");
-    let mut cursor = messages_collection
-        .find(filter, find_options)
+    // One page of messages, each joined to its job in the same query
+    let pipeline = vec![
+        mongodb::bson::doc! {"$match": page.filter(filter)},
+        mongodb::bson::doc! {"$sort": page.sort()},
+        mongodb::bson::doc! {"$limit": page.fetch_limit()},
+        mongodb::bson::doc! {"$lookup": {
+            "from": "jobs",
+            "localField": "id",
+            "foreignField": "message_id",
+            "as": "job",
+        }},
+        mongodb::bson::doc! {"$set": {"job": {"$arrayElemAt": ["$job", 0]}}},
+    ];
+    let mut cursor = app_state
+        .database
+        .collection::<Message>("messages")
+        .aggregate(pipeline, None)
         .await
         .map_err(|e| PeerPowerError::Database {
             message: format!("Failed to fetch messages: {}", e),
         })?;
 
     let mut messages = Vec::new();
-    while let Ok(Some(message)) = cursor.try_next().await {
-        // Find associated job
-        let jobs_collection = app_state.database.collection::<Job>("jobs");
-        if let Ok(Some(job)) = jobs_collection
-            .find_one(mongodb::bson::doc! {"message_id": &message.id}, None)
-            .await
-        {
-            messages.push(MessageStatusResponse {
-                message_id: message.id,
-                job_id: job.id,
-                status: format!("{:?}", message.status).to_lowercase(),
-                provider_id: message.provider_id,
-                created_at: message.created_at.to_rfc3339(),
-                updated_at: message.updated_at.to_rfc3339(),
-                delivery_attempts: job.retry_count,
-                last_error: job.error_message,
-            });
-        }
+    while let Some(mut doc) = cursor
+        .try_next()
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch messages: {}", e),
+        })?
+    {
+        // Unset for a message whose finished job retention has deleted
+        let job = doc
+            .remove("job")
+            .map(mongodb::bson::from_bson::<Job>)
+            .transpose()
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to decode job: {}", e),
+            })?;
+        let message =
+            mongodb::bson::from_document::<Message>(doc).map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to decode message: {}", e),
+            })?;
+        messages.push((message, job));
     }
 
+    let messages = page
+        .page(messages, |(message, _)| {
+            Cursor::new(message.created_at, &message.id)
+        })
+        .map(|(message, job)| MessageStatusResponse::new(message, job));
     Ok(Json(messages))
 }
 
+/// Create a named message filter preset
+pub async fn create_saved_filter(
+    State(app_state): State<Arc<AppState>>,
+    ClientUser(user_id): ClientUser,
+    JsonExtractor(request): JsonExtractor<SavedFilterRequest>,
+) -> Result<Json<SavedFilter>> {
+    request.validate()?;
+
+    let tags = Message::normalize_tags(&request.tags).map_err(|message| {
+        PeerPowerError::ValidationError {
+            field: "tags".to_string(),
+            message,
+        }
+    })?;
+    let saved_filter = SavedFilter::new(user_id, request.name.trim().to_string(), request.status, tags);
+
+    app_state
+        .database
+        .collection::<SavedFilter>("saved_filters")
+        .insert_one(&saved_filter, None)
+        .await
+        .map_err(|e| {
+            if e.to_string().contains("duplicate key") {
+                PeerPowerError::ValidationError {
+                    field: "name".to_string(),
+                    message: "A filter with this name already exists".to_string(),
+                }
+            } else {
+                PeerPowerError::Database {
+                    message: format!("Failed to store saved filter: {}", e),
+                }
+            }
+        })?;
+
+    Ok(Json(saved_filter))
+}
+
+/// List the client's saved filter presets
+pub async fn list_saved_filters(
+    State(app_state): State<Arc<AppState>>,
+    ClientUser(user_id): ClientUser,
+) -> Result<Json<Vec<SavedFilter>>> {
+    let cursor = app_state
+        .database
+        .collection::<SavedFilter>("saved_filters")
+        .find(
+            mongodb::bson::doc! {"client_id": &user_id},
+            mongodb::options::FindOptions::builder()
+                .sort(mongodb::bson::doc! {"name": 1})
+                .build(),
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch saved filters: {}", e),
+        })?;
+
+    let filters = cursor
+        .try_collect()
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to read saved filters: {}", e),
+        })?;
+
+    Ok(Json(filters))
+}
+
+/// Get a single saved filter preset
+pub async fn get_saved_filter(
+    State(app_state): State<Arc<AppState>>,
+    Path(filter_id): Path<String>,
+    ClientUser(user_id): ClientUser,
+) -> Result<Json<SavedFilter>> {
+    Ok(Json(find_saved_filter(&app_state, &user_id, &filter_id).await?))
+}
+
+/// Delete a saved filter preset
+pub async fn delete_saved_filter(
+    State(app_state): State<Arc<AppState>>,
+    Path(filter_id): Path<String>,
+    ClientUser(user_id): ClientUser,
+) -> Result<StatusCode> {
+    let result = app_state
+        .database
+        .collection::<SavedFilter>("saved_filters")
+        .delete_one(
+            mongodb::bson::doc! {"id": &filter_id, "client_id": &user_id},
+            None,
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to delete saved filter: {}", e),
+        })?;
+
+    if result.deleted_count == 0 {
+        return Err(PeerPowerError::NotFound {
+            resource: format!("Saved filter with ID: {}", filter_id),
+        });
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Delivery stats per tag for the client's recent messages
+pub async fn get_tag_analytics(
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<TagAnalyticsQuery>,
+    ClientUser(user_id): ClientUser,
+) -> Result<Json<Vec<TagStatsEntry>>> {
+    let days = params.days.unwrap_or(30).clamp(1, 365);
+    let since = stored_timestamp(chrono::Utc::now() - chrono::Duration::days(days));
+
+    let pipeline = vec![
+        mongodb::bson::doc! {"$match": {"client_id": &user_id, "created_at": {"$gte": since}}},
+        mongodb::bson::doc! {"$unwind": "$tags"},
+        mongodb::bson::doc! {
+            "$group": {
+                "_id": "$tags",
+                "total": {"$sum": 1},
+                "delivered": {"$sum": {"$cond": [{"$eq": ["$status", "Delivered"]}, 1, 0]}},
+                "failed": {"$sum": {"$cond": [{"$eq": ["$status", "Failed"]}, 1, 0]}},
+            }
+        },
+        mongodb::bson::doc! {"$sort": {"total": -1}},
+    ];
+
+    let mut cursor = app_state
+        .database
+        .collection::<mongodb::bson::Document>("messages")
+        .aggregate(pipeline, None)
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to aggregate tag analytics: {}", e),
+        })?;
+
+    let count = |doc: &mongodb::bson::Document, field: &str| {
+        doc.get_i64(field)
+            .or_else(|_| doc.get_i32(field).map(i64::from))
+            .unwrap_or(0)
+            .max(0) as u64
+    };
+
+    let mut entries = Vec::new();
+    while let Some(doc) = cursor
+        .try_next()
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to read tag analytics: {}", e),
+        })?
+    {
+        let total_messages = count(&doc, "total");
+        let delivered_messages = count(&doc, "delivered");
+        entries.push(TagStatsEntry {
+            tag: doc.get_str("_id").unwrap_or_default().to_string(),
+            total_messages,
+            delivered_messages,
+            failed_messages: count(&doc, "failed"),
+            success_rate: if total_messages > 0 {
+                (delivered_messages as f64 / total_messages as f64) * 100.0
+            } else {
+                0.0
+            },
+        });
+    }
+
+    Ok(Json(entries))
+}
+
+async fn find_saved_filter(app_state: &AppState, user_id: &str, filter_id: &str) -> Result<SavedFilter> {
+    app_state
+        .database
+        .collection::<SavedFilter>("saved_filters")
+        .find_one(
+            mongodb::bson::doc! {"id": filter_id, "client_id": user_id},
+            None,
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch saved filter: {}", e),
+        })?
+        .ok_or_else(|| PeerPowerError::NotFound {
+            resource: format!("Saved filter with ID: {}", filter_id),
+        })
+}
+
+/// Split a comma-separated tag query into normalized tags
+fn parse_tag_list(tags: &str) -> Vec<String> {
+    tags.split(',')
+        .map(|tag| tag.trim().to_lowercase())
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QualityScoreQuery {
+    pub days: Option<i64>,
+}
+
+/// Daily service quality scores for the authenticated client
+pub async fn get_quality_scores(
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<QualityScoreQuery>,
+    ClientUser(user_id): ClientUser,
+) -> Result<Json<Vec<ClientQualityScore>>> {
+    let days = params.days.unwrap_or(30).clamp(1, 365);
+
+    let cursor = app_state
+        .database
+        .collection::<ClientQualityScore>("quality_scores")
+        .find(
+            mongodb::bson::doc! {"client_id": &user_id},
+            mongodb::options::FindOptions::builder()
+                .sort(mongodb::bson::doc! {"date": -1})
+                .limit(days)
+                .build(),
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch quality scores: {}", e),
+        })?;
+
+    let scores = cursor
+        .try_collect()
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to read quality scores: {}", e),
+        })?;
+
+    Ok(Json(scores))
+}
+
 /// Confirm message delivery (called by providers)
 pub async fn confirm_delivery(
     State(app_state): State<Arc<AppState>>,
     Path(message_id): Path<String>,
-    AuthenticatedUser(user_id): AuthenticatedUser,
+    ProviderUser(user_id): ProviderUser,
     JsonExtractor(delivery_request): JsonExtractor<DeliveryConfirmationRequest>,
 ) -> Result<Json<DeliveryConfirmationResponse>> {
     delivery_request.validate()?;
@@ -310,13 +752,10 @@ pub async fn confirm_delivery(
     info!("Delivery confirmation for message {} from user {}", message_id, user_id);
 
     // Find the message and verify the user is the assigned provider
-    let messages_collection = app_state.database.collection::<Message>("messages");
-    let mut message = messages_collection
-        .find_one(mongodb::bson::doc! {"id": &message_id}, None)
-        .await
-        .map_err(|e| PeerPowerError::Database {
-            message: format!("Failed to fetch message: {}", e),
-        })?
+    let mut message = app_state
+        .message_repository
+        .find_by_id(&message_id)
+        .await?
         .ok_or_else(|| PeerPowerError::NotFound {
             resource: format!("Message with ID: {}", message_id),
         })?;
@@ -341,6 +780,19 @@ pub async fn confirm_delivery(
         });
     }
 
+    // The access token alone isn't enough; the device that holds the key must have signed it
+    app_state.device_keys.verify(
+        provider.device_key.as_ref(),
+        &SignedConfirmation {
+            message_id: &message_id,
+            status: &delivery_request.status,
+            delivery_time: delivery_request.delivery_time.as_deref(),
+            provider_message_id: delivery_request.provider_message_id.as_deref(),
+            signed_at: delivery_request.signed_at,
+            signature: delivery_request.signature.as_deref(),
+        },
+    )?;
+
     // Update message status based on delivery confirmation
     let new_status = match delivery_request.status.as_str() {
         "delivered" => crate::shared::types::MessageStatus::Delivered,
@@ -352,115 +804,279 @@ pub async fn confirm_delivery(
         }),
     };
 
-    message.status = new_status;
-    message.updated_at = chrono::Utc::now();
+    // A final report answers a pending cancellation: the device had sent it
+    let previous = message.status.clone();
+    if message.is_cancel_pending() && new_status != MessageStatus::Sent {
+        message.resolve_cancellation(CancellationStatus::AlreadySent)?;
+    }
+    message.transition_to(new_status)?;
 
-    // Update the message in database
-    messages_collection
-        .update_one(
-            mongodb::bson::doc! {"id": &message_id},
-            mongodb::bson::doc! {
-                "$set": {
-                    "status": format!("{:?}", message.status),
-                    "updated_at": message.updated_at
-                }
-            },
-            None,
+    // Self-test loopback messages feed onboarding health, not earnings
+    let self_test = message.client_id == SELF_TEST_CLIENT_ID;
+    let earnings = if message.status == MessageStatus::Delivered && !self_test {
+        Some(delivery_earnings(&app_state, &message, &provider.id))
+    } else {
+        None
+    };
+
+    // The message, its job, the provider's stats and a delivery's charge,
+    // earning and fee, all or nothing
+    let posted = match app_state
+        .delivery_confirmations
+        .record(
+            &message,
+            &previous,
+            delivery_request.error_message.clone(),
+            earnings.as_ref(),
         )
-        .await
-        .map_err(|e| PeerPowerError::Database {
-            message: format!("Failed to update message: {}", e),
-        })?;
+        .await?
+    {
+        Recorded::Saved { posted } => posted,
+        Recorded::Stale => {
+            return Err(PeerPowerError::ValidationError {
+                field: "message".to_string(),
+                message: "The message changed while being confirmed; try again".to_string(),
+            })
+        }
+    };
+    if message.status == MessageStatus::Delivered {
+        app_state.delivery_predictor.record_outcome(&message);
+    }
 
-    // Update the associated job
-    let jobs_collection = app_state.database.collection::<Job>("jobs");
-    let job_update = if delivery_request.status == "delivered" {
-        mongodb::bson::doc! {
-            "$set": {
-                "status": "completed",
-                "completed_at": chrono::Utc::now(),
-                "updated_at": chrono::Utc::now()
-            }
+    // Per-cohort delivery outcomes for canary comparison
+    match message.status {
+        MessageStatus::Delivered => CanaryRouter::record_outcome(message.cohort, "delivered"),
+        MessageStatus::Failed => CanaryRouter::record_outcome(message.cohort, "failed"),
+        _ => {}
+    }
+    if matches!(message.status, MessageStatus::Delivered | MessageStatus::Failed) {
+        if let Some(seconds) = message.confirmation_seconds(message.updated_at) {
+            metrics::histogram!(
+                "job_confirmation_latency_seconds",
+                "status" => delivery_request.status.clone()
+            )
+            .record(seconds);
         }
-    } else {
-        mongodb::bson::doc! {
-            "$set": {
-                "status": "failed",
-                "error_message": delivery_request.error_message.unwrap_or("Delivery failed".to_string()),
-                "updated_at": chrono::Utc::now()
+    }
+
+    if self_test {
+        if delivery_request.status != "pending" {
+            record_self_test_result(
+                &app_state,
+                &provider.id,
+                &message_id,
+                delivery_request.status == "delivered",
+                delivery_request.error_message.clone(),
+            )
+            .await?;
+        }
+
+        return Ok(Json(DeliveryConfirmationResponse {
+            message_id: message.id,
+            status: format!("{:?}", message.status).to_lowercase(),
+            updated_at: message.updated_at.to_rfc3339(),
+            provider_earnings: None,
+        }));
+    }
+
+    if posted {
+        // Counts toward referral bonuses for both the sender and the provider
+        for user_id in [&message.client_id, &provider.user_id] {
+            if let Err(e) = app_state.referrals.record_delivery(user_id).await {
+                warn!("Failed to count referral delivery for {}: {}", user_id, e);
             }
         }
-    };
+    }
 
-    jobs_collection
-        .update_one(
-            mongodb::bson::doc! {"message_id": &message_id},
-            job_update,
-            None,
+    info!("Message {} delivery confirmed with status: {}", message_id, delivery_request.status);
+
+    Ok(Json(DeliveryConfirmationResponse {
+        message_id: message.id,
+        status: format!("{:?}", message.status).to_lowercase(),
+        updated_at: message.updated_at.to_rfc3339(),
+        provider_earnings: earnings.map(|earnings| earnings.event.amount.to_ppt()),
+    }))
+}
+
+/// The charge, earning and fee a delivered message posts to the ledger, and
+/// its earnings event; earnings are boosted during off-peak hours
+fn delivery_earnings(
+    app_state: &AppState,
+    message: &Message,
+    provider_id: &str,
+) -> DeliveryEarnings {
+    let price = MessagePrice::new(
+        &app_state.config.pricing,
+        message
+            .segment_cost
+            .unwrap_or_else(|| Money::from_ppt(app_state.config.pricing.base_message_cost)),
+        &message.content,
+        &message.priority,
+        pricing::message_type(&message.client_id),
+    )
+    .surged(message.surge_multiplier.unwrap_or(1.0));
+    let time_of_day_multiplier = app_state
+        .config
+        .earnings
+        .time_of_day_multiplier(message.updated_at);
+    let earnings = Money::from_ppt(price.provider_earnings).scale(time_of_day_multiplier);
+    let off_peak_bonus = earnings - Money::from_ppt(price.provider_earnings);
+
+    let mut event = EarningsEvent::new(
+        message.id.clone(),
+        provider_id.to_string(),
+        message.client_id.clone(),
+        earnings,
+        off_peak_bonus,
+        message.priority.clone(),
+        app_state.config.earnings.local_date(message.updated_at),
+    );
+    event.confirmation_seconds = message.confirmation_seconds(message.updated_at);
+    DeliveryEarnings {
+        transaction: LedgerTransaction::message_delivery(
+            &message.id,
+            &message.client_id,
+            provider_id,
+            Money::from_ppt(price.charge),
+            earnings,
+        ),
+        event,
+    }
+}
+
+/// Cancel a message. Messages not yet dispatched are cancelled at once;
+/// for one already on a provider's device the cancellation is `requested`
+/// until the device confirms it dropped it (or doesn't answer in time), and
+/// it is only charged if the device reports it had already been sent.
+pub async fn cancel_message(
+    State(app_state): State<Arc<AppState>>,
+    Path(message_id): Path<String>,
+    ClientUser(user_id): ClientUser,
+    client: ClientInfo,
+) -> Result<Json<CancellationResponse>> {
+    let message = app_state
+        .cancellations
+        .cancel(&user_id, &message_id)
+        .await?;
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(Some(user_id), "message.cancelled", "message", &message_id)
+                .with_client(client.ip, client.user_agent)
+                .with_metadata("status", format!("{:?}", message.status).to_lowercase()),
         )
+        .await;
+
+    Ok(Json(CancellationResponse::from(message)))
+}
+
+/// A provider device acknowledging it received an `sms_dispatch`; without
+/// this before the deadline, the job is dispatched again
+pub async fn acknowledge_dispatch(
+    State(app_state): State<Arc<AppState>>,
+    Path(message_id): Path<String>,
+    ProviderUser(user_id): ProviderUser,
+) -> Result<StatusCode> {
+    let provider = app_state
+        .database
+        .collection::<crate::domain::entities::Provider>("providers")
+        .find_one(mongodb::bson::doc! {"user_id": &user_id}, None)
         .await
         .map_err(|e| PeerPowerError::Database {
-            message: format!("Failed to update job: {}", e),
+            message: format!("Failed to fetch provider: {}", e),
+        })?
+        .ok_or_else(|| PeerPowerError::NotFound {
+            resource: format!("Provider for user: {}", user_id),
         })?;
 
-    // Calculate provider earnings if delivered
-    let provider_earnings = if delivery_request.status == "delivered" {
-        Some(calculate_provider_earnings(&message.content, &message.priority))
-    } else {
-        None
-    };
+    let mut job = app_state
+        .job_repository
+        .find_by_message_id(&message_id)
+        .await?
+        .filter(|job| job.provider_id == provider.id)
+        .ok_or_else(|| PeerPowerError::NotFound {
+            resource: format!("Dispatch of message: {}", message_id),
+        })?;
 
-    // If delivered, update provider stats and earnings
-    if delivery_request.status == "delivered" {
-        let earnings = provider_earnings.unwrap_or(0.0);
-        providers_collection
-            .update_one(
-                mongodb::bson::doc! {"id": &provider.id},
+    // Repeated acknowledgments, or ones after a confirmation, change nothing;
+    // the replace only lands while the job is still dispatched
+    if matches!(job.status, JobStatus::Dispatched) {
+        job.acknowledge();
+        app_state
+            .database
+            .collection::<Job>("jobs")
+            .replace_one(
                 mongodb::bson::doc! {
-                    "$inc": {
-                        "total_messages_delivered": 1,
-                        "earnings_total": earnings
-                    },
-                    "$set": {
-                        "updated_at": chrono::Utc::now()
-                    }
+                    "id": &job.id,
+                    "status": format!("{:?}", JobStatus::Dispatched),
                 },
+                &job,
                 None,
             )
             .await
             .map_err(|e| PeerPowerError::Database {
-                message: format!("Failed to update provider stats: {}", e),
+                message: format!("Failed to update job: {}", e),
             })?;
     }
+    Ok(StatusCode::NO_CONTENT)
+}
 
-    info!("Message {} delivery confirmed with status: {}", message_id, delivery_request.status);
+/// A provider device's answer to a `cancel_dispatch` FCM message
+pub async fn acknowledge_cancellation(
+    State(app_state): State<Arc<AppState>>,
+    Path(message_id): Path<String>,
+    ProviderUser(user_id): ProviderUser,
+    JsonExtractor(request): JsonExtractor<CancellationAckRequest>,
+) -> Result<Json<CancellationResponse>> {
+    let provider = app_state
+        .database
+        .collection::<crate::domain::entities::Provider>("providers")
+        .find_one(mongodb::bson::doc! {"user_id": &user_id}, None)
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch provider: {}", e),
+        })?
+        .ok_or_else(|| PeerPowerError::NotFound {
+            resource: format!("Provider for user: {}", user_id),
+        })?;
 
-    Ok(Json(DeliveryConfirmationResponse {
-        message_id: message.id,
-        status: format!("{:?}", message.status).to_lowercase(),
-        updated_at: message.updated_at.to_rfc3339(),
-        provider_earnings,
-    }))
+    let message = app_state
+        .cancellations
+        .acknowledge(&provider.id, &message_id, request.cancelled)
+        .await?;
+    Ok(Json(CancellationResponse::from(message)))
 }
 
 /// Webhook endpoint for external delivery confirmations
 pub async fn delivery_webhook(
     State(app_state): State<Arc<AppState>>,
     Path(message_id): Path<String>,
-    JsonExtractor(delivery_request): JsonExtractor<DeliveryConfirmationRequest>,
+    headers: HeaderMap,
+    body: Bytes,
 ) -> Result<Json<serde_json::Value>> {
+    // The signature covers the raw body, so verify before parsing it
+    let integration = app_state
+        .webhook_verifier
+        .verify(&headers, &message_id, &body)
+        .await?;
+
+    let delivery_request: DeliveryConfirmationRequest =
+        serde_json::from_slice(&body).map_err(|e| PeerPowerError::InvalidPayload {
+            message: format!("Invalid delivery report: {}", e),
+        })?;
     delivery_request.validate()?;
 
-    info!("Webhook delivery confirmation for message {}", message_id);
+    info!(
+        "Webhook delivery confirmation for message {} from {}",
+        message_id, integration
+    );
 
     // Find the message
-    let messages_collection = app_state.database.collection::<Message>("messages");
-    let mut message = messages_collection
-        .find_one(mongodb::bson::doc! {"id": &message_id}, None)
-        .await
-        .map_err(|e| PeerPowerError::Database {
-            message: format!("Failed to fetch message: {}", e),
-        })?
+    let mut message = app_state
+        .message_repository
+        .find_by_id(&message_id)
+        .await?
         .ok_or_else(|| PeerPowerError::NotFound {
             resource: format!("Message with ID: {}", message_id),
         })?;
@@ -476,25 +1092,16 @@ pub async fn delivery_webhook(
         }),
     };
 
-    message.status = new_status;
-    message.updated_at = chrono::Utc::now();
+    message.transition_to(new_status)?;
+    if message.status == MessageStatus::Delivered {
+        app_state.delivery_predictor.record_outcome(&message);
+    }
 
     // Update the message in database
-    messages_collection
-        .update_one(
-            mongodb::bson::doc! {"id": &message_id},
-            mongodb::bson::doc! {
-                "$set": {
-                    "status": format!("{:?}", message.status),
-                    "updated_at": message.updated_at
-                }
-            },
-            None,
-        )
-        .await
-        .map_err(|e| PeerPowerError::Database {
-            message: format!("Failed to update message: {}", e),
-        })?;
+    app_state
+        .message_repository
+        .update_status(&message_id, message.status)
+        .await?;
 
     info!("Webhook processed successfully for message {}", message_id);
 
@@ -504,33 +1111,3 @@ pub async fn delivery_webhook(
         "timestamp": chrono::Utc::now().to_rfc3339()
     })))
 }
-
-/// Calculate message cost based on content length and priority
-fn calculate_message_cost(content: &str, priority: &MessagePriority) -> f64 {
-    let base_cost = 0.01; // Base cost in PPT tokens
-    let length_multiplier = (content.len() as f64 / 160.0).ceil(); // SMS is typically 160 chars
-
-    let priority_multiplier = match priority {
-        MessagePriority::Low => 0.8,
-        MessagePriority::Normal => 1.0,
-        MessagePriority::High => 1.5,
-        MessagePriority::Urgent => 2.0,
-    };
-
-    base_cost * length_multiplier * priority_multiplier
-}
-
-/// Calculate provider earnings for a delivered message
-fn calculate_provider_earnings(content: &str, priority: &MessagePriority) -> f64 {
-    let base_earnings = 0.008; // 80% of base cost goes to provider
-    let length_multiplier = (content.len() as f64 / 160.0).ceil();
-
-    let priority_multiplier = match priority {
-        MessagePriority::Low => 0.8,
-        MessagePriority::Normal => 1.0,
-        MessagePriority::High => 1.5,
-        MessagePriority::Urgent => 2.0,
-    };
-
-    base_earnings * length_multiplier * priority_multiplier
-}
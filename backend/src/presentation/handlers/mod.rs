@@ -1,13 +1,35 @@
 pub mod admin_handlers;
 pub mod auth_handlers;
+pub mod debug_handlers;
+pub mod dispute_handlers;
+pub mod download_handlers;
 pub mod earnings_handlers;
+pub mod identity_handlers;
+pub mod job_handlers;
+pub mod ledger_handlers;
 pub mod message_handlers;
+pub mod number_handlers;
+pub mod payment_handlers;
+pub mod payout_handlers;
+pub mod pricing_handlers;
 pub mod provider_handlers;
+pub mod referral_handlers;
 pub mod user_handlers;
 
 pub use admin_handlers::*;
 pub use auth_handlers::*;
+pub use debug_handlers::*;
+pub use dispute_handlers::*;
+pub use download_handlers::*;
 pub use earnings_handlers::*;
+pub use identity_handlers::*;
+pub use job_handlers::*;
+pub use ledger_handlers::*;
 pub use message_handlers::*;
+pub use number_handlers::*;
+pub use payment_handlers::*;
+pub use payout_handlers::*;
+pub use pricing_handlers::*;
 pub use provider_handlers::*;
+pub use referral_handlers::*;
 pub use user_handlers::*;
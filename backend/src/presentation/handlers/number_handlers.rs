@@ -0,0 +1,328 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::Json,
+    Json as JsonExtractor,
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::info;
+use validator::Validate;
+
+use crate::domain::entities::number_pool::month_bounds;
+use crate::domain::entities::{
+    AuditLogEntry, DedicatedNumber, InboundMessage, NumberRentalCharge, Provider,
+};
+use crate::infrastructure::NumberPoolCapacity;
+use crate::presentation::middleware::{AdminUser, ClientInfo, ClientUser, ProviderUser};
+use crate::shared::types::PhoneNumber;
+use crate::shared::{AppState, PeerPowerError, Result};
+
+#[derive(Debug, Deserialize)]
+pub struct InboundListQuery {
+    pub page: Option<u32>,
+    pub limit: Option<u32>,
+    pub number_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct InboundMessageRequest {
+    #[validate(length(min = 3, max = 20, message = "Invalid sender number"))]
+    pub from: String,
+    #[validate(length(min = 1, max = 1600, message = "Content must be 1-1600 characters"))]
+    pub content: String,
+    pub received_at: Option<DateTime<Utc>>, // when the device received it; defaults to now
+}
+
+#[derive(Debug, Serialize)]
+pub struct InboundMessageResponse {
+    pub inbound_id: String,
+    pub in_reply_to: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct AssignNumberRequest {
+    #[validate(length(min = 1, message = "Client ID is required"))]
+    pub client_id: String,
+    #[validate(length(min = 1, message = "Provider ID is required"))]
+    pub provider_id: String,
+    pub daily_capacity: Option<u32>, // defaults to the provider's daily limit
+    pub monthly_rent: Option<f64>,   // defaults to NUMBER_RENTAL_MONTHLY_FEE
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DedicatedNumberListQuery {
+    pub client_id: Option<String>,
+    #[serde(default)]
+    pub include_released: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NumberRentalQuery {
+    pub period: Option<String>, // "YYYY-MM"
+    pub client_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NumberBillingRequest {
+    pub period: String, // "YYYY-MM"
+}
+
+#[derive(Debug, Serialize)]
+pub struct NumberBillingResponse {
+    pub period: String,
+    pub charges_created: usize,
+    pub amount_billed: f64,
+    pub charges: Vec<NumberRentalCharge>,
+}
+
+/// The caller's dedicated numbers and today's remaining capacity
+pub async fn get_my_numbers(
+    State(app_state): State<Arc<AppState>>,
+    ClientUser(user_id): ClientUser,
+) -> Result<Json<NumberPoolCapacity>> {
+    let capacity = app_state.number_pool.capacity(&user_id).await?;
+    Ok(Json(capacity))
+}
+
+/// Replies received on the caller's dedicated numbers
+pub async fn list_inbound_messages(
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<InboundListQuery>,
+    ClientUser(user_id): ClientUser,
+) -> Result<Json<Vec<InboundMessage>>> {
+    let page = params.page.unwrap_or(1).max(1);
+    let limit = params.limit.unwrap_or(20).clamp(1, 100);
+
+    let messages = app_state
+        .number_pool
+        .list_inbound(&user_id, params.number_id.as_deref(), page, limit)
+        .await?;
+    Ok(Json(messages))
+}
+
+/// Report an SMS received on a provider's SIM that is a dedicated number
+pub async fn report_inbound_message(
+    State(app_state): State<Arc<AppState>>,
+    Path(provider_id): Path<String>,
+    ProviderUser(user_id): ProviderUser,
+    JsonExtractor(request): JsonExtractor<InboundMessageRequest>,
+) -> Result<Json<InboundMessageResponse>> {
+    request.validate()?;
+
+    let provider = app_state
+        .database
+        .collection::<Provider>("providers")
+        .find_one(
+            mongodb::bson::doc! {
+                "id": &provider_id,
+                "user_id": &user_id
+            },
+            None,
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch provider: {}", e),
+        })?
+        .ok_or_else(|| PeerPowerError::NotFound {
+            resource: format!("Provider with ID: {}", provider_id),
+        })?;
+
+    let number = app_state
+        .number_pool
+        .find_active_by_provider(&provider.id)
+        .await?
+        .ok_or_else(|| PeerPowerError::ValidationError {
+            field: "provider_id".to_string(),
+            message: "Provider is not a dedicated number".to_string(),
+        })?;
+
+    // Privacy-mode clients get the sender masked, matched by hash like recipients
+    let mut from = PhoneNumber::new(request.from)?;
+    let mut from_hash = None;
+    let client = app_state
+        .user_repository
+        .find_by_id(&number.client_id)
+        .await?;
+    if client.is_some_and(|client| client.recipient_privacy)
+        && app_state.recipient_vault.is_configured()
+    {
+        from_hash = Some(app_state.recipient_vault.hash(&from));
+        from = from.masked();
+    }
+
+    let inbound = app_state
+        .number_pool
+        .record_inbound(
+            &number,
+            from,
+            from_hash,
+            request.content,
+            request
+                .received_at
+                .unwrap_or_else(crate::shared::utils::now),
+        )
+        .await?;
+
+    info!(
+        "Inbound message {} received on dedicated number {} for client {}",
+        inbound.id, number.id, number.client_id
+    );
+
+    Ok(Json(InboundMessageResponse {
+        inbound_id: inbound.id,
+        in_reply_to: inbound.in_reply_to,
+    }))
+}
+
+/// Assign a provider's SIM to a client as a dedicated number (admin only)
+pub async fn assign_dedicated_number(
+    State(app_state): State<Arc<AppState>>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+    axum::Json(request): axum::Json<AssignNumberRequest>,
+) -> Result<Json<DedicatedNumber>> {
+    request.validate()?;
+
+    app_state
+        .user_repository
+        .find_by_id(&request.client_id)
+        .await?
+        .ok_or_else(|| PeerPowerError::NotFound {
+            resource: format!("Client: {}", request.client_id),
+        })?;
+
+    let number = app_state
+        .number_pool
+        .assign(
+            &request.client_id,
+            &request.provider_id,
+            request.daily_capacity,
+            request.monthly_rent,
+            &user_id,
+        )
+        .await?;
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(
+                Some(user_id),
+                "dedicated_number.assigned",
+                "dedicated_number",
+                &number.id,
+            )
+            .with_client(client.ip, client.user_agent)
+            .with_metadata("client_id", number.client_id.clone())
+            .with_metadata("provider_id", number.provider_id.clone())
+            .with_metadata("daily_capacity", number.daily_capacity.to_string())
+            .with_metadata("monthly_rent", number.monthly_rent.to_string()),
+        )
+        .await;
+
+    Ok(Json(number))
+}
+
+/// List dedicated numbers (admin only)
+pub async fn list_dedicated_numbers(
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<DedicatedNumberListQuery>,
+    AdminUser(_user_id): AdminUser,
+) -> Result<Json<Vec<DedicatedNumber>>> {
+    let numbers = app_state
+        .number_pool
+        .list(params.client_id.as_deref(), params.include_released)
+        .await?;
+    Ok(Json(numbers))
+}
+
+/// Return a dedicated number's SIM to the shared pool (admin only)
+pub async fn release_dedicated_number(
+    State(app_state): State<Arc<AppState>>,
+    Path(number_id): Path<String>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+) -> Result<Json<DedicatedNumber>> {
+    let number = app_state.number_pool.release(&number_id).await?;
+
+    info!(
+        "Dedicated number {} released from client {}",
+        number.id, number.client_id
+    );
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(
+                Some(user_id),
+                "dedicated_number.released",
+                "dedicated_number",
+                &number.id,
+            )
+            .with_client(client.ip, client.user_agent)
+            .with_metadata("client_id", number.client_id.clone())
+            .with_metadata("provider_id", number.provider_id.clone()),
+        )
+        .await;
+
+    Ok(Json(number))
+}
+
+/// Rental charges for dedicated numbers (admin only)
+pub async fn list_number_rentals(
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<NumberRentalQuery>,
+    AdminUser(_user_id): AdminUser,
+) -> Result<Json<Vec<NumberRentalCharge>>> {
+    let charges = app_state
+        .number_pool
+        .rentals(params.period.as_deref(), params.client_id.as_deref())
+        .await?;
+    Ok(Json(charges))
+}
+
+/// Bill a month's rentals now instead of waiting for the daily rollup (admin only)
+pub async fn run_number_billing(
+    State(app_state): State<Arc<AppState>>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+    axum::Json(request): axum::Json<NumberBillingRequest>,
+) -> Result<Json<NumberBillingResponse>> {
+    let month = NaiveDate::parse_from_str(&format!("{}-01", request.period.trim()), "%Y-%m-%d")
+        .map_err(|_| PeerPowerError::ValidationError {
+            field: "period".to_string(),
+            message: "Expected a month as YYYY-MM".to_string(),
+        })?;
+    // Charges are final once written, so the month has to be over
+    if month_bounds(month).1 >= crate::shared::utils::now().date_naive() {
+        return Err(PeerPowerError::ValidationError {
+            field: "period".to_string(),
+            message: "Only completed months can be billed".to_string(),
+        });
+    }
+
+    let charges = app_state.number_pool.bill_month(month).await?;
+    let period = month.format("%Y-%m").to_string();
+    let amount_billed = charges.iter().map(|charge| charge.amount).sum();
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(
+                Some(user_id),
+                "number_rentals.billed",
+                "number_rentals",
+                &period,
+            )
+            .with_client(client.ip, client.user_agent)
+            .with_metadata("charges_created", charges.len().to_string()),
+        )
+        .await;
+
+    Ok(Json(NumberBillingResponse {
+        period,
+        charges_created: charges.len(),
+        amount_billed,
+        charges,
+    }))
+}
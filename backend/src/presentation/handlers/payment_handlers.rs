@@ -0,0 +1,357 @@
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+    Json as JsonExtractor,
+};
+use futures::stream::TryStreamExt;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{error, info};
+
+use crate::domain::entities::{AuditLogEntry, LedgerAccountKind, PayoutCurrency, TopUp};
+use crate::infrastructure::payments::{BarayDisbursementData, BarayEvent, FxRate};
+use crate::presentation::middleware::{AdminUser, ClientInfo, ClientUser};
+use crate::shared::{AppState, PeerPowerError, Result};
+
+const MIN_TOPUP_PPT: f64 = 1.0;
+const MAX_TOPUP_PPT: f64 = 100_000.0;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateTopUpRequest {
+    pub amount: f64, // in `currency`
+    pub currency: PayoutCurrency,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExchangeRateOverrideRequest {
+    pub rate: f64, // `currency` per PPT
+}
+
+#[derive(Debug, Serialize)]
+pub struct TopUpResponse {
+    pub id: String,
+    pub status: String,
+    pub amount: f64,
+    pub currency: String,
+    pub credit_ppt: f64,
+    pub fx_rate: f64,
+    pub checkout_url: Option<String>,
+    pub expires_at: Option<String>,
+    pub failure_reason: Option<String>,
+    pub created_at: String,
+    pub completed_at: Option<String>,
+}
+
+impl From<&TopUp> for TopUpResponse {
+    fn from(topup: &TopUp) -> Self {
+        Self {
+            id: topup.id.clone(),
+            status: format!("{:?}", topup.status),
+            amount: topup.amount,
+            currency: topup.currency.as_str().to_string(),
+            credit_ppt: topup.credit_ppt.to_ppt(),
+            fx_rate: topup.rate,
+            checkout_url: topup.checkout_url.clone(),
+            expires_at: topup.expires_at.map(|at| at.to_rfc3339()),
+            failure_reason: topup.failure_reason.clone(),
+            created_at: topup.created_at.to_rfc3339(),
+            completed_at: topup.completed_at.map(|at| at.to_rfc3339()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct WalletResponse {
+    pub balance: f64,
+    pub currency: &'static str,
+    pub total_topped_up: f64,
+    pub total_spent: f64,
+}
+
+/// Start a top-up: returns the Baray checkout URL to send the client to
+pub async fn create_topup(
+    State(app_state): State<Arc<AppState>>,
+    ClientUser(user_id): ClientUser,
+    client: ClientInfo,
+    JsonExtractor(request): JsonExtractor<CreateTopUpRequest>,
+) -> Result<(StatusCode, Json<TopUpResponse>)> {
+    if request.currency == PayoutCurrency::Ppt {
+        return Err(PeerPowerError::ValidationError {
+            field: "currency".to_string(),
+            message: "Top-ups are paid in KHR or USD".to_string(),
+        });
+    }
+    let rate = app_state.exchange_rates.ppt_rate(request.currency).await?;
+    let credit = request.amount / rate.rate;
+    if !request.amount.is_finite() || !(MIN_TOPUP_PPT..=MAX_TOPUP_PPT).contains(&credit) {
+        return Err(PeerPowerError::ValidationError {
+            field: "amount".to_string(),
+            message: format!(
+                "Top-ups must buy between {} and {} PPT ({} to {} {})",
+                MIN_TOPUP_PPT,
+                MAX_TOPUP_PPT,
+                MIN_TOPUP_PPT * rate.rate,
+                MAX_TOPUP_PPT * rate.rate,
+                request.currency.as_str()
+            ),
+        });
+    }
+
+    let topup = app_state
+        .topups
+        .create(&user_id, request.amount, request.currency)
+        .await?;
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(Some(user_id), "payment.topup_created", "topup", &topup.id)
+                .with_client(client.ip, client.user_agent)
+                .with_metadata("amount", topup.amount.to_string())
+                .with_metadata("currency", topup.currency.as_str())
+                .with_metadata("credit_ppt", topup.credit_ppt.to_string()),
+        )
+        .await;
+
+    Ok((StatusCode::CREATED, Json(TopUpResponse::from(&topup))))
+}
+
+/// The client's top-ups, newest first
+pub async fn list_topups(
+    State(app_state): State<Arc<AppState>>,
+    ClientUser(user_id): ClientUser,
+) -> Result<Json<Vec<TopUpResponse>>> {
+    let find_options = mongodb::options::FindOptions::builder()
+        .sort(mongodb::bson::doc! {"created_at": -1})
+        .limit(100)
+        .build();
+    let topups: Vec<TopUp> = app_state
+        .database
+        .collection::<TopUp>("topups")
+        .find(mongodb::bson::doc! {"user_id": &user_id}, find_options)
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch top-ups: {}", e),
+        })?
+        .try_collect()
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to read top-ups: {}", e),
+        })?;
+
+    Ok(Json(topups.iter().map(TopUpResponse::from).collect()))
+}
+
+/// Status of one top-up, polled by the app after checkout
+pub async fn get_topup(
+    State(app_state): State<Arc<AppState>>,
+    Path(topup_id): Path<String>,
+    ClientUser(user_id): ClientUser,
+) -> Result<Json<TopUpResponse>> {
+    let topup = app_state
+        .database
+        .collection::<TopUp>("topups")
+        .find_one(
+            mongodb::bson::doc! {
+                "id": &topup_id,
+                "user_id": &user_id
+            },
+            None,
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch top-up: {}", e),
+        })?
+        .ok_or_else(|| PeerPowerError::NotFound {
+            resource: format!("Top-up with ID: {}", topup_id),
+        })?;
+
+    Ok(Json(TopUpResponse::from(&topup)))
+}
+
+/// The client's PPT credit balance, from the ledger
+pub async fn get_wallet(
+    State(app_state): State<Arc<AppState>>,
+    ClientUser(user_id): ClientUser,
+) -> Result<Json<WalletResponse>> {
+    let account = app_state
+        .ledger
+        .balance(LedgerAccountKind::Client, &user_id)
+        .await?;
+
+    Ok(Json(WalletResponse {
+        balance: account.balance.to_ppt(),
+        currency: PayoutCurrency::Ppt.as_str(),
+        total_topped_up: account.credits.to_ppt(),
+        total_spent: account.debits.to_ppt(),
+    }))
+}
+
+/// Events from Baray: completed checkouts credit the client's wallet and
+/// finished disbursements settle provider payouts. Each event id is applied
+/// once; redeliveries are acknowledged without being applied again.
+pub async fn baray_webhook(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<serde_json::Value>> {
+    // The signature covers the raw body, so verify before parsing it
+    app_state.topups.verify_webhook(&headers, &body)?;
+
+    let event: BarayEvent =
+        serde_json::from_slice(&body).map_err(|e| PeerPowerError::InvalidPayload {
+            message: format!("Invalid Baray event: {}", e),
+        })?;
+    info!("Baray webhook {} ({})", event.id, event.event_type);
+
+    if !app_state.baray_events.claim(&event).await? {
+        info!("Baray event {} already processed", event.id);
+        return Ok(Json(serde_json::json!({
+            "status": "duplicate",
+            "event_id": event.id
+        })));
+    }
+    if let Err(e) = apply_baray_event(&app_state, &event).await {
+        // Unclaim it so Baray's retry is applied rather than skipped
+        if let Err(release_error) = app_state.baray_events.release(&event.id).await {
+            error!(
+                "Failed to release Baray event {}: {}",
+                event.id, release_error
+            );
+        }
+        return Err(e);
+    }
+
+    Ok(Json(serde_json::json!({
+        "status": "ok",
+        "event_id": event.id
+    })))
+}
+
+async fn apply_baray_event(app_state: &AppState, event: &BarayEvent) -> Result<()> {
+    match event.event_type.as_str() {
+        kind if kind.starts_with("checkout.") => {
+            if let Some(topup) = app_state.topups.apply_event(event).await? {
+                app_state
+                    .audit_logger
+                    .record_best_effort(
+                        AuditLogEntry::new(
+                            Some(topup.user_id.clone()),
+                            "payment.topup_settled",
+                            "topup",
+                            &topup.id,
+                        )
+                        .with_metadata("status", format!("{:?}", topup.status))
+                        .with_metadata("event_id", event.id.clone())
+                        .with_metadata("credit_ppt", topup.credit_ppt.to_string()),
+                    )
+                    .await;
+            }
+        }
+        kind if kind.starts_with("disbursement.") => {
+            let data: BarayDisbursementData = event.data()?;
+            if let Some(payout) = app_state
+                .payout_processor
+                .apply_disbursement_event(&data.reference, &data.disbursement)
+                .await?
+            {
+                app_state
+                    .audit_logger
+                    .record_best_effort(
+                        AuditLogEntry::new(None, "payout.settled", "payout", &payout.id)
+                            .with_metadata("provider_id", payout.provider_id.clone())
+                            .with_metadata("status", format!("{:?}", payout.status))
+                            .with_metadata("event_id", event.id.clone())
+                            .with_metadata("baray_reference", data.disbursement.id.clone()),
+                    )
+                    .await;
+            }
+        }
+        other => info!("Ignoring Baray event {} of type {}", event.id, other),
+    }
+    Ok(())
+}
+
+fn parse_fiat_currency(code: &str) -> Result<PayoutCurrency> {
+    match code.to_uppercase().as_str() {
+        "KHR" => Ok(PayoutCurrency::Khr),
+        "USD" => Ok(PayoutCurrency::Usd),
+        _ => Err(PeerPowerError::ValidationError {
+            field: "currency".to_string(),
+            message: "Currency must be KHR or USD".to_string(),
+        }),
+    }
+}
+
+/// Current PPT rates and where each came from (admin only)
+pub async fn get_exchange_rates(
+    State(app_state): State<Arc<AppState>>,
+    AdminUser(_user_id): AdminUser,
+) -> Result<Json<Vec<FxRate>>> {
+    Ok(Json(app_state.exchange_rates.current().await?))
+}
+
+/// Pin the PPT rate for a currency, overriding the rate source (admin only)
+pub async fn override_exchange_rate(
+    State(app_state): State<Arc<AppState>>,
+    Path(currency): Path<String>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+    JsonExtractor(request): JsonExtractor<ExchangeRateOverrideRequest>,
+) -> Result<Json<FxRate>> {
+    let currency = parse_fiat_currency(&currency)?;
+    let rate = app_state
+        .exchange_rates
+        .set_override(currency, request.rate, &user_id)
+        .await?;
+
+    info!("PPT/{} rate pinned at {}", currency.as_str(), rate.rate);
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(
+                Some(user_id),
+                "exchange_rate.overridden",
+                "exchange_rate",
+                currency.as_str(),
+            )
+            .with_client(client.ip, client.user_agent)
+            .with_metadata("rate", rate.rate.to_string()),
+        )
+        .await;
+
+    Ok(Json(rate))
+}
+
+/// Go back to the rate source for a currency (admin only)
+pub async fn clear_exchange_rate_override(
+    State(app_state): State<Arc<AppState>>,
+    Path(currency): Path<String>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+) -> Result<Json<FxRate>> {
+    let currency = parse_fiat_currency(&currency)?;
+    if !app_state.exchange_rates.clear_override(currency).await? {
+        return Err(PeerPowerError::NotFound {
+            resource: format!("Exchange rate override for {}", currency.as_str()),
+        });
+    }
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(
+                Some(user_id),
+                "exchange_rate.override_cleared",
+                "exchange_rate",
+                currency.as_str(),
+            )
+            .with_client(client.ip, client.user_agent),
+        )
+        .await;
+
+    Ok(Json(app_state.exchange_rates.ppt_rate(currency).await?))
+}
@@ -0,0 +1,647 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    Json as JsonExtractor,
+};
+use futures::stream::TryStreamExt;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::info;
+
+use crate::domain::entities::{
+    AuditLogEntry, LedgerAccountKind, LedgerTransaction, Payout, PayoutCurrency, PayoutMethod,
+    PayoutMethodKind, PayoutRun, PayoutStatus, Provider, AUTO_APPROVER,
+};
+use crate::domain::errors::DomainError;
+use crate::infrastructure::blockchain::{
+    is_wallet_address, ChainSettlementBatch, ProviderSettlementStatus,
+};
+use crate::infrastructure::payments::check_withdrawal;
+use crate::presentation::middleware::{AdminUser, ClientInfo, ProviderUser};
+use crate::shared::utils::stored_timestamp;
+use crate::shared::{AppState, Money, PeerPowerError, Result};
+
+const MAX_PAYOUT_METHODS: usize = 5;
+
+#[derive(Debug, Deserialize)]
+pub struct PayoutMethodRequest {
+    pub id: Option<String>, // omit to add a new method
+    pub kind: PayoutMethodKind,
+    pub account_reference: String,
+    pub currency: PayoutCurrency,
+    #[serde(default)]
+    pub is_default: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdatePayoutMethodsRequest {
+    pub methods: Vec<PayoutMethodRequest>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PayoutMethodsResponse {
+    pub methods: Vec<PayoutMethod>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RequestPayoutRequest {
+    pub amount: f64,                      // PPT to withdraw from earnings
+    pub payout_method_id: Option<String>, // defaults to the provider's default method
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AdminPayoutQuery {
+    pub status: Option<PayoutStatus>, // defaults to Pending, i.e. the review queue
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApprovePayoutsRequest {
+    pub payout_ids: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApprovePayoutsResponse {
+    pub requested: usize,
+    pub approved: u64, // ids not Pending are skipped
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RejectPayoutRequest {
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PayoutResponse {
+    pub id: String,
+    pub provider_id: String,
+    pub status: String,
+    pub failure_reason: Option<String>,
+    pub review_flags: Vec<String>,
+    pub payout_method_id: Option<String>,
+    pub amount: f64,
+    pub currency: String,
+    pub native_amount: f64,
+    pub native_currency: String,
+    pub fx_rate: Option<f64>,
+    pub fx_rate_source: Option<String>,
+    pub fx_locked_at: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl From<&Payout> for PayoutResponse {
+    fn from(payout: &Payout) -> Self {
+        let lock = payout.fx_lock.as_ref();
+        Self {
+            id: payout.id.clone(),
+            provider_id: payout.provider_id.clone(),
+            status: format!("{:?}", payout.status),
+            failure_reason: payout.failure_reason.clone(),
+            review_flags: payout.review_flags.clone(),
+            payout_method_id: payout.payout_method_id.clone(),
+            amount: payout.amount,
+            currency: payout.currency.clone(),
+            native_amount: payout.native_amount().to_ppt(),
+            native_currency: lock
+                .map(|lock| lock.native_currency.clone())
+                .unwrap_or_else(|| payout.currency.clone()),
+            fx_rate: lock.map(|lock| lock.rate),
+            fx_rate_source: lock.map(|lock| lock.rate_source.clone()),
+            fx_locked_at: lock.map(|lock| lock.locked_at.to_rfc3339()),
+            created_at: payout.created_at.to_rfc3339(),
+            updated_at: payout.updated_at.to_rfc3339(),
+        }
+    }
+}
+
+async fn owned_provider(
+    app_state: &AppState,
+    provider_id: &str,
+    user_id: &str,
+) -> Result<Provider> {
+    app_state
+        .database
+        .collection::<Provider>("providers")
+        .find_one(
+            mongodb::bson::doc! {
+                "id": provider_id,
+                "user_id": user_id
+            },
+            None,
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch provider: {}", e),
+        })?
+        .ok_or_else(|| PeerPowerError::NotFound {
+            resource: format!("Provider with ID: {}", provider_id),
+        })
+}
+
+/// Get the provider's payout methods
+pub async fn get_payout_methods(
+    State(app_state): State<Arc<AppState>>,
+    Path(provider_id): Path<String>,
+    ProviderUser(user_id): ProviderUser,
+) -> Result<Json<PayoutMethodsResponse>> {
+    let provider = owned_provider(&app_state, &provider_id, &user_id).await?;
+
+    Ok(Json(PayoutMethodsResponse {
+        methods: provider.payout_methods,
+    }))
+}
+
+/// Replace the provider's payout methods, each with its own payout currency
+pub async fn update_payout_methods(
+    State(app_state): State<Arc<AppState>>,
+    Path(provider_id): Path<String>,
+    ProviderUser(user_id): ProviderUser,
+    JsonExtractor(request): JsonExtractor<UpdatePayoutMethodsRequest>,
+) -> Result<Json<PayoutMethodsResponse>> {
+    if request.methods.len() > MAX_PAYOUT_METHODS {
+        return Err(PeerPowerError::ValidationError {
+            field: "methods".to_string(),
+            message: format!("At most {} payout methods are allowed", MAX_PAYOUT_METHODS),
+        });
+    }
+    if request
+        .methods
+        .iter()
+        .filter(|method| method.is_default)
+        .count()
+        > 1
+    {
+        return Err(PeerPowerError::ValidationError {
+            field: "is_default".to_string(),
+            message: "Only one payout method can be the default".to_string(),
+        });
+    }
+
+    let mut methods = Vec::with_capacity(request.methods.len());
+    for method in request.methods {
+        let method = PayoutMethod {
+            id: method.id.unwrap_or_else(crate::shared::utils::generate_id),
+            kind: method.kind,
+            account_reference: method.account_reference.trim().to_string(),
+            currency: method.currency,
+            is_default: method.is_default,
+        };
+        if method.account_reference.is_empty() {
+            return Err(PeerPowerError::ValidationError {
+                field: "account_reference".to_string(),
+                message: "Account reference is required".to_string(),
+            });
+        }
+        if method.kind == PayoutMethodKind::PptWallet
+            && !is_wallet_address(&method.account_reference)
+        {
+            return Err(PeerPowerError::ValidationError {
+                field: "account_reference".to_string(),
+                message: "PPT wallets must be a 0x-prefixed Selendra address".to_string(),
+            });
+        }
+        if !method.supports_currency() {
+            return Err(PeerPowerError::ValidationError {
+                field: "currency".to_string(),
+                message: format!(
+                    "{:?} payout methods cannot pay out in {}",
+                    method.kind,
+                    method.currency.as_str()
+                ),
+            });
+        }
+        methods.push(method);
+    }
+    if !methods.iter().any(|method| method.is_default) {
+        if let Some(first) = methods.first_mut() {
+            first.is_default = true;
+        }
+    }
+
+    let methods_bson = mongodb::bson::to_bson(&methods).map_err(|e| PeerPowerError::Internal {
+        message: format!("Failed to serialize payout methods: {}", e),
+    })?;
+
+    let result = app_state
+        .database
+        .collection::<Provider>("providers")
+        .update_one(
+            mongodb::bson::doc! {
+                "id": &provider_id,
+                "user_id": &user_id
+            },
+            mongodb::bson::doc! {
+                "$set": {
+                    "payout_methods": methods_bson,
+                    "updated_at": stored_timestamp(chrono::Utc::now()),
+                }
+            },
+            None,
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to update payout methods: {}", e),
+        })?;
+
+    if result.matched_count == 0 {
+        return Err(PeerPowerError::NotFound {
+            resource: format!("Provider with ID: {}", provider_id),
+        });
+    }
+
+    info!(
+        "Provider {} payout methods updated ({} methods)",
+        provider_id,
+        methods.len()
+    );
+
+    Ok(Json(PayoutMethodsResponse { methods }))
+}
+
+/// Request a payout of PPT earnings, converted at a rate locked now
+pub async fn request_payout(
+    State(app_state): State<Arc<AppState>>,
+    Path(provider_id): Path<String>,
+    ProviderUser(user_id): ProviderUser,
+    client: ClientInfo,
+    JsonExtractor(request): JsonExtractor<RequestPayoutRequest>,
+) -> Result<(StatusCode, Json<PayoutResponse>)> {
+    let native_amount = Money::from_ppt(PayoutCurrency::Ppt.round(request.amount));
+
+    let provider = owned_provider(&app_state, &provider_id, &user_id).await?;
+    let method = match &request.payout_method_id {
+        Some(method_id) => provider
+            .payout_methods
+            .iter()
+            .find(|method| &method.id == method_id),
+        None => provider
+            .payout_methods
+            .iter()
+            .find(|method| method.is_default),
+    }
+    .cloned()
+    .ok_or_else(|| PeerPowerError::ValidationError {
+        field: "payout_method_id".to_string(),
+        message: "No matching payout method; add one first".to_string(),
+    })?;
+
+    // One payout request per provider at a time so the balance check holds
+    let lock_key = format!("payout_lock:{}", provider_id);
+    if !app_state.redis.acquire_lock(&lock_key, 30).await? {
+        return Err(PeerPowerError::RateLimitExceeded {
+            resource: "Payout request already in progress".to_string(),
+        });
+    }
+    let result = create_locked_payout(&app_state, &provider, &method, native_amount).await;
+    app_state.redis.release_lock(&lock_key).await?;
+    let payout = result?;
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(Some(user_id), "payout.requested", "payout", &payout.id)
+                .with_client(client.ip, client.user_agent)
+                .with_metadata("provider_id", provider_id)
+                .with_metadata("native_amount", native_amount.to_string())
+                .with_metadata("amount", payout.amount.to_string())
+                .with_metadata("currency", payout.currency.clone())
+                .with_metadata("review_flags", payout.review_flags.join(","))
+                .with_metadata(
+                    "fx_rate",
+                    payout
+                        .fx_lock
+                        .as_ref()
+                        .map(|lock| lock.rate.to_string())
+                        .unwrap_or_default(),
+                ),
+        )
+        .await;
+
+    Ok((StatusCode::CREATED, Json(PayoutResponse::from(&payout))))
+}
+
+async fn create_locked_payout(
+    app_state: &AppState,
+    provider: &Provider,
+    method: &PayoutMethod,
+    native_amount: Money,
+) -> Result<Payout> {
+    // Payouts are debited when requested, so the ledger balance is what's available
+    let available = app_state
+        .ledger
+        .balance(LedgerAccountKind::Provider, &provider.id)
+        .await?
+        .balance;
+    if native_amount > available {
+        return Err(DomainError::InsufficientBalance {
+            requested: native_amount,
+            available: available.max(Money::ZERO),
+        }
+        .into());
+    }
+
+    // Limits and velocity are judged on the last day's requests
+    let find_options = mongodb::options::FindOptions::builder()
+        .sort(mongodb::bson::doc! {"created_at": -1})
+        .limit(100)
+        .build();
+    let recent: Vec<Payout> = app_state
+        .database
+        .collection::<Payout>("payouts")
+        .find(
+            mongodb::bson::doc! {"provider_id": &provider.id},
+            find_options,
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch payouts: {}", e),
+        })?
+        .try_collect()
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to read payouts: {}", e),
+        })?;
+    let config = &app_state.config.payouts;
+    let flags = check_withdrawal(
+        config,
+        provider.created_at,
+        native_amount,
+        &recent,
+        crate::shared::utils::now(),
+    )?;
+
+    let rate = app_state.exchange_rates.ppt_rate(method.currency).await?;
+    let mut payout = Payout::locked(
+        provider.id.clone(),
+        method,
+        native_amount,
+        rate.rate,
+        rate.source,
+    );
+    payout.review_flags = flags.iter().map(|flag| flag.to_string()).collect();
+    if payout.review_flags.is_empty() && config.auto_approve {
+        payout.status = PayoutStatus::Approved;
+        payout.approved_by = Some(AUTO_APPROVER.to_string());
+    } else if !payout.review_flags.is_empty() {
+        metrics::counter!("payouts_flagged_total").increment(1);
+    }
+
+    app_state
+        .database
+        .collection::<Payout>("payouts")
+        .insert_one(&payout, None)
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to create payout: {}", e),
+        })?;
+    app_state
+        .ledger
+        .post(&LedgerTransaction::payout(
+            &payout.id,
+            &provider.id,
+            native_amount,
+        ))
+        .await?;
+
+    info!(
+        "Payout {} for provider {}: {} PPT -> {} {} at {} ({:?})",
+        payout.id,
+        provider.id,
+        native_amount,
+        payout.amount,
+        payout.currency,
+        rate.rate,
+        payout.status
+    );
+    Ok(payout)
+}
+
+/// List the provider's payouts, newest first
+pub async fn list_payouts(
+    State(app_state): State<Arc<AppState>>,
+    Path(provider_id): Path<String>,
+    ProviderUser(user_id): ProviderUser,
+) -> Result<Json<Vec<PayoutResponse>>> {
+    owned_provider(&app_state, &provider_id, &user_id).await?;
+
+    let find_options = mongodb::options::FindOptions::builder()
+        .sort(mongodb::bson::doc! {"created_at": -1})
+        .limit(100)
+        .build();
+    let payouts: Vec<Payout> = app_state
+        .database
+        .collection::<Payout>("payouts")
+        .find(
+            mongodb::bson::doc! {"provider_id": &provider_id},
+            find_options,
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch payouts: {}", e),
+        })?
+        .try_collect()
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to read payouts: {}", e),
+        })?;
+
+    Ok(Json(payouts.iter().map(PayoutResponse::from).collect()))
+}
+
+/// Payout details, including the FX rate applied at settlement
+pub async fn get_payout(
+    State(app_state): State<Arc<AppState>>,
+    Path((provider_id, payout_id)): Path<(String, String)>,
+    ProviderUser(user_id): ProviderUser,
+) -> Result<Json<PayoutResponse>> {
+    owned_provider(&app_state, &provider_id, &user_id).await?;
+
+    let payout = app_state
+        .database
+        .collection::<Payout>("payouts")
+        .find_one(
+            mongodb::bson::doc! {
+                "id": &payout_id,
+                "provider_id": &provider_id
+            },
+            None,
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch payout: {}", e),
+        })?
+        .ok_or_else(|| PeerPowerError::NotFound {
+            resource: format!("Payout: {}", payout_id),
+        })?;
+
+    Ok(Json(PayoutResponse::from(&payout)))
+}
+
+/// On-chain settlement of the provider's earnings to their PPT wallet
+pub async fn get_settlement_status(
+    State(app_state): State<Arc<AppState>>,
+    Path(provider_id): Path<String>,
+    ProviderUser(user_id): ProviderUser,
+) -> Result<Json<ProviderSettlementStatus>> {
+    let provider = owned_provider(&app_state, &provider_id, &user_id).await?;
+
+    Ok(Json(
+        app_state.chain_settler.provider_status(&provider).await?,
+    ))
+}
+
+/// Payouts by status, oldest first, for the approval queue (admin only)
+pub async fn list_admin_payouts(
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<AdminPayoutQuery>,
+    AdminUser(_user_id): AdminUser,
+) -> Result<Json<Vec<PayoutResponse>>> {
+    let status = params.status.unwrap_or(PayoutStatus::Pending);
+    let find_options = mongodb::options::FindOptions::builder()
+        .sort(mongodb::bson::doc! {"created_at": 1})
+        .limit(params.limit.unwrap_or(100).clamp(1, 500))
+        .build();
+    let payouts: Vec<Payout> = app_state
+        .database
+        .collection::<Payout>("payouts")
+        .find(
+            mongodb::bson::doc! {"status": format!("{:?}", status)},
+            find_options,
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch payouts: {}", e),
+        })?
+        .try_collect()
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to read payouts: {}", e),
+        })?;
+
+    Ok(Json(payouts.iter().map(PayoutResponse::from).collect()))
+}
+
+/// Approve pending payouts for the next payout run (admin only)
+pub async fn approve_payouts(
+    State(app_state): State<Arc<AppState>>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+    JsonExtractor(request): JsonExtractor<ApprovePayoutsRequest>,
+) -> Result<Json<ApprovePayoutsResponse>> {
+    if request.payout_ids.is_empty() || request.payout_ids.len() > 500 {
+        return Err(PeerPowerError::ValidationError {
+            field: "payout_ids".to_string(),
+            message: "Approve between 1 and 500 payouts at a time".to_string(),
+        });
+    }
+
+    let approved = app_state
+        .payout_processor
+        .approve(&request.payout_ids, &user_id)
+        .await?;
+    info!(
+        "Admin {} approved {} of {} payouts",
+        user_id,
+        approved,
+        request.payout_ids.len()
+    );
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(Some(user_id), "payout.approved", "payout", "batch")
+                .with_client(client.ip, client.user_agent)
+                .with_metadata("payout_ids", request.payout_ids.join(","))
+                .with_metadata("approved", approved.to_string()),
+        )
+        .await;
+
+    Ok(Json(ApprovePayoutsResponse {
+        requested: request.payout_ids.len(),
+        approved,
+    }))
+}
+
+/// Refuse a payout before it is sent, returning the PPT to the provider (admin only)
+pub async fn reject_payout(
+    State(app_state): State<Arc<AppState>>,
+    Path(payout_id): Path<String>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+    JsonExtractor(request): JsonExtractor<RejectPayoutRequest>,
+) -> Result<Json<PayoutResponse>> {
+    let reason = request.reason.trim();
+    if reason.is_empty() {
+        return Err(PeerPowerError::ValidationError {
+            field: "reason".to_string(),
+            message: "A rejection reason is required".to_string(),
+        });
+    }
+
+    let payout = app_state
+        .payout_processor
+        .reject(&payout_id, reason)
+        .await?;
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(Some(user_id), "payout.rejected", "payout", &payout.id)
+                .with_client(client.ip, client.user_agent)
+                .with_metadata("provider_id", payout.provider_id.clone())
+                .with_metadata("reason", reason),
+        )
+        .await;
+
+    Ok(Json(PayoutResponse::from(&payout)))
+}
+
+/// Run the payout processor now instead of waiting for the next batch (admin only)
+pub async fn run_payouts(
+    State(app_state): State<Arc<AppState>>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+) -> Result<Json<PayoutRun>> {
+    let run = app_state.payout_processor.run().await?;
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(Some(user_id), "payout.run_triggered", "payout_run", &run.id)
+                .with_client(client.ip, client.user_agent)
+                .with_metadata("submitted", run.submitted_count.to_string())
+                .with_metadata("failed", run.failed_count.to_string()),
+        )
+        .await;
+
+    Ok(Json(run))
+}
+
+/// Run an on-chain settlement batch now (admin only)
+pub async fn run_chain_settlements(
+    State(app_state): State<Arc<AppState>>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+) -> Result<Json<ChainSettlementBatch>> {
+    let batch = app_state.chain_settler.run().await?;
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(
+                Some(user_id),
+                "chain_settlement.run_triggered",
+                "chain_settlement_batch",
+                &batch.batch_id,
+            )
+            .with_client(client.ip, client.user_agent)
+            .with_metadata("created", batch.created.to_string())
+            .with_metadata("submitted", batch.submitted.to_string())
+            .with_metadata("failed", batch.failed.to_string()),
+        )
+        .await;
+
+    Ok(Json(batch))
+}
@@ -0,0 +1,269 @@
+use axum::{
+    extract::{Path, State},
+    response::Json,
+    Json as JsonExtractor,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::info;
+use validator::Validate;
+
+use crate::domain::entities::{AuditLogEntry, PricingPlan, PricingPlanKind, VolumeTier};
+use crate::infrastructure::pricing_plans::ClientRate;
+use crate::presentation::middleware::{AdminUser, ClientInfo, ClientUser};
+use crate::shared::{AppState, Money, PeerPowerError, Result};
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct PricingPlanRequest {
+    #[validate(length(min = 1, max = 64, message = "Plan name must be 1-64 characters"))]
+    pub name: String,
+    pub kind: PricingPlanKind,
+    pub segment_cost: f64, // PPT
+    #[serde(default)]
+    pub tiers: Vec<VolumeTierRate>,
+    #[serde(default)]
+    pub committed_monthly_messages: u64,
+}
+
+/// A volume tier as the API takes and reports it, in PPT
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct VolumeTierRate {
+    pub min_monthly_messages: u64,
+    pub segment_cost: f64,
+}
+
+impl From<VolumeTier> for VolumeTierRate {
+    fn from(tier: VolumeTier) -> Self {
+        Self {
+            min_monthly_messages: tier.min_monthly_messages,
+            segment_cost: tier.segment_cost.to_ppt(),
+        }
+    }
+}
+
+impl From<VolumeTierRate> for VolumeTier {
+    fn from(tier: VolumeTierRate) -> Self {
+        Self {
+            min_monthly_messages: tier.min_monthly_messages,
+            segment_cost: Money::from_ppt(tier.segment_cost),
+        }
+    }
+}
+
+impl PricingPlanRequest {
+    fn into_plan(self, created_by: String) -> Result<PricingPlan> {
+        self.validate()?;
+        let plan = PricingPlan::new(
+            self.name.trim().to_string(),
+            self.kind,
+            Money::from_ppt(self.segment_cost),
+            self.tiers.into_iter().map(VolumeTier::from).collect(),
+            self.committed_monthly_messages,
+            created_by,
+        );
+        plan.validate()
+            .map_err(|message| PeerPowerError::ValidationError {
+                field: "plan".to_string(),
+                message,
+            })?;
+        Ok(plan)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AssignPricingPlanRequest {
+    pub plan_id: Option<String>, // none: back to the default rate
+}
+
+#[derive(Debug, Serialize)]
+pub struct PricingPlanResponse {
+    pub id: String,
+    pub name: String,
+    pub kind: PricingPlanKind,
+    pub segment_cost: f64, // PPT
+    pub tiers: Vec<VolumeTierRate>,
+    pub committed_monthly_messages: u64,
+    pub created_by: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<PricingPlan> for PricingPlanResponse {
+    fn from(plan: PricingPlan) -> Self {
+        Self {
+            id: plan.id,
+            name: plan.name,
+            kind: plan.kind,
+            segment_cost: plan.segment_cost.to_ppt(),
+            tiers: plan.tiers.into_iter().map(VolumeTierRate::from).collect(),
+            committed_monthly_messages: plan.committed_monthly_messages,
+            created_by: plan.created_by,
+            created_at: plan.created_at,
+            updated_at: plan.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ClientRateResponse {
+    pub plan: Option<PricingPlanResponse>, // none: pay-as-you-go at the default rate
+    pub monthly_messages: u64,
+    pub segment_cost: f64, // PPT
+}
+
+impl From<ClientRate> for ClientRateResponse {
+    fn from(rate: ClientRate) -> Self {
+        Self {
+            plan: rate.plan.map(PricingPlanResponse::from),
+            monthly_messages: rate.monthly_messages,
+            segment_cost: rate.segment_cost.to_ppt(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ClientPricingResponse {
+    #[serde(flatten)]
+    pub rate: ClientRateResponse,
+    pub currency: &'static str,
+}
+
+/// The client's pricing plan and what a segment costs them right now
+pub async fn get_pricing(
+    State(app_state): State<Arc<AppState>>,
+    ClientUser(user_id): ClientUser,
+) -> Result<Json<ClientPricingResponse>> {
+    let client = app_state.user_repository.find_by_id(&user_id).await?;
+    let rate = app_state
+        .pricing_plans
+        .rate_for(
+            &user_id,
+            client
+                .as_ref()
+                .and_then(|client| client.pricing_plan_id.as_deref()),
+        )
+        .await?;
+
+    Ok(Json(ClientPricingResponse {
+        rate: rate.into(),
+        currency: "PPT",
+    }))
+}
+
+/// List pricing plans (admin only)
+pub async fn list_pricing_plans(
+    State(app_state): State<Arc<AppState>>,
+    AdminUser(_user_id): AdminUser,
+) -> Result<Json<Vec<PricingPlanResponse>>> {
+    let plans = app_state.pricing_plans.list().await?;
+    let plans = plans.into_iter().map(PricingPlanResponse::from).collect();
+    Ok(Json(plans))
+}
+
+/// Create a pricing plan (admin only)
+pub async fn create_pricing_plan(
+    State(app_state): State<Arc<AppState>>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+    JsonExtractor(request): JsonExtractor<PricingPlanRequest>,
+) -> Result<Json<PricingPlanResponse>> {
+    let plan = request.into_plan(user_id.clone())?;
+    app_state.pricing_plans.create(&plan).await?;
+
+    info!(
+        "Pricing plan {} created: {} at {} PPT per segment",
+        plan.id, plan.name, plan.segment_cost
+    );
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(
+                Some(user_id),
+                "pricing_plan.created",
+                "pricing_plan",
+                &plan.id,
+            )
+            .with_client(client.ip, client.user_agent)
+            .with_metadata("kind", format!("{:?}", plan.kind))
+            .with_metadata("segment_cost", plan.segment_cost.to_string()),
+        )
+        .await;
+
+    Ok(Json(plan.into()))
+}
+
+/// Replace a pricing plan's rates (admin only). Clients on the plan pay
+/// the new rates for messages sent from now on.
+pub async fn update_pricing_plan(
+    State(app_state): State<Arc<AppState>>,
+    Path(plan_id): Path<String>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+    JsonExtractor(request): JsonExtractor<PricingPlanRequest>,
+) -> Result<Json<PricingPlanResponse>> {
+    let plan = request.into_plan(user_id.clone())?;
+    let plan = app_state.pricing_plans.update(&plan_id, &plan).await?;
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(
+                Some(user_id),
+                "pricing_plan.updated",
+                "pricing_plan",
+                &plan.id,
+            )
+            .with_client(client.ip, client.user_agent)
+            .with_metadata("kind", format!("{:?}", plan.kind))
+            .with_metadata("segment_cost", plan.segment_cost.to_string()),
+        )
+        .await;
+
+    Ok(Json(plan.into()))
+}
+
+/// Put a client on a pricing plan, or back on the default rate (admin only)
+pub async fn assign_client_pricing_plan(
+    State(app_state): State<Arc<AppState>>,
+    Path(client_id): Path<String>,
+    AdminUser(user_id): AdminUser,
+    client: ClientInfo,
+    JsonExtractor(request): JsonExtractor<AssignPricingPlanRequest>,
+) -> Result<Json<ClientRateResponse>> {
+    if let Some(plan_id) = &request.plan_id {
+        app_state.pricing_plans.get(plan_id).await?;
+    }
+
+    let mut user = app_state
+        .user_repository
+        .find_by_id(&client_id)
+        .await?
+        .ok_or_else(|| PeerPowerError::NotFound {
+            resource: format!("User with ID: {}", client_id),
+        })?;
+    user.pricing_plan_id = request.plan_id.clone();
+    user.updated_at = chrono::Utc::now();
+    app_state.user_repository.update(&user).await?;
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(Some(user_id), "pricing_plan.assigned", "user", &client_id)
+                .with_client(client.ip, client.user_agent)
+                .with_metadata(
+                    "plan_id",
+                    request
+                        .plan_id
+                        .clone()
+                        .unwrap_or_else(|| "none".to_string()),
+                ),
+        )
+        .await;
+
+    let rate = app_state
+        .pricing_plans
+        .rate_for(&client_id, user.pricing_plan_id.as_deref())
+        .await?;
+    Ok(Json(rate.into()))
+}
@@ -3,17 +3,26 @@ use axum::{
     response::Json,
     Json as JsonExtractor,
 };
-use chrono::{DateTime, Utc};
+use chrono::Utc;
 use futures::stream::TryStreamExt;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use tracing::info;
+use tracing::{info, warn};
 use validator::Validate;
 
-use crate::domain::entities::provider::{Location, Provider};
-use crate::presentation::handlers::message_handlers::AuthenticatedUser;
+use crate::domain::entities::message::MessagePriority;
+use crate::domain::entities::provider::{
+    KycSubmission, Location, OnboardingStep, Provider, ProviderOnboarding, ProviderSelfTest,
+    RecipientRule, SELF_TEST_CLIENT_ID,
+};
+use crate::domain::entities::{AuditLogEntry, Job, Message, PlatformEvent, ProviderStake};
+use crate::infrastructure::device_keys::DeviceKeyVerifier;
+use crate::presentation::middleware::{ClientInfo, ProviderUser};
+use crate::shared::field_encryption;
+use crate::shared::pagination::Cursor;
 use crate::shared::types::{Carrier, PhoneNumber, ProviderStatus};
-use crate::shared::{AppState, PeerPowerError, Result};
+use crate::shared::utils::stored_timestamp;
+use crate::shared::{AppState, PageRequest, PageResponse, PeerPowerError, Result};
 
 #[derive(Debug, Deserialize, Validate)]
 pub struct RegisterProviderRequest {
@@ -21,6 +30,8 @@ pub struct RegisterProviderRequest {
     pub phone: String,
     pub fcm_token: String, // For push notifications
     pub location: Option<Location>,
+    pub device_public_key: String, // SPKI PEM; the device keeps the private key
+    pub integrity_token: Option<String>, // Play Integrity token, nonce = device key fingerprint
 }
 
 #[derive(Debug, Serialize)]
@@ -30,6 +41,8 @@ pub struct RegisterProviderResponse {
     pub registered_at: String,
     pub carrier: String,
     pub phone: String,
+    pub device_key_fingerprint: String,
+    pub attestation_passed: Option<bool>, // None when no attestation was checked
 }
 
 #[derive(Debug, Serialize)]
@@ -43,6 +56,7 @@ pub struct ProviderStatusResponse {
     pub last_heartbeat: Option<String>,
     pub message_count_today: u32,
     pub success_rate: f64,
+    pub self_test_status: Option<String>,
     pub created_at: String,
     pub updated_at: String,
 }
@@ -51,8 +65,8 @@ pub struct ProviderStatusResponse {
 pub struct ProviderListQuery {
     pub status: Option<String>,
     pub carrier: Option<String>,
-    pub page: Option<u32>,
-    pub limit: Option<u32>,
+    pub cursor: Option<String>, // next_cursor of the previous page
+    pub limit: Option<i64>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -61,12 +75,108 @@ pub struct HeartbeatRequest {
     pub location: Option<String>,
     pub battery_level: Option<u8>,
     pub signal_strength: Option<u8>,
+    pub sim_carrier: Option<String>, // SIM operator detected on the device
+}
+
+#[derive(Debug, Serialize)]
+pub struct CarrierVerificationResponse {
+    pub provider_id: String,
+    pub phone: String,
+    pub expires_in_minutes: i64,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ConfirmCarrierVerificationRequest {
+    #[validate(length(equal = 6, message = "OTP must be 6 digits"))]
+    pub otp: String,
+    #[validate(length(min = 1, message = "SIM carrier is required"))]
+    pub sim_carrier: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConfirmCarrierVerificationResponse {
+    pub provider_id: String,
+    pub previous_carrier: String,
+    pub carrier: String,
+    pub carrier_changed: bool,
+    pub verified_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SelfTestResponse {
+    pub provider_id: String,
+    pub message_id: String,
+    pub recipient: String,
+    pub status: String,
+    pub requested_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecipientRulesPayload {
+    pub rules: Vec<RecipientRule>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OnboardingResponse {
+    pub provider_id: String,
+    pub steps: Vec<crate::domain::entities::provider::OnboardingStepState>,
+    pub completed_steps: usize,
+    pub total_steps: usize,
+    pub complete: bool,
+    pub next_step: Option<OnboardingStep>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EnrollDeviceKeyRequest {
+    pub device_public_key: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AttestDeviceRequest {
+    pub integrity_token: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AttestationResponse {
+    pub provider_id: String,
+    pub passed: bool,
+    pub device_verdicts: Vec<String>,
+    pub app_verdict: String,
+    pub attested_at: String,
+    pub max_daily_messages: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StakeResponse {
+    pub provider_id: String,
+    pub staking_enabled: bool,
+    pub stake: Option<ProviderStake>,
+    pub max_daily_messages: u32, // effective limit, including what the stake unlocks
+    pub priority: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeviceKeyResponse {
+    pub provider_id: String,
+    pub fingerprint: String,
+    pub enrolled_at: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct SubmitKycRequest {
+    #[validate(length(min = 1, max = 32, message = "Invalid document type"))]
+    pub document_type: String,
+    #[validate(length(min = 1, max = 128, message = "Invalid document reference"))]
+    pub document_reference: String,
 }
 
+const CARRIER_VERIFICATION_PURPOSE: &str = "carrier_reverify";
+const MAX_RECIPIENT_RULES: usize = 50;
+
 /// Register a new SMS provider
 pub async fn register_provider(
     State(app_state): State<Arc<AppState>>,
-    AuthenticatedUser(user_id): AuthenticatedUser,
+    ProviderUser(user_id): ProviderUser,
     JsonExtractor(register_request): JsonExtractor<RegisterProviderRequest>,
 ) -> Result<Json<RegisterProviderResponse>> {
     // Validate request
@@ -77,6 +187,7 @@ pub async fn register_provider(
     // Parse and validate phone number
     let phone = PhoneNumber::new(register_request.phone)?;
     let carrier = Carrier::from_phone_number(&phone);
+    let device_key = DeviceKeyVerifier::enroll(&register_request.device_public_key)?;
 
     // Check if user exists and is verified
     let user = app_state
@@ -94,12 +205,17 @@ pub async fn register_provider(
         });
     }
 
+    let attestation = app_state
+        .play_integrity
+        .attest_registration(register_request.integrity_token.as_deref(), &device_key)
+        .await?;
+
     // Check if provider with this phone already exists
     let providers_collection = app_state.database.collection::<Provider>("providers");
     let existing_provider = providers_collection
         .find_one(
             mongodb::bson::doc! {
-                "phone": phone.as_str(),
+                "phone": field_encryption::phone_filter(&phone),
                 "user_id": &user_id
             },
             None,
@@ -122,6 +238,8 @@ pub async fn register_provider(
     // Set additional fields
     provider.fcm_token = Some(register_request.fcm_token);
     provider.location = register_request.location;
+    provider.device_key = Some(device_key);
+    provider.attestation = attestation;
 
     // Store provider in database
     providers_collection
@@ -131,27 +249,29 @@ pub async fn register_provider(
             message: format!("Failed to store provider: {}", e),
         })?;
 
-    // Add provider to Redis active providers set for quick lookup
-    let redis_key = format!("providers:active:{:?}", carrier);
-    app_state
-        .redis
-        .sadd(&redis_key, &provider.id)
-        .await
-        .map_err(|e| PeerPowerError::Database {
-            message: format!("Failed to cache provider: {}", e),
-        })?;
+    // Cached for dispatch once it comes online
+    app_state.provider_cache.store(&provider).await?;
 
     info!(
         "Provider {} registered successfully for user {}",
         provider.id, user_id
     );
 
+    if let Err(e) = app_state.identity.issue_provider_credential(&provider).await {
+        warn!("Provider credential not issued for {}: {}", provider.id, e);
+    }
+
     Ok(Json(RegisterProviderResponse {
         provider_id: provider.id,
         status: format!("{:?}", provider.status).to_lowercase(),
         registered_at: provider.created_at.to_rfc3339(),
         carrier: format!("{:?}", provider.carrier),
         phone: provider.phone.as_str().to_string(),
+        device_key_fingerprint: provider
+            .device_key
+            .map(|key| key.fingerprint)
+            .unwrap_or_default(),
+        attestation_passed: provider.attestation.map(|attestation| attestation.passed),
     }))
 }
 
@@ -159,14 +279,15 @@ pub async fn register_provider(
 pub async fn get_provider_status(
     State(app_state): State<Arc<AppState>>,
     Path(provider_id): Path<String>,
-    AuthenticatedUser(user_id): AuthenticatedUser,
+    ProviderUser(user_id): ProviderUser,
 ) -> Result<Json<ProviderStatusResponse>> {
     let providers_collection = app_state.database.collection::<Provider>("providers");
     let provider = providers_collection
         .find_one(
             mongodb::bson::doc! {
                 "id": &provider_id,
-                "user_id": &user_id
+                "user_id": &user_id,
+                "deleted_at": null
             },
             None,
         )
@@ -192,7 +313,7 @@ pub async fn get_provider_status(
         .count_documents(
             mongodb::bson::doc! {
                 "provider_id": &provider.id,
-                "created_at": {"$gte": today_start}
+                "created_at": {"$gte": stored_timestamp(today_start)}
             },
             None,
         )
@@ -214,23 +335,28 @@ pub async fn get_provider_status(
         last_heartbeat: provider.last_heartbeat.map(|dt| dt.to_rfc3339()),
         message_count_today,
         success_rate,
+        self_test_status: provider
+            .self_test
+            .as_ref()
+            .map(|test| format!("{:?}", test.status).to_lowercase()),
         created_at: provider.created_at.to_rfc3339(),
         updated_at: provider.updated_at.to_rfc3339(),
     }))
 }
 
-/// List user's providers
+/// List user's providers, newest first, a page at a time
 pub async fn list_providers(
     State(app_state): State<Arc<AppState>>,
     Query(params): Query<ProviderListQuery>,
-    AuthenticatedUser(user_id): AuthenticatedUser,
-) -> Result<Json<Vec<ProviderStatusResponse>>> {
+    ProviderUser(user_id): ProviderUser,
+) -> Result<Json<PageResponse<ProviderStatusResponse>>> {
     use mongodb::bson::{doc, Document};
 
+    let page = PageRequest::new(params.cursor.as_deref(), params.limit)?;
     let providers_collection = app_state.database.collection::<Document>("providers");
 
     // Build query filter
-    let mut filter = doc! {"user_id": &user_id};
+    let mut filter = doc! {"user_id": &user_id, "deleted_at": null};
     if let Some(status) = params.status {
         filter.insert("status", status);
     }
@@ -239,44 +365,51 @@ pub async fn list_providers(
     }
 
     // Find providers
-    let mut cursor =
-        providers_collection
-            .find(filter, None)
-            .await
-            .map_err(|e| PeerPowerError::Database {
-                message: format!("Failed to query providers: {}", e),
-            })?;
-
-    let mut providers = Vec::new();
-    while let Some(doc) = cursor
-        .try_next()
+    let docs: Vec<Document> = providers_collection
+        .find(
+            page.filter(filter),
+            mongodb::options::FindOptions::builder()
+                .sort(page.sort())
+                .limit(page.fetch_limit())
+                .build(),
+        )
         .await
         .map_err(|e| PeerPowerError::Database {
-            message: format!("Failed to iterate providers: {}", e),
+            message: format!("Failed to query providers: {}", e),
         })?
-    {
+        .try_collect()
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to iterate providers: {}", e),
+        })?;
+    let docs = page.page(docs, |doc| {
+        Cursor::from_stored(
+            doc.get_str("created_at").unwrap_or_default(),
+            doc.get_str("id").unwrap_or_default(),
+        )
+    });
+
+    let mut providers = Vec::new();
+    for doc in docs.items {
         // Extract fields from the document
         let provider_id = doc.get_str("id").unwrap_or("").to_string();
         let user_id = doc.get_str("user_id").unwrap_or("").to_string();
-        let phone = doc.get_str("phone").unwrap_or("").to_string();
+        let phone = field_encryption::decrypt_phone(doc.get_str("phone").unwrap_or(""))?;
         let carrier = doc.get_str("carrier").unwrap_or("Unknown").to_string();
         let status = doc.get_str("status").unwrap_or("offline").to_string();
 
         // Handle datetime fields safely
         let created_at = doc
-            .get_datetime("created_at")
-            .map(|dt| DateTime::<Utc>::from(*dt).to_rfc3339())
+            .get_str("created_at")
+            .map(str::to_string)
             .unwrap_or_else(|_| Utc::now().to_rfc3339());
 
         let updated_at = doc
-            .get_datetime("updated_at")
-            .map(|dt| DateTime::<Utc>::from(*dt).to_rfc3339())
+            .get_str("updated_at")
+            .map(str::to_string)
             .unwrap_or_else(|_| Utc::now().to_rfc3339());
 
-        let last_heartbeat = doc
-            .get_datetime("last_heartbeat")
-            .map(|dt| DateTime::<Utc>::from(*dt).to_rfc3339())
-            .ok();
+        let last_heartbeat = doc.get_str("last_heartbeat").map(str::to_string).ok();
 
         // For now, use placeholder values for message count and success rate
         // TODO: Calculate actual values from message history
@@ -290,31 +423,75 @@ pub async fn list_providers(
             last_heartbeat,
             message_count_today: 0, // TODO: Calculate actual count
             success_rate: 95.0,     // TODO: Calculate actual success rate
+            self_test_status: doc
+                .get_document("self_test")
+                .ok()
+                .and_then(|test| test.get_str("status").ok())
+                .map(|status| status.to_lowercase()),
             created_at,
             updated_at,
         });
     }
 
-    Ok(Json(providers))
+    Ok(Json(PageResponse {
+        items: providers,
+        next_cursor: docs.next_cursor,
+        has_more: docs.has_more,
+    }))
 }
 
 /// Provider heartbeat endpoint (keeps provider status updated)
 pub async fn provider_heartbeat(
     State(app_state): State<Arc<AppState>>,
     Path(provider_id): Path<String>,
-    AuthenticatedUser(user_id): AuthenticatedUser,
+    ProviderUser(user_id): ProviderUser,
     JsonExtractor(heartbeat_request): JsonExtractor<HeartbeatRequest>,
 ) -> Result<Json<serde_json::Value>> {
     let providers_collection = app_state.database.collection::<Provider>("providers");
+    let provider = providers_collection
+        .find_one(
+            mongodb::bson::doc! {
+                "id": &provider_id,
+                "user_id": &user_id,
+                "deleted_at": null
+            },
+            None,
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch provider: {}", e),
+        })?
+        .ok_or_else(|| PeerPowerError::NotFound {
+            resource: format!("Provider with ID: {}", provider_id),
+        })?;
+
+    // Devices report online as soon as they start; hold them offline until onboarded
+    let mut status = heartbeat_request.status.clone();
+    if status == ProviderStatus::Online {
+        // This heartbeat itself satisfies the first-heartbeat step
+        let mut heartbeat_provider = provider.clone();
+        heartbeat_provider.first_heartbeat_at.get_or_insert_with(chrono::Utc::now);
+        if !provider_onboarding(&app_state, &heartbeat_provider).await?.is_complete() {
+            status = ProviderStatus::Offline;
+        }
+    }
 
     // Update provider status and last heartbeat
     let mut update_doc = mongodb::bson::doc! {
         "$set": {
-            "status": format!("{:?}", heartbeat_request.status),
-            "last_heartbeat": chrono::Utc::now(),
-            "updated_at": chrono::Utc::now(),
+            "status": format!("{:?}", status),
+            "last_heartbeat": stored_timestamp(chrono::Utc::now()),
+            "updated_at": stored_timestamp(chrono::Utc::now()),
         }
     };
+    if provider.first_heartbeat_at.is_none() {
+        update_doc
+            .get_mut("$set")
+            .unwrap()
+            .as_document_mut()
+            .unwrap()
+            .insert("first_heartbeat_at", stored_timestamp(chrono::Utc::now()));
+    }
 
     // Add optional fields if present
     if let Some(location) = heartbeat_request.location {
@@ -346,7 +523,8 @@ pub async fn provider_heartbeat(
         .update_one(
             mongodb::bson::doc! {
                 "id": &provider_id,
-                "user_id": &user_id
+                "user_id": &user_id,
+                "deleted_at": null
             },
             update_doc,
             None,
@@ -362,17 +540,26 @@ pub async fn provider_heartbeat(
         });
     }
 
-    // Update Redis cache if provider is online
-    if heartbeat_request.status == ProviderStatus::Online {
-        // Add to active providers set
-        let redis_key = "providers:active"; // We'll determine SIM type from database if needed
+    // Refresh the dispatch cache; the heartbeat also keeps it from expiring
+    let came_online = status == ProviderStatus::Online && provider.status != ProviderStatus::Online;
+    let mut cached = provider;
+    cached.status = status.clone();
+    cached.last_heartbeat = Some(chrono::Utc::now());
+    app_state.provider_cache.store(&cached).await?;
+    if came_online {
         app_state
-            .redis
-            .sadd(&redis_key, &provider_id)
-            .await
-            .map_err(|e| PeerPowerError::Database {
-                message: format!("Failed to cache provider: {}", e),
-            })?;
+            .events
+            .publish(PlatformEvent::provider_online(&cached))
+            .await;
+    }
+
+    // Compare the SIM carrier reported by the device with the registered one
+    if let Some(detected) = heartbeat_request
+        .sim_carrier
+        .as_deref()
+        .and_then(Carrier::from_name)
+    {
+        check_carrier_anomaly(&app_state, &provider_id, detected).await?;
     }
 
     info!(
@@ -382,6 +569,7 @@ pub async fn provider_heartbeat(
 
     Ok(Json(serde_json::json!({
         "status": "ok",
+        "provider_status": format!("{:?}", status).to_lowercase(),
         "timestamp": chrono::Utc::now().to_rfc3339()
     })))
 }
@@ -390,7 +578,7 @@ pub async fn provider_heartbeat(
 pub async fn update_provider_status(
     State(app_state): State<Arc<AppState>>,
     Path(provider_id): Path<String>,
-    AuthenticatedUser(user_id): AuthenticatedUser,
+    ProviderUser(user_id): ProviderUser,
     JsonExtractor(status_request): JsonExtractor<serde_json::Value>,
 ) -> Result<Json<serde_json::Value>> {
     let status_str =
@@ -416,16 +604,50 @@ pub async fn update_provider_status(
     };
 
     let providers_collection = app_state.database.collection::<Provider>("providers");
+
+    let mut online_provider = None;
+    if status == ProviderStatus::Online {
+        let provider = providers_collection
+            .find_one(
+                mongodb::bson::doc! {
+                    "id": &provider_id,
+                    "user_id": &user_id,
+                    "deleted_at": null
+                },
+                None,
+            )
+            .await
+            .map_err(|e| PeerPowerError::Database {
+                message: format!("Failed to fetch provider: {}", e),
+            })?
+            .ok_or_else(|| PeerPowerError::NotFound {
+                resource: format!("Provider with ID: {}", provider_id),
+            })?;
+
+        let onboarding = provider_onboarding(&app_state, &provider).await?;
+        if !onboarding.is_complete() {
+            return Err(PeerPowerError::ValidationError {
+                field: "status".to_string(),
+                message: format!(
+                    "Provider onboarding is incomplete: {:?}",
+                    onboarding.pending_steps()
+                ),
+            });
+        }
+        online_provider = Some(provider);
+    }
+
     let result = providers_collection
         .update_one(
             mongodb::bson::doc! {
                 "id": &provider_id,
-                "user_id": &user_id
+                "user_id": &user_id,
+                "deleted_at": null
             },
             mongodb::bson::doc! {
                 "$set": {
                     "status": format!("{:?}", status),
-                    "updated_at": chrono::Utc::now(),
+                    "updated_at": stored_timestamp(chrono::Utc::now()),
                 }
             },
             None,
@@ -441,6 +663,22 @@ pub async fn update_provider_status(
         });
     }
 
+    // Only online providers are cached for dispatch
+    match online_provider {
+        Some(mut provider) => {
+            let came_online = provider.status != ProviderStatus::Online;
+            provider.status = status.clone();
+            app_state.provider_cache.store(&provider).await?;
+            if came_online {
+                app_state
+                    .events
+                    .publish(PlatformEvent::provider_online(&provider))
+                    .await;
+            }
+        }
+        None => app_state.provider_cache.remove(&provider_id).await?,
+    }
+
     Ok(Json(serde_json::json!({
         "status": "updated",
         "provider_id": provider_id,
@@ -448,3 +686,875 @@ pub async fn update_provider_status(
         "timestamp": chrono::Utc::now().to_rfc3339()
     })))
 }
+
+/// Flag a provider whose device reports a different SIM carrier than registered
+async fn check_carrier_anomaly(
+    app_state: &Arc<AppState>,
+    provider_id: &str,
+    detected: Carrier,
+) -> Result<()> {
+    let providers_collection = app_state.database.collection::<Provider>("providers");
+    let mut provider = match providers_collection
+        .find_one(mongodb::bson::doc! {"id": provider_id}, None)
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch provider: {}", e),
+        })? {
+        Some(provider) => provider,
+        None => return Ok(()),
+    };
+
+    if provider.carrier == detected || provider.carrier_mismatch.is_some() {
+        return Ok(());
+    }
+
+    warn!(
+        "Carrier mismatch for provider {}: registered {:?}, device reports {:?}",
+        provider.id, provider.carrier, detected
+    );
+    provider.flag_carrier_mismatch(detected, "heartbeat");
+
+    let mismatch = mongodb::bson::to_bson(&provider.carrier_mismatch).map_err(|e| {
+        PeerPowerError::Internal {
+            message: format!("Failed to serialize carrier mismatch: {}", e),
+        }
+    })?;
+    providers_collection
+        .update_one(
+            mongodb::bson::doc! {"id": &provider.id},
+            mongodb::bson::doc! {
+                "$set": {
+                    "carrier_mismatch": mismatch,
+                    "carrier_reverification_required": true,
+                    "updated_at": stored_timestamp(provider.updated_at),
+                }
+            },
+            None,
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to flag carrier mismatch: {}", e),
+        })?;
+
+    // Pull the provider out of carrier routing until it re-verifies
+    app_state.provider_cache.remove(&provider.id).await?;
+
+    if let Some(fcm_token) = provider.fcm_token.as_deref() {
+        if let Err(e) = app_state
+            .fcm_service
+            .send_provider_status_update(fcm_token, "carrier_reverification_required")
+            .await
+        {
+            warn!("Failed to notify provider {} of carrier mismatch: {}", provider.id, e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Start carrier re-verification by sending a code to the provider's SIM
+pub async fn start_carrier_verification(
+    State(app_state): State<Arc<AppState>>,
+    Path(provider_id): Path<String>,
+    ProviderUser(user_id): ProviderUser,
+) -> Result<Json<CarrierVerificationResponse>> {
+    let providers_collection = app_state.database.collection::<Provider>("providers");
+    let provider = providers_collection
+        .find_one(
+            mongodb::bson::doc! {
+                "id": &provider_id,
+                "user_id": &user_id,
+                "deleted_at": null
+            },
+            None,
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch provider: {}", e),
+        })?
+        .ok_or_else(|| PeerPowerError::NotFound {
+            resource: format!("Provider with ID: {}", provider_id),
+        })?;
+
+    info!("Starting carrier re-verification for provider {}", provider.id);
+
+    app_state
+        .auth_service
+        .send_verification_code(&provider.phone, CARRIER_VERIFICATION_PURPOSE)
+        .await?;
+
+    Ok(Json(CarrierVerificationResponse {
+        provider_id: provider.id,
+        phone: provider.phone.as_str().to_string(),
+        expires_in_minutes: app_state.config.auth.otp_expiration_minutes,
+    }))
+}
+
+/// Confirm carrier re-verification with the received code and the device's SIM carrier
+pub async fn confirm_carrier_verification(
+    State(app_state): State<Arc<AppState>>,
+    Path(provider_id): Path<String>,
+    ProviderUser(user_id): ProviderUser,
+    JsonExtractor(confirm_request): JsonExtractor<ConfirmCarrierVerificationRequest>,
+) -> Result<Json<ConfirmCarrierVerificationResponse>> {
+    confirm_request.validate()?;
+
+    let sim_carrier = Carrier::from_name(&confirm_request.sim_carrier).ok_or_else(|| {
+        PeerPowerError::ValidationError {
+            field: "sim_carrier".to_string(),
+            message: "Invalid carrier. Must be one of: Smart, Metfone, Cellcard, Qb".to_string(),
+        }
+    })?;
+
+    let providers_collection = app_state.database.collection::<Provider>("providers");
+    let mut provider = providers_collection
+        .find_one(
+            mongodb::bson::doc! {
+                "id": &provider_id,
+                "user_id": &user_id,
+                "deleted_at": null
+            },
+            None,
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch provider: {}", e),
+        })?
+        .ok_or_else(|| PeerPowerError::NotFound {
+            resource: format!("Provider with ID: {}", provider_id),
+        })?;
+
+    app_state
+        .auth_service
+        .confirm_verification_code(
+            &provider.phone,
+            CARRIER_VERIFICATION_PURPOSE,
+            &confirm_request.otp,
+        )
+        .await?;
+
+    let previous_carrier = provider.carrier.clone();
+    let prefix_carrier = Carrier::from_phone_number(&provider.phone);
+    if prefix_carrier != sim_carrier {
+        // Number has likely been ported; trust the SIM the code was delivered to
+        warn!(
+            "Provider {} number prefix suggests {:?} but SIM reports {:?}",
+            provider.id, prefix_carrier, sim_carrier
+        );
+    }
+
+    provider.confirm_carrier(sim_carrier);
+
+    providers_collection
+        .update_one(
+            mongodb::bson::doc! {"id": &provider.id},
+            mongodb::bson::doc! {
+                "$set": {
+                    "carrier": format!("{:?}", provider.carrier),
+                    "carrier_verified_at": provider.carrier_verified_at.map(stored_timestamp),
+                    "carrier_reverification_required": false,
+                    "carrier_mismatch": null,
+                    "updated_at": stored_timestamp(provider.updated_at),
+                }
+            },
+            None,
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to update provider carrier: {}", e),
+        })?;
+
+    let carrier_changed = previous_carrier != provider.carrier;
+    // Back in routing, under the verified carrier
+    app_state.provider_cache.store(&provider).await?;
+
+    info!(
+        "Provider {} carrier verified as {:?} (changed: {})",
+        provider.id, provider.carrier, carrier_changed
+    );
+
+    Ok(Json(ConfirmCarrierVerificationResponse {
+        provider_id: provider.id,
+        previous_carrier: format!("{:?}", previous_carrier),
+        carrier: format!("{:?}", provider.carrier),
+        carrier_changed,
+        verified_at: provider
+            .carrier_verified_at
+            .map(|dt| dt.to_rfc3339())
+            .unwrap_or_default(),
+    }))
+}
+
+/// Dispatch a loopback test SMS through the provider device to confirm it can send
+pub async fn run_self_test(
+    State(app_state): State<Arc<AppState>>,
+    Path(provider_id): Path<String>,
+    ProviderUser(user_id): ProviderUser,
+) -> Result<Json<SelfTestResponse>> {
+    let providers_collection = app_state.database.collection::<Provider>("providers");
+    let provider = providers_collection
+        .find_one(
+            mongodb::bson::doc! {
+                "id": &provider_id,
+                "user_id": &user_id,
+                "deleted_at": null
+            },
+            None,
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch provider: {}", e),
+        })?
+        .ok_or_else(|| PeerPowerError::NotFound {
+            resource: format!("Provider with ID: {}", provider_id),
+        })?;
+
+    if provider.self_test.as_ref().map(|test| test.is_in_progress()).unwrap_or(false) {
+        return Err(PeerPowerError::ValidationError {
+            field: "self_test".to_string(),
+            message: "A self-test is already in progress".to_string(),
+        });
+    }
+
+    let fcm_token = provider.fcm_token.clone().ok_or_else(|| PeerPowerError::ValidationError {
+        field: "fcm_token".to_string(),
+        message: "Provider must be online with a registered device to run a self-test".to_string(),
+    })?;
+
+    // Send to the system verification number when configured, otherwise back to the SIM itself
+    let recipient = match app_state.config.providers.self_test_number.as_deref() {
+        Some(number) => PhoneNumber::new(number.to_string())?,
+        None => provider.phone.clone(),
+    };
+
+    let code: String = {
+        use rand::Rng;
+        let mut rng = rand::thread_rng();
+        (0..6).map(|_| rng.gen_range(0..10).to_string()).collect()
+    };
+    let mut message = Message::new(
+        SELF_TEST_CLIENT_ID.to_string(),
+        format!("PeerPower self-test {}", code),
+        recipient,
+        MessagePriority::Urgent,
+        Some(provider.id.clone()),
+        None,
+    );
+    message.assign_to_provider(provider.id.clone())?;
+    let mut job = Job::new(message.id.clone(), provider.id.clone());
+    let mut self_test = ProviderSelfTest::new(
+        message.id.clone(),
+        message.recipient.as_str().to_string(),
+    );
+
+    info!(
+        "Running self-test for provider {} to {}",
+        provider.id,
+        message.recipient.as_str()
+    );
+
+    match app_state
+        .fcm_service
+        .send_sms_dispatch_request(
+            &fcm_token,
+            &message.id,
+            message.recipient.as_str(),
+            &message.content,
+            &format!("{:?}", message.priority).to_lowercase(),
+        )
+        .await
+    {
+        Ok(_) => {
+            message.mark_sent()?;
+            job.mark_in_progress();
+        }
+        Err(e) => {
+            warn!("Self-test dispatch to provider {} failed: {}", provider.id, e);
+            message.mark_failed(e.to_string());
+            job.mark_failed(e.to_string());
+            self_test.complete(false, Some(format!("Dispatch failed: {}", e)));
+        }
+    }
+
+    app_state
+        .database
+        .collection::<Message>("messages")
+        .insert_one(&message, None)
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to store self-test message: {}", e),
+        })?;
+    app_state
+        .database
+        .collection::<Job>("jobs")
+        .insert_one(&job, None)
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to store self-test job: {}", e),
+        })?;
+
+    store_self_test(&app_state, &provider.id, &self_test).await?;
+
+    Ok(Json(SelfTestResponse {
+        provider_id: provider.id,
+        message_id: self_test.message_id,
+        recipient: self_test.recipient,
+        status: format!("{:?}", self_test.status).to_lowercase(),
+        requested_at: self_test.requested_at.to_rfc3339(),
+    }))
+}
+
+/// Settle a provider's pending self-test from its delivery confirmation
+pub async fn record_self_test_result(
+    app_state: &AppState,
+    provider_id: &str,
+    message_id: &str,
+    passed: bool,
+    failure_reason: Option<String>,
+) -> Result<()> {
+    let provider = app_state
+        .database
+        .collection::<Provider>("providers")
+        .find_one(mongodb::bson::doc! {"id": provider_id}, None)
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch provider: {}", e),
+        })?;
+
+    // Ignore confirmations for superseded tests
+    let Some(mut self_test) = provider
+        .and_then(|provider| provider.self_test)
+        .filter(|test| test.message_id == message_id)
+    else {
+        return Ok(());
+    };
+
+    self_test.complete(passed, failure_reason);
+    info!("Provider {} self-test {:?}", provider_id, self_test.status);
+
+    store_self_test(app_state, provider_id, &self_test).await
+}
+
+async fn store_self_test(
+    app_state: &AppState,
+    provider_id: &str,
+    self_test: &ProviderSelfTest,
+) -> Result<()> {
+    let self_test = mongodb::bson::to_bson(self_test).map_err(|e| PeerPowerError::Internal {
+        message: format!("Failed to serialize self-test: {}", e),
+    })?;
+
+    app_state
+        .database
+        .collection::<Provider>("providers")
+        .update_one(
+            mongodb::bson::doc! {"id": provider_id},
+            mongodb::bson::doc! {
+                "$set": {
+                    "self_test": self_test,
+                    "updated_at": stored_timestamp(chrono::Utc::now()),
+                }
+            },
+            None,
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to store self-test result: {}", e),
+        })?;
+
+    Ok(())
+}
+
+/// Get the provider's recipient restrictions
+pub async fn get_recipient_rules(
+    State(app_state): State<Arc<AppState>>,
+    Path(provider_id): Path<String>,
+    ProviderUser(user_id): ProviderUser,
+) -> Result<Json<RecipientRulesPayload>> {
+    let provider = app_state
+        .database
+        .collection::<Provider>("providers")
+        .find_one(
+            mongodb::bson::doc! {
+                "id": &provider_id,
+                "user_id": &user_id,
+                "deleted_at": null
+            },
+            None,
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch provider: {}", e),
+        })?
+        .ok_or_else(|| PeerPowerError::NotFound {
+            resource: format!("Provider with ID: {}", provider_id),
+        })?;
+
+    Ok(Json(RecipientRulesPayload {
+        rules: provider.recipient_rules,
+    }))
+}
+
+/// Replace the provider's recipient restrictions (enforced during provider selection)
+pub async fn update_recipient_rules(
+    State(app_state): State<Arc<AppState>>,
+    Path(provider_id): Path<String>,
+    ProviderUser(user_id): ProviderUser,
+    JsonExtractor(request): JsonExtractor<RecipientRulesPayload>,
+) -> Result<Json<RecipientRulesPayload>> {
+    if request.rules.len() > MAX_RECIPIENT_RULES {
+        return Err(PeerPowerError::ValidationError {
+            field: "rules".to_string(),
+            message: format!("At most {} recipient rules are allowed", MAX_RECIPIENT_RULES),
+        });
+    }
+
+    for rule in &request.rules {
+        if let RecipientRule::DenyPrefix { prefix } = rule {
+            let digits = prefix.strip_prefix('+').unwrap_or_default();
+            if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+                return Err(PeerPowerError::ValidationError {
+                    field: "prefix".to_string(),
+                    message: format!("Invalid prefix '{}': must be '+' followed by digits", prefix),
+                });
+            }
+        }
+    }
+
+    let rules = mongodb::bson::to_bson(&request.rules).map_err(|e| PeerPowerError::Internal {
+        message: format!("Failed to serialize recipient rules: {}", e),
+    })?;
+
+    let result = app_state
+        .database
+        .collection::<Provider>("providers")
+        .update_one(
+            mongodb::bson::doc! {
+                "id": &provider_id,
+                "user_id": &user_id,
+                "deleted_at": null
+            },
+            mongodb::bson::doc! {
+                "$set": {
+                    "recipient_rules": rules,
+                    "updated_at": stored_timestamp(chrono::Utc::now()),
+                }
+            },
+            None,
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to update recipient rules: {}", e),
+        })?;
+
+    if result.matched_count == 0 {
+        return Err(PeerPowerError::NotFound {
+            resource: format!("Provider with ID: {}", provider_id),
+        });
+    }
+
+    info!(
+        "Provider {} recipient rules updated ({} rules)",
+        provider_id,
+        request.rules.len()
+    );
+
+    Ok(Json(request))
+}
+
+/// Evaluate a provider's onboarding checklist against its owning user
+async fn provider_onboarding(
+    app_state: &Arc<AppState>,
+    provider: &Provider,
+) -> Result<ProviderOnboarding> {
+    let phone_verified = app_state
+        .user_repository
+        .find_by_id(&provider.user_id)
+        .await?
+        .map(|user| user.is_verified)
+        .unwrap_or(false);
+
+    Ok(provider.onboarding(phone_verified))
+}
+
+/// Onboarding progress for the provider app
+pub async fn get_onboarding_status(
+    State(app_state): State<Arc<AppState>>,
+    Path(provider_id): Path<String>,
+    ProviderUser(user_id): ProviderUser,
+) -> Result<Json<OnboardingResponse>> {
+    let providers_collection = app_state.database.collection::<Provider>("providers");
+    let provider = providers_collection
+        .find_one(
+            mongodb::bson::doc! {
+                "id": &provider_id,
+                "user_id": &user_id,
+                "deleted_at": null
+            },
+            None,
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch provider: {}", e),
+        })?
+        .ok_or_else(|| PeerPowerError::NotFound {
+            resource: format!("Provider with ID: {}", provider_id),
+        })?;
+
+    let onboarding = provider_onboarding(&app_state, &provider).await?;
+
+    Ok(Json(OnboardingResponse {
+        provider_id: provider.id,
+        completed_steps: onboarding.completed_count(),
+        total_steps: onboarding.steps.len(),
+        complete: onboarding.is_complete(),
+        next_step: onboarding.pending_steps().first().copied(),
+        steps: onboarding.steps,
+    }))
+}
+
+/// Record the provider's KYC document submission
+pub async fn submit_kyc(
+    State(app_state): State<Arc<AppState>>,
+    Path(provider_id): Path<String>,
+    ProviderUser(user_id): ProviderUser,
+    client: ClientInfo,
+    JsonExtractor(kyc_request): JsonExtractor<SubmitKycRequest>,
+) -> Result<Json<OnboardingResponse>> {
+    kyc_request
+        .validate()
+        .map_err(|e| PeerPowerError::ValidationError {
+            field: "request".to_string(),
+            message: format!("Validation failed: {}", e),
+        })?;
+
+    let kyc = KycSubmission {
+        document_type: kyc_request.document_type.trim().to_lowercase(),
+        document_reference: kyc_request.document_reference,
+        submitted_at: chrono::Utc::now(),
+    };
+    let kyc_bson = mongodb::bson::to_bson(&kyc).map_err(|e| PeerPowerError::Internal {
+        message: format!("Failed to serialize KYC submission: {}", e),
+    })?;
+
+    let providers_collection = app_state.database.collection::<Provider>("providers");
+    let result = providers_collection
+        .update_one(
+            mongodb::bson::doc! {
+                "id": &provider_id,
+                "user_id": &user_id,
+                "deleted_at": null
+            },
+            mongodb::bson::doc! {
+                "$set": {
+                    "kyc": kyc_bson,
+                    "updated_at": stored_timestamp(chrono::Utc::now()),
+                }
+            },
+            None,
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to store KYC submission: {}", e),
+        })?;
+
+    if result.matched_count == 0 {
+        return Err(PeerPowerError::NotFound {
+            resource: format!("Provider with ID: {}", provider_id),
+        });
+    }
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(
+                Some(user_id.clone()),
+                "provider.kyc_submitted",
+                "provider",
+                &provider_id,
+            )
+            .with_client(client.ip, client.user_agent)
+            .with_metadata("document_type", kyc.document_type.clone()),
+        )
+        .await;
+
+    info!("KYC submitted for provider {}", provider_id);
+
+    get_onboarding_status(State(app_state), Path(provider_id), ProviderUser(user_id)).await
+}
+
+/// Enroll the device key for a provider registered before device keys existed.
+/// Replacing an enrolled key (e.g. a new phone) needs support to reset it first,
+/// so a stolen access token can't swap in its own key.
+pub async fn enroll_device_key(
+    State(app_state): State<Arc<AppState>>,
+    Path(provider_id): Path<String>,
+    ProviderUser(user_id): ProviderUser,
+    client: ClientInfo,
+    JsonExtractor(request): JsonExtractor<EnrollDeviceKeyRequest>,
+) -> Result<Json<DeviceKeyResponse>> {
+    let device_key = DeviceKeyVerifier::enroll(&request.device_public_key)?;
+
+    let providers_collection = app_state.database.collection::<Provider>("providers");
+    let provider = providers_collection
+        .find_one(
+            mongodb::bson::doc! {
+                "id": &provider_id,
+                "user_id": &user_id,
+                "deleted_at": null
+            },
+            None,
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch provider: {}", e),
+        })?
+        .ok_or_else(|| PeerPowerError::NotFound {
+            resource: format!("Provider with ID: {}", provider_id),
+        })?;
+
+    if provider.device_key.is_some() {
+        return Err(PeerPowerError::ValidationError {
+            field: "device_public_key".to_string(),
+            message: "A device key is already enrolled; contact support to replace it".to_string(),
+        });
+    }
+
+    let key_bson = mongodb::bson::to_bson(&device_key).map_err(|e| PeerPowerError::Internal {
+        message: format!("Failed to serialize device key: {}", e),
+    })?;
+    // Only fills an empty slot, so two racing enrollments can't both win
+    let result = providers_collection
+        .update_one(
+            mongodb::bson::doc! {
+                "id": &provider_id,
+                "user_id": &user_id,
+                "device_key": null
+            },
+            mongodb::bson::doc! {
+                "$set": {
+                    "device_key": key_bson,
+                    "updated_at": stored_timestamp(chrono::Utc::now()),
+                }
+            },
+            None,
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to store device key: {}", e),
+        })?;
+
+    if result.modified_count == 0 {
+        return Err(PeerPowerError::ValidationError {
+            field: "device_public_key".to_string(),
+            message: "A device key is already enrolled; contact support to replace it".to_string(),
+        });
+    }
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(
+                Some(user_id),
+                "provider.device_key_enrolled",
+                "provider",
+                &provider_id,
+            )
+            .with_client(client.ip, client.user_agent)
+            .with_metadata("fingerprint", device_key.fingerprint.clone()),
+        )
+        .await;
+
+    info!("Device key enrolled for provider {}", provider_id);
+
+    Ok(Json(DeviceKeyResponse {
+        provider_id,
+        fingerprint: device_key.fingerprint,
+        enrolled_at: device_key.enrolled_at.to_rfc3339(),
+    }))
+}
+
+/// Re-run Play Integrity attestation for the provider's device, e.g. for a
+/// provider registered before attestation existed. A failing verdict drops
+/// the daily limit back to the unattested cap.
+pub async fn attest_device(
+    State(app_state): State<Arc<AppState>>,
+    Path(provider_id): Path<String>,
+    ProviderUser(user_id): ProviderUser,
+    client: ClientInfo,
+    JsonExtractor(request): JsonExtractor<AttestDeviceRequest>,
+) -> Result<Json<AttestationResponse>> {
+    let providers_collection = app_state.database.collection::<Provider>("providers");
+    let provider = providers_collection
+        .find_one(
+            mongodb::bson::doc! {
+                "id": &provider_id,
+                "user_id": &user_id,
+                "deleted_at": null
+            },
+            None,
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch provider: {}", e),
+        })?
+        .ok_or_else(|| PeerPowerError::NotFound {
+            resource: format!("Provider with ID: {}", provider_id),
+        })?;
+
+    let Some(device_key) = provider.device_key.as_ref() else {
+        return Err(PeerPowerError::ValidationError {
+            field: "device_public_key".to_string(),
+            message: "Enroll a device key before attesting the device".to_string(),
+        });
+    };
+    let attestation = app_state
+        .play_integrity
+        .verify(&request.integrity_token, device_key)
+        .await?;
+
+    let unattested_cap = app_state.play_integrity.unattested_max_daily_messages();
+    let max_daily_messages = if attestation.passed {
+        provider.max_daily_messages
+    } else {
+        provider.max_daily_messages.min(unattested_cap)
+    };
+
+    let attestation_bson =
+        mongodb::bson::to_bson(&attestation).map_err(|e| PeerPowerError::Internal {
+            message: format!("Failed to serialize attestation: {}", e),
+        })?;
+    providers_collection
+        .update_one(
+            mongodb::bson::doc! {"id": &provider_id},
+            mongodb::bson::doc! {
+                "$set": {
+                    "attestation": attestation_bson,
+                    "max_daily_messages": max_daily_messages,
+                    "updated_at": stored_timestamp(chrono::Utc::now()),
+                }
+            },
+            None,
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to store attestation: {}", e),
+        })?;
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(
+                Some(user_id),
+                "provider.device_attested",
+                "provider",
+                &provider_id,
+            )
+            .with_client(client.ip, client.user_agent)
+            .with_metadata("passed", attestation.passed.to_string())
+            .with_metadata("device_verdicts", attestation.device_verdicts.join(",")),
+        )
+        .await;
+
+    Ok(Json(AttestationResponse {
+        provider_id,
+        passed: attestation.passed,
+        device_verdicts: attestation.device_verdicts,
+        app_verdict: attestation.app_verdict,
+        attested_at: attestation.attested_at.to_rfc3339(),
+        max_daily_messages,
+    }))
+}
+
+/// The provider's stake as last read from the staking contract
+pub async fn get_stake(
+    State(app_state): State<Arc<AppState>>,
+    Path(provider_id): Path<String>,
+    ProviderUser(user_id): ProviderUser,
+) -> Result<Json<StakeResponse>> {
+    let provider = app_state
+        .database
+        .collection::<Provider>("providers")
+        .find_one(
+            mongodb::bson::doc! {
+                "id": &provider_id,
+                "user_id": &user_id,
+                "deleted_at": null
+            },
+            None,
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch provider: {}", e),
+        })?
+        .ok_or_else(|| PeerPowerError::NotFound {
+            resource: format!("Provider with ID: {}", provider_id),
+        })?;
+
+    Ok(Json(StakeResponse {
+        provider_id,
+        staking_enabled: app_state.staking.is_configured(),
+        max_daily_messages: provider.daily_limit(),
+        priority: provider.has_stake_priority(),
+        stake: provider.stake,
+    }))
+}
+
+/// Re-read the provider's stake from the staking contract after staking or
+/// unstaking PPT from their wallet, and apply the limits it unlocks
+pub async fn sync_stake(
+    State(app_state): State<Arc<AppState>>,
+    Path(provider_id): Path<String>,
+    ProviderUser(user_id): ProviderUser,
+    client: ClientInfo,
+) -> Result<Json<StakeResponse>> {
+    let mut provider = app_state
+        .database
+        .collection::<Provider>("providers")
+        .find_one(
+            mongodb::bson::doc! {
+                "id": &provider_id,
+                "user_id": &user_id,
+                "deleted_at": null
+            },
+            None,
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch provider: {}", e),
+        })?
+        .ok_or_else(|| PeerPowerError::NotFound {
+            resource: format!("Provider with ID: {}", provider_id),
+        })?;
+
+    let stake = app_state.staking.sync(&provider).await?;
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(
+                Some(user_id),
+                "provider.stake_synced",
+                "provider",
+                &provider_id,
+            )
+            .with_client(client.ip, client.user_agent)
+            .with_metadata("wallet_address", stake.wallet_address.clone())
+            .with_metadata("amount", stake.amount.to_string()),
+        )
+        .await;
+
+    info!(
+        "Provider {} stake synced: {} PPT, daily limit {}",
+        provider_id, stake.amount, stake.max_daily_messages
+    );
+
+    provider.stake = Some(stake);
+    Ok(Json(StakeResponse {
+        provider_id,
+        staking_enabled: true,
+        max_daily_messages: provider.daily_limit(),
+        priority: provider.has_stake_priority(),
+        stake: provider.stake,
+    }))
+}
@@ -0,0 +1,78 @@
+use axum::{extract::State, response::Json};
+use serde::Serialize;
+use std::sync::Arc;
+
+use crate::domain::entities::{Referral, ReferralStatus};
+use crate::presentation::handlers::user_handlers::AuthenticatedUser;
+use crate::shared::{AppState, Result};
+
+#[derive(Debug, Serialize)]
+pub struct ReferralResponse {
+    pub id: String,
+    pub status: &'static str,
+    pub successful_deliveries: u32,
+    pub required_deliveries: u32,
+    pub bonus: f64, // PPT paid to the user this response is for
+    pub created_at: String,
+    pub rewarded_at: Option<String>,
+}
+
+impl ReferralResponse {
+    fn new(referral: &Referral, bonus: f64) -> Self {
+        Self {
+            id: referral.id.clone(),
+            status: referral.status.as_str(),
+            successful_deliveries: referral
+                .successful_deliveries
+                .min(referral.required_deliveries),
+            required_deliveries: referral.required_deliveries,
+            bonus,
+            created_at: referral.created_at.to_rfc3339(),
+            rewarded_at: referral.rewarded_at.map(|at| at.to_rfc3339()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReferralSummaryResponse {
+    pub code: String,
+    pub required_deliveries: u32,
+    pub referrer_bonus: f64,
+    pub referee_bonus: f64,
+    pub referrals: Vec<ReferralResponse>,
+    pub referred_by: Option<ReferralResponse>,
+    pub total_earned: f64, // PPT from rewarded referrals, either side
+}
+
+/// The caller's referral code, the users they referred and what it has earned
+pub async fn get_referrals(
+    State(app_state): State<Arc<AppState>>,
+    AuthenticatedUser(user_id): AuthenticatedUser,
+) -> Result<Json<ReferralSummaryResponse>> {
+    let code = app_state.referrals.code_for(&user_id).await?;
+    let (made, referred_by) = app_state.referrals.for_user(&user_id).await?;
+
+    let referrals: Vec<ReferralResponse> = made
+        .iter()
+        .map(|referral| ReferralResponse::new(referral, referral.referrer_bonus))
+        .collect();
+    let referred_by =
+        referred_by.map(|referral| ReferralResponse::new(&referral, referral.referee_bonus));
+    let total_earned = referrals
+        .iter()
+        .chain(referred_by.iter())
+        .filter(|referral| referral.status == ReferralStatus::Rewarded.as_str())
+        .map(|referral| referral.bonus)
+        .sum();
+
+    let config = app_state.referrals.config();
+    Ok(Json(ReferralSummaryResponse {
+        code: code.code,
+        required_deliveries: config.required_deliveries,
+        referrer_bonus: config.referrer_bonus,
+        referee_bonus: config.referee_bonus,
+        referrals,
+        referred_by,
+        total_earned,
+    }))
+}
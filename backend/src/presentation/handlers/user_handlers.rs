@@ -3,15 +3,20 @@ use axum::{
     extract::{FromRequestParts, Path, Request, State},
     http::{request::Parts, StatusCode},
     response::Json,
-    Json as JsonExtractor,
+    Extension, Json as JsonExtractor,
 };
+use futures::stream::TryStreamExt;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tracing::{info, warn};
 use validator::Validate;
 
-use crate::domain::entities::User;
+use crate::domain::entities::{
+    ApiClient, AuditLogEntry, AuthEvent, AuthEventKind, QuietHours, User, API_CLIENT_SCOPES,
+};
 use crate::domain::services::TokenClaims;
+use crate::presentation::middleware::ClientInfo;
+use crate::shared::utils::stored_timestamp;
 use crate::shared::{AppState, PeerPowerError, Result};
 
 /// Extractor for authenticated user ID
@@ -46,6 +51,7 @@ pub struct UserProfileResponse {
     pub reputation_score: f64,
     pub is_provider: bool,
     pub is_verified: bool,
+    pub quiet_hours: Option<QuietHours>, // none: the system-wide quiet hours apply
     pub created_at: String,
     pub updated_at: String,
 }
@@ -60,6 +66,7 @@ impl From<&User> for UserProfileResponse {
             reputation_score: user.reputation_score,
             is_provider: user.is_provider,
             is_verified: user.is_verified,
+            quiet_hours: user.quiet_hours,
             created_at: user.created_at.to_rfc3339(),
             updated_at: user.updated_at.to_rfc3339(),
         }
@@ -127,9 +134,12 @@ pub async fn update_user_profile(
             resource: format!("User with ID: {}", user_id),
         })?;
 
-    // Update fields
-    if let Some(did) = update_request.did {
-        user.did = Some(did);
+    // DIDs are issued by the platform or registered with a signed proof
+    if update_request.did.is_some() {
+        return Err(PeerPowerError::ValidationError {
+            field: "did".to_string(),
+            message: "Register a DID with PUT /users/did".to_string(),
+        });
     }
 
     if let Some(evm_address) = update_request.evm_address {
@@ -153,6 +163,43 @@ pub async fn update_user_profile(
     Ok(Json(UserProfileResponse::from(&user)))
 }
 
+#[derive(Debug, Deserialize)]
+pub struct QuietHoursRequest {
+    pub quiet_hours: Option<QuietHours>, // null to follow the system-wide quiet hours
+}
+
+/// Set the hours the user's messages are held instead of delivered, or go
+/// back to the system-wide ones (protected route)
+pub async fn update_quiet_hours(
+    AuthenticatedUser(user_id): AuthenticatedUser,
+    State(app_state): State<Arc<AppState>>,
+    JsonExtractor(request): JsonExtractor<QuietHoursRequest>,
+) -> Result<Json<UserProfileResponse>> {
+    if let Some(quiet_hours) = &request.quiet_hours {
+        quiet_hours
+            .validate()
+            .map_err(|message| PeerPowerError::ValidationError {
+                field: "quiet_hours".to_string(),
+                message,
+            })?;
+    }
+
+    let mut user = app_state
+        .user_repository
+        .find_by_id(&user_id)
+        .await?
+        .ok_or_else(|| PeerPowerError::NotFound {
+            resource: format!("User with ID: {}", user_id),
+        })?;
+
+    user.quiet_hours = request.quiet_hours;
+    user.updated_at = chrono::Utc::now();
+    app_state.user_repository.update(&user).await?;
+
+    info!("Updated quiet hours for user: {}", user_id);
+    Ok(Json(UserProfileResponse::from(&user)))
+}
+
 /// Register as a provider (protected route)
 pub async fn register_provider(
     AuthenticatedUser(user_id): AuthenticatedUser,
@@ -177,7 +224,7 @@ pub async fn register_provider(
     let collection = app_state.database.collection::<User>("users");
     let mut update_doc = mongodb::bson::doc! {
         "is_provider": true,
-        "updated_at": chrono::Utc::now()
+        "updated_at": stored_timestamp(chrono::Utc::now())
     };
 
     // Store provider-specific data (for now in the same collection)
@@ -213,3 +260,307 @@ pub async fn register_provider(
     info!("Successfully registered provider for user: {}", user_id);
     Ok(StatusCode::CREATED)
 }
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateApiClientRequest {
+    #[validate(length(min = 1, max = 100, message = "Name must be 1-100 characters"))]
+    pub name: String,
+    #[validate(length(min = 1, message = "At least one scope is required"))]
+    pub scopes: Vec<String>,
+    #[validate(range(min = 60, message = "Token lifetime must be at least 60 seconds"))]
+    pub token_lifetime_seconds: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiClientResponse {
+    pub client_id: String,
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub token_lifetime_seconds: i64,
+    pub created_at: String,
+    pub last_used_at: Option<String>,
+    pub revoked: bool,
+}
+
+impl ApiClientResponse {
+    fn new(client: &ApiClient, default_lifetime_seconds: i64) -> Self {
+        Self {
+            client_id: client.client_id.clone(),
+            name: client.name.clone(),
+            scopes: client.scopes.clone(),
+            token_lifetime_seconds: client
+                .token_lifetime_seconds
+                .unwrap_or(default_lifetime_seconds),
+            created_at: client.created_at.to_rfc3339(),
+            last_used_at: client.last_used_at.map(|at| at.to_rfc3339()),
+            revoked: client.is_revoked(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreateApiClientResponse {
+    #[serde(flatten)]
+    pub client: ApiClientResponse,
+    pub client_secret: String, // only ever returned here
+}
+
+/// Register an OAuth2 machine client for the current user
+pub async fn create_api_client(
+    AuthenticatedUser(user_id): AuthenticatedUser,
+    State(app_state): State<Arc<AppState>>,
+    client: ClientInfo,
+    JsonExtractor(request): JsonExtractor<CreateApiClientRequest>,
+) -> Result<(StatusCode, Json<CreateApiClientResponse>)> {
+    request.validate()?;
+
+    let auth_config = &app_state.config.auth;
+    if let Some(scope) = request
+        .scopes
+        .iter()
+        .find(|scope| !API_CLIENT_SCOPES.contains(&scope.as_str()))
+    {
+        return Err(PeerPowerError::ValidationError {
+            field: "scopes".to_string(),
+            message: format!(
+                "Unknown scope '{}' (allowed: {})",
+                scope,
+                API_CLIENT_SCOPES.join(", ")
+            ),
+        });
+    }
+    if request
+        .token_lifetime_seconds
+        .is_some_and(|lifetime| lifetime > auth_config.client_token_max_lifetime_seconds)
+    {
+        return Err(PeerPowerError::ValidationError {
+            field: "token_lifetime_seconds".to_string(),
+            message: format!(
+                "Token lifetime cannot exceed {} seconds",
+                auth_config.client_token_max_lifetime_seconds
+            ),
+        });
+    }
+
+    let client_secret = hex::encode(rand::random::<[u8; 32]>());
+    let mut scopes = request.scopes;
+    scopes.sort();
+    scopes.dedup();
+    let api_client = ApiClient::new(
+        user_id.clone(),
+        request.name,
+        crate::shared::utils::hash_password(&client_secret)?,
+        scopes,
+        request.token_lifetime_seconds,
+    );
+
+    app_state
+        .database
+        .collection::<ApiClient>("api_clients")
+        .insert_one(&api_client, None)
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to create API client: {}", e),
+        })?;
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(
+                Some(user_id.clone()),
+                "api_client.created",
+                "api_client",
+                &api_client.client_id,
+            )
+            .with_client(client.ip, client.user_agent)
+            .with_metadata("scopes", api_client.scopes.join(" ")),
+        )
+        .await;
+
+    info!(
+        "Created API client {} for user: {}",
+        api_client.client_id, user_id
+    );
+    Ok((
+        StatusCode::CREATED,
+        Json(CreateApiClientResponse {
+            client: ApiClientResponse::new(&api_client, auth_config.client_token_lifetime_seconds),
+            client_secret,
+        }),
+    ))
+}
+
+/// List the current user's machine clients
+pub async fn list_api_clients(
+    AuthenticatedUser(user_id): AuthenticatedUser,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<Vec<ApiClientResponse>>> {
+    let find_options = mongodb::options::FindOptions::builder()
+        .sort(mongodb::bson::doc! {"created_at": -1})
+        .build();
+    let clients: Vec<ApiClient> = app_state
+        .database
+        .collection::<ApiClient>("api_clients")
+        .find(
+            mongodb::bson::doc! {"owner_user_id": &user_id},
+            find_options,
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to fetch API clients: {}", e),
+        })?
+        .try_collect()
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to read API clients: {}", e),
+        })?;
+
+    let default_lifetime = app_state.config.auth.client_token_lifetime_seconds;
+    Ok(Json(
+        clients
+            .iter()
+            .map(|client| ApiClientResponse::new(client, default_lifetime))
+            .collect(),
+    ))
+}
+
+/// Revoke a machine client; tokens already issued stay valid until expiry
+pub async fn revoke_api_client(
+    AuthenticatedUser(user_id): AuthenticatedUser,
+    State(app_state): State<Arc<AppState>>,
+    Path(client_id): Path<String>,
+    client: ClientInfo,
+) -> Result<StatusCode> {
+    let result = app_state
+        .database
+        .collection::<ApiClient>("api_clients")
+        .update_one(
+            mongodb::bson::doc! {
+                "client_id": &client_id,
+                "owner_user_id": &user_id,
+                "revoked_at": null
+            },
+            revocation(),
+            None,
+        )
+        .await
+        .map_err(|e| PeerPowerError::Database {
+            message: format!("Failed to revoke API client: {}", e),
+        })?;
+
+    if result.matched_count == 0 {
+        return Err(PeerPowerError::NotFound {
+            resource: format!("API client: {}", client_id),
+        });
+    }
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(
+                Some(user_id),
+                "api_client.revoked",
+                "api_client",
+                &client_id,
+            )
+            .with_client(client.ip, client.user_agent),
+        )
+        .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionResponse {
+    pub id: String,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: String,
+    pub last_used_at: String,
+    pub current: bool, // the session making this request
+}
+
+/// Devices currently signed in to the caller's account
+pub async fn list_sessions(
+    Extension(claims): Extension<TokenClaims>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<Vec<SessionResponse>>> {
+    let sessions = app_state.sessions.list_active(&claims.sub).await?;
+
+    Ok(Json(
+        sessions
+            .into_iter()
+            .map(|session| SessionResponse {
+                current: claims.sid.as_deref() == Some(session.id.as_str()),
+                id: session.id,
+                ip_address: session.ip_address,
+                user_agent: session.user_agent,
+                created_at: session.created_at.to_rfc3339(),
+                last_used_at: session.last_used_at.to_rfc3339(),
+            })
+            .collect(),
+    ))
+}
+
+/// Sign out one device; its access and refresh tokens stop working at once
+pub async fn revoke_session(
+    AuthenticatedUser(user_id): AuthenticatedUser,
+    State(app_state): State<Arc<AppState>>,
+    Path(session_id): Path<String>,
+    client: ClientInfo,
+) -> Result<StatusCode> {
+    if app_state
+        .sessions
+        .find_active(&user_id, &session_id)
+        .await?
+        .is_none()
+    {
+        return Err(PeerPowerError::NotFound {
+            resource: format!("Session: {}", session_id),
+        });
+    }
+
+    app_state
+        .auth_service
+        .revoke_session(&user_id, &session_id)
+        .await?;
+    app_state.sessions.end(&session_id).await?;
+
+    app_state
+        .sessions
+        .record_event(
+            AuthEvent::new(
+                AuthEventKind::SessionRevoked,
+                Some(user_id),
+                Some(session_id),
+            )
+            .with_client(client.ip, client.user_agent),
+        )
+        .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn revocation() -> mongodb::bson::Document {
+    mongodb::bson::doc! {"$set": {"revoked_at": stored_timestamp(chrono::Utc::now())}}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::test_support::read_back;
+
+    #[test]
+    fn test_revoked_api_client_reads_back() {
+        let api_client = ApiClient::new(
+            "user-1".to_string(),
+            "Billing sync".to_string(),
+            "secret-hash".to_string(),
+            vec!["messages:send".to_string()],
+            None,
+        );
+
+        let revoked = read_back(&api_client, &revocation());
+        assert!(revoked.is_revoked());
+    }
+}
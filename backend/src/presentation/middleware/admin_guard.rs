@@ -0,0 +1,94 @@
+use axum::{
+    extract::{OriginalUri, Request, State},
+    http::Method,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::domain::entities::AuditLogEntry;
+use crate::domain::services::{TokenAudience, TokenClaims};
+use crate::presentation::middleware::ClientInfo;
+use crate::shared::{AppState, PeerPowerError};
+
+/// Admin role check for everything under `/api/v1/admin`. Runs after
+/// `auth_middleware`: the token must be a non-impersonation admin-console
+/// token held by a user listed in `ADMIN_USER_IDS`.
+pub async fn admin_guard_middleware(
+    State(app_state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(claims) = request.extensions().get::<TokenClaims>() else {
+        return PeerPowerError::AuthenticationFailed {
+            reason: "Missing access token".to_string(),
+        }
+        .into_response();
+    };
+
+    if !claims.allows(TokenAudience::Admin)
+        || claims.is_machine_client()
+        || claims.is_impersonation()
+    {
+        return PeerPowerError::InsufficientScope {
+            required: "an admin console token".to_string(),
+        }
+        .into_response();
+    }
+
+    let admins = &app_state.config.admin.user_ids;
+    let is_admin = if admins.is_empty() {
+        app_state.config.is_development()
+    } else {
+        admins.iter().any(|admin| admin == &claims.sub)
+    };
+    if !is_admin {
+        warn!("User {} denied access to the admin API", claims.sub);
+        metrics::counter!("admin_access_denied_total").increment(1);
+        return PeerPowerError::InsufficientScope {
+            required: "the admin role".to_string(),
+        }
+        .into_response();
+    }
+
+    next.run(request).await
+}
+
+/// Audit every admin API call with its outcome, on top of the domain events
+/// individual handlers record
+pub async fn admin_audit_middleware(
+    State(app_state): State<Arc<AppState>>,
+    client: ClientInfo,
+    request: Request,
+    next: Next,
+) -> Response {
+    let method = request.method().clone();
+    if method == Method::GET && !app_state.config.admin.audit_reads {
+        return next.run(request).await;
+    }
+
+    let actor = request
+        .extensions()
+        .get::<TokenClaims>()
+        .map(|claims| claims.sub.clone());
+    let path = request
+        .extensions()
+        .get::<OriginalUri>()
+        .map(|uri| uri.path().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+
+    let response = next.run(request).await;
+
+    app_state
+        .audit_logger
+        .record_best_effort(
+            AuditLogEntry::new(actor, "admin.request", "route", &path)
+                .with_client(client.ip, client.user_agent)
+                .with_metadata("method", method.as_str())
+                .with_metadata("status", response.status().as_u16().to_string()),
+        )
+        .await;
+
+    response
+}
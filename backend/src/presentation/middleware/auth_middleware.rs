@@ -7,7 +7,9 @@ use axum::{
 use std::sync::Arc;
 use tracing::warn;
 
+use crate::domain::entities::AuditLogEntry;
 use crate::domain::services::{AuthService, TokenClaims};
+use crate::infrastructure::audit_logger::with_impersonator;
 use crate::shared::{PeerPowerError, AppState};
 
 /// JWT authentication middleware
@@ -28,6 +30,31 @@ pub async fn auth_middleware<B>(
             StatusCode::UNAUTHORIZED
         })?;
     
+    // Support impersonation: audit every request made with the token
+    if let Some(admin_id) = claims.act.clone() {
+        let user_id = claims.sub.clone();
+        let method = request.method().to_string();
+        let path = request.uri().path().to_string();
+        request.extensions_mut().insert(claims);
+
+        let response = with_impersonator(admin_id.clone(), next.run(request)).await;
+        app_state
+            .audit_logger
+            .record_best_effort(
+                AuditLogEntry::new(
+                    Some(admin_id),
+                    "support.impersonated_request",
+                    "user",
+                    &user_id,
+                )
+                .with_metadata("method", method)
+                .with_metadata("path", path)
+                .with_metadata("status", response.status().as_u16().to_string()),
+            )
+            .await;
+        return Ok(response);
+    }
+
     // Add claims to request extensions for handlers to use
     request.extensions_mut().insert(claims);
     
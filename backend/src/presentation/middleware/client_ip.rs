@@ -0,0 +1,123 @@
+use async_trait::async_trait;
+use axum::{
+    extract::{ConnectInfo, FromRequestParts},
+    http::{header::USER_AGENT, request::Parts, HeaderMap},
+};
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+use crate::shared::AppState;
+
+/// Client network details for audit logging and rate limiting
+#[derive(Debug, Clone)]
+pub struct ClientInfo {
+    pub ip: Option<String>, // as reported, for audit logs; clients can forge it
+    pub trusted_ip: Option<String>, // the address limits can key on
+    pub user_agent: Option<String>,
+}
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for ClientInfo {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        app_state: &Arc<AppState>,
+    ) -> std::result::Result<Self, Self::Rejection> {
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+
+        let ip = forwarded_ip(&parts.headers).or_else(|| peer.map(|peer| peer.to_string()));
+        let trusted_ip = peer
+            .map(|peer| {
+                trusted_ip(
+                    peer,
+                    &parts.headers,
+                    &app_state.config.server.trusted_proxies,
+                )
+            })
+            .map(|ip| ip.to_string());
+
+        let user_agent = parts
+            .headers
+            .get(USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string());
+
+        Ok(ClientInfo {
+            ip,
+            trusted_ip,
+            user_agent,
+        })
+    }
+}
+
+/// First address in X-Forwarded-For, falling back to X-Real-IP (set by the load balancer)
+fn forwarded_ip(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get("x-forwarded-for")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(',').next())
+        .or_else(|| headers.get("x-real-ip").and_then(|value| value.to_str().ok()))
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// The connection's peer, unless it is one of our proxies: then the nearest
+/// X-Forwarded-For hop that isn't, read from the right, since each proxy
+/// appends the address it saw and anything further left is the client's say
+fn trusted_ip(peer: IpAddr, headers: &HeaderMap, trusted_proxies: &[IpAddr]) -> IpAddr {
+    if !trusted_proxies.contains(&peer) {
+        return peer;
+    }
+
+    let hops = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .collect::<Vec<_>>();
+
+    let mut nearest = peer;
+    for hop in hops.into_iter().rev() {
+        let Ok(hop) = hop.trim().parse::<IpAddr>() else {
+            break;
+        };
+        nearest = hop;
+        if !trusted_proxies.contains(&hop) {
+            break;
+        }
+    }
+    nearest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn forwarded(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", value.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_spoofed_forwarded_for_is_ignored() {
+        let client: IpAddr = "198.51.100.7".parse().unwrap();
+        let proxy: IpAddr = "10.0.0.2".parse().unwrap();
+
+        // Straight from the client, the header is not believed at all
+        let headers = forwarded("203.0.113.9");
+        assert_eq!(trusted_ip(client, &headers, &[proxy]), client);
+
+        // Through the proxy, the forged leftmost entry is skipped
+        let headers = forwarded("203.0.113.9, 198.51.100.7");
+        assert_eq!(trusted_ip(proxy, &headers, &[proxy]), client);
+
+        // Nothing usable forwarded: the proxy itself is what we know
+        assert_eq!(trusted_ip(proxy, &forwarded("unknown"), &[proxy]), proxy);
+    }
+}
@@ -0,0 +1,71 @@
+use axum::http::{HeaderName, HeaderValue, Method};
+use std::time::Duration;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+use crate::config::AppConfig;
+use crate::shared::{PeerPowerError, Result};
+
+/// Build the CORS layer from `CorsConfig`.
+///
+/// Invalid entries fail startup rather than being skipped, so a typo in an
+/// origin can't silently lock a frontend out. A wildcard origin is refused in
+/// production, and with credentials anywhere (browsers reject that pairing).
+pub fn cors_layer(config: &AppConfig) -> Result<CorsLayer> {
+    let cors = &config.cors;
+    let invalid = |setting: &str, value: &str| PeerPowerError::Configuration {
+        message: format!("Invalid {} entry: {}", setting, value),
+    };
+
+    let any_origin = cors.allowed_origins.iter().any(|origin| origin == "*");
+    if any_origin && config.is_production() {
+        return Err(PeerPowerError::Configuration {
+            message: "CORS_ALLOWED_ORIGINS cannot be \"*\" in production".to_string(),
+        });
+    }
+    if any_origin && cors.allow_credentials {
+        return Err(PeerPowerError::Configuration {
+            message: "CORS_ALLOW_CREDENTIALS requires explicit CORS_ALLOWED_ORIGINS".to_string(),
+        });
+    }
+
+    let allow_origin = if any_origin {
+        AllowOrigin::any()
+    } else {
+        let origins = cors
+            .allowed_origins
+            .iter()
+            .map(|origin| {
+                HeaderValue::from_str(origin.trim_end_matches('/'))
+                    .map_err(|_| invalid("CORS_ALLOWED_ORIGINS", origin))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        AllowOrigin::list(origins)
+    };
+    let methods = cors
+        .allowed_methods
+        .iter()
+        .map(|method| {
+            Method::from_bytes(method.as_bytes())
+                .map_err(|_| invalid("CORS_ALLOWED_METHODS", method))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let headers = cors
+        .allowed_headers
+        .iter()
+        .map(|header| {
+            HeaderName::from_bytes(header.as_bytes())
+                .map_err(|_| invalid("CORS_ALLOWED_HEADERS", header))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(CorsLayer::new()
+        .allow_origin(allow_origin)
+        .allow_methods(methods)
+        .allow_headers(headers)
+        .allow_credentials(cors.allow_credentials)
+        .expose_headers([
+            HeaderName::from_static("x-request-id"),
+            axum::http::header::RETRY_AFTER,
+        ])
+        .max_age(Duration::from_secs(cors.max_age_seconds)))
+}
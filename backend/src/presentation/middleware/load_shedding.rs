@@ -0,0 +1,38 @@
+use axum::{
+    extract::{Request, State},
+    http::{header::RETRY_AFTER, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+
+use crate::shared::{AppState, PeerPowerError};
+
+/// Rejects routes shed at the current load level with `503` and the reason
+pub async fn load_shedding_middleware(
+    State(app_state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(status) = app_state.load_shedder.check(request.uri().path()) else {
+        return next.run(request).await;
+    };
+
+    let mut response = PeerPowerError::ServiceDegraded {
+        level: status.level.as_str().to_string(),
+        reason: status.reason.unwrap_or("unknown").to_string(),
+    }
+    .into_response();
+    // Levels are re-evaluated every sample, so retrying soon is reasonable
+    response.headers_mut().insert(
+        RETRY_AFTER,
+        HeaderValue::from(
+            app_state
+                .config
+                .load_shedding
+                .sample_interval_seconds
+                .max(1),
+        ),
+    );
+    response
+}
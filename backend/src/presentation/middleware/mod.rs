@@ -1,3 +1,17 @@
+pub mod admin_guard;
 pub mod auth_middleware;
+pub mod client_ip;
+pub mod cors;
+pub mod load_shedding;
+pub mod rate_limit;
+pub mod request_guard;
+pub mod token_scope;
 
+pub use admin_guard::*;
 pub use auth_middleware::*;
+pub use client_ip::*;
+pub use cors::*;
+pub use load_shedding::*;
+pub use rate_limit::*;
+pub use request_guard::*;
+pub use token_scope::*;
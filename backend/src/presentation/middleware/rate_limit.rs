@@ -0,0 +1,43 @@
+use axum::{
+    extract::{Request, State},
+    http::{header::RETRY_AFTER, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+
+use crate::infrastructure::RateDecision;
+use crate::presentation::middleware::ClientInfo;
+use crate::shared::{AppState, PeerPowerError};
+
+/// Per-IP rate limiting; over-limit requests get `429` with `Retry-After`
+pub async fn rate_limit_middleware(
+    State(app_state): State<Arc<AppState>>,
+    client: ClientInfo,
+    request: Request,
+    next: Next,
+) -> Response {
+    // Without a peer address (never the case behind `axum::serve`) requests
+    // share one bucket rather than going unlimited
+    let ip = client.trusted_ip.unwrap_or_else(|| "unknown".to_string());
+
+    match app_state
+        .rate_limiter
+        .check(&ip, request.uri().path())
+        .await
+    {
+        RateDecision::Allowed => next.run(request).await,
+        RateDecision::Limited {
+            retry_after_seconds,
+        } => {
+            let mut response = PeerPowerError::RateLimitExceeded {
+                resource: format!("requests; retry in {}s", retry_after_seconds),
+            }
+            .into_response();
+            response
+                .headers_mut()
+                .insert(RETRY_AFTER, HeaderValue::from(retry_after_seconds));
+            response
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use axum::{
+    extract::{Request, State},
+    http::{
+        header::{CONTENT_LENGTH, CONTENT_TYPE, TRANSFER_ENCODING},
+        Method, StatusCode,
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+
+use crate::shared::{AppState, PeerPowerError};
+
+/// Body types handlers accept: JSON, the OAuth token form, and settlement CSV uploads
+const ACCEPTED_CONTENT_TYPES: &[&str] = &[
+    "application/json",
+    "application/x-www-form-urlencoded",
+    "text/csv",
+    "text/plain",
+];
+
+/// Longest extractor rejection text carried into the error message
+const MAX_REJECTION_BYTES: usize = 4096;
+
+/// Rejects oversized or unexpected request bodies up front, and rewrites
+/// axum's plain-text extractor rejections into the standard error body so
+/// malformed payloads get a `422 INVALID_PAYLOAD` like any other error.
+///
+/// Chunked bodies without a Content-Length are capped by the
+/// `DefaultBodyLimit` layer installed alongside this one.
+pub async fn request_guard_middleware(
+    State(app_state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let limit_bytes = app_state.config.server.max_body_bytes;
+    let headers = request.headers();
+
+    let content_length = headers
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+    if content_length.is_some_and(|length| length > limit_bytes) {
+        return PeerPowerError::PayloadTooLarge { limit_bytes }.into_response();
+    }
+
+    let content_type = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string())
+        .unwrap_or_else(|| "none".to_string());
+    let has_body =
+        content_length.is_some_and(|length| length > 0) || headers.contains_key(TRANSFER_ENCODING);
+    let sends_body = matches!(
+        *request.method(),
+        Method::POST | Method::PUT | Method::PATCH
+    );
+    if sends_body && has_body && !is_accepted_content_type(&content_type) {
+        return PeerPowerError::UnsupportedMediaType { content_type }.into_response();
+    }
+
+    let response = next.run(request).await;
+    normalize_rejection(response, limit_bytes, content_type).await
+}
+
+fn is_accepted_content_type(content_type: &str) -> bool {
+    let media_type = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    ACCEPTED_CONTENT_TYPES.contains(&media_type.as_str())
+        || (media_type.starts_with("application/") && media_type.ends_with("+json"))
+}
+
+/// Our own errors are JSON; a plain-text client error can only be an
+/// extractor rejection (bad JSON, wrong field types, bad path or query)
+async fn normalize_rejection(
+    response: Response,
+    limit_bytes: usize,
+    content_type: String,
+) -> Response {
+    let status = response.status();
+    let is_plain_text = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/plain"));
+    let is_rejection = matches!(
+        status,
+        StatusCode::BAD_REQUEST
+            | StatusCode::PAYLOAD_TOO_LARGE
+            | StatusCode::UNSUPPORTED_MEDIA_TYPE
+            | StatusCode::UNPROCESSABLE_ENTITY
+    );
+    if !is_plain_text || !is_rejection {
+        return response;
+    }
+
+    let message = match axum::body::to_bytes(response.into_body(), MAX_REJECTION_BYTES).await {
+        Ok(bytes) => String::from_utf8_lossy(&bytes).trim().to_string(),
+        Err(_) => "Malformed request".to_string(),
+    };
+
+    match status {
+        StatusCode::PAYLOAD_TOO_LARGE => PeerPowerError::PayloadTooLarge { limit_bytes },
+        StatusCode::UNSUPPORTED_MEDIA_TYPE => PeerPowerError::UnsupportedMediaType { content_type },
+        _ => PeerPowerError::InvalidPayload { message },
+    }
+    .into_response()
+}
@@ -0,0 +1,148 @@
+use async_trait::async_trait;
+use axum::{
+    extract::FromRequestParts,
+    http::{request::Parts, Method},
+};
+
+use crate::domain::services::{TokenAudience, TokenClaims};
+use crate::shared::{PeerPowerError, Result};
+
+/// Authenticated user of the client (messaging) app, or a machine client
+/// holding the scope the endpoint needs
+pub struct ClientUser(pub String);
+
+/// Authenticated user of the provider app (jobs, earnings, payouts)
+pub struct ProviderUser(pub String);
+
+/// Authenticated user of the admin console
+pub struct AdminUser(pub String);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ClientUser
+where
+    S: Send + Sync,
+{
+    type Rejection = PeerPowerError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self> {
+        authorize(parts, TokenAudience::Client).map(ClientUser)
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ProviderUser
+where
+    S: Send + Sync,
+{
+    type Rejection = PeerPowerError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self> {
+        authorize(parts, TokenAudience::Provider).map(ProviderUser)
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for AdminUser
+where
+    S: Send + Sync,
+{
+    type Rejection = PeerPowerError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self> {
+        authorize(parts, TokenAudience::Admin).map(AdminUser)
+    }
+}
+
+/// Check the token's app against the endpoint's, returning the user id.
+/// Machine clients only reach client endpoints, and only with the scope
+/// that endpoint maps to.
+fn authorize(parts: &Parts, audience: TokenAudience) -> Result<String> {
+    let claims = parts.extensions.get::<TokenClaims>().ok_or_else(|| {
+        PeerPowerError::AuthenticationFailed {
+            reason: "Missing access token".to_string(),
+        }
+    })?;
+
+    if !claims.allows(audience) {
+        return Err(PeerPowerError::InsufficientScope {
+            required: format!("a {} app token", audience.as_str()),
+        });
+    }
+
+    // Impersonation is for seeing what the user sees, not for admin work
+    if claims.is_impersonation() && audience == TokenAudience::Admin {
+        return Err(PeerPowerError::InsufficientScope {
+            required: "a non-impersonation admin token".to_string(),
+        });
+    }
+
+    if claims.is_machine_client() {
+        let required = match audience {
+            TokenAudience::Client => machine_client_scope(&parts.method, parts.uri.path()),
+            _ => None,
+        };
+        match required {
+            Some(scope) if claims.has_scope(scope) => {}
+            Some(scope) => {
+                return Err(PeerPowerError::InsufficientScope {
+                    required: format!("scope {}", scope),
+                })
+            }
+            None => {
+                return Err(PeerPowerError::InsufficientScope {
+                    required: "a user token".to_string(),
+                })
+            }
+        }
+    }
+
+    Ok(claims.sub.clone())
+}
+
+/// Scope a machine client needs for a client endpoint; `None` means the
+/// endpoint is for signed-in users only
+pub fn machine_client_scope(method: &Method, path: &str) -> Option<&'static str> {
+    let path = path.trim_end_matches('/');
+    let path = path.strip_prefix("/api/v1").unwrap_or(path);
+
+    if *method == Method::POST && matches!(path, "/messages/send" | "/messages/quote") {
+        Some("messages:send")
+    } else if path.starts_with("/analytics/") {
+        Some("analytics:read")
+    } else if path.starts_with("/messages") && *method == Method::GET {
+        Some("messages:read")
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_machine_client_scope_by_route() {
+        assert_eq!(
+            machine_client_scope(&Method::POST, "/api/v1/messages/send"),
+            Some("messages:send")
+        );
+        assert_eq!(
+            machine_client_scope(&Method::GET, "/messages/msg-1"),
+            Some("messages:read")
+        );
+        assert_eq!(
+            machine_client_scope(&Method::GET, "/analytics/tags"),
+            Some("analytics:read")
+        );
+        // Saved filters, exports and dedicated numbers stay with signed-in users
+        assert_eq!(
+            machine_client_scope(&Method::POST, "/messages/filters"),
+            None
+        );
+        assert_eq!(
+            machine_client_scope(&Method::POST, "/messages/export"),
+            None
+        );
+        assert_eq!(machine_client_scope(&Method::GET, "/numbers"), None);
+    }
+}
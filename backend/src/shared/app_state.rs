@@ -1,11 +1,64 @@
 use std::sync::Arc;
 
 use crate::config::AppConfig;
-use crate::domain::repositories::UserRepository;
-use crate::domain::services::AuthService;
+use crate::domain::repositories::{JobRepository, MessageRepository, UserRepository};
+use crate::domain::services::{AuthService, BlockchainService, DispatchStrategy, OtpChannel};
+use crate::infrastructure::audit_logger::AuditLogger;
 use crate::infrastructure::auth_service_impl::AuthServiceImpl;
+use crate::infrastructure::backup_service::BackupService;
+use crate::infrastructure::blockchain::{ChainSettler, ProviderStaking, SelendraClient};
+use crate::infrastructure::canary::CanaryRouter;
+use crate::infrastructure::carrier_pause::CarrierKillSwitch;
+use crate::infrastructure::carrier_redetection::CarrierRedetector;
 use crate::infrastructure::database::user_repository::MongoUserRepository;
+use crate::infrastructure::database::{MongoJobRepository, MongoMessageRepository};
+use crate::infrastructure::delivery_confirmations::DeliveryConfirmations;
+use crate::infrastructure::delivery_prediction::DeliveryPredictor;
+use crate::infrastructure::device_keys::DeviceKeyVerifier;
+use crate::infrastructure::cancellations::CancellationService;
+use crate::infrastructure::dead_letters::DeadLetterQueue;
+use crate::infrastructure::job_admin::JobAdmin;
+use crate::infrastructure::dispatch_strategies::dispatch_strategy;
+use crate::infrastructure::disputes::DisputeService;
+use crate::infrastructure::earnings_reconciler::EarningsReconciler;
+use crate::infrastructure::event_bus::EventBus;
+use crate::infrastructure::identity::IdentityService;
+use crate::infrastructure::impact_analysis::ImpactAnalyzer;
+use crate::infrastructure::job_outbox::JobOutbox;
+use crate::infrastructure::job_queue::JobQueue;
+use crate::infrastructure::jwt_keys::JwtKeySet;
+use crate::infrastructure::ledger::Ledger;
+use crate::infrastructure::load_shedder::LoadShedder;
+use crate::infrastructure::intake_backpressure::IntakeBackpressure;
+use crate::infrastructure::login_lockout::LoginLockout;
+use crate::infrastructure::message_changes::MessageChangeFeed;
 use crate::infrastructure::messaging::fcm_service::{FcmService, FcmServiceImpl};
+use crate::infrastructure::messaging::otp_sms::SmsOtpChannel;
+use crate::infrastructure::messaging::otp_telegram::TelegramOtpChannel;
+use crate::infrastructure::messaging::otp_voice::VoiceOtpChannel;
+use crate::infrastructure::messaging::sms_gateway::SmsGatewayClient;
+use crate::infrastructure::number_pool::NumberPool;
+use crate::infrastructure::provider_cache::ProviderCache;
+use crate::infrastructure::otp_challenge::OtpChallenger;
+use crate::infrastructure::payments::{
+    BarayClient, BarayWebhookEvents, ExchangeRates, PayoutProcessor, SettlementReconciler,
+    TopUpService,
+};
+use crate::infrastructure::phone_backfill::PhoneEncryptionBackfill;
+use crate::infrastructure::play_integrity::PlayIntegrityVerifier;
+use crate::infrastructure::quality_bonuses::QualityBonuses;
+use crate::infrastructure::pricing_plans::PricingPlans;
+use crate::infrastructure::rate_limiter::RateLimiter;
+use crate::infrastructure::recipient_privacy::RecipientVault;
+use crate::infrastructure::referrals::ReferralService;
+use crate::infrastructure::retention::RetentionTask;
+use crate::infrastructure::routing_rules::RoutingRuleEngine;
+use crate::infrastructure::session_store::SessionStore;
+use crate::infrastructure::sla_monitor::SlaMonitor;
+use crate::infrastructure::storage::ObjectStorageClient;
+use crate::infrastructure::surge_pricing::SurgePricing;
+use crate::infrastructure::volume_bonuses::VolumeBonuses;
+use crate::infrastructure::webhook_signature::WebhookVerifier;
 use crate::shared::Result;
 
 // Application state for dependency injection
@@ -13,14 +66,72 @@ use crate::shared::Result;
 pub struct AppState {
     pub config: AppConfig,
     pub database: crate::infrastructure::database::MongoDatabase,
+    pub reporting: crate::infrastructure::database::MongoDatabase, // read-only analytics, may lag
     pub redis: crate::infrastructure::database::RedisConnection,
     pub auth_service: Arc<dyn AuthService>,
+    pub jwt_keys: Arc<JwtKeySet>,
+    pub sessions: Arc<SessionStore>,
+    pub telegram: Arc<TelegramOtpChannel>,
     pub user_repository: Arc<dyn UserRepository>,
+    pub message_repository: Arc<dyn MessageRepository>,
+    pub job_repository: Arc<dyn JobRepository>,
     pub fcm_service: Arc<dyn FcmService>,
+    pub dispatch_strategy: Arc<dyn DispatchStrategy>,
+    pub routing_rules: Arc<RoutingRuleEngine>,
+    pub job_queue: Arc<JobQueue>,
+    pub job_outbox: Arc<JobOutbox>,
+    pub delivery_confirmations: Arc<DeliveryConfirmations>,
+    pub dead_letters: Arc<DeadLetterQueue>,
+    pub canary: Arc<CanaryRouter>,
+    pub audit_logger: Arc<AuditLogger>,
+    pub baray_client: Arc<BarayClient>,
+    pub settlement_reconciler: Arc<SettlementReconciler>,
+    pub exchange_rates: Arc<ExchangeRates>,
+    pub ledger: Arc<Ledger>,
+    pub topups: Arc<TopUpService>,
+    pub baray_events: Arc<BarayWebhookEvents>,
+    pub payout_processor: Arc<PayoutProcessor>,
+    pub referrals: Arc<ReferralService>,
+    pub volume_bonuses: Arc<VolumeBonuses>,
+    pub quality_bonuses: Arc<QualityBonuses>,
+    pub disputes: Arc<DisputeService>,
+    pub cancellations: Arc<CancellationService>,
+    pub sla_monitor: Arc<SlaMonitor>,
+    pub retention: Arc<RetentionTask>,
+    pub job_admin: Arc<JobAdmin>,
+    pub earnings_reconciler: Arc<EarningsReconciler>,
+    pub pricing_plans: Arc<PricingPlans>,
+    pub surge_pricing: Arc<SurgePricing>,
+    pub blockchain: Arc<dyn BlockchainService>,
+    pub chain_settler: Arc<ChainSettler>,
+    pub staking: Arc<ProviderStaking>,
+    pub backup_service: Arc<BackupService>,
+    pub recipient_vault: Arc<RecipientVault>,
+    pub delivery_predictor: Arc<DeliveryPredictor>,
+    pub carrier_kill_switch: Arc<CarrierKillSwitch>,
+    pub provider_cache: Arc<ProviderCache>,
+    pub events: Arc<EventBus>,
+    pub message_changes: Arc<MessageChangeFeed>,
+    pub carrier_redetector: Arc<CarrierRedetector>,
+    pub impact_analyzer: Arc<ImpactAnalyzer>,
+    pub phone_backfill: Arc<PhoneEncryptionBackfill>,
+    pub rate_limiter: Arc<RateLimiter>,
+    pub login_lockout: Arc<LoginLockout>,
+    pub otp_challenger: Arc<OtpChallenger>,
+    pub load_shedder: Arc<LoadShedder>,
+    pub intake_backpressure: Arc<IntakeBackpressure>,
+    pub number_pool: Arc<NumberPool>,
+    pub webhook_verifier: Arc<WebhookVerifier>,
+    pub device_keys: Arc<DeviceKeyVerifier>,
+    pub identity: Arc<IdentityService>,
+    pub play_integrity: Arc<PlayIntegrityVerifier>,
 }
 
 impl AppState {
     pub async fn new(config: AppConfig) -> Result<Self> {
+        // Phone numbers are encrypted on their way into MongoDB, so the key comes first
+        crate::shared::field_encryption::install(&config.privacy)?;
+
         // Initialize database connections
         let database =
             crate::infrastructure::database::MongoDatabase::new(&config.database).await?;
@@ -33,25 +144,377 @@ impl AppState {
         let user_repo = Arc::new(MongoUserRepository::new(Arc::new(
             database.database().clone(),
         )));
+        let message_repository = Arc::new(MongoMessageRepository::new(Arc::new(
+            database.database().clone(),
+        )));
+        let job_repository = Arc::new(MongoJobRepository::new(Arc::new(
+            database.database().clone(),
+        )));
+
+        // Create job queue
+        let job_queue = Arc::new(JobQueue::new(redis.clone(), config.instance.id.clone()));
+
+        // New jobs are stored with an outbox entry and relayed to Redis
+        let job_outbox = Arc::new(JobOutbox::new(
+            database.client().clone(),
+            Arc::new(database.database().clone()),
+            redis.clone(),
+            job_queue.clone(),
+        ));
+
+        // A provider's report updates its message, job and stats together
+        let delivery_confirmations = Arc::new(DeliveryConfirmations::new(
+            database.client().clone(),
+            Arc::new(database.database().clone()),
+        ));
+
+        // Jobs that used up their retries, held for admin review
+        let dead_letters = Arc::new(DeadLetterQueue::new(
+            Arc::new(database.database().clone()),
+            job_queue.clone(),
+        ));
+
+        // OTP channels: SMS through the network (with an external gateway
+        // fallback), Telegram for linked numbers, and voice calls
+        let telegram = Arc::new(TelegramOtpChannel::new(
+            config.external.telegram.clone(),
+            Arc::new(database.database().clone()),
+        ));
+        let otp_channels: Vec<Arc<dyn OtpChannel>> = vec![
+            Arc::new(SmsOtpChannel::new(
+                Arc::new(database.database().clone()),
+                job_queue.clone(),
+                job_outbox.clone(),
+                Arc::new(SmsGatewayClient::new(config.external.sms_gateway.clone())),
+            )),
+            telegram.clone(),
+            Arc::new(VoiceOtpChannel::new(config.external.voice_gateway.clone())),
+        ];
 
-        // Create auth service
+        // Load token signing keys and create auth service
+        let jwt_keys = Arc::new(JwtKeySet::load(&config.auth, config.is_production())?);
         let auth_service: Arc<dyn AuthService> = Arc::new(AuthServiceImpl::new(
             config.auth.clone(),
             Arc::new(redis.clone()),
             user_repo.clone(),
+            jwt_keys.clone(),
+            otp_channels,
         ));
 
+        // Device sessions and the auth event log
+        let sessions = Arc::new(SessionStore::new(Arc::new(database.database().clone())));
+
         // Create FCM service
-        let fcm_service: Arc<dyn FcmService> =
-            Arc::new(FcmServiceImpl::new(config.external.fcm.clone()));
+        let fcm_service: Arc<dyn FcmService> = Arc::new(FcmServiceImpl::new(
+            config.external.fcm.clone(),
+            config.retries.fcm.clone(),
+        ));
+
+        // Create dispatch strategy
+        let dispatch_strategy = dispatch_strategy(config.jobs.dispatch_strategy);
+
+        // Create routing rule engine (operator overrides applied before selection)
+        let routing_rules = Arc::new(RoutingRuleEngine::new(Arc::new(
+            database.database().clone(),
+        )));
+
+        // Create canary router
+        let canary = Arc::new(CanaryRouter::new(redis.clone(), config.instance.clone()));
+
+        // Create audit logger
+        let audit_logger = Arc::new(AuditLogger::new(Arc::new(database.database().clone())));
+
+        // Create Baray client
+        let baray_client = Arc::new(BarayClient::new(config.external.baray.clone()));
+        let exchange_rates = Arc::new(ExchangeRates::new(
+            config.exchange_rates.clone(),
+            redis.clone(),
+        ));
+
+        // Double-entry ledger of charges, earnings, fees and top-ups
+        let ledger = Arc::new(Ledger::new(Arc::new(database.database().clone())));
+        let settlement_reconciler = Arc::new(SettlementReconciler::new(
+            Arc::new(database.database().clone()),
+            ledger.clone(),
+        ));
+
+        // Client top-ups through Baray checkout
+        let topups = Arc::new(TopUpService::new(
+            Arc::new(database.database().clone()),
+            baray_client.clone(),
+            exchange_rates.clone(),
+            ledger.clone(),
+            config.external.baray.clone(),
+        ));
+
+        // Baray webhook event ids, so redeliveries are applied once
+        let baray_events = Arc::new(BarayWebhookEvents::new(Arc::new(
+            database.database().clone(),
+        )));
+
+        // Approved provider payouts sent out as Baray disbursements
+        let payout_processor = Arc::new(PayoutProcessor::new(
+            Arc::new(database.database().clone()),
+            redis.clone(),
+            baray_client.clone(),
+            ledger.clone(),
+            config.payouts.clone(),
+        ));
+
+        // Selendra transactions from the platform key, and provider earnings
+        // settled as PPT transfers through them
+        let blockchain: Arc<dyn BlockchainService> =
+            Arc::new(SelendraClient::new(config.external.selendra.clone())?);
+        let chain_settler = Arc::new(ChainSettler::new(
+            Arc::new(database.database().clone()),
+            redis.clone(),
+            blockchain.clone(),
+            ledger.clone(),
+            config.external.selendra.clone(),
+        ));
+
+        // PPT staked by providers for higher limits, slashed for fraud
+        let staking = Arc::new(ProviderStaking::new(
+            Arc::new(database.database().clone()),
+            blockchain.clone(),
+            config.staking.clone(),
+            &config.external.selendra,
+            config.external.play_integrity.unattested_max_daily_messages,
+        ));
+
+        // Create backup service over encrypted object storage
+        let backup_service = Arc::new(BackupService::new(
+            Arc::new(database.database().clone()),
+            Arc::new(ObjectStorageClient::new(config.backups.clone())),
+        ));
+
+        // Recipient encryption for clients in privacy mode
+        let recipient_vault = Arc::new(RecipientVault::new(&config.privacy)?);
+
+        // Delivery-time estimates for quotes, send responses and SLA tracking
+        let delivery_predictor = Arc::new(DeliveryPredictor::new(
+            Arc::new(database.database().clone()),
+            job_queue.clone(),
+            config.external.delivery_model.clone(),
+            config.earnings.utc_offset_hours,
+        ));
+
+        // Carrier kill switch, and dry runs / confirmations for bulk admin actions
+        let carrier_kill_switch = Arc::new(CarrierKillSwitch::new(redis.clone()));
+        // Online providers by carrier, so dispatch can skip the candidates query
+        let provider_cache = Arc::new(ProviderCache::new(redis.clone()));
+        // Delivery, provider and job events, shared with every instance
+        let events = Arc::new(EventBus::new(redis.clone(), config.instance.id.clone()));
+        let message_changes = Arc::new(MessageChangeFeed::new(
+            Arc::new(database.database().clone()),
+            redis.clone(),
+            events.clone(),
+            config.instance.id.clone(),
+            config.sla.webhook_secret.clone(),
+            config.change_streams.clone(),
+        ));
+        let carrier_redetector = Arc::new(CarrierRedetector::new(
+            Arc::new(database.database().clone()),
+            recipient_vault.clone(),
+        ));
+        let impact_analyzer = Arc::new(ImpactAnalyzer::new(
+            Arc::new(database.database().clone()),
+            redis.clone(),
+        ));
+        let phone_backfill = Arc::new(PhoneEncryptionBackfill::new(Arc::new(
+            database.database().clone(),
+        )));
+
+        // Per-IP request limits, shared across instances through Redis
+        let rate_limiter = Arc::new(RateLimiter::new(redis.clone(), config.rate_limits.clone()));
+
+        // Escalating lockouts after repeated failed OTP verifications
+        let login_lockout = Arc::new(LoginLockout::new(redis.clone(), config.lockout.clone()));
+
+        // Proof-of-work before OTPs are sent to unfamiliar IPs
+        let otp_challenger = Arc::new(OtpChallenger::new(
+            redis.clone(),
+            config.otp_challenge.clone(),
+        ));
+
+        // Sheds analytics and exports first when Mongo/Redis slow down
+        let load_shedder = Arc::new(LoadShedder::new(
+            database.clone(),
+            redis.clone(),
+            job_queue.clone(),
+            config.load_shedding.clone(),
+        ));
+
+        // Holds back low-priority sends while the job backlog is over its limits
+        let intake_backpressure = Arc::new(IntakeBackpressure::new(
+            Arc::new(database.database().clone()),
+            job_queue.clone(),
+            config.backpressure.clone(),
+        ));
+
+        // Dedicated numbers rented to clients, and their monthly billing
+        let number_pool = Arc::new(NumberPool::new(
+            Arc::new(database.database().clone()),
+            ledger.clone(),
+            config.number_pool.clone(),
+        ));
+
+        // Referral codes and the bonuses paid once a referee is active
+        let referrals = Arc::new(ReferralService::new(
+            Arc::new(database.database().clone()),
+            ledger.clone(),
+            config.referrals.clone(),
+        ));
+
+        // Monthly volume and quality bonuses, credited by the daily rollup
+        let volume_bonuses = Arc::new(VolumeBonuses::new(
+            Arc::new(database.database().clone()),
+            ledger.clone(),
+            config.earnings.clone(),
+        ));
+        let quality_bonuses = Arc::new(QualityBonuses::new(
+            Arc::new(database.database().clone()),
+            ledger.clone(),
+            config.earnings.clone(),
+        ));
+
+        // Client disputes of deliveries, reversed through the ledger when upheld
+        let disputes = Arc::new(DisputeService::new(
+            Arc::new(database.database().clone()),
+            ledger.clone(),
+        ));
+
+        // Message cancellations, confirmed with the provider's device once dispatched
+        let cancellations = Arc::new(CancellationService::new(
+            Arc::new(database.database().clone()),
+            fcm_service.clone(),
+            redis.clone(),
+        ));
+
+        // Delivery SLAs per priority, escalating messages that miss them
+        let sla_monitor = Arc::new(SlaMonitor::new(
+            Arc::new(database.database().clone()),
+            job_queue.clone(),
+            redis.clone(),
+            config.sla.clone(),
+        ));
+
+        // Deletes finished messages, jobs and login history past their window
+        let retention = Arc::new(RetentionTask::new(
+            Arc::new(database.database().clone()),
+            redis.clone(),
+            config.retention.clone(),
+        ));
+
+        // Operator actions on stuck jobs
+        let job_admin = Arc::new(JobAdmin::new(
+            Arc::new(database.database().clone()),
+            job_queue.clone(),
+            cancellations.clone(),
+        ));
+
+        // Nightly check of provider earnings against the ledger
+        let earnings_reconciler = Arc::new(EarningsReconciler::new(
+            Arc::new(database.database().clone()),
+            ledger.clone(),
+            config.reconciliation.clone(),
+        ));
+
+        // Per-client pricing plans, consulted when messages are priced
+        let pricing_plans = Arc::new(PricingPlans::new(
+            Arc::new(database.database().clone()),
+            &config.pricing,
+        ));
+
+        // Per-carrier surge multipliers from queued demand and providers online
+        let surge_pricing = Arc::new(SurgePricing::new(
+            Arc::new(database.database().clone()),
+            config.surge_pricing.clone(),
+        ));
+
+        // Signed delivery reports from external integrations
+        let webhook_verifier = Arc::new(WebhookVerifier::new(
+            redis.clone(),
+            config.external.delivery_webhook.clone(),
+        ));
+
+        // Device-signed delivery confirmations from providers
+        let device_keys = Arc::new(DeviceKeyVerifier::new(config.providers.clone()));
+
+        // DIDs and signed credentials for verified users and providers
+        let identity = Arc::new(IdentityService::new(
+            Arc::new(database.database().clone()),
+            user_repo.clone(),
+            config.identity.clone(),
+        )?);
+
+        // Play Integrity attestation of provider devices
+        let play_integrity = Arc::new(PlayIntegrityVerifier::new(
+            config.external.play_integrity.clone(),
+        )?);
 
         Ok(Self {
             config,
+            reporting: database.reporting(),
             database,
             redis,
             auth_service,
+            jwt_keys,
+            sessions,
+            telegram,
             user_repository: user_repo,
+            message_repository,
+            job_repository,
             fcm_service,
+            dispatch_strategy,
+            routing_rules,
+            job_queue,
+            job_outbox,
+            delivery_confirmations,
+            dead_letters,
+            canary,
+            audit_logger,
+            baray_client,
+            settlement_reconciler,
+            exchange_rates,
+            ledger,
+            topups,
+            baray_events,
+            payout_processor,
+            referrals,
+            volume_bonuses,
+            quality_bonuses,
+            disputes,
+            cancellations,
+            sla_monitor,
+            retention,
+            job_admin,
+            earnings_reconciler,
+            pricing_plans,
+            surge_pricing,
+            blockchain,
+            chain_settler,
+            staking,
+            backup_service,
+            recipient_vault,
+            delivery_predictor,
+            carrier_kill_switch,
+            provider_cache,
+            events,
+            message_changes,
+            carrier_redetector,
+            impact_analyzer,
+            phone_backfill,
+            rate_limiter,
+            login_lockout,
+            otp_challenger,
+            load_shedder,
+            intake_backpressure,
+            number_pool,
+            webhook_verifier,
+            device_keys,
+            identity,
+            play_integrity,
         })
     }
 }
@@ -1,11 +1,13 @@
 use axum::{
-    http::StatusCode,
+    http::{header, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
 use serde_json::json;
 use thiserror::Error;
 
+use crate::domain::errors::DomainError;
+
 /// Main application error type
 #[derive(Debug, Error)]
 pub enum PeerPowerError {
@@ -18,6 +20,15 @@ pub enum PeerPowerError {
     #[error("Authentication failed: {reason}")]
     AuthenticationFailed { reason: String },
 
+    #[error("Token not valid for this endpoint: requires {required}")]
+    InsufficientScope { required: String },
+
+    #[error("Too many failed verifications; try again in {retry_after_seconds} seconds")]
+    AccountLocked { retry_after_seconds: u64 },
+
+    #[error("Challenge required: {reason}")]
+    ChallengeRequired { reason: String },
+
     #[error("Validation error: {field} - {message}")]
     ValidationError { field: String, message: String },
 
@@ -27,6 +38,24 @@ pub enum PeerPowerError {
     #[error("External service error: {service} - {message}")]
     ExternalService { service: String, message: String },
 
+    #[error("Invalid request payload: {message}")]
+    InvalidPayload { message: String },
+
+    #[error("Request body exceeds {limit_bytes} bytes")]
+    PayloadTooLarge { limit_bytes: usize },
+
+    #[error("Unsupported content type: {content_type}")]
+    UnsupportedMediaType { content_type: String },
+
+    #[error("Service degraded ({level}, {reason}): endpoint temporarily disabled")]
+    ServiceDegraded { level: String, reason: String },
+
+    #[error("Message intake throttled ({reason}); retry in {retry_after_seconds} seconds")]
+    IntakeThrottled {
+        reason: String,
+        retry_after_seconds: u64,
+    },
+
     #[error("Rate limit exceeded: {resource}")]
     RateLimitExceeded { resource: String },
 
@@ -44,6 +73,9 @@ pub enum PeerPowerError {
 
     #[error("SMS delivery failed: {reason}")]
     SmsDeliveryFailed { reason: String },
+
+    #[error(transparent)]
+    Domain(#[from] DomainError),
 }
 
 impl PeerPowerError {
@@ -53,15 +85,28 @@ impl PeerPowerError {
             PeerPowerError::Database { .. } => StatusCode::INTERNAL_SERVER_ERROR,
             PeerPowerError::ProviderUnavailable { .. } => StatusCode::SERVICE_UNAVAILABLE,
             PeerPowerError::AuthenticationFailed { .. } => StatusCode::UNAUTHORIZED,
+            PeerPowerError::InsufficientScope { .. } => StatusCode::FORBIDDEN,
+            PeerPowerError::AccountLocked { .. } => StatusCode::LOCKED,
+            PeerPowerError::ChallengeRequired { .. } => StatusCode::PRECONDITION_REQUIRED,
             PeerPowerError::ValidationError { .. } => StatusCode::BAD_REQUEST,
             PeerPowerError::PaymentFailed { .. } => StatusCode::PAYMENT_REQUIRED,
             PeerPowerError::ExternalService { .. } => StatusCode::BAD_GATEWAY,
+            PeerPowerError::InvalidPayload { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            PeerPowerError::PayloadTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            PeerPowerError::UnsupportedMediaType { .. } => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            PeerPowerError::ServiceDegraded { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            PeerPowerError::IntakeThrottled { .. } => StatusCode::SERVICE_UNAVAILABLE,
             PeerPowerError::RateLimitExceeded { .. } => StatusCode::TOO_MANY_REQUESTS,
             PeerPowerError::NotFound { .. } => StatusCode::NOT_FOUND,
             PeerPowerError::Internal { .. } => StatusCode::INTERNAL_SERVER_ERROR,
             PeerPowerError::Configuration { .. } => StatusCode::INTERNAL_SERVER_ERROR,
             PeerPowerError::BlockchainError { .. } => StatusCode::BAD_GATEWAY,
             PeerPowerError::SmsDeliveryFailed { .. } => StatusCode::BAD_GATEWAY,
+            PeerPowerError::Domain(err) => match err {
+                DomainError::QuotaExceeded { .. } => StatusCode::TOO_MANY_REQUESTS,
+                DomainError::InsufficientBalance { .. } => StatusCode::PAYMENT_REQUIRED,
+                DomainError::IllegalStateTransition { .. } => StatusCode::CONFLICT,
+            },
         }
     }
 
@@ -71,15 +116,41 @@ impl PeerPowerError {
             PeerPowerError::Database { .. } => "DATABASE_ERROR",
             PeerPowerError::ProviderUnavailable { .. } => "PROVIDER_UNAVAILABLE",
             PeerPowerError::AuthenticationFailed { .. } => "AUTHENTICATION_FAILED",
+            PeerPowerError::InsufficientScope { .. } => "INSUFFICIENT_SCOPE",
+            PeerPowerError::AccountLocked { .. } => "ACCOUNT_LOCKED",
+            PeerPowerError::ChallengeRequired { .. } => "CHALLENGE_REQUIRED",
             PeerPowerError::ValidationError { .. } => "VALIDATION_ERROR",
             PeerPowerError::PaymentFailed { .. } => "PAYMENT_FAILED",
             PeerPowerError::ExternalService { .. } => "EXTERNAL_SERVICE_ERROR",
+            PeerPowerError::InvalidPayload { .. } => "INVALID_PAYLOAD",
+            PeerPowerError::PayloadTooLarge { .. } => "PAYLOAD_TOO_LARGE",
+            PeerPowerError::UnsupportedMediaType { .. } => "UNSUPPORTED_MEDIA_TYPE",
+            PeerPowerError::ServiceDegraded { .. } => "SERVICE_DEGRADED",
+            PeerPowerError::IntakeThrottled { .. } => "INTAKE_THROTTLED",
             PeerPowerError::RateLimitExceeded { .. } => "RATE_LIMIT_EXCEEDED",
             PeerPowerError::NotFound { .. } => "NOT_FOUND",
             PeerPowerError::Internal { .. } => "INTERNAL_ERROR",
             PeerPowerError::Configuration { .. } => "CONFIGURATION_ERROR",
             PeerPowerError::BlockchainError { .. } => "BLOCKCHAIN_ERROR",
             PeerPowerError::SmsDeliveryFailed { .. } => "SMS_DELIVERY_FAILED",
+            PeerPowerError::Domain(err) => match err {
+                DomainError::QuotaExceeded { .. } => "QUOTA_EXCEEDED",
+                DomainError::InsufficientBalance { .. } => "INSUFFICIENT_BALANCE",
+                DomainError::IllegalStateTransition { .. } => "ILLEGAL_STATE_TRANSITION",
+            },
+        }
+    }
+
+    /// Whether a Redis command failed, e.g. because Redis is down
+    pub fn is_redis_unavailable(&self) -> bool {
+        matches!(self, PeerPowerError::ExternalService { service, .. } if service == "Redis")
+    }
+
+    /// The underlying business-rule failure, if this is one
+    pub fn as_domain(&self) -> Option<&DomainError> {
+        match self {
+            PeerPowerError::Domain(err) => Some(err),
+            _ => None,
         }
     }
 }
@@ -89,15 +160,32 @@ impl IntoResponse for PeerPowerError {
     fn into_response(self) -> Response {
         let status_code = self.status_code();
 
-        let body = Json(json!({
-            "error": {
-                "code": self.error_code(),
-                "message": self.to_string(),
-                "timestamp": chrono::Utc::now().to_rfc3339(),
-            }
-        }));
+        let mut error = json!({
+            "code": self.error_code(),
+            "message": self.to_string(),
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+        });
+        // Shed requests say why, so clients can tell degradation from an outage
+        if let PeerPowerError::ServiceDegraded { level, reason } = &self {
+            error["degradation"] = json!({ "level": level, "reason": reason });
+        }
+
+        let body = Json(json!({ "error": error }));
 
-        (status_code, body).into_response()
+        let mut response = (status_code, body).into_response();
+        if let PeerPowerError::AccountLocked {
+            retry_after_seconds,
+        }
+        | PeerPowerError::IntakeThrottled {
+            retry_after_seconds,
+            ..
+        } = &self
+        {
+            if let Ok(value) = HeaderValue::from_str(&retry_after_seconds.to_string()) {
+                response.headers_mut().insert(header::RETRY_AFTER, value);
+            }
+        }
+        response
     }
 }
 
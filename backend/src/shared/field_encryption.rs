@@ -0,0 +1,191 @@
+//! Deterministic encryption of phone numbers stored in MongoDB.
+//!
+//! Provider and message entities mark their phone fields with
+//! `#[serde(with = "encrypted_phone")]`, so numbers are encrypted whenever
+//! they are written and decrypted when read back; the user repository calls
+//! [`encrypt_phone`] / [`decrypt_phone`] in its document mapping. The same
+//! number always encrypts to the same value, which keeps equality lookups and
+//! unique indexes working: query with [`phone_filter`].
+//!
+//! Values are `enc:v1:` followed by base64 of a 12-byte synthetic nonce
+//! (HMAC-SHA256 of the number, truncated) and the AES-256-GCM ciphertext.
+//! Plaintext values from before encryption was enabled are still read until
+//! `PhoneEncryptionBackfill` has rewritten them.
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::sync::OnceLock;
+
+use crate::config::PrivacyConfig;
+use crate::shared::types::PhoneNumber;
+use crate::shared::{PeerPowerError, Result};
+
+const PREFIX: &str = "enc:v1:";
+const NONCE_LEN: usize = 12;
+
+static PHONE_CIPHER: OnceLock<Option<PhoneCipher>> = OnceLock::new();
+
+pub struct PhoneCipher {
+    cipher: Aes256Gcm,
+    nonce_key: Vec<u8>,
+}
+
+impl PhoneCipher {
+    /// Derive separate encryption and nonce keys from the configured master key
+    pub fn new(master_key_hex: &str) -> Result<Self> {
+        let master_key = hex::decode(master_key_hex.trim())
+            .ok()
+            .filter(|bytes| bytes.len() == 32)
+            .ok_or_else(|| PeerPowerError::Configuration {
+                message: "PHONE_ENCRYPTION_KEY must be 64 hex characters".to_string(),
+            })?;
+        let derive = |label: &[u8]| {
+            let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&master_key)
+                .expect("HMAC accepts keys of any length");
+            mac.update(label);
+            mac.finalize().into_bytes().to_vec()
+        };
+
+        Ok(Self {
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&derive(b"phone-encryption"))),
+            nonce_key: derive(b"phone-nonce"),
+        })
+    }
+
+    pub fn encrypt(&self, plaintext: &str) -> String {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&self.nonce_key)
+            .expect("HMAC accepts keys of any length");
+        mac.update(plaintext.as_bytes());
+        let synthetic = mac.finalize().into_bytes();
+        let nonce = Nonce::from_slice(&synthetic[..NONCE_LEN]);
+
+        let ciphertext = self
+            .cipher
+            .encrypt(nonce, plaintext.as_bytes())
+            .expect("AES-GCM encryption of a short value cannot fail");
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        format!(
+            "{}{}",
+            PREFIX,
+            base64::engine::general_purpose::STANDARD.encode(sealed)
+        )
+    }
+
+    pub fn decrypt(&self, stored: &str) -> Result<String> {
+        let undecryptable = |reason: &str| PeerPowerError::Internal {
+            message: format!("Failed to decrypt phone number: {}", reason),
+        };
+
+        let sealed = base64::engine::general_purpose::STANDARD
+            .decode(stored.strip_prefix(PREFIX).unwrap_or(stored))
+            .map_err(|_| undecryptable("not base64"))?;
+        if sealed.len() <= NONCE_LEN {
+            return Err(undecryptable("ciphertext too short"));
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let plaintext = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| undecryptable("wrong key or corrupted value"))?;
+        String::from_utf8(plaintext).map_err(|_| undecryptable("not UTF-8"))
+    }
+}
+
+/// Set up the process-wide cipher; phone numbers are stored in plaintext
+/// when no key is configured. Later calls keep the first configuration.
+pub fn install(config: &PrivacyConfig) -> Result<()> {
+    let cipher = match config.phone_encryption_key.as_deref() {
+        Some(key) => Some(PhoneCipher::new(key)?),
+        None => None,
+    };
+    let _ = PHONE_CIPHER.set(cipher);
+    Ok(())
+}
+
+fn cipher() -> Option<&'static PhoneCipher> {
+    PHONE_CIPHER.get().and_then(Option::as_ref)
+}
+
+pub fn is_enabled() -> bool {
+    cipher().is_some()
+}
+
+pub fn is_encrypted(stored: &str) -> bool {
+    stored.starts_with(PREFIX)
+}
+
+/// The value a phone number is stored as
+pub fn encrypt_phone(phone: &PhoneNumber) -> String {
+    match cipher() {
+        Some(cipher) => cipher.encrypt(phone.as_str()),
+        None => phone.as_str().to_string(),
+    }
+}
+
+/// Query value matching a phone number, whether or not its document has
+/// been encrypted yet
+pub fn phone_filter(phone: &PhoneNumber) -> mongodb::bson::Bson {
+    match cipher() {
+        Some(cipher) => mongodb::bson::bson!({
+            "$in": [cipher.encrypt(phone.as_str()), phone.as_str()]
+        }),
+        None => mongodb::bson::Bson::String(phone.as_str().to_string()),
+    }
+}
+
+/// Read a stored phone value, decrypting it if needed
+pub fn decrypt_phone(stored: &str) -> Result<String> {
+    if !is_encrypted(stored) {
+        return Ok(stored.to_string());
+    }
+    cipher()
+        .ok_or_else(|| PeerPowerError::Configuration {
+            message: "PHONE_ENCRYPTION_KEY is required to read encrypted phone numbers".to_string(),
+        })?
+        .decrypt(stored)
+}
+
+/// Serde adapter for `PhoneNumber` fields stored encrypted
+pub mod encrypted_phone {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use crate::shared::types::PhoneNumber;
+
+    pub fn serialize<S: Serializer>(phone: &PhoneNumber, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&super::encrypt_phone(phone))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<PhoneNumber, D::Error> {
+        let stored = String::deserialize(deserializer)?;
+        let phone = super::decrypt_phone(&stored).map_err(serde::de::Error::custom)?;
+        // Stored numbers were validated on the way in (and may be masked), so
+        // they are not re-normalized here
+        Ok(PhoneNumber::from_stored(phone))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phone_encryption_is_deterministic_and_reversible() {
+        let cipher = PhoneCipher::new(&"11".repeat(32)).unwrap();
+
+        let first = cipher.encrypt("+85512345678");
+        assert!(is_encrypted(&first));
+        assert_eq!(first, cipher.encrypt("+85512345678"));
+        assert_ne!(first, cipher.encrypt("+85512345679"));
+        assert_eq!(cipher.decrypt(&first).unwrap(), "+85512345678");
+
+        let other = PhoneCipher::new(&"22".repeat(32)).unwrap();
+        assert!(other.decrypt(&first).is_err());
+        assert!(PhoneCipher::new("too-short").is_err());
+    }
+}
@@ -1,8 +1,17 @@
 pub mod app_state;
 pub mod errors;
+pub mod field_encryption;
+pub mod money;
+pub mod pagination;
+pub mod retry;
+#[cfg(test)]
+pub mod test_support;
 
 pub use app_state::AppState;
 pub use errors::{PeerPowerError, Result};
+pub use money::Money;
+pub use pagination::{PageRequest, PageResponse};
+pub use retry::{RetryClass, RetryPolicy};
 
 /// Common types used across the application
 pub mod types {
@@ -31,13 +40,30 @@ pub mod types {
             }
         }
 
+        /// A number read back from storage, validated when it was written
+        pub(crate) fn from_stored(phone: String) -> Self {
+            PhoneNumber(phone)
+        }
+
         pub fn as_str(&self) -> &str {
             &self.0
         }
+
+        /// Country/carrier prefix and last two digits only, e.g. `+85512*****78`
+        pub fn masked(&self) -> PhoneNumber {
+            let chars: Vec<char> = self.0.chars().collect();
+            let hidden = chars.len().saturating_sub(8);
+            let masked = chars
+                .iter()
+                .enumerate()
+                .map(|(i, c)| if i >= 6 && i < 6 + hidden { '*' } else { *c })
+                .collect();
+            PhoneNumber(masked)
+        }
     }
 
     /// Carrier types in Cambodia
-    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
     pub enum Carrier {
         Smart,
         Metfone,
@@ -47,6 +73,20 @@ pub mod types {
     }
 
     impl Carrier {
+        /// Every carrier, `Unknown` last
+        pub const ALL: [Carrier; 5] = [
+            Carrier::Smart,
+            Carrier::Metfone,
+            Carrier::Cellcard,
+            Carrier::Qb,
+            Carrier::Unknown,
+        ];
+
+        /// Lowercase name used in queue keys and metric labels
+        pub fn key(&self) -> String {
+            format!("{:?}", self).to_lowercase()
+        }
+
         pub fn from_phone_number(phone: &PhoneNumber) -> Self {
             let phone_str = phone.as_str();
 
@@ -89,6 +129,63 @@ pub mod types {
 
             Carrier::Unknown
         }
+
+        /// Parse a carrier name as reported by the provider app (SIM operator name)
+        pub fn from_name(name: &str) -> Option<Self> {
+            match name.trim().to_lowercase().as_str() {
+                "smart" => Some(Carrier::Smart),
+                "metfone" => Some(Carrier::Metfone),
+                "cellcard" => Some(Carrier::Cellcard),
+                "qb" => Some(Carrier::Qb),
+                _ => None,
+            }
+        }
+    }
+
+    /// Landline area codes (E.164 prefix) by province. Mobile ranges carry no
+    /// geographic information, so only fixed-line destinations resolve to a province.
+    const PROVINCE_AREA_CODES: &[(&str, &str)] = &[
+        ("+85523", "Phnom Penh"),
+        ("+85524", "Kandal"),
+        ("+85525", "Kampong Speu"),
+        ("+85526", "Kampong Chhnang"),
+        ("+85532", "Takeo"),
+        ("+85533", "Kampot"),
+        ("+85534", "Preah Sihanouk"),
+        ("+85535", "Koh Kong"),
+        ("+85536", "Kep"),
+        ("+85542", "Kampong Cham"),
+        ("+85543", "Prey Veng"),
+        ("+85544", "Svay Rieng"),
+        ("+85552", "Pursat"),
+        ("+85553", "Battambang"),
+        ("+85554", "Banteay Meanchey"),
+        ("+85555", "Pailin"),
+        ("+85562", "Kampong Thom"),
+        ("+85563", "Siem Reap"),
+        ("+85564", "Preah Vihear"),
+        ("+85565", "Oddar Meanchey"),
+        ("+85572", "Kratie"),
+        ("+85573", "Mondulkiri"),
+        ("+85574", "Stung Treng"),
+        ("+85575", "Ratanakiri"),
+    ];
+
+    /// Province for a destination number, where the prefix encodes one
+    pub fn province_for_number(number: &str) -> Option<&'static str> {
+        PROVINCE_AREA_CODES
+            .iter()
+            .find(|(prefix, _)| number.starts_with(prefix))
+            .map(|(_, province)| *province)
+    }
+
+    /// Canonical spelling of a free-form province name (as entered by providers)
+    pub fn canonical_province(name: &str) -> Option<&'static str> {
+        let name = name.trim();
+        PROVINCE_AREA_CODES
+            .iter()
+            .map(|(_, province)| *province)
+            .find(|province| province.eq_ignore_ascii_case(name))
     }
 
     /// Message status throughout the system
@@ -110,6 +207,23 @@ pub mod types {
         Busy,
         Suspended,
     }
+
+    /// Deployment cohort that handles a message (canary rollouts)
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+    pub enum DeploymentCohort {
+        #[default]
+        Stable,
+        Canary,
+    }
+
+    impl DeploymentCohort {
+        pub fn as_str(&self) -> &'static str {
+            match self {
+                DeploymentCohort::Stable => "stable",
+                DeploymentCohort::Canary => "canary",
+            }
+        }
+    }
 }
 
 /// Utilities for common operations
@@ -127,6 +241,13 @@ pub mod utils {
     pub fn now() -> DateTime<Utc> {
         Utc::now()
     }
+
+    /// A timestamp in the form documents store it: an RFC 3339 string, as
+    /// chrono serializes it. Queries on stored timestamps have to compare
+    /// against this; MongoDB never matches a BSON date against a string.
+    pub fn stored_timestamp(at: DateTime<Utc>) -> String {
+        at.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true)
+    }
     /// Hash a password using Argon2
     pub fn hash_password(password: &str) -> Result<String> {
         use argon2::{
@@ -160,4 +281,36 @@ pub mod utils {
             .verify_password(password.as_bytes(), &parsed_hash)
             .is_ok())
     }
+
+    /// Compute a hex-encoded HMAC-SHA256 of `payload`
+    pub fn hmac_sha256_hex(secret: &str, payload: &[u8]) -> String {
+        use hmac::{Hmac, Mac};
+
+        let mut mac = Hmac::<sha2::Sha256>::new_from_slice(secret.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(payload);
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    /// Verify a hex-encoded HMAC-SHA256 signature in constant time
+    pub fn verify_hmac_sha256_hex(secret: &str, payload: &[u8], signature: &str) -> bool {
+        use hmac::{Hmac, Mac};
+
+        let Ok(signature) = hex::decode(signature) else {
+            return false;
+        };
+        let mut mac = Hmac::<sha2::Sha256>::new_from_slice(secret.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(payload);
+        mac.verify_slice(&signature).is_ok()
+    }
+
+    /// Escape a single CSV field (RFC 4180)
+    pub fn csv_field(value: &str) -> String {
+        if value.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value.to_string()
+        }
+    }
 }
@@ -0,0 +1,260 @@
+use mongodb::bson::{doc, Bson, Document};
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
+
+/// Micro-PPT in one PPT
+const MICROS_PER_PPT: i64 = 1_000_000;
+
+/// An amount of PPT, held as a whole number of micro-PPT (10^-6 PPT) so
+/// sums, reversals and balances are exact.
+///
+/// Stored in Mongo as an Int64 of micro-PPT, which `$sum` adds exactly.
+/// Amounts written before this type existed are doubles in PPT; they are
+/// read back as such, and [`Money::micros_expr`] converts them inside
+/// aggregations. APIs keep taking and reporting PPT as decimal numbers,
+/// through [`ppt`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Money(i64);
+
+impl Money {
+    pub const ZERO: Money = Money(0);
+
+    pub fn from_micros(micros: i64) -> Self {
+        Money(micros)
+    }
+
+    /// Round a PPT amount (from a request, config or rate) to the nearest micro-PPT
+    pub fn from_ppt(ppt: f64) -> Self {
+        if ppt.is_finite() {
+            Money((ppt * MICROS_PER_PPT as f64).round() as i64)
+        } else {
+            Money::ZERO
+        }
+    }
+
+    pub fn micros(self) -> i64 {
+        self.0
+    }
+
+    /// The amount in PPT, for responses and rate conversions
+    pub fn to_ppt(self) -> f64 {
+        self.0 as f64 / MICROS_PER_PPT as f64
+    }
+
+    /// Multiply by a rate or multiplier, rounding to the nearest micro-PPT
+    pub fn scale(self, factor: f64) -> Self {
+        Money::from_ppt(self.to_ppt() * factor)
+    }
+
+    /// `percent` percent of the amount, rounded down so shares never exceed the whole
+    pub fn percent(self, percent: f64) -> Self {
+        Money(((self.0 as f64) * percent / 100.0).floor() as i64)
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn is_positive(self) -> bool {
+        self.0 > 0
+    }
+
+    pub fn is_negative(self) -> bool {
+        self.0 < 0
+    }
+
+    pub fn abs(self) -> Self {
+        Money(self.0.abs())
+    }
+
+    /// A value read from a document or aggregation result: integers are
+    /// micro-PPT, doubles are legacy PPT
+    pub fn from_bson(value: &Bson) -> Option<Self> {
+        match value {
+            Bson::Int64(micros) => Some(Money(*micros)),
+            Bson::Int32(micros) => Some(Money(i64::from(*micros))),
+            Bson::Double(ppt) => Some(Money::from_ppt(*ppt)),
+            _ => None,
+        }
+    }
+
+    /// An amount in an aggregation result; missing amounts are zero
+    pub fn from_field(document: &Document, key: &str) -> Self {
+        document
+            .get(key)
+            .and_then(Money::from_bson)
+            .unwrap_or_default()
+    }
+
+    /// Aggregation expression for the micro-PPT in `field` (e.g.
+    /// `"$postings.amount"`), converting legacy PPT doubles so `$sum` and
+    /// comparisons see one unit
+    pub fn micros_expr(field: &str) -> Bson {
+        Bson::Document(doc! {
+            "$cond": [
+                {"$eq": [{"$type": field}, "double"]},
+                {"$toLong": {"$round": [{"$multiply": [field, MICROS_PER_PPT]}, 0]}},
+                {"$ifNull": [field, 0_i64]},
+            ]
+        })
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let micros = self.0.unsigned_abs();
+        write!(
+            f,
+            "{}{}.{:06}",
+            sign,
+            micros / MICROS_PER_PPT as u64,
+            micros % MICROS_PER_PPT as u64
+        )
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+
+    fn add(self, other: Money) -> Money {
+        Money(self.0 + other.0)
+    }
+}
+
+impl Sub for Money {
+    type Output = Money;
+
+    fn sub(self, other: Money) -> Money {
+        Money(self.0 - other.0)
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+
+    fn neg(self) -> Money {
+        Money(-self.0)
+    }
+}
+
+impl AddAssign for Money {
+    fn add_assign(&mut self, other: Money) {
+        self.0 += other.0;
+    }
+}
+
+impl SubAssign for Money {
+    fn sub_assign(&mut self, other: Money) {
+        self.0 -= other.0;
+    }
+}
+
+impl Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter: I) -> Money {
+        Money(iter.map(|money| money.0).sum())
+    }
+}
+
+impl<'a> Sum<&'a Money> for Money {
+    fn sum<I: Iterator<Item = &'a Money>>(iter: I) -> Money {
+        iter.copied().sum()
+    }
+}
+
+impl From<Money> for Bson {
+    fn from(money: Money) -> Bson {
+        Bson::Int64(money.0)
+    }
+}
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i64(self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct MoneyVisitor;
+
+        impl Visitor<'_> for MoneyVisitor {
+            type Value = Money;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("micro-PPT as an integer, or PPT as a double")
+            }
+
+            fn visit_i64<E: de::Error>(self, micros: i64) -> Result<Money, E> {
+                Ok(Money(micros))
+            }
+
+            fn visit_u64<E: de::Error>(self, micros: u64) -> Result<Money, E> {
+                i64::try_from(micros)
+                    .map(Money)
+                    .map_err(|_| E::custom("amount out of range"))
+            }
+
+            fn visit_f64<E: de::Error>(self, ppt: f64) -> Result<Money, E> {
+                Ok(Money::from_ppt(ppt))
+            }
+        }
+
+        deserializer.deserialize_any(MoneyVisitor)
+    }
+}
+
+/// Serde adapter for response fields, which report PPT as a decimal number:
+/// `#[serde(with = "crate::shared::money::ppt")]`
+pub mod ppt {
+    use super::Money;
+    use serde::Serializer;
+
+    pub fn serialize<S: Serializer>(money: &Money, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(money.to_ppt())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_money_is_exact_where_f64_drifts() {
+        let cent = Money::from_ppt(0.01);
+        assert_eq!(cent.micros(), 10_000);
+
+        let total: Money = std::iter::repeat_n(cent, 1_000).sum();
+        assert_eq!(total, Money::from_ppt(10.0));
+        assert_ne!(std::iter::repeat_n(0.01_f64, 1_000).sum::<f64>(), 10.0);
+
+        assert_eq!(Money::from_ppt(0.0000004), Money::ZERO);
+        assert_eq!(Money::from_ppt(12.5).percent(10.0), Money::from_ppt(1.25));
+        assert_eq!(Money::from_ppt(0.01).scale(1.5).to_string(), "0.015000");
+        assert_eq!((-Money::from_ppt(2.000001)).to_string(), "-2.000001");
+    }
+
+    #[test]
+    fn test_money_reads_legacy_doubles() {
+        #[derive(Serialize, Deserialize)]
+        struct Row {
+            amount: Money,
+        }
+
+        let stored = mongodb::bson::to_document(&Row {
+            amount: Money::from_ppt(0.008),
+        })
+        .unwrap();
+        assert_eq!(stored.get("amount"), Some(&Bson::Int64(8_000)));
+
+        let legacy: Row = mongodb::bson::from_document(doc! {"amount": 0.008}).unwrap();
+        assert_eq!(legacy.amount.micros(), 8_000);
+        assert_eq!(
+            Money::from_bson(&Bson::Int32(5)),
+            Some(Money::from_micros(5))
+        );
+    }
+}
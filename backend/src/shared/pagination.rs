@@ -0,0 +1,157 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use mongodb::bson::{doc, Document};
+use serde::Serialize;
+
+use super::{PeerPowerError, Result};
+
+/// Items per page when the caller sets no limit
+pub const DEFAULT_PAGE_SIZE: i64 = 20;
+pub const MAX_PAGE_SIZE: i64 = 100;
+
+/// One page of a listing, newest first
+#[derive(Debug, Serialize)]
+pub struct PageResponse<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>, // pass back as `cursor` for the next page
+    pub has_more: bool,
+}
+
+impl<T> PageResponse<T> {
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> PageResponse<U> {
+        PageResponse {
+            items: self.items.into_iter().map(f).collect(),
+            next_cursor: self.next_cursor,
+            has_more: self.has_more,
+        }
+    }
+}
+
+/// Where a page ended: the last item's `created_at` as stored, and its id
+/// to order items created in the same instant. Opaque to clients.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cursor {
+    created_at: String,
+    id: String,
+}
+
+impl Cursor {
+    pub fn new(created_at: DateTime<Utc>, id: &str) -> Self {
+        Self::from_stored(&super::utils::stored_timestamp(created_at), id)
+    }
+
+    /// From a `created_at` read back as stored
+    pub fn from_stored(created_at: &str, id: &str) -> Self {
+        Self {
+            created_at: created_at.to_string(),
+            id: id.to_string(),
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(format!("{}|{}", self.created_at, self.id))
+    }
+
+    pub fn decode(cursor: &str) -> Result<Self> {
+        URL_SAFE_NO_PAD
+            .decode(cursor)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .and_then(|decoded| {
+                let (created_at, id) = decoded.split_once('|')?;
+                DateTime::parse_from_rfc3339(created_at).ok()?;
+                (!id.is_empty()).then(|| Self::from_stored(created_at, id))
+            })
+            .ok_or_else(|| PeerPowerError::ValidationError {
+                field: "cursor".to_string(),
+                message: "Invalid pagination cursor".to_string(),
+            })
+    }
+}
+
+/// A listing's `cursor` and `limit` parameters. Pages are ordered by
+/// `created_at` then `id`, newest first, and each one is read with a range
+/// query from where the last ended rather than by skipping past it.
+#[derive(Debug, Clone)]
+pub struct PageRequest {
+    after: Option<Cursor>,
+    limit: i64,
+}
+
+impl PageRequest {
+    pub fn new(cursor: Option<&str>, limit: Option<i64>) -> Result<Self> {
+        Ok(Self {
+            after: cursor.map(Cursor::decode).transpose()?,
+            limit: limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE),
+        })
+    }
+
+    /// `filter` narrowed to the items after the cursor
+    pub fn filter(&self, filter: Document) -> Document {
+        let Some(after) = &self.after else {
+            return filter;
+        };
+        doc! {"$and": [
+            filter,
+            {"$or": [
+                {"created_at": {"$lt": &after.created_at}},
+                {"created_at": &after.created_at, "id": {"$lt": &after.id}},
+            ]},
+        ]}
+    }
+
+    pub fn sort(&self) -> Document {
+        doc! {"created_at": -1, "id": -1}
+    }
+
+    /// One more than a page, to tell whether another follows
+    pub fn fetch_limit(&self) -> i64 {
+        self.limit + 1
+    }
+
+    /// The page out of up to `fetch_limit` items read in `sort` order
+    pub fn page<T>(&self, mut items: Vec<T>, cursor: impl Fn(&T) -> Cursor) -> PageResponse<T> {
+        let has_more = items.len() as i64 > self.limit;
+        items.truncate(self.limit as usize);
+        let next_cursor = if has_more {
+            items.last().map(|item| cursor(item).encode())
+        } else {
+            None
+        };
+        PageResponse {
+            items,
+            next_cursor,
+            has_more,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_round_trips() {
+        let cursor = Cursor::from_stored("2024-05-01T08:30:00.125Z", "message-1");
+        assert_eq!(Cursor::decode(&cursor.encode()).unwrap(), cursor);
+        assert!(Cursor::decode("not a cursor").is_err());
+        assert!(Cursor::decode(&URL_SAFE_NO_PAD.encode("yesterday|message-1")).is_err());
+    }
+
+    #[test]
+    fn test_page_has_more_only_past_the_limit() {
+        let request = PageRequest::new(None, Some(2)).unwrap();
+        assert_eq!(request.fetch_limit(), 3);
+        let cursor = |id: &&str| Cursor::from_stored("2024-05-01T08:30:00Z", id);
+
+        let page = request.page(vec!["c", "b", "a"], cursor);
+        assert_eq!(page.items, vec!["c", "b"]);
+        assert!(page.has_more);
+        assert_eq!(page.next_cursor, Some(cursor(&"b").encode()));
+
+        let page = request.page(vec!["b", "a"], cursor);
+        assert!(!page.has_more);
+        assert_eq!(page.next_cursor, None);
+    }
+}
@@ -0,0 +1,154 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+use super::PeerPowerError;
+
+/// Kinds of failure a retry policy can treat differently
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RetryClass {
+    Transient,   // network errors, 5xx responses, database hiccups
+    Throttled,   // rate limits and quotas; usually wants a longer wait
+    Rejected,    // the request itself was refused; trying again rarely helps
+    Unavailable, // nothing could take the work right now, e.g. no provider online
+}
+
+impl RetryClass {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "transient" => Some(Self::Transient),
+            "throttled" => Some(Self::Throttled),
+            "rejected" => Some(Self::Rejected),
+            "unavailable" => Some(Self::Unavailable),
+            _ => None,
+        }
+    }
+
+    /// Best guess from an error that carries no status of its own
+    pub fn of(error: &PeerPowerError) -> Self {
+        match error {
+            PeerPowerError::RateLimitExceeded { .. }
+            | PeerPowerError::AccountLocked { .. }
+            | PeerPowerError::IntakeThrottled { .. } => Self::Throttled,
+            PeerPowerError::ProviderUnavailable { .. } | PeerPowerError::ServiceDegraded { .. } => {
+                Self::Unavailable
+            }
+            PeerPowerError::Database { .. }
+            | PeerPowerError::ExternalService { .. }
+            | PeerPowerError::Internal { .. }
+            | PeerPowerError::BlockchainError { .. }
+            | PeerPowerError::SmsDeliveryFailed { .. } => Self::Transient,
+            _ => Self::Rejected,
+        }
+    }
+}
+
+/// Replaces a policy's attempts and base delay for one class of failure
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct RetryOverride {
+    pub max_attempts: u32,
+    pub base_delay_ms: u64,
+}
+
+/// How often and how far apart to try something again.
+///
+/// The wait before retry `n` is `base_delay_ms * 2^(n-1)`, capped at
+/// `max_delay_ms`, then shortened by up to `jitter` of itself at random so
+/// failures that happened together don't retry together.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    pub max_attempts: u32, // including the first
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+    pub jitter: f64, // 0.0 to 1.0
+    pub overrides: HashMap<RetryClass, RetryOverride>,
+}
+
+impl RetryPolicy {
+    /// Whether another attempt is allowed after `attempts` have failed
+    pub fn should_retry(&self, attempts: u32, class: RetryClass) -> bool {
+        attempts < self.max_attempts_for(class)
+    }
+
+    pub fn max_attempts_for(&self, class: RetryClass) -> u32 {
+        self.overrides
+            .get(&class)
+            .map_or(self.max_attempts, |o| o.max_attempts)
+    }
+
+    /// Wait before retry `retry` (1 for the first retry), with jitter
+    pub fn delay(&self, retry: u32, class: RetryClass) -> Duration {
+        self.delay_with_roll(retry, class, rand::thread_rng().gen())
+    }
+
+    /// `delay` with the random roll (0.0 to 1.0) given
+    fn delay_with_roll(&self, retry: u32, class: RetryClass, roll: f64) -> Duration {
+        let base = self
+            .overrides
+            .get(&class)
+            .map_or(self.base_delay_ms, |o| o.base_delay_ms);
+        let exponent = retry.saturating_sub(1).min(20);
+        let backoff = base.saturating_mul(1 << exponent).min(self.max_delay_ms);
+        let jitter = self.jitter.clamp(0.0, 1.0) * roll.clamp(0.0, 1.0);
+        Duration::from_millis((backoff as f64 * (1.0 - jitter)) as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 4,
+            base_delay_ms: 1000,
+            max_delay_ms: 5000,
+            jitter: 0.5,
+            overrides: HashMap::from([(
+                RetryClass::Rejected,
+                RetryOverride {
+                    max_attempts: 1,
+                    base_delay_ms: 0,
+                },
+            )]),
+        }
+    }
+
+    #[test]
+    fn test_delay_doubles_up_to_the_cap() {
+        let policy = policy();
+        let delays: Vec<u64> = (1..=5)
+            .map(|retry| {
+                policy
+                    .delay_with_roll(retry, RetryClass::Transient, 0.0)
+                    .as_millis() as u64
+            })
+            .collect();
+        assert_eq!(delays, vec![1000, 2000, 4000, 5000, 5000]);
+    }
+
+    #[test]
+    fn test_jitter_only_shortens_the_delay() {
+        let policy = policy();
+        assert_eq!(
+            policy.delay_with_roll(2, RetryClass::Transient, 1.0),
+            Duration::from_millis(1000)
+        );
+        let delay = policy.delay(2, RetryClass::Transient);
+        assert!(delay >= Duration::from_millis(1000) && delay <= Duration::from_millis(2000));
+    }
+
+    #[test]
+    fn test_overrides_apply_to_their_class_only() {
+        let policy = policy();
+        assert!(!policy.should_retry(1, RetryClass::Rejected));
+        assert!(policy.should_retry(3, RetryClass::Transient));
+        assert!(!policy.should_retry(4, RetryClass::Transient));
+        assert_eq!(
+            policy.delay_with_roll(1, RetryClass::Rejected, 0.0),
+            Duration::ZERO
+        );
+    }
+}
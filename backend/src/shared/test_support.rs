@@ -0,0 +1,148 @@
+//! Helpers for tests of what update paths write
+
+use mongodb::bson::{self, Bson, Document};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::cmp::Ordering;
+
+/// `entity` as a typed read returns it once `update` has been applied to
+/// its stored document. Panics if the result no longer deserializes, as
+/// when an update writes a BSON date into a field stored as a string.
+pub fn read_back<T: Serialize + DeserializeOwned>(entity: &T, update: &Document) -> T {
+    let stored = bson::to_document(entity).expect("entity serializes to a document");
+    apply(stored, update, false)
+}
+
+/// As `read_back`, for the document an upsert inserts from its `filter`
+pub fn read_back_from<T: DeserializeOwned>(filter: Document, update: &Document) -> T {
+    apply(filter, update, true)
+}
+
+fn apply<T: DeserializeOwned>(mut stored: Document, update: &Document, inserted: bool) -> T {
+    for (operator, fields) in update {
+        let fields = fields
+            .as_document()
+            .unwrap_or_else(|| panic!("{} takes a document", operator));
+        for (path, value) in fields {
+            match operator.as_str() {
+                "$set" => set_path(&mut stored, path, Some(value.clone())),
+                "$setOnInsert" if inserted => set_path(&mut stored, path, Some(value.clone())),
+                "$setOnInsert" => {}
+                "$unset" => set_path(&mut stored, path, None),
+                "$inc" => {
+                    let total = increment(lookup(&stored, path), value);
+                    set_path(&mut stored, path, Some(total));
+                }
+                other => panic!("read_back does not apply {}", other),
+            }
+        }
+    }
+    bson::from_document(stored)
+        .unwrap_or_else(|e| panic!("updated document does not deserialize: {}", e))
+}
+
+fn set_path(document: &mut Document, path: &str, value: Option<Bson>) {
+    let Some((field, rest)) = path.split_once('.') else {
+        match value {
+            Some(value) => document.insert(path, value),
+            None => document.remove(path),
+        };
+        return;
+    };
+    if !matches!(document.get(field), Some(Bson::Document(_))) {
+        document.insert(field, Document::new());
+    }
+    if let Some(Bson::Document(inner)) = document.get_mut(field) {
+        set_path(inner, rest, value);
+    }
+}
+
+/// `current` plus `by`, where a missing field counts as zero
+fn increment(current: Option<&Bson>, by: &Bson) -> Bson {
+    match (current, by) {
+        (None | Some(Bson::Null), _) => by.clone(),
+        (Some(Bson::Int32(current)), Bson::Int32(by)) => Bson::Int32(current + by),
+        (Some(Bson::Int64(current)), Bson::Int32(by)) => Bson::Int64(current + *by as i64),
+        (Some(Bson::Int64(current)), Bson::Int64(by)) => Bson::Int64(current + by),
+        (Some(current), by) => Bson::Double(
+            number(current).expect("$inc applies to a number")
+                + number(by).expect("$inc takes a number"),
+        ),
+    }
+}
+
+/// Whether MongoDB selects `document` with `filter`, for the operators our
+/// queries use. As in MongoDB, range bounds only match values of the same
+/// type, so a BSON date bound never matches a timestamp stored as a string.
+pub fn matches(document: &Document, filter: &Document) -> bool {
+    filter.iter().all(|(key, condition)| match key.as_str() {
+        "$or" => clauses(condition).any(|clause| matches(document, clause)),
+        "$and" => clauses(condition).all(|clause| matches(document, clause)),
+        path => field_matches(lookup(document, path), condition),
+    })
+}
+
+fn clauses(condition: &Bson) -> impl Iterator<Item = &Document> {
+    condition
+        .as_array()
+        .expect("logical operators take an array")
+        .iter()
+        .map(|clause| clause.as_document().expect("clauses are documents"))
+}
+
+fn lookup<'a>(document: &'a Document, path: &str) -> Option<&'a Bson> {
+    match path.split_once('.') {
+        Some((field, rest)) => lookup(document.get_document(field).ok()?, rest),
+        None => document.get(path),
+    }
+}
+
+fn field_matches(value: Option<&Bson>, condition: &Bson) -> bool {
+    let Bson::Document(operators) = condition else {
+        return equals(value, condition);
+    };
+    if !operators.keys().all(|key| key.starts_with('$')) {
+        return equals(value, condition);
+    }
+    operators.iter().all(|(operator, operand)| {
+        let order = value.and_then(|value| compare(value, operand));
+        match operator.as_str() {
+            "$lt" => order == Some(Ordering::Less),
+            "$lte" => matches!(order, Some(Ordering::Less | Ordering::Equal)),
+            "$gt" => order == Some(Ordering::Greater),
+            "$gte" => matches!(order, Some(Ordering::Greater | Ordering::Equal)),
+            "$ne" => !equals(value, operand),
+            "$in" => operand
+                .as_array()
+                .expect("$in takes an array")
+                .iter()
+                .any(|candidate| equals(value, candidate)),
+            other => panic!("matches does not support {}", other),
+        }
+    })
+}
+
+/// Equality, where `null` also matches a missing field
+fn equals(value: Option<&Bson>, expected: &Bson) -> bool {
+    match expected {
+        Bson::Null => matches!(value, None | Some(Bson::Null)),
+        _ => value == Some(expected),
+    }
+}
+
+fn compare(value: &Bson, bound: &Bson) -> Option<Ordering> {
+    match (value, bound) {
+        (Bson::String(value), Bson::String(bound)) => Some(value.cmp(bound)),
+        (Bson::DateTime(value), Bson::DateTime(bound)) => Some(value.cmp(bound)),
+        _ => number(value)?.partial_cmp(&number(bound)?),
+    }
+}
+
+fn number(value: &Bson) -> Option<f64> {
+    match value {
+        Bson::Int32(value) => Some(*value as f64),
+        Bson::Int64(value) => Some(*value as f64),
+        Bson::Double(value) => Some(*value),
+        _ => None,
+    }
+}